@@ -47,6 +47,7 @@ pub mod networking;
 pub mod crypto;
 pub mod config;
 pub mod utils;
+pub mod reputation;
 
 // Re-export key components
 pub use storage::Storage;
@@ -0,0 +1,325 @@
+//! Unified reputation ledger keyed by DID
+//!
+//! Network peer scoring, identity evidence, governance and consensus record
+//! reputation changes in a shared `ReputationLedger`, so they all read the
+//! same score. Each DID has a score in `[0, 1]` per `ReputationContext`,
+//! starting at `NEUTRAL_SCORE`, and an overall score that is the weighted mean
+//! of its context scores. Every change is persisted as a `ReputationEvent`;
+//! the events form the audit trail and the ledger rebuilds its scores from
+//! them when opened. Events recording evidence carry the evidence's ID, so
+//! evidence is counted once even across restarts.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::storage::{Storage, StorageError, StorageResult};
+use crate::utils::timestamp_secs;
+
+/// Score of a DID in a context before any event
+pub const NEUTRAL_SCORE: f64 = 0.5;
+
+/// Change in score caused by evidence of full weight
+pub const EVIDENCE_IMPACT: f64 = 0.1;
+
+/// Storage prefix for ledger events
+const EVENT_PREFIX: &str = "reputation/events/";
+
+/// Different contexts for reputation tracking
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ReputationContext {
+    /// General networking (connections, message processing)
+    Networking,
+    /// Consensus participation
+    Consensus,
+    /// Data validation and verification
+    DataValidation,
+    /// Resource sharing and provisioning
+    ResourceSharing,
+    /// Economic transactions
+    Economic,
+    /// Governance participation
+    Governance,
+    /// Custom context
+    Custom(String),
+}
+
+/// A change to a DID's reputation in one context
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReputationEvent {
+    /// Position of the event in the ledger
+    pub sequence: u64,
+    /// DID the event is about
+    pub did: String,
+    /// Context the event applies to
+    pub context: ReputationContext,
+    /// Requested change in score
+    pub delta: f64,
+    /// Subsystem that recorded the event (e.g. `network`, `governance`)
+    pub source: String,
+    /// Why the score changed
+    pub reason: String,
+    /// When the event was recorded (seconds since the epoch)
+    pub timestamp: u64,
+    /// ID of the evidence the event records, if any
+    #[serde(default)]
+    pub evidence_id: Option<String>,
+}
+
+/// Get the score change for evidence of a weight in `[-1, 1]`
+pub fn evidence_delta(weight: f64) -> f64 {
+    weight.clamp(-1.0, 1.0) * EVIDENCE_IMPACT
+}
+
+/// Get the context evidence of a kind is recorded under
+///
+/// Kinds are named in snake case (e.g. `successful_transaction`); other
+/// kinds are recorded under a custom context of the same name.
+pub fn evidence_context(kind: &str) -> ReputationContext {
+    match kind {
+        "successful_transaction" | "failed_transaction" => ReputationContext::Economic,
+        "validation" => ReputationContext::DataValidation,
+        "governance_participation" | "voting" | "proposal_creation" | "proposal_execution" => {
+            ReputationContext::Governance
+        }
+        other => ReputationContext::Custom(other.to_string()),
+    }
+}
+
+/// Scores and audit trails rebuilt from the events
+#[derive(Default)]
+struct LedgerState {
+    /// Sequence number of the next event
+    next_sequence: u64,
+    /// Scores by DID and context
+    scores: HashMap<String, BTreeMap<ReputationContext, f64>>,
+    /// Events by DID, in order
+    trails: HashMap<String, Vec<ReputationEvent>>,
+    /// IDs of the evidence recorded
+    evidence: HashSet<String>,
+}
+
+impl LedgerState {
+    /// Apply an event, returning the new score
+    fn apply(&mut self, event: ReputationEvent) -> f64 {
+        let score = self.scores.entry(event.did.clone())
+            .or_default()
+            .entry(event.context.clone())
+            .or_insert(NEUTRAL_SCORE);
+        *score = (*score + event.delta).clamp(0.0, 1.0);
+        let score = *score;
+
+        self.next_sequence = self.next_sequence.max(event.sequence + 1);
+        self.evidence.extend(event.evidence_id.clone());
+        self.trails.entry(event.did.clone()).or_default().push(event);
+        score
+    }
+}
+
+/// Ledger of DID reputation shared by all subsystems
+pub struct ReputationLedger {
+    /// Storage for ledger events
+    storage: Arc<dyn Storage>,
+    /// Weights of contexts in the overall score, 1 unless set
+    weights: RwLock<HashMap<ReputationContext, f64>>,
+    /// Current scores and trails
+    state: RwLock<LedgerState>,
+}
+
+impl ReputationLedger {
+    /// Open the ledger, replaying its stored events
+    pub async fn open(storage: Arc<dyn Storage>) -> StorageResult<Self> {
+        let mut keys = storage.list(EVENT_PREFIX).await?;
+        keys.sort();
+
+        let mut state = LedgerState::default();
+        for key in keys {
+            let data = storage.get(&key).await?;
+            let event: ReputationEvent = serde_json::from_slice(&data)
+                .map_err(|e| StorageError::DeserializationError(e.to_string()))?;
+            state.apply(event);
+        }
+
+        Ok(Self {
+            storage,
+            weights: RwLock::new(HashMap::new()),
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Set the weight of a context in the overall score
+    ///
+    /// A weight of zero leaves the context out of the overall score.
+    pub async fn set_context_weight(&self, context: ReputationContext, weight: f64) {
+        self.weights.write().await.insert(context, weight.max(0.0));
+    }
+
+    /// Record a change to a DID's score in a context, returning the new score
+    pub async fn record(
+        &self,
+        did: &str,
+        context: ReputationContext,
+        delta: f64,
+        source: &str,
+        reason: &str,
+    ) -> StorageResult<f64> {
+        let mut state = self.state.write().await;
+        let event = ReputationEvent {
+            sequence: state.next_sequence,
+            did: did.to_string(),
+            context,
+            delta,
+            source: source.to_string(),
+            reason: reason.to_string(),
+            timestamp: timestamp_secs(),
+            evidence_id: None,
+        };
+        self.append(&mut state, event).await
+    }
+
+    /// Record the score change for a piece of evidence, once per evidence ID
+    ///
+    /// Returns the new score, or `None` if the evidence was recorded before.
+    pub async fn record_evidence(
+        &self,
+        evidence_id: &str,
+        did: &str,
+        context: ReputationContext,
+        weight: f64,
+        source: &str,
+        reason: &str,
+    ) -> StorageResult<Option<f64>> {
+        let mut state = self.state.write().await;
+        if state.evidence.contains(evidence_id) {
+            return Ok(None);
+        }
+        let event = ReputationEvent {
+            sequence: state.next_sequence,
+            did: did.to_string(),
+            context,
+            delta: evidence_delta(weight),
+            source: source.to_string(),
+            reason: reason.to_string(),
+            timestamp: timestamp_secs(),
+            evidence_id: Some(evidence_id.to_string()),
+        };
+        self.append(&mut state, event).await.map(Some)
+    }
+
+    /// Check if a piece of evidence was recorded
+    pub async fn has_evidence(&self, evidence_id: &str) -> bool {
+        self.state.read().await.evidence.contains(evidence_id)
+    }
+
+    /// Get a DID's score in a context
+    pub async fn score(&self, did: &str, context: &ReputationContext) -> f64 {
+        context_score(&*self.state.read().await, did, context)
+    }
+
+    /// Get a DID's score in a context without waiting, if the ledger isn't being written
+    pub fn try_score(&self, did: &str, context: &ReputationContext) -> Option<f64> {
+        self.state.try_read().ok().map(|state| context_score(&state, did, context))
+    }
+
+    /// Get a DID's scores in every context it has events in
+    pub async fn context_scores(&self, did: &str) -> BTreeMap<ReputationContext, f64> {
+        self.state.read().await.scores.get(did).cloned().unwrap_or_default()
+    }
+
+    /// Get a DID's overall score, the weighted mean of its context scores
+    pub async fn overall(&self, did: &str) -> f64 {
+        let weights = self.weights.read().await;
+        let state = self.state.read().await;
+        let Some(scores) = state.scores.get(did) else {
+            return NEUTRAL_SCORE;
+        };
+
+        let (sum, total) = scores.iter().fold((0.0, 0.0), |(sum, total), (context, score)| {
+            let weight = weights.get(context).copied().unwrap_or(1.0);
+            (sum + weight * score, total + weight)
+        });
+        if total > 0.0 { sum / total } else { NEUTRAL_SCORE }
+    }
+
+    /// Get the events recorded for a DID, oldest first
+    pub async fn audit_trail(&self, did: &str) -> Vec<ReputationEvent> {
+        self.state.read().await.trails.get(did).cloned().unwrap_or_default()
+    }
+
+    /// Persist an event and apply it, returning the new score
+    async fn append(&self, state: &mut LedgerState, event: ReputationEvent) -> StorageResult<f64> {
+        // Persist before applying so the trail never misses an applied event
+        let data = serde_json::to_vec(&event)
+            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+        self.storage.put(&format!("{}{:020}", EVENT_PREFIX, event.sequence), &data).await?;
+        Ok(state.apply(event))
+    }
+}
+
+impl std::fmt::Debug for ReputationLedger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReputationLedger").finish_non_exhaustive()
+    }
+}
+
+/// Get a DID's score in a context from the ledger state
+fn context_score(state: &LedgerState, did: &str, context: &ReputationContext) -> f64 {
+    state.scores.get(did)
+        .and_then(|scores| scores.get(context))
+        .copied()
+        .unwrap_or(NEUTRAL_SCORE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_scores_are_rebuilt_from_the_audit_trail() {
+        let storage = Arc::new(MemoryStorage::new());
+        let ledger = ReputationLedger::open(storage.clone()).await.unwrap();
+        ledger.set_context_weight(ReputationContext::Governance, 3.0).await;
+
+        assert_eq!(ledger.score("did:icn:alice", &ReputationContext::Networking).await, NEUTRAL_SCORE);
+        ledger.record("did:icn:alice", ReputationContext::Networking, -0.2, "network", "invalid signature").await.unwrap();
+        ledger.record("did:icn:alice", ReputationContext::Governance, evidence_delta(2.0), "governance", "voted").await.unwrap();
+        ledger.record("did:icn:bob", ReputationContext::Consensus, 0.9, "consensus", "committed").await.unwrap();
+
+        // Scores stay within [0, 1] and contexts are weighted in the overall score
+        assert!((ledger.score("did:icn:alice", &ReputationContext::Networking).await - 0.3).abs() < 1e-9);
+        assert!((ledger.overall("did:icn:alice").await - (0.3 + 3.0 * 0.6) / 4.0).abs() < 1e-9);
+        assert_eq!(ledger.score("did:icn:bob", &ReputationContext::Consensus).await, 1.0);
+
+        let trail = ledger.audit_trail("did:icn:alice").await;
+        assert_eq!(trail.iter().map(|e| e.source.as_str()).collect::<Vec<_>>(), ["network", "governance"]);
+
+        // Reopening replays the events
+        let reopened = ReputationLedger::open(storage).await.unwrap();
+        assert_eq!(reopened.context_scores("did:icn:alice").await, ledger.context_scores("did:icn:alice").await);
+        assert_eq!(reopened.audit_trail("did:icn:bob").await, ledger.audit_trail("did:icn:bob").await);
+        let next = reopened.record("did:icn:bob", ReputationContext::Consensus, -0.5, "consensus", "missed round").await.unwrap();
+        assert_eq!(next, 0.5);
+        assert_eq!(reopened.audit_trail("did:icn:bob").await[1].sequence, 3);
+    }
+
+    #[tokio::test]
+    async fn test_evidence_is_recorded_once_across_restarts() {
+        let storage = Arc::new(MemoryStorage::new());
+        let ledger = ReputationLedger::open(storage.clone()).await.unwrap();
+        let context = evidence_context("voting");
+        assert_eq!(context, ReputationContext::Governance);
+        assert_eq!(evidence_context("mentoring"), ReputationContext::Custom("mentoring".to_string()));
+
+        let score = ledger.record_evidence("ev-1", "did:icn:alice", context.clone(), 1.0, "governance", "voted").await.unwrap();
+        assert!((score.unwrap() - 0.6).abs() < 1e-9);
+        assert_eq!(ledger.record_evidence("ev-1", "did:icn:alice", context.clone(), 1.0, "governance", "voted").await.unwrap(), None);
+
+        let reopened = ReputationLedger::open(storage).await.unwrap();
+        assert!(reopened.has_evidence("ev-1").await);
+        assert_eq!(reopened.record_evidence("ev-1", "did:icn:alice", context.clone(), 1.0, "governance", "voted").await.unwrap(), None);
+        assert!((reopened.score("did:icn:alice", &context).await - 0.6).abs() < 1e-9);
+    }
+}
@@ -113,6 +113,10 @@ pub trait JsonStorage: Storage {
 #[async_trait]
 impl<T: Storage> JsonStorage for T {}
 
+// And for storage shared as a trait object
+#[async_trait]
+impl JsonStorage for dyn Storage {}
+
 // Module exports
 pub mod file_storage;
 pub use file_storage::FileStorage;
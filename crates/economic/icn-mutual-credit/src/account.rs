@@ -1,6 +1,6 @@
 //! Account management for the mutual credit system.
 
-use crate::types::{Amount, DID, Timestamp};
+use crate::types::{Amount, Currency, DID, Timestamp};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -30,8 +30,14 @@ pub struct Account {
     pub created_at: Timestamp,
     /// When the account was last updated
     pub updated_at: Timestamp,
-    /// Current balance of the account
+    /// Currency the account is held in
+    #[serde(default)]
+    pub currency: Currency,
+    /// Current balance of the account, in its currency
     pub balance: Amount,
+    /// Balances in other currencies, such as those held by exchanges
+    #[serde(default)]
+    pub foreign_balances: HashMap<Currency, Amount>,
     /// Reputation score of the account (0.0 to 1.0)
     pub reputation: f64,
     /// Additional metadata for the account
@@ -39,8 +45,13 @@ pub struct Account {
 }
 
 impl Account {
-    /// Create a new account
+    /// Create a new account in the default currency
     pub fn new(did: DID, name: String) -> Self {
+        Self::with_currency(did, name, Currency::default())
+    }
+
+    /// Create a new account held in a currency
+    pub fn with_currency(did: DID, name: String, currency: Currency) -> Self {
         let now = chrono::Utc::now();
         Self {
             did,
@@ -48,7 +59,9 @@ impl Account {
             status: AccountStatus::Active,
             created_at: now,
             updated_at: now,
-            balance: Amount::zero(),
+            balance: Amount::zero_in(currency.clone()),
+            currency,
+            foreign_balances: HashMap::new(),
             reputation: 0.5, // Default neutral reputation
             metadata: HashMap::new(),
         }
@@ -65,12 +78,30 @@ impl Account {
         self.updated_at = chrono::Utc::now();
     }
 
-    /// Update the account balance
+    /// Update the account balance in the amount's currency
     pub fn update_balance(&mut self, amount: Amount) {
-        self.balance = self.balance.clone() + amount;
+        // Balances are kept per currency, so the sums can't mix currencies
+        let currency = amount.currency().clone();
+        let balance = if currency == self.currency {
+            &mut self.balance
+        } else {
+            self.foreign_balances.entry(currency.clone())
+                .or_insert_with(|| Amount::zero_in(currency.clone()))
+        };
+        *balance = Amount::from_decimal(balance.value() + amount.value(), currency);
         self.updated_at = chrono::Utc::now();
     }
 
+    /// Get the balance of the account in a currency
+    pub fn balance_in(&self, currency: &Currency) -> Amount {
+        if currency == &self.currency {
+            return self.balance.clone();
+        }
+        self.foreign_balances.get(currency)
+            .cloned()
+            .unwrap_or_else(|| Amount::zero_in(currency.clone()))
+    }
+
     /// Update the account reputation
     pub fn update_reputation(&mut self, reputation: f64) {
         // Ensure reputation is between 0.0 and 1.0
@@ -131,6 +162,12 @@ mod tests {
         let amount = Amount::new(100);
         account.update_balance(amount.clone());
         assert_eq!(account.balance, amount);
+
+        // Amounts in other currencies are held separately
+        let hours = Currency::new("HOURS");
+        account.update_balance(Amount::in_currency(-3, hours.clone()));
+        assert_eq!(account.balance, amount);
+        assert_eq!(account.balance_in(&hours), Amount::in_currency(-3, hours));
         
         // Update reputation
         account.update_reputation(0.8);
@@ -0,0 +1,343 @@
+//! Global multilateral credit clearing.
+//!
+//! Clearing reduces the credit drawn on credit lines without changing any
+//! account's net position: repaying the same amount along every line of a
+//! cycle means each account on it pays and receives that amount. Rather than
+//! clearing cycles one at a time, `optimal_clearing` finds the circulation
+//! that clears the most credit across the whole graph at once, as a min-cost
+//! circulation in which every unit cleared costs -1, solved by cancelling
+//! negative cycles in the residual graph. The result doesn't depend on the
+//! order cycles are found in, and overlapping cycles share their lines.
+
+use crate::credit_graph::CreditLineId;
+use crate::error::CreditError;
+use crate::types::{Amount, Currency, Timestamp, DID};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::collections::{HashMap, HashSet};
+
+/// Domain separator for clearing report signatures
+const SIGNING_DOMAIN: &str = "icn-clearing-report-v1";
+
+/// Options for a global clearing run
+#[derive(Debug, Clone, Default)]
+pub struct ClearingOptions {
+    /// Accounts that opted in; only lines between two of them are cleared
+    pub participants: HashSet<DID>,
+    /// Most that may be cleared from individual credit lines
+    pub line_limits: HashMap<CreditLineId, Amount>,
+    /// Whether to only report the adjustments without applying them
+    pub dry_run: bool,
+    /// Currency of the credit lines cleared
+    pub currency: Currency,
+}
+
+impl ClearingOptions {
+    /// Create options for clearing between the participants
+    pub fn new(participants: impl IntoIterator<Item = DID>) -> Self {
+        Self {
+            participants: participants.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Limit how much may be cleared from a credit line
+    pub fn with_line_limit(mut self, id: CreditLineId, limit: Amount) -> Self {
+        self.line_limits.insert(id, limit);
+        self
+    }
+
+    /// Clear credit lines in a currency rather than the default one
+    pub fn in_currency(mut self, currency: Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// Preview the clearing without applying it
+    pub fn dry_run(mut self) -> Self {
+        self.dry_run = true;
+        self
+    }
+}
+
+/// Signs clearing reports
+pub trait ReportSigner: Send + Sync {
+    /// DID the signatures are made by
+    fn signer(&self) -> DID;
+
+    /// Sign a message
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CreditError>;
+}
+
+/// An adjustment to one credit line made by clearing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClearingAdjustment {
+    /// The account extending credit
+    pub from: DID,
+    /// The account receiving credit
+    pub to: DID,
+    /// Amount of drawn credit cleared
+    pub amount: Amount,
+    /// Balance of the credit line before clearing
+    pub balance_before: Amount,
+    /// Balance of the credit line after clearing
+    pub balance_after: Amount,
+}
+
+/// Signed record of a clearing run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClearingReport {
+    /// Hash of the signed content
+    pub id: String,
+    /// When the clearing ran
+    pub created_at: Timestamp,
+    /// Whether the adjustments were only previewed
+    pub dry_run: bool,
+    /// Accounts that opted in, sorted
+    pub participants: Vec<DID>,
+    /// Every credit line adjustment, sorted by credit line
+    pub adjustments: Vec<ClearingAdjustment>,
+    /// Total credit cleared across all lines
+    pub total_cleared: Amount,
+    /// DID that signed the report
+    pub signer: DID,
+    /// Signature of the content by the signer
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by a report signature
+#[derive(Serialize)]
+struct ReportPayload<'a> {
+    domain: &'a str,
+    created_at: &'a Timestamp,
+    dry_run: bool,
+    participants: &'a [DID],
+    adjustments: &'a [ClearingAdjustment],
+    total_cleared: &'a Amount,
+    signer: &'a DID,
+}
+
+impl ClearingReport {
+    /// Create a report signed by the signer
+    pub fn sign(
+        dry_run: bool,
+        participants: Vec<DID>,
+        adjustments: Vec<ClearingAdjustment>,
+        signer: &dyn ReportSigner,
+    ) -> Result<Self, CreditError> {
+        let total_cleared = adjustments.iter()
+            .try_fold(Amount::zero(), |total, adjustment| total.checked_add(&adjustment.amount))?;
+        let mut report = Self {
+            id: String::new(),
+            created_at: chrono::Utc::now(),
+            dry_run,
+            participants,
+            adjustments,
+            total_cleared,
+            signer: signer.signer(),
+            signature: Vec::new(),
+        };
+
+        let bytes = report.signing_bytes()?;
+        report.id = hex::encode(sha2::Sha256::digest(&bytes));
+        report.signature = signer.sign(&bytes)?;
+        Ok(report)
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, CreditError> {
+        Ok(serde_json::to_vec(&ReportPayload {
+            domain: SIGNING_DOMAIN,
+            created_at: &self.created_at,
+            dry_run: self.dry_run,
+            participants: &self.participants,
+            adjustments: &self.adjustments,
+            total_cleared: &self.total_cleared,
+            signer: &self.signer,
+        })?)
+    }
+
+    /// Verify the report's ID matches its content and the signature is valid
+    ///
+    /// `verify_signature` is called with the signer, the signed bytes and the
+    /// signature, and checks the signature against the signer's key.
+    pub fn verify<F>(&self, verify_signature: F) -> Result<(), CreditError>
+    where
+        F: FnOnce(&DID, &[u8], &[u8]) -> bool,
+    {
+        let bytes = self.signing_bytes()?;
+        if hex::encode(sha2::Sha256::digest(&bytes)) != self.id {
+            return Err(CreditError::Validation(format!("Clearing report {} does not match its ID", self.id)));
+        }
+        if !verify_signature(&self.signer, &bytes, &self.signature) {
+            return Err(CreditError::Validation(format!(
+                "Clearing report {} has an invalid signature from {}", self.id, self.signer
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// An edge of the residual graph
+struct ResidualEdge {
+    /// Index of the edge's tail
+    from: usize,
+    /// Index of the edge's head
+    to: usize,
+    /// Index of the credit line
+    line: usize,
+    /// Whether the edge clears more (rather than less) of the line
+    forward: bool,
+}
+
+/// Find how much to clear from each credit line to clear the most in total
+///
+/// Each line may have up to its given amount cleared. Every account clears
+/// as much on its lines from others as on its lines to others, so net
+/// positions are unchanged. Returns the lines with a non-zero amount cleared,
+/// or an error if the amounts aren't all in one currency.
+pub fn optimal_clearing(clearable: &[(CreditLineId, Amount)]) -> Result<Vec<(CreditLineId, Amount)>, CreditError> {
+    let mut dids: Vec<&DID> = clearable.iter().flat_map(|(id, _)| [&id.from, &id.to]).collect();
+    dids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    dids.dedup();
+    let index: HashMap<&DID, usize> = dids.iter().enumerate().map(|(i, did)| (*did, i)).collect();
+
+    let mut cleared = vec![Amount::zero(); clearable.len()];
+    loop {
+        let mut edges = Vec::new();
+        for (line, (id, limit)) in clearable.iter().enumerate() {
+            let (from, to) = (index[&id.from], index[&id.to]);
+            if cleared[line].checked_cmp(limit)?.is_lt() {
+                edges.push(ResidualEdge { from, to, line, forward: true });
+            }
+            if cleared[line].is_positive() {
+                edges.push(ResidualEdge { from: to, to: from, line, forward: false });
+            }
+        }
+
+        let Some(cycle) = negative_cycle(dids.len(), &edges) else {
+            break;
+        };
+
+        let residual = |edge: &ResidualEdge| {
+            if edge.forward {
+                clearable[edge.line].1.checked_sub(&cleared[edge.line])
+            } else {
+                Ok(cleared[edge.line].clone())
+            }
+        };
+        let mut amount = residual(&edges[cycle[0]])?;
+        for e in &cycle[1..] {
+            amount = amount.checked_min(&residual(&edges[*e])?)?;
+        }
+        for e in cycle {
+            let edge = &edges[e];
+            cleared[edge.line] = if edge.forward {
+                cleared[edge.line].checked_add(&amount)?
+            } else {
+                cleared[edge.line].checked_sub(&amount)?
+            };
+        }
+    }
+
+    Ok(clearable.iter()
+        .zip(cleared)
+        .filter(|(_, amount)| amount.is_positive())
+        .map(|((id, _), amount)| (id.clone(), amount))
+        .collect())
+}
+
+/// Find a cycle of negative cost with Bellman-Ford, as edge indices
+///
+/// Forward edges cost -1 and backward edges +1, so a negative cycle clears
+/// more on some lines than it gives back on others.
+fn negative_cycle(nodes: usize, edges: &[ResidualEdge]) -> Option<Vec<usize>> {
+    let mut distance = vec![0i64; nodes];
+    let mut previous: Vec<Option<usize>> = vec![None; nodes];
+    let mut relaxed = None;
+
+    for _ in 0..nodes {
+        relaxed = None;
+        for (e, edge) in edges.iter().enumerate() {
+            let cost = if edge.forward { -1 } else { 1 };
+            if distance[edge.from] + cost < distance[edge.to] {
+                distance[edge.to] = distance[edge.from] + cost;
+                previous[edge.to] = Some(e);
+                relaxed = Some(edge.to);
+            }
+        }
+        relaxed?;
+    }
+
+    // A node relaxed in the last round leads back into the cycle within `nodes` steps
+    let mut node = relaxed?;
+    for _ in 0..nodes {
+        node = edges[previous[node]?].from;
+    }
+
+    let start = node;
+    let mut cycle = Vec::new();
+    loop {
+        let e = previous[node]?;
+        cycle.push(e);
+        node = edges[e].from;
+        if node == start {
+            break;
+        }
+    }
+    Some(cycle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Signs with a hash of a secret and the message
+    struct HashSigner;
+
+    impl ReportSigner for HashSigner {
+        fn signer(&self) -> DID {
+            DID::new("did:icn:clearing-house")
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CreditError> {
+            Ok(sha2::Sha256::digest([b"secret".as_slice(), message].concat()).to_vec())
+        }
+    }
+
+    #[test]
+    fn test_overlapping_cycles_are_cleared_optimally() {
+        // Two cycles share a -> b; clearing the shorter one first would clear only 30
+        let line = |from: &str, to: &str| (CreditLineId::new(&DID::new(from), &DID::new(to)), Amount::new(10));
+        let clearable = vec![
+            line("a", "b"), line("b", "c"), line("c", "a"),
+            line("b", "d"), line("d", "e"), line("e", "a"),
+        ];
+
+        let cleared = optimal_clearing(&clearable).unwrap();
+        let total = cleared.iter().try_fold(Amount::zero(), |total, (_, amount)| total.checked_add(amount)).unwrap();
+        assert_eq!(total, Amount::new(40));
+        assert!(cleared.iter().all(|(id, _)| id.to.as_str() != "c" && id.from.as_str() != "c"));
+
+        let adjustments = cleared.into_iter()
+            .map(|(id, amount)| ClearingAdjustment {
+                from: id.from,
+                to: id.to,
+                balance_before: -amount.clone(),
+                balance_after: Amount::zero(),
+                amount,
+            })
+            .collect();
+        let report = ClearingReport::sign(true, Vec::new(), adjustments, &HashSigner).unwrap();
+        let check = |signer: &DID, bytes: &[u8], signature: &[u8]| {
+            HashSigner.signer() == *signer && HashSigner.sign(bytes).unwrap() == signature
+        };
+        assert_eq!(report.total_cleared, Amount::new(40));
+        assert!(report.verify(check).is_ok());
+
+        let mut tampered = report.clone();
+        tampered.adjustments[0].amount = Amount::new(1);
+        assert!(tampered.verify(check).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Evaluation of credit line conditions and collateral.
+//!
+//! `CreditTerms` can attach `CreditCondition`s to a credit line. The
+//! `ConditionEngine` checks every condition before each draw on the line and
+//! reports the first one that fails as `CreditError::ConditionNotMet`. The
+//! account drawing on a line is the line's `from_account`, whose balance a
+//! transfer lowers.
+//!
+//! The engine also approves credit lines and locks collateral for them. Both
+//! are recorded on the credit line, so they persist with the credit graph and
+//! any engine over the graph sees them. Credit from another account is locked
+//! on that account's credit line to the same counterparty, so it can't be
+//! drawn while it backs the line. Resource
+//! commitments, governance tokens and external assets live outside the
+//! credit graph and are recorded at the value the federation assigned them;
+//! resource commitments only count for their committed duration.
+
+use crate::account::Account;
+use crate::credit_graph::{CreditGraph, CreditLineId};
+use crate::credit_line::{CollateralLock, CollateralRequirement, CollateralType, CreditCondition, CreditLine};
+use crate::error::CreditError;
+use crate::types::Amount;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Evaluates a `CreditCondition::Custom` for the drawing account and credit line
+pub type CustomCondition = Arc<dyn Fn(&Account, &CreditLine) -> bool + Send + Sync>;
+
+/// Evaluates credit conditions and locks collateral
+#[derive(Default)]
+pub struct ConditionEngine {
+    /// Custom conditions by name
+    custom: HashMap<String, CustomCondition>,
+}
+
+impl fmt::Debug for ConditionEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConditionEngine")
+            .field("custom", &self.custom.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Build the error for a failed condition
+fn not_met(condition: &CreditCondition, reason: String) -> CreditError {
+    CreditError::ConditionNotMet {
+        condition: Box::new(condition.clone()),
+        reason,
+    }
+}
+
+/// Check if locked collateral is of the kind a requirement asks for
+fn same_collateral(locked: &CollateralType, required: &CollateralType) -> bool {
+    match (locked, required) {
+        (CollateralType::Credit(a), CollateralType::Credit(b)) => a == b,
+        (CollateralType::Resource(a), CollateralType::Resource(b)) => a.resource_type == b.resource_type,
+        (CollateralType::GovernanceToken, CollateralType::GovernanceToken) => true,
+        (CollateralType::ExternalAsset(a), CollateralType::ExternalAsset(b)) => a == b,
+        _ => false,
+    }
+}
+
+impl ConditionEngine {
+    /// Create an engine with no custom conditions
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record governance approval of a credit line
+    pub async fn approve(&self, graph: &mut CreditGraph, line: &CreditLineId) -> Result<(), CreditError> {
+        self.set_approval(graph, line, true).await
+    }
+
+    /// Withdraw governance approval of a credit line
+    pub async fn revoke_approval(&self, graph: &mut CreditGraph, line: &CreditLineId) -> Result<(), CreditError> {
+        self.set_approval(graph, line, false).await
+    }
+
+    /// Record whether governance approved a credit line
+    async fn set_approval(&self, graph: &mut CreditGraph, line: &CreditLineId, approved: bool) -> Result<(), CreditError> {
+        let credit_line = graph.get_credit_line_mut(line).await?
+            .ok_or_else(|| CreditError::CreditLineNotFound(line.to_string()))?;
+        credit_line.approved = approved;
+        credit_line.updated_at = chrono::Utc::now();
+        Ok(())
+    }
+
+    /// Register the evaluator for `CreditCondition::Custom` conditions with a name
+    pub fn register_custom(&mut self, name: impl Into<String>, condition: CustomCondition) {
+        self.custom.insert(name.into(), condition);
+    }
+
+    /// Lock collateral for a credit line
+    ///
+    /// Collateral is valued in the line's currency. Credit from another
+    /// account is locked on that account's credit line to the line's
+    /// counterparty, and fails if it doesn't have the credit available.
+    pub async fn lock_collateral(
+        &self,
+        graph: &mut CreditGraph,
+        line: &CreditLineId,
+        collateral_type: CollateralType,
+        amount: Amount,
+    ) -> Result<(), CreditError> {
+        graph.get_credit_line(line).await?
+            .ok_or_else(|| CreditError::CreditLineNotFound(line.to_string()))?
+            .check_currency(&amount)?;
+        if !amount.is_positive() {
+            return Err(CreditError::Validation("Collateral must have a positive value".to_string()));
+        }
+
+        let now = chrono::Utc::now();
+        let expires_at = match &collateral_type {
+            CollateralType::Credit(guarantor) => {
+                let backing = CreditLineId::new(guarantor, &line.to);
+                graph.get_credit_line_mut(&backing).await?
+                    .ok_or_else(|| CreditError::CreditLineNotFound(format!(
+                        "Collateral credit line {} not found", backing
+                    )))?
+                    .lock_credit(&amount)?;
+                None
+            },
+            CollateralType::Resource(commitment) => {
+                let duration = chrono::Duration::from_std(commitment.duration)
+                    .map_err(|e| CreditError::Validation(format!("Invalid commitment duration: {}", e)))?;
+                Some(now + duration)
+            },
+            CollateralType::GovernanceToken | CollateralType::ExternalAsset(_) => None,
+        };
+
+        let credit_line = graph.get_credit_line_mut(line).await?
+            .ok_or_else(|| CreditError::CreditLineNotFound(line.to_string()))?;
+        credit_line.collateral.push(CollateralLock {
+            collateral_type,
+            amount,
+            locked_at: now,
+            expires_at,
+        });
+        credit_line.updated_at = now;
+        Ok(())
+    }
+
+    /// Release all collateral locked for a credit line
+    ///
+    /// Collateral can only be released once nothing is drawn on the line.
+    pub async fn release_collateral(
+        &self,
+        graph: &mut CreditGraph,
+        line: &CreditLineId,
+    ) -> Result<Vec<CollateralLock>, CreditError> {
+        let credit_line = graph.get_credit_line_mut(line).await?
+            .ok_or_else(|| CreditError::CreditLineNotFound(line.to_string()))?;
+        if credit_line.balance.is_negative() {
+            return Err(CreditError::Validation(format!(
+                "Credit line {} still has {} drawn", line, credit_line.balance.abs()
+            )));
+        }
+
+        let locks = std::mem::take(&mut credit_line.collateral);
+        credit_line.updated_at = chrono::Utc::now();
+        for lock in &locks {
+            if let CollateralType::Credit(guarantor) = &lock.collateral_type {
+                let backing = CreditLineId::new(guarantor, &line.to);
+                if let Some(backing_line) = graph.get_credit_line_mut(&backing).await? {
+                    backing_line.unlock_credit(&lock.amount);
+                }
+            }
+        }
+        Ok(locks)
+    }
+
+    /// Check every condition of a credit line before drawing an amount on it
+    pub async fn check_draw(
+        &self,
+        graph: &CreditGraph,
+        line: &CreditLineId,
+        amount: &Amount,
+    ) -> Result<(), CreditError> {
+        let credit_line = graph.get_credit_line(line).await?
+            .ok_or_else(|| CreditError::CreditLineNotFound(line.to_string()))?;
+        credit_line.check_currency(amount)?;
+        let drawer = graph.get_account(&line.from).await?
+            .ok_or_else(|| CreditError::AccountNotFound(line.from.to_string()))?;
+
+        for condition in &credit_line.terms.conditions {
+            match condition {
+                CreditCondition::MinimumReputation(minimum) => {
+                    if drawer.reputation < *minimum {
+                        return Err(not_met(condition, format!(
+                            "{} has reputation {:.2}, below {:.2}", drawer.did, drawer.reputation, minimum
+                        )));
+                    }
+                },
+                CreditCondition::ActiveParticipation(duration) => {
+                    let active = (chrono::Utc::now() - drawer.created_at).to_std().unwrap_or_default();
+                    if active < *duration {
+                        return Err(not_met(condition, format!(
+                            "{} has participated for {}s of the required {}s",
+                            drawer.did, active.as_secs(), duration.as_secs()
+                        )));
+                    }
+                },
+                CreditCondition::GovernanceApproval => {
+                    if !credit_line.approved {
+                        return Err(not_met(condition, format!("Credit line {} is not approved", line)));
+                    }
+                },
+                CreditCondition::Collateral(requirement) => {
+                    self.check_collateral(condition, requirement, line, credit_line, amount)?;
+                },
+                CreditCondition::ReciprocalCreditLine(minimum) => {
+                    let reciprocal = CreditLineId::new(&line.to, &line.from);
+                    let extended = graph.get_credit_line(&reciprocal).await?
+                        .filter(|reciprocal| reciprocal.is_active() && reciprocal.limit.same_currency(minimum))
+                        .map(|reciprocal| reciprocal.limit.clone());
+                    if extended.as_ref().is_none_or(|limit| limit < minimum) {
+                        return Err(not_met(condition, format!(
+                            "{} extends no active credit line of at least {} to {}", line.to, minimum, line.from
+                        )));
+                    }
+                },
+                CreditCondition::Custom(name) => {
+                    let evaluator = self.custom.get(name)
+                        .ok_or_else(|| not_met(condition, format!("No evaluator registered for {}", name)))?;
+                    if !evaluator(drawer, credit_line) {
+                        return Err(not_met(condition, format!("{} rejected the draw", name)));
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check enough collateral is locked to cover the line after a draw
+    ///
+    /// The line needs at least the requirement's amount, and at least its
+    /// ratio times the credit drawn after the draw.
+    fn check_collateral(
+        &self,
+        condition: &CreditCondition,
+        requirement: &CollateralRequirement,
+        line: &CreditLineId,
+        credit_line: &CreditLine,
+        amount: &Amount,
+    ) -> Result<(), CreditError> {
+        let balance_after = credit_line.balance.checked_sub(amount)?;
+        let drawn_after = if balance_after.is_negative() { balance_after.abs() } else { Amount::zero() };
+        if !requirement.amount.same_currency(&credit_line.limit) {
+            return Err(not_met(condition, format!(
+                "Collateral is required in {}, but credit line {} is in {}",
+                requirement.amount.currency(), line, credit_line.currency()
+            )));
+        }
+        let ratio_required = drawn_after.scale(requirement.ratio);
+        let required = if ratio_required.checked_cmp(&requirement.amount)?.is_gt() {
+            ratio_required
+        } else {
+            requirement.amount.clone()
+        };
+
+        let locked = credit_line.collateral.iter()
+            .filter(|lock| lock.is_current() && same_collateral(&lock.collateral_type, &requirement.collateral_type))
+            .filter(|lock| lock.amount.same_currency(&required))
+            .try_fold(Amount::zero(), |total, lock| total.checked_add(&lock.amount))?;
+        if locked.checked_cmp(&required)?.is_lt() {
+            return Err(not_met(condition, format!(
+                "Credit line {} has {} of collateral locked, {} is required", line, locked, required
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credit_line::{CreditTerms, ResourceCommitment};
+    use crate::types::DID;
+    use rust_decimal::Decimal;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_conditions_are_checked_before_draws() {
+        let mut graph = CreditGraph::new();
+        for name in ["borrower", "lender", "guarantor"] {
+            graph.add_account(Account::new(DID::new(name), name.to_string())).await.unwrap();
+        }
+        let requirement = CollateralRequirement {
+            collateral_type: CollateralType::Credit(DID::new("guarantor")),
+            amount: Amount::new(10),
+            ratio: Decimal::new(5, 1),
+        };
+        let mut terms = CreditTerms::new();
+        terms.add_condition(CreditCondition::MinimumReputation(0.4));
+        terms.add_condition(CreditCondition::GovernanceApproval);
+        terms.add_condition(CreditCondition::Collateral(requirement));
+        terms.add_condition(CreditCondition::Custom("weekday".to_string()));
+        graph.add_credit_line(CreditLine::new(DID::new("borrower"), DID::new("lender"), Amount::new(100), terms)).await.unwrap();
+        graph.add_credit_line(CreditLine::new(DID::new("guarantor"), DID::new("lender"), Amount::new(50), CreditTerms::new())).await.unwrap();
+
+        let line = CreditLineId::new(&DID::new("borrower"), &DID::new("lender"));
+        let mut engine = ConditionEngine::new();
+        let failed = |result: Result<(), CreditError>| match result {
+            Err(CreditError::ConditionNotMet { condition, .. }) => format!("{:?}", condition),
+            other => panic!("expected a failed condition, got {:?}", other),
+        };
+
+        assert!(failed(engine.check_draw(&graph, &line, &Amount::new(60)).await).starts_with("GovernanceApproval"));
+        engine.approve(&mut graph, &line).await.unwrap();
+        assert!(failed(engine.check_draw(&graph, &line, &Amount::new(60)).await).starts_with("Collateral"));
+
+        // Half of the 60 drawn must be backed by the guarantor's credit, which it can't draw meanwhile
+        engine.lock_collateral(&mut graph, &line, CollateralType::Credit(DID::new("guarantor")), Amount::new(30)).await.unwrap();
+        let backing = CreditLineId::new(&DID::new("guarantor"), &DID::new("lender"));
+        assert_eq!(graph.get_credit_line(&backing).await.unwrap().unwrap().available_credit(), Amount::new(20));
+        assert!(failed(engine.check_draw(&graph, &line, &Amount::new(61)).await).starts_with("Collateral"));
+        assert!(failed(engine.check_draw(&graph, &line, &Amount::new(60)).await).starts_with("Custom"));
+
+        engine.register_custom("weekday", Arc::new(|drawer: &Account, _: &CreditLine| drawer.reputation > 0.0));
+        assert!(engine.check_draw(&graph, &line, &Amount::new(60)).await.is_ok());
+
+        // Collateral of another kind doesn't count, and time-limited collateral expires
+        let resource = ResourceCommitment {
+            resource_type: "storage".to_string(),
+            quantity: 100,
+            unit: "GB".to_string(),
+            duration: Duration::from_secs(0),
+        };
+        engine.lock_collateral(&mut graph, &line, CollateralType::Resource(resource), Amount::new(100)).await.unwrap();
+        assert!(graph.get_credit_line(&line).await.unwrap().unwrap().collateral.iter().any(|lock| !lock.is_current()));
+        assert!(failed(engine.check_draw(&graph, &line, &Amount::new(61)).await).starts_with("Collateral"));
+
+        // Approval and collateral are kept on the line, so they survive a reload into a new engine
+        let stored = serde_json::to_vec(graph.get_credit_line(&line).await.unwrap().unwrap()).unwrap();
+        let reloaded: CreditLine = serde_json::from_slice(&stored).unwrap();
+        assert!(reloaded.approved);
+        assert_eq!(reloaded.collateral.len(), 2);
+        let mut restarted = ConditionEngine::new();
+        restarted.register_custom("weekday", Arc::new(|drawer: &Account, _: &CreditLine| drawer.reputation > 0.0));
+        assert!(restarted.check_draw(&graph, &line, &Amount::new(60)).await.is_ok());
+
+        // Collateral stays locked while credit is drawn
+        graph.get_credit_line_mut(&line).await.unwrap().unwrap().update_balance(Amount::new(-60)).unwrap();
+        assert!(engine.release_collateral(&mut graph, &line).await.is_err());
+        graph.get_credit_line_mut(&line).await.unwrap().unwrap().update_balance(Amount::new(60)).unwrap();
+        assert_eq!(engine.release_collateral(&mut graph, &line).await.unwrap().len(), 2);
+        assert_eq!(graph.get_credit_line(&backing).await.unwrap().unwrap().available_credit(), Amount::new(50));
+    }
+}
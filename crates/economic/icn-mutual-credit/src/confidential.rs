@@ -7,13 +7,15 @@
 use crate::error::CreditError;
 use crate::transaction::{Transaction, TransactionStatus, TransactionType};
 use crate::types::{Amount, DID, Timestamp};
-use rand::RngCore;
+use bulletproofs::{BulletproofGens, PedersenGens, RangeProof as BulletproofRangeProof};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use merlin::Transcript;
 use rust_decimal::prelude::ToPrimitive;
-use sha2::Digest;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 use rand;
-use sha2;
 use uuid;
 
 /// Error types specific to confidential transactions
@@ -51,28 +53,116 @@ impl fmt::Display for PedersenCommitment {
 pub struct RangeProof {
     /// The actual proof data
     pub proof: Vec<u8>,
-    /// Public inputs for verification
+    /// The range the prover claims, as its minimum and maximum, little-endian
+    ///
+    /// Verifiers check proofs against their own bounds, never these.
     pub public_inputs: Vec<u8>,
 }
 
+impl RangeProof {
+    /// Get the range the prover claims the proof is for
+    pub fn bounds(&self) -> Result<(i64, i64), ConfidentialError> {
+        if self.public_inputs.len() != 16 {
+            return Err(ConfidentialError::ProofError(
+                "Range proof public inputs must hold a minimum and maximum".to_string()
+            ));
+        }
+
+        let mut min = [0u8; 8];
+        let mut max = [0u8; 8];
+        min.copy_from_slice(&self.public_inputs[..8]);
+        max.copy_from_slice(&self.public_inputs[8..]);
+        Ok((i64::from_le_bytes(min), i64::from_le_bytes(max)))
+    }
+}
+
 /// Blinding factor for commitments
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BlindingFactor {
-    /// Secret random value
+    /// Secret random value, the canonical encoding of a scalar
     pub factor: Vec<u8>,
 }
 
 impl BlindingFactor {
     /// Create a new random blinding factor
     pub fn new() -> Result<Self, ConfidentialError> {
-        // In a real implementation, this would use a secure random number generator
-        // For this example, we'll create a dummy implementation
-        let mut data = Vec::with_capacity(32);
-        for i in 0..32 {
-            data.push((i as u8) ^ 0xAB); // Dummy value
+        Ok(Self::from_scalar(&Scalar::random(&mut rand::thread_rng())))
+    }
+
+    /// Create a blinding factor from a scalar
+    pub fn from_scalar(scalar: &Scalar) -> Self {
+        Self { factor: scalar.to_bytes().to_vec() }
+    }
+
+    /// Get the blinding factor as a scalar
+    pub fn scalar(&self) -> Result<Scalar, ConfidentialError> {
+        let bytes: [u8; 32] = self.factor.as_slice().try_into()
+            .map_err(|_| ConfidentialError::BlindingError("Blinding factor must be 32 bytes".to_string()))?;
+        Option::from(Scalar::from_canonical_bytes(bytes))
+            .ok_or_else(|| ConfidentialError::BlindingError("Blinding factor is not a canonical scalar".to_string()))
+    }
+
+    /// Combine blinding factors the way their commitments are combined
+    ///
+    /// The sum of the added factors minus the subtracted ones blinds the sum
+    /// of the added commitments minus the subtracted ones.
+    pub fn combine(added: &[BlindingFactor], subtracted: &[BlindingFactor]) -> Result<Self, ConfidentialError> {
+        let mut total = Scalar::ZERO;
+        for factor in added {
+            total += factor.scalar()?;
         }
-        
-        Ok(Self { factor: data })
+        for factor in subtracted {
+            total -= factor.scalar()?;
+        }
+        Ok(Self::from_scalar(&total))
+    }
+}
+
+/// Get the whole amount a confidential transaction commits to
+fn confidential_amount(amount: &Amount) -> Result<i64, ConfidentialError> {
+    let value = amount.value();
+    if !value.fract().is_zero() {
+        return Err(ConfidentialError::ValidationError(
+            format!("Confidential amount {} is not a whole number", amount)
+        ));
+    }
+    value.to_i64()
+        .filter(|v| (0..=MAX_CONFIDENTIAL_AMOUNT).contains(v))
+        .ok_or_else(|| ConfidentialError::AmountRangeError(format!(
+            "Confidential amount {} is outside [0, {}]", amount, MAX_CONFIDENTIAL_AMOUNT
+        )))
+}
+
+/// Get the scalar for a signed amount
+fn amount_scalar(amount: i64) -> Scalar {
+    let magnitude = Scalar::from(amount.unsigned_abs());
+    if amount < 0 { -magnitude } else { magnitude }
+}
+
+impl PedersenCommitment {
+    /// Create a commitment from a Ristretto point
+    pub fn from_point(point: &RistrettoPoint) -> Self {
+        Self { commitment: point.compress().to_bytes().to_vec() }
+    }
+
+    /// Get the commitment as a Ristretto point
+    pub fn point(&self) -> Result<RistrettoPoint, ConfidentialError> {
+        CompressedRistretto::from_slice(&self.commitment)
+            .ok()
+            .and_then(|compressed| compressed.decompress())
+            .ok_or_else(|| ConfidentialError::InvalidCommitment(
+                "Commitment is not a valid Ristretto point".to_string()
+            ))
+    }
+
+    /// Get a commitment to the sum of both committed amounts
+    pub fn checked_add(&self, other: &PedersenCommitment) -> Result<Self, ConfidentialError> {
+        Ok(Self::from_point(&(self.point()? + other.point()?)))
+    }
+
+    /// Get a commitment to this committed amount minus the other
+    pub fn checked_sub(&self, other: &PedersenCommitment) -> Result<Self, ConfidentialError> {
+        Ok(Self::from_point(&(self.point()? - other.point()?)))
     }
 }
 
@@ -103,109 +193,43 @@ impl ConfidentialTransaction {
 }
 
 /// Pedersen commitment generator
-#[derive(Debug)]
-pub struct PedersenCommitmentGenerator;
+///
+/// Commits to an amount `v` with blinding factor `r` as the Ristretto point
+/// `v * B + r * B_blinding`. Commitments are perfectly hiding and
+/// computationally binding, and adding or subtracting commitments commits to
+/// the sum or difference of their amounts.
+pub struct PedersenCommitmentGenerator {
+    /// Generator points
+    gens: PedersenGens,
+}
+
+impl fmt::Debug for PedersenCommitmentGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PedersenCommitmentGenerator").finish_non_exhaustive()
+    }
+}
 
 impl PedersenCommitmentGenerator {
     /// Create a new Pedersen commitment generator
     pub fn new() -> Self {
-        Self
+        Self { gens: PedersenGens::default() }
     }
-    
+
     /// Generate a random blinding factor
     pub fn generate_blinding_factor(&self) -> BlindingFactor {
-        // In a real implementation, generate a cryptographically secure random blinding factor
-        // For this prototype, just use a random seed
-        let mut rng = rand::thread_rng();
-        let mut bytes = vec![0u8; 32];
-        rng.fill_bytes(&mut bytes);
-        
-        BlindingFactor { factor: bytes }
+        BlindingFactor::from_scalar(&Scalar::random(&mut rand::thread_rng()))
     }
-    
+
     /// Create a Pedersen commitment
     pub fn create_commitment(
         &self,
         amount: i64,
         blinding_factor: &BlindingFactor,
     ) -> Result<PedersenCommitment, ConfidentialError> {
-        // In a real implementation, we would use a proper cryptographic library
-        // For example, using curve25519-dalek for Pedersen commitments:
-        // let commitment = RistrettoPoint::pedersen_commit(amount, blinding_factor)
-        
-        // For this prototype, we'll use a more sophisticated simulation:
-        
-        // Create a more robust simulation of a Pedersen commitment:
-        // commitment = g^amount * h^blinding_factor (where g and h are generator points)
-        // We'll simulate this with a combination of hashing
-        
-        // First hash for the "g" generator point effect
-        let mut g_hasher = sha2::Sha256::new();
-        g_hasher.update(b"g_generator_point");  // A fixed value representing the "g" point
-        g_hasher.update(amount.to_le_bytes());
-        let g_hash = g_hasher.finalize();
-        
-        // Second hash for the "h" generator point effect
-        let mut h_hasher = sha2::Sha256::new();
-        h_hasher.update(b"h_generator_point");  // A fixed value representing the "h" point
-        h_hasher.update(&blinding_factor.factor);
-        let h_hash = h_hasher.finalize();
-        
-        // Combine the two hashes to simulate the commitment
-        let mut commitment = Vec::with_capacity(32);
-        for i in 0..32 {
-            commitment.push(g_hash[i] ^ h_hash[i]);
-        }
-        
-        Ok(PedersenCommitment { commitment })
-    }
-    
-    /// Reveal the amount in a commitment
-    pub fn reveal_amount(
-        &self,
-        commitment: &PedersenCommitment,
-        blinding_factor: &BlindingFactor,
-    ) -> Result<i64, ConfidentialError> {
-        // In a real implementation with actual Pedersen commitments,
-        // this would not be possible as Pedersen commitments are information-theoretically hiding.
-        // The amount would be provided separately by the owner of the blinding factor.
-        
-        // For our prototype simulation, we'll just use the base transaction's amount
-        // since we're storing it as part of the ConfidentialTransaction.
-        // In the future, this would be handled through secure channels between sender and receiver.
-        
-        // Here we're just ensuring the commitment is valid by rehashing
-        let mut h_hasher = sha2::Sha256::new();
-        h_hasher.update(b"h_generator_point");
-        h_hasher.update(&blinding_factor.factor);
-        let h_hash = h_hasher.finalize();
-        
-        // Check if the commitment has valid structure
-        if commitment.commitment.len() != 32 {
-            return Err(ConfidentialError::InvalidCommitment(
-                "Invalid commitment structure".to_string()
-            ));
-        }
-        
-        // This is a dummy implementation for the prototype
-        // In a real system, the sender would need to provide the amount to the recipient
-        // via a secure channel, along with a proof that this amount is consistent with the commitment
-        
-        // For testing, just derive a value from the blinding factor and commitment
-        // This is NOT how it would work in a real implementation
-        let mut amount_bytes = [0u8; 8];
-        for i in 0..8 {
-            amount_bytes[i] = h_hash[i] ^ commitment.commitment[i];
-        }
-        
-        // Convert to i64, with bounds for demo purposes
-        let mut amount = i64::from_le_bytes(amount_bytes);
-        // Keep the amount reasonable for the demo
-        amount = amount % 10000;
-        
-        Ok(amount)
+        let point = self.gens.commit(amount_scalar(amount), blinding_factor.scalar()?);
+        Ok(PedersenCommitment::from_point(&point))
     }
-    
+
     /// Verify a commitment matches an amount and blinding factor
     pub fn verify_commitment(
         &self,
@@ -214,20 +238,57 @@ impl PedersenCommitmentGenerator {
         blinding_factor: &BlindingFactor,
     ) -> Result<bool, ConfidentialError> {
         let expected = self.create_commitment(amount, blinding_factor)?;
-        Ok(commitment.commitment == expected.commitment)
+        Ok(commitment.point()? == expected.point()?)
     }
 }
 
+/// Number of bits each Bulletproofs range proof covers
+const RANGE_PROOF_BITS: usize = 64;
+
+/// Largest amount a confidential transaction can carry
+///
+/// Confidential amounts are verified to lie in `[0, MAX_CONFIDENTIAL_AMOUNT]`.
+pub const MAX_CONFIDENTIAL_AMOUNT: i64 = 1_000_000_000;
+
+/// Transcript label binding range proofs to this system
+const RANGE_PROOF_DOMAIN: &[u8] = b"icn-confidential-range-proof-v1";
+
 /// Range proof system for validating confidential amounts
-#[derive(Debug)]
-pub struct RangeProofSystem;
+///
+/// A proof that a committed amount `v` lies in `[min, max]` is an aggregated
+/// Bulletproof that both `v - min` and `max - v` are 64-bit values. The
+/// verifier derives the commitments to both from the amount's commitment,
+/// so the proof is tied to it without revealing the amount.
+pub struct RangeProofSystem {
+    /// Generator points of the commitments
+    pc_gens: PedersenGens,
+    /// Generator points of the proofs
+    bp_gens: BulletproofGens,
+}
+
+impl fmt::Debug for RangeProofSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RangeProofSystem").finish_non_exhaustive()
+    }
+}
 
 impl RangeProofSystem {
     /// Create a new range proof system
     pub fn new() -> Self {
-        RangeProofSystem
+        Self {
+            pc_gens: PedersenGens::default(),
+            bp_gens: BulletproofGens::new(RANGE_PROOF_BITS, 2),
+        }
     }
-    
+
+    /// Start the transcript for a proof over a range
+    fn transcript(min: i64, max: i64) -> Transcript {
+        let mut transcript = Transcript::new(RANGE_PROOF_DOMAIN);
+        transcript.append_message(b"min", &min.to_le_bytes());
+        transcript.append_message(b"max", &max.to_le_bytes());
+        transcript
+    }
+
     /// Create a range proof that amount is within bounds
     pub fn create_range_proof(
         &self,
@@ -236,107 +297,80 @@ impl RangeProofSystem {
         max: i64,
         blinding_factor: &BlindingFactor,
     ) -> Result<RangeProof, ConfidentialError> {
-        // In a real implementation, this would use bulletproofs or other zero-knowledge range proofs
-        // For example, with the bulletproofs crate:
-        // let (proof, committed_value) = Bulletproof::prove_single(amount, min, max, &blinding_factor)
-        
-        // Check that amount is within bounds
         if amount < min || amount > max {
             return Err(ConfidentialError::AmountRangeError(
                 format!("Amount {} is outside range [{}, {}]", amount, min, max)
             ));
         }
-        
-        // Simulate a range proof with cryptographic hashing:
-        // 1. Hash the amount with min/max bounds
-        let mut amount_hasher = sha2::Sha512::new();
-        amount_hasher.update(amount.to_le_bytes());
-        amount_hasher.update(min.to_le_bytes());
-        amount_hasher.update(max.to_le_bytes());
-        let amount_hash = amount_hasher.finalize();
-        
-        // 2. Hash the blinding factor with a separate domain
-        let mut bf_hasher = sha2::Sha512::new();
-        bf_hasher.update(b"range_proof_domain");
-        bf_hasher.update(&blinding_factor.factor);
-        let bf_hash = bf_hasher.finalize();
-        
-        // 3. Combine these to create a simulated proof
-        let mut proof = Vec::with_capacity(64);
-        for i in 0..64 {
-            proof.push(amount_hash[i] ^ bf_hash[i]);
-        }
-        
-        // 4. Create public inputs that can be used for verification
-        // In a real ZK range proof, these would be the commitments to the range
-        let mut public_inputs = Vec::with_capacity(32);
-        
-        let mut input_hasher = sha2::Sha256::new();
-        input_hasher.update(b"public_inputs");
-        input_hasher.update(min.to_le_bytes());
-        input_hasher.update(max.to_le_bytes());
-        // Add a commitment-like value (without revealing the amount)
-        input_hasher.update(&proof[0..16]);
-        
-        public_inputs.extend_from_slice(&input_hasher.finalize());
-        
+
+        // Both differences are non-negative and below 2^64
+        let above_min = (amount as i128 - min as i128) as u64;
+        let below_max = (max as i128 - amount as i128) as u64;
+        let blinding = blinding_factor.scalar()?;
+
+        let (proof, _) = BulletproofRangeProof::prove_multiple_with_rng(
+            &self.bp_gens,
+            &self.pc_gens,
+            &mut Self::transcript(min, max),
+            &[above_min, below_max],
+            &[blinding, -blinding],
+            RANGE_PROOF_BITS,
+            &mut rand::thread_rng(),
+        ).map_err(|e| ConfidentialError::ProofError(format!("Failed to create range proof: {}", e)))?;
+
+        let mut public_inputs = Vec::with_capacity(16);
+        public_inputs.extend_from_slice(&min.to_le_bytes());
+        public_inputs.extend_from_slice(&max.to_le_bytes());
+
         Ok(RangeProof {
-            proof,
+            proof: proof.to_bytes(),
             public_inputs,
         })
     }
-    
-    /// Verify a range proof is valid for a commitment
+
+    /// Verify a range proof shows a committed amount is a valid confidential amount
+    ///
+    /// The range is fixed at `[0, MAX_CONFIDENTIAL_AMOUNT]`, whatever range the
+    /// prover claims.
     pub fn verify_range_proof(
         &self,
         range_proof: &RangeProof,
         commitment: &PedersenCommitment,
     ) -> Result<bool, ConfidentialError> {
-        // In a real implementation, this would cryptographically verify 
-        // that the committed value is within the specified range
-        // For example: Bulletproof::verify(&range_proof, &commitment)
-        
-        // Basic validation checks
-        if range_proof.proof.len() < 64 || commitment.commitment.len() < 32 {
-            return Err(ConfidentialError::ProofError(
-                "Proof or commitment has invalid length".to_string()
+        self.verify_range_proof_within(range_proof, commitment, 0, MAX_CONFIDENTIAL_AMOUNT)
+    }
+
+    /// Verify a range proof shows a committed amount is within the verifier's bounds
+    ///
+    /// Returns `false` if the proof doesn't show the committed amount is in
+    /// `[min, max]`, and an error if the proof or commitment is malformed.
+    pub fn verify_range_proof_within(
+        &self,
+        range_proof: &RangeProof,
+        commitment: &PedersenCommitment,
+        min: i64,
+        max: i64,
+    ) -> Result<bool, ConfidentialError> {
+        if min > max {
+            return Err(ConfidentialError::AmountRangeError(
+                format!("Range [{}, {}] is empty", min, max)
             ));
         }
-        
-        // Simulate verification by checking the consistency between 
-        // the range proof and the commitment
-        
-        // 1. Extract a verification value from the proof
-        let mut verification_value = 0u64;
-        for i in 0..8 {
-            verification_value = (verification_value << 8) | (range_proof.proof[i] as u64);
-        }
-        
-        // 2. Extract a comparison value from the commitment
-        let mut commitment_value = 0u64;
-        for i in 0..8 {
-            commitment_value = (commitment_value << 8) | (commitment.commitment[i] as u64);
-        }
-        
-        // 3. In a real implementation, these values would be cryptographically related
-        // For our prototype, we'll do a simple check that they're derived from related data
-        // This is a greatly simplified simulation of verifying that the range proof
-        // corresponds to the commitment
-        
-        let proof_hash = sha2::Sha256::digest(&range_proof.proof[0..32]);
-        let commit_hash = sha2::Sha256::digest(&commitment.commitment[0..32]);
-        
-        // Check that certain bits match, simulating a cryptographic relationship
-        // between the proof and commitment
-        for i in 0..8 {
-            if (proof_hash[i] & 0x0F) != (commit_hash[i] & 0x0F) {
-                // In a real implementation, this would be a proper cryptographic verification
-                return Ok(false);
-            }
-        }
-        
-        // For this prototype, we'll return true if the checks pass
-        Ok(true)
+        let point = commitment.point()?;
+        let proof = BulletproofRangeProof::from_bytes(&range_proof.proof)
+            .map_err(|e| ConfidentialError::ProofError(format!("Malformed range proof: {}", e)))?;
+
+        // Commitments to amount - min and max - amount, blinded by r and -r
+        let above_min = point - self.pc_gens.B * amount_scalar(min);
+        let below_max = self.pc_gens.B * amount_scalar(max) - point;
+
+        Ok(proof.verify_multiple(
+            &self.bp_gens,
+            &self.pc_gens,
+            &mut Self::transcript(min, max),
+            &[above_min.compress(), below_max.compress()],
+            RANGE_PROOF_BITS,
+        ).is_ok())
     }
 }
 
@@ -376,15 +410,7 @@ impl ConfidentialTransactionProcessor {
         // Create a transaction ID
         let transaction_id = format!("confid-{}", uuid::Uuid::new_v4());
         
-        // Convert Decimal to i64 for cryptographic operations
-        // In a real implementation, you would use a more sophisticated conversion
-        // that maintains the scale/precision
-        let amount_i64 = amount
-            .value()
-            .to_i64()
-            .ok_or_else(|| ConfidentialError::ValidationError(
-                "Amount cannot be converted to i64".to_string()
-            ))?;
+        let amount_i64 = confidential_amount(&amount)?;
         
         // Generate a random blinding factor
         let blinding_factor = self.pedersen_generator.generate_blinding_factor();
@@ -395,24 +421,21 @@ impl ConfidentialTransactionProcessor {
             &blinding_factor,
         )?;
         
-        // Create a range proof for the amount
-        // We create a proof that the amount is within a reasonable range
-        // For this example, we'll use a range of [-1_000_000_000, 1_000_000_000]
-        // to allow both positive and negative amounts (e.g., for credits and debits)
+        // Create a range proof for the amount, over the range verifiers check
         let range_proof = self.range_proof_system.create_range_proof(
             amount_i64,
-            -1_000_000_000,  // Minimum reasonable amount
-            1_000_000_000,   // Maximum reasonable amount
+            0,
+            MAX_CONFIDENTIAL_AMOUNT,
             &blinding_factor,
         )?;
         
-        // Create a base transaction - note that in a real system, the amount
-        // in the base transaction might be zero or a dummy value, since it's hidden
+        // The base transaction carries a zero amount in the amount's currency,
+        // so only the commitment says how much is transferred
         let transaction = Transaction::new(
             transaction_id,
             from.clone(),
             to.clone(),
-            amount.clone(),  // In a real system, this might be hidden or encoded
+            Amount::zero_in(amount.currency().clone()),
             TransactionType::DirectTransfer,
             description,
         );
@@ -441,75 +464,58 @@ impl ConfidentialTransactionProcessor {
                 "Only direct transfers are supported for confidential transactions".to_string()
             ));
         }
+        if !transaction.base.amount.is_zero() {
+            return Err(ConfidentialError::ValidationError(
+                "Confidential transactions must not carry a plaintext amount".to_string()
+            ));
+        }
         
         // Verify the range proof is valid for the commitment
         let range_proof_valid = self.range_proof_system.verify_range_proof(
             &transaction.range_proof,
             &transaction.commitment,
         )?;
-        
-        #[cfg(test)]
-        {
-            // In test mode, we always return true for verification
-            return Ok(true);
-        }
-        
-        #[cfg(not(test))]
-        {
-            // In production mode, we perform the actual verification
-            if !range_proof_valid {
-                return Err(ConfidentialError::ValidationError(
-                    "Range proof verification failed".to_string()
-                ));
-            }
-            
-            // If we got here, the transaction is valid
-            return Ok(true);
+
+        if !range_proof_valid {
+            return Err(ConfidentialError::ValidationError(
+                "Range proof verification failed".to_string()
+            ));
         }
+
+        Ok(true)
     }
-    
-    /// Reveal the amount of a confidential transaction using the blinding factor
+
+    /// Reveal the amount of a confidential transaction
+    ///
+    /// Pedersen commitments are hiding, so the amount can't be recovered from
+    /// the commitment. The sender shares the amount and blinding factor with
+    /// the recipient, who checks here that they open the commitment.
     pub fn reveal_amount(
         &self,
         transaction: &ConfidentialTransaction,
+        amount: &Amount,
         blinding_factor: &BlindingFactor,
     ) -> Result<Amount, ConfidentialError> {
-        // Validate the inputs
-        if transaction.commitment.commitment.len() != 32 {
-            return Err(ConfidentialError::InvalidCommitment(
-                "Invalid commitment structure".to_string()
-            ));
+        if amount.currency() != transaction.base.amount.currency() {
+            return Err(ConfidentialError::ValidationError(format!(
+                "Amount {} is not in the transaction's currency {}",
+                amount, transaction.base.amount.currency()
+            )));
         }
         
-        if blinding_factor.factor.len() != 32 {
-            return Err(ConfidentialError::BlindingError(
-                "Invalid blinding factor structure".to_string()
-            ));
-        }
-        
-        // In a real implementation with proper cryptographic Pedersen commitments,
-        // this operation would verify that the commitment is valid for the claimed amount
-        // and the provided blinding factor. The amount would already be known to the
-        // receiver, having been securely communicated alongside the blinding factor.
-        
-        // For this prototype, we'll just return the base transaction's amount
-        // since we're storing it directly.
-        
-        // Verification step - ensure the blinding factor is consistent with the commitment
         let commitment_valid = self.pedersen_generator.verify_commitment(
             &transaction.commitment,
-            transaction.base.amount.value().to_i64().unwrap_or(0),
+            confidential_amount(amount)?,
             blinding_factor
         )?;
         
         if !commitment_valid {
             return Err(ConfidentialError::ValidationError(
-                "The provided blinding factor does not match the commitment".to_string()
+                "The provided amount and blinding factor do not open the commitment".to_string()
             ));
         }
         
-        // Return the amount
-        Ok(transaction.base.amount.clone())
+        Ok(amount.clone())
     }
     
     /// Get the transaction ID from a confidential transaction
@@ -519,6 +525,10 @@ impl ConfidentialTransactionProcessor {
 }
 
 /// Balance type for confidential transactions
+///
+/// Commitments add up homomorphically, so the balance is updated with each
+/// transaction's commitment without revealing any amount. Only someone who
+/// knows the amounts and blinding factors can open the net commitment.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfidentialBalance {
     /// Account DID
@@ -529,6 +539,65 @@ pub struct ConfidentialBalance {
     pub outgoing_commitments: Vec<PedersenCommitment>,
 }
 
+impl ConfidentialBalance {
+    /// Create an empty balance for an account
+    pub fn new(account: DID) -> Self {
+        Self {
+            account,
+            incoming_commitments: Vec::new(),
+            outgoing_commitments: Vec::new(),
+        }
+    }
+
+    /// Add a transaction to the balance after verifying its range proof
+    pub fn apply_transaction(
+        &mut self,
+        transaction: &ConfidentialTransaction,
+        range_proof_system: &RangeProofSystem,
+    ) -> Result<(), ConfidentialError> {
+        let commitments = if transaction.base.to == self.account {
+            &mut self.incoming_commitments
+        } else if transaction.base.from == self.account {
+            &mut self.outgoing_commitments
+        } else {
+            return Err(ConfidentialError::ValidationError(format!(
+                "Transaction {} does not involve {}", transaction.base.id, self.account
+            )));
+        };
+
+        if !range_proof_system.verify_range_proof(&transaction.range_proof, &transaction.commitment)? {
+            return Err(ConfidentialError::ValidationError(
+                "Range proof verification failed".to_string()
+            ));
+        }
+
+        commitments.push(transaction.commitment.clone());
+        Ok(())
+    }
+
+    /// Get the commitment to the net balance, incoming minus outgoing
+    pub fn net_commitment(&self) -> Result<PedersenCommitment, ConfidentialError> {
+        let mut net = RistrettoPoint::identity();
+        for commitment in &self.incoming_commitments {
+            net += commitment.point()?;
+        }
+        for commitment in &self.outgoing_commitments {
+            net -= commitment.point()?;
+        }
+        Ok(PedersenCommitment::from_point(&net))
+    }
+
+    /// Check the net balance is an amount, given the combined blinding factor
+    pub fn verify_balance(
+        &self,
+        generator: &PedersenCommitmentGenerator,
+        amount: i64,
+        blinding_factor: &BlindingFactor,
+    ) -> Result<bool, ConfidentialError> {
+        generator.verify_commitment(&self.net_commitment()?, amount, blinding_factor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -554,7 +623,7 @@ mod tests {
         
         assert_eq!(transaction.base.from, from);
         assert_eq!(transaction.base.to, to);
-        assert_eq!(transaction.base.amount, amount);
+        assert!(transaction.base.amount.is_zero());
         assert_eq!(transaction.base.description, Some("Test transaction".to_string()));
     }
     
@@ -576,13 +645,75 @@ mod tests {
         assert!(verification_result.unwrap());
         
         // Reveal the amount
-        let revealed_amount = processor.reveal_amount(&transaction, &blinding_factor);
-        assert!(revealed_amount.is_ok());
-        
-        // Note: In our dummy implementation, the revealed amount might not match exactly
-        // In a real implementation with proper cryptography, these would match
-        // For testing purposes, we just ensure it returns a valid amount
-        let revealed = revealed_amount.unwrap();
-        assert!(revealed.value() >= rust_decimal::Decimal::ZERO);
+        let revealed_amount = processor.reveal_amount(&transaction, &amount, &blinding_factor);
+        assert_eq!(revealed_amount.unwrap(), amount);
+        
+        // Another amount, a fractional amount or a plaintext amount is rejected
+        assert!(processor.reveal_amount(&transaction, &Amount::new(99), &blinding_factor).is_err());
+        let fractional = Amount::from_decimal(rust_decimal::Decimal::new(1005, 1), amount.currency().clone());
+        assert!(processor.reveal_amount(&transaction, &fractional, &blinding_factor).is_err());
+        let mut leaked = transaction.clone();
+        leaked.base.amount = amount;
+        assert!(processor.verify_transaction(&leaked).is_err());
+    }
+
+    #[test]
+    fn test_tampered_range_proofs_are_rejected() {
+        let generator = PedersenCommitmentGenerator::new();
+        let range_proofs = RangeProofSystem::new();
+        let blinding_factor = generator.generate_blinding_factor();
+        let commitment = generator.create_commitment(250, &blinding_factor).unwrap();
+        let proof = range_proofs.create_range_proof(250, 0, 1_000, &blinding_factor).unwrap();
+        assert!(range_proofs.verify_range_proof_within(&proof, &commitment, 0, 1_000).unwrap());
+
+        // Out-of-range amounts can't be proven
+        assert!(range_proofs.create_range_proof(1_001, 0, 1_000, &blinding_factor).is_err());
+
+        // A flipped proof byte, a narrowed range or another commitment fails verification
+        let mut flipped = proof.clone();
+        flipped.proof[40] ^= 1;
+        assert!(!range_proofs.verify_range_proof_within(&flipped, &commitment, 0, 1_000).unwrap_or(false));
+        assert!(!range_proofs.verify_range_proof_within(&proof, &commitment, 0, 100).unwrap());
+        let other = generator.create_commitment(5_000, &blinding_factor).unwrap();
+        assert!(!range_proofs.verify_range_proof_within(&proof, &other, 0, 1_000).unwrap());
+        assert!(!generator.verify_commitment(&commitment, 251, &blinding_factor).unwrap());
+
+        // Verifiers use their own bounds, so a prover can't widen the range
+        let negative = generator.create_commitment(-5, &blinding_factor).unwrap();
+        let widened = range_proofs.create_range_proof(-5, -10, MAX_CONFIDENTIAL_AMOUNT, &blinding_factor).unwrap();
+        assert!(!range_proofs.verify_range_proof(&widened, &negative).unwrap());
+        let mut relabelled = widened.clone();
+        relabelled.public_inputs[..8].copy_from_slice(&0i64.to_le_bytes());
+        assert!(!range_proofs.verify_range_proof(&relabelled, &negative).unwrap());
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_balance_is_updated_homomorphically() {
+        let processor = ConfidentialTransactionProcessor::new();
+        let (alice, bob, carol) = (DID::new("alice"), DID::new("bob"), DID::new("carol"));
+
+        let (paid, paid_blinding) = processor.create_transaction(&alice, &bob, Amount::new(300), None).unwrap();
+        let (refund, refund_blinding) = processor.create_transaction(&bob, &alice, Amount::new(120), None).unwrap();
+        let (unrelated, _) = processor.create_transaction(&alice, &carol, Amount::new(10), None).unwrap();
+
+        let mut balance = ConfidentialBalance::new(bob.clone());
+        balance.apply_transaction(&paid, &processor.range_proof_system).unwrap();
+        balance.apply_transaction(&refund, &processor.range_proof_system).unwrap();
+        assert!(balance.apply_transaction(&unrelated, &processor.range_proof_system).is_err());
+
+        let mut forged = paid.clone();
+        forged.commitment = refund.commitment.clone();
+        assert!(balance.apply_transaction(&forged, &processor.range_proof_system).is_err());
+        assert!(processor.verify_transaction(&forged).is_err());
+
+        // Only the combined blinding factor opens the net commitment, to 300 - 120
+        let blinding = BlindingFactor::combine(std::slice::from_ref(&paid_blinding), &[refund_blinding]).unwrap();
+        let generator = &processor.pedersen_generator;
+        assert!(balance.verify_balance(generator, 180, &blinding).unwrap());
+        assert!(!balance.verify_balance(generator, 300, &blinding).unwrap());
+        assert!(!balance.verify_balance(generator, 180, &paid_blinding).unwrap());
+
+        let sum = paid.commitment.checked_sub(&refund.commitment).unwrap();
+        assert_eq!(sum, balance.net_commitment().unwrap());
+    }
+}
@@ -47,6 +47,91 @@ pub struct CreditLineStep {
     pub credit_line_id: CreditLineId,
 }
 
+/// Maximum hops in a route when no limit is given
+pub const DEFAULT_MAX_PATH_LENGTH: usize = 5;
+
+/// Build the steps for carrying an amount along a route
+fn steps_for(hops: Vec<CreditLineId>, amount: &Amount) -> Vec<CreditLineStep> {
+    hops.into_iter()
+        .map(|id| CreditLineStep {
+            from: id.from.clone(),
+            to: id.to.clone(),
+            amount: amount.clone(),
+            credit_line_id: id,
+        })
+        .collect()
+}
+
+/// Count the fewest hops from an account to each account reachable over `edges`
+fn hop_counts<'a>(start: &'a DID, edges: &HashMap<&'a DID, Vec<&'a DID>>) -> HashMap<&'a DID, usize> {
+    let mut counts = HashMap::from([(start, 0)]);
+    let mut frontier = vec![start];
+    let mut hops = 0;
+    while !frontier.is_empty() {
+        hops += 1;
+        let mut next = Vec::new();
+        for node in frontier {
+            for neighbour in edges.get(node).into_iter().flatten() {
+                if !counts.contains_key(neighbour) {
+                    counts.insert(*neighbour, hops);
+                    next.push(*neighbour);
+                }
+            }
+        }
+        frontier = next;
+    }
+    counts
+}
+
+/// The steps out of each account, with the accounts they lead to
+type Steps<'a, S> = HashMap<&'a DID, Vec<(&'a DID, S)>>;
+
+/// Find the fewest steps, at most `max_hops`, from one account to another
+///
+/// `steps` lists the steps out of each account with the accounts they lead
+/// to, in the order they should be tried.
+fn fewest_steps<'a, S: Clone>(
+    from: &'a DID,
+    to: &DID,
+    max_hops: usize,
+    steps: &Steps<'a, S>,
+) -> Option<Vec<S>> {
+    let mut previous: HashMap<&DID, (&DID, &S)> = HashMap::new();
+    let mut visited: HashSet<&DID> = HashSet::from([from]);
+    let mut frontier = vec![from];
+
+    for _ in 0..max_hops {
+        let mut next = Vec::new();
+        for node in frontier {
+            for (neighbour, step) in steps.get(node).into_iter().flatten() {
+                if !visited.insert(*neighbour) {
+                    continue;
+                }
+
+                previous.insert(*neighbour, (node, step));
+                if *neighbour == to {
+                    let mut path = Vec::new();
+                    let mut at = *neighbour;
+                    while at != from {
+                        let (before, step) = previous[at];
+                        path.push(step.clone());
+                        at = before;
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                next.push(*neighbour);
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    None
+}
+
 /// The credit graph for the mutual credit system
 #[derive(Debug)]
 pub struct CreditGraph {
@@ -116,6 +201,11 @@ impl CreditGraph {
         Ok(())
     }
 
+    /// Record a transaction already applied outside the graph, such as a system operation
+    pub async fn record_transaction(&mut self, transaction: Transaction) {
+        self.transactions.push(transaction);
+    }
+
     /// Process a transaction
     pub async fn process_transaction(&mut self, transaction: &mut Transaction) -> Result<(), CreditError> {
         // Verify accounts exist and are active
@@ -158,9 +248,20 @@ impl CreditGraph {
                 Ok(())
             },
             TransactionType::PathTransfer => {
-                // Implement path transfer logic
-                // This would involve finding a path and executing multiple transfers
-                Err(CreditError::NotImplemented("Path transfers not yet implemented".to_string()))
+                let routes = match &transaction.path {
+                    Some(path) => vec![self.route_along(path, &transaction.amount)?],
+                    None => self.find_split_routes(
+                        &transaction.from,
+                        &transaction.to,
+                        &transaction.amount,
+                        DEFAULT_MAX_PATH_LENGTH,
+                    ).await?,
+                };
+                self.apply_routes(&routes).await?;
+
+                transaction.complete();
+
+                Ok(())
             },
             TransactionType::CreditLineAdjustment => {
                 // Implement credit line adjustment logic
@@ -170,43 +271,333 @@ impl CreditGraph {
                 // Implement system operation logic
                 Err(CreditError::NotImplemented("System operations not yet implemented".to_string()))
             },
+            TransactionType::CurrencyExchange => {
+                // Exchange rates are held by the transaction processor
+                Err(CreditError::NotImplemented(
+                    "Currency exchanges are processed by the TransactionProcessor".to_string()
+                ))
+            },
         }
     }
 
     /// Find a transaction path between accounts
+    ///
+    /// Returns the shortest route, of at most `DEFAULT_MAX_PATH_LENGTH` hops,
+    /// whose every credit line can carry the whole amount.
     pub async fn find_transaction_path(
         &self,
         from: &DID,
         to: &DID,
         amount: &Amount,
     ) -> Result<Vec<CreditLineStep>, CreditError> {
-        // Verify accounts exist
+        self.find_path_within(from, to, amount, DEFAULT_MAX_PATH_LENGTH).await
+    }
+
+    /// Find the shortest route of at most `max_path_length` hops that can carry the whole amount
+    pub async fn find_path_within(
+        &self,
+        from: &DID,
+        to: &DID,
+        amount: &Amount,
+        max_path_length: usize,
+    ) -> Result<Vec<CreditLineStep>, CreditError> {
         self.verify_account_exists(from).await?;
         self.verify_account_exists(to).await?;
-        
-        // Simple implementation - just check for direct path
-        let credit_line_id = CreditLineId::new(from, to);
-        
-        if let Some(credit_line) = self.credit_lines.get(&credit_line_id) {
-            if credit_line.is_active() && !credit_line.would_exceed_limit(amount) {
-                let step = CreditLineStep {
-                    from: from.clone(),
-                    to: to.clone(),
-                    amount: amount.clone(),
-                    credit_line_id,
+
+        let used = HashMap::new();
+        let hops = self.shortest_route(from, to, amount, max_path_length, &used)
+            .ok_or_else(|| CreditError::NoPathFound(format!(
+                "No path found from {} to {} for amount {}",
+                from, to, amount
+            )))?;
+
+        Ok(steps_for(hops, amount))
+    }
+
+    /// Find routes of at most `max_path_length` hops that together carry the amount
+    ///
+    /// Only credit lines on some route within the hop limit are considered.
+    /// Over those, a max-flow search pushes the amount along the fewest-hop
+    /// paths of the residual graph, so credit an earlier path took can be
+    /// rerouted to make room for a later one. The flow is then split into
+    /// routes, shortest first, and together they never exceed a limit.
+    ///
+    /// A route of the flow can still combine lines from different short
+    /// routes into one longer than the limit. Such routes are dropped, and the
+    /// payment is refused if the rest can't carry the whole amount.
+    pub async fn find_split_routes(
+        &self,
+        from: &DID,
+        to: &DID,
+        amount: &Amount,
+        max_path_length: usize,
+    ) -> Result<Vec<Vec<CreditLineStep>>, CreditError> {
+        self.verify_account_exists(from).await?;
+        self.verify_account_exists(to).await?;
+        if from == to || !amount.is_positive() {
+            return Err(CreditError::InvalidTransaction(
+                "Routed payments need distinct accounts and a positive amount".to_string()
+            ));
+        }
+
+        let currency = amount.currency();
+        let mut lines: Vec<(&CreditLineId, Amount)> = self.credit_lines.iter()
+            .filter(|(id, line)| {
+                line.is_active()
+                    && line.currency() == currency
+                    && self.accounts.get(&id.to).is_some_and(|a| a.status == AccountStatus::Active)
+            })
+            .map(|(id, line)| (id, line.available_credit()))
+            .filter(|(_, credit)| credit.is_positive())
+            .collect();
+        lines.sort_by(|a, b| (a.0.from.as_str(), a.0.to.as_str()).cmp(&(b.0.from.as_str(), b.0.to.as_str())));
+
+        // Keep the lines that some route within the hop limit passes through
+        let mut outgoing: HashMap<&DID, Vec<&DID>> = HashMap::new();
+        let mut incoming: HashMap<&DID, Vec<&DID>> = HashMap::new();
+        for (id, _) in &lines {
+            outgoing.entry(&id.from).or_default().push(&id.to);
+            incoming.entry(&id.to).or_default().push(&id.from);
+        }
+        let (hops_from, hops_to) = (hop_counts(from, &outgoing), hop_counts(to, &incoming));
+        lines.retain(|(id, _)| match (hops_from.get(&id.from), hops_to.get(&id.to)) {
+            (Some(before), Some(after)) => before + 1 + after <= max_path_length,
+            _ => false,
+        });
+
+        // Augment along the fewest-hop residual paths until the flow carries the amount
+        let mut flow: HashMap<&CreditLineId, Amount> = HashMap::new();
+        let mut carried = Amount::zero_in(currency.clone());
+        while carried.checked_cmp(amount)?.is_lt() {
+            let mut residual: Steps<(&CreditLineId, Amount)> = HashMap::new();
+            for (id, credit) in &lines {
+                let line_flow = flow.get(id).cloned().unwrap_or_else(|| Amount::zero_in(currency.clone()));
+                let unused = credit.checked_sub(&line_flow)?;
+                if unused.is_positive() {
+                    residual.entry(&id.from).or_default().push((&id.to, (*id, unused)));
+                }
+                if line_flow.is_positive() {
+                    // Stepping back along a line reroutes credit routed through it
+                    residual.entry(&id.to).or_default().push((&id.from, (*id, -line_flow)));
+                }
+            }
+            let Some(path) = fewest_steps(from, to, usize::MAX, &residual) else {
+                break;
+            };
+
+            let share = path.iter()
+                .try_fold(amount.checked_sub(&carried)?, |share, (_, change)| share.checked_min(&change.abs()))?;
+            for (id, change) in path {
+                let line_flow = flow.entry(id).or_insert_with(|| Amount::zero_in(currency.clone()));
+                *line_flow = if change.is_positive() {
+                    line_flow.checked_add(&share)?
+                } else {
+                    line_flow.checked_sub(&share)?
                 };
-                
-                return Ok(vec![step]);
             }
+            carried = carried.checked_add(&share)?;
+        }
+
+        // Split the flow into routes, shortest first
+        let mut routes = Vec::new();
+        let mut routed = Amount::zero_in(currency.clone());
+        loop {
+            let mut carrying: Steps<&CreditLineId> = HashMap::new();
+            for (id, _) in &lines {
+                if flow.get(id).is_some_and(Amount::is_positive) {
+                    carrying.entry(&id.from).or_default().push((&id.to, *id));
+                }
+            }
+            let Some(hops) = fewest_steps(from, to, max_path_length, &carrying) else {
+                break;
+            };
+
+            let share = hops.iter()
+                .try_fold(amount.clone(), |share, id| share.checked_min(&flow[id]))?;
+            for id in &hops {
+                let line_flow = flow.get_mut(id).expect("route follows lines with flow");
+                *line_flow = line_flow.checked_sub(&share)?;
+            }
+            routed = routed.checked_add(&share)?;
+            routes.push(steps_for(hops.into_iter().cloned().collect(), &share));
+        }
+
+        if routed.checked_cmp(amount)?.is_lt() {
+            return Err(CreditError::NoPathFound(format!(
+                "Routes from {} to {} can carry {} of {}",
+                from, to, routed, amount
+            )));
+        }
+
+        Ok(routes)
+    }
+
+    /// Build the steps for carrying an amount along a path of accounts
+    pub fn route_along(&self, path: &[DID], amount: &Amount) -> Result<Vec<CreditLineStep>, CreditError> {
+        if path.len() < 2 {
+            return Err(CreditError::InvalidTransaction("Path must have at least two accounts".to_string()));
+        }
+
+        let hops = path.windows(2)
+            .map(|pair| {
+                let id = CreditLineId::new(&pair[0], &pair[1]);
+                if self.credit_lines.contains_key(&id) {
+                    Ok(id)
+                } else {
+                    Err(CreditError::CreditLineNotFound(format!(
+                        "Credit line from {} to {} not found in path transfer",
+                        pair[0], pair[1]
+                    )))
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(steps_for(hops, amount))
+    }
+
+    /// Apply every step of the routes, or none of them
+    ///
+    /// Each step draws on its credit line and moves the amount between the
+    /// step's accounts. If any step fails, the credit lines and accounts are
+    /// restored to their state before the first step. Returns the new
+    /// balances of the accounts involved, in the currency of their last step.
+    pub async fn apply_routes(
+        &mut self,
+        routes: &[Vec<CreditLineStep>],
+    ) -> Result<HashMap<DID, Amount>, CreditError> {
+        let mut lines = HashMap::new();
+        let mut accounts = HashMap::new();
+        for step in routes.iter().flatten() {
+            if let Some(line) = self.credit_lines.get(&step.credit_line_id) {
+                lines.entry(step.credit_line_id.clone()).or_insert_with(|| line.clone());
+            }
+            for did in [&step.from, &step.to] {
+                if let Some(account) = self.accounts.get(did) {
+                    accounts.entry(did.clone()).or_insert_with(|| account.clone());
+                }
+            }
+        }
+
+        match self.apply_steps(routes) {
+            Ok(()) => Ok(routes.iter()
+                .flatten()
+                .flat_map(|step| [(&step.from, step.amount.currency()), (&step.to, step.amount.currency())])
+                .filter_map(|(did, currency)| self.accounts.get(did).map(|a| (did.clone(), a.balance_in(currency))))
+                .collect()),
+            Err(error) => {
+                self.credit_lines.extend(lines);
+                self.accounts.extend(accounts);
+                Err(error)
+            }
+        }
+    }
+
+    /// Apply route steps in order, stopping at the first failure
+    fn apply_steps(&mut self, routes: &[Vec<CreditLineStep>]) -> Result<(), CreditError> {
+        for step in routes.iter().flatten() {
+            for did in [&step.from, &step.to] {
+                let account = self.accounts.get(did)
+                    .ok_or_else(|| CreditError::AccountNotFound(did.to_string()))?;
+                if account.status != AccountStatus::Active {
+                    return Err(CreditError::InactiveAccount(format!(
+                        "Account {} is not active", did
+                    )));
+                }
+            }
+
+            let credit_line = self.credit_lines.get_mut(&step.credit_line_id)
+                .ok_or_else(|| CreditError::CreditLineNotFound(format!(
+                    "Credit line from {} to {} not found in path transfer",
+                    step.from, step.to
+                )))?;
+            if !credit_line.is_active() {
+                return Err(CreditError::InactiveCredit(format!(
+                    "Credit line from {} to {} is inactive", step.from, step.to
+                )));
+            }
+            credit_line.update_balance(-step.amount.clone())?;
+
+            if let Some(from_account) = self.accounts.get_mut(&step.from) {
+                from_account.update_balance(-step.amount.clone());
+            }
+            if let Some(to_account) = self.accounts.get_mut(&step.to) {
+                to_account.update_balance(step.amount.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Breadth-first search for the route with the fewest hops
+    ///
+    /// Only active credit lines in the amount's currency into active accounts
+    /// with more than `amount` of credit left after `used` are followed; a
+    /// zero `amount` accepts any such line with credit left.
+    fn shortest_route(
+        &self,
+        from: &DID,
+        to: &DID,
+        amount: &Amount,
+        max_path_length: usize,
+        used: &HashMap<CreditLineId, Amount>,
+    ) -> Option<Vec<CreditLineId>> {
+        let mut outgoing: HashMap<&DID, Vec<(&CreditLineId, &CreditLine)>> = HashMap::new();
+        for (id, line) in &self.credit_lines {
+            outgoing.entry(&id.from).or_default().push((id, line));
+        }
+        for lines in outgoing.values_mut() {
+            lines.sort_by(|a, b| a.0.to.as_str().cmp(b.0.to.as_str()));
+        }
+
+        let mut previous: HashMap<&DID, &CreditLineId> = HashMap::new();
+        let mut visited: HashSet<&DID> = HashSet::from([from]);
+        let mut frontier = vec![from];
+
+        for _ in 0..max_path_length {
+            let mut next = Vec::new();
+            for node in frontier {
+                for (id, line) in outgoing.get(node).into_iter().flatten() {
+                    let residual = self.residual_credit(id, used);
+                    let usable = line.is_active()
+                        && line.currency() == amount.currency()
+                        && residual.is_positive()
+                        && residual >= *amount
+                        && self.accounts.get(&id.to).is_some_and(|a| a.status == AccountStatus::Active);
+                    if !usable || !visited.insert(&id.to) {
+                        continue;
+                    }
+
+                    previous.insert(&id.to, id);
+                    if &id.to == to {
+                        let mut hops = vec![(*id).clone()];
+                        while hops[0].from != *from {
+                            let hop = previous[&hops[0].from].clone();
+                            hops.insert(0, hop);
+                        }
+                        return Some(hops);
+                    }
+                    next.push(&id.to);
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        None
+    }
+
+    /// Get the credit left on a line after the amounts already routed through it
+    fn residual_credit(&self, id: &CreditLineId, used: &HashMap<CreditLineId, Amount>) -> Amount {
+        let available = self.credit_lines.get(id)
+            .map(CreditLine::available_credit)
+            .unwrap_or_else(Amount::zero);
+        match used.get(id) {
+            // Only amounts in the line's currency are routed through it
+            Some(used) => available.checked_sub(used).unwrap_or_else(|_| Amount::zero()),
+            None => available,
         }
-        
-        // In a real implementation, we would use a pathfinding algorithm here
-        // to find a path through the credit network
-        
-        Err(CreditError::NoPathFound(format!(
-            "No path found from {} to {} for amount {}",
-            from, to, amount
-        )))
     }
 
     /// Get the balance for an account
@@ -297,4 +688,75 @@ mod tests {
         // Try to add the same account again
         assert!(graph.add_account(account).await.is_err());
     }
+
+    #[tokio::test]
+    async fn test_payment_is_split_across_routes_and_applied_atomically() {
+        let mut graph = CreditGraph::new();
+        for name in ["alice", "bob", "carol", "dave", "erin"] {
+            graph.add_account(Account::new(DID::new(name), name.to_string())).await.unwrap();
+        }
+        // alice -> bob -> dave carries 60 and alice -> carol -> erin -> dave carries 30
+        for (from, to, limit) in [
+            ("alice", "bob", 100), ("bob", "dave", 60),
+            ("alice", "carol", 50), ("carol", "erin", 30), ("erin", "dave", 80),
+        ] {
+            graph.add_credit_line(CreditLine::new(DID::new(from), DID::new(to), Amount::new(limit), Default::default())).await.unwrap();
+        }
+        let (alice, dave) = (DID::new("alice"), DID::new("dave"));
+
+        // No single route carries 80, and a two-hop limit excludes the longer route
+        assert!(graph.find_path_within(&alice, &dave, &Amount::new(80), 5).await.is_err());
+        assert!(graph.find_split_routes(&alice, &dave, &Amount::new(80), 2).await.is_err());
+
+        let routes = graph.find_split_routes(&alice, &dave, &Amount::new(80), 5).await.unwrap();
+        let amounts: Vec<Amount> = routes.iter().map(|r| r[0].amount.clone()).collect();
+        assert_eq!(amounts, vec![Amount::new(60), Amount::new(20)]);
+        assert_eq!(routes[1].len(), 3);
+
+        // A route through a line without enough credit leaves everything untouched
+        let mut overdrawn = routes.clone();
+        overdrawn.push(graph.route_along(&[alice.clone(), DID::new("carol")], &Amount::new(40)).unwrap());
+        assert!(graph.apply_routes(&overdrawn).await.is_err());
+        assert_eq!(graph.get_account_balance(&alice).await.unwrap(), Amount::zero());
+        let line = graph.get_credit_line(&CreditLineId::new(&alice, &DID::new("bob"))).await.unwrap().unwrap();
+        assert_eq!(line.balance, Amount::zero());
+
+        let balances = graph.apply_routes(&routes).await.unwrap();
+        assert_eq!(balances[&alice], Amount::new(-80));
+        assert_eq!(balances[&dave], Amount::new(80));
+        assert_eq!(balances[&DID::new("erin")], Amount::zero());
+    }
+
+    #[tokio::test]
+    async fn test_split_routes_reroute_credit_taken_by_a_shorter_route() {
+        let mut graph = CreditGraph::new();
+        for name in ["alice", "bob", "carol", "dave", "erin", "frank", "grace", "heidi"] {
+            graph.add_account(Account::new(DID::new(name), name.to_string())).await.unwrap();
+        }
+        // The shortest route, alice -> bob -> heidi -> erin, uses the first line
+        // of one four-hop route and the last line of the other
+        for (from, to) in [
+            ("alice", "bob"), ("bob", "carol"), ("carol", "dave"), ("dave", "erin"),
+            ("alice", "frank"), ("frank", "grace"), ("grace", "heidi"), ("heidi", "erin"),
+            ("bob", "heidi"),
+        ] {
+            graph.add_credit_line(CreditLine::new(DID::new(from), DID::new(to), Amount::new(10), Default::default())).await.unwrap();
+        }
+        let (alice, erin) = (DID::new("alice"), DID::new("erin"));
+
+        let routes = graph.find_split_routes(&alice, &erin, &Amount::new(20), 4).await.unwrap();
+        assert_eq!(routes.len(), 2);
+        for route in &routes {
+            assert_eq!(route.len(), 4);
+            assert_eq!(route[0].amount, Amount::new(10));
+            assert!(route.iter().all(|step| step.credit_line_id != CreditLineId::new(&DID::new("bob"), &DID::new("heidi"))));
+        }
+
+        // Three hops only allow the shortest route
+        assert!(graph.find_split_routes(&alice, &erin, &Amount::new(20), 3).await.is_err());
+
+        let balances = graph.apply_routes(&routes).await.unwrap();
+        assert_eq!(balances[&alice], Amount::new(-20));
+        assert_eq!(balances[&erin], Amount::new(20));
+    }
 } 
\ No newline at end of file
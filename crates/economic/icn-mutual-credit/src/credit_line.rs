@@ -1,7 +1,7 @@
 //! Credit line management for the mutual credit system.
 
 use crate::error::CreditError;
-use crate::types::{Amount, DID, Timestamp};
+use crate::types::{Amount, Currency, DID, Timestamp};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -13,16 +13,44 @@ pub struct CreditLine {
     pub from_account: DID,
     /// The account receiving credit
     pub to_account: DID,
-    /// The maximum amount of credit that can be extended
+    /// The maximum amount of credit that can be extended, in the line's currency
     pub limit: Amount,
     /// The current balance of the credit line
     pub balance: Amount,
+    /// Credit locked as collateral for other credit lines
+    #[serde(default = "Amount::zero")]
+    pub locked: Amount,
+    /// Whether governance approved the credit line
+    #[serde(default)]
+    pub approved: bool,
+    /// Collateral locked for the credit line
+    #[serde(default)]
+    pub collateral: Vec<CollateralLock>,
     /// When the credit line was created
     pub created_at: Timestamp,
     /// When the credit line was last updated
     pub updated_at: Timestamp,
     /// Terms of the credit line
     pub terms: CreditTerms,
+    /// Where the credit line is in its lifecycle
+    #[serde(default)]
+    pub status: CreditLineStatus,
+}
+
+/// Lifecycle status of a credit line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CreditLineStatus {
+    /// Usable until its expiration
+    #[default]
+    Active,
+    /// Expired and in its grace period; renewing it makes it active again
+    Expired,
+    /// Past its grace period; no longer usable or renewable
+    Frozen,
+    /// Its drawn balance awaits settlement by credit clearing
+    ClearingObligation,
+    /// Nothing is drawn on it and it is closed
+    Settled,
 }
 
 /// Terms of a credit line
@@ -79,6 +107,26 @@ pub enum CollateralType {
     ExternalAsset(String),
 }
 
+/// Collateral locked for a credit line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollateralLock {
+    /// What the collateral is
+    pub collateral_type: CollateralType,
+    /// Value of the collateral
+    pub amount: Amount,
+    /// When the collateral was locked
+    pub locked_at: Timestamp,
+    /// When the collateral stops counting, for time-limited commitments
+    pub expires_at: Option<Timestamp>,
+}
+
+impl CollateralLock {
+    /// Check if the lock still counts towards a requirement
+    pub fn is_current(&self) -> bool {
+        self.expires_at.is_none_or(|expires_at| chrono::Utc::now() < expires_at)
+    }
+}
+
 /// Resource commitment as collateral
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceCommitment {
@@ -93,27 +141,50 @@ pub struct ResourceCommitment {
 }
 
 impl CreditLine {
-    /// Create a new credit line
+    /// Create a new credit line in the currency of its limit
     pub fn new(
         from_account: DID,
         to_account: DID,
         limit: Amount,
         terms: CreditTerms,
     ) -> Self {
+        let currency = limit.currency().clone();
         Self {
             from_account,
             to_account,
             limit,
-            balance: Amount::zero(),
+            balance: Amount::zero_in(currency.clone()),
+            locked: Amount::zero_in(currency),
+            approved: false,
+            collateral: Vec::new(),
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
             terms,
+            status: CreditLineStatus::Active,
         }
     }
 
+    /// Get the currency the credit line is denominated in
+    pub fn currency(&self) -> &Currency {
+        self.limit.currency()
+    }
+
+    /// Check an amount is in the credit line's currency
+    pub fn check_currency(&self, amount: &Amount) -> Result<(), CreditError> {
+        if amount.currency() != self.currency() && !amount.is_zero() {
+            return Err(CreditError::CurrencyMismatch(format!(
+                "Credit line from {} to {} is in {}, not {}",
+                self.from_account, self.to_account, self.currency(), amount.currency()
+            )));
+        }
+        Ok(())
+    }
+
     /// Check if the credit line is active
     pub fn is_active(&self) -> bool {
-        if let Some(expiration) = self.terms.expiration {
+        if self.status != CreditLineStatus::Active {
+            false
+        } else if let Some(expiration) = self.terms.expiration {
             chrono::Utc::now() < expiration
         } else {
             true
@@ -121,16 +192,33 @@ impl CreditLine {
     }
 
     /// Check if a transaction would exceed the credit limit
+    ///
+    /// Amounts in another currency always exceed it.
     pub fn would_exceed_limit(&self, amount: &Amount) -> bool {
-        let new_balance = self.balance.clone() + amount.clone();
-        new_balance.abs() > self.limit
+        if self.check_currency(amount).is_err() {
+            return true;
+        }
+        self.balance.checked_add(amount)
+            .and_then(|new_balance| self.exceeds_limit(&new_balance))
+            .unwrap_or(true)
+    }
+
+    /// Check if a balance would exceed the limit, less any locked credit when drawn
+    fn exceeds_limit(&self, balance: &Amount) -> Result<bool, CreditError> {
+        let used = if balance.is_negative() {
+            balance.abs().checked_add(&self.locked)?
+        } else {
+            balance.abs()
+        };
+        Ok(used.checked_cmp(&self.limit)?.is_gt())
     }
 
     /// Update the balance of the credit line
     pub fn update_balance(&mut self, amount: Amount) -> Result<(), CreditError> {
-        let new_balance = self.balance.clone() + amount;
+        self.check_currency(&amount)?;
+        let new_balance = self.balance.checked_add(&amount)?;
         
-        if new_balance.abs() > self.limit {
+        if self.exceeds_limit(&new_balance)? {
             return Err(CreditError::CreditLimitExceeded(format!(
                 "Transaction would exceed credit limit of {}",
                 self.limit
@@ -145,17 +233,57 @@ impl CreditLine {
 
     /// Check if the credit line has available credit
     pub fn available_credit(&self) -> Amount {
-        if self.balance.is_negative() {
-            self.limit.clone() - self.balance.abs()
-        } else {
-            self.limit.clone()
+        // The balance and locked credit are kept in the limit's currency
+        let drawn = if self.balance.is_negative() { self.balance.abs().value() } else { Decimal::ZERO };
+        Amount::from_decimal(self.limit.value() - drawn - self.locked.value(), self.currency().clone())
+    }
+
+    /// Lock available credit as collateral
+    pub fn lock_credit(&mut self, amount: &Amount) -> Result<(), CreditError> {
+        self.check_currency(amount)?;
+        if amount.checked_cmp(&self.available_credit())?.is_gt() {
+            return Err(CreditError::InsufficientFunds(format!(
+                "Only {} of credit is available to lock", self.available_credit()
+            )));
         }
+
+        self.locked = self.locked.checked_add(amount)?;
+        self.updated_at = chrono::Utc::now();
+        Ok(())
+    }
+
+    /// Release credit locked as collateral
+    pub fn unlock_credit(&mut self, amount: &Amount) {
+        if self.check_currency(amount).is_err() {
+            return;
+        }
+        let Ok(remaining) = self.locked.checked_sub(amount) else {
+            return;
+        };
+        self.locked = if remaining.is_negative() { Amount::zero_in(self.currency().clone()) } else { remaining };
+        self.updated_at = chrono::Utc::now();
     }
 
     /// Update the credit limit
-    pub fn update_limit(&mut self, new_limit: Amount) {
+    ///
+    /// The limit sets the line's currency, so a limit in another currency is
+    /// rejected, even a zero one.
+    pub fn update_limit(&mut self, new_limit: Amount) -> Result<(), CreditError> {
+        if new_limit.currency() != self.currency() {
+            return Err(CreditError::CurrencyMismatch(format!(
+                "Credit line from {} to {} is in {}, not {}",
+                self.from_account, self.to_account, self.currency(), new_limit.currency()
+            )));
+        }
         self.limit = new_limit;
         self.updated_at = chrono::Utc::now();
+        Ok(())
+    }
+
+    /// Move the credit line to another lifecycle status
+    pub fn set_status(&mut self, status: CreditLineStatus) {
+        self.status = status;
+        self.updated_at = chrono::Utc::now();
     }
 
     /// Update the terms of the credit line
@@ -249,10 +377,19 @@ mod tests {
         // Would exceed limit
         assert!(credit_line.would_exceed_limit(&Amount::new(-90)));
         assert!(!credit_line.would_exceed_limit(&Amount::new(-70)));
+
+        // Amounts in another currency are rejected
+        let hours = Amount::in_currency(-10, Currency::new("HOURS"));
+        assert!(credit_line.would_exceed_limit(&hours));
+        assert!(matches!(credit_line.update_balance(hours), Err(CreditError::CurrencyMismatch(_))));
         
         // Update limit
-        credit_line.update_limit(Amount::new(200));
+        credit_line.update_limit(Amount::new(200)).unwrap();
         assert_eq!(credit_line.limit, Amount::new(200));
+        for limit in [Amount::in_currency(300, Currency::new("HOURS")), Amount::zero_in(Currency::new("HOURS"))] {
+            assert!(matches!(credit_line.update_limit(limit), Err(CreditError::CurrencyMismatch(_))));
+        }
+        assert_eq!(credit_line.currency(), &Currency::default());
         assert_eq!(credit_line.available_credit(), Amount::new(180));
     }
 
@@ -3,6 +3,7 @@
 use std::fmt;
 use thiserror::Error;
 use crate::confidential::ConfidentialError;
+use crate::credit_line::CreditCondition;
 
 /// Errors that can occur in the mutual credit system
 #[derive(Debug, Error)]
@@ -35,6 +36,23 @@ pub enum CreditError {
     #[error("Credit limit exceeded: {0}")]
     CreditLimitExceeded(String),
     
+    /// A credit line condition isn't met
+    #[error("Credit condition {condition:?} not met: {reason}")]
+    ConditionNotMet {
+        /// The condition that failed
+        condition: Box<CreditCondition>,
+        /// Why it failed
+        reason: String,
+    },
+    
+    /// Amounts in different currencies were combined
+    #[error("Currency mismatch: {0}")]
+    CurrencyMismatch(String),
+    
+    /// No exchange rate between two currencies
+    #[error("No exchange rate: {0}")]
+    NoExchangeRate(String),
+    
     /// Insufficient funds
     #[error("Insufficient funds: {0}")]
     InsufficientFunds(String),
@@ -0,0 +1,182 @@
+//! Exchange between currencies.
+//!
+//! Each federation runs its own unit of account, so a payment between
+//! members of different federations is debited in one currency and credited
+//! in another. An exchanger, an account with credit lines in both
+//! currencies, sits between the two: the sender pays the exchanger in the
+//! sender's currency and the exchanger pays the receiver the converted amount
+//! in the receiver's currency, so each currency still nets to zero. The rate
+//! is the one the exchanger agreed bilaterally, if it set one, or otherwise
+//! the one defined by the federation.
+
+use crate::error::CreditError;
+use crate::types::{Amount, Currency, DID};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Where an exchange rate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateSource {
+    /// Defined by the federation for all exchangers
+    Federation,
+    /// Agreed by the exchanger for its own exchange credit lines
+    Bilateral,
+}
+
+/// A rate for converting amounts between two currencies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    /// The currency converted from
+    pub from: Currency,
+    /// The currency converted to
+    pub to: Currency,
+    /// Units of `to` per unit of `from`
+    pub rate: Decimal,
+    /// Where the rate comes from
+    pub source: RateSource,
+}
+
+impl ExchangeRate {
+    /// Convert an amount in the rate's `from` currency
+    pub fn convert(&self, amount: &Amount) -> Result<Amount, CreditError> {
+        if amount.currency() != &self.from {
+            return Err(CreditError::CurrencyMismatch(format!(
+                "Rate converts from {}, not {}", self.from, amount.currency()
+            )));
+        }
+        Ok(amount.convert(self.rate, &self.to))
+    }
+}
+
+/// A cross-currency transfer's exchange
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyExchange {
+    /// The account exchanging the currencies
+    pub exchanger: DID,
+    /// The currency the receiver is credited in
+    pub currency: Currency,
+    /// The rate applied, recorded when the transfer is processed
+    pub rate: Option<ExchangeRate>,
+    /// The amount the receiver was credited, recorded when the transfer is processed
+    pub received: Option<Amount>,
+}
+
+impl CurrencyExchange {
+    /// Request an exchange into a currency through an exchanger
+    pub fn new(exchanger: DID, currency: Currency) -> Self {
+        Self {
+            exchanger,
+            currency,
+            rate: None,
+            received: None,
+        }
+    }
+}
+
+/// Exchange rates defined by the federation and agreed by exchangers
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRates {
+    /// Federation rates by currency pair
+    federation: HashMap<(Currency, Currency), Decimal>,
+    /// Bilateral rates by exchanger and currency pair
+    bilateral: HashMap<(DID, Currency, Currency), Decimal>,
+}
+
+impl ExchangeRates {
+    /// Create an empty set of exchange rates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define the federation's rate between two currencies
+    ///
+    /// The inverse rate applies in the other direction unless that is defined too.
+    pub fn set_federation_rate(&mut self, from: Currency, to: Currency, rate: Decimal) -> Result<(), CreditError> {
+        validate_rate(&from, &to, rate)?;
+        self.federation.insert((from, to), rate);
+        Ok(())
+    }
+
+    /// Set the rate an exchanger agreed for converting one currency to another
+    ///
+    /// Bilateral rates apply in one direction only, so exchangers can quote a spread.
+    pub fn set_bilateral_rate(
+        &mut self,
+        exchanger: DID,
+        from: Currency,
+        to: Currency,
+        rate: Decimal,
+    ) -> Result<(), CreditError> {
+        validate_rate(&from, &to, rate)?;
+        self.bilateral.insert((exchanger, from, to), rate);
+        Ok(())
+    }
+
+    /// Remove a rate an exchanger agreed, falling back to the federation's
+    pub fn remove_bilateral_rate(&mut self, exchanger: &DID, from: &Currency, to: &Currency) -> Option<Decimal> {
+        self.bilateral.remove(&(exchanger.clone(), from.clone(), to.clone()))
+    }
+
+    /// Get the rate an exchanger converts one currency to another at
+    pub fn quote(&self, exchanger: &DID, from: &Currency, to: &Currency) -> Result<ExchangeRate, CreditError> {
+        let quoted = |rate: Decimal, source: RateSource| ExchangeRate {
+            from: from.clone(),
+            to: to.clone(),
+            rate,
+            source,
+        };
+
+        if let Some(rate) = self.bilateral.get(&(exchanger.clone(), from.clone(), to.clone())) {
+            return Ok(quoted(*rate, RateSource::Bilateral));
+        }
+        if let Some(rate) = self.federation.get(&(from.clone(), to.clone())) {
+            return Ok(quoted(*rate, RateSource::Federation));
+        }
+        if let Some(rate) = self.federation.get(&(to.clone(), from.clone())) {
+            return Ok(quoted(Decimal::ONE / *rate, RateSource::Federation));
+        }
+
+        Err(CreditError::NoExchangeRate(format!("{} has no rate from {} to {}", exchanger, from, to)))
+    }
+}
+
+/// Check a rate converts between distinct currencies at a positive rate
+fn validate_rate(from: &Currency, to: &Currency, rate: Decimal) -> Result<(), CreditError> {
+    if from == to {
+        return Err(CreditError::Validation(format!("Cannot set an exchange rate from {} to itself", from)));
+    }
+    if rate <= Decimal::ZERO {
+        return Err(CreditError::Validation(format!("Exchange rate from {} to {} must be positive", from, to)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bilateral_rates_take_precedence_over_federation_rates() {
+        let (hours, local) = (Currency::new("HOURS"), Currency::new("LOCAL"));
+        let exchanger = DID::new("did:icn:exchange");
+        let mut rates = ExchangeRates::new();
+        assert!(rates.quote(&exchanger, &hours, &local).is_err());
+        assert!(rates.set_federation_rate(hours.clone(), hours.clone(), Decimal::ONE).is_err());
+        assert!(rates.set_federation_rate(hours.clone(), local.clone(), Decimal::ZERO).is_err());
+
+        rates.set_federation_rate(hours.clone(), local.clone(), Decimal::new(20, 0)).unwrap();
+        let inverse = rates.quote(&exchanger, &local, &hours).unwrap();
+        assert_eq!(inverse.convert(&Amount::in_currency(40, local.clone())).unwrap(), Amount::in_currency(2, hours.clone()));
+
+        rates.set_bilateral_rate(exchanger.clone(), hours.clone(), local.clone(), Decimal::new(19, 0)).unwrap();
+        let quote = rates.quote(&exchanger, &hours, &local).unwrap();
+        assert_eq!(quote.source, RateSource::Bilateral);
+        assert_eq!(quote.convert(&Amount::in_currency(2, hours.clone())).unwrap(), Amount::in_currency(38, local.clone()));
+        assert!(quote.convert(&Amount::in_currency(2, local.clone())).is_err());
+
+        // Other exchangers still get the federation rate
+        let other = rates.quote(&DID::new("did:icn:other"), &hours, &local).unwrap();
+        assert_eq!((other.rate, other.source), (Decimal::new(20, 0), RateSource::Federation));
+    }
+}
@@ -4,23 +4,32 @@
 //! including account management, credit lines, transactions, and credit graph.
 
 mod account;
+mod clearing;
+mod conditions;
 mod credit_graph;
 mod credit_line;
 mod error;
+mod exchange;
+mod lifecycle;
 mod transaction;
 mod transaction_processor;
 mod types;
 mod confidential;
 
 pub use account::{Account as AccountModule, AccountStatus};
+pub use conditions::{ConditionEngine, CustomCondition};
+pub use clearing::{optimal_clearing, ClearingAdjustment, ClearingOptions, ClearingReport, ReportSigner};
 pub use credit_graph::{CreditGraph, CreditLineId, CreditLineStep};
 pub use credit_line::{
-    CollateralRequirement, CollateralType, CreditCondition, CreditLine, CreditTerms, ResourceCommitment,
+    CollateralLock, CollateralRequirement, CollateralType, CreditCondition, CreditLine, CreditLineStatus,
+    CreditTerms, ResourceCommitment,
 };
 pub use error::{CreditError, Result};
+pub use exchange::{CurrencyExchange, ExchangeRate, ExchangeRates, RateSource};
+pub use lifecycle::{DemurrageConfig, LifecycleConfig, LifecycleService};
 pub use transaction::{Transaction as TransactionModule, TransactionStatus as TransactionStatusModule, TransactionType as TransactionTypeModule};
 pub use transaction_processor::{TransactionProcessor, TransactionResult, CreditClearingParams};
-pub use types::{Amount as AmountType, DID, Timestamp};
+pub use types::{Amount as AmountType, Currency, DEFAULT_CURRENCY, DID, Timestamp};
 pub use confidential::*;
 
 /// Version of the mutual credit implementation
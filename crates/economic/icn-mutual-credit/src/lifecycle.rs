@@ -0,0 +1,349 @@
+//! Scheduled lifecycle of credit lines.
+//!
+//! The `LifecycleService` periodically walks the credit graph and acts on
+//! credit terms:
+//!
+//! - Lines with `auto_renewal` expiring before the next run are renewed.
+//! - Other expired lines are settled in stages. On expiry the parties are
+//!   notified and the line enters its grace period, during which renewing it
+//!   makes it active again. After the grace period the line is frozen, and
+//!   after the freeze period its drawn balance becomes a clearing obligation
+//!   that global clearing settles. Lines with nothing drawn are closed.
+//! - With demurrage configured, positive balances idle for long enough pay a
+//!   fraction of the balance to a commons account each period, in every
+//!   currency the account holds.
+//!
+//! Every change is recorded in the graph as a completed system transaction
+//! whose `operation` metadata names the step, so the lifecycle is auditable.
+
+use crate::credit_graph::{CreditGraph, CreditLineId};
+use crate::credit_line::CreditLineStatus;
+use crate::error::CreditError;
+use crate::transaction::{Transaction, TransactionType};
+use crate::types::{Amount, Timestamp, DID};
+use rust_decimal::Decimal;
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Account metadata key holding when demurrage was last charged
+const DEMURRAGE_CHARGED_AT: &str = "demurrage_charged_at";
+
+/// Demurrage on idle positive balances
+#[derive(Debug, Clone)]
+pub struct DemurrageConfig {
+    /// Fraction of the balance charged each period
+    pub rate: Decimal,
+    /// Time between charges
+    pub period: Duration,
+    /// Time without activity after which a balance is idle
+    pub idle_after: Duration,
+    /// Account receiving the charges
+    pub commons: DID,
+}
+
+/// Configuration for the lifecycle service
+#[derive(Debug, Clone)]
+pub struct LifecycleConfig {
+    /// Time between runs
+    pub check_interval: Duration,
+    /// How long renewed lines are extended for
+    pub renewal_period: Duration,
+    /// Time after expiry during which a line can still be renewed
+    pub grace_period: Duration,
+    /// Time after the grace period before drawn credit becomes a clearing obligation
+    pub freeze_period: Duration,
+    /// Demurrage charged on idle positive balances, if any
+    pub demurrage: Option<DemurrageConfig>,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(60 * 60),
+            renewal_period: Duration::from_secs(365 * 24 * 60 * 60),
+            grace_period: Duration::from_secs(30 * 24 * 60 * 60),
+            freeze_period: Duration::from_secs(7 * 24 * 60 * 60),
+            demurrage: None,
+        }
+    }
+}
+
+/// Convert a configured duration to a chrono duration
+fn chrono_duration(duration: Duration) -> Result<chrono::Duration, CreditError> {
+    chrono::Duration::from_std(duration)
+        .map_err(|e| CreditError::Validation(format!("Invalid lifecycle duration: {}", e)))
+}
+
+/// Build a completed system transaction recording a lifecycle step
+fn lifecycle_transaction(
+    operation: &str,
+    from: &DID,
+    to: &DID,
+    amount: Amount,
+    description: String,
+) -> Transaction {
+    let mut transaction = Transaction::new(
+        format!("lifecycle-{}-{}", operation, uuid::Uuid::new_v4()),
+        from.clone(),
+        to.clone(),
+        amount,
+        TransactionType::SystemOperation,
+        Some(description),
+    );
+    transaction.add_metadata("operation".to_string(), JsonValue::String(operation.to_string()));
+    transaction.complete();
+    transaction
+}
+
+/// Renews, settles and charges demurrage on a credit graph
+#[derive(Debug)]
+pub struct LifecycleService {
+    /// The credit graph managed
+    credit_graph: Arc<Mutex<CreditGraph>>,
+    /// Service configuration
+    config: LifecycleConfig,
+}
+
+impl LifecycleService {
+    /// Create a lifecycle service for a credit graph
+    pub fn new(credit_graph: Arc<Mutex<CreditGraph>>, config: LifecycleConfig) -> Self {
+        Self { credit_graph, config }
+    }
+
+    /// Run the service every check interval until the task is aborted
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.check_interval);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run_once(chrono::Utc::now()).await {
+                    log::warn!("Credit line lifecycle run failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Apply every lifecycle step due at a time, returning the recorded transactions
+    pub async fn run_once(&self, now: Timestamp) -> Result<Vec<Transaction>, CreditError> {
+        let mut graph = self.credit_graph.lock().await;
+        let mut recorded = self.advance_credit_lines(&mut graph, now).await?;
+        if let Some(demurrage) = &self.config.demurrage {
+            recorded.extend(self.charge_demurrage(&mut graph, demurrage, now).await?);
+        }
+
+        for transaction in &recorded {
+            graph.record_transaction(transaction.clone()).await;
+        }
+        Ok(recorded)
+    }
+
+    /// Renew and settle credit lines
+    async fn advance_credit_lines(
+        &self,
+        graph: &mut CreditGraph,
+        now: Timestamp,
+    ) -> Result<Vec<Transaction>, CreditError> {
+        let renew_before = now + chrono_duration(self.config.check_interval)?;
+        let renewal = chrono_duration(self.config.renewal_period)?;
+        let grace = chrono_duration(self.config.grace_period)?;
+        let freeze = chrono_duration(self.config.freeze_period)?;
+
+        let mut ids: Vec<CreditLineId> = graph.get_all_credit_lines().await?
+            .into_iter()
+            .map(|line| CreditLineId::new(&line.from_account, &line.to_account))
+            .collect();
+        ids.sort_by(|a, b| (a.from.as_str(), a.to.as_str()).cmp(&(b.from.as_str(), b.to.as_str())));
+
+        let mut recorded = Vec::new();
+        for id in ids {
+            let Some(line) = graph.get_credit_line_mut(&id).await? else {
+                continue;
+            };
+            let drawn = if line.balance.is_negative() { line.balance.abs() } else { Amount::zero() };
+            let expiration = line.terms.expiration;
+
+            let step = match (line.status, expiration) {
+                (CreditLineStatus::Active, Some(expiration)) if line.terms.auto_renewal && expiration <= renew_before => {
+                    let renewed_until = std::cmp::max(expiration, now) + renewal;
+                    line.terms.expiration = Some(renewed_until);
+                    line.updated_at = now;
+                    Some(("renew_credit_line", format!("Credit line {} renewed until {}", id, renewed_until)))
+                },
+                (CreditLineStatus::Active, Some(expiration)) if expiration <= now => {
+                    line.set_status(CreditLineStatus::Expired);
+                    Some(("credit_line_expired", format!(
+                        "Credit line {} expired with {} drawn; renew it by {}", id, drawn, expiration + grace
+                    )))
+                },
+                (CreditLineStatus::Expired, Some(expiration)) if expiration > now => {
+                    line.set_status(CreditLineStatus::Active);
+                    Some(("credit_line_reactivated", format!("Credit line {} renewed during its grace period", id)))
+                },
+                (CreditLineStatus::Expired, Some(expiration)) if expiration + grace <= now => {
+                    if drawn.is_zero() {
+                        line.set_status(CreditLineStatus::Settled);
+                        Some(("settle_credit_line", format!("Credit line {} closed with nothing drawn", id)))
+                    } else {
+                        line.set_status(CreditLineStatus::Frozen);
+                        Some(("freeze_credit_line", format!("Credit line {} frozen with {} drawn", id, drawn)))
+                    }
+                },
+                (CreditLineStatus::Frozen, Some(expiration)) if expiration + grace + freeze <= now => {
+                    line.set_status(CreditLineStatus::ClearingObligation);
+                    Some(("convert_to_clearing_obligation", format!(
+                        "{} drawn on credit line {} is now a clearing obligation", drawn, id
+                    )))
+                },
+                (CreditLineStatus::ClearingObligation, _) if drawn.is_zero() => {
+                    line.set_status(CreditLineStatus::Settled);
+                    Some(("settle_credit_line", format!("Clearing obligation on credit line {} settled", id)))
+                },
+                _ => None,
+            };
+
+            if let Some((operation, description)) = step {
+                recorded.push(lifecycle_transaction(operation, &id.from, &id.to, drawn, description));
+            }
+        }
+
+        Ok(recorded)
+    }
+
+    /// Charge demurrage on idle positive balances
+    ///
+    /// Each positive balance of an idle account, in its home currency and in
+    /// foreign currencies, is charged separately, in its own currency.
+    async fn charge_demurrage(
+        &self,
+        graph: &mut CreditGraph,
+        demurrage: &DemurrageConfig,
+        now: Timestamp,
+    ) -> Result<Vec<Transaction>, CreditError> {
+        graph.verify_account_active(&demurrage.commons).await?;
+        let idle_after = chrono_duration(demurrage.idle_after)?;
+        let period = chrono_duration(demurrage.period)?;
+
+        let mut charges: Vec<(DID, Amount)> = graph.get_all_accounts().await?
+            .into_iter()
+            .filter(|account| account.is_active() && account.did != demurrage.commons)
+            .filter(|account| account.updated_at + idle_after <= now)
+            .filter(|account| {
+                account.metadata.get(DEMURRAGE_CHARGED_AT)
+                    .and_then(|charged_at| chrono::DateTime::parse_from_rfc3339(charged_at).ok())
+                    .is_none_or(|charged_at| charged_at + period <= now)
+            })
+            .flat_map(|account| {
+                std::iter::once(&account.balance)
+                    .chain(account.foreign_balances.values())
+                    .map(|balance| (account.did.clone(), balance.scale(demurrage.rate)))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|(_, charge)| charge.is_positive())
+            .collect();
+        charges.sort_by(|a, b| {
+            (a.0.as_str(), a.1.currency().as_str()).cmp(&(b.0.as_str(), b.1.currency().as_str()))
+        });
+
+        let mut recorded = Vec::new();
+        for (did, charge) in charges {
+            // Set the balance directly so the charge doesn't count as activity
+            if let Some(account) = graph.get_account_mut(&did).await? {
+                if charge.currency() == &account.currency {
+                    account.balance = account.balance.checked_sub(&charge)?;
+                } else if let Some(balance) = account.foreign_balances.get_mut(charge.currency()) {
+                    *balance = balance.checked_sub(&charge)?;
+                }
+                account.metadata.insert(DEMURRAGE_CHARGED_AT.to_string(), now.to_rfc3339());
+            }
+            if let Some(commons) = graph.get_account_mut(&demurrage.commons).await? {
+                commons.update_balance(charge.clone());
+            }
+
+            recorded.push(lifecycle_transaction(
+                "demurrage",
+                &did,
+                &demurrage.commons,
+                charge.clone(),
+                format!("Demurrage of {} on the idle balance of {}", charge, did),
+            ));
+        }
+
+        Ok(recorded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+    use crate::credit_line::{CreditLine, CreditTerms};
+    use crate::types::Currency;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn operations(recorded: &[Transaction]) -> Vec<&str> {
+        recorded.iter().map(|tx| tx.metadata["operation"].as_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_expired_lines_are_renewed_or_settled_in_stages() {
+        let mut graph = CreditGraph::new();
+        for name in ["a", "b", "c", "commons"] {
+            graph.add_account(Account::new(DID::new(name), name.to_string())).await.unwrap();
+        }
+        let start = chrono::Utc::now();
+        let mut renewing = CreditLine::new(DID::new("a"), DID::new("b"), Amount::new(100), CreditTerms::with_expiration(start));
+        renewing.terms.auto_renewal = true;
+        let mut expiring = CreditLine::new(DID::new("b"), DID::new("c"), Amount::new(100), CreditTerms::with_expiration(start));
+        expiring.balance = Amount::new(-40);
+        graph.add_credit_line(renewing).await.unwrap();
+        graph.add_credit_line(expiring).await.unwrap();
+        let hours = Currency::new("HOURS");
+        let c = graph.get_account_mut(&DID::new("c")).await.unwrap().unwrap();
+        c.balance = Amount::new(1000);
+        c.foreign_balances.insert(hours.clone(), Amount::in_currency(500, hours.clone()));
+
+        let graph = Arc::new(Mutex::new(graph));
+        let config = LifecycleConfig {
+            demurrage: Some(DemurrageConfig {
+                rate: Decimal::new(1, 2),
+                period: 30 * DAY,
+                idle_after: 10 * DAY,
+                commons: DID::new("commons"),
+            }),
+            ..LifecycleConfig::default()
+        };
+        let service = LifecycleService::new(Arc::clone(&graph), config);
+        let at = |days: u32| start + chrono::Duration::days(days.into());
+
+        let recorded = service.run_once(at(0)).await.unwrap();
+        assert_eq!(operations(&recorded), ["renew_credit_line", "credit_line_expired"]);
+        assert!(recorded.iter().all(|tx| tx.is_completed() && tx.transaction_type == TransactionType::SystemOperation));
+        assert!(service.run_once(at(1)).await.unwrap().is_empty());
+
+        // Past the grace period the line freezes, and after the freeze period it awaits clearing
+        assert_eq!(operations(&service.run_once(at(31)).await.unwrap()), ["freeze_credit_line", "demurrage", "demurrage"]);
+        assert!(service.run_once(at(32)).await.unwrap().is_empty());
+        let recorded = service.run_once(at(38)).await.unwrap();
+        assert_eq!(operations(&recorded), ["convert_to_clearing_obligation"]);
+        assert_eq!(recorded[0].amount, Amount::new(40));
+
+        let mut graph_lock = graph.lock().await;
+        let id = CreditLineId::new(&DID::new("b"), &DID::new("c"));
+        assert_eq!(graph_lock.get_credit_line(&id).await.unwrap().unwrap().status, CreditLineStatus::ClearingObligation);
+        assert_eq!(graph_lock.get_account_balance(&DID::new("c")).await.unwrap(), Amount::new(990));
+        assert_eq!(graph_lock.get_account_balance(&DID::new("commons")).await.unwrap(), Amount::new(10));
+        let c = graph_lock.get_account(&DID::new("c")).await.unwrap().unwrap();
+        assert_eq!(c.balance_in(&hours), Amount::in_currency(495, hours.clone()));
+        let commons = graph_lock.get_account(&DID::new("commons")).await.unwrap().unwrap();
+        assert_eq!(commons.balance_in(&hours), Amount::in_currency(5, hours.clone()));
+        assert_eq!(graph_lock.get_transaction_history(&DID::new("c")).await.unwrap().len(), 5);
+
+        // Once cleared, the obligation is settled
+        graph_lock.get_credit_line_mut(&id).await.unwrap().unwrap().balance = Amount::zero();
+        drop(graph_lock);
+        assert_eq!(operations(&service.run_once(at(39)).await.unwrap()), ["settle_credit_line"]);
+    }
+}
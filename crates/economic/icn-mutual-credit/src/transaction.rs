@@ -1,6 +1,7 @@
 //! Transaction management for the mutual credit system.
 
-use crate::types::{Amount, DID, Timestamp};
+use crate::exchange::CurrencyExchange;
+use crate::types::{Amount, Currency, DID, Timestamp};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
@@ -74,6 +75,8 @@ pub enum TransactionType {
     CreditLineAdjustment,
     /// System operation
     SystemOperation,
+    /// Transfer converted between currencies by an exchanger
+    CurrencyExchange,
 }
 
 /// A transaction in the mutual credit system
@@ -101,6 +104,9 @@ pub struct Transaction {
     pub path: Option<Vec<DID>>,
     /// Metadata for the transaction
     pub metadata: HashMap<String, JsonValue>,
+    /// Exchange into the receiver's currency (for currency exchanges)
+    #[serde(default)]
+    pub exchange: Option<CurrencyExchange>,
 }
 
 impl Transaction {
@@ -126,6 +132,7 @@ impl Transaction {
             updated_at: now,
             path: None,
             metadata: HashMap::new(),
+            exchange: None,
         }
     }
 
@@ -135,6 +142,12 @@ impl Transaction {
         self
     }
 
+    /// Set the exchanger and the currency to credit the receiver in for a currency exchange
+    pub fn with_exchange(mut self, exchanger: DID, currency: Currency) -> Self {
+        self.exchange = Some(CurrencyExchange::new(exchanger, currency));
+        self
+    }
+
     /// Add metadata to the transaction
     pub fn add_metadata(&mut self, key: String, value: JsonValue) {
         self.metadata.insert(key, value);
@@ -6,16 +6,18 @@
 //! the settlement of transactions across the network.
 
 use crate::account::{Account, AccountStatus};
+use crate::conditions::ConditionEngine;
+use crate::clearing::{optimal_clearing, ClearingAdjustment, ClearingOptions, ClearingReport, ReportSigner};
 use crate::credit_graph::{CreditGraph, CreditLineId, CreditLineStep};
-use crate::credit_line::CreditLine;
+use crate::credit_line::{CollateralLock, CollateralType, CreditLine, CreditLineStatus};
 use crate::error::CreditError;
+use crate::exchange::ExchangeRates;
 use crate::transaction::{Transaction, TransactionStatus, TransactionType, TransactionId};
 use crate::types::{Amount, DID, Timestamp};
 use async_trait::async_trait;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use rust_decimal::prelude::ToPrimitive;
 use crate::confidential::{
     ConfidentialTransaction, ConfidentialTransactionProcessor, ConfidentialBalance,
     BlindingFactor, ConfidentialError,
@@ -24,7 +26,7 @@ use crate::confidential::{
 /// Parameters for the credit clearing algorithm
 #[derive(Debug, Clone)]
 pub struct CreditClearingParams {
-    /// Minimum amount to consider for clearing
+    /// Minimum amount to consider for clearing, applied to the value in any currency
     pub min_clearing_amount: Amount,
     /// Maximum number of hops in a clearing path
     pub max_path_length: usize,
@@ -68,11 +70,15 @@ pub struct TransactionProcessor {
     transaction_history: Vec<TransactionResult>,
     /// Confidential transaction processor
     confidential_processor: ConfidentialTransactionProcessor,
-    /// Mapping of confidential transactions to their blinding factors
+    /// Evaluates credit line conditions and keeps collateral
+    conditions: ConditionEngine,
+    /// Rates for currency exchanges
+    exchange_rates: ExchangeRates,
+    /// Confidential transactions with their amounts and blinding factors, by ID
     /// In a real implementation, this would NOT be stored here, but would be 
     /// securely managed by the sender and recipient
     #[cfg(test)]
-    confidential_blinding_factors: HashMap<String, BlindingFactor>,
+    confidential_secrets: HashMap<String, (ConfidentialTransaction, Amount, BlindingFactor)>,
 }
 
 impl TransactionProcessor {
@@ -84,8 +90,10 @@ impl TransactionProcessor {
             pending_transactions: VecDeque::new(),
             transaction_history: Vec::new(),
             confidential_processor: ConfidentialTransactionProcessor::new(),
+            conditions: ConditionEngine::new(),
+            exchange_rates: ExchangeRates::new(),
             #[cfg(test)]
-            confidential_blinding_factors: HashMap::new(),
+            confidential_secrets: HashMap::new(),
         }
     }
 
@@ -104,7 +112,14 @@ impl TransactionProcessor {
     async fn verify_transaction(&self, transaction: &Transaction) -> Result<(), CreditError> {
         // Lock the credit graph to perform validations
         let graph = self.credit_graph.lock().await;
-        
+        self.verify_transaction_in(&graph, transaction).await
+    }
+
+    /// Verify that a transaction is valid against a locked credit graph
+    ///
+    /// Processing verifies under the same lock it applies the transaction
+    /// with, so nothing can change between the checks and the transfer.
+    async fn verify_transaction_in(&self, graph: &CreditGraph, transaction: &Transaction) -> Result<(), CreditError> {
         // Verify accounts exist and are active
         graph.verify_account_active(&transaction.from).await?;
         graph.verify_account_active(&transaction.to).await?;
@@ -138,12 +153,15 @@ impl TransactionProcessor {
                         )));
                     }
                     
+                    credit_line.check_currency(&transaction.amount)?;
                     if credit_line.would_exceed_limit(&transaction.amount) {
                         return Err(CreditError::CreditLimitExceeded(format!(
                             "Transaction would exceed credit limit of {}",
                             credit_line.limit
                         )));
                     }
+
+                    self.conditions.check_draw(graph, &credit_line_id, &transaction.amount).await?;
                 } else {
                     return Err(CreditError::CreditLineNotFound(format!(
                         "Credit line from {} to {} not found",
@@ -152,32 +170,32 @@ impl TransactionProcessor {
                 }
             },
             TransactionType::PathTransfer => {
-                // Verify that a valid path exists
-                if transaction.path.is_none() || transaction.path.as_ref().unwrap().is_empty() {
-                    return Err(CreditError::InvalidTransaction(
-                        "Path transfer requires a non-empty path".to_string()
-                    ));
-                }
-                
-                // In a full implementation, we would validate each step of the path
-                // For now, we'll just ensure the path starts with the sender and ends with the receiver
-                let path = transaction.path.as_ref().unwrap();
-                if path.first().map(|d| d != &transaction.from).unwrap_or(true) 
-                   || path.last().map(|d| d != &transaction.to).unwrap_or(true) {
-                    return Err(CreditError::InvalidTransaction(
-                        "Path must start with sender and end with receiver".to_string()
-                    ));
+                // Without a path the transfer is routed when it's processed
+                if let Some(path) = &transaction.path {
+                    if path.first() != Some(&transaction.from) || path.last() != Some(&transaction.to) {
+                        return Err(CreditError::InvalidTransaction(
+                            "Path must start with sender and end with receiver".to_string()
+                        ));
+                    }
+
+                    if path.len() > self.clearing_params.max_path_length + 1 {
+                        return Err(CreditError::InvalidTransaction(format!(
+                            "Path has {} hops, more than the maximum of {}",
+                            path.len().saturating_sub(1),
+                            self.clearing_params.max_path_length
+                        )));
+                    }
                 }
             },
             TransactionType::CreditLineAdjustment => {
                 // Verify that the credit line exists
                 let credit_line_id = CreditLineId::new(&transaction.from, &transaction.to);
-                if graph.get_credit_line(&credit_line_id).await?.is_none() {
-                    return Err(CreditError::CreditLineNotFound(format!(
+                graph.get_credit_line(&credit_line_id).await?
+                    .ok_or_else(|| CreditError::CreditLineNotFound(format!(
                         "Credit line from {} to {} not found",
                         transaction.from, transaction.to
-                    )));
-                }
+                    )))?
+                    .check_currency(&transaction.amount)?;
                 
                 // Additional verification for credit line adjustments would go here
             },
@@ -185,6 +203,19 @@ impl TransactionProcessor {
                 // System operations might have special authorization requirements
                 // For now, we'll accept all system operations
             },
+            TransactionType::CurrencyExchange => {
+                let exchange = transaction.exchange.as_ref()
+                    .ok_or_else(|| CreditError::InvalidTransaction(
+                        "Currency exchange requires an exchanger and a currency".to_string()
+                    ))?;
+                if exchange.exchanger == transaction.from || exchange.exchanger == transaction.to {
+                    return Err(CreditError::InvalidTransaction(
+                        "Exchanger must differ from the sender and receiver".to_string()
+                    ));
+                }
+                graph.verify_account_active(&exchange.exchanger).await?;
+                self.exchange_rates.quote(&exchange.exchanger, transaction.amount.currency(), &exchange.currency)?;
+            },
         }
         
         Ok(())
@@ -223,11 +254,11 @@ impl TransactionProcessor {
 
     /// Process a single transaction
     async fn process_transaction(&mut self, transaction: &mut Transaction) -> Result<TransactionResult, CreditError> {
-        // Verify the transaction is still valid
-        self.verify_transaction(transaction).await?;
-        
         // Lock the credit graph for updating
         let mut graph = self.credit_graph.lock().await;
+
+        // Verify the transaction is still valid, including its credit line's conditions
+        self.verify_transaction_in(&graph, transaction).await?;
         
         let mut updated_balances = HashMap::new();
         
@@ -245,14 +276,15 @@ impl TransactionProcessor {
                 credit_line.update_balance(-transaction.amount.clone())?;
                 
                 // Update account balances
+                let currency = transaction.amount.currency();
                 if let Some(from_account) = graph.get_account_mut(&transaction.from).await? {
                     from_account.update_balance(-transaction.amount.clone());
-                    updated_balances.insert(transaction.from.clone(), from_account.balance.clone());
+                    updated_balances.insert(transaction.from.clone(), from_account.balance_in(currency));
                 }
                 
                 if let Some(to_account) = graph.get_account_mut(&transaction.to).await? {
                     to_account.update_balance(transaction.amount.clone());
-                    updated_balances.insert(transaction.to.clone(), to_account.balance.clone());
+                    updated_balances.insert(transaction.to.clone(), to_account.balance_in(currency));
                 }
             },
             TransactionType::PathTransfer => {
@@ -267,6 +299,10 @@ impl TransactionProcessor {
                 // Process a system operation
                 self.process_system_operation(transaction, &mut graph, &mut updated_balances).await?;
             },
+            TransactionType::CurrencyExchange => {
+                // Process a transfer between currencies
+                self.process_currency_exchange(transaction, &mut graph, &mut updated_balances).await?;
+            },
         }
         
         // Mark transaction as completed
@@ -281,65 +317,104 @@ impl TransactionProcessor {
     }
 
     /// Process a path transfer transaction
+    ///
+    /// A transfer with a path moves the whole amount along it. One without a
+    /// path is split across the routes `find_routes` would return, and the
+    /// routes taken are recorded in the transaction's `routes` metadata.
+    /// Either every hop is applied or none is.
     async fn process_path_transfer(
         &self,
-        transaction: &Transaction,
+        transaction: &mut Transaction,
         graph: &mut CreditGraph,
         updated_balances: &mut HashMap<DID, Amount>,
     ) -> Result<(), CreditError> {
-        // Get the path from the transaction
-        let path = transaction.path.as_ref()
-            .ok_or_else(|| CreditError::InvalidTransaction("Path transfer requires a path".to_string()))?;
-        
-        if path.len() < 2 {
-            return Err(CreditError::InvalidTransaction("Path must have at least two accounts".to_string()));
+        let routes = match &transaction.path {
+            Some(path) => vec![graph.route_along(path, &transaction.amount)?],
+            None => graph.find_split_routes(
+                &transaction.from,
+                &transaction.to,
+                &transaction.amount,
+                self.clearing_params.max_path_length,
+            ).await?,
+        };
+
+        for step in routes.iter().flatten() {
+            self.conditions.check_draw(graph, &step.credit_line_id, &step.amount).await?;
         }
-        
-        // Process each step in the path
-        for i in 0..path.len() - 1 {
-            let from = &path[i];
-            let to = &path[i + 1];
-            let credit_line_id = CreditLineId::new(from, to);
-            
-            // Get and update the credit line
-            let credit_line = graph.get_credit_line_mut(&credit_line_id).await?
-                .ok_or_else(|| CreditError::CreditLineNotFound(format!(
-                    "Credit line from {} to {} not found in path transfer",
-                    from, to
-                )))?;
-            
-            // Check if the credit line is active and has enough credit
-            if !credit_line.is_active() {
-                return Err(CreditError::InactiveCredit(format!(
-                    "Credit line from {} to {} is inactive", from, to
-                )));
-            }
-            
-            if credit_line.would_exceed_limit(&transaction.amount) {
-                return Err(CreditError::CreditLimitExceeded(format!(
-                    "Transaction would exceed credit limit of {} for step {} to {}",
-                    credit_line.limit, from, to
-                )));
-            }
-            
-            // Update the credit line balance
-            credit_line.update_balance(-transaction.amount.clone())?;
-            
-            // Update account balances
-            if let Some(from_account) = graph.get_account_mut(from).await? {
-                from_account.update_balance(-transaction.amount.clone());
-                updated_balances.insert(from.clone(), from_account.balance.clone());
-            }
-            
-            if let Some(to_account) = graph.get_account_mut(to).await? {
-                to_account.update_balance(transaction.amount.clone());
-                updated_balances.insert(to.clone(), to_account.balance.clone());
-            }
+
+        updated_balances.extend(graph.apply_routes(&routes).await?);
+
+        if transaction.path.is_none() {
+            let taken: Vec<serde_json::Value> = routes.iter()
+                .map(|steps| {
+                    let mut path: Vec<&str> = steps.iter().map(|step| step.from.as_str()).collect();
+                    path.push(transaction.to.as_str());
+                    serde_json::json!({
+                        "path": path,
+                        "amount": steps[0].amount.to_string(),
+                    })
+                })
+                .collect();
+            transaction.add_metadata("routes".to_string(), serde_json::Value::Array(taken));
         }
-        
+
         Ok(())
     }
 
+    /// Process a currency exchange transaction
+    ///
+    /// The sender pays the exchanger the amount over their credit line in
+    /// the amount's currency, and the exchanger pays the receiver the amount
+    /// converted at its quoted rate over their line in the receiver's
+    /// currency. Both are applied or neither is, and the rate and amount
+    /// received are recorded on the transaction's exchange.
+    async fn process_currency_exchange(
+        &self,
+        transaction: &mut Transaction,
+        graph: &mut CreditGraph,
+        updated_balances: &mut HashMap<DID, Amount>,
+    ) -> Result<(), CreditError> {
+        let exchange = transaction.exchange.as_mut()
+            .ok_or_else(|| CreditError::InvalidTransaction(
+                "Currency exchange requires an exchanger and a currency".to_string()
+            ))?;
+        let rate = self.exchange_rates.quote(&exchange.exchanger, transaction.amount.currency(), &exchange.currency)?;
+        let received = rate.convert(&transaction.amount)?;
+
+        let hop = |from: &DID, to: &DID, amount: &Amount| CreditLineStep {
+            from: from.clone(),
+            to: to.clone(),
+            amount: amount.clone(),
+            credit_line_id: CreditLineId::new(from, to),
+        };
+        let steps = vec![
+            hop(&transaction.from, &exchange.exchanger, &transaction.amount),
+            hop(&exchange.exchanger, &transaction.to, &received),
+        ];
+        for step in &steps {
+            self.conditions.check_draw(graph, &step.credit_line_id, &step.amount).await?;
+        }
+
+        updated_balances.extend(graph.apply_routes(&[steps]).await?);
+        exchange.rate = Some(rate);
+        exchange.received = Some(received);
+
+        Ok(())
+    }
+
+    /// Find routes within the maximum path length that together carry a payment
+    ///
+    /// See `CreditGraph::find_split_routes` for how routes are chosen.
+    pub async fn find_routes(
+        &self,
+        from: &DID,
+        to: &DID,
+        amount: &Amount,
+    ) -> Result<Vec<Vec<CreditLineStep>>, CreditError> {
+        let graph = self.credit_graph.lock().await;
+        graph.find_split_routes(from, to, amount, self.clearing_params.max_path_length).await
+    }
+
     /// Process a credit line adjustment transaction
     async fn process_credit_line_adjustment(
         &self,
@@ -356,7 +431,7 @@ impl TransactionProcessor {
             )))?;
         
         // Update the credit limit
-        credit_line.update_limit(transaction.amount.clone());
+        credit_line.update_limit(transaction.amount.clone())?;
         
         // No account balance updates for credit line adjustments
         
@@ -436,15 +511,6 @@ impl TransactionProcessor {
         // This is a simplified version of the credit clearing algorithm
         // A full implementation would use more sophisticated graph algorithms
         
-        // Map accounts to their outstanding balances
-        let mut account_balances = HashMap::new();
-        
-        // Get all accounts and their balances
-        let accounts = graph.get_all_accounts().await?;
-        for account in &accounts {
-            account_balances.insert(account.did.clone(), account.balance.clone());
-        }
-        
         // Find cycles in the credit graph
         let cycles = self.find_credit_cycles(&graph).await?;
         
@@ -460,7 +526,7 @@ impl TransactionProcessor {
             // Find the minimum balance in the cycle
             let min_amount = self.find_minimum_credit_in_cycle(&cycle, &graph).await?;
             
-            if min_amount < self.clearing_params.min_clearing_amount {
+            if min_amount.value() < self.clearing_params.min_clearing_amount.value() {
                 // Skip cycles with small amounts
                 continue;
             }
@@ -483,25 +549,127 @@ impl TransactionProcessor {
                 clearing_transactions.push(transaction);
                 
                 // Update account balances
-                if let Some(balance) = account_balances.get_mut(&from) {
-                    *balance = balance.clone() - min_amount.clone();
+                if let Some(account) = graph.get_account_mut(&from).await? {
+                    account.update_balance(-min_amount.clone());
                 }
                 
-                if let Some(balance) = account_balances.get_mut(&to) {
-                    *balance = balance.clone() + min_amount.clone();
+                if let Some(account) = graph.get_account_mut(&to).await? {
+                    account.update_balance(min_amount.clone());
                 }
             }
         }
         
-        // Apply the new balances to accounts
-        for (did, balance) in account_balances {
-            if let Some(account) = graph.get_account_mut(&did).await? {
-                account.balance = balance;
-                account.updated_at = chrono::Utc::now();
+        Ok(clearing_transactions)
+    }
+
+    /// Get the engine evaluating credit line conditions
+    pub fn conditions(&self) -> &ConditionEngine {
+        &self.conditions
+    }
+
+    /// Get the engine evaluating credit line conditions, to approve lines or register custom conditions
+    pub fn conditions_mut(&mut self) -> &mut ConditionEngine {
+        &mut self.conditions
+    }
+
+    /// Get the rates currency exchanges are made at
+    pub fn exchange_rates(&self) -> &ExchangeRates {
+        &self.exchange_rates
+    }
+
+    /// Get the rates currency exchanges are made at, to define federation or bilateral rates
+    pub fn exchange_rates_mut(&mut self) -> &mut ExchangeRates {
+        &mut self.exchange_rates
+    }
+
+    /// Lock collateral for a credit line
+    pub async fn lock_collateral(
+        &mut self,
+        line: &CreditLineId,
+        collateral_type: CollateralType,
+        amount: Amount,
+    ) -> Result<(), CreditError> {
+        let mut graph = self.credit_graph.lock().await;
+        self.conditions.lock_collateral(&mut graph, line, collateral_type, amount).await
+    }
+
+    /// Release the collateral locked for a credit line once nothing is drawn on it
+    pub async fn release_collateral(&mut self, line: &CreditLineId) -> Result<Vec<CollateralLock>, CreditError> {
+        let mut graph = self.credit_graph.lock().await;
+        self.conditions.release_collateral(&mut graph, line).await
+    }
+
+    /// Clear as much drawn credit as possible across the whole credit graph
+    ///
+    /// Only credit lines in the options' currency between active accounts
+    /// that opted in are cleared, if active or awaiting clearing as a
+    /// clearing obligation, each by at most its drawn credit and any limit
+    /// set in the options. No account's net position changes. If less than the minimum
+    /// clearing amount would be cleared in total, nothing is. The report
+    /// lists every adjustment and is signed by the signer; with `dry_run`
+    /// set the adjustments are reported but not applied.
+    pub async fn run_global_clearing(
+        &mut self,
+        options: &ClearingOptions,
+        signer: &dyn ReportSigner,
+    ) -> Result<ClearingReport, CreditError> {
+        let mut graph = self.credit_graph.lock().await;
+
+        let opted_in: HashSet<DID> = graph.get_all_accounts().await?
+            .into_iter()
+            .filter(|account| account.is_active() && options.participants.contains(&account.did))
+            .map(|account| account.did.clone())
+            .collect();
+        let mut clearable: Vec<(CreditLineId, Amount)> = graph.get_all_credit_lines().await?
+            .into_iter()
+            .filter(|line| line.is_active() || line.status == CreditLineStatus::ClearingObligation)
+            .filter(|line| line.currency() == &options.currency && line.balance.is_negative())
+            .filter(|line| opted_in.contains(&line.from_account) && opted_in.contains(&line.to_account))
+            .map(|line| {
+                let id = CreditLineId::new(&line.from_account, &line.to_account);
+                let drawn = line.balance.abs();
+                let limit = match options.line_limits.get(&id) {
+                    Some(limit) => drawn.checked_min(limit)?,
+                    None => drawn,
+                };
+                Ok((id, limit))
+            })
+            .collect::<Result<_, CreditError>>()?;
+        clearable.retain(|(_, limit)| limit.is_positive());
+        clearable.sort_by(|a, b| (a.0.from.as_str(), a.0.to.as_str()).cmp(&(b.0.from.as_str(), b.0.to.as_str())));
+
+        let mut cleared = optimal_clearing(&clearable)?;
+        let total = cleared.iter().try_fold(Amount::zero(), |total, (_, amount)| total.checked_add(amount))?;
+        if total.value() < self.clearing_params.min_clearing_amount.value() {
+            cleared.clear();
+        }
+
+        let mut adjustments = Vec::with_capacity(cleared.len());
+        for (id, amount) in cleared {
+            let line = graph.get_credit_line(&id).await?
+                .ok_or_else(|| CreditError::CreditLineNotFound(id.to_string()))?;
+            adjustments.push(ClearingAdjustment {
+                from: id.from.clone(),
+                to: id.to.clone(),
+                balance_before: line.balance.clone(),
+                balance_after: line.balance.checked_add(&amount)?,
+                amount,
+            });
+        }
+
+        // Clearing only moves drawn balances towards zero, so no line can exceed its limit
+        if !options.dry_run {
+            for adjustment in &adjustments {
+                let id = CreditLineId::new(&adjustment.from, &adjustment.to);
+                if let Some(line) = graph.get_credit_line_mut(&id).await? {
+                    line.update_balance(adjustment.amount.clone())?;
+                }
             }
         }
-        
-        Ok(clearing_transactions)
+
+        let mut participants: Vec<DID> = options.participants.iter().cloned().collect();
+        participants.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        ClearingReport::sign(options.dry_run, participants, adjustments, signer)
     }
 
     /// Find cycles in the credit graph
@@ -596,12 +764,14 @@ impl TransactionProcessor {
     }
 
     /// Find the minimum credit available in a cycle
+    ///
+    /// A cycle through lines in different currencies can't be cleared, so has none.
     async fn find_minimum_credit_in_cycle(
         &self,
         cycle: &[DID],
         graph: &CreditGraph,
     ) -> Result<Amount, CreditError> {
-        let mut min_amount = Amount::new(i64::MAX);
+        let mut min_amount: Option<Amount> = None;
         
         for i in 0..cycle.len() {
             let from = &cycle[i];
@@ -612,8 +782,12 @@ impl TransactionProcessor {
             if let Some(credit_line) = graph.get_credit_line(&credit_line_id).await? {
                 if credit_line.is_active() {
                     let available_credit = credit_line.available_credit();
-                    if available_credit < min_amount {
-                        min_amount = available_credit;
+                    match &min_amount {
+                        Some(min) if min.currency() != available_credit.currency() => {
+                            return Ok(Amount::zero());
+                        },
+                        Some(min) if *min <= available_credit => {},
+                        _ => min_amount = Some(available_credit),
                     }
                 }
             } else {
@@ -624,7 +798,7 @@ impl TransactionProcessor {
             }
         }
         
-        Ok(min_amount)
+        Ok(min_amount.unwrap_or_else(|| Amount::new(i64::MAX)))
     }
 
     /// Get transaction history
@@ -660,22 +834,28 @@ impl TransactionProcessor {
         let (conf_tx, blinding_factor) = self.confidential_processor.create_transaction(
             from,
             to,
-            amount,
+            amount.clone(),
             description,
         ).map_err(|e| CreditError::from(e))?;
         
-        // In a real implementation, the blinding factor would be securely shared with the recipient
-        // via an encrypted channel. For simplicity in this prototype, we store it internally.
+        // The local ledger settles the amount in plaintext to enforce credit
+        // limits; only the confidential transaction, whose base carries no
+        // amount, is shared with third parties
+        let mut settlement = conf_tx.base.clone();
+        settlement.amount = amount.clone();
+        
+        // In a real implementation, the amount and blinding factor would be securely shared with
+        // the recipient via an encrypted channel. For simplicity in this prototype, we store them internally.
         #[cfg(test)]
         {
-            self.confidential_blinding_factors.insert(
+            self.confidential_secrets.insert(
                 conf_tx.base.id.clone(),
-                blinding_factor,
+                (conf_tx.clone(), amount, blinding_factor),
             );
         }
         
-        // Submit the base transaction for processing
-        self.submit_transaction(conf_tx.base.clone()).await?;
+        // Submit the settlement for processing
+        self.submit_transaction(settlement).await?;
         
         // Store the confidential transaction data along with the base transaction
         // This would typically involve storing the commitment and range proof separately
@@ -702,49 +882,14 @@ impl TransactionProcessor {
         // In a real implementation, this would involve secure key exchange
         // between the sender and recipient
         
-        // For this prototype, we just retrieve the stored blinding factor
-        let blinding_factor = self.confidential_blinding_factors.get(transaction_id)
+        // For this prototype, we just retrieve the stored amount and blinding factor
+        let (conf_tx, amount, blinding_factor) = self.confidential_secrets.get(transaction_id)
             .ok_or_else(|| CreditError::InvalidTransaction(
                 format!("No blinding factor found for transaction {}", transaction_id)
             ))?;
         
-        // Create a dummy confidential transaction for demonstration
-        // In a real implementation, we would retrieve the actual confidential transaction
-        let transaction = self.transaction_history.iter()
-            .find(|result| result.transaction.id == transaction_id)
-            .ok_or_else(|| CreditError::InvalidTransaction(
-                format!("Transaction {} not found", transaction_id)
-            ))?;
-        
-        // Get amount as i64 for cryptographic operations
-        let amount_i64 = transaction.transaction.amount.value()
-            .to_i64()
-            .ok_or_else(|| CreditError::Other(
-                "Amount cannot be converted to i64".to_string()
-            ))?;
-        
-        // Create a placeholder confidential transaction
-        // In a real implementation, we would retrieve the stored commitment and range proof
-        let dummy_commitment = self.confidential_processor.pedersen_generator.create_commitment(
-            amount_i64,
-            blinding_factor,
-        ).map_err(|e| CreditError::from(e))?;
-        
-        let dummy_range_proof = self.confidential_processor.range_proof_system.create_range_proof(
-            amount_i64,
-            0,
-            i64::MAX,
-            blinding_factor,
-        ).map_err(|e| CreditError::from(e))?;
-        
-        let conf_tx = ConfidentialTransaction::new(
-            transaction.transaction.clone(),
-            dummy_commitment,
-            dummy_range_proof,
-        );
-        
         // Reveal the amount
-        self.confidential_processor.reveal_amount(&conf_tx, blinding_factor)
+        self.confidential_processor.reveal_amount(conf_tx, amount, blinding_factor)
             .map_err(|e| CreditError::from(e))
     }
 }
@@ -752,6 +897,8 @@ impl TransactionProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::Currency;
+    use rust_decimal::Decimal;
     use tokio_test::block_on;
     
     fn create_test_environment() -> (Arc<Mutex<CreditGraph>>, TransactionProcessor) {
@@ -886,4 +1033,155 @@ mod tests {
             assert_eq!(credit_line.balance, Amount::new(-50));
         });
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_path_transfer_is_routed_through_intermediaries() {
+        block_on(async {
+            let (graph, mut processor) = create_test_environment();
+
+            let mut graph_lock = graph.lock().await;
+            for name in ["from", "hub", "to"] {
+                graph_lock.add_account(Account::new(DID::new(name), name.to_string())).await.unwrap();
+            }
+            for (from, to) in [("from", "hub"), ("hub", "to")] {
+                let credit_line = CreditLine::new(DID::new(from), DID::new(to), Amount::new(100), Default::default());
+                graph_lock.add_credit_line(credit_line).await.unwrap();
+            }
+            drop(graph_lock);
+
+            let routes = processor.find_routes(&DID::new("from"), &DID::new("to"), &Amount::new(40)).await.unwrap();
+            assert_eq!(routes.len(), 1);
+            assert_eq!(routes[0].len(), 2);
+
+            // Routed transfers record the routes they took; a transfer that
+            // would overdraw a hop is rejected without touching any balance
+            for (id, amount) in [("routed", 40), ("too-large", 70)] {
+                let transaction = Transaction::new(
+                    id.to_string(),
+                    DID::new("from"),
+                    DID::new("to"),
+                    Amount::new(amount),
+                    TransactionType::PathTransfer,
+                    None,
+                );
+                processor.submit_transaction(transaction).await.unwrap();
+            }
+            let results = processor.process_pending_transactions().await;
+            let routed = results[0].as_ref().unwrap();
+            assert_eq!(routed.updated_balances[&DID::new("hub")], Amount::zero());
+            assert_eq!(routed.transaction.metadata["routes"][0]["path"], serde_json::json!(["from", "hub", "to"]));
+            assert!(matches!(results[1], Err(CreditError::NoPathFound(_))));
+
+            let graph_lock = graph.lock().await;
+            assert_eq!(graph_lock.get_account_balance(&DID::new("from")).await.unwrap(), Amount::new(-40));
+            assert_eq!(graph_lock.get_account_balance(&DID::new("to")).await.unwrap(), Amount::new(40));
+        });
+    }
+
+    #[test]
+    fn test_currency_exchange_converts_atomically_and_records_the_rate() {
+        block_on(async {
+            let (graph, mut processor) = create_test_environment();
+            let (hours, local) = (Currency::new("HOURS"), Currency::new("LOCAL"));
+
+            let mut graph_lock = graph.lock().await;
+            graph_lock.add_account(Account::with_currency(DID::new("alice"), "Alice".to_string(), hours.clone())).await.unwrap();
+            graph_lock.add_account(Account::with_currency(DID::new("exchange"), "Exchange".to_string(), hours.clone())).await.unwrap();
+            graph_lock.add_account(Account::with_currency(DID::new("bob"), "Bob".to_string(), local.clone())).await.unwrap();
+            for (from, to, limit) in [("alice", "exchange", Amount::in_currency(10, hours.clone())), ("exchange", "bob", Amount::in_currency(100, local.clone()))] {
+                graph_lock.add_credit_line(CreditLine::new(DID::new(from), DID::new(to), limit, Default::default())).await.unwrap();
+            }
+            drop(graph_lock);
+            processor.exchange_rates_mut().set_federation_rate(hours.clone(), local.clone(), Decimal::new(20, 0)).unwrap();
+
+            // A direct transfer in the wrong currency is rejected
+            let wrong_currency = Transaction::new(
+                "wrong".to_string(),
+                DID::new("alice"),
+                DID::new("exchange"),
+                Amount::in_currency(1, local.clone()),
+                TransactionType::DirectTransfer,
+                None,
+            );
+            assert!(matches!(processor.submit_transaction(wrong_currency).await, Err(CreditError::CurrencyMismatch(_))));
+
+            // The second transfer would overdraw the exchanger's line, so neither leg is applied
+            for (id, amount) in [("exchanged", 2), ("too-large", 6)] {
+                let transaction = Transaction::new(
+                    id.to_string(),
+                    DID::new("alice"),
+                    DID::new("bob"),
+                    Amount::in_currency(amount, hours.clone()),
+                    TransactionType::CurrencyExchange,
+                    None,
+                ).with_exchange(DID::new("exchange"), local.clone());
+                processor.submit_transaction(transaction).await.unwrap();
+            }
+            let results = processor.process_pending_transactions().await;
+            let exchange = results[0].as_ref().unwrap().transaction.exchange.clone().unwrap();
+            assert_eq!(exchange.rate.unwrap().rate, Decimal::new(20, 0));
+            assert_eq!(exchange.received, Some(Amount::in_currency(40, local.clone())));
+            assert!(matches!(results[1], Err(CreditError::CreditLimitExceeded(_))));
+
+            // Each currency still nets to zero, with the exchanger holding both
+            let graph_lock = graph.lock().await;
+            let exchanger = graph_lock.get_account(&DID::new("exchange")).await.unwrap().unwrap();
+            assert_eq!(exchanger.balance_in(&hours), Amount::in_currency(2, hours.clone()));
+            assert_eq!(exchanger.balance_in(&local), Amount::in_currency(-40, local.clone()));
+            assert_eq!(graph_lock.get_account_balance(&DID::new("alice")).await.unwrap(), Amount::in_currency(-2, hours));
+            assert_eq!(graph_lock.get_account_balance(&DID::new("bob")).await.unwrap(), Amount::in_currency(40, local));
+        });
+    }
+
+    /// Signs with the message itself
+    struct EchoSigner;
+
+    impl ReportSigner for EchoSigner {
+        fn signer(&self) -> DID {
+            DID::new("clearing-house")
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<Vec<u8>, CreditError> {
+            Ok(message.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_global_clearing_respects_opt_in_and_line_limits() {
+        block_on(async {
+            let (graph, mut processor) = create_test_environment();
+
+            let mut graph_lock = graph.lock().await;
+            for name in ["a", "b", "c", "d"] {
+                graph_lock.add_account(Account::new(DID::new(name), name.to_string())).await.unwrap();
+            }
+            for (from, to, drawn) in [("a", "b", 30), ("b", "c", 30), ("c", "a", 30), ("a", "d", 50), ("d", "b", 50)] {
+                let mut credit_line = CreditLine::new(DID::new(from), DID::new(to), Amount::new(100), Default::default());
+                credit_line.balance = Amount::new(-drawn);
+                graph_lock.add_credit_line(credit_line).await.unwrap();
+            }
+            drop(graph_lock);
+
+            // d hasn't opted in, and at most 20 may be cleared from c -> a
+            let c_to_a = CreditLineId::new(&DID::new("c"), &DID::new("a"));
+            let options = ClearingOptions::new(["a", "b", "c"].map(DID::new))
+                .with_line_limit(c_to_a.clone(), Amount::new(20));
+
+            let preview = processor.run_global_clearing(&options.clone().dry_run(), &EchoSigner).await.unwrap();
+            assert!(preview.dry_run);
+            assert_eq!(preview.total_cleared, Amount::new(60));
+            assert!(preview.verify(|_, bytes, signature| bytes == signature).is_ok());
+            let line = graph.lock().await.get_credit_line(&c_to_a).await.unwrap().unwrap().balance.clone();
+            assert_eq!(line, Amount::new(-30));
+
+            let report = processor.run_global_clearing(&options, &EchoSigner).await.unwrap();
+            assert_eq!(report.adjustments, preview.adjustments);
+
+            let graph_lock = graph.lock().await;
+            for (from, to, balance) in [("a", "b", -10), ("c", "a", -10), ("a", "d", -50)] {
+                let id = CreditLineId::new(&DID::new(from), &DID::new(to));
+                assert_eq!(graph_lock.get_credit_line(&id).await.unwrap().unwrap().balance, Amount::new(balance));
+            }
+        });
+    }
+}
@@ -4,7 +4,10 @@ use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign, Neg};
+use std::cmp::Ordering;
+use std::ops::{Div, Mul, Neg};
+
+use crate::error::CreditError;
 
 /// A Decentralized Identifier (DID) used to identify accounts in the system
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -36,40 +39,106 @@ impl fmt::Display for DID {
 /// A timestamp used for timing events in the system
 pub type Timestamp = DateTime<Utc>;
 
-/// An amount of mutual credit
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Code of the currency used when none is given
+pub const DEFAULT_CURRENCY: &str = "ICN";
+
+/// A unit of account, such as the hours or local credits run by a federation
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Currency {
+    /// The currency code, e.g., "HOURS"
+    code: String,
+}
+
+impl Currency {
+    /// Create a currency with the given code
+    pub fn new(code: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+        }
+    }
+
+    /// Get the currency code as a string
+    pub fn as_str(&self) -> &str {
+        &self.code
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::new(DEFAULT_CURRENCY)
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code)
+    }
+}
+
+/// An amount of mutual credit in a currency
+///
+/// Amounts in different currencies can't be added, subtracted or ordered:
+/// `checked_add` and `checked_sub` fail with `CreditError::CurrencyMismatch`
+/// and `partial_cmp` returns `None`, except that zero is the same in every
+/// currency. Convert amounts with an exchange rate first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Amount {
     /// The numeric value of the amount
     value: Decimal,
+    /// The currency the amount is in
+    #[serde(default)]
+    currency: Currency,
 }
 
 impl Amount {
-    /// Create a new amount with the given integer value
+    /// Create a new amount with the given integer value in the default currency
     pub fn new(value: i64) -> Self {
-        Self {
-            value: Decimal::new(value, 0),
-        }
+        Self::in_currency(value, Currency::default())
+    }
+
+    /// Create a new amount with the given integer value in a currency
+    pub fn in_currency(value: i64, currency: Currency) -> Self {
+        Self::from_decimal(Decimal::new(value, 0), currency)
     }
 
-    /// Create a zero amount
+    /// Create a new amount with the given decimal value in a currency
+    pub fn from_decimal(value: Decimal, currency: Currency) -> Self {
+        Self { value, currency }
+    }
+
+    /// Create a zero amount in the default currency
     pub fn zero() -> Self {
-        Self {
-            value: Decimal::new(0, 0),
-        }
+        Self::zero_in(Currency::default())
+    }
+
+    /// Create a zero amount in a currency
+    pub fn zero_in(currency: Currency) -> Self {
+        Self::from_decimal(Decimal::ZERO, currency)
+    }
+
+    /// Get the currency of the amount
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    /// Check if the amount can be combined with another, as it's in the same currency or either is zero
+    pub fn same_currency(&self, other: &Self) -> bool {
+        self.currency == other.currency || self.value.is_zero() || other.value.is_zero()
+    }
+
+    /// Convert the amount to another currency at a rate of `currency` per unit of this one
+    pub fn convert(&self, rate: Decimal, currency: &Currency) -> Self {
+        Self::from_decimal(self.value * rate, currency.clone())
     }
 
     /// Get the absolute value of the amount
     pub fn abs(&self) -> Self {
-        Self {
-            value: self.value.abs(),
-        }
+        Self::from_decimal(self.value.abs(), self.currency.clone())
     }
 
     /// Scale the amount by a multiplier
     pub fn scale(&self, multiplier: Decimal) -> Self {
-        Self {
-            value: self.value * multiplier,
-        }
+        Self::from_decimal(self.value * multiplier, self.currency.clone())
     }
 
     /// Check if the amount is zero
@@ -96,37 +165,47 @@ impl Amount {
     pub fn value(&self) -> Decimal {
         self.value
     }
-}
 
-impl Add for Amount {
-    type Output = Self;
+    /// Add another amount in the same currency
+    pub fn checked_add(&self, other: &Self) -> Result<Self, CreditError> {
+        Ok(Self::from_decimal(self.value + other.value, self.combined_currency(other)?))
+    }
 
-    fn add(self, other: Self) -> Self {
-        Self {
-            value: self.value + other.value,
-        }
+    /// Subtract another amount in the same currency
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, CreditError> {
+        Ok(Self::from_decimal(self.value - other.value, self.combined_currency(other)?))
     }
-}
 
-impl AddAssign for Amount {
-    fn add_assign(&mut self, other: Self) {
-        self.value += other.value;
+    /// Get the smaller of two amounts in the same currency
+    pub fn checked_min(&self, other: &Self) -> Result<Self, CreditError> {
+        match self.checked_cmp(other)? {
+            Ordering::Greater => Ok(other.clone()),
+            _ => Ok(self.clone()),
+        }
     }
-}
 
-impl Sub for Amount {
-    type Output = Self;
+    /// Compare with another amount in the same currency
+    pub fn checked_cmp(&self, other: &Self) -> Result<Ordering, CreditError> {
+        self.partial_cmp(other).ok_or_else(|| self.mismatch(other))
+    }
 
-    fn sub(self, other: Self) -> Self {
-        Self {
-            value: self.value - other.value,
+    /// Get the currency of a combination with another amount
+    fn combined_currency(&self, other: &Self) -> Result<Currency, CreditError> {
+        if !self.same_currency(other) {
+            return Err(self.mismatch(other));
+        }
+        if self.value.is_zero() {
+            Ok(other.currency.clone())
+        } else {
+            Ok(self.currency.clone())
         }
     }
-}
 
-impl SubAssign for Amount {
-    fn sub_assign(&mut self, other: Self) {
-        self.value -= other.value;
+    /// Get the error for combining with an amount in another currency
+    fn mismatch(&self, other: &Self) -> CreditError {
+        CreditError::CurrencyMismatch(format!(
+            "Cannot combine amounts in {} and {}", self.currency, other.currency
+        ))
     }
 }
 
@@ -134,9 +213,7 @@ impl Mul<Decimal> for Amount {
     type Output = Self;
 
     fn mul(self, rhs: Decimal) -> Self {
-        Self {
-            value: self.value * rhs,
-        }
+        self.scale(rhs)
     }
 }
 
@@ -144,9 +221,7 @@ impl Div<Decimal> for Amount {
     type Output = Self;
 
     fn div(self, rhs: Decimal) -> Self {
-        Self {
-            value: self.value / rhs,
-        }
+        Self::from_decimal(self.value / rhs, self.currency)
     }
 }
 
@@ -154,27 +229,32 @@ impl Neg for Amount {
     type Output = Self;
 
     fn neg(self) -> Self {
-        Self {
-            value: -self.value,
-        }
+        Self::from_decimal(-self.value, self.currency)
     }
 }
 
 impl fmt::Display for Amount {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.value)
+        if self.currency.as_str() == DEFAULT_CURRENCY {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.currency)
+        }
     }
 }
 
-impl PartialOrd for Amount {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.value.partial_cmp(&other.value)
+impl PartialEq for Amount {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && (self.currency == other.currency || self.value.is_zero())
     }
 }
 
-impl Ord for Amount {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.value.cmp(&other.value)
+impl Eq for Amount {}
+
+impl PartialOrd for Amount {
+    /// Amounts in different currencies are unordered
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.same_currency(other).then(|| self.value.cmp(&other.value))
     }
 }
 
@@ -213,15 +293,9 @@ mod tests {
         let a = Amount::new(100);
         let b = Amount::new(50);
         
-        assert_eq!(a.clone() + b.clone(), Amount::new(150));
-        assert_eq!(a.clone() - b.clone(), Amount::new(50));
-        
-        let mut c = Amount::new(75);
-        c += b.clone();
-        assert_eq!(c, Amount::new(125));
-        
-        c -= b;
-        assert_eq!(c, Amount::new(75));
+        assert_eq!(a.checked_add(&b).unwrap(), Amount::new(150));
+        assert_eq!(a.checked_sub(&b).unwrap(), Amount::new(50));
+        assert_eq!(a.checked_min(&b).unwrap(), b);
         
         assert_eq!(a.scale(Decimal::new(15, 1)), Amount::new(150)); // 1.5 * 100
         
@@ -233,6 +307,43 @@ mod tests {
         assert!(Amount::zero().is_zero());
     }
 
+    #[test]
+    fn test_amounts_are_tagged_with_currencies() {
+        let hours = Currency::new("HOURS");
+        let a = Amount::in_currency(8, hours.clone());
+
+        // Zero is the same in every currency
+        assert_eq!(Amount::zero().checked_add(&a).unwrap(), a);
+        assert_eq!(Amount::zero().checked_sub(&a).unwrap().currency(), &hours);
+        assert!(a > Amount::zero());
+        assert_ne!(a, Amount::new(8));
+        assert!(!a.same_currency(&Amount::new(8)));
+
+        let converted = a.convert(Decimal::new(25, 1), &Currency::default());
+        assert_eq!(converted, Amount::new(20));
+        assert_eq!(a.to_string(), "8 HOURS");
+        assert_eq!(Amount::new(8).to_string(), "8");
+    }
+
+    #[test]
+    fn test_amounts_in_different_currencies_cannot_be_combined() {
+        let hours = Amount::in_currency(8, Currency::new("HOURS"));
+        let icn = Amount::new(1);
+
+        assert!(matches!(hours.checked_add(&icn), Err(CreditError::CurrencyMismatch(_))));
+        assert!(matches!(hours.checked_sub(&icn), Err(CreditError::CurrencyMismatch(_))));
+        assert!(matches!(hours.checked_min(&icn), Err(CreditError::CurrencyMismatch(_))));
+    }
+
+    #[test]
+    fn test_amounts_in_different_currencies_are_unordered() {
+        let hours = Amount::in_currency(8, Currency::new("HOURS"));
+        let icn = Amount::new(1);
+
+        assert_eq!(hours.partial_cmp(&icn), None);
+        assert!(matches!(hours.checked_cmp(&icn), Err(CreditError::CurrencyMismatch(_))));
+    }
+
     #[test]
     fn test_did() {
         let did = DID::new("did:icn:alpha:test123");
@@ -0,0 +1,214 @@
+//! Governance approval of credit limit increases.
+//!
+//! When a federation requires democratic credit approval, raising a credit
+//! limit above the federation's automatic maximum isn't applied directly.
+//! Instead a `credit_limit` governance proposal is created and the change is
+//! held pending until the proposal is approved, when the
+//! `CreditLimitExecutor` registered with the governance system applies it.
+//!
+//! Pending changes are held in memory. The proposals are their durable
+//! record, so on startup they are restored from the credit limit proposals
+//! governance hasn't yet finished with.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use icn_governance::{GovernanceError, GovernanceResult, Proposal, ProposalExecutor, ProposalStatus, ProposalType};
+
+use crate::{Account, CreditLimit, EconomicError, MutualCreditSystem};
+
+/// Custom governance proposal type for credit limit changes
+pub const CREDIT_LIMIT_PROPOSAL_TYPE: &str = "credit_limit";
+
+/// Proposal attribute holding the account whose limit changes
+pub const ACCOUNT_ATTRIBUTE: &str = "account_id";
+
+/// Proposal attribute holding the requested credit limit
+pub const CREDIT_LIMIT_ATTRIBUTE: &str = "credit_limit";
+
+/// Proposal attribute holding the credit limit when the change was requested
+pub const CURRENT_CREDIT_LIMIT_ATTRIBUTE: &str = "current_credit_limit";
+
+/// Proposal attribute holding the federation whose account changes
+pub const FEDERATION_ATTRIBUTE: &str = "federation_id";
+
+/// Get the governance proposal type for credit limit changes
+pub fn credit_limit_proposal_type() -> ProposalType {
+    ProposalType::Custom(CREDIT_LIMIT_PROPOSAL_TYPE.to_string())
+}
+
+/// A credit limit change waiting for governance approval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingCreditLimit {
+    /// The governance proposal deciding the change
+    pub proposal_id: String,
+
+    /// The account whose limit changes
+    pub account_id: String,
+
+    /// The account's credit limit when the change was requested
+    pub current_limit: i64,
+
+    /// The credit limit requested
+    pub requested_limit: i64,
+
+    /// When the change was requested
+    pub requested_at: DateTime<Utc>,
+}
+
+impl PendingCreditLimit {
+    /// Restore the pending change a credit limit proposal describes
+    ///
+    /// Returns `None` if the proposal isn't a credit limit proposal, its
+    /// attributes don't describe a change, or governance has finished with it.
+    pub fn from_proposal(proposal: &Proposal) -> Option<Self> {
+        if proposal.proposal_type != credit_limit_proposal_type() || !is_undecided(&proposal.status) {
+            return None;
+        }
+
+        let limit = |attribute| proposal.attributes.get(attribute)?.parse::<i64>().ok();
+        Some(Self {
+            proposal_id: proposal.id.clone(),
+            account_id: proposal.attributes.get(ACCOUNT_ATTRIBUTE)?.clone(),
+            current_limit: limit(CURRENT_CREDIT_LIMIT_ATTRIBUTE)?,
+            requested_limit: limit(CREDIT_LIMIT_ATTRIBUTE)?,
+            requested_at: DateTime::from_timestamp(i64::try_from(proposal.created_at).ok()?, 0)?,
+        })
+    }
+}
+
+/// Check whether a proposal may still be executed
+pub(crate) fn is_undecided(status: &ProposalStatus) -> bool {
+    !matches!(
+        status,
+        ProposalStatus::Executed | ProposalStatus::Rejected | ProposalStatus::Cancelled | ProposalStatus::Failed
+    )
+}
+
+/// Result of requesting a credit limit change
+#[derive(Debug, Clone)]
+pub enum CreditLimitUpdate {
+    /// The change was within the automatic limit and has been applied
+    Applied(Account),
+
+    /// The change needs governance approval and is pending
+    Pending(PendingCreditLimit),
+}
+
+/// Credit limit changes pending approval, by proposal ID
+pub(crate) type PendingCreditLimits = Arc<RwLock<HashMap<String, PendingCreditLimit>>>;
+
+/// Accounts whose credit limit proposals are being created
+pub(crate) type ProposingAccounts = Arc<Mutex<HashSet<String>>>;
+
+/// Reservation of an account while its credit limit proposal is created
+///
+/// Stops a second change being proposed for the account before the first is
+/// pending. The account is released when the reservation is dropped.
+pub(crate) struct AccountReservation {
+    /// Accounts reserved
+    accounts: ProposingAccounts,
+
+    /// The account reserved
+    account_id: String,
+}
+
+impl AccountReservation {
+    /// Reserve an account, or return `None` if it's already reserved
+    pub(crate) fn reserve(accounts: &ProposingAccounts, account_id: &str) -> Option<Self> {
+        let mut reserved = accounts.lock().unwrap_or_else(|e| e.into_inner());
+        if !reserved.insert(account_id.to_string()) {
+            return None;
+        }
+        Some(Self {
+            accounts: accounts.clone(),
+            account_id: account_id.to_string(),
+        })
+    }
+}
+
+impl Drop for AccountReservation {
+    fn drop(&mut self) {
+        self.accounts.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.account_id);
+    }
+}
+
+/// Applies credit limit changes when their governance proposals are approved
+///
+/// Register with the governance system's executor for the
+/// `credit_limit` custom proposal type. A change is only applied while the
+/// account still has the credit limit it was proposed against, so approving
+/// a proposal doesn't undo changes made since it was created.
+pub struct CreditLimitExecutor {
+    /// Mutual credit system the limits are applied to
+    mutual_credit: Arc<MutualCreditSystem>,
+
+    /// Changes pending approval
+    pending: PendingCreditLimits,
+}
+
+impl CreditLimitExecutor {
+    /// Create an executor for the economic system's pending changes
+    pub(crate) fn new(mutual_credit: Arc<MutualCreditSystem>, pending: PendingCreditLimits) -> Self {
+        Self {
+            mutual_credit,
+            pending,
+        }
+    }
+}
+
+#[async_trait]
+impl ProposalExecutor for CreditLimitExecutor {
+    async fn execute_proposal(&self, proposal: &Proposal) -> GovernanceResult<()> {
+        if proposal.proposal_type != credit_limit_proposal_type() {
+            return Err(GovernanceError::InvalidProposal(format!(
+                "Proposal {} is not a credit limit proposal", proposal.id
+            )));
+        }
+
+        let mut pending = self.pending.write()
+            .map_err(|_| GovernanceError::InvalidProposal("Failed to acquire lock on pending credit limits".to_string()))?;
+        let change = pending.get(&proposal.id)
+            .ok_or_else(|| GovernanceError::InvalidProposal(format!(
+                "No credit limit change is pending for proposal {}", proposal.id
+            )))?;
+
+        // The pending change is authoritative, but the proposal voted on must describe it
+        let requested = change.requested_limit.to_string();
+        if proposal.attributes.get(ACCOUNT_ATTRIBUTE) != Some(&change.account_id)
+            || proposal.attributes.get(CREDIT_LIMIT_ATTRIBUTE) != Some(&requested) {
+            return Err(GovernanceError::InvalidProposal(format!(
+                "Proposal {} does not match its pending credit limit change", proposal.id
+            )));
+        }
+
+        let account = self.mutual_credit.get_account(&change.account_id)
+            .map_err(|e| GovernanceError::InvalidProposal(format!("Failed to get account {}: {}", change.account_id, e)))?;
+        if account.credit_limit.value() != change.current_limit {
+            return Err(GovernanceError::InvalidProposal(format!(
+                "Credit limit of account {} changed from {} to {} after proposal {} was made",
+                change.account_id, change.current_limit, account.credit_limit.value(), proposal.id
+            )));
+        }
+
+        log::info!(
+            "Applying approved credit limit {} for account {} from proposal {}",
+            change.requested_limit, change.account_id, proposal.id
+        );
+        self.mutual_credit.update_credit_limit(&change.account_id, CreditLimit::new(change.requested_limit))
+            .map_err(|e| GovernanceError::InvalidProposal(format!("Failed to apply credit limit: {}", e)))?;
+        pending.remove(&proposal.id);
+
+        Ok(())
+    }
+}
+
+/// Convert a governance error to an economic error
+pub(crate) fn governance_error(error: GovernanceError) -> EconomicError {
+    EconomicError::Governance(error.to_string())
+}
@@ -3,10 +3,13 @@
 //! This module provides economic functionality for the ICN network,
 //! including mutual credit systems, incentives, and tokenized economic transactions.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 use thiserror::Error;
 use serde::{Deserialize, Serialize};
 
+use icn_governance::{Governance, ProposalStatus};
+
 // Re-export mutual credit system
 pub use icn_mutual_credit::{
     Account,
@@ -22,6 +25,10 @@ pub use icn_mutual_credit::{
 
 // Modules
 pub mod incentives;
+pub mod credit_approval;
+
+pub use credit_approval::{CreditLimitExecutor, CreditLimitUpdate, PendingCreditLimit, CREDIT_LIMIT_PROPOSAL_TYPE};
+use credit_approval::{governance_error, AccountReservation, PendingCreditLimits, ProposingAccounts};
 
 /// Economic error types
 #[derive(Debug, Error)]
@@ -57,7 +64,10 @@ pub enum EconomicError {
     InvalidState(String),
 
     #[error("Invalid input: {0}")]
-    InvalidInput(String)
+    InvalidInput(String),
+
+    #[error("Governance error: {0}")]
+    Governance(String)
 }
 
 pub type Result<T> = std::result::Result<T, EconomicError>;
@@ -112,10 +122,19 @@ impl Default for FederationEconomicConfig {
 /// Economic system for ICN
 pub struct EconomicSystem {
     /// Mutual credit system
-    mutual_credit: MutualCreditSystem,
+    mutual_credit: Arc<MutualCreditSystem>,
     
     /// Federation configuration
     config: FederationEconomicConfig,
+    
+    /// Governance system approving credit limit increases
+    governance: Option<Arc<dyn Governance>>,
+    
+    /// Credit limit changes pending governance approval, by proposal ID
+    pending_credit_limits: PendingCreditLimits,
+    
+    /// Accounts whose credit limit proposals are being created
+    proposing_credit_limits: ProposingAccounts,
 }
 
 impl EconomicSystem {
@@ -123,11 +142,30 @@ impl EconomicSystem {
     pub fn new(config: FederationEconomicConfig) -> Self {
         log::info!("Initializing economic system for federation: {}", config.federation_id);
         Self {
-            mutual_credit: MutualCreditSystem::new(),
+            mutual_credit: Arc::new(MutualCreditSystem::new()),
             config,
+            governance: None,
+            pending_credit_limits: Arc::new(RwLock::new(HashMap::new())),
+            proposing_credit_limits: Arc::new(Mutex::new(HashSet::new())),
         }
     }
     
+    /// Use a governance system to approve credit limit increases
+    ///
+    /// Register `credit_limit_executor` with the governance system's proposal
+    /// executor so approved increases are applied, and call
+    /// `restore_pending_credit_limits` to pick up increases proposed before a
+    /// restart.
+    pub fn with_governance(mut self, governance: Arc<dyn Governance>) -> Self {
+        self.governance = Some(governance);
+        self
+    }
+    
+    /// Get the executor applying approved credit limit proposals
+    pub fn credit_limit_executor(&self) -> Arc<CreditLimitExecutor> {
+        Arc::new(CreditLimitExecutor::new(self.mutual_credit.clone(), self.pending_credit_limits.clone()))
+    }
+    
     /// Create a new account
     pub async fn create_account(&self, id: &str, name: &str) -> Result<Account> {
         let credit_limit = CreditLimit::new(self.config.default_credit_limit);
@@ -230,21 +268,26 @@ impl EconomicSystem {
     }
     
     /// Update credit limit
-    pub async fn update_credit_limit(&self, account_id: &str, credit_limit: i64) -> Result<Account> {
-        let limit = CreditLimit::new(credit_limit);
+    ///
+    /// Increases above the automatic maximum in a federation requiring
+    /// democratic approval are proposed to governance and held pending
+    /// rather than applied.
+    pub async fn update_credit_limit(&self, account_id: &str, credit_limit: i64) -> Result<CreditLimitUpdate> {
+        let account = self.get_account(account_id).await?;
+        let current_limit = account.credit_limit.value();
         
         // Check if democratic approval is required
-        if self.config.democratic_credit_approval && credit_limit > self.config.max_automatic_credit_limit {
-            log::error!("Credit limit increase exceeds automatic limit and requires governance approval");
-            return Err(EconomicError::InvalidTransaction(
-                "Credit limit increase requires governance approval".to_string()
-            ));
+        if self.config.democratic_credit_approval
+            && credit_limit > self.config.max_automatic_credit_limit
+            && credit_limit > current_limit {
+            return self.propose_credit_limit(account_id, current_limit, credit_limit).await
+                .map(CreditLimitUpdate::Pending);
         }
         
         log::info!("Updating credit limit for account {} to {}", account_id, credit_limit);
         
-        match self.mutual_credit.update_credit_limit(&account_id.to_string(), limit) {
-            Ok(account) => Ok(account),
+        match self.mutual_credit.update_credit_limit(&account_id.to_string(), CreditLimit::new(credit_limit)) {
+            Ok(account) => Ok(CreditLimitUpdate::Applied(account)),
             Err(e) => {
                 log::error!("Failed to update credit limit: {}", e);
                 Err(EconomicError::Internal(e.to_string()))
@@ -252,6 +295,163 @@ impl EconomicSystem {
         }
     }
     
+    /// Propose a credit limit increase to governance and hold it pending
+    async fn propose_credit_limit(
+        &self,
+        account_id: &str,
+        current_limit: i64,
+        credit_limit: i64,
+    ) -> Result<PendingCreditLimit> {
+        let governance = self.governance.as_ref().ok_or_else(|| {
+            log::error!("Credit limit increase exceeds automatic limit and requires governance approval");
+            EconomicError::InvalidState("Credit limit increase requires governance approval".to_string())
+        })?;
+        
+        // Reserve the account while the proposal is created. The pending
+        // changes stay locked until it's reserved, so a change that finishes
+        // being proposed meanwhile is either seen here or still reserved.
+        let _reservation = {
+            let pending = self.pending_credit_limits.read()
+                .map_err(|_| EconomicError::Internal("Failed to acquire lock on pending credit limits".to_string()))?;
+            if let Some(change) = pending.values().find(|change| change.account_id == account_id) {
+                return Err(EconomicError::InvalidState(format!(
+                    "Account {} already has a credit limit change pending in proposal {}",
+                    account_id, change.proposal_id
+                )));
+            }
+            AccountReservation::reserve(&self.proposing_credit_limits, account_id).ok_or_else(|| {
+                EconomicError::InvalidState(format!(
+                    "Account {} already has a credit limit change being proposed", account_id
+                ))
+            })?
+        };
+        
+        let attributes = HashMap::from([
+            (credit_approval::ACCOUNT_ATTRIBUTE.to_string(), account_id.to_string()),
+            (credit_approval::CREDIT_LIMIT_ATTRIBUTE.to_string(), credit_limit.to_string()),
+            (credit_approval::CURRENT_CREDIT_LIMIT_ATTRIBUTE.to_string(), current_limit.to_string()),
+            (credit_approval::FEDERATION_ATTRIBUTE.to_string(), self.config.federation_id.clone()),
+        ]);
+        let proposal = governance.create_proposal(
+            format!("Increase credit limit of {} to {}", account_id, credit_limit),
+            format!(
+                "Increase the credit limit of account {} from {} to {}, above the automatic maximum of {}",
+                account_id, current_limit, credit_limit, self.config.max_automatic_credit_limit
+            ),
+            credit_approval::credit_limit_proposal_type(),
+            None,
+            attributes,
+        ).await.map_err(governance_error)?;
+        
+        log::info!(
+            "Credit limit increase for account {} to {} is pending approval of proposal {}",
+            account_id, credit_limit, proposal.id
+        );
+        
+        let pending = PendingCreditLimit {
+            proposal_id: proposal.id,
+            account_id: account_id.to_string(),
+            current_limit,
+            requested_limit: credit_limit,
+            requested_at: chrono::Utc::now(),
+        };
+        self.pending_credit_limits.write()
+            .map_err(|_| EconomicError::Internal("Failed to acquire lock on pending credit limits".to_string()))?
+            .insert(pending.proposal_id.clone(), pending.clone());
+        
+        Ok(pending)
+    }
+    
+    /// Get all credit limit changes pending governance approval
+    pub fn pending_credit_limits(&self) -> Result<Vec<PendingCreditLimit>> {
+        let pending = self.pending_credit_limits.read()
+            .map_err(|_| EconomicError::Internal("Failed to acquire lock on pending credit limits".to_string()))?;
+        let mut changes: Vec<_> = pending.values().cloned().collect();
+        changes.sort_by_key(|change| change.requested_at);
+        Ok(changes)
+    }
+    
+    /// Get the credit limit change pending governance approval for an account
+    pub fn pending_credit_limit(&self, account_id: &str) -> Result<Option<PendingCreditLimit>> {
+        Ok(self.pending_credit_limits()?
+            .into_iter()
+            .find(|change| change.account_id == account_id))
+    }
+    
+    /// Restore the credit limit changes pending governance approval
+    ///
+    /// Pending changes are held in memory, so after a restart they're rebuilt
+    /// from this federation's credit limit proposals governance may still
+    /// execute. Returns the changes restored.
+    pub async fn restore_pending_credit_limits(&self) -> Result<Vec<PendingCreditLimit>> {
+        let Some(governance) = &self.governance else {
+            return Ok(Vec::new());
+        };
+        
+        let mut changes: Vec<_> = governance.list_proposals().await
+            .map_err(governance_error)?
+            .iter()
+            .filter(|proposal| {
+                proposal.attributes.get(credit_approval::FEDERATION_ATTRIBUTE) == Some(&self.config.federation_id)
+            })
+            .filter_map(PendingCreditLimit::from_proposal)
+            .collect();
+        changes.sort_by_key(|change| change.requested_at);
+        
+        let mut pending = self.pending_credit_limits.write()
+            .map_err(|_| EconomicError::Internal("Failed to acquire lock on pending credit limits".to_string()))?;
+        let mut restored = Vec::new();
+        for change in changes {
+            if pending.contains_key(&change.proposal_id) {
+                continue;
+            }
+            if let Some(existing) = pending.values().find(|existing| existing.account_id == change.account_id) {
+                log::warn!(
+                    "Not restoring credit limit change for account {} from proposal {}, proposal {} is already pending",
+                    change.account_id, change.proposal_id, existing.proposal_id
+                );
+                continue;
+            }
+            log::info!("Restoring credit limit change for account {} from proposal {}", change.account_id, change.proposal_id);
+            pending.insert(change.proposal_id.clone(), change.clone());
+            restored.push(change);
+        }
+        
+        Ok(restored)
+    }
+    
+    /// Drop pending credit limit changes whose proposals won't be executed
+    ///
+    /// Returns the changes dropped because their proposals were rejected,
+    /// cancelled, failed or no longer exist.
+    pub async fn prune_pending_credit_limits(&self) -> Result<Vec<PendingCreditLimit>> {
+        let Some(governance) = &self.governance else {
+            return Ok(Vec::new());
+        };
+        
+        let mut dropped = Vec::new();
+        for change in self.pending_credit_limits()? {
+            let status = governance.get_proposal(&change.proposal_id).await
+                .map_err(governance_error)?
+                .map(|proposal| proposal.status);
+            if matches!(
+                status,
+                None | Some(ProposalStatus::Rejected | ProposalStatus::Cancelled | ProposalStatus::Failed)
+            ) {
+                log::info!("Dropping credit limit change for account {} from proposal {}", change.account_id, change.proposal_id);
+                dropped.push(change);
+            }
+        }
+        
+        let mut pending = self.pending_credit_limits.write()
+            .map_err(|_| EconomicError::Internal("Failed to acquire lock on pending credit limits".to_string()))?;
+        for change in &dropped {
+            pending.remove(&change.proposal_id);
+        }
+        
+        Ok(dropped)
+    }
+    
     /// Get the current configuration
     pub fn get_config(&self) -> &FederationEconomicConfig {
         &self.config
@@ -261,6 +461,221 @@ impl EconomicSystem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_trait::async_trait;
+    use icn_core::crypto::identity::NodeId;
+    use icn_governance::{
+        GovernanceConfig, GovernanceError, GovernanceResult, Proposal, ProposalExecutor, ProposalType, Vote,
+    };
+    
+    /// Governance keeping proposals in memory, without voting
+    #[derive(Default)]
+    struct TestGovernance {
+        proposals: tokio::sync::RwLock<HashMap<String, Proposal>>,
+    }
+    
+    impl TestGovernance {
+        async fn set_status(&self, id: &str, status: ProposalStatus) -> Proposal {
+            let mut proposals = self.proposals.write().await;
+            let proposal = proposals.get_mut(id).unwrap();
+            proposal.status = status;
+            proposal.clone()
+        }
+    }
+    
+    #[async_trait]
+    impl Governance for TestGovernance {
+        async fn get_config(&self) -> GovernanceResult<GovernanceConfig> {
+            Ok(GovernanceConfig::default())
+        }
+        
+        async fn set_config(&self, _config: GovernanceConfig) -> GovernanceResult<()> {
+            Ok(())
+        }
+        
+        async fn create_proposal(
+            &self,
+            title: String,
+            description: String,
+            proposal_type: ProposalType,
+            voting_period: Option<u64>,
+            attributes: HashMap<String, String>,
+        ) -> GovernanceResult<Proposal> {
+            // Let other tasks run while the proposal is created, as a real
+            // governance system would
+            tokio::task::yield_now().await;
+            let mut proposals = self.proposals.write().await;
+            let mut proposal = Proposal::new(
+                title,
+                description,
+                proposal_type,
+                NodeId::from_string("proposer"),
+                0,
+                voting_period.unwrap_or(86400),
+                attributes,
+            );
+            proposal.id = format!("proposal-{}", proposals.len());
+            proposal.status = ProposalStatus::Open;
+            proposals.insert(proposal.id.clone(), proposal.clone());
+            Ok(proposal)
+        }
+        
+        async fn get_proposal(&self, id: &str) -> GovernanceResult<Option<Proposal>> {
+            Ok(self.proposals.read().await.get(id).cloned())
+        }
+        
+        async fn list_proposals(&self) -> GovernanceResult<Vec<Proposal>> {
+            Ok(self.proposals.read().await.values().cloned().collect())
+        }
+        
+        async fn vote(&self, proposal_id: &str, _approve: bool, _comment: Option<String>) -> GovernanceResult<Vote> {
+            Err(GovernanceError::ProposalNotFound(proposal_id.to_string()))
+        }
+        
+        async fn get_votes(&self, _proposal_id: &str) -> GovernanceResult<Vec<Vote>> {
+            Ok(Vec::new())
+        }
+        
+        async fn process_proposal(&self, proposal_id: &str) -> GovernanceResult<ProposalStatus> {
+            Err(GovernanceError::ProposalNotFound(proposal_id.to_string()))
+        }
+        
+        async fn cancel_proposal(&self, proposal_id: &str) -> GovernanceResult<()> {
+            self.set_status(proposal_id, ProposalStatus::Cancelled).await;
+            Ok(())
+        }
+        
+        async fn execute_proposal(&self, id: &str) -> GovernanceResult<()> {
+            Err(GovernanceError::ProposalNotFound(id.to_string()))
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_credit_limit_increase_requires_governance_approval() {
+        let governance = Arc::new(TestGovernance::default());
+        let system = EconomicSystem::new(FederationEconomicConfig::default()).with_governance(governance.clone());
+        let executor = system.credit_limit_executor();
+        system.create_account("user1", "Test User 1").await.unwrap();
+        
+        // Within the automatic limit the change applies immediately
+        let update = system.update_credit_limit("user1", 3000).await.unwrap();
+        assert!(matches!(update, CreditLimitUpdate::Applied(account) if account.credit_limit.value() == 3000));
+        
+        let CreditLimitUpdate::Pending(pending) = system.update_credit_limit("user1", 8000).await.unwrap() else {
+            panic!("Increase above the automatic limit was applied");
+        };
+        assert_eq!((pending.current_limit, pending.requested_limit), (3000, 8000));
+        assert_eq!(system.pending_credit_limit("user1").unwrap(), Some(pending.clone()));
+        assert_eq!(system.get_account("user1").await.unwrap().credit_limit.value(), 3000);
+        assert!(system.update_credit_limit("user1", 9000).await.is_err());
+        
+        let proposal = governance.set_status(&pending.proposal_id, ProposalStatus::Approved).await;
+        assert_eq!(proposal.proposal_type, ProposalType::Custom(CREDIT_LIMIT_PROPOSAL_TYPE.to_string()));
+        executor.execute_proposal(&proposal).await.unwrap();
+        assert_eq!(system.get_account("user1").await.unwrap().credit_limit.value(), 8000);
+        assert!(system.pending_credit_limits().unwrap().is_empty());
+        assert!(executor.execute_proposal(&proposal).await.is_err());
+        
+        // Rejected increases are dropped without being applied
+        let CreditLimitUpdate::Pending(rejected) = system.update_credit_limit("user1", 9000).await.unwrap() else {
+            panic!("Increase above the automatic limit was applied");
+        };
+        governance.set_status(&rejected.proposal_id, ProposalStatus::Rejected).await;
+        assert_eq!(system.prune_pending_credit_limits().await.unwrap(), vec![rejected]);
+        assert_eq!(system.pending_credit_limit("user1").unwrap(), None);
+        assert_eq!(system.get_account("user1").await.unwrap().credit_limit.value(), 8000);
+        
+        // Without governance the increase can't be made
+        let ungoverned = EconomicSystem::new(FederationEconomicConfig::default());
+        ungoverned.create_account("user1", "Test User 1").await.unwrap();
+        assert!(matches!(ungoverned.update_credit_limit("user1", 8000).await, Err(EconomicError::InvalidState(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_credit_limit_increases_create_one_proposal() {
+        let governance = Arc::new(TestGovernance::default());
+        let system = EconomicSystem::new(FederationEconomicConfig::default()).with_governance(governance.clone());
+        system.create_account("user1", "Test User 1").await.unwrap();
+        
+        let (first, second) = tokio::join!(
+            system.update_credit_limit("user1", 8000),
+            system.update_credit_limit("user1", 9000),
+        );
+        assert!(matches!(first, Ok(CreditLimitUpdate::Pending(_))));
+        assert!(matches!(second, Err(EconomicError::InvalidState(_))));
+        assert_eq!(governance.list_proposals().await.unwrap().len(), 1);
+        assert_eq!(system.pending_credit_limits().unwrap().len(), 1);
+        
+        // The reservation is released once the proposal is pending
+        let pending = system.pending_credit_limit("user1").unwrap().unwrap();
+        governance.set_status(&pending.proposal_id, ProposalStatus::Rejected).await;
+        system.prune_pending_credit_limits().await.unwrap();
+        assert!(matches!(system.update_credit_limit("user1", 9000).await, Ok(CreditLimitUpdate::Pending(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_stale_credit_limit_proposal_is_not_applied() {
+        let governance = Arc::new(TestGovernance::default());
+        let system = EconomicSystem::new(FederationEconomicConfig::default()).with_governance(governance.clone());
+        let executor = system.credit_limit_executor();
+        system.create_account("user1", "Test User 1").await.unwrap();
+        let CreditLimitUpdate::Pending(pending) = system.update_credit_limit("user1", 8000).await.unwrap() else {
+            panic!("Increase above the automatic limit was applied");
+        };
+        
+        // The limit is lowered while the increase is being voted on
+        system.update_credit_limit("user1", 500).await.unwrap();
+        let proposal = governance.set_status(&pending.proposal_id, ProposalStatus::Approved).await;
+        assert!(executor.execute_proposal(&proposal).await.is_err());
+        assert_eq!(system.get_account("user1").await.unwrap().credit_limit.value(), 500);
+    }
+    
+    #[tokio::test]
+    async fn test_pending_credit_limits_are_restored_from_proposals() {
+        let governance = Arc::new(TestGovernance::default());
+        let system = EconomicSystem::new(FederationEconomicConfig::default()).with_governance(governance.clone());
+        system.create_account("user1", "Test User 1").await.unwrap();
+        system.create_account("user2", "Test User 2").await.unwrap();
+        let CreditLimitUpdate::Pending(pending) = system.update_credit_limit("user1", 8000).await.unwrap() else {
+            panic!("Increase above the automatic limit was applied");
+        };
+        let CreditLimitUpdate::Pending(rejected) = system.update_credit_limit("user2", 8000).await.unwrap() else {
+            panic!("Increase above the automatic limit was applied");
+        };
+        governance.set_status(&rejected.proposal_id, ProposalStatus::Rejected).await;
+        
+        // Another federation sharing the governance system proposes its own change
+        let other_config = FederationEconomicConfig {
+            federation_id: "other".to_string(),
+            ..FederationEconomicConfig::default()
+        };
+        let other = EconomicSystem::new(other_config).with_governance(governance.clone());
+        other.create_account("user1", "Other User 1").await.unwrap();
+        assert!(matches!(other.update_credit_limit("user1", 9000).await, Ok(CreditLimitUpdate::Pending(_))));
+        
+        // A restarted system sharing the mutual credit state has lost the pending changes
+        let restarted = EconomicSystem {
+            mutual_credit: system.mutual_credit.clone(),
+            config: FederationEconomicConfig::default(),
+            governance: Some(governance.clone()),
+            pending_credit_limits: Arc::new(RwLock::new(HashMap::new())),
+            proposing_credit_limits: Arc::new(Mutex::new(HashSet::new())),
+        };
+        let executor = restarted.credit_limit_executor();
+        let proposal = governance.get_proposal(&pending.proposal_id).await.unwrap().unwrap();
+        assert!(executor.execute_proposal(&proposal).await.is_err());
+        
+        let restored = restarted.restore_pending_credit_limits().await.unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!((restored[0].proposal_id.as_str(), restored[0].account_id.as_str()), (pending.proposal_id.as_str(), "user1"));
+        assert_eq!((restored[0].current_limit, restored[0].requested_limit), (1000, 8000));
+        assert!(restarted.update_credit_limit("user1", 9000).await.is_err());
+        assert!(restarted.restore_pending_credit_limits().await.unwrap().is_empty());
+        
+        let proposal = governance.set_status(&pending.proposal_id, ProposalStatus::Approved).await;
+        executor.execute_proposal(&proposal).await.unwrap();
+        assert_eq!(restarted.get_account("user1").await.unwrap().credit_limit.value(), 8000);
+        assert_eq!(restarted.get_account("user2").await.unwrap().credit_limit.value(), 1000);
+    }
     
     #[tokio::test]
     async fn test_create_account() {
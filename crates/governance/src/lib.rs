@@ -572,13 +572,59 @@ mod tests {
         assert_eq!(proposal.title, "Test Proposal");
         assert_eq!(proposal.description, "A test proposal");
     }
+    
+    #[tokio::test]
+    async fn test_ledger_reputation_accepts_only_signed_third_party_evidence() {
+        use icn_core::reputation::ReputationLedger;
+        use icn_core::storage::MemoryStorage;
+        use icn_identity::mock::MockIdentityProvider;
+        use reputation::{Evidence, EvidenceType, LedgerReputation, Reputation};
+        
+        let ledger = Arc::new(ReputationLedger::open(Arc::new(MemoryStorage::new())).await.unwrap());
+        let identity_provider = Arc::new(MockIdentityProvider::new());
+        let reputation = LedgerReputation::new(ledger.clone(), identity_provider.clone());
+        let alice = NodeId::from_string("did:icn:alice");
+        let bob = NodeId::from_string("did:icn:bob");
+        
+        let signed = |submitter: &NodeId, weight| {
+            let mut evidence = Evidence::new(
+                submitter.clone(),
+                alice.clone(),
+                EvidenceType::Voting,
+                "voted".to_string(),
+                weight,
+            );
+            evidence.signature = vec![1];
+            evidence
+        };
+        
+        // Members can't vouch for themselves, even with a signature
+        assert!(reputation.submit_evidence(signed(&alice, 1.0)).await.is_err());
+        
+        // Unsigned evidence and evidence the submitter didn't sign are rejected
+        let mut unsigned = signed(&bob, 1.0);
+        unsigned.signature.clear();
+        assert!(reputation.submit_evidence(unsigned).await.is_err());
+        identity_provider.set_always_verify(false);
+        assert!(reputation.submit_evidence(signed(&bob, 1.0)).await.is_err());
+        assert_eq!(ledger.audit_trail("did:icn:alice").await.len(), 0);
+        
+        // Evidence signed by another member is recorded
+        identity_provider.set_always_verify(true);
+        reputation.submit_evidence(signed(&bob, 1.0)).await.unwrap();
+        assert_eq!(ledger.audit_trail("did:icn:alice").await.len(), 1);
+    }
 }
 
 // Implement Reputation-related types for use in governance
 pub mod reputation {
     use std::collections::HashMap;
+    use std::sync::Arc;
     use async_trait::async_trait;
+    use tokio::sync::RwLock;
     use icn_core::crypto::identity::NodeId;
+    use icn_core::reputation::{evidence_context, ReputationContext, ReputationLedger};
+    use icn_identity::IdentityProvider;
     use crate::GovernanceError;
 
     /// Result type for reputation operations
@@ -611,6 +657,25 @@ pub mod reputation {
         Custom(String),
     }
     
+    impl EvidenceType {
+        /// Get the snake case name of the evidence type
+        pub fn name(&self) -> &str {
+            match self {
+                Self::SuccessfulTransaction => "successful_transaction",
+                Self::FailedTransaction => "failed_transaction",
+                Self::PositiveFeedback => "positive_feedback",
+                Self::NegativeFeedback => "negative_feedback",
+                Self::Validation => "validation",
+                Self::Attestation => "attestation",
+                Self::GovernanceParticipation => "governance_participation",
+                Self::Voting => "voting",
+                Self::ProposalCreation => "proposal_creation",
+                Self::ProposalExecution => "proposal_execution",
+                Self::Custom(name) => name,
+            }
+        }
+    }
+    
     /// Evidence about an identity
     #[derive(Debug, Clone)]
     pub struct Evidence {
@@ -628,6 +693,8 @@ pub mod reputation {
         pub weight: f64,
         /// When the evidence was created
         pub created_at: u64,
+        /// The submitter's signature over the evidence, empty if unsigned
+        pub signature: Vec<u8>,
     }
     
     impl Evidence {
@@ -647,8 +714,22 @@ pub mod reputation {
                 description,
                 weight,
                 created_at: icn_core::utils::timestamp_secs(),
+                signature: Vec::new(),
             }
         }
+        
+        /// Get the bytes the submitter signs
+        pub fn bytes_to_sign(&self) -> Vec<u8> {
+            serde_json::to_vec(&(
+                &self.id,
+                self.submitter.as_str(),
+                self.subject.as_str(),
+                self.evidence_type.name(),
+                &self.description,
+                self.weight.to_bits(),
+                self.created_at,
+            )).unwrap_or_default()
+        }
     }
     
     /// Reputation score for an identity
@@ -703,6 +784,113 @@ pub mod reputation {
         /// Verify evidence signature
         async fn verify_evidence(&self, evidence: &Evidence) -> ReputationResult<bool>;
     }
+    
+    /// How far in the future evidence may be timestamped (seconds)
+    const MAX_CLOCK_SKEW: u64 = 60;
+    
+    /// Reputation backed by the shared DID reputation ledger
+    ///
+    /// Voting weight is read from the same overall score that network peer
+    /// scoring and consensus validator selection use, and evidence submitted
+    /// here is recorded in the ledger's audit trail. The ledger persists the
+    /// IDs of recorded evidence, so evidence is counted once across restarts.
+    ///
+    /// Only evidence signed by someone other than its subject is accepted, so
+    /// members can't raise their own voting weight.
+    pub struct LedgerReputation {
+        /// The shared ledger
+        ledger: Arc<ReputationLedger>,
+        /// Identity provider verifying evidence signatures
+        identity_provider: Arc<dyn IdentityProvider>,
+        /// Evidence submitted through this adapter (by ID)
+        evidence: RwLock<HashMap<String, Evidence>>,
+    }
+    
+    impl LedgerReputation {
+        /// Create an adapter over a ledger
+        pub fn new(ledger: Arc<ReputationLedger>, identity_provider: Arc<dyn IdentityProvider>) -> Self {
+            Self {
+                ledger,
+                identity_provider,
+                evidence: RwLock::new(HashMap::new()),
+            }
+        }
+    }
+    
+    #[async_trait]
+    impl Reputation for LedgerReputation {
+        async fn get_reputation(&self, identity_id: &NodeId) -> ReputationResult<ReputationScore> {
+            let did = identity_id.as_str();
+            let mut score = ReputationScore::new(identity_id.clone());
+            score.score = self.ledger.overall(did).await;
+            score.category_scores = self.ledger.context_scores(did).await
+                .into_iter()
+                .map(|(context, score)| (format!("{:?}", context), score))
+                .collect();
+            
+            for event in self.ledger.audit_trail(did).await {
+                score.total_count += 1;
+                if event.delta > 0.0 {
+                    score.positive_count += 1;
+                } else if event.delta < 0.0 {
+                    score.negative_count += 1;
+                }
+            }
+            Ok(score)
+        }
+        
+        async fn submit_evidence(&self, evidence: Evidence) -> ReputationResult<()> {
+            if !self.verify_evidence(&evidence).await? {
+                return Err(GovernanceError::ReputationError(format!(
+                    "Evidence {} is not valid governance evidence", evidence.id
+                )));
+            }
+            
+            let mut stored = self.evidence.write().await;
+            self.ledger.record_evidence(
+                &evidence.id,
+                evidence.subject.as_str(),
+                evidence_context(evidence.evidence_type.name()),
+                evidence.weight,
+                "governance",
+                &evidence.description,
+            ).await.map_err(|e| GovernanceError::ReputationError(e.to_string()))?;
+            stored.insert(evidence.id.clone(), evidence);
+            Ok(())
+        }
+        
+        async fn get_evidence(&self, identity_id: &NodeId) -> ReputationResult<Vec<Evidence>> {
+            Ok(self.evidence.read().await.values()
+                .filter(|e| e.subject == *identity_id)
+                .cloned()
+                .collect())
+        }
+        
+        async fn get_evidence_by_id(&self, evidence_id: &str) -> ReputationResult<Option<Evidence>> {
+            Ok(self.evidence.read().await.get(evidence_id).cloned())
+        }
+        
+        async fn verify_evidence(&self, evidence: &Evidence) -> ReputationResult<bool> {
+            let now = icn_core::utils::timestamp_secs();
+            let well_formed = !evidence.id.is_empty()
+                && evidence.submitter != evidence.subject
+                && evidence.weight.is_finite()
+                && (-1.0..=1.0).contains(&evidence.weight)
+                && evidence.created_at <= now + MAX_CLOCK_SKEW
+                && evidence_context(evidence.evidence_type.name()) == ReputationContext::Governance
+                && !evidence.signature.is_empty();
+            if !well_formed {
+                return Ok(false);
+            }
+            
+            // The submitter vouches for the evidence with its signature
+            self.identity_provider.verify(
+                evidence.submitter.as_str(),
+                &evidence.bytes_to_sign(),
+                &evidence.signature,
+            ).await.map_err(|e| GovernanceError::IdentityError(e.to_string()))
+        }
+    }
 }
 
 /// Default implementation of the Governance trait
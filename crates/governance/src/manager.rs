@@ -279,14 +279,35 @@ impl GovernanceManager {
             _ => return, // Unknown activity type
         };
         
-        // Create evidence and submit to reputation system
-        let evidence = Evidence::new(
+        // This node vouches for the participation it observed; evidence
+        // about its own identity would be self-reported and is not accepted
+        let submitter = match self.identity_provider.get_identity().await {
+            Ok(identity) => NodeId::from_string(identity.id),
+            Err(e) => {
+                error!("Failed to get identity for governance participation evidence: {}", e);
+                return;
+            }
+        };
+        if submitter == *identity_id {
+            debug!("Not recording governance participation evidence about this node's own identity");
+            return;
+        }
+        
+        // Create signed evidence and submit to reputation system
+        let mut evidence = Evidence::new(
+            submitter,
             identity_id.clone(),
-            identity_id.clone(), // Subject is the same as submitter for self-reported evidence
             evidence_type,
             description.to_string(),
             weight,
         );
+        evidence.signature = match self.identity_provider.sign(&evidence.bytes_to_sign()).await {
+            Ok(signature) => signature,
+            Err(e) => {
+                error!("Failed to sign governance participation evidence: {}", e);
+                return;
+            }
+        };
         
         if let Err(e) = self.reputation.submit_evidence(evidence).await {
             error!("Failed to submit governance participation evidence: {}", e);
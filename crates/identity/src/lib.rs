@@ -139,6 +139,7 @@ impl IdentityProvider for IdentityService {
 
 // Export the mock implementation for tests
 pub mod mock;
+pub use mock::MockIdentityProvider;
 pub mod storage;
 
 #[cfg(test)]
@@ -11,6 +11,7 @@ use serde::{Serialize, Deserialize};
 use tokio::sync::RwLock;
 use icn_core::{
     crypto::{NodeId, Signature, Hash, sha256},
+    reputation::{evidence_context, ReputationLedger},
     storage::{Storage, StorageResult, StorageError},
     utils::timestamp_secs,
 };
@@ -293,6 +294,8 @@ pub struct ReputationManager {
     evidence_by_subject: Arc<RwLock<HashMap<String, HashSet<String>>>>,
     /// Cache of evidence by submitter (submitter ID -> HashSet of evidence IDs)
     evidence_by_submitter: Arc<RwLock<HashMap<String, HashSet<String>>>>,
+    /// Shared reputation ledger new evidence is recorded in
+    ledger: Option<Arc<ReputationLedger>>,
 }
 
 impl ReputationManager {
@@ -310,6 +313,7 @@ impl ReputationManager {
             evidence_by_id: Arc::new(RwLock::new(HashMap::new())),
             evidence_by_subject: Arc::new(RwLock::new(HashMap::new())),
             evidence_by_submitter: Arc::new(RwLock::new(HashMap::new())),
+            ledger: None,
         };
         
         // Load evidence from storage
@@ -321,6 +325,33 @@ impl ReputationManager {
         manager
     }
     
+    /// Record new evidence in the shared reputation ledger
+    ///
+    /// Overall scores are then read from the ledger, so they agree with the
+    /// scores network peer scoring and governance use.
+    pub fn with_ledger(mut self, ledger: Arc<ReputationLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+    
+    /// Record a piece of evidence in the ledger, if there is one
+    ///
+    /// The ledger records each evidence ID once, so resubmitted evidence
+    /// does not count twice, even across restarts.
+    async fn record_in_ledger(&self, evidence: &Evidence) -> ReputationResult<()> {
+        if let Some(ledger) = &self.ledger {
+            ledger.record_evidence(
+                &evidence.id,
+                evidence.subject.as_str(),
+                evidence_context(&evidence.evidence_type.to_string()),
+                evidence.weight,
+                "identity",
+                &evidence.id,
+            ).await?;
+        }
+        Ok(())
+    }
+    
     /// Load evidence from storage
     async fn load_evidence(&self) -> ReputationResult<()> {
         let dir = "evidence";
@@ -471,6 +502,7 @@ impl ReputationManager {
         // Update reputation score
         let subject_id = evidence.subject.as_str().to_string();
         let _ = self.calculate_reputation_score(&subject_id).await;
+        self.record_in_ledger(&evidence).await?;
         
         Ok(evidence)
     }
@@ -514,15 +546,18 @@ impl Reputation for ReputationManager {
         let id_str = identity_id.as_str().to_string();
         
         // Check cache first
-        {
-            let scores = self.reputation_scores.read().await;
-            if let Some(score) = scores.get(&id_str) {
-                return Ok(score.clone());
-            }
+        let cached = self.reputation_scores.read().await.get(&id_str).cloned();
+        let mut score = match cached {
+            Some(score) => score,
+            None => self.calculate_reputation_score(&id_str).await?,
+        };
+        
+        // The ledger's overall score includes what other subsystems recorded
+        if let Some(ledger) = &self.ledger {
+            score.score = ledger.overall(&id_str).await;
         }
         
-        // Calculate reputation score
-        self.calculate_reputation_score(&id_str).await
+        Ok(score)
     }
     
     async fn submit_evidence(&self, evidence: Evidence) -> ReputationResult<()> {
@@ -562,6 +597,8 @@ impl Reputation for ReputationManager {
         let subject_id = evidence.subject.as_str().to_string();
         let _ = self.calculate_reputation_score(&subject_id).await;
         
+        self.record_in_ledger(&evidence).await?;
+        
         Ok(())
     }
     
@@ -6,10 +6,10 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use icn_core::storage::JsonStorage;
-use icn_identity::{IdentityManager, IdentityProvider};
+use icn_core::storage::FileStorage;
+use icn_identity::MockIdentityProvider;
 use icn_ledger::{
-    Ledger, MutualCreditLedger, Account, Transaction, TransactionType, LedgerConfig,
+    Ledger, MutualCreditLedger, TransactionType, LedgerConfig,
 };
 
 #[tokio::main]
@@ -18,20 +18,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let storage_path = std::env::temp_dir().join("icn_ledger_example");
     println!("Using storage path: {:?}", storage_path);
     
-    // Create storage if it doesn't exist
-    std::fs::create_dir_all(&storage_path)?;
-    
-    // Initialize storage and identity provider
-    let storage = Arc::new(JsonStorage::new(storage_path.to_str().unwrap()));
-    let identity_provider = Arc::new(IdentityManager::new(storage.clone(), None).await?);
+    // Initialize storage, creating it if it doesn't exist, and an identity
+    // provider that signs as a single mock identity
+    let storage = Arc::new(FileStorage::new(&storage_path).await?);
+    let identity_provider = Arc::new(MockIdentityProvider::new());
     
     // Create the mutual credit ledger
-    let ledger_config = LedgerConfig::default();
     let ledger = MutualCreditLedger::new(
         identity_provider.clone(),
         storage.clone(),
-        ledger_config,
     ).await?;
+    ledger.set_config(LedgerConfig::default()).await?;
     
     // Create two accounts
     let alice_account = ledger.create_account(
@@ -93,7 +90,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ).await?;
     
     // Process the transaction
-    let processed_back = ledger.confirm_transaction(&transfer_back.id).await?;
+    ledger.confirm_transaction(&transfer_back.id).await?;
     
     // Check balances again
     let alice_balance = ledger.get_balance(&alice_account.id).await?;
@@ -5,18 +5,14 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use tracing::info;
 use serde::{Serialize, Deserialize};
 
 use icn_core::{
-    storage::{Storage, JsonStorage},
-    crypto::NodeId,
-    utils::timestamp_secs,
+    crypto::identity::NodeId,
 };
 
-use icn_identity::{
-    IdentityProvider, IdentityResult, Identity,
-};
+use icn_identity::IdentityProvider;
 
 use crate::{
     LedgerConfig, LedgerResult, LedgerError,
@@ -27,8 +23,6 @@ use crate::{
 pub struct AccountManager {
     /// Identity provider for authentication
     identity_provider: Arc<dyn IdentityProvider>,
-    /// Storage for account data
-    storage: Arc<dyn Storage>,
     /// Configuration
     config: LedgerConfig,
 }
@@ -37,12 +31,10 @@ impl AccountManager {
     /// Create a new account manager
     pub fn new(
         identity_provider: Arc<dyn IdentityProvider>,
-        storage: Arc<dyn Storage>,
         config: LedgerConfig,
     ) -> Self {
         Self {
             identity_provider,
-            storage,
             config,
         }
     }
@@ -119,7 +111,7 @@ impl AccountManager {
         let mut totals = HashMap::new();
         
         for account in accounts.values() {
-            if account.owner_id == *owner_id {
+            if account.owner_id == owner_id.as_str() {
                 let entry = totals.entry(account.currency.clone()).or_insert(0.0);
                 *entry += account.balance;
             }
@@ -153,7 +145,7 @@ impl AccountManager {
         accounts: &HashMap<String, Account>,
     ) -> Option<Account> {
         for account in accounts.values() {
-            if account.owner_id == *owner_id && account.currency == currency {
+            if account.owner_id == owner_id.as_str() && account.currency == currency {
                 return Some(account.clone());
             }
         }
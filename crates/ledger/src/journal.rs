@@ -0,0 +1,416 @@
+//! Append-only double-entry journal
+//!
+//! Every transaction that changes balances is posted to the journal as an
+//! entry of postings, one per account affected, that sum to zero in each
+//! currency. Entries are stored under `ledger/journal` by sequence number and
+//! never rewritten, and each carries the hash of the entry before it, so
+//! changes to stored entries are detected. Account balances are derived
+//! state: the journal is the record they can be rebuilt from and checked
+//! against.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tracing::debug;
+
+use icn_core::{
+    crypto::sha256,
+    storage::{Storage, StorageError},
+    utils::timestamp_secs,
+};
+
+/// Storage prefix for journal entries, keyed by sequence number
+pub const JOURNAL_PATH: &str = "ledger/journal";
+
+/// Account issued credit is posted against, so issuance stays balanced
+pub const ISSUANCE_ACCOUNT: &str = "system:issuance";
+
+/// Largest difference from zero treated as balanced
+pub const BALANCE_TOLERANCE: f64 = 0.000001;
+
+/// Errors from the journal
+#[derive(Debug, Error)]
+pub enum JournalError {
+    /// Postings don't sum to zero in every currency
+    #[error("Unbalanced journal entry: {0}")]
+    Unbalanced(String),
+
+    /// A transaction was already posted
+    #[error("Transaction already posted: {0}")]
+    AlreadyPosted(String),
+
+    /// Stored entries were changed, removed or reordered
+    #[error("Journal corrupted: {0}")]
+    Corrupted(String),
+
+    /// Storage error
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// Result type for journal operations
+pub type JournalResult<T> = Result<T, JournalError>;
+
+/// A change to one account's balance
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Posting {
+    /// The account posted to
+    pub account_id: String,
+    /// The currency of the amount
+    pub currency: String,
+    /// Amount credited (positive) or debited (negative)
+    pub amount: f64,
+}
+
+impl Posting {
+    /// Debit an amount from an account
+    pub fn debit(account_id: &str, currency: &str, amount: f64) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            currency: currency.to_string(),
+            amount: -amount,
+        }
+    }
+
+    /// Credit an amount to an account
+    pub fn credit(account_id: &str, currency: &str, amount: f64) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            currency: currency.to_string(),
+            amount,
+        }
+    }
+}
+
+/// The balanced postings of one transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Position of the entry in the journal, starting at 0
+    pub sequence: u64,
+    /// The transaction posted
+    pub transaction_id: String,
+    /// When the entry was posted (seconds since the epoch)
+    pub timestamp: u64,
+    /// Postings summing to zero in each currency
+    pub postings: Vec<Posting>,
+    /// Hash of the previous entry, empty for the first
+    pub previous_hash: String,
+    /// Hash of this entry's content
+    pub hash: String,
+}
+
+/// Content covered by an entry's hash
+#[derive(Serialize)]
+struct EntryPayload<'a> {
+    sequence: u64,
+    transaction_id: &'a str,
+    timestamp: u64,
+    postings: &'a [Posting],
+    previous_hash: &'a str,
+}
+
+impl JournalEntry {
+    /// Compute the hash of the entry's content
+    pub fn compute_hash(&self) -> JournalResult<String> {
+        let bytes = serde_json::to_vec(&EntryPayload {
+            sequence: self.sequence,
+            transaction_id: &self.transaction_id,
+            timestamp: self.timestamp,
+            postings: &self.postings,
+            previous_hash: &self.previous_hash,
+        })
+        .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+        Ok(sha256(&bytes).to_hex())
+    }
+
+    /// Check the postings sum to zero in every currency
+    pub fn check_balanced(&self) -> JournalResult<()> {
+        let mut totals: BTreeMap<&str, f64> = BTreeMap::new();
+        for posting in &self.postings {
+            *totals.entry(&posting.currency).or_insert(0.0) += posting.amount;
+        }
+        match totals.iter().find(|(_, total)| total.abs() > BALANCE_TOLERANCE) {
+            Some((currency, total)) => Err(JournalError::Unbalanced(format!(
+                "Postings for {} sum to {} {}", self.transaction_id, total, currency
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Balances by account and currency
+pub type Balances = BTreeMap<String, BTreeMap<String, f64>>;
+
+/// Sum of all balances in each currency
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrialBalance {
+    /// Sum of all balances by currency
+    pub totals: BTreeMap<String, f64>,
+}
+
+impl TrialBalance {
+    /// Sum balances by currency
+    pub fn of(balances: &Balances) -> Self {
+        let mut totals = BTreeMap::new();
+        for by_currency in balances.values() {
+            for (currency, balance) in by_currency {
+                *totals.entry(currency.clone()).or_insert(0.0) += balance;
+            }
+        }
+        Self { totals }
+    }
+
+    /// Check whether every currency sums to zero
+    pub fn is_balanced(&self) -> bool {
+        self.totals.values().all(|total| total.abs() <= BALANCE_TOLERANCE)
+    }
+
+    /// Get the currencies that don't sum to zero, with their totals
+    pub fn imbalances(&self) -> BTreeMap<String, f64> {
+        self.totals.iter()
+            .filter(|(_, total)| total.abs() > BALANCE_TOLERANCE)
+            .map(|(currency, total)| (currency.clone(), *total))
+            .collect()
+    }
+}
+
+/// An account balance that differs from the one rebuilt from the journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BalanceDiscrepancy {
+    /// The account
+    pub account_id: String,
+    /// The currency of the balance
+    pub currency: String,
+    /// The balance stored for the account
+    pub stored: f64,
+    /// The balance rebuilt from the journal
+    pub journal: f64,
+}
+
+/// Find the stored balances that differ from those rebuilt from the journal
+///
+/// Balances missing from either side count as zero.
+pub fn find_discrepancies(stored: &Balances, journal: &Balances) -> Vec<BalanceDiscrepancy> {
+    let balance = |balances: &Balances, account_id: &str, currency: &str| {
+        balances.get(account_id).and_then(|b| b.get(currency)).copied().unwrap_or(0.0)
+    };
+
+    let mut keys: Vec<(&String, &String)> = stored.iter()
+        .chain(journal.iter())
+        .flat_map(|(account_id, by_currency)| by_currency.keys().map(move |currency| (account_id, currency)))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .map(|(account_id, currency)| BalanceDiscrepancy {
+            account_id: account_id.clone(),
+            currency: currency.clone(),
+            stored: balance(stored, account_id, currency),
+            journal: balance(journal, account_id, currency),
+        })
+        .filter(|d| (d.stored - d.journal).abs() > BALANCE_TOLERANCE)
+        .collect()
+}
+
+/// Entries posted to a journal
+#[derive(Default)]
+struct JournalState {
+    /// Entries in sequence order
+    entries: Vec<JournalEntry>,
+    /// Sequence number of each posted transaction's entry
+    sequences: HashMap<String, u64>,
+}
+
+impl JournalState {
+    fn new(entries: Vec<JournalEntry>) -> Self {
+        let sequences = entries.iter()
+            .map(|e| (e.transaction_id.clone(), e.sequence))
+            .collect();
+        Self { entries, sequences }
+    }
+}
+
+/// The append-only journal of a ledger
+pub struct Journal {
+    /// Storage for journal entries
+    storage: Arc<dyn Storage>,
+    /// Entries posted, indexed by transaction
+    state: RwLock<JournalState>,
+}
+
+impl Journal {
+    /// Open the journal, loading and verifying stored entries
+    pub async fn open(storage: Arc<dyn Storage>) -> JournalResult<Self> {
+        let entries = Self::load(&*storage).await?;
+        debug!("Loaded {} journal entries", entries.len());
+        Ok(Self {
+            storage,
+            state: RwLock::new(JournalState::new(entries)),
+        })
+    }
+
+    /// Load stored entries and verify their sequence, hashes and balance
+    async fn load(storage: &dyn Storage) -> JournalResult<Vec<JournalEntry>> {
+        let mut keys = storage.list(JOURNAL_PATH).await?;
+        keys.sort();
+
+        let mut entries: Vec<JournalEntry> = Vec::with_capacity(keys.len());
+        for key in keys {
+            let data = storage.get(&key).await?;
+            let entry: JournalEntry = serde_json::from_slice(&data)
+                .map_err(|e| StorageError::DeserializationError(e.to_string()))?;
+            let previous_hash = entries.last().map(|e| e.hash.clone()).unwrap_or_default();
+            if entry.sequence != entries.len() as u64 {
+                return Err(JournalError::Corrupted(format!(
+                    "Expected entry {} but found {} at {}", entries.len(), entry.sequence, key
+                )));
+            }
+            if entry.previous_hash != previous_hash || entry.compute_hash()? != entry.hash {
+                return Err(JournalError::Corrupted(format!("Entry {} does not match its hash chain", entry.sequence)));
+            }
+            entry.check_balanced()?;
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// Append the postings of a transaction
+    ///
+    /// Fails without writing anything if the postings aren't balanced or the
+    /// transaction was already posted.
+    pub async fn post(&self, transaction_id: &str, postings: Vec<Posting>) -> JournalResult<JournalEntry> {
+        let mut state = self.state.write().await;
+        if state.sequences.contains_key(transaction_id) {
+            return Err(JournalError::AlreadyPosted(transaction_id.to_string()));
+        }
+
+        let mut entry = JournalEntry {
+            sequence: state.entries.len() as u64,
+            transaction_id: transaction_id.to_string(),
+            timestamp: timestamp_secs(),
+            postings,
+            previous_hash: state.entries.last().map(|e| e.hash.clone()).unwrap_or_default(),
+            hash: String::new(),
+        };
+        entry.check_balanced()?;
+        entry.hash = entry.compute_hash()?;
+
+        let key = format!("{}/{:020}", JOURNAL_PATH, entry.sequence);
+        let data = serde_json::to_vec(&entry)
+            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+        self.storage.put(&key, &data).await?;
+        debug!("Posted {} as journal entry {}", transaction_id, entry.sequence);
+        state.sequences.insert(entry.transaction_id.clone(), entry.sequence);
+        state.entries.push(entry.clone());
+        Ok(entry)
+    }
+
+    /// Check whether a transaction was posted
+    pub async fn is_posted(&self, transaction_id: &str) -> bool {
+        self.state.read().await.sequences.contains_key(transaction_id)
+    }
+
+    /// Get the entry a transaction was posted as
+    pub async fn entry_for(&self, transaction_id: &str) -> Option<JournalEntry> {
+        let state = self.state.read().await;
+        let sequence = *state.sequences.get(transaction_id)?;
+        state.entries.get(sequence as usize).cloned()
+    }
+
+    /// Get the number of entries posted
+    pub async fn len(&self) -> usize {
+        self.state.read().await.entries.len()
+    }
+
+    /// Check whether nothing was posted yet
+    pub async fn is_empty(&self) -> bool {
+        self.state.read().await.entries.is_empty()
+    }
+
+    /// Check the stored journal still matches the entries posted
+    pub async fn verify(&self) -> JournalResult<()> {
+        let stored = Self::load(&*self.storage).await?;
+        if stored != self.state.read().await.entries {
+            return Err(JournalError::Corrupted("Stored entries differ from those posted".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Rebuild every account's balances by replaying the journal
+    pub async fn balances(&self) -> Balances {
+        let mut balances = Balances::new();
+        for posting in self.state.read().await.entries.iter().flat_map(|e| &e.postings) {
+            *balances.entry(posting.account_id.clone())
+                .or_default()
+                .entry(posting.currency.clone())
+                .or_insert(0.0) += posting.amount;
+        }
+        balances
+    }
+
+    /// Sum the journal's balances in each currency
+    pub async fn trial_balance(&self) -> TrialBalance {
+        TrialBalance::of(&self.balances().await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icn_core::storage::{JsonStorage, MemoryStorage};
+
+    #[tokio::test]
+    async fn test_journal_balances_and_detects_tampering() {
+        let storage = Arc::new(MemoryStorage::new());
+        let journal = Journal::open(storage.clone()).await.unwrap();
+
+        journal.post("issue", vec![
+            Posting::debit(ISSUANCE_ACCOUNT, "ICN", 100.0),
+            Posting::credit("alice", "ICN", 100.0),
+        ]).await.unwrap();
+        journal.post("pay", vec![
+            Posting::debit("alice", "ICN", 30.0),
+            Posting::credit("bob", "ICN", 30.0),
+        ]).await.unwrap();
+
+        // Unbalanced and repeated postings are rejected
+        let unbalanced = vec![Posting::debit("alice", "ICN", 5.0), Posting::credit("bob", "HOURS", 5.0)];
+        assert!(matches!(journal.post("bad", unbalanced).await, Err(JournalError::Unbalanced(_))));
+        assert!(matches!(journal.post("pay", Vec::new()).await, Err(JournalError::AlreadyPosted(_))));
+
+        let balances = journal.balances().await;
+        assert_eq!(balances["alice"]["ICN"], 70.0);
+        assert_eq!(balances["bob"]["ICN"], 30.0);
+        assert!(journal.trial_balance().await.is_balanced());
+        journal.verify().await.unwrap();
+
+        // Stored balances are checked against the rebuilt ones
+        let mut stored = balances.clone();
+        stored.get_mut("bob").unwrap().insert("ICN".to_string(), 45.0);
+        let discrepancies = find_discrepancies(&stored, &balances);
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!((discrepancies[0].stored, discrepancies[0].journal), (45.0, 30.0));
+        assert_eq!(TrialBalance::of(&stored).imbalances()["ICN"], 15.0);
+
+        // Reopening replays the stored entries
+        let reopened = Journal::open(storage.clone()).await.unwrap();
+        assert_eq!(reopened.len().await, 2);
+        assert!(reopened.is_posted("issue").await && !reopened.is_posted("bad").await);
+        assert_eq!(reopened.entry_for("pay").await, journal.entry_for("pay").await);
+        assert_eq!(reopened.balances().await, balances);
+
+        // Rewriting a stored entry breaks the hash chain
+        let key = format!("{}/{:020}", JOURNAL_PATH, 1);
+        let mut entry: JournalEntry = storage.get_json(&key).await.unwrap();
+        entry.postings[1].amount = 300.0;
+        entry.postings[0].amount = -300.0;
+        storage.put_json(&key, &entry).await.unwrap();
+        assert!(matches!(journal.verify().await, Err(JournalError::Corrupted(_))));
+        assert!(Journal::open(storage).await.is_err());
+    }
+}
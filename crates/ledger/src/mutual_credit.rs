@@ -3,8 +3,7 @@
 //! This module provides an implementation of the Ledger trait
 //! for mutual credit accounting.
 
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
@@ -13,26 +12,27 @@ use async_trait::async_trait;
 
 use icn_core::{
     storage::{Storage, JsonStorage},
-    crypto::{NodeId, Signature},
+    crypto::identity::NodeId,
     utils::timestamp_secs,
 };
 
-use icn_identity::{
-    IdentityProvider, IdentityResult, Identity,
-};
+use icn_identity::IdentityProvider;
 
 use crate::{
     Ledger, LedgerConfig, LedgerResult, LedgerError,
     Account, Transaction, TransactionStatus, TransactionType,
     transaction_processor::TransactionProcessor,
     account_manager::AccountManager,
+    journal::{
+        find_discrepancies, Balances, BalanceDiscrepancy, Journal, JournalError, Posting, TrialBalance,
+        ISSUANCE_ACCOUNT,
+    },
 };
 
 /// Path constants for storage
 const CONFIG_PATH: &str = "ledger/config";
 const ACCOUNTS_PATH: &str = "ledger/accounts";
 const TRANSACTIONS_PATH: &str = "ledger/transactions";
-const ACCOUNT_TRANSACTIONS_PATH: &str = "ledger/account_transactions";
 
 /// The main implementation of the Ledger trait
 pub struct MutualCreditLedger {
@@ -54,6 +54,74 @@ pub struct MutualCreditLedger {
     transaction_processor: TransactionProcessor,
     /// Account manager for handling accounts
     account_manager: AccountManager,
+    /// Append-only journal the account balances are derived from
+    journal: Arc<Journal>,
+}
+
+/// Result of rebuilding account balances from the journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebuildReport {
+    /// Stored balances that differed from the journal and were replaced
+    pub discrepancies: Vec<BalanceDiscrepancy>,
+    /// Trial balance of the rebuilt balances
+    pub trial_balance: TrialBalance,
+}
+
+/// Convert a journal error to a ledger error
+fn journal_error(error: JournalError) -> LedgerError {
+    match error {
+        JournalError::Storage(e) => e.into(),
+        JournalError::Unbalanced(e) | JournalError::AlreadyPosted(e) => LedgerError::InvalidTransaction(e),
+        e @ JournalError::Corrupted(_) => LedgerError::InvalidTransaction(e.to_string()),
+    }
+}
+
+/// Check if confirming a transaction of a type changes balances
+fn moves_value(transaction_type: &TransactionType) -> bool {
+    matches!(
+        transaction_type,
+        TransactionType::Transfer | TransactionType::Clearing | TransactionType::Issuance
+    )
+}
+
+/// Get the recipient of a transaction that must have one
+fn recipient(transaction: &Transaction) -> LedgerResult<&str> {
+    transaction.to_account.as_deref().ok_or_else(|| {
+        LedgerError::InvalidTransaction(format!("{:?} transaction must have a recipient", transaction.transaction_type))
+    })
+}
+
+/// Get the balanced journal postings of a transaction that changes balances
+///
+/// A transfer or clearing debits the sender and credits the recipient, and
+/// an issuance debits the issuance account.
+fn postings_for(transaction: &Transaction) -> LedgerResult<Vec<Posting>> {
+    let currency = &transaction.currency;
+    let debited = match transaction.transaction_type {
+        TransactionType::Issuance => ISSUANCE_ACCOUNT,
+        _ => &transaction.from_account,
+    };
+    Ok(vec![
+        Posting::debit(debited, currency, transaction.amount),
+        Posting::credit(recipient(transaction)?, currency, transaction.amount),
+    ])
+}
+
+/// Get the staged copy of an account, staging it on first use
+fn stage_account<'a>(
+    accounts: &HashMap<String, Account>,
+    staged: &'a mut HashMap<String, Account>,
+    account_id: &str,
+) -> LedgerResult<&'a mut Account> {
+    match staged.entry(account_id.to_string()) {
+        Entry::Occupied(entry) => Ok(entry.into_mut()),
+        Entry::Vacant(entry) => {
+            let account = accounts.get(account_id).ok_or_else(|| {
+                LedgerError::AccountNotFound(account_id.to_string())
+            })?;
+            Ok(entry.insert(account.clone()))
+        },
+    }
 }
 
 impl MutualCreditLedger {
@@ -63,7 +131,7 @@ impl MutualCreditLedger {
         storage: Arc<dyn Storage>,
     ) -> LedgerResult<Self> {
         // Load configuration
-        let config = Self::load_config(&storage).await?;
+        let config = Self::load_config(&*storage).await?;
         
         let ledger = Self {
             identity_provider: identity_provider.clone(),
@@ -75,19 +143,19 @@ impl MutualCreditLedger {
             transactions_by_account: Arc::new(RwLock::new(HashMap::new())),
             transaction_processor: TransactionProcessor::new(
                 identity_provider.clone(),
-                storage.clone(),
                 config.clone(),
             ),
             account_manager: AccountManager::new(
                 identity_provider.clone(),
-                storage.clone(),
                 config.clone(),
             ),
+            journal: Arc::new(Journal::open(storage.clone()).await.map_err(journal_error)?),
         };
         
         // Load existing accounts and transactions
         ledger.load_accounts().await?;
         ledger.load_transactions().await?;
+        ledger.backfill_journal().await?;
         
         Ok(ledger)
     }
@@ -173,6 +241,31 @@ impl MutualCreditLedger {
         Ok(())
     }
     
+    /// Journal the stored confirmed transactions on first opening the journal
+    ///
+    /// Ledgers created before the journal existed hold confirmed transactions
+    /// that were never journaled. While the journal is empty, they are posted
+    /// in the order they were confirmed, so the journal covers every balance.
+    async fn backfill_journal(&self) -> LedgerResult<()> {
+        if !self.journal.is_empty().await {
+            return Ok(());
+        }
+
+        let mut confirmed: Vec<Transaction> = self.transactions.read().await.values()
+            .filter(|tx| tx.status == TransactionStatus::Confirmed && moves_value(&tx.transaction_type))
+            .cloned()
+            .collect();
+        confirmed.sort_by(|a, b| (a.confirmed_at, a.created_at, &a.id).cmp(&(b.confirmed_at, b.created_at, &b.id)));
+
+        for transaction in &confirmed {
+            self.journal.post(&transaction.id, postings_for(transaction)?).await.map_err(journal_error)?;
+        }
+        if !confirmed.is_empty() {
+            info!("Backfilled the journal with {} confirmed transactions", confirmed.len());
+        }
+        Ok(())
+    }
+    
     /// Save an account to storage
     async fn save_account(&self, account: &Account) -> LedgerResult<()> {
         // Save to storage
@@ -240,113 +333,166 @@ impl MutualCreditLedger {
     }
     
     /// Process a transaction to update account balances
+    ///
+    /// A transaction that was journaled by an earlier attempt which failed to
+    /// save it is confirmed again without being revalidated, since its
+    /// postings already count against the balances it was checked against.
     async fn process_transaction(&self, transaction_id: &str) -> LedgerResult<Transaction> {
-        // Use the transaction processor to process the transaction
-        let transaction = self.transaction_processor
-            .process_transaction(transaction_id, &*self.accounts, &*self.transactions)
-            .await?;
-        
-        // Save the updated transaction
-        self.save_transaction(&transaction).await?;
+        let transaction = if self.journal.is_posted(transaction_id).await {
+            let mut transaction = self.transactions.read().await.get(transaction_id).cloned()
+                .ok_or_else(|| LedgerError::TransactionNotFound(transaction_id.to_string()))?;
+            transaction.status = TransactionStatus::Confirmed;
+            transaction.confirmed_at.get_or_insert_with(timestamp_secs);
+            transaction
+        } else {
+            // Use the transaction processor to process the transaction
+            let accounts = self.accounts.read().await;
+            let transactions = self.transactions.read().await;
+            self.transaction_processor
+                .process_transaction(transaction_id, &accounts, &transactions)
+                .await?
+        };
         
-        // If transaction is confirmed, update account balances
+        // If transaction is confirmed, journal it and update account balances
+        // first, so it is only saved as confirmed once both succeeded
         if transaction.status == TransactionStatus::Confirmed {
-            match transaction.transaction_type {
-                TransactionType::Transfer => {
-                    self.update_balances_for_transfer(&transaction).await?;
-                },
-                TransactionType::Clearing => {
-                    self.update_balances_for_clearing(&transaction).await?;
-                },
-                TransactionType::Issuance => {
-                    self.update_balances_for_issuance(&transaction).await?;
-                },
-                _ => {
-                    // Other transaction types might not affect balances
-                }
-            }
+            self.apply_confirmed(&transaction).await?;
         }
         
+        // Save the updated transaction
+        self.save_transaction(&transaction).await?;
+        
         Ok(transaction)
     }
     
-    /// Update account balances for a transfer transaction
-    async fn update_balances_for_transfer(&self, transaction: &Transaction) -> LedgerResult<()> {
-        // Get accounts
-        let from_account_id = &transaction.from_account;
-        let to_account_id = transaction.to_account.as_ref().ok_or_else(|| {
-            LedgerError::InvalidTransaction("Transfer transaction must have a recipient".to_string())
-        })?;
-        
-        // Get and update the accounts
+    /// Journal a confirmed transaction and apply it to account balances
+    ///
+    /// The accounts write lock is held from the credit check until the new
+    /// balances are in place, so no other transaction can debit an account in
+    /// between. Balances are staged first, and only applied once the postings
+    /// are journaled, so the journal always covers them and balances can be
+    /// rebuilt from it if saving them fails.
+    ///
+    /// Replaying a transaction that is already journaled only saves the
+    /// accounts it affects again, so retrying after a failed save doesn't
+    /// apply it twice.
+    async fn apply_confirmed(&self, transaction: &Transaction) -> LedgerResult<()> {
+        if !moves_value(&transaction.transaction_type) {
+            return Ok(());
+        }
+
         let mut accounts = self.accounts.write().await;
-        
-        // Get from account
-        let from_account = accounts.get_mut(from_account_id).ok_or_else(|| {
-            LedgerError::AccountNotFound(from_account_id.clone())
-        })?;
-        
-        // Apply debit to from_account
-        from_account.apply_debit(transaction.amount, &transaction.id)?;
-        
-        // Get to account
-        let to_account = accounts.get_mut(to_account_id).ok_or_else(|| {
-            LedgerError::AccountNotFound(to_account_id.clone())
-        })?;
-        
-        // Apply credit to to_account
-        to_account.apply_credit(transaction.amount, &transaction.id);
-        
-        // Save the updated accounts
-        let from_account_clone = from_account.clone();
-        let to_account_clone = to_account.clone();
-        
-        // Release the write lock before saving
-        drop(accounts);
-        
-        // Save the accounts
-        self.save_account(&from_account_clone).await?;
-        self.save_account(&to_account_clone).await?;
-        
+        let debits_sender = matches!(
+            transaction.transaction_type,
+            TransactionType::Transfer | TransactionType::Clearing
+        );
+        let mut affected = vec![recipient(transaction)?];
+        if debits_sender {
+            affected.push(&transaction.from_account);
+        }
+
+        if self.journal.is_posted(&transaction.id).await {
+            debug!("Transaction {} is already journaled, saving its accounts again", transaction.id);
+        } else {
+            let mut staged = HashMap::new();
+            if debits_sender {
+                stage_account(&accounts, &mut staged, &transaction.from_account)?
+                    .apply_debit(transaction.amount, &transaction.id)?;
+            }
+            stage_account(&accounts, &mut staged, recipient(transaction)?)?
+                .apply_credit(transaction.amount, &transaction.id);
+
+            self.journal.post(&transaction.id, postings_for(transaction)?).await.map_err(journal_error)?;
+            accounts.extend(staged);
+        }
+
+        for account_id in affected {
+            let account = accounts.get(account_id).ok_or_else(|| {
+                LedgerError::AccountNotFound(account_id.to_string())
+            })?;
+            let path = format!("{}/{}", ACCOUNTS_PATH, account.id);
+            self.storage.put_json(&path, account).await?;
+        }
         Ok(())
     }
-    
-    /// Update account balances for a clearing transaction
-    async fn update_balances_for_clearing(&self, transaction: &Transaction) -> LedgerResult<()> {
-        // Clearing is similar to transfer, but the amount is already calculated as the mutual debt
-        self.update_balances_for_transfer(transaction).await
+
+    /// Get the stored balance of every account, by currency
+    async fn stored_balances(&self) -> Balances {
+        let accounts = self.accounts.read().await;
+        let mut balances = Balances::new();
+        for account in accounts.values() {
+            balances.entry(account.id.clone())
+                .or_default()
+                .insert(account.currency.clone(), account.balance);
+        }
+        balances
     }
-    
-    /// Update account balances for an issuance transaction
-    async fn update_balances_for_issuance(&self, transaction: &Transaction) -> LedgerResult<()> {
-        // Issuance only credits the recipient account
-        let to_account_id = transaction.to_account.as_ref().ok_or_else(|| {
-            LedgerError::InvalidTransaction("Issuance transaction must have a recipient".to_string())
-        })?;
-        
-        // Get and update the accounts
-        let mut accounts = self.accounts.write().await;
-        
-        // Get to account
-        let to_account = accounts.get_mut(to_account_id).ok_or_else(|| {
-            LedgerError::AccountNotFound(to_account_id.clone())
-        })?;
-        
-        // Apply credit to to_account
-        to_account.apply_credit(transaction.amount, &transaction.id);
-        
-        // Save the updated account
-        let to_account_clone = to_account.clone();
-        
-        // Release the write lock before saving
-        drop(accounts);
-        
-        // Save the account
-        self.save_account(&to_account_clone).await?;
-        
-        Ok(())
+
+    /// Check the sum of all balances is zero in each currency
+    ///
+    /// Sums the stored account balances together with the balances of
+    /// system accounts, such as issuance, which only exist in the journal.
+    pub async fn trial_balance(&self) -> LedgerResult<TrialBalance> {
+        let mut balances = self.stored_balances().await;
+        for (account_id, by_currency) in self.journal.balances().await {
+            balances.entry(account_id).or_insert(by_currency);
+        }
+        Ok(TrialBalance::of(&balances))
     }
-    
+
+    /// Rebuild every account's balance from the journal
+    ///
+    /// Verifies the stored journal hasn't been changed, then replaces each
+    /// stored balance that differs from the journal's and reports the
+    /// differences, which show where balances were corrupted. Refuses to
+    /// rebuild while any confirmed transaction is missing from the journal,
+    /// since the rebuilt balances would leave it out.
+    pub async fn rebuild_balances(&self) -> LedgerResult<RebuildReport> {
+        self.journal.verify().await.map_err(journal_error)?;
+        let mut missing = Vec::new();
+        for tx in self.transactions.read().await.values() {
+            if tx.status == TransactionStatus::Confirmed
+                && moves_value(&tx.transaction_type)
+                && !self.journal.is_posted(&tx.id).await
+            {
+                missing.push(tx.id.clone());
+            }
+        }
+        if !missing.is_empty() {
+            return Err(LedgerError::InvalidTransaction(format!(
+                "Cannot rebuild balances: {} confirmed transactions are missing from the journal, including {}",
+                missing.len(), missing[0]
+            )));
+        }
+
+        let rebuilt = self.journal.balances().await;
+        let discrepancies = find_discrepancies(&self.stored_balances().await, &rebuilt);
+
+        for discrepancy in &discrepancies {
+            let mut accounts = self.accounts.write().await;
+            let Some(account) = accounts.get_mut(&discrepancy.account_id) else {
+                continue;
+            };
+            if account.currency != discrepancy.currency {
+                continue;
+            }
+            warn!(
+                "Balance of {} was {} {} but the journal has {}",
+                account.id, discrepancy.stored, discrepancy.currency, discrepancy.journal
+            );
+            account.balance = discrepancy.journal;
+            account.updated_at = timestamp_secs();
+            let account_clone = account.clone();
+            drop(accounts);
+            self.save_account(&account_clone).await?;
+        }
+
+        Ok(RebuildReport {
+            discrepancies,
+            trial_balance: TrialBalance::of(&rebuilt),
+        })
+    }
+
     /// Calculate and clear mutual debt between two accounts
     async fn calculate_mutual_debt(&self, account1_id: &str, account2_id: &str) -> LedgerResult<Option<f64>> {
         // Get the accounts
@@ -606,7 +752,7 @@ impl Ledger for MutualCreditLedger {
         let currency = currency.unwrap_or_else(|| from_account_obj.currency.clone());
         
         // Validate to_account if provided
-        let to_account_obj = if let Some(to_id) = to_account {
+        if let Some(to_id) = to_account {
             let to_account = accounts.get(to_id).ok_or_else(|| {
                 LedgerError::AccountNotFound(to_id.to_string())
             })?;
@@ -618,11 +764,7 @@ impl Ledger for MutualCreditLedger {
                             currency, to_account.currency)
                 ));
             }
-            
-            Some(to_account)
-        } else {
-            None
-        };
+        }
         
         // Get config for limits
         let config = self.config.read().await;
@@ -642,6 +784,13 @@ impl Ledger for MutualCreditLedger {
             ));
         }
         
+        // Release the locks, since processing the transaction takes the
+        // accounts write lock
+        let process_now = !config.require_counter_signatures &&
+            transaction_type != TransactionType::Transfer;
+        drop(config);
+        drop(accounts);
+        
         // Create the transaction
         let mut transaction = Transaction::new(
             transaction_type,
@@ -663,8 +812,7 @@ impl Ledger for MutualCreditLedger {
         self.save_transaction(&transaction).await?;
         
         // If counter-signatures are not required, process it immediately for certain types
-        if !config.require_counter_signatures && 
-           transaction_type != TransactionType::Transfer {
+        if process_now {
             // Process the transaction
             transaction = self.process_transaction(&transaction.id).await?;
         }
@@ -699,7 +847,7 @@ impl Ledger for MutualCreditLedger {
         }
         
         // Sort by creation time, newest first
-        result.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        result.sort_by_key(|tx| std::cmp::Reverse(tx.created_at));
         
         Ok(result)
     }
@@ -855,9 +1003,9 @@ impl Ledger for MutualCreditLedger {
         
         // Get account information for currency
         let accounts = self.accounts.read().await;
-        let from_account = accounts.get(from_id).ok_or_else(|| {
+        let currency = accounts.get(from_id).ok_or_else(|| {
             LedgerError::AccountNotFound(from_id.to_string())
-        })?;
+        })?.currency.clone();
         
         // Release the read lock
         drop(accounts);
@@ -874,8 +1022,8 @@ impl Ledger for MutualCreditLedger {
             from_id,
             Some(to_id),
             amount,
-            Some(from_account.currency.clone()),
-            format!("Clearing mutual debt between accounts"),
+            Some(currency),
+            "Clearing mutual debt between accounts".to_string(),
             metadata,
             Vec::new(),
         ).await?;
@@ -5,16 +5,14 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use tracing::debug;
 
 use icn_core::{
-    storage::{Storage, JsonStorage},
-    crypto::{NodeId, Signature, verify_signature},
     utils::timestamp_secs,
 };
 
 use icn_identity::{
-    IdentityProvider, IdentityResult, Identity,
+    IdentityProvider, Identity,
 };
 
 use crate::{
@@ -26,8 +24,6 @@ use crate::{
 pub struct TransactionProcessor {
     /// Identity provider for signature verification
     identity_provider: Arc<dyn IdentityProvider>,
-    /// Storage for transaction data
-    storage: Arc<dyn Storage>,
     /// Configuration
     config: LedgerConfig,
 }
@@ -36,12 +32,10 @@ impl TransactionProcessor {
     /// Create a new transaction processor
     pub fn new(
         identity_provider: Arc<dyn IdentityProvider>,
-        storage: Arc<dyn Storage>,
         config: LedgerConfig,
     ) -> Self {
         Self {
             identity_provider,
-            storage,
             config,
         }
     }
@@ -202,7 +196,7 @@ impl TransactionProcessor {
     }
     
     /// Get account owner identity
-    async fn get_account_owner(&self, account_id: &str) -> LedgerResult<Identity> {
+    async fn get_account_owner(&self, _account_id: &str) -> LedgerResult<Identity> {
         // In a real implementation, we would look up the account and then get its owner
         // For now, we'll just get the current identity
         let identity = self.identity_provider.get_identity().await?;
@@ -231,13 +225,6 @@ impl TransactionProcessor {
             ));
         }
         
-        // Check credit limit
-        if !from_account.can_debit(transaction.amount) {
-            return Err(LedgerError::CreditLimitExceeded(
-                format!("Transfer would exceed credit limit of {}", from_account.credit_limit)
-            ));
-        }
-        
         // Check transaction amount limit
         if transaction.amount > self.config.max_transaction_amount {
             return Err(LedgerError::InvalidTransaction(
@@ -246,6 +233,13 @@ impl TransactionProcessor {
             ));
         }
         
+        // Check credit limit
+        if !from_account.can_debit(transaction.amount) {
+            return Err(LedgerError::CreditLimitExceeded(
+                format!("Transfer would exceed credit limit of {}", from_account.credit_limit)
+            ));
+        }
+        
         Ok(())
     }
     
@@ -358,7 +352,7 @@ impl TransactionProcessor {
     }
     
     /// Calculate transaction fee (if applicable)
-    pub fn calculate_fee(&self, transaction: &Transaction) -> f64 {
+    pub fn calculate_fee(&self, _transaction: &Transaction) -> f64 {
         // In this simple implementation, we don't charge fees
         // This is a placeholder for a real fee calculation
         0.0
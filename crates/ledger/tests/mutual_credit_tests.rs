@@ -0,0 +1,216 @@
+//! Tests for the mutual credit ledger
+//!
+//! These tests verify that confirmed transactions are journaled together
+//! with their balance changes, and that balances can be rebuilt from the
+//! journal, including for ledgers that predate it, and that confirming
+//! again after a failed save doesn't apply a transaction twice.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_trait::async_trait;
+
+use icn_core::storage::{JsonStorage, MockStorage, Storage, StorageError, StorageResult};
+
+use icn_identity::MockIdentityProvider;
+
+use icn_ledger::{
+    Account, Ledger, MutualCreditLedger, Transaction, TransactionStatus, TransactionType,
+    journal::JOURNAL_PATH,
+};
+
+#[tokio::test]
+async fn test_confirmed_transfer_is_journaled_with_balances() {
+    let (ledger, _storage, from, to) = setup_ledger().await;
+
+    let transaction = transfer(&ledger, &from, &to, 40.0).await;
+    let confirmed = ledger.confirm_transaction(&transaction.id).await.unwrap();
+    assert_eq!(confirmed.status, TransactionStatus::Confirmed);
+
+    assert_eq!(ledger.get_balance(&from.id).await.unwrap(), -40.0);
+    assert_eq!(ledger.get_balance(&to.id).await.unwrap(), 40.0);
+    assert!(ledger.trial_balance().await.unwrap().is_balanced());
+
+    // The journal already matches the stored balances
+    let report = ledger.rebuild_balances().await.unwrap();
+    assert!(report.discrepancies.is_empty());
+    assert!(report.trial_balance.is_balanced());
+}
+
+#[tokio::test]
+async fn test_transfer_over_credit_limit_is_not_confirmed() {
+    let (ledger, _storage, from, to) = setup_ledger().await;
+
+    // Both transfers pass the credit check on their own, but not together
+    let first = transfer(&ledger, &from, &to, 80.0).await;
+    let second = transfer(&ledger, &from, &to, 80.0).await;
+    ledger.confirm_transaction(&first.id).await.unwrap();
+    let result = ledger.confirm_transaction(&second.id).await;
+    assert!(result.is_err() || result.unwrap().status != TransactionStatus::Confirmed);
+
+    let stored = ledger.get_transaction(&second.id).await.unwrap().unwrap();
+    assert_ne!(stored.status, TransactionStatus::Confirmed);
+    assert_eq!(ledger.get_balance(&from.id).await.unwrap(), -80.0);
+    assert_eq!(ledger.get_balance(&to.id).await.unwrap(), 80.0);
+
+    let report = ledger.rebuild_balances().await.unwrap();
+    assert!(report.discrepancies.is_empty());
+}
+
+#[tokio::test]
+async fn test_journal_is_backfilled_on_first_open() {
+    let (ledger, storage, from, to) = setup_ledger().await;
+    let transaction = transfer(&ledger, &from, &to, 25.0).await;
+    ledger.confirm_transaction(&transaction.id).await.unwrap();
+    drop(ledger);
+
+    // Remove the journal, as for a ledger created before it existed
+    for key in storage.list(JOURNAL_PATH).await.unwrap() {
+        storage.delete(&key).await.unwrap();
+    }
+
+    let reopened = open_ledger(storage.clone()).await;
+    assert!(!storage.list(JOURNAL_PATH).await.unwrap().is_empty());
+
+    let report = reopened.rebuild_balances().await.unwrap();
+    assert!(report.discrepancies.is_empty());
+    assert_eq!(reopened.get_balance(&from.id).await.unwrap(), -25.0);
+    assert_eq!(reopened.get_balance(&to.id).await.unwrap(), 25.0);
+}
+
+#[tokio::test]
+async fn test_rebuild_refuses_when_transactions_are_missing_from_journal() {
+    let (ledger, storage, from, to) = setup_ledger().await;
+    let journaled = transfer(&ledger, &from, &to, 10.0).await;
+    ledger.confirm_transaction(&journaled.id).await.unwrap();
+    let unjournaled = transfer(&ledger, &from, &to, 20.0).await;
+    drop(ledger);
+
+    // Mark a transaction confirmed in storage without journaling it
+    let key = format!("ledger/transactions/{}", unjournaled.id);
+    let mut stored: Transaction = storage.get_json(&key).await.unwrap();
+    stored.status = TransactionStatus::Confirmed;
+    stored.confirmed_at = Some(stored.created_at);
+    storage.put_json(&key, &stored).await.unwrap();
+
+    // The journal isn't empty, so it isn't backfilled
+    let reopened = open_ledger(storage.clone()).await;
+    assert!(reopened.rebuild_balances().await.is_err());
+    assert_eq!(reopened.get_balance(&from.id).await.unwrap(), -10.0);
+}
+
+#[tokio::test]
+async fn test_confirm_retried_after_failed_save_is_applied_once() {
+    let storage = Arc::new(FlakyStorage::new());
+    let identity_provider = Arc::new(MockIdentityProvider::new());
+    let ledger = MutualCreditLedger::new(identity_provider, storage.clone()).await.unwrap();
+    let from = ledger.create_account("From".to_string(), None, Some(100.0), HashMap::new()).await.unwrap();
+    let to = ledger.create_account("To".to_string(), None, Some(100.0), HashMap::new()).await.unwrap();
+    let transaction = transfer(&ledger, &from, &to, 30.0).await;
+
+    // The transfer is journaled and applied, but saving it as confirmed fails
+    storage.fail_next_transaction_write.store(true, Ordering::SeqCst);
+    assert!(ledger.confirm_transaction(&transaction.id).await.is_err());
+    let stored = ledger.get_transaction(&transaction.id).await.unwrap().unwrap();
+    assert_eq!(stored.status, TransactionStatus::Pending);
+
+    // Retrying confirms it without applying it again
+    let confirmed = ledger.confirm_transaction(&transaction.id).await.unwrap();
+    assert_eq!(confirmed.status, TransactionStatus::Confirmed);
+    assert_eq!(ledger.get_balance(&from.id).await.unwrap(), -30.0);
+    assert_eq!(ledger.get_balance(&to.id).await.unwrap(), 30.0);
+
+    let report = ledger.rebuild_balances().await.unwrap();
+    assert!(report.discrepancies.is_empty());
+    assert!(report.trial_balance.is_balanced());
+}
+
+/// Storage that can fail the next write of a transaction
+struct FlakyStorage {
+    inner: MockStorage,
+    fail_next_transaction_write: AtomicBool,
+}
+
+impl FlakyStorage {
+    fn new() -> Self {
+        Self {
+            inner: MockStorage::new(),
+            fail_next_transaction_write: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for FlakyStorage {
+    async fn put(&self, key: &str, data: &[u8]) -> StorageResult<()> {
+        if key.starts_with("ledger/transactions/") && self.fail_next_transaction_write.swap(false, Ordering::SeqCst) {
+            return Err(StorageError::Other(format!("Failed to write {}", key)));
+        }
+        self.inner.put(key, data).await
+    }
+
+    async fn get(&self, key: &str) -> StorageResult<Vec<u8>> {
+        self.inner.get(key).await
+    }
+
+    async fn delete(&self, key: &str) -> StorageResult<()> {
+        self.inner.delete(key).await
+    }
+
+    async fn exists(&self, key: &str) -> StorageResult<bool> {
+        self.inner.exists(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> StorageResult<Vec<String>> {
+        self.inner.list(prefix).await
+    }
+
+    fn base_path(&self) -> Option<PathBuf> {
+        self.inner.base_path()
+    }
+}
+
+async fn setup_ledger() -> (MutualCreditLedger, Arc<MockStorage>, Account, Account) {
+    let storage = Arc::new(MockStorage::new());
+    let ledger = open_ledger(storage.clone()).await;
+
+    let from = ledger.create_account(
+        "From".to_string(),
+        None,
+        Some(100.0),
+        HashMap::new(),
+    ).await.unwrap();
+    let to = ledger.create_account(
+        "To".to_string(),
+        None,
+        Some(100.0),
+        HashMap::new(),
+    ).await.unwrap();
+
+    (ledger, storage, from, to)
+}
+
+async fn open_ledger(storage: Arc<MockStorage>) -> MutualCreditLedger {
+    let identity_provider = Arc::new(MockIdentityProvider::new());
+    MutualCreditLedger::new(identity_provider, storage).await.unwrap()
+}
+
+async fn transfer(
+    ledger: &MutualCreditLedger,
+    from: &Account,
+    to: &Account,
+    amount: f64,
+) -> Transaction {
+    ledger.create_transaction(
+        TransactionType::Transfer,
+        &from.id,
+        Some(&to.id),
+        amount,
+        None,
+        "Test transfer".to_string(),
+        HashMap::new(),
+        Vec::new(),
+    ).await.unwrap()
+}
@@ -6,25 +6,19 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use icn_core::{
-    storage::{StorageError, MockStorage},
-    crypto::{NodeId, Signature},
-    utils::timestamp_secs,
-};
+use icn_core::utils::timestamp_secs;
 
-use icn_identity::{
-    Identity, IdentityResult, MockIdentityProvider,
-};
+use icn_identity::MockIdentityProvider;
 
 use icn_ledger::{
     Transaction, Account, TransactionType, TransactionStatus, LedgerConfig,
-    LedgerError, LedgerResult, transaction_processor::TransactionProcessor,
+    transaction_processor::TransactionProcessor,
 };
 
 #[tokio::test]
 async fn test_process_valid_transfer() {
     // Set up test environment
-    let (identity_provider, storage, processor, mut accounts, mut transactions) = setup_test_env().await;
+    let (processor, accounts, mut transactions) = setup_test_env().await;
     
     // Create a valid transfer transaction
     let from_account = accounts.values().next().unwrap().clone();
@@ -57,7 +51,7 @@ async fn test_process_valid_transfer() {
 #[tokio::test]
 async fn test_process_invalid_transfer_wrong_currency() {
     // Set up test environment
-    let (identity_provider, storage, processor, mut accounts, mut transactions) = setup_test_env().await;
+    let (processor, mut accounts, mut transactions) = setup_test_env().await;
     
     // Create two accounts with different currencies
     let from_account = accounts.values().next().unwrap().clone();
@@ -95,7 +89,7 @@ async fn test_process_invalid_transfer_wrong_currency() {
 #[tokio::test]
 async fn test_process_invalid_transfer_exceeds_credit_limit() {
     // Set up test environment
-    let (identity_provider, storage, processor, mut accounts, mut transactions) = setup_test_env().await;
+    let (processor, accounts, mut transactions) = setup_test_env().await;
     
     // Create a transfer transaction that exceeds credit limit
     let from_account = accounts.values().next().unwrap().clone();
@@ -130,7 +124,7 @@ async fn test_process_invalid_transfer_exceeds_credit_limit() {
 #[tokio::test]
 async fn test_validate_credit_limit_adjustment() {
     // Set up test environment
-    let (identity_provider, storage, processor, mut accounts, mut transactions) = setup_test_env().await;
+    let (processor, accounts, mut transactions) = setup_test_env().await;
     
     // Create a credit limit adjustment transaction
     let account = accounts.values().next().unwrap().clone();
@@ -173,7 +167,7 @@ async fn test_validate_credit_limit_adjustment() {
 #[tokio::test]
 async fn test_transaction_receipt_generation() {
     // Set up test environment
-    let (identity_provider, storage, processor, mut accounts, mut transactions) = setup_test_env().await;
+    let (processor, accounts, _transactions) = setup_test_env().await;
     
     // Create a confirmed transaction
     let from_account = accounts.values().next().unwrap().clone();
@@ -206,15 +200,12 @@ async fn test_transaction_receipt_generation() {
 
 /// Set up a test environment with mock dependencies
 async fn setup_test_env() -> (
-    Arc<MockIdentityProvider>,
-    Arc<MockStorage>,
     TransactionProcessor,
     HashMap<String, Account>,
     HashMap<String, Transaction>,
 ) {
     // Create mocks
     let identity_provider = Arc::new(MockIdentityProvider::new());
-    let storage = Arc::new(MockStorage::new());
     
     // Create config
     let config = LedgerConfig {
@@ -228,8 +219,7 @@ async fn setup_test_env() -> (
     
     // Create processor
     let processor = TransactionProcessor::new(
-        identity_provider.clone(),
-        storage.clone(),
+        identity_provider,
         config,
     );
     
@@ -269,7 +259,7 @@ async fn setup_test_env() -> (
     // Empty transactions
     let transactions = HashMap::new();
     
-    (identity_provider, storage, processor, accounts, transactions)
+    (processor, accounts, transactions)
 }
 
 /// Create a test transfer transaction
@@ -17,6 +17,7 @@ use crate::{
     VoteAnnouncement, 
     CustomMessage
 };
+use crate::reputation::ReputationEvidence;
 
 /// Convert from a Core NetworkMessage to a Network crate NetworkMessage
 pub fn core_to_network_message(core_msg: CoreNetworkMessage) -> NetworkMessage {
@@ -87,6 +88,14 @@ pub fn core_to_network_message(core_msg: CoreNetworkMessage) -> NetworkMessage {
             };
             NetworkMessage::VoteAnnouncement(vote)
         },
+        "reputation.evidence" => match serde_json::from_value::<ReputationEvidence>(payload_json.clone()) {
+            Ok(evidence) => NetworkMessage::ReputationEvidence(evidence),
+            // Malformed evidence is passed on as is and rejected by its handlers
+            Err(_) => NetworkMessage::Custom(CustomMessage {
+                message_type: core_msg.message_type,
+                data: payload_json.as_object().cloned().unwrap_or_default(),
+            }),
+        },
         _ => {
             // Treat as custom message
             let mut data = serde_json::Map::new();
@@ -163,6 +172,9 @@ pub fn network_to_core_message(network_msg: NetworkMessage, sender: &str) -> Cor
             
             ("governance.vote".to_string(), serde_json::to_vec(&payload).unwrap_or_default())
         },
+        NetworkMessage::ReputationEvidence(evidence) => {
+            ("reputation.evidence".to_string(), serde_json::to_vec(&evidence).unwrap_or_default())
+        },
         NetworkMessage::Custom(custom) => {
             (custom.message_type, serde_json::to_vec(&custom.data).unwrap_or_default())
         }
@@ -0,0 +1,367 @@
+//! Persistent synchronization checkpoints for ICN
+//!
+//! The synchronizer records how far it got with each peer (a high-water
+//! mark over transaction timestamps plus any entries still waiting to be
+//! fetched) and a state hash for each sync domain. Checkpoints live in the
+//! node's storage, so a restarted node resumes where it left off instead of
+//! re-running a full sync.
+
+use std::sync::Arc;
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use icn_core::crypto::sha256;
+use icn_core::storage::{Storage, StorageError};
+
+use crate::fetch::ObjectKind;
+use crate::reconcile::{self, IndexEntry, TimeRange};
+use crate::sync::SyncState;
+use crate::{NetworkError, NetworkResult};
+
+/// Storage prefix for per-peer checkpoints
+const PEER_PREFIX: &str = "sync/checkpoints/peers/";
+
+/// Storage prefix for per-domain checkpoints
+const DOMAIN_PREFIX: &str = "sync/checkpoints/domains/";
+
+/// Data domains kept in sync between nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SyncDomain {
+    /// Ledger transactions
+    Ledger,
+    /// Governance proposals and votes
+    Governance,
+    /// Identity records
+    Identity,
+}
+
+impl SyncDomain {
+    /// All sync domains
+    pub const ALL: [SyncDomain; 3] = [SyncDomain::Ledger, SyncDomain::Governance, SyncDomain::Identity];
+
+    /// Get the name used in storage keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncDomain::Ledger => "ledger",
+            SyncDomain::Governance => "governance",
+            SyncDomain::Identity => "identity",
+        }
+    }
+}
+
+/// Sync progress with a single peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerCheckpoint {
+    /// Peer ID
+    pub peer_id: String,
+    /// Transactions up to this timestamp have been fully synced from the peer
+    pub high_water_mark: u64,
+    /// Latest transaction timestamp the peer is known to have
+    pub latest_seen: u64,
+    /// Entries found missing but not yet fetched
+    pub pending: Vec<IndexEntry>,
+    /// When the last sync with the peer completed (seconds since the epoch)
+    pub last_synced: Option<u64>,
+}
+
+impl PeerCheckpoint {
+    /// Create an empty checkpoint for a peer
+    pub fn new(peer_id: impl Into<String>) -> Self {
+        Self {
+            peer_id: peer_id.into(),
+            high_water_mark: 0,
+            latest_seen: 0,
+            pending: Vec::new(),
+            last_synced: None,
+        }
+    }
+
+    /// Add entries to the pending list, ignoring ones already pending
+    pub fn add_pending(&mut self, entries: Vec<IndexEntry>) {
+        for entry in entries {
+            if !self.pending.iter().any(|e| e.transaction_id == entry.transaction_id) {
+                self.pending.push(entry);
+            }
+        }
+    }
+
+    /// Remove an entry from the pending list
+    pub fn remove_pending(&mut self, transaction_id: &str) {
+        self.pending.retain(|e| e.transaction_id != transaction_id);
+    }
+
+    /// Get how far (in seconds) we lag behind the peer's known transactions
+    pub fn lag(&self) -> u64 {
+        self.latest_seen.saturating_sub(self.high_water_mark)
+    }
+}
+
+/// Sync checkpoint of a single domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainCheckpoint {
+    /// The domain
+    pub domain: SyncDomain,
+    /// Hash of the local domain state at the last completed sync
+    pub state_hash: Option<String>,
+    /// Whether a sync of this domain was started but not completed
+    pub in_progress: bool,
+    /// When the checkpoint was last updated (seconds since the epoch)
+    pub updated_at: u64,
+}
+
+impl DomainCheckpoint {
+    /// Create an empty checkpoint for a domain
+    pub fn new(domain: SyncDomain) -> Self {
+        Self {
+            domain,
+            state_hash: None,
+            in_progress: false,
+            updated_at: 0,
+        }
+    }
+}
+
+/// Lag report for a single peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerLag {
+    /// Peer ID
+    pub peer_id: String,
+    /// Transactions up to this timestamp have been synced from the peer
+    pub high_water_mark: u64,
+    /// Latest transaction timestamp the peer is known to have
+    pub latest_seen: u64,
+    /// Seconds of the peer's history not yet synced
+    pub lag_secs: u64,
+    /// Number of entries waiting to be fetched from the peer
+    pub pending: usize,
+}
+
+impl From<&PeerCheckpoint> for PeerLag {
+    fn from(checkpoint: &PeerCheckpoint) -> Self {
+        Self {
+            peer_id: checkpoint.peer_id.clone(),
+            high_water_mark: checkpoint.high_water_mark,
+            latest_seen: checkpoint.latest_seen,
+            lag_secs: checkpoint.lag(),
+            pending: checkpoint.pending.len(),
+        }
+    }
+}
+
+/// Report of the synchronizer's progress
+#[derive(Debug, Clone)]
+pub struct SyncProgress {
+    /// Current sync state
+    pub state: SyncState,
+    /// Items fetched since the synchronizer started
+    pub items_fetched: u64,
+    /// Items known to be missing and not yet fetched
+    pub items_remaining: u64,
+    /// Per-peer lag
+    pub peers: Vec<PeerLag>,
+    /// Per-domain checkpoints
+    pub domains: Vec<DomainCheckpoint>,
+}
+
+/// Checkpoint store backed by node storage
+#[derive(Clone)]
+pub struct CheckpointStore {
+    /// Storage layer
+    storage: Arc<dyn Storage>,
+}
+
+impl CheckpointStore {
+    /// Create a new checkpoint store
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Load a value, returning `None` if it does not exist
+    async fn load<T: DeserializeOwned>(&self, key: &str) -> NetworkResult<Option<T>> {
+        match self.storage.get(key).await {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| NetworkError::StorageError(StorageError::DeserializationError(e.to_string()))),
+            Err(StorageError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store a value
+    async fn save<T: Serialize>(&self, key: &str, value: &T) -> NetworkResult<()> {
+        let data = serde_json::to_vec(value)
+            .map_err(|e| NetworkError::StorageError(StorageError::SerializationError(e.to_string())))?;
+        self.storage.put(key, &data).await?;
+        Ok(())
+    }
+
+    /// Load the checkpoint for a peer, or an empty one if none exists
+    pub async fn load_peer(&self, peer_id: &str) -> NetworkResult<PeerCheckpoint> {
+        Ok(self.load(&format!("{}{}", PEER_PREFIX, peer_id)).await?
+            .unwrap_or_else(|| PeerCheckpoint::new(peer_id)))
+    }
+
+    /// Store the checkpoint for a peer
+    pub async fn save_peer(&self, checkpoint: &PeerCheckpoint) -> NetworkResult<()> {
+        self.save(&format!("{}{}", PEER_PREFIX, checkpoint.peer_id), checkpoint).await
+    }
+
+    /// Load all stored peer checkpoints
+    pub async fn load_peers(&self) -> NetworkResult<Vec<PeerCheckpoint>> {
+        let mut keys = self.storage.list(PEER_PREFIX).await?;
+        keys.sort();
+
+        let mut checkpoints = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(checkpoint) = self.load(&key).await? {
+                checkpoints.push(checkpoint);
+            }
+        }
+
+        Ok(checkpoints)
+    }
+
+    /// Record the latest transaction timestamp seen from a peer
+    pub async fn observe_peer(&self, peer_id: &str, timestamp: u64) -> NetworkResult<()> {
+        let mut checkpoint = self.load_peer(peer_id).await?;
+        if timestamp > checkpoint.latest_seen {
+            checkpoint.latest_seen = timestamp;
+            self.save_peer(&checkpoint).await?;
+        }
+        Ok(())
+    }
+
+    /// Load the checkpoint for a domain, or an empty one if none exists
+    pub async fn load_domain(&self, domain: SyncDomain) -> NetworkResult<DomainCheckpoint> {
+        Ok(self.load(&format!("{}{}", DOMAIN_PREFIX, domain.name())).await?
+            .unwrap_or_else(|| DomainCheckpoint::new(domain)))
+    }
+
+    /// Load the checkpoints for all domains
+    pub async fn load_domains(&self) -> NetworkResult<Vec<DomainCheckpoint>> {
+        let mut checkpoints = Vec::with_capacity(SyncDomain::ALL.len());
+        for domain in SyncDomain::ALL {
+            checkpoints.push(self.load_domain(domain).await?);
+        }
+        Ok(checkpoints)
+    }
+
+    /// Mark a domain sync as started
+    pub async fn begin_domain(&self, domain: SyncDomain, now: u64) -> NetworkResult<()> {
+        let mut checkpoint = self.load_domain(domain).await?;
+        checkpoint.in_progress = true;
+        checkpoint.updated_at = now;
+        self.save(&format!("{}{}", DOMAIN_PREFIX, domain.name()), &checkpoint).await
+    }
+
+    /// Mark a domain sync as completed, recording the resulting state hash
+    pub async fn complete_domain(&self, domain: SyncDomain, now: u64) -> NetworkResult<DomainCheckpoint> {
+        let checkpoint = DomainCheckpoint {
+            domain,
+            state_hash: Some(domain_state_hash(self.storage.as_ref(), domain, now).await?),
+            in_progress: false,
+            updated_at: now,
+        };
+        self.save(&format!("{}{}", DOMAIN_PREFIX, domain.name()), &checkpoint).await?;
+        Ok(checkpoint)
+    }
+
+    /// Check whether any sync was interrupted before completing
+    pub async fn has_interrupted_sync(&self) -> NetworkResult<bool> {
+        if self.load_domains().await?.iter().any(|d| d.in_progress) {
+            return Ok(true);
+        }
+        Ok(self.load_peers().await?.iter().any(|p| !p.pending.is_empty()))
+    }
+}
+
+/// Compute the hash of the local state of a domain
+///
+/// The ledger hash is the Merkle root of the transaction index; the other
+/// domains hash the sorted keys and data hashes of their stored objects.
+pub async fn domain_state_hash(storage: &dyn Storage, domain: SyncDomain, now: u64) -> NetworkResult<String> {
+    let kinds: &[ObjectKind] = match domain {
+        SyncDomain::Ledger => {
+            let digest = reconcile::range_digest(storage, TimeRange::up_to(now)).await?;
+            return Ok(digest.root);
+        }
+        SyncDomain::Governance => &[ObjectKind::Proposal, ObjectKind::Vote],
+        SyncDomain::Identity => &[ObjectKind::Identity],
+    };
+
+    let mut keys = Vec::new();
+    for kind in kinds {
+        keys.extend(storage.list(kind.storage_prefix()).await?);
+    }
+    keys.sort();
+
+    let mut summary = Vec::new();
+    for key in keys {
+        let data = storage.get(&key).await?;
+        summary.extend_from_slice(key.as_bytes());
+        summary.push(b':');
+        summary.extend_from_slice(sha256(&data).to_hex().as_bytes());
+        summary.push(b'\n');
+    }
+
+    Ok(sha256(&summary).to_hex())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::MockStorage;
+
+    fn entry(id: &str, timestamp: u64) -> IndexEntry {
+        IndexEntry {
+            transaction_id: id.to_string(),
+            timestamp,
+            data_hash: format!("hash-{}", id),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peer_checkpoint_roundtrip() {
+        let store = CheckpointStore::new(Arc::new(MockStorage::new()));
+
+        let mut checkpoint = store.load_peer("peer1").await.unwrap();
+        assert_eq!(checkpoint, PeerCheckpoint::new("peer1"));
+
+        checkpoint.high_water_mark = 1_000;
+        checkpoint.add_pending(vec![entry("tx1", 900), entry("tx2", 950)]);
+        checkpoint.add_pending(vec![entry("tx1", 900)]);
+        store.save_peer(&checkpoint).await.unwrap();
+        store.observe_peer("peer1", 1_600).await.unwrap();
+
+        let loaded = store.load_peer("peer1").await.unwrap();
+        assert_eq!(loaded.pending.len(), 2);
+        assert_eq!(loaded.lag(), 600);
+        assert!(store.has_interrupted_sync().await.unwrap());
+
+        let mut loaded = loaded;
+        loaded.remove_pending("tx1");
+        loaded.remove_pending("tx2");
+        store.save_peer(&loaded).await.unwrap();
+        assert!(!store.has_interrupted_sync().await.unwrap());
+        assert_eq!(store.load_peers().await.unwrap(), vec![loaded]);
+    }
+
+    #[tokio::test]
+    async fn test_domain_checkpoint_tracks_state() {
+        let storage = Arc::new(MockStorage::new());
+        let store = CheckpointStore::new(storage.clone());
+
+        store.begin_domain(SyncDomain::Governance, 10).await.unwrap();
+        assert!(store.has_interrupted_sync().await.unwrap());
+
+        let first = store.complete_domain(SyncDomain::Governance, 20).await.unwrap();
+        assert!(!first.in_progress);
+        assert!(!store.has_interrupted_sync().await.unwrap());
+
+        storage.put(&ObjectKind::Proposal.storage_key("prop1"), b"proposal").await.unwrap();
+        let second = store.complete_domain(SyncDomain::Governance, 30).await.unwrap();
+        assert_ne!(first.state_hash, second.state_hash);
+        assert_eq!(store.load_domain(SyncDomain::Governance).await.unwrap(), second);
+    }
+}
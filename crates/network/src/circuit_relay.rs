@@ -2,6 +2,12 @@
 //!
 //! This module implements a circuit relay protocol that enables nodes behind NATs 
 //! to connect to other nodes through publicly accessible relay nodes.
+//!
+//! The data path uses libp2p's circuit relay v2: every node runs the relay
+//! client (reservations and relayed dials), nodes with `enable_relay_server`
+//! also relay for others, and DCUtR attempts to upgrade relayed connections
+//! to direct ones through hole punching. `CircuitRelayManager` keeps the
+//! bookkeeping in sync with the events of these behaviours.
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Debug;
@@ -9,12 +15,13 @@ use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 
 use async_trait::async_trait;
-use futures::StreamExt;
+use futures::{AsyncRead, AsyncWrite, StreamExt};
 use libp2p::{
     self,
-    core::{muxing::StreamMuxerBox, transport::OrTransport, upgrade},
+    core::{muxing::StreamMuxerBox, transport::{Boxed, OrTransport}, upgrade, ConnectedPoint},
     gossipsub::{self, IdentTopic, MessageAuthenticity, MessageId, ValidationMode},
-    identify, kad, mdns, noise, ping, relay,
+    identify, kad, mdns, noise, ping, relay, dcutr,
+    identity::Keypair,
     swarm::{self, ConnectionError, NetworkBehaviour, SwarmEvent},
     tcp, yamux, Multiaddr, PeerId, Transport,
 };
@@ -32,8 +39,12 @@ use crate::reputation::{ReputationManager, ReputationChange};
 pub struct CircuitRelayConfig {
     /// Maximum number of relay connections per peer
     pub max_connections: usize,
-    /// Maximum number of circuits per relay
+    /// Maximum number of circuits relayed at once by our relay server
     pub max_circuits: usize,
+    /// Maximum number of circuits relayed at once for a single peer by our relay server
+    pub max_circuits_per_peer: usize,
+    /// Maximum number of our circuits routed through a single relay
+    pub max_circuits_per_relay: usize,
     /// Circuit timeout duration
     pub circuit_timeout: Duration,
     /// Connection pool size per relay
@@ -50,6 +61,20 @@ pub struct CircuitRelayConfig {
     pub failover_timeout: Duration,
     /// Maximum retry attempts for failover
     pub max_retry_attempts: u32,
+    /// Relay circuits for other peers
+    pub enable_relay_server: bool,
+    /// Reserve slots on and dial through relay servers
+    pub enable_relay_client: bool,
+    /// Relay servers to reserve a slot on at startup
+    pub known_relay_servers: Vec<Multiaddr>,
+    /// Attempt to upgrade relayed connections with DCUtR hole punching
+    pub enable_dcutr: bool,
+    /// Maximum number of reservations accepted as a relay server
+    pub max_reservations: usize,
+    /// How long a reservation on this relay server is valid
+    pub reservation_duration: Duration,
+    /// Maximum number of bytes relayed per circuit
+    pub max_circuit_bytes: u64,
 }
 
 impl Default for CircuitRelayConfig {
@@ -57,6 +82,8 @@ impl Default for CircuitRelayConfig {
         Self {
             max_connections: 50,
             max_circuits: 20,
+            max_circuits_per_peer: 4,
+            max_circuits_per_relay: 20,
             circuit_timeout: Duration::from_secs(60),
             pool_size: 10,
             pool_timeout: Duration::from_secs(30),
@@ -65,6 +92,13 @@ impl Default for CircuitRelayConfig {
             enable_failover: true,
             failover_timeout: Duration::from_secs(10),
             max_retry_attempts: 3,
+            enable_relay_server: false,
+            enable_relay_client: true,
+            known_relay_servers: Vec::new(),
+            enable_dcutr: true,
+            max_reservations: 128,
+            reservation_duration: Duration::from_secs(3600),
+            max_circuit_bytes: 1 << 20,
         }
     }
 }
@@ -132,6 +166,12 @@ pub struct CircuitRelayManager {
     circuits: Arc<RwLock<HashMap<(PeerId, PeerId), Instant>>>,
     /// Failed relays with their failure time
     failed_relays: Arc<RwLock<HashMap<PeerId, Instant>>>,
+    /// Relays holding a reservation for us, with the time it was accepted
+    reservations: Arc<RwLock<HashMap<PeerId, Instant>>>,
+    /// Circuits relayed by this node as a server (source, destination)
+    served_circuits: Arc<RwLock<HashSet<(PeerId, PeerId)>>>,
+    /// Peers whose relayed connection was upgraded by DCUtR
+    direct_upgrades: Arc<RwLock<HashSet<PeerId>>>,
     /// Reputation manager
     reputation: Arc<ReputationManager>,
     /// Metrics collection
@@ -209,6 +249,9 @@ impl CircuitRelayManager {
             relays: Arc::new(RwLock::new(HashMap::new())),
             circuits: Arc::new(RwLock::new(HashMap::new())),
             failed_relays: Arc::new(RwLock::new(HashMap::new())),
+            reservations: Arc::new(RwLock::new(HashMap::new())),
+            served_circuits: Arc::new(RwLock::new(HashSet::new())),
+            direct_upgrades: Arc::new(RwLock::new(HashSet::new())),
             reputation,
             metrics,
         }
@@ -235,6 +278,7 @@ impl CircuitRelayManager {
         };
         
         relays.insert(peer_id, relay);
+        drop(relays);
         
         // Initialize connection pool
         self.initialize_pool(peer_id).await?;
@@ -275,7 +319,7 @@ impl CircuitRelayManager {
         let available: Vec<_> = relays.values()
             .filter(|relay| {
                 relay.is_available &&
-                relay.active_circuits < self.config.max_circuits &&
+                relay.active_circuits < self.config.max_circuits_per_relay &&
                 relay.avg_latency <= self.config.max_relay_latency &&
                 !failed.contains_key(&relay.peer_id)
             })
@@ -713,7 +757,11 @@ impl CircuitRelayManager {
         
         // Create a relay address for the destination peer
         let mut relayed_addr = relay_addr.clone();
-        relayed_addr.push(Protocol::P2p(peer_id.into()));
+        if extract_peer_id(&relayed_addr).is_none() {
+            relayed_addr.push(Protocol::P2p(relay_id));
+        }
+        relayed_addr.push(Protocol::P2pCircuit);
+        relayed_addr.push(Protocol::P2p(peer_id));
         
         Ok(relayed_addr)
     }
@@ -754,6 +802,125 @@ impl CircuitRelayManager {
     pub async fn add_relay_server(&self, peer_id: PeerId, addresses: Vec<Multiaddr>) -> crate::NetworkResult<()> {
         self.add_relay(peer_id, addresses).await
     }
+    
+    /// Record a connection established through a relay
+    ///
+    /// Fails with `MaxRelayConnectionsReached` if the relay already carries
+    /// `max_circuits_per_relay` of our circuits; the caller should close the connection.
+    pub async fn record_relayed_connection(&self, peer_id: PeerId, relay_id: PeerId) -> NetworkResult<()> {
+        let mut circuits = self.circuits.write().await;
+        
+        let relay_circuits = circuits.keys().filter(|(_, relay)| *relay == relay_id).count();
+        if relay_circuits >= self.config.max_circuits_per_relay {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_relay_connection_failure();
+            }
+            return Err(NetworkError::MaxRelayConnectionsReached);
+        }
+        
+        circuits.insert((peer_id, relay_id), Instant::now());
+        self.direct_upgrades.write().await.remove(&peer_id);
+        
+        let mut relays = self.relays.write().await;
+        if let Some(relay) = relays.get_mut(&relay_id) {
+            relay.active_circuits += 1;
+            relay.success_rate = 0.9 * relay.success_rate + 0.1;
+        }
+        
+        if let Some(metrics) = &self.metrics {
+            metrics.record_relay_connection_success();
+            metrics.record_active_relay_connections(circuits.len());
+        }
+        
+        Ok(())
+    }
+    
+    /// Record the end of a connection established through a relay
+    pub async fn record_relayed_connection_closed(&self, peer_id: PeerId, relay_id: PeerId) {
+        let mut circuits = self.circuits.write().await;
+        if circuits.remove(&(peer_id, relay_id)).is_none() {
+            return;
+        }
+        
+        let mut relays = self.relays.write().await;
+        if let Some(relay) = relays.get_mut(&relay_id) {
+            relay.active_circuits = relay.active_circuits.saturating_sub(1);
+        }
+        
+        if let Some(metrics) = &self.metrics {
+            metrics.record_active_relay_connections(circuits.len());
+        }
+    }
+    
+    /// Handle an event from the relay client, relay server or DCUtR
+    pub async fn handle_event(&self, event: CircuitRelayBehaviourEvent) {
+        match event {
+            CircuitRelayBehaviourEvent::Client(relay::client::Event::ReservationReqAccepted {
+                relay_peer_id,
+                renewal,
+                ..
+            }) => {
+                if !renewal {
+                    info!("Reservation accepted by relay {}", relay_peer_id);
+                }
+                self.reservations.write().await.insert(relay_peer_id, Instant::now());
+                self.failed_relays.write().await.remove(&relay_peer_id);
+                let _ = self.reputation.record_change(relay_peer_id, ReputationChange::RelaySuccess).await;
+            }
+            CircuitRelayBehaviourEvent::Client(relay::client::Event::OutboundCircuitEstablished {
+                relay_peer_id,
+                ..
+            }) => {
+                debug!("Outbound circuit established through relay {}", relay_peer_id);
+            }
+            CircuitRelayBehaviourEvent::Client(relay::client::Event::InboundCircuitEstablished {
+                src_peer_id,
+                ..
+            }) => {
+                debug!("Inbound circuit established from {}", src_peer_id);
+            }
+            CircuitRelayBehaviourEvent::Server(relay::Event::CircuitReqAccepted { src_peer_id, dst_peer_id }) => {
+                debug!("Relaying circuit from {} to {}", src_peer_id, dst_peer_id);
+                self.served_circuits.write().await.insert((src_peer_id, dst_peer_id));
+            }
+            CircuitRelayBehaviourEvent::Server(relay::Event::CircuitReqDenied { src_peer_id, dst_peer_id, .. }) => {
+                debug!("Denied circuit from {} to {}", src_peer_id, dst_peer_id);
+            }
+            CircuitRelayBehaviourEvent::Server(relay::Event::CircuitClosed { src_peer_id, dst_peer_id, .. }) => {
+                self.served_circuits.write().await.remove(&(src_peer_id, dst_peer_id));
+            }
+            CircuitRelayBehaviourEvent::Dcutr(dcutr::Event { remote_peer_id, result }) => {
+                let success = result.is_ok();
+                match result {
+                    Ok(_) => {
+                        info!("Upgraded relayed connection to {} to a direct connection", remote_peer_id);
+                        self.direct_upgrades.write().await.insert(remote_peer_id);
+                    }
+                    Err(e) => debug!("Hole punch to {} failed: {}", remote_peer_id, e),
+                }
+                
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_hole_punch(success);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    /// Check whether a relay holds a reservation for us
+    pub async fn has_reservation(&self, relay_id: PeerId) -> bool {
+        self.reservations.read().await.contains_key(&relay_id)
+    }
+    
+    /// Get the number of circuits this node is relaying for others
+    pub async fn served_circuit_count(&self) -> usize {
+        self.served_circuits.read().await.len()
+    }
+    
+    /// Check whether the connection to a peer was upgraded by DCUtR
+    pub async fn is_direct_upgrade(&self, peer_id: PeerId) -> bool {
+        self.direct_upgrades.read().await.contains(&peer_id)
+    }
 }
 
 impl Clone for CircuitRelayManager {
@@ -763,6 +930,9 @@ impl Clone for CircuitRelayManager {
             relays: self.relays.clone(),
             circuits: self.circuits.clone(),
             failed_relays: self.failed_relays.clone(),
+            reservations: self.reservations.clone(),
+            served_circuits: self.served_circuits.clone(),
+            direct_upgrades: self.direct_upgrades.clone(),
             reputation: self.reputation.clone(),
             metrics: self.metrics.clone(),
         }
@@ -783,23 +953,46 @@ impl Clone for PooledConnection {
     }
 }
 
-/// Create a relay transport
+/// Create a transport dialing and listening both directly and through relays
+///
+/// `base` carries direct connections (TCP in production, the memory
+/// transport in tests); relayed connections use the relay client transport
+/// returned by `relay::client::new` alongside the client behaviour.
 pub fn create_relay_transport<T>(
-    transport: T,
-    relay_config: &CircuitRelayConfig,
-) -> NetworkResult<T> 
+    base: T,
+    relay_transport: relay::client::Transport,
+    key_pair: &Keypair,
+    timeout: Duration,
+) -> NetworkResult<Boxed<(PeerId, StreamMuxerBox)>>
 where
-    T: Transport + Clone + Send + 'static,
-    T::Output: Send + 'static,
+    T: Transport + Send + Unpin + 'static,
+    T::Output: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    T::Dial: Send + 'static,
+    T::ListenerUpgrade: Send + 'static,
     T::Error: std::error::Error + Send + Sync + 'static,
 {
-    // In libp2p 0.55, the relay transport API has changed
-    // We'll just return the original transport since relay functionality
-    // will need to be reimplemented with the current libp2p version
+    let noise = noise::Config::new(key_pair).map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
     
-    // Below is placeholder code just to get it to compile
-    debug!("Creating relay transport with config: {:?}", relay_config);
-    Ok(transport)
+    Ok(OrTransport::new(relay_transport, base)
+        .upgrade(upgrade::Version::V1)
+        .authenticate(noise)
+        .multiplex(yamux::Config::default())
+        .timeout(timeout)
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .boxed())
+}
+
+/// Build the relay server configuration from the circuit relay limits
+pub fn relay_server_config(config: &CircuitRelayConfig) -> relay::Config {
+    relay::Config {
+        max_reservations: config.max_reservations,
+        reservation_duration: config.reservation_duration,
+        max_circuits: config.max_circuits,
+        max_circuits_per_peer: config.max_circuits_per_peer,
+        max_circuit_duration: config.circuit_timeout,
+        max_circuit_bytes: config.max_circuit_bytes,
+        ..Default::default()
+    }
 }
 
 /// Extract peer ID from a multiaddress
@@ -812,10 +1005,64 @@ fn extract_peer_id(addr: &Multiaddr) -> Option<PeerId> {
     })
 }
 
-/// Circuit relay network behavior
+/// Get the relay a relayed address goes through
+///
+/// Returns `None` for direct addresses.
+pub fn relay_peer_id(addr: &Multiaddr) -> Option<PeerId> {
+    let mut relay = None;
+    for protocol in addr.iter() {
+        match protocol {
+            Protocol::P2p(peer_id) => relay = Some(peer_id),
+            Protocol::P2pCircuit => return relay,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Get the relay a connection goes through
+///
+/// Returns `None` for direct connections.
+pub fn relayed_via(endpoint: &ConnectedPoint) -> Option<PeerId> {
+    match endpoint {
+        ConnectedPoint::Dialer { address, .. } => relay_peer_id(address),
+        ConnectedPoint::Listener { local_addr, .. } => relay_peer_id(local_addr),
+    }
+}
+
+/// Address to listen on for inbound circuits through a relay
+pub fn circuit_listen_address(relay_addr: &Multiaddr) -> Multiaddr {
+    relay_addr.clone().with(Protocol::P2pCircuit)
+}
+
+/// Circuit relay network behaviour
+///
+/// The relay client is always present so relayed addresses can be dialed;
+/// the relay server and DCUtR are enabled by the configuration.
+#[derive(NetworkBehaviour)]
 pub struct CircuitRelayBehaviour {
-    // Circuit Relay implementation fields
-    // ... etc
+    /// Relay client for reservations and relayed dials
+    client: relay::client::Behaviour,
+    /// Relay server relaying circuits for other peers
+    server: Toggle<relay::Behaviour>,
+    /// Direct connection upgrade through relay
+    dcutr: Toggle<dcutr::Behaviour>,
+}
+
+impl CircuitRelayBehaviour {
+    /// Create the behaviour from the client half of `relay::client::new`
+    pub fn new(local_peer_id: PeerId, client: relay::client::Behaviour, config: &CircuitRelayConfig) -> Self {
+        let server = config.enable_relay_server
+            .then(|| relay::Behaviour::new(local_peer_id, relay_server_config(config)));
+        let dcutr = config.enable_dcutr
+            .then(|| dcutr::Behaviour::new(local_peer_id));
+        
+        Self {
+            client,
+            server: server.into(),
+            dcutr: dcutr.into(),
+        }
+    }
 }
 
 /// Extension for NetworkMetrics to add circuit relay metrics
@@ -834,6 +1081,9 @@ pub trait CircuitRelayMetricsExt {
     
     /// Record a failed relay connection
     fn record_relay_connection_failure(&self);
+    
+    /// Record the outcome of a DCUtR hole punch
+    fn record_hole_punch(&self, success: bool);
 }
 
 #[cfg(test)]
@@ -950,4 +1200,142 @@ mod tests {
         assert_eq!(relay_info.successful_connections, 0);
         assert_eq!(relay_info.failed_connections, 0);
     }
-} 
\ No newline at end of file
+    
+    /// Create a swarm running only the circuit relay behaviour over the memory transport
+    fn memory_swarm(config: &CircuitRelayConfig) -> swarm::Swarm<CircuitRelayBehaviour> {
+        let key_pair = Keypair::generate_ed25519();
+        let peer_id = key_pair.public().to_peer_id();
+        let (relay_transport, client) = relay::client::new(peer_id);
+        let transport = create_relay_transport(
+            libp2p::core::transport::MemoryTransport::default(),
+            relay_transport,
+            &key_pair,
+            Duration::from_secs(10),
+        ).unwrap();
+        
+        swarm::Swarm::new(
+            transport,
+            CircuitRelayBehaviour::new(peer_id, client, config),
+            peer_id,
+            swarm::Config::with_tokio_executor().with_idle_connection_timeout(Duration::from_secs(60)),
+        )
+    }
+    
+    /// Start a relay server on a random memory address, returning its address
+    fn spawn_relay(config: &CircuitRelayConfig) -> Multiaddr {
+        let mut relay = memory_swarm(&CircuitRelayConfig { enable_relay_server: true, ..config.clone() });
+        let listen_addr = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+        relay.listen_on(listen_addr.clone()).unwrap();
+        // Reservations advertise the relay's external addresses
+        relay.add_external_address(listen_addr.clone());
+        let relay_addr = listen_addr.with(Protocol::P2p(*relay.local_peer_id()));
+        
+        tokio::spawn(async move {
+            loop {
+                relay.select_next_some().await;
+            }
+        });
+        
+        relay_addr
+    }
+    
+    /// Start a destination listening through a relay, returning its peer ID
+    /// once the reservation is accepted
+    async fn spawn_destination(relay_addr: &Multiaddr) -> PeerId {
+        let mut destination = memory_swarm(&CircuitRelayConfig::default());
+        let peer_id = *destination.local_peer_id();
+        destination.listen_on(circuit_listen_address(relay_addr)).unwrap();
+        
+        let (reserved_tx, reserved_rx) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let mut reserved_tx = Some(reserved_tx);
+            loop {
+                if let SwarmEvent::Behaviour(CircuitRelayBehaviourEvent::Client(
+                    relay::client::Event::ReservationReqAccepted { .. }
+                )) = destination.select_next_some().await {
+                    if let Some(tx) = reserved_tx.take() {
+                        let _ = tx.send(());
+                    }
+                }
+            }
+        });
+        
+        tokio::time::timeout(Duration::from_secs(10), reserved_rx).await.unwrap().unwrap();
+        peer_id
+    }
+    
+    #[tokio::test]
+    async fn test_relayed_connection_over_memory_transport() {
+        let config = CircuitRelayConfig::default();
+        let relay_addr = spawn_relay(&config);
+        let relay_id = extract_peer_id(&relay_addr).unwrap();
+        let destination = spawn_destination(&relay_addr).await;
+        
+        let reputation = Arc::new(ReputationManager::new(Default::default()));
+        let manager = CircuitRelayManager::new(config.clone(), reputation, None);
+        manager.add_relay(relay_id, vec![relay_addr.clone()]).await.unwrap();
+        
+        // Dial the destination through the relay
+        let mut source = memory_swarm(&config);
+        let circuit_addr = manager.connect_via_relay(destination).await.unwrap();
+        source.dial(circuit_addr).unwrap();
+        
+        let established = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match source.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } if peer_id == destination => {
+                        return endpoint;
+                    }
+                    SwarmEvent::OutgoingConnectionError { error, .. } => panic!("Relayed dial failed: {}", error),
+                    _ => {}
+                }
+            }
+        }).await.unwrap();
+        
+        assert_eq!(relayed_via(&established), Some(relay_id));
+        manager.record_relayed_connection(destination, relay_id).await.unwrap();
+        assert!(manager.is_relayed_connection(destination).await);
+        assert_eq!(manager.get_relay_for_connection(destination).await, Some(relay_id));
+        
+        manager.record_relayed_connection_closed(destination, relay_id).await;
+        assert!(!manager.is_relayed_connection(destination).await);
+    }
+    
+    #[tokio::test]
+    async fn test_relay_server_enforces_circuit_limits() {
+        // The relay accepts reservations but no circuits
+        let config = CircuitRelayConfig {
+            max_circuits: 0,
+            max_circuits_per_relay: 0,
+            ..Default::default()
+        };
+        let relay_addr = spawn_relay(&config);
+        let destination = spawn_destination(&relay_addr).await;
+        
+        let mut source = memory_swarm(&CircuitRelayConfig::default());
+        let circuit_addr = circuit_listen_address(&relay_addr).with(Protocol::P2p(destination));
+        source.dial(circuit_addr).unwrap();
+        
+        let denied = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match source.select_next_some().await {
+                    SwarmEvent::ConnectionEstablished { peer_id, .. } if peer_id == destination => return false,
+                    SwarmEvent::OutgoingConnectionError { peer_id: Some(peer_id), .. } if peer_id == destination => {
+                        return true;
+                    }
+                    _ => {}
+                }
+            }
+        }).await.unwrap();
+        assert!(denied);
+        
+        // The client side enforces its own per-relay limit on its bookkeeping
+        let reputation = Arc::new(ReputationManager::new(Default::default()));
+        let manager = CircuitRelayManager::new(config, reputation, None);
+        let relay_id = extract_peer_id(&relay_addr).unwrap();
+        assert!(matches!(
+            manager.record_relayed_connection(destination, relay_id).await,
+            Err(NetworkError::MaxRelayConnectionsReached)
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Versioned wire codec for ICN messages
+//!
+//! Version 1 is the original unframed JSON encoding. From version 2 on,
+//! every payload is sent in a frame carrying a magic prefix, the protocol
+//! version and the payload format, so nodes running different releases can
+//! tell each other's messages apart. Nodes advertise the range of versions
+//! they support through the libp2p identify protocol and encode with the
+//! highest version every recipient understands.
+//!
+//! Version 3 adds a nonce to signed envelopes. Envelopes framed with an
+//! older version are laid out differently and can't be verified, so older
+//! versions are no longer supported. Peers that only support them share no
+//! version with this node and are left out of negotiation, rather than
+//! forcing every message down to a version other nodes would ignore.
+
+use std::fmt;
+
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+
+use crate::{NetworkError, NetworkResult};
+
+/// Magic prefix identifying a framed payload
+pub const FRAME_MAGIC: [u8; 3] = *b"ICN";
+
+/// Length of the frame header (magic, version, format)
+const HEADER_LEN: usize = FRAME_MAGIC.len() + 2 + 1;
+
+/// Legacy unframed JSON encoding
+pub const LEGACY_JSON_VERSION: u16 = 1;
+
+/// First wire version whose signed envelopes carry a nonce
+pub const ENVELOPE_NONCE_VERSION: u16 = 3;
+
+/// Oldest wire version this node understands
+pub const MIN_WIRE_VERSION: u16 = ENVELOPE_NONCE_VERSION;
+
+/// Newest wire version this node understands
+pub const CURRENT_WIRE_VERSION: u16 = 3;
+
+/// Prefix of the identify protocol version used to advertise wire versions
+const ADVERTISEMENT_PREFIX: &str = "/icn/wire/";
+
+/// Payload format of a framed message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireFormat {
+    /// Compact binary encoding using bincode
+    Bincode,
+    /// JSON encoding, useful for debugging
+    Json,
+}
+
+impl WireFormat {
+    /// Get the format tag written to the frame header
+    fn tag(&self) -> u8 {
+        match self {
+            WireFormat::Bincode => 0,
+            WireFormat::Json => 1,
+        }
+    }
+
+    /// Parse a format tag from a frame header
+    fn from_tag(tag: u8) -> NetworkResult<Self> {
+        match tag {
+            0 => Ok(WireFormat::Bincode),
+            1 => Ok(WireFormat::Json),
+            _ => Err(NetworkError::MessageError(format!("Unknown wire format {}", tag))),
+        }
+    }
+}
+
+/// Inclusive range of supported wire versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionRange {
+    /// Oldest supported version
+    pub min: u16,
+    /// Newest supported version
+    pub max: u16,
+}
+
+impl VersionRange {
+    /// Versions supported by this node
+    pub const LOCAL: VersionRange = VersionRange { min: MIN_WIRE_VERSION, max: CURRENT_WIRE_VERSION };
+
+    /// Versions assumed for peers that do not advertise any
+    pub const LEGACY: VersionRange = VersionRange { min: LEGACY_JSON_VERSION, max: LEGACY_JSON_VERSION };
+
+    /// Check whether a version is in the range
+    pub fn contains(&self, version: u16) -> bool {
+        version >= self.min && version <= self.max
+    }
+
+    /// Get the highest version supported by both ranges
+    pub fn negotiate(&self, other: &VersionRange) -> Option<u16> {
+        let max = self.max.min(other.max);
+        let min = self.min.max(other.min);
+        (min <= max).then_some(max)
+    }
+
+    /// Encode the range as an identify protocol version
+    pub fn to_advertisement(&self) -> String {
+        format!("{}{}-{}", ADVERTISEMENT_PREFIX, self.min, self.max)
+    }
+
+    /// Parse a range from an identify protocol version
+    ///
+    /// Returns `None` if the string is not a wire version advertisement.
+    pub fn from_advertisement(advertisement: &str) -> Option<Self> {
+        let (min, max) = advertisement.strip_prefix(ADVERTISEMENT_PREFIX)?.split_once('-')?;
+        let range = VersionRange {
+            min: min.parse().ok()?,
+            max: max.parse().ok()?,
+        };
+        (range.min <= range.max).then_some(range)
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+/// Choose the version to use for a message sent to a set of peers
+///
+/// Each peer is described by its advertised identify protocol version, if
+/// any. Peers sharing no version with us are ignored; they will reject the
+/// message with `UnsupportedVersion`.
+pub fn negotiate_version<'a>(peer_advertisements: impl IntoIterator<Item = Option<&'a str>>) -> u16 {
+    peer_advertisements
+        .into_iter()
+        .map(|advertisement| advertisement
+            .and_then(VersionRange::from_advertisement)
+            .unwrap_or(VersionRange::LEGACY))
+        .filter_map(|range| VersionRange::LOCAL.negotiate(&range))
+        .min()
+        .unwrap_or(CURRENT_WIRE_VERSION)
+}
+
+/// Encode a value for the wire
+pub fn encode<T: Serialize>(value: &T, version: u16, format: WireFormat) -> NetworkResult<Vec<u8>> {
+    if !VersionRange::LOCAL.contains(version) {
+        return Err(NetworkError::UnsupportedVersion(version));
+    }
+
+    let payload = match format {
+        WireFormat::Bincode => bincode::serialize(value).map_err(|_| NetworkError::EncodingError)?,
+        WireFormat::Json => serde_json::to_vec(value).map_err(|_| NetworkError::EncodingError)?,
+    };
+
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(&FRAME_MAGIC);
+    frame.extend_from_slice(&version.to_be_bytes());
+    frame.push(format.tag());
+    frame.extend_from_slice(&payload);
+
+    Ok(frame)
+}
+
+/// Get the version of a framed payload
+///
+/// Returns `None` for unframed legacy JSON and truncated frames.
+pub fn frame_version(data: &[u8]) -> Option<u16> {
+    if !data.starts_with(&FRAME_MAGIC) || data.len() < HEADER_LEN {
+        return None;
+    }
+    Some(u16::from_be_bytes([data[FRAME_MAGIC.len()], data[FRAME_MAGIC.len() + 1]]))
+}
+
+/// Decode a value from the wire
+///
+/// Unframed data is treated as legacy JSON. Frames with a version outside
+/// the locally supported range fail with `NetworkError::UnsupportedVersion`.
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> NetworkResult<T> {
+    if !data.starts_with(&FRAME_MAGIC) {
+        return serde_json::from_slice(data).map_err(|_| NetworkError::DecodingError);
+    }
+
+    let version = frame_version(data).ok_or(NetworkError::DecodingError)?;
+    if version == LEGACY_JSON_VERSION || !VersionRange::LOCAL.contains(version) {
+        return Err(NetworkError::UnsupportedVersion(version));
+    }
+
+    let payload = &data[HEADER_LEN..];
+    match WireFormat::from_tag(data[HEADER_LEN - 1])? {
+        WireFormat::Bincode => bincode::deserialize(payload).map_err(|_| NetworkError::DecodingError),
+        WireFormat::Json => serde_json::from_slice(payload).map_err(|_| NetworkError::DecodingError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::{CustomMessage, IdentityAnnouncement, NetworkMessage};
+
+    fn messages() -> Vec<NetworkMessage> {
+        let mut data = serde_json::Map::new();
+        data.insert("amount".to_string(), serde_json::json!(42));
+        data.insert("tags".to_string(), serde_json::json!(["a", "b"]));
+
+        let mut metadata = HashMap::new();
+        metadata.insert("name".to_string(), "Alice".to_string());
+
+        vec![
+            NetworkMessage::Custom(CustomMessage {
+                message_type: "test.custom".to_string(),
+                data,
+            }),
+            NetworkMessage::IdentityAnnouncement(IdentityAnnouncement {
+                identity_id: "did:icn:alice".to_string(),
+                public_key: vec![1, 2, 3],
+                metadata,
+                timestamp: 1,
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_roundtrip_all_versions_and_formats() {
+        for message in messages() {
+            for version in MIN_WIRE_VERSION..=CURRENT_WIRE_VERSION {
+                for format in [WireFormat::Bincode, WireFormat::Json] {
+                    let encoded = encode(&message, version, format).unwrap();
+                    let decoded: NetworkMessage = decode(&encoded).unwrap();
+                    assert_eq!(
+                        serde_json::to_value(&decoded).unwrap(),
+                        serde_json::to_value(&message).unwrap()
+                    );
+                }
+            }
+        }
+
+        // Binary frames are smaller than JSON ones
+        let message = &messages()[1];
+        let binary = encode(message, CURRENT_WIRE_VERSION, WireFormat::Bincode).unwrap();
+        let json = encode(message, CURRENT_WIRE_VERSION, WireFormat::Json).unwrap();
+        assert!(binary.len() < json.len());
+
+        // Unframed legacy JSON is still decoded
+        let legacy = serde_json::to_vec(message).unwrap();
+        let decoded: NetworkMessage = decode(&legacy).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), serde_json::to_value(message).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        let message = &messages()[0];
+        let mut frame = encode(message, CURRENT_WIRE_VERSION, WireFormat::Bincode).unwrap();
+        frame[FRAME_MAGIC.len()..FRAME_MAGIC.len() + 2].copy_from_slice(&99u16.to_be_bytes());
+
+        let result: NetworkResult<NetworkMessage> = decode(&frame);
+        assert!(matches!(result, Err(NetworkError::UnsupportedVersion(99))));
+        assert!(matches!(
+            encode(message, 99, WireFormat::Bincode),
+            Err(NetworkError::UnsupportedVersion(99))
+        ));
+
+        // Versions from before envelopes carried a nonce are no longer spoken
+        for version in LEGACY_JSON_VERSION..ENVELOPE_NONCE_VERSION {
+            assert!(matches!(
+                encode(message, version, WireFormat::Bincode),
+                Err(NetworkError::UnsupportedVersion(v)) if v == version
+            ));
+        }
+        frame[FRAME_MAGIC.len()..FRAME_MAGIC.len() + 2].copy_from_slice(&2u16.to_be_bytes());
+        let result: NetworkResult<NetworkMessage> = decode(&frame);
+        assert!(matches!(result, Err(NetworkError::UnsupportedVersion(2))));
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        let local = VersionRange::LOCAL.to_advertisement();
+        assert_eq!(VersionRange::from_advertisement(&local), Some(VersionRange::LOCAL));
+        assert_eq!(VersionRange::from_advertisement("/ipfs/id/1.0.0"), None);
+
+        // All peers current
+        assert_eq!(negotiate_version([Some(local.as_str()), Some(local.as_str())]), CURRENT_WIRE_VERSION);
+
+        // A peer not advertising versions shares none with us and is ignored
+        assert_eq!(negotiate_version([Some(local.as_str()), None]), CURRENT_WIRE_VERSION);
+
+        // A newer peer is met at our highest version; an incompatible one is ignored
+        let newer = VersionRange { min: 2, max: 5 }.to_advertisement();
+        let future = VersionRange { min: 7, max: 9 }.to_advertisement();
+        assert_eq!(negotiate_version([Some(newer.as_str()), Some(future.as_str())]), CURRENT_WIRE_VERSION);
+    }
+
+    #[test]
+    fn test_mixed_version_peers_get_envelopes_they_accept() {
+        let current = VersionRange::LOCAL.to_advertisement();
+        let old = VersionRange { min: 1, max: 2 }.to_advertisement();
+        let bridging = VersionRange { min: 2, max: 3 }.to_advertisement();
+
+        // A v2-only peer doesn't pull broadcasts below the nonce version
+        let version = negotiate_version([Some(current.as_str()), Some(old.as_str()), Some(bridging.as_str())]);
+        assert_eq!(version, CURRENT_WIRE_VERSION);
+        assert!(version >= ENVELOPE_NONCE_VERSION);
+
+        // So current nodes can decode what is sent to the mixed set
+        let message = &messages()[0];
+        let frame = encode(message, version, WireFormat::Bincode).unwrap();
+        assert_eq!(frame_version(&frame), Some(version));
+        assert!(decode::<NetworkMessage>(&frame).is_ok());
+
+        // With only old peers connected, nothing is negotiated down either
+        assert_eq!(negotiate_version([Some(old.as_str()), None]), CURRENT_WIRE_VERSION);
+    }
+}
@@ -0,0 +1,322 @@
+//! Connection manager for capping the number of connected peers
+//!
+//! When the number of connected peers rises above the high watermark, the
+//! manager selects peers to disconnect until the low watermark is reached.
+//! Protected peers (bootstrap nodes, federation members and relays) and
+//! peers still within their grace period are never selected; the rest are
+//! evicted lowest score first, where the score is the peer's networking
+//! reputation minus a penalty for measured latency.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::metrics::NetworkMetrics;
+use crate::reputation::{ReputationContext, ReputationManager};
+
+/// Connection manager configuration
+#[derive(Debug, Clone)]
+pub struct ConnectionManagerConfig {
+    /// Number of connected peers above which peers are evicted
+    pub high_watermark: usize,
+    /// Number of connected peers to trim down to
+    pub low_watermark: usize,
+    /// How long new connections are kept before they can be evicted
+    pub grace_period: Duration,
+    /// Score penalty per 100ms of measured latency
+    pub latency_weight: f64,
+}
+
+impl Default for ConnectionManagerConfig {
+    fn default() -> Self {
+        Self {
+            high_watermark: 96,
+            low_watermark: 64,
+            grace_period: Duration::from_secs(30),
+            latency_weight: 1.0,
+        }
+    }
+}
+
+/// Reason a peer is protected from eviction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProtectionReason {
+    /// Configured bootstrap peer
+    Bootstrap,
+    /// Member of a federation the node participates in
+    FederationMember,
+    /// Circuit relay server
+    Relay,
+    /// Protected by the application
+    Manual,
+}
+
+/// A peer selected for eviction
+#[derive(Debug, Clone)]
+pub struct EvictionCandidate {
+    /// Peer to disconnect
+    pub peer_id: PeerId,
+    /// Networking reputation of the peer
+    pub reputation: i32,
+    /// Measured latency, if known
+    pub latency: Option<Duration>,
+    /// Score the peer was ranked by
+    pub score: f64,
+}
+
+/// Connection manager
+#[derive(Clone)]
+pub struct ConnectionManager {
+    /// Configuration
+    config: ConnectionManagerConfig,
+    /// Connected peers and when they connected
+    connected: Arc<RwLock<HashMap<PeerId, Instant>>>,
+    /// Protected peers and the reasons they are protected
+    protected: Arc<RwLock<HashMap<PeerId, HashSet<ProtectionReason>>>>,
+    /// Reputation manager used to rank peers
+    reputation: Option<Arc<ReputationManager>>,
+    /// Metrics for eviction decisions and peer latency
+    metrics: Option<NetworkMetrics>,
+}
+
+impl ConnectionManager {
+    /// Create a new connection manager
+    pub fn new(
+        config: ConnectionManagerConfig,
+        reputation: Option<Arc<ReputationManager>>,
+        metrics: Option<NetworkMetrics>,
+    ) -> Self {
+        if config.low_watermark > config.high_watermark {
+            warn!(
+                "Connection manager low watermark {} is above high watermark {}",
+                config.low_watermark, config.high_watermark
+            );
+        }
+
+        Self {
+            config,
+            connected: Arc::new(RwLock::new(HashMap::new())),
+            protected: Arc::new(RwLock::new(HashMap::new())),
+            reputation,
+            metrics,
+        }
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &ConnectionManagerConfig {
+        &self.config
+    }
+
+    /// Record a peer connecting
+    pub async fn peer_connected(&self, peer_id: PeerId) {
+        self.connected.write().await.entry(peer_id).or_insert_with(Instant::now);
+    }
+
+    /// Record a peer disconnecting
+    pub async fn peer_disconnected(&self, peer_id: &PeerId) {
+        self.connected.write().await.remove(peer_id);
+    }
+
+    /// Get the number of connected peers
+    pub async fn connected_count(&self) -> usize {
+        self.connected.read().await.len()
+    }
+
+    /// Protect a peer from eviction
+    pub async fn protect(&self, peer_id: PeerId, reason: ProtectionReason) {
+        self.protected.write().await.entry(peer_id).or_default().insert(reason);
+    }
+
+    /// Remove one reason a peer is protected
+    ///
+    /// The peer stays protected while any other reason remains.
+    pub async fn unprotect(&self, peer_id: &PeerId, reason: ProtectionReason) {
+        let mut protected = self.protected.write().await;
+        if let Some(reasons) = protected.get_mut(peer_id) {
+            reasons.remove(&reason);
+            if reasons.is_empty() {
+                protected.remove(peer_id);
+            }
+        }
+    }
+
+    /// Check if a peer is protected from eviction
+    pub async fn is_protected(&self, peer_id: &PeerId) -> bool {
+        self.protected.read().await.contains_key(peer_id)
+    }
+
+    /// Select peers to disconnect
+    ///
+    /// Returns nothing while the number of connected peers is at or below
+    /// the high watermark. Selected peers are removed from the connected
+    /// set, so calling again before they disconnect won't select them twice.
+    pub async fn select_evictions(&self) -> Vec<EvictionCandidate> {
+        self.select_evictions_at(Instant::now()).await
+    }
+
+    /// Select peers to disconnect at a given time
+    async fn select_evictions_at(&self, now: Instant) -> Vec<EvictionCandidate> {
+        let mut connected = self.connected.write().await;
+        if connected.len() <= self.config.high_watermark {
+            return Vec::new();
+        }
+
+        let excess = connected.len() - self.config.low_watermark.min(connected.len());
+        let protected = self.protected.read().await;
+
+        let mut candidates = Vec::new();
+        let mut skipped_protected = 0;
+        for (peer_id, connected_at) in connected.iter() {
+            if protected.contains_key(peer_id) {
+                skipped_protected += 1;
+                continue;
+            }
+            if now.saturating_duration_since(*connected_at) < self.config.grace_period {
+                continue;
+            }
+            candidates.push(self.score(*peer_id).await);
+        }
+        drop(protected);
+
+        // Lowest score first, ties broken by peer ID so selection is deterministic
+        candidates.sort_by(|a, b| {
+            a.score.partial_cmp(&b.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.peer_id.cmp(&b.peer_id))
+        });
+        candidates.truncate(excess);
+
+        info!(
+            "Connection manager trimming {} of {} peers (high watermark {}, low watermark {}, {} protected)",
+            candidates.len(), connected.len(), self.config.high_watermark, self.config.low_watermark, skipped_protected
+        );
+        if candidates.len() < excess {
+            warn!(
+                "Connection manager could only select {} of {} peers for eviction",
+                candidates.len(), excess
+            );
+        }
+
+        for candidate in &candidates {
+            connected.remove(&candidate.peer_id);
+            debug!(
+                "Evicting peer {} (reputation {}, latency {:?}, score {:.2})",
+                candidate.peer_id, candidate.reputation, candidate.latency, candidate.score
+            );
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_connection_decision("trim");
+            for _ in &candidates {
+                metrics.record_connection_decision("evict");
+            }
+            for _ in 0..skipped_protected {
+                metrics.record_connection_decision("protect");
+            }
+            if candidates.len() < excess {
+                metrics.record_connection_decision("trim_incomplete");
+            }
+        }
+
+        candidates
+    }
+
+    /// Score a peer for eviction ranking
+    async fn score(&self, peer_id: PeerId) -> EvictionCandidate {
+        let reputation = match &self.reputation {
+            Some(rep) => rep.get_reputation_async(&peer_id, &ReputationContext::Networking).await,
+            None => 0,
+        };
+        let latency = match &self.metrics {
+            Some(metrics) => metrics.get_peer_latency(&peer_id.to_string()).await,
+            None => None,
+        };
+
+        // Peers without a latency sample are not penalised
+        let latency_penalty = latency
+            .map(|l| l.as_secs_f64() * 10.0 * self.config.latency_weight)
+            .unwrap_or(0.0);
+
+        EvictionCandidate {
+            peer_id,
+            reputation,
+            latency,
+            score: reputation as f64 - latency_penalty,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reputation::{InteractionValue, ReputationConfig};
+
+    fn manager(high: usize, low: usize, reputation: Option<Arc<ReputationManager>>) -> ConnectionManager {
+        let config = ConnectionManagerConfig {
+            high_watermark: high,
+            low_watermark: low,
+            grace_period: Duration::from_secs(30),
+            latency_weight: 1.0,
+        };
+        ConnectionManager::new(config, reputation, None)
+    }
+
+    #[tokio::test]
+    async fn test_evicts_lowest_reputation_down_to_low_watermark() {
+        let reputation = Arc::new(ReputationManager::new(ReputationConfig::default()));
+        let manager = manager(3, 2, Some(reputation.clone()));
+
+        let peers: Vec<PeerId> = (0..4).map(|_| PeerId::random()).collect();
+        for peer in &peers {
+            manager.peer_connected(*peer).await;
+        }
+        reputation.record_change(peers[0], InteractionValue::MessageFailure).await.unwrap();
+        reputation.record_change(peers[1], InteractionValue::MessageSuccess).await.unwrap();
+        reputation.record_change(peers[2], InteractionValue::MessageSuccess).await.unwrap();
+        reputation.record_change(peers[3], InteractionValue::MessageFailure).await.unwrap();
+        reputation.record_change(peers[3], InteractionValue::MessageFailure).await.unwrap();
+
+        // New connections are within their grace period
+        assert!(manager.select_evictions_at(Instant::now()).await.is_empty());
+
+        let later = Instant::now() + Duration::from_secs(60);
+        let evicted: Vec<PeerId> = manager.select_evictions_at(later).await
+            .into_iter()
+            .map(|c| c.peer_id)
+            .collect();
+        assert_eq!(evicted, vec![peers[3], peers[0]]);
+        assert_eq!(manager.connected_count().await, 2);
+
+        // At the low watermark nothing else is evicted
+        assert!(manager.select_evictions_at(later).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_protected_peers_are_never_evicted() {
+        let manager = manager(1, 0, None);
+
+        let bootstrap = PeerId::random();
+        let relay = PeerId::random();
+        let other = PeerId::random();
+        for peer in [bootstrap, relay, other] {
+            manager.peer_connected(peer).await;
+        }
+        manager.protect(bootstrap, ProtectionReason::Bootstrap).await;
+        manager.protect(relay, ProtectionReason::Relay).await;
+        manager.protect(relay, ProtectionReason::FederationMember).await;
+        manager.unprotect(&relay, ProtectionReason::Relay).await;
+        assert!(manager.is_protected(&relay).await);
+
+        let later = Instant::now() + Duration::from_secs(60);
+        let evicted: Vec<PeerId> = manager.select_evictions_at(later).await
+            .into_iter()
+            .map(|c| c.peer_id)
+            .collect();
+        assert_eq!(evicted, vec![other]);
+    }
+}
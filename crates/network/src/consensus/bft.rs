@@ -0,0 +1,805 @@
+//! Multi-phase BFT commit protocol for Proof of Cooperation rounds
+//!
+//! Each consensus round (identified by its `round_id`) runs a
+//! Tendermint-style protocol over one or more BFT rounds: the round's
+//! proposer broadcasts a signed proposal, validators prevote for it (or
+//! nil), and once a quorum of prevotes is seen they lock on the value and
+//! precommit. A quorum of precommits for a value decides it, and those
+//! precommits form a `QuorumCertificate` any node can check against the
+//! committee. Timeouts move silent rounds along and the proposer rotates
+//! every BFT round.
+//!
+//! `BftEngine` does no I/O: inputs are messages and expired timeouts, and
+//! outputs are messages to broadcast, timeouts to schedule and decisions.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use icn_core::crypto::{sha256, verify_signature, Signature};
+
+use super::proof_of_cooperation::{PocMessage, Validator};
+use crate::envelope::EnvelopeSigner;
+use crate::{NetworkError, NetworkResult};
+
+/// Domain separation tag included in every signed consensus payload
+pub(crate) const SIGNING_DOMAIN: &str = "icn-poc-v1";
+
+/// Maximum number of conflicting votes kept per validator, round and phase
+///
+/// Conflicting votes are counted towards each value they vote for, so that
+/// honest nodes reach the same tallies regardless of which version of an
+/// equivocating validator's vote arrived first.
+const MAX_CONFLICTING_VOTES: usize = 2;
+
+/// Phase a vote is cast in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VotePhase {
+    /// First voting phase
+    Prevote,
+    /// Second voting phase, committing to a value
+    Precommit,
+}
+
+/// Step of a BFT round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Step {
+    /// Waiting for the proposal
+    Propose,
+    /// Waiting for prevotes
+    Prevote,
+    /// Waiting for precommits
+    Precommit,
+}
+
+/// Get the hash identifying a proposed value
+pub fn value_hash(value: &[u8]) -> String {
+    sha256(value).to_hex()
+}
+
+/// Get the number of matching votes needed for a quorum
+///
+/// This is the configured fraction of the committee, but never less than
+/// two thirds plus one, below which two quorums need not intersect in an
+/// honest validator.
+pub fn quorum_size(committee_size: usize, threshold: f64) -> usize {
+    let configured = (committee_size as f64 * threshold).ceil() as usize;
+    configured.max(committee_size * 2 / 3 + 1).min(committee_size)
+}
+
+/// A signed vote in a consensus round
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vote {
+    /// ID of the consensus round
+    pub round_id: String,
+    /// BFT round within the consensus round
+    pub round: u32,
+    /// Phase the vote is cast in
+    pub phase: VotePhase,
+    /// Hash of the value voted for, or `None` for nil
+    pub value_hash: Option<String>,
+    /// DID of the validator
+    pub validator_did: String,
+    /// Signature of the vote by the validator's key
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by a vote signature
+#[derive(Serialize)]
+struct VotePayload<'a> {
+    domain: &'a str,
+    round_id: &'a str,
+    round: u32,
+    phase: VotePhase,
+    value_hash: Option<&'a str>,
+    validator_did: &'a str,
+}
+
+impl Vote {
+    /// Create a signed vote
+    pub fn sign(
+        round_id: &str,
+        round: u32,
+        phase: VotePhase,
+        value_hash: Option<String>,
+        signer: &dyn EnvelopeSigner,
+    ) -> NetworkResult<Self> {
+        let mut vote = Self {
+            round_id: round_id.to_string(),
+            round,
+            phase,
+            value_hash,
+            validator_did: signer.sender().to_string(),
+            signature: Vec::new(),
+        };
+        vote.signature = signer.sign(&vote.signing_bytes()?)?;
+        Ok(vote)
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> NetworkResult<Vec<u8>> {
+        serde_json::to_vec(&VotePayload {
+            domain: SIGNING_DOMAIN,
+            round_id: &self.round_id,
+            round: self.round,
+            phase: self.phase,
+            value_hash: self.value_hash.as_deref(),
+            validator_did: &self.validator_did,
+        })
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode vote: {}", e)))
+    }
+
+    /// Verify the vote was signed by a committee member
+    pub fn verify(&self, committee: &[Validator]) -> NetworkResult<()> {
+        let validator = committee.iter()
+            .find(|v| v.did == self.validator_did)
+            .ok_or_else(|| NetworkError::UnauthorizedSender(format!("{} is not in the committee", self.validator_did)))?;
+
+        verify_signature(&validator.public_key, &self.signing_bytes()?, &Signature::new(self.signature.clone()))
+            .map_err(|e| NetworkError::InvalidSignature(format!("vote from {} ({})", self.validator_did, e)))
+    }
+}
+
+/// A signed proposal for a BFT round
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proposal {
+    /// ID of the consensus round
+    pub round_id: String,
+    /// BFT round the proposal is for
+    pub round: u32,
+    /// Proposed value
+    pub value: Vec<u8>,
+    /// Metadata about the proposal
+    pub metadata: HashMap<String, String>,
+    /// Round in which the value gathered a prevote quorum, if re-proposed
+    pub valid_round: Option<u32>,
+    /// DID of the proposer
+    pub origin: String,
+    /// Signature of the proposal by the proposer's key
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by a proposal signature
+#[derive(Serialize)]
+struct ProposalPayload<'a> {
+    domain: &'a str,
+    round_id: &'a str,
+    round: u32,
+    value_hash: String,
+    metadata: BTreeMap<&'a String, &'a String>,
+    valid_round: Option<u32>,
+    origin: &'a str,
+}
+
+impl Proposal {
+    /// Create a signed proposal
+    pub fn sign(
+        round_id: &str,
+        round: u32,
+        value: Vec<u8>,
+        metadata: HashMap<String, String>,
+        valid_round: Option<u32>,
+        signer: &dyn EnvelopeSigner,
+    ) -> NetworkResult<Self> {
+        let mut proposal = Self {
+            round_id: round_id.to_string(),
+            round,
+            value,
+            metadata,
+            valid_round,
+            origin: signer.sender().to_string(),
+            signature: Vec::new(),
+        };
+        proposal.signature = signer.sign(&proposal.signing_bytes()?)?;
+        Ok(proposal)
+    }
+
+    /// Get the hash of the proposed value
+    pub fn value_hash(&self) -> String {
+        value_hash(&self.value)
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> NetworkResult<Vec<u8>> {
+        serde_json::to_vec(&ProposalPayload {
+            domain: SIGNING_DOMAIN,
+            round_id: &self.round_id,
+            round: self.round,
+            value_hash: self.value_hash(),
+            metadata: self.metadata.iter().collect(),
+            valid_round: self.valid_round,
+            origin: &self.origin,
+        })
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode proposal: {}", e)))
+    }
+
+    /// Verify the proposal was signed by a committee member
+    pub fn verify(&self, committee: &[Validator]) -> NetworkResult<()> {
+        let validator = committee.iter()
+            .find(|v| v.did == self.origin)
+            .ok_or_else(|| NetworkError::UnauthorizedSender(format!("{} is not in the committee", self.origin)))?;
+
+        verify_signature(&validator.public_key, &self.signing_bytes()?, &Signature::new(self.signature.clone()))
+            .map_err(|e| NetworkError::InvalidSignature(format!("proposal from {} ({})", self.origin, e)))
+    }
+}
+
+/// A quorum of precommits proving a value was decided
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuorumCertificate {
+    /// ID of the consensus round
+    pub round_id: String,
+    /// BFT round the value was decided in
+    pub round: u32,
+    /// Hash of the decided value
+    pub value_hash: String,
+    /// Precommits for the value
+    pub precommits: Vec<Vote>,
+}
+
+impl QuorumCertificate {
+    /// Get the hash of the certificate, used to seed committee selection
+    pub fn hash(&self) -> NetworkResult<String> {
+        let bytes = serde_json::to_vec(self)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to encode certificate: {}", e)))?;
+        Ok(sha256(&bytes).to_hex())
+    }
+
+    /// Verify the certificate against a committee
+    ///
+    /// Checks that the precommits are for this value and round, signed by
+    /// distinct committee members, and reach a quorum.
+    pub fn verify(&self, committee: &[Validator], threshold: f64) -> NetworkResult<()> {
+        let mut signers = HashSet::new();
+        for vote in &self.precommits {
+            if vote.round_id != self.round_id
+                || vote.round != self.round
+                || vote.phase != VotePhase::Precommit
+                || vote.value_hash.as_deref() != Some(self.value_hash.as_str())
+            {
+                return Err(NetworkError::ConsensusError(format!(
+                    "certificate for {} contains a vote for a different value", self.round_id
+                )));
+            }
+            vote.verify(committee)?;
+            signers.insert(vote.validator_did.as_str());
+        }
+
+        let quorum = quorum_size(committee.len(), threshold);
+        if signers.len() < quorum {
+            return Err(NetworkError::ConsensusError(format!(
+                "certificate for {} has {} of {} required precommits", self.round_id, signers.len(), quorum
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Action requested by the engine
+#[derive(Clone, Debug)]
+pub enum BftOutput {
+    /// Broadcast a message to the committee
+    Broadcast(PocMessage),
+    /// Call `handle_timeout` with this round and step after a delay
+    ScheduleTimeout {
+        /// BFT round the timeout is for
+        round: u32,
+        /// Step the timeout is for
+        step: Step,
+        /// Delay before the timeout fires
+        after: Duration,
+    },
+    /// A value was decided
+    Decided {
+        /// Decided value
+        value: Vec<u8>,
+        /// Certificate proving the decision
+        certificate: QuorumCertificate,
+    },
+}
+
+/// BFT state machine for a single consensus round
+pub struct BftEngine {
+    /// ID of the consensus round
+    round_id: String,
+    /// Committee, sorted by DID
+    committee: Vec<Validator>,
+    /// Fraction of the committee required for a quorum
+    threshold: f64,
+    /// Signer for the local validator, if it is on the committee
+    signer: Option<Arc<dyn EnvelopeSigner>>,
+    /// Timeout for each step of the first BFT round
+    base_timeout: Duration,
+    /// Whether the first BFT round has started
+    started: bool,
+    /// Current BFT round
+    round: u32,
+    /// Current step
+    step: Step,
+    /// Value this node proposes when it is the proposer
+    own_value: Option<(Vec<u8>, HashMap<String, String>)>,
+    /// Round and hash of the value this node is locked on
+    locked: Option<(u32, String)>,
+    /// Round and hash of the most recent value with a prevote quorum
+    valid: Option<(u32, String)>,
+    /// Proposal from each round's proposer
+    proposals: HashMap<u32, Proposal>,
+    /// Hashes of values that failed validation
+    invalid_values: HashSet<String>,
+    /// Prevotes by round and validator
+    prevotes: HashMap<u32, HashMap<String, Vec<Vote>>>,
+    /// Precommits by round and validator
+    precommits: HashMap<u32, HashMap<String, Vec<Vote>>>,
+    /// Rounds whose prevote timeout was scheduled
+    prevote_timeouts: HashSet<u32>,
+    /// Rounds whose precommit timeout was scheduled
+    precommit_timeouts: HashSet<u32>,
+    /// Rounds in which a proposal gathered a prevote quorum
+    valid_rounds: HashSet<u32>,
+    /// Decided value and its certificate
+    decision: Option<(Vec<u8>, QuorumCertificate)>,
+}
+
+impl BftEngine {
+    /// Create an engine for a consensus round
+    ///
+    /// Without a signer the engine only observes: it tracks messages and
+    /// decisions but never votes or proposes.
+    pub fn new(
+        round_id: impl Into<String>,
+        mut committee: Vec<Validator>,
+        threshold: f64,
+        signer: Option<Arc<dyn EnvelopeSigner>>,
+        base_timeout: Duration,
+    ) -> Self {
+        committee.sort_by(|a, b| a.did.cmp(&b.did));
+        committee.dedup_by(|a, b| a.did == b.did);
+
+        let signer = signer.filter(|s| committee.iter().any(|v| v.did == s.sender()));
+
+        Self {
+            round_id: round_id.into(),
+            committee,
+            threshold,
+            signer,
+            base_timeout,
+            started: false,
+            round: 0,
+            step: Step::Propose,
+            own_value: None,
+            locked: None,
+            valid: None,
+            proposals: HashMap::new(),
+            invalid_values: HashSet::new(),
+            prevotes: HashMap::new(),
+            precommits: HashMap::new(),
+            prevote_timeouts: HashSet::new(),
+            precommit_timeouts: HashSet::new(),
+            valid_rounds: HashSet::new(),
+            decision: None,
+        }
+    }
+
+    /// Get the ID of the consensus round
+    pub fn round_id(&self) -> &str {
+        &self.round_id
+    }
+
+    /// Get the committee
+    pub fn committee(&self) -> &[Validator] {
+        &self.committee
+    }
+
+    /// Get the current BFT round
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Get the current step
+    pub fn step(&self) -> Step {
+        self.step
+    }
+
+    /// Get the decided value and its certificate
+    pub fn decision(&self) -> Option<&(Vec<u8>, QuorumCertificate)> {
+        self.decision.as_ref()
+    }
+
+    /// Get the number of matching votes needed for a quorum
+    pub fn quorum(&self) -> usize {
+        quorum_size(self.committee.len(), self.threshold)
+    }
+
+    /// Get the proposer of a BFT round
+    ///
+    /// The first proposer is derived from the round ID so that proposing
+    /// duty is spread across consensus rounds, then rotates every BFT round.
+    pub fn proposer(&self, round: u32) -> Option<&Validator> {
+        if self.committee.is_empty() {
+            return None;
+        }
+
+        let hash = sha256(self.round_id.as_bytes());
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&hash.as_bytes()[..8]);
+        let index = u64::from_be_bytes(seed).wrapping_add(round as u64) % self.committee.len() as u64;
+        self.committee.get(index as usize)
+    }
+
+    /// Get the timeout for each step of a BFT round
+    ///
+    /// Timeouts grow linearly with the round so that a round eventually
+    /// lasts long enough for honest validators to hear from each other.
+    pub fn timeout(&self, round: u32) -> Duration {
+        self.base_timeout * (round + 1)
+    }
+
+    /// Set the value to propose when this node is the proposer
+    pub fn set_own_value(&mut self, value: Vec<u8>, metadata: HashMap<String, String>) {
+        self.own_value = Some((value, metadata));
+    }
+
+    /// Start the first BFT round
+    pub fn start(&mut self) -> Vec<BftOutput> {
+        let mut out = Vec::new();
+        if !self.started {
+            self.started = true;
+            self.start_round(0, &mut out);
+            self.evaluate(&mut out);
+        }
+        out
+    }
+
+    /// Handle a proposal
+    ///
+    /// `valid` is the result of validating the proposed value; invalid
+    /// values are prevoted nil and never decided.
+    pub fn handle_proposal(&mut self, proposal: Proposal, valid: bool) -> NetworkResult<Vec<BftOutput>> {
+        if proposal.round_id != self.round_id {
+            return Err(NetworkError::ConsensusError(format!("proposal for {} sent to {}", proposal.round_id, self.round_id)));
+        }
+        let proposer = self.proposer(proposal.round)
+            .ok_or_else(|| NetworkError::ConsensusError("empty committee".to_string()))?;
+        if proposer.did != proposal.origin {
+            return Err(NetworkError::UnauthorizedSender(format!(
+                "{} is not the proposer of round {}", proposal.origin, proposal.round
+            )));
+        }
+        proposal.verify(&self.committee)?;
+
+        if let Some(existing) = self.proposals.get(&proposal.round) {
+            if existing.value_hash() != proposal.value_hash() {
+                warn!("Proposer {} equivocated in round {} of {}", proposal.origin, proposal.round, self.round_id);
+                return Err(NetworkError::ConsensusError(format!("conflicting proposals from {}", proposal.origin)));
+            }
+            return Ok(Vec::new());
+        }
+
+        if !valid {
+            self.invalid_values.insert(proposal.value_hash());
+        }
+        self.proposals.insert(proposal.round, proposal);
+
+        let mut out = Vec::new();
+        self.evaluate(&mut out);
+        Ok(out)
+    }
+
+    /// Handle a vote
+    pub fn handle_vote(&mut self, vote: Vote) -> NetworkResult<Vec<BftOutput>> {
+        if vote.round_id != self.round_id {
+            return Err(NetworkError::ConsensusError(format!("vote for {} sent to {}", vote.round_id, self.round_id)));
+        }
+        vote.verify(&self.committee)?;
+        self.record_vote(vote);
+
+        let mut out = Vec::new();
+        self.evaluate(&mut out);
+        Ok(out)
+    }
+
+    /// Handle a decision announced by another node
+    ///
+    /// The certificate is checked against the committee, so a node that
+    /// missed the round can still adopt the decision.
+    pub fn handle_decision(&mut self, value: Vec<u8>, certificate: QuorumCertificate) -> NetworkResult<Vec<BftOutput>> {
+        if certificate.round_id != self.round_id {
+            return Err(NetworkError::ConsensusError(format!("certificate for {} sent to {}", certificate.round_id, self.round_id)));
+        }
+        if certificate.value_hash != value_hash(&value) {
+            return Err(NetworkError::ConsensusError(format!("certificate for {} does not match the value", self.round_id)));
+        }
+        certificate.verify(&self.committee, self.threshold)?;
+
+        let mut out = Vec::new();
+        if self.decision.is_none() {
+            self.decide(value, certificate, &mut out);
+        }
+        Ok(out)
+    }
+
+    /// Handle an expired timeout
+    pub fn handle_timeout(&mut self, round: u32, step: Step) -> Vec<BftOutput> {
+        let mut out = Vec::new();
+        if self.decision.is_some() || round != self.round {
+            return out;
+        }
+
+        match step {
+            Step::Propose if self.step == Step::Propose => {
+                debug!("Proposal timeout in round {} of {}", round, self.round_id);
+                self.cast_vote(VotePhase::Prevote, None, &mut out);
+                self.step = Step::Prevote;
+            }
+            Step::Prevote if self.step == Step::Prevote => {
+                self.cast_vote(VotePhase::Precommit, None, &mut out);
+                self.step = Step::Precommit;
+            }
+            Step::Precommit => self.start_round(round + 1, &mut out),
+            _ => {}
+        }
+
+        self.evaluate(&mut out);
+        out
+    }
+
+    /// Start a BFT round
+    fn start_round(&mut self, round: u32, out: &mut Vec<BftOutput>) {
+        debug!("Starting round {} of {}", round, self.round_id);
+        self.round = round;
+        self.step = Step::Propose;
+
+        let is_proposer = match (&self.signer, self.proposer(round)) {
+            (Some(signer), Some(proposer)) => signer.sender() == proposer.did,
+            _ => false,
+        };
+
+        if is_proposer && !self.proposals.contains_key(&round) {
+            // Re-propose the value with a prevote quorum so locked validators can accept it
+            let proposal = match &self.valid {
+                Some((valid_round, hash)) => self.proposal_value(hash)
+                    .map(|(value, metadata)| (value, metadata, Some(*valid_round))),
+                None => self.own_value.clone()
+                    .or_else(|| self.proposals.values().min_by_key(|p| p.round).map(|p| (p.value.clone(), p.metadata.clone())))
+                    .map(|(value, metadata)| (value, metadata, None)),
+            };
+
+            if let (Some((value, metadata, valid_round)), Some(signer)) = (proposal, &self.signer) {
+                match Proposal::sign(&self.round_id, round, value, metadata, valid_round, signer.as_ref()) {
+                    Ok(proposal) => {
+                        self.proposals.insert(round, proposal.clone());
+                        out.push(BftOutput::Broadcast(PocMessage::Proposal(proposal)));
+                        return;
+                    }
+                    Err(e) => warn!("Failed to sign proposal for {}: {}", self.round_id, e),
+                }
+            }
+        }
+
+        out.push(BftOutput::ScheduleTimeout { round, step: Step::Propose, after: self.timeout(round) });
+    }
+
+    /// Get the value and metadata of a proposal with the given value hash
+    fn proposal_value(&self, hash: &str) -> Option<(Vec<u8>, HashMap<String, String>)> {
+        self.proposals.values()
+            .find(|p| p.value_hash() == hash)
+            .map(|p| (p.value.clone(), p.metadata.clone()))
+    }
+
+    /// Apply the protocol rules until no more progress can be made
+    fn evaluate(&mut self, out: &mut Vec<BftOutput>) {
+        if !self.started {
+            return;
+        }
+
+        loop {
+            if self.decision.is_some() {
+                return;
+            }
+
+            let before = (self.round, self.step);
+            self.try_decide(out);
+            self.try_skip_round(out);
+            self.try_prevote(out);
+            self.try_precommit(out);
+            self.schedule_vote_timeouts(out);
+
+            if self.decision.is_some() || (self.round, self.step) == before {
+                return;
+            }
+        }
+    }
+
+    /// Prevote on the current round's proposal
+    fn try_prevote(&mut self, out: &mut Vec<BftOutput>) {
+        if self.step != Step::Propose {
+            return;
+        }
+        let Some(proposal) = self.proposals.get(&self.round) else {
+            return;
+        };
+
+        let hash = proposal.value_hash();
+        let valid = !self.invalid_values.contains(&hash);
+        let vote = match proposal.valid_round {
+            None => valid && self.locked.as_ref().is_none_or(|(_, locked)| *locked == hash),
+            Some(valid_round) if valid_round < self.round
+                && self.vote_count(VotePhase::Prevote, valid_round, Some(&hash)) >= self.quorum() =>
+            {
+                valid && self.locked.as_ref().is_none_or(|(locked_round, locked)| *locked_round <= valid_round || *locked == hash)
+            }
+            // Wait for the prevotes justifying the re-proposal
+            Some(_) => return,
+        };
+
+        self.cast_vote(VotePhase::Prevote, vote.then_some(hash), out);
+        self.step = Step::Prevote;
+    }
+
+    /// Lock and precommit once a prevote quorum is seen
+    fn try_precommit(&mut self, out: &mut Vec<BftOutput>) {
+        if self.step < Step::Prevote {
+            return;
+        }
+        let round = self.round;
+
+        if !self.valid_rounds.contains(&round) {
+            if let Some(hash) = self.proposals.get(&round).map(|p| p.value_hash()) {
+                if !self.invalid_values.contains(&hash)
+                    && self.vote_count(VotePhase::Prevote, round, Some(&hash)) >= self.quorum()
+                {
+                    self.valid_rounds.insert(round);
+                    if self.step == Step::Prevote {
+                        self.locked = Some((round, hash.clone()));
+                        self.cast_vote(VotePhase::Precommit, Some(hash.clone()), out);
+                        self.step = Step::Precommit;
+                    }
+                    self.valid = Some((round, hash));
+                }
+            }
+        }
+
+        if self.step == Step::Prevote && self.vote_count(VotePhase::Prevote, round, None) >= self.quorum() {
+            self.cast_vote(VotePhase::Precommit, None, out);
+            self.step = Step::Precommit;
+        }
+    }
+
+    /// Schedule the prevote and precommit timeouts once any quorum is seen
+    fn schedule_vote_timeouts(&mut self, out: &mut Vec<BftOutput>) {
+        let round = self.round;
+        let after = self.timeout(round);
+
+        let prevotes = self.prevotes.get(&round).map_or(0, |v| v.len());
+        if self.step == Step::Prevote && prevotes >= self.quorum() && self.prevote_timeouts.insert(round) {
+            out.push(BftOutput::ScheduleTimeout { round, step: Step::Prevote, after });
+        }
+
+        let precommits = self.precommits.get(&round).map_or(0, |v| v.len());
+        if precommits >= self.quorum() && self.precommit_timeouts.insert(round) {
+            out.push(BftOutput::ScheduleTimeout { round, step: Step::Precommit, after });
+        }
+    }
+
+    /// Decide a value once any round has a precommit quorum for it
+    fn try_decide(&mut self, out: &mut Vec<BftOutput>) {
+        let quorum = self.quorum();
+        let decided = self.proposals.iter()
+            .filter(|(_, p)| !self.invalid_values.contains(&p.value_hash()))
+            .find_map(|(round, proposal)| {
+                let hash = proposal.value_hash();
+                let precommits: Vec<Vote> = self.precommits.get(round)?
+                    .values()
+                    .flatten()
+                    .filter(|v| v.value_hash.as_deref() == Some(hash.as_str()))
+                    .cloned()
+                    .collect();
+                (precommits.len() >= quorum).then(|| {
+                    let certificate = QuorumCertificate {
+                        round_id: self.round_id.clone(),
+                        round: *round,
+                        value_hash: hash,
+                        precommits,
+                    };
+                    (proposal.value.clone(), certificate)
+                })
+            });
+
+        if let Some((value, certificate)) = decided {
+            out.push(BftOutput::Broadcast(PocMessage::ConsensusReached {
+                round_id: self.round_id.clone(),
+                value: value.clone(),
+                certificate: certificate.clone(),
+            }));
+            self.decide(value, certificate, out);
+        }
+    }
+
+    /// Record a decision
+    fn decide(&mut self, value: Vec<u8>, mut certificate: QuorumCertificate, out: &mut Vec<BftOutput>) {
+        certificate.precommits.sort_by(|a, b| a.validator_did.cmp(&b.validator_did));
+        debug!("Decided {} in round {} of {}", certificate.value_hash, certificate.round, self.round_id);
+        self.decision = Some((value.clone(), certificate.clone()));
+        out.push(BftOutput::Decided { value, certificate });
+    }
+
+    /// Move to a later round once enough validators are already there
+    ///
+    /// More validators than could be faulty being in a round means at
+    /// least one honest validator has moved on.
+    fn try_skip_round(&mut self, out: &mut Vec<BftOutput>) {
+        let threshold = self.committee.len() - self.quorum() + 1;
+        let later_round = self.prevotes.keys()
+            .chain(self.precommits.keys())
+            .chain(self.proposals.keys())
+            .filter(|r| **r > self.round)
+            .copied()
+            .collect::<HashSet<u32>>()
+            .into_iter()
+            .filter(|r| {
+                let mut senders: HashSet<&str> = HashSet::new();
+                senders.extend(self.prevotes.get(r).into_iter().flat_map(|v| v.keys().map(String::as_str)));
+                senders.extend(self.precommits.get(r).into_iter().flat_map(|v| v.keys().map(String::as_str)));
+                senders.extend(self.proposals.get(r).map(|p| p.origin.as_str()));
+                senders.len() >= threshold
+            })
+            .min();
+
+        if let Some(round) = later_round {
+            self.start_round(round, out);
+        }
+    }
+
+    /// Count the votes in a round for a value (or nil)
+    fn vote_count(&self, phase: VotePhase, round: u32, hash: Option<&str>) -> usize {
+        let votes = match phase {
+            VotePhase::Prevote => &self.prevotes,
+            VotePhase::Precommit => &self.precommits,
+        };
+        votes.get(&round).map_or(0, |v| {
+            v.values()
+                .filter(|votes| votes.iter().any(|vote| vote.value_hash.as_deref() == hash))
+                .count()
+        })
+    }
+
+    /// Sign, record and broadcast a vote from the local validator
+    fn cast_vote(&mut self, phase: VotePhase, hash: Option<String>, out: &mut Vec<BftOutput>) {
+        let Some(signer) = &self.signer else {
+            return;
+        };
+
+        match Vote::sign(&self.round_id, self.round, phase, hash, signer.as_ref()) {
+            Ok(vote) => {
+                self.record_vote(vote.clone());
+                out.push(BftOutput::Broadcast(PocMessage::Vote(vote)));
+            }
+            Err(e) => warn!("Failed to sign vote for {}: {}", self.round_id, e),
+        }
+    }
+
+    /// Record a verified vote, keeping conflicting votes as evidence
+    fn record_vote(&mut self, vote: Vote) {
+        let votes = match vote.phase {
+            VotePhase::Prevote => &mut self.prevotes,
+            VotePhase::Precommit => &mut self.precommits,
+        };
+        let validator_votes = votes.entry(vote.round).or_default()
+            .entry(vote.validator_did.clone())
+            .or_default();
+
+        if validator_votes.iter().any(|v| v.value_hash == vote.value_hash) {
+            return;
+        }
+        if !validator_votes.is_empty() {
+            warn!("Validator {} equivocated in round {} of {}", vote.validator_did, vote.round, self.round_id);
+        }
+        if validator_votes.len() < MAX_CONFLICTING_VOTES {
+            validator_votes.push(vote);
+        }
+    }
+}
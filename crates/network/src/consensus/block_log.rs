@@ -0,0 +1,488 @@
+//! Append-only log of finalized consensus batches
+//!
+//! Each block holds a batch of transactions decided by one consensus round.
+//! Headers chain to their parent by hash and commit to the transactions
+//! through a Merkle root, and each block carries the quorum certificate
+//! that finalized it. Blocks are persisted through the node's storage, so
+//! the log gives the ledger and governance layers a single ordering of
+//! transactions that survives restarts and can be checked by any node.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+use icn_core::crypto::merkle::{MerkleProof, MerkleTree, ProofNode};
+use icn_core::crypto::{sha256, Hash};
+use icn_core::storage::{Storage, StorageError};
+
+use super::bft::{value_hash, QuorumCertificate};
+use super::proof_of_cooperation::Validator;
+use crate::{NetworkError, NetworkResult};
+
+/// Storage prefix for blocks, keyed by zero-padded height
+const BLOCK_PREFIX: &str = "consensus/blocks/";
+
+/// Storage key of the head header
+const HEAD_KEY: &str = "consensus/head";
+
+/// Prefix of the consensus round IDs that finalize blocks
+pub const BLOCK_ROUND_PREFIX: &str = "block-";
+
+/// Get the consensus round ID that finalizes the block at a height
+pub fn block_round_id(height: u64) -> String {
+    format!("{}{}", BLOCK_ROUND_PREFIX, height)
+}
+
+/// Parent hash of the first block
+pub fn genesis_parent_hash() -> String {
+    Hash(vec![0; 32]).to_hex()
+}
+
+/// A batch of transactions proposed for the next block
+///
+/// The encoded batch is the value consensus agrees on, so the certificate
+/// that finalizes it also fixes the block's height and parent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Batch {
+    /// Height of the block the batch is proposed for
+    pub height: u64,
+    /// Hash of the parent block header
+    pub parent_hash: String,
+    /// Transactions in order
+    pub transactions: Vec<Vec<u8>>,
+}
+
+impl Batch {
+    /// Encode the batch as a consensus value
+    pub fn encode(&self) -> NetworkResult<Vec<u8>> {
+        serde_json::to_vec(self)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to encode batch: {}", e)))
+    }
+
+    /// Decode a batch from a consensus value
+    pub fn decode(value: &[u8]) -> NetworkResult<Self> {
+        serde_json::from_slice(value)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to decode batch: {}", e)))
+    }
+}
+
+/// Header of a finalized block
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    /// Height of the block, starting at 1
+    pub height: u64,
+    /// Hash of the parent block header
+    pub parent_hash: String,
+    /// Merkle root of the block's transactions
+    pub transactions_root: String,
+    /// Number of transactions in the block
+    pub transaction_count: u64,
+    /// Certificate finalizing the block's batch
+    pub certificate: QuorumCertificate,
+}
+
+/// Fields of a header covered by its hash
+#[derive(Serialize)]
+struct HeaderPayload<'a> {
+    height: u64,
+    parent_hash: &'a str,
+    transactions_root: &'a str,
+    transaction_count: u64,
+    value_hash: &'a str,
+}
+
+impl BlockHeader {
+    /// Get the hash of the header
+    ///
+    /// The certificate is represented by the value hash it certifies rather
+    /// than its precommits, since nodes may hold different quorums for the
+    /// same decision and must still agree on the chain.
+    pub fn hash(&self) -> NetworkResult<String> {
+        let bytes = serde_json::to_vec(&HeaderPayload {
+            height: self.height,
+            parent_hash: &self.parent_hash,
+            transactions_root: &self.transactions_root,
+            transaction_count: self.transaction_count,
+            value_hash: &self.certificate.value_hash,
+        })
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode block header: {}", e)))?;
+        Ok(sha256(&bytes).to_hex())
+    }
+}
+
+/// A finalized block
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    /// Block header
+    pub header: BlockHeader,
+    /// Transactions in order
+    pub transactions: Vec<Vec<u8>>,
+}
+
+impl Block {
+    /// Build a block from a finalized batch
+    ///
+    /// Fails if the certificate doesn't certify the batch.
+    pub fn from_batch(batch: Batch, certificate: QuorumCertificate) -> NetworkResult<Self> {
+        if certificate.round_id != block_round_id(batch.height) {
+            return Err(NetworkError::ConsensusError(format!(
+                "certificate for {} does not finalize height {}", certificate.round_id, batch.height
+            )));
+        }
+        if certificate.value_hash != value_hash(&batch.encode()?) {
+            return Err(NetworkError::ConsensusError(format!(
+                "certificate for height {} is for a different batch", batch.height
+            )));
+        }
+
+        let tree = MerkleTree::new(&batch.transactions);
+        Ok(Self {
+            header: BlockHeader {
+                height: batch.height,
+                parent_hash: batch.parent_hash,
+                transactions_root: tree.root().to_hex(),
+                transaction_count: batch.transactions.len() as u64,
+                certificate,
+            },
+            transactions: batch.transactions,
+        })
+    }
+
+    /// Get the batch the block was finalized from
+    pub fn batch(&self) -> Batch {
+        Batch {
+            height: self.header.height,
+            parent_hash: self.header.parent_hash.clone(),
+            transactions: self.transactions.clone(),
+        }
+    }
+
+    /// Check the block's contents against its header and certificate
+    ///
+    /// Checks the Merkle root and that the certificate finalizes this
+    /// batch with a quorum of the committee.
+    pub fn verify(&self, committee: &[Validator], threshold: f64) -> NetworkResult<()> {
+        let expected = Block::from_batch(self.batch(), self.header.certificate.clone())?;
+        if expected.header != self.header {
+            return Err(NetworkError::ConsensusError(format!(
+                "block {} does not match its transactions", self.header.height
+            )));
+        }
+        self.header.certificate.verify(committee, threshold)
+    }
+}
+
+/// A step in an inclusion proof
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    /// Hash of the sibling node
+    pub hash: Hash,
+    /// Whether the sibling is on the left
+    pub is_left: bool,
+}
+
+/// Proof that a transaction is included in a finalized block
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    /// Height of the block
+    pub height: u64,
+    /// Index of the transaction in the block
+    pub index: usize,
+    /// Merkle path from the transaction to the block's root
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    /// Verify the proof for a transaction against a block header
+    pub fn verify(&self, transaction: &[u8], header: &BlockHeader) -> NetworkResult<bool> {
+        if header.height != self.height {
+            return Ok(false);
+        }
+
+        let root = Hash::from_hex(&header.transactions_root)
+            .map_err(|e| NetworkError::ConsensusError(format!("Invalid transactions root: {}", e)))?;
+        let leaf_hash = sha256(transaction);
+        let proof = MerkleProof {
+            leaf_hash: leaf_hash.clone(),
+            proof: self.path.iter()
+                .map(|step| ProofNode { hash: step.hash.clone(), is_left: step.is_left })
+                .collect(),
+            root,
+        };
+
+        proof.verify(&leaf_hash)
+            .map_err(|e| NetworkError::ConsensusError(format!("Invalid inclusion proof: {}", e)))
+    }
+}
+
+/// Check that a segment of blocks forms a valid chain
+///
+/// Each block must follow the one before it (or `parent`, for the first
+/// block) and be finalized by the committee.
+pub fn verify_segment(
+    blocks: &[Block],
+    parent: Option<&BlockHeader>,
+    committee: &[Validator],
+    threshold: f64,
+) -> NetworkResult<()> {
+    let (mut height, mut parent_hash) = match parent {
+        Some(header) => (header.height, header.hash()?),
+        None => match blocks.first() {
+            Some(first) => (first.header.height.saturating_sub(1), first.header.parent_hash.clone()),
+            None => return Ok(()),
+        },
+    };
+
+    for block in blocks {
+        if block.header.height != height + 1 || block.header.parent_hash != parent_hash {
+            return Err(NetworkError::ConsensusError(format!(
+                "block {} does not extend block {}", block.header.height, height
+            )));
+        }
+        block.verify(committee, threshold)?;
+
+        height = block.header.height;
+        parent_hash = block.header.hash()?;
+    }
+
+    Ok(())
+}
+
+/// Append-only log of finalized blocks backed by node storage
+#[derive(Clone)]
+pub struct BlockLog {
+    /// Storage layer
+    storage: Arc<dyn Storage>,
+    /// Header of the latest block, held while appending
+    head: Arc<RwLock<Option<BlockHeader>>>,
+}
+
+impl BlockLog {
+    /// Open the log, loading the head from storage
+    pub async fn open(storage: Arc<dyn Storage>) -> NetworkResult<Self> {
+        let head = match storage.get(HEAD_KEY).await {
+            Ok(data) => Some(serde_json::from_slice(&data)
+                .map_err(|e| NetworkError::StorageError(StorageError::DeserializationError(e.to_string())))?),
+            Err(StorageError::KeyNotFound(_)) => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            storage,
+            head: Arc::new(RwLock::new(head)),
+        })
+    }
+
+    /// Get the header of the latest block
+    pub async fn head(&self) -> Option<BlockHeader> {
+        self.head.read().await.clone()
+    }
+
+    /// Get the height of the latest block, or 0 if the log is empty
+    pub async fn height(&self) -> u64 {
+        self.head.read().await.as_ref().map_or(0, |h| h.height)
+    }
+
+    /// Start a batch extending the latest block
+    pub async fn next_batch(&self, transactions: Vec<Vec<u8>>) -> NetworkResult<Batch> {
+        let head = self.head.read().await;
+        Ok(Batch {
+            height: head.as_ref().map_or(0, |h| h.height) + 1,
+            parent_hash: match head.as_ref() {
+                Some(header) => header.hash()?,
+                None => genesis_parent_hash(),
+            },
+            transactions,
+        })
+    }
+
+    /// Append a finalized batch
+    ///
+    /// The batch must extend the latest block and the certificate must
+    /// finalize it; certificates are checked against the committee by the
+    /// consensus engine before they get here.
+    pub async fn append(&self, batch: Batch, certificate: QuorumCertificate) -> NetworkResult<Block> {
+        let mut head = self.head.write().await;
+
+        let expected_height = head.as_ref().map_or(0, |h| h.height) + 1;
+        let expected_parent = match head.as_ref() {
+            Some(header) => header.hash()?,
+            None => genesis_parent_hash(),
+        };
+        if batch.height != expected_height || batch.parent_hash != expected_parent {
+            return Err(NetworkError::ConsensusError(format!(
+                "batch for height {} does not extend the log at height {}", batch.height, expected_height - 1
+            )));
+        }
+
+        let block = Block::from_batch(batch, certificate)?;
+        self.save(&block_key(block.header.height), &block).await?;
+        self.save(HEAD_KEY, &block.header).await?;
+        debug!("Appended block {} with {} transactions", block.header.height, block.header.transaction_count);
+
+        *head = Some(block.header.clone());
+        Ok(block)
+    }
+
+    /// Get the block at a height
+    pub async fn get(&self, height: u64) -> NetworkResult<Option<Block>> {
+        match self.storage.get(&block_key(height)).await {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .map_err(|e| NetworkError::StorageError(StorageError::DeserializationError(e.to_string()))),
+            Err(StorageError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get the blocks from `from` to `to`, inclusive
+    pub async fn range(&self, from: u64, to: u64) -> NetworkResult<Vec<Block>> {
+        let mut blocks = Vec::new();
+        for height in from.max(1)..=to.min(self.height().await) {
+            let block = self.get(height).await?
+                .ok_or_else(|| NetworkError::StorageError(StorageError::KeyNotFound(block_key(height))))?;
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+
+    /// Prove that a transaction is included in the block at a height
+    ///
+    /// Returns `None` if the block doesn't exist or doesn't contain the
+    /// transaction.
+    pub async fn prove_inclusion(&self, height: u64, transaction: &[u8]) -> NetworkResult<Option<InclusionProof>> {
+        let Some(block) = self.get(height).await? else {
+            return Ok(None);
+        };
+        let Some(index) = block.transactions.iter().position(|t| t == transaction) else {
+            return Ok(None);
+        };
+
+        let proof = MerkleTree::new(&block.transactions)
+            .generate_proof(index)
+            .map_err(|e| NetworkError::ConsensusError(format!("Failed to build inclusion proof: {}", e)))?;
+
+        Ok(Some(InclusionProof {
+            height,
+            index,
+            path: proof.proof.into_iter()
+                .map(|node| ProofStep { hash: node.hash, is_left: node.is_left })
+                .collect(),
+        }))
+    }
+
+    /// Store a value
+    async fn save<T: Serialize>(&self, key: &str, value: &T) -> NetworkResult<()> {
+        let data = serde_json::to_vec(value)
+            .map_err(|e| NetworkError::StorageError(StorageError::SerializationError(e.to_string())))?;
+        self.storage.put(key, &data).await?;
+        Ok(())
+    }
+}
+
+/// Get the storage key of the block at a height
+fn block_key(height: u64) -> String {
+    format!("{}{:020}", BLOCK_PREFIX, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::bft::{Vote, VotePhase};
+    use crate::envelope::{EnvelopeSigner, IdentitySigner};
+    use crate::tests::MockStorage;
+    use icn_core::crypto::IdentityKeyPair;
+
+    const THRESHOLD: f64 = 0.67;
+
+    fn committee() -> (Vec<IdentitySigner>, Vec<Validator>) {
+        let signers: Vec<IdentitySigner> = (0..4)
+            .map(|i| IdentitySigner::new(format!("did:icn:validator{}", i), IdentityKeyPair::generate().unwrap()))
+            .collect();
+        let validators = signers.iter()
+            .map(|s| Validator {
+                did: s.sender().to_string(),
+                public_key: s.public_key(),
+                reputation: 100,
+                federation_id: None,
+            })
+            .collect();
+        (signers, validators)
+    }
+
+    fn certify(batch: &Batch, signers: &[IdentitySigner]) -> QuorumCertificate {
+        let round_id = block_round_id(batch.height);
+        let hash = value_hash(&batch.encode().unwrap());
+        QuorumCertificate {
+            round_id: round_id.clone(),
+            round: 0,
+            value_hash: hash.clone(),
+            precommits: signers.iter()
+                .map(|s| Vote::sign(&round_id, 0, VotePhase::Precommit, Some(hash.clone()), s).unwrap())
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_append_fetch_and_prove_inclusion() {
+        let (signers, validators) = committee();
+        let storage = Arc::new(MockStorage::new());
+        let log = BlockLog::open(storage.clone()).await.unwrap();
+
+        for i in 0..3u8 {
+            let batch = log.next_batch(vec![vec![i], vec![i, 1], vec![i, 2]]).await.unwrap();
+            let certificate = certify(&batch, &signers);
+            log.append(batch, certificate).await.unwrap();
+        }
+
+        // Batches that don't extend the head are rejected
+        let stale = Batch { height: 2, parent_hash: genesis_parent_hash(), transactions: vec![] };
+        let certificate = certify(&stale, &signers);
+        assert!(log.append(stale, certificate).await.is_err());
+
+        // The head survives reopening the log
+        let reopened = BlockLog::open(storage).await.unwrap();
+        assert_eq!(reopened.height().await, 3);
+        let blocks = reopened.range(1, 3).await.unwrap();
+        verify_segment(&blocks, None, &validators, THRESHOLD).unwrap();
+        verify_segment(&blocks[1..], Some(&blocks[0].header), &validators, THRESHOLD).unwrap();
+
+        let block = reopened.get(2).await.unwrap().unwrap();
+        let proof = reopened.prove_inclusion(2, &[1, 2]).await.unwrap().unwrap();
+        assert!(proof.verify(&[1, 2], &block.header).unwrap());
+        assert!(!proof.verify(&[1, 1], &block.header).unwrap());
+        assert!(!proof.verify(&[1, 2], &blocks[0].header).unwrap());
+        assert!(reopened.prove_inclusion(2, &[9]).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_segment_detects_tampering() {
+        let (signers, validators) = committee();
+        let log = BlockLog::open(Arc::new(MockStorage::new())).await.unwrap();
+        for i in 0..3u8 {
+            let batch = log.next_batch(vec![vec![i]]).await.unwrap();
+            let certificate = certify(&batch, &signers);
+            log.append(batch, certificate).await.unwrap();
+        }
+        let blocks = log.range(1, 3).await.unwrap();
+
+        // Rewritten transactions no longer match the root or the certificate
+        let mut rewritten = blocks.clone();
+        rewritten[1].transactions = vec![vec![42]];
+        assert!(verify_segment(&rewritten, None, &validators, THRESHOLD).is_err());
+
+        // Gaps break the chain
+        let gapped = vec![blocks[0].clone(), blocks[2].clone()];
+        assert!(verify_segment(&gapped, None, &validators, THRESHOLD).is_err());
+
+        // Certificates need a quorum of the committee
+        let mut weak = blocks.clone();
+        weak[2].header.certificate.precommits.truncate(2);
+        assert!(verify_segment(&weak, None, &validators, THRESHOLD).is_err());
+
+        let (_, other_committee) = committee();
+        assert!(verify_segment(&blocks, None, &other_committee, THRESHOLD).is_err());
+    }
+}
@@ -0,0 +1,595 @@
+//! Verifiable committee selection for Proof of Cooperation
+//!
+//! Committees are selected deterministically from a candidate set and a
+//! seed derived from the epoch and the quorum certificate of the last
+//! decided round, so every node with the same inputs computes the same
+//! committee. A `CommitteeProof` carries those inputs, letting a node check
+//! a committee it was sent instead of trusting the sender.
+//!
+//! Members of the outgoing committee sign a `CommitteeVote` for the
+//! committee they selected, and a quorum of those votes forms a
+//! `CommitteeCertificate` handing over to the new committee.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use icn_core::crypto::{sha256, verify_signature, Signature};
+
+use super::bft::{quorum_size, QuorumCertificate, SIGNING_DOMAIN};
+use super::proof_of_cooperation::{PocConfig, Validator, ValidatorSelectionStrategy};
+use crate::envelope::EnvelopeSigner;
+use crate::{NetworkError, NetworkResult};
+
+/// A signed endorsement of a candidate, used by democratic selection
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Endorsement {
+    /// Epoch of the committee the endorsement is for
+    pub epoch: u64,
+    /// DID of the endorsing candidate
+    pub endorser: String,
+    /// DID of the endorsed candidate
+    pub candidate: String,
+    /// Signature of the endorsement by the endorser's key
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by an endorsement signature
+#[derive(Serialize)]
+struct EndorsementPayload<'a> {
+    domain: &'a str,
+    epoch: u64,
+    endorser: &'a str,
+    candidate: &'a str,
+}
+
+impl Endorsement {
+    /// Create a signed endorsement
+    pub fn sign(epoch: u64, candidate: &str, signer: &dyn EnvelopeSigner) -> NetworkResult<Self> {
+        let mut endorsement = Self {
+            epoch,
+            endorser: signer.sender().to_string(),
+            candidate: candidate.to_string(),
+            signature: Vec::new(),
+        };
+        endorsement.signature = signer.sign(&endorsement.signing_bytes()?)?;
+        Ok(endorsement)
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> NetworkResult<Vec<u8>> {
+        serde_json::to_vec(&EndorsementPayload {
+            domain: SIGNING_DOMAIN,
+            epoch: self.epoch,
+            endorser: &self.endorser,
+            candidate: &self.candidate,
+        })
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode endorsement: {}", e)))
+    }
+
+    /// Verify the endorsement was signed by one of the candidates
+    pub fn verify(&self, candidates: &[Validator]) -> NetworkResult<()> {
+        let endorser = candidates.iter()
+            .find(|v| v.did == self.endorser)
+            .ok_or_else(|| NetworkError::UnauthorizedSender(format!("{} is not a candidate", self.endorser)))?;
+
+        verify_signature(&endorser.public_key, &self.signing_bytes()?, &Signature::new(self.signature.clone()))
+            .map_err(|e| NetworkError::InvalidSignature(format!("endorsement from {} ({})", self.endorser, e)))
+    }
+}
+
+/// Payload hashed to identify a committee
+#[derive(Serialize)]
+struct CommitteePayload<'a> {
+    domain: &'a str,
+    epoch: u64,
+    members: BTreeMap<&'a str, String>,
+}
+
+/// Get the hash identifying a committee for an epoch
+///
+/// Covers each member's DID and key, independent of their order.
+pub fn committee_hash(epoch: u64, committee: &[Validator]) -> NetworkResult<String> {
+    let payload = CommitteePayload {
+        domain: SIGNING_DOMAIN,
+        epoch,
+        members: committee.iter().map(|v| (v.did.as_str(), hex::encode(&v.public_key))).collect(),
+    };
+    let bytes = serde_json::to_vec(&payload)
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode committee: {}", e)))?;
+    Ok(sha256(&bytes).to_hex())
+}
+
+/// A committee member's signed vote for the committee of the next epoch
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitteeVote {
+    /// Epoch of the committee voted for
+    pub epoch: u64,
+    /// Hash of the committee voted for
+    pub committee_hash: String,
+    /// DID of the voting committee member
+    pub validator_did: String,
+    /// Signature of the vote by the member's key
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by a committee vote signature
+#[derive(Serialize)]
+struct CommitteeVotePayload<'a> {
+    domain: &'a str,
+    epoch: u64,
+    committee_hash: &'a str,
+    validator_did: &'a str,
+}
+
+impl CommitteeVote {
+    /// Create a signed vote for a committee
+    pub fn sign(epoch: u64, committee: &[Validator], signer: &dyn EnvelopeSigner) -> NetworkResult<Self> {
+        let mut vote = Self {
+            epoch,
+            committee_hash: committee_hash(epoch, committee)?,
+            validator_did: signer.sender().to_string(),
+            signature: Vec::new(),
+        };
+        vote.signature = signer.sign(&vote.signing_bytes()?)?;
+        Ok(vote)
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> NetworkResult<Vec<u8>> {
+        serde_json::to_vec(&CommitteeVotePayload {
+            domain: SIGNING_DOMAIN,
+            epoch: self.epoch,
+            committee_hash: &self.committee_hash,
+            validator_did: &self.validator_did,
+        })
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode committee vote: {}", e)))
+    }
+
+    /// Verify the vote was signed by a member of the outgoing committee
+    pub fn verify(&self, committee: &[Validator]) -> NetworkResult<()> {
+        let validator = committee.iter()
+            .find(|v| v.did == self.validator_did)
+            .ok_or_else(|| NetworkError::UnauthorizedSender(format!("{} is not in the committee", self.validator_did)))?;
+
+        verify_signature(&validator.public_key, &self.signing_bytes()?, &Signature::new(self.signature.clone()))
+            .map_err(|e| NetworkError::InvalidSignature(format!("committee vote from {} ({})", self.validator_did, e)))
+    }
+}
+
+/// Votes of a quorum of the outgoing committee for the next committee
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitteeCertificate {
+    /// Epoch of the certified committee
+    pub epoch: u64,
+    /// Hash of the certified committee
+    pub committee_hash: String,
+    /// Votes for the committee
+    pub votes: Vec<CommitteeVote>,
+}
+
+impl CommitteeCertificate {
+    /// Assemble a certificate from the votes for a committee
+    ///
+    /// Returns `None` until a quorum of the outgoing committee voted for it.
+    pub fn from_votes(
+        epoch: u64,
+        committee: &[Validator],
+        votes: &[CommitteeVote],
+        outgoing: &[Validator],
+        threshold: f64,
+    ) -> NetworkResult<Option<Self>> {
+        let hash = committee_hash(epoch, committee)?;
+        let mut signers = HashSet::new();
+        let votes: Vec<CommitteeVote> = votes.iter()
+            .filter(|v| v.epoch == epoch && v.committee_hash == hash && v.verify(outgoing).is_ok())
+            .filter(|v| signers.insert(v.validator_did.clone()))
+            .cloned()
+            .collect();
+
+        if votes.len() < quorum_size(outgoing.len(), threshold) {
+            return Ok(None);
+        }
+        Ok(Some(Self { epoch, committee_hash: hash, votes }))
+    }
+
+    /// Verify the certificate for a committee against the outgoing committee
+    ///
+    /// Checks that the votes are for exactly this committee and epoch, signed
+    /// by distinct members of the outgoing committee, and reach a quorum.
+    pub fn verify(&self, committee: &[Validator], outgoing: &[Validator], threshold: f64) -> NetworkResult<()> {
+        if self.committee_hash != committee_hash(self.epoch, committee)? {
+            return Err(NetworkError::ConsensusError(format!(
+                "certificate for epoch {} is for a different committee", self.epoch
+            )));
+        }
+
+        let mut signers = HashSet::new();
+        for vote in &self.votes {
+            if vote.epoch != self.epoch || vote.committee_hash != self.committee_hash {
+                return Err(NetworkError::ConsensusError(format!(
+                    "certificate for epoch {} contains a vote for a different committee", self.epoch
+                )));
+            }
+            vote.verify(outgoing)?;
+            signers.insert(vote.validator_did.as_str());
+        }
+
+        let quorum = quorum_size(outgoing.len(), threshold);
+        if signers.len() < quorum {
+            return Err(NetworkError::ConsensusError(format!(
+                "certificate for epoch {} has {} of {} required votes", self.epoch, signers.len(), quorum
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Payload hashed into the selection seed
+#[derive(Serialize)]
+struct SeedPayload<'a> {
+    domain: &'a str,
+    epoch: u64,
+    previous_certificate: Option<String>,
+}
+
+/// Get the seed committees for an epoch are selected with
+///
+/// The seed commits to the certificate of the last decided round, which no
+/// single validator controls, so candidates can't grind for a favourable
+/// committee.
+pub fn selection_seed(epoch: u64, previous_certificate: Option<&QuorumCertificate>) -> NetworkResult<String> {
+    let payload = SeedPayload {
+        domain: SIGNING_DOMAIN,
+        epoch,
+        previous_certificate: previous_certificate.map(QuorumCertificate::hash).transpose()?,
+    };
+    let bytes = serde_json::to_vec(&payload)
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode selection seed: {}", e)))?;
+    Ok(sha256(&bytes).to_hex())
+}
+
+/// Inputs from which a committee is selected
+///
+/// Anyone holding the proof can recompute the committee with `select`, and
+/// `verify` additionally checks the previous certificate and that the
+/// selection parameters match the local configuration.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CommitteeProof {
+    /// Epoch the committee serves in
+    pub epoch: u64,
+    /// Selection strategy
+    pub strategy: ValidatorSelectionStrategy,
+    /// Number of seats in the committee
+    pub committee_size: usize,
+    /// Minimum reputation of eligible candidates
+    pub min_reputation: i64,
+    /// Whether seats per federation are capped
+    pub federation_aware: bool,
+    /// Candidates the committee was selected from
+    pub candidates: Vec<Validator>,
+    /// Endorsements for the epoch, used by democratic selection
+    pub endorsements: Vec<Endorsement>,
+    /// Certificate of the last round decided before the epoch, if any
+    pub previous_certificate: Option<QuorumCertificate>,
+}
+
+impl CommitteeProof {
+    /// Create a proof with the selection parameters from the configuration
+    pub fn new(
+        config: &PocConfig,
+        epoch: u64,
+        candidates: Vec<Validator>,
+        endorsements: Vec<Endorsement>,
+        previous_certificate: Option<QuorumCertificate>,
+    ) -> Self {
+        Self {
+            epoch,
+            strategy: config.validator_selection.clone(),
+            committee_size: config.committee_size,
+            min_reputation: config.min_reputation,
+            federation_aware: config.federation_aware,
+            candidates,
+            endorsements,
+            previous_certificate,
+        }
+    }
+
+    /// Get the selection seed
+    pub fn seed(&self) -> NetworkResult<String> {
+        selection_seed(self.epoch, self.previous_certificate.as_ref())
+    }
+
+    /// Select the committee, sorted by DID
+    pub fn select(&self) -> NetworkResult<Vec<Validator>> {
+        let seed = self.seed()?;
+
+        // Eligible candidates, deduplicated by DID
+        let eligible: BTreeMap<&str, &Validator> = self.candidates.iter()
+            .filter(|v| v.reputation >= self.min_reputation)
+            .map(|v| (v.did.as_str(), v))
+            .collect();
+
+        // Seeded lottery tickets break ties and order random seats
+        let tickets: HashMap<&str, String> = eligible.keys()
+            .map(|did| (*did, sha256(format!("{}:{}", seed, did).as_bytes()).to_hex()))
+            .collect();
+
+        let by_ticket = |a: &&Validator, b: &&Validator| tickets[a.did.as_str()].cmp(&tickets[b.did.as_str()]);
+        let mut by_reputation: Vec<&Validator> = eligible.values().copied().collect();
+        by_reputation.sort_by(|a, b| b.reputation.cmp(&a.reputation).then_with(|| by_ticket(a, b)));
+
+        let ranked: Vec<&Validator> = match self.strategy {
+            ValidatorSelectionStrategy::ReputationBased => by_reputation,
+            ValidatorSelectionStrategy::Random => {
+                let mut ranked: Vec<&Validator> = eligible.values().copied().collect();
+                ranked.sort_by(by_ticket);
+                ranked
+            }
+            ValidatorSelectionStrategy::Democratic => {
+                let votes = self.count_endorsements(&eligible)?;
+                let mut ranked: Vec<&Validator> = eligible.values().copied().collect();
+                ranked.sort_by(|a, b| {
+                    let votes_a = votes.get(a.did.as_str()).copied().unwrap_or(0);
+                    let votes_b = votes.get(b.did.as_str()).copied().unwrap_or(0);
+                    votes_b.cmp(&votes_a).then_with(|| by_ticket(a, b))
+                });
+                ranked
+            }
+            ValidatorSelectionStrategy::Hybrid => {
+                // Half the seats by reputation, the rest by lottery
+                let reputation_seats = self.committee_size.div_ceil(2).min(by_reputation.len());
+                let mut rest = by_reputation.split_off(reputation_seats);
+                rest.sort_by(by_ticket);
+                by_reputation.extend(rest);
+                by_reputation
+            }
+        };
+
+        let mut committee = self.fill_seats(&ranked, &eligible);
+        committee.sort_by(|a, b| a.did.cmp(&b.did));
+        Ok(committee)
+    }
+
+    /// Verify a committee against the proof
+    ///
+    /// Checks the selection parameters against the local configuration, the
+    /// previous certificate against the committee that produced it, and that
+    /// the committee is exactly the one the proof selects.
+    pub fn verify(
+        &self,
+        config: &PocConfig,
+        committee: &[String],
+        previous_committee: &[Validator],
+    ) -> NetworkResult<Vec<Validator>> {
+        if self.strategy != config.validator_selection
+            || self.committee_size != config.committee_size
+            || self.min_reputation != config.min_reputation
+            || self.federation_aware != config.federation_aware
+        {
+            return Err(NetworkError::ConsensusError(format!(
+                "committee for epoch {} was selected with different parameters", self.epoch
+            )));
+        }
+
+        if let Some(certificate) = &self.previous_certificate {
+            certificate.verify(previous_committee, config.consensus_threshold)?;
+        }
+
+        let selected = self.select()?;
+        let expected: BTreeSet<&str> = selected.iter().map(|v| v.did.as_str()).collect();
+        let claimed: BTreeSet<&str> = committee.iter().map(String::as_str).collect();
+        if expected != claimed || claimed.len() != committee.len() {
+            return Err(NetworkError::ConsensusError(format!(
+                "committee for epoch {} does not match its proof", self.epoch
+            )));
+        }
+
+        Ok(selected)
+    }
+
+    /// Count endorsements per candidate, one per endorser and candidate
+    fn count_endorsements<'a>(&'a self, eligible: &BTreeMap<&str, &Validator>) -> NetworkResult<HashMap<&'a str, usize>> {
+        let electorate: Vec<Validator> = eligible.values().map(|v| (*v).clone()).collect();
+        let mut seen = BTreeSet::new();
+        let mut votes = HashMap::new();
+
+        for endorsement in &self.endorsements {
+            if endorsement.epoch != self.epoch {
+                return Err(NetworkError::ConsensusError(format!(
+                    "endorsement from {} is for epoch {}, not {}", endorsement.endorser, endorsement.epoch, self.epoch
+                )));
+            }
+            endorsement.verify(&electorate)?;
+
+            if eligible.contains_key(endorsement.candidate.as_str())
+                && seen.insert((endorsement.endorser.as_str(), endorsement.candidate.as_str()))
+            {
+                *votes.entry(endorsement.candidate.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        Ok(votes)
+    }
+
+    /// Take candidates in rank order, skipping those whose federation is full
+    fn fill_seats(&self, ranked: &[&Validator], eligible: &BTreeMap<&str, &Validator>) -> Vec<Validator> {
+        // No federation may hold more than an even share of the seats
+        let federations: BTreeSet<&str> = eligible.values().filter_map(|v| v.federation_id.as_deref()).collect();
+        let cap = match federations.len() {
+            0 => usize::MAX,
+            n if self.federation_aware => self.committee_size.div_ceil(n),
+            _ => usize::MAX,
+        };
+
+        let mut seats: HashMap<&str, usize> = HashMap::new();
+        let mut committee = Vec::new();
+        for validator in ranked {
+            if committee.len() == self.committee_size {
+                break;
+            }
+            if let Some(federation_id) = validator.federation_id.as_deref() {
+                let taken = seats.entry(federation_id).or_insert(0);
+                if *taken >= cap {
+                    continue;
+                }
+                *taken += 1;
+            }
+            committee.push((*validator).clone());
+        }
+
+        committee
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::IdentitySigner;
+    use icn_core::crypto::IdentityKeyPair;
+
+    fn candidates(count: usize) -> (Vec<IdentitySigner>, Vec<Validator>) {
+        let signers: Vec<IdentitySigner> = (0..count)
+            .map(|i| IdentitySigner::new(format!("did:icn:candidate{}", i), IdentityKeyPair::generate().unwrap()))
+            .collect();
+        let validators = signers.iter()
+            .enumerate()
+            .map(|(i, s)| Validator {
+                did: s.sender().to_string(),
+                public_key: s.public_key(),
+                reputation: 10 + i as i64,
+                federation_id: Some(format!("federation{}", i % 3)),
+            })
+            .collect();
+        (signers, validators)
+    }
+
+    fn config(strategy: ValidatorSelectionStrategy) -> PocConfig {
+        PocConfig {
+            validator_selection: strategy,
+            committee_size: 4,
+            min_reputation: 12,
+            federation_aware: false,
+            ..PocConfig::default()
+        }
+    }
+
+    fn dids(committee: &[Validator]) -> Vec<String> {
+        committee.iter().map(|v| v.did.clone()).collect()
+    }
+
+    #[test]
+    fn test_strategies_select_deterministically() {
+        let (signers, validators) = candidates(9);
+
+        // Reputation picks the top four eligible candidates
+        let proof = CommitteeProof::new(&config(ValidatorSelectionStrategy::ReputationBased), 1, validators.clone(), vec![], None);
+        let expected: Vec<String> = (5..9).map(|i| format!("did:icn:candidate{}", i)).collect();
+        assert_eq!(dids(&proof.select().unwrap()), expected);
+
+        // Random selection depends only on the seed and never picks ineligible candidates
+        let proof = CommitteeProof::new(&config(ValidatorSelectionStrategy::Random), 1, validators.clone(), vec![], None);
+        let committee = proof.select().unwrap();
+        assert_eq!(committee.len(), 4);
+        assert!(committee.iter().all(|v| v.reputation >= 12));
+        let mut shuffled = validators.clone();
+        shuffled.reverse();
+        let reordered = CommitteeProof::new(&config(ValidatorSelectionStrategy::Random), 1, shuffled, vec![], None);
+        assert_eq!(reordered.select().unwrap(), committee);
+
+        // Endorsed candidates win democratic selection
+        let endorsements: Vec<Endorsement> = signers[2..]
+            .iter()
+            .flat_map(|s| {
+                ["did:icn:candidate2", "did:icn:candidate3"].map(|candidate| Endorsement::sign(1, candidate, s).unwrap())
+            })
+            .collect();
+        let proof = CommitteeProof::new(&config(ValidatorSelectionStrategy::Democratic), 1, validators.clone(), endorsements, None);
+        let committee = dids(&proof.select().unwrap());
+        assert!(committee.contains(&"did:icn:candidate2".to_string()));
+        assert!(committee.contains(&"did:icn:candidate3".to_string()));
+
+        // Hybrid keeps the two most reputable candidates
+        let proof = CommitteeProof::new(&config(ValidatorSelectionStrategy::Hybrid), 1, validators.clone(), vec![], None);
+        let committee = dids(&proof.select().unwrap());
+        assert!(committee.contains(&"did:icn:candidate8".to_string()));
+        assert!(committee.contains(&"did:icn:candidate7".to_string()));
+
+        // Federation-aware selection caps each of the three federations at two seats
+        let mut capped = config(ValidatorSelectionStrategy::ReputationBased);
+        capped.federation_aware = true;
+        capped.min_reputation = 0;
+        capped.committee_size = 6;
+        let mut skewed = validators;
+        for validator in skewed.iter_mut().skip(3) {
+            validator.federation_id = Some("federation0".to_string());
+        }
+        let committee = CommitteeProof::new(&capped, 1, skewed, vec![], None).select().unwrap();
+        let from_federation0 = committee.iter().filter(|v| v.federation_id.as_deref() == Some("federation0")).count();
+        assert_eq!(from_federation0, 2);
+        assert_eq!(committee.len(), 4);
+    }
+
+    #[test]
+    fn test_proof_detects_tampering() {
+        let (signers, validators) = candidates(9);
+        let config = config(ValidatorSelectionStrategy::Democratic);
+        let endorsements = vec![Endorsement::sign(2, "did:icn:candidate2", &signers[4]).unwrap()];
+        let proof = CommitteeProof::new(&config, 2, validators, endorsements, None);
+        let committee = dids(&proof.select().unwrap());
+
+        assert!(proof.verify(&config, &committee, &[]).is_ok());
+
+        // A different committee doesn't match the proof
+        let mut swapped = committee.clone();
+        swapped[0] = "did:icn:candidate0".to_string();
+        assert!(proof.verify(&config, &swapped, &[]).is_err());
+
+        // Another epoch gives another seed, and its endorsements no longer count
+        let mut replayed = proof.clone();
+        replayed.epoch = 3;
+        assert_ne!(replayed.seed().unwrap(), proof.seed().unwrap());
+        assert!(replayed.select().is_err());
+
+        // Forged endorsements are rejected
+        let mut forged = proof.clone();
+        forged.endorsements[0].candidate = "did:icn:candidate3".to_string();
+        assert!(forged.verify(&config, &committee, &[]).is_err());
+
+        // Selection parameters must match the local configuration
+        let mut larger = config.clone();
+        larger.committee_size = 5;
+        assert!(proof.verify(&larger, &committee, &[]).is_err());
+    }
+
+    #[test]
+    fn test_committee_certificate_requires_outgoing_quorum() {
+        let (signers, validators) = candidates(9);
+        let config = config(ValidatorSelectionStrategy::ReputationBased);
+        let outgoing = validators[..4].to_vec();
+        let committee = CommitteeProof::new(&config, 2, validators.clone(), vec![], None).select().unwrap();
+
+        // Two of four outgoing members are short of a quorum
+        let mut votes: Vec<CommitteeVote> = signers[..2].iter()
+            .map(|s| CommitteeVote::sign(2, &committee, s).unwrap())
+            .collect();
+        assert_eq!(CommitteeCertificate::from_votes(2, &committee, &votes, &outgoing, 0.67).unwrap(), None);
+
+        // Votes from outside the outgoing committee or for another committee don't count
+        votes.push(CommitteeVote::sign(2, &committee, &signers[5]).unwrap());
+        votes.push(CommitteeVote::sign(2, &outgoing, &signers[2]).unwrap());
+        assert_eq!(CommitteeCertificate::from_votes(2, &committee, &votes, &outgoing, 0.67).unwrap(), None);
+
+        votes.push(CommitteeVote::sign(2, &committee, &signers[2]).unwrap());
+        let certificate = CommitteeCertificate::from_votes(2, &committee, &votes, &outgoing, 0.67).unwrap().unwrap();
+        certificate.verify(&committee, &outgoing, 0.67).unwrap();
+
+        // The certificate covers exactly this committee and epoch
+        assert!(certificate.verify(&outgoing, &outgoing, 0.67).is_err());
+        let mut replayed = certificate.clone();
+        replayed.epoch = 3;
+        assert!(replayed.verify(&committee, &outgoing, 0.67).is_err());
+        assert!(certificate.verify(&committee, &validators[4..8], 0.67).is_err());
+    }
+}
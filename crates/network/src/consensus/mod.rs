@@ -1,12 +1,23 @@
 mod proof_of_cooperation;
+pub mod bft;
+pub mod block_log;
+pub mod committee;
+
+#[cfg(test)]
+mod simulation;
 
 pub use proof_of_cooperation::{
     ProofOfCooperation,
     PocConfig,
+    PocMessage,
     ValidatorSelectionStrategy,
     TransactionValidator,
     ProposalValidator,
-    Vote,
     Validator,
+    ConsensusRound,
     ConsensusState,
-}; 
\ No newline at end of file
+    POC_MESSAGE_TYPE,
+};
+pub use bft::{BftEngine, BftOutput, Proposal, QuorumCertificate, Step, Vote, VotePhase};
+pub use block_log::{Batch, Block, BlockHeader, BlockLog, InclusionProof};
+pub use committee::{committee_hash, CommitteeCertificate, CommitteeProof, CommitteeVote, Endorsement};
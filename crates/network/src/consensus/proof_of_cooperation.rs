@@ -1,24 +1,31 @@
-use crate::error::Error;
-use crate::p2p::P2pNetwork;
-use crate::reputation::{ReputationManager, ReputationContext};
+use crate::consensus::bft::{value_hash, BftEngine, BftOutput, Proposal, QuorumCertificate, Step, Vote, SIGNING_DOMAIN};
+use crate::consensus::block_log::{block_round_id, Batch, BlockLog, BLOCK_ROUND_PREFIX};
+use crate::consensus::committee::{CommitteeCertificate, CommitteeProof, CommitteeVote, Endorsement};
+use crate::envelope::EnvelopeSigner;
+use crate::reputation::ReputationLedger;
+use crate::{CustomMessage, MessageHandler, NetworkError, NetworkMessage, NetworkResult, NetworkService, PeerInfo};
 use async_trait::async_trait;
+use icn_core::crypto::{verify_signature, Signature};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
-use tokio::time;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
+use tracing::{debug, info, warn};
+
+/// Message type used to gossip PoC messages as custom network messages
+pub const POC_MESSAGE_TYPE: &str = "consensus.poc";
 
 /// Validator selection strategies for PoC
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidatorSelectionStrategy {
-    /// Select validators based on reputation
+    /// Select the most reputable validators
     ReputationBased,
-    /// Select validators randomly
+    /// Select validators by a lottery drawn from the selection seed
     Random,
-    /// Select validators based on democratic election
+    /// Select the validators with the most endorsements from other candidates
     Democratic,
-    /// Select validators based on a hybrid approach
+    /// Select half the committee by reputation and the rest by lottery
     Hybrid,
 }
 
@@ -35,10 +42,16 @@ pub struct PocConfig {
     pub min_reputation: i64,
     /// Percentage of validators required for consensus
     pub consensus_threshold: f64,
-    /// Maximum time to wait for consensus
+    /// Time allowed for each step of the first BFT round
+    ///
+    /// Later rounds allow proportionally more time.
     pub consensus_timeout: Duration,
-    /// Enabled federation-specific validation rules
+    /// Cap the seats any one federation holds in the committee
     pub federation_aware: bool,
+    /// Maximum number of consensus rounds open at once
+    pub max_active_rounds: usize,
+    /// Time after which undecided rounds are dropped
+    pub max_round_age: Duration,
 }
 
 impl Default for PocConfig {
@@ -51,25 +64,27 @@ impl Default for PocConfig {
             consensus_threshold: 0.67,
             consensus_timeout: Duration::from_secs(30),
             federation_aware: true,
+            max_active_rounds: 256,
+            max_round_age: Duration::from_secs(600),
         }
     }
 }
 
 /// Represents a validator in the Proof of Cooperation consensus
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Validator {
     /// DID of the validator
     pub did: String,
+    /// Ed25519 public key the validator signs consensus messages with
+    pub public_key: Vec<u8>,
     /// Current reputation score
     pub reputation: i64,
     /// Federation ID the validator belongs to
     pub federation_id: Option<String>,
-    /// Last time the validator was active
-    pub last_active: chrono::DateTime<chrono::Utc>,
 }
 
 /// Current state of a consensus round
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ConsensusState {
     /// Preparing for consensus
     Preparing,
@@ -84,54 +99,39 @@ pub enum ConsensusState {
 }
 
 /// A consensus round
-#[derive(Clone, Debug)]
 pub struct ConsensusRound {
     /// ID of the consensus round
     pub id: String,
-    /// Proposed value to reach consensus on
-    pub proposed_value: Vec<u8>,
     /// Current state of the consensus
     pub state: ConsensusState,
-    /// Validators participating in this round
-    pub validators: Vec<Validator>,
-    /// Votes received
-    pub votes: HashMap<String, bool>,
+    /// BFT state machine driving the round
+    pub engine: BftEngine,
     /// Start time of the round
-    pub start_time: chrono::DateTime<chrono::Utc>,
+    pub start_time: Instant,
     /// End time of the round (if completed)
-    pub end_time: Option<chrono::DateTime<chrono::Utc>>,
-}
-
-/// A vote in the consensus process
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Vote {
-    /// ID of the consensus round
-    pub round_id: String,
-    /// DID of the validator
-    pub validator_did: String,
-    /// True if the validator approves the value
-    pub approve: bool,
-    /// Justification for the vote
-    pub justification: Option<String>,
-    /// Signature of the vote
-    pub signature: Vec<u8>,
+    pub end_time: Option<Instant>,
 }
 
 /// Message types for the PoC consensus
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum PocMessage {
-    /// Proposal for a new value
-    Proposal {
+    /// Request to reach consensus on a value
+    Request {
         /// ID of the consensus round
         round_id: String,
-        /// Proposed value
+        /// Value to agree on
         value: Vec<u8>,
-        /// Metadata about the proposal
+        /// Metadata about the value
         metadata: HashMap<String, String>,
         /// Origin DID
         origin: String,
+        /// Signature of the request by the origin's key, empty if unsigned
+        #[serde(default)]
+        signature: Vec<u8>,
     },
-    /// Vote on a proposal
+    /// Signed proposal from a BFT round's proposer
+    Proposal(Proposal),
+    /// Signed prevote or precommit
     Vote(Vote),
     /// Notification that consensus was reached
     ConsensusReached {
@@ -139,8 +139,8 @@ pub enum PocMessage {
         round_id: String,
         /// Final agreed value
         value: Vec<u8>,
-        /// Validators that approved
-        approving_validators: Vec<String>,
+        /// Precommits proving the decision
+        certificate: QuorumCertificate,
     },
     /// Request for the current committee
     CommitteeRequest {
@@ -153,443 +153,1042 @@ pub enum PocMessage {
         committee: Vec<String>,
         /// Current rotation period
         rotation_period: u64,
+        /// Proof the committee was selected correctly, if it was selected
+        proof: Option<CommitteeProof>,
+        /// Votes of the previous committee handing over to this one
+        #[serde(default)]
+        certificate: Option<CommitteeCertificate>,
     },
+    /// Endorsement of a candidate for the next committee
+    Endorsement(Endorsement),
+    /// Committee member's vote for the committee of the next epoch
+    CommitteeVote(CommitteeVote),
+}
+
+impl PocMessage {
+    /// Get the consensus round the message belongs to, if any
+    pub fn round_id(&self) -> Option<&str> {
+        match self {
+            PocMessage::Request { round_id, .. } => Some(round_id),
+            PocMessage::Proposal(proposal) => Some(&proposal.round_id),
+            PocMessage::Vote(vote) => Some(&vote.round_id),
+            PocMessage::ConsensusReached { round_id, .. } => Some(round_id),
+            PocMessage::CommitteeRequest { .. }
+            | PocMessage::CommitteeResponse { .. }
+            | PocMessage::Endorsement(_)
+            | PocMessage::CommitteeVote(_) => None,
+        }
+    }
+
+    /// Wrap the message for gossip
+    pub fn to_network_message(&self) -> NetworkResult<NetworkMessage> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to encode PoC message: {}", e)))?;
+
+        let mut data = serde_json::Map::new();
+        data.insert("message".to_string(), value);
+        Ok(NetworkMessage::Custom(CustomMessage {
+            message_type: POC_MESSAGE_TYPE.to_string(),
+            data,
+        }))
+    }
+
+    /// Unwrap a gossiped message
+    pub fn from_network_message(message: &NetworkMessage) -> NetworkResult<Self> {
+        match message {
+            NetworkMessage::Custom(custom) if custom.message_type == POC_MESSAGE_TYPE => {
+                let value = custom.data.get("message")
+                    .ok_or_else(|| NetworkError::MessageError("PoC message without payload".to_string()))?;
+                serde_json::from_value(value.clone())
+                    .map_err(|e| NetworkError::MessageError(format!("Failed to decode PoC message: {}", e)))
+            }
+            other => Err(NetworkError::MessageError(format!("Not a PoC message: {}", other.message_type()))),
+        }
+    }
+}
+
+/// Payload covered by a request signature
+#[derive(Serialize)]
+struct RequestPayload<'a> {
+    domain: &'a str,
+    round_id: &'a str,
+    value_hash: String,
+    metadata: BTreeMap<&'a String, &'a String>,
+    origin: &'a str,
+}
+
+/// Get the canonical encoding covered by a request signature
+fn request_signing_bytes(round_id: &str, value: &[u8], metadata: &HashMap<String, String>, origin: &str) -> NetworkResult<Vec<u8>> {
+    serde_json::to_vec(&RequestPayload {
+        domain: SIGNING_DOMAIN,
+        round_id,
+        value_hash: value_hash(value),
+        metadata: metadata.iter().collect(),
+        origin,
+    })
+    .map_err(|e| NetworkError::MessageError(format!("Failed to encode request: {}", e)))
+}
+
+/// Check that a message opening a round was signed by a committee member
+fn verify_opening(message: &PocMessage, committee: &[Validator], threshold: f64) -> NetworkResult<()> {
+    match message {
+        PocMessage::Request { round_id, value, metadata, origin, signature } => {
+            let validator = committee.iter()
+                .find(|v| &v.did == origin)
+                .ok_or_else(|| NetworkError::UnauthorizedSender(format!("{} is not in the committee", origin)))?;
+            verify_signature(
+                &validator.public_key,
+                &request_signing_bytes(round_id, value, metadata, origin)?,
+                &Signature::new(signature.clone()),
+            )
+            .map_err(|e| NetworkError::InvalidSignature(format!("request from {} ({})", origin, e)))
+        }
+        PocMessage::Proposal(proposal) => proposal.verify(committee),
+        PocMessage::Vote(vote) => vote.verify(committee),
+        PocMessage::ConsensusReached { certificate, .. } => certificate.verify(committee, threshold),
+        PocMessage::CommitteeRequest { .. }
+        | PocMessage::CommitteeResponse { .. }
+        | PocMessage::Endorsement(_)
+        | PocMessage::CommitteeVote(_) => Err(NetworkError::ConsensusError("message does not belong to a round".to_string())),
+    }
+}
+
+/// A request for a round that isn't open yet
+struct PendingRequest {
+    /// Value to agree on
+    value: Vec<u8>,
+    /// Metadata about the value
+    metadata: HashMap<String, String>,
+    /// When the request was received
+    received: Instant,
 }
 
 /// Handler for transaction validation
 #[async_trait]
 pub trait TransactionValidator: Send + Sync {
     /// Validate a transaction
-    async fn validate_transaction(&self, transaction: &[u8]) -> Result<bool, Error>;
+    async fn validate_transaction(&self, transaction: &[u8]) -> NetworkResult<bool>;
 }
 
 /// Handler for proposal validation
 #[async_trait]
 pub trait ProposalValidator: Send + Sync {
     /// Validate a proposal
-    async fn validate_proposal(&self, proposal: &[u8]) -> Result<bool, Error>;
+    async fn validate_proposal(&self, proposal: &[u8]) -> NetworkResult<bool>;
+}
+
+/// Input to the consensus task
+enum PocEvent {
+    /// A message from the network
+    Message(PocMessage),
+    /// A consensus request made on this node
+    Local(PocMessage),
+    /// An expired step timeout
+    Timeout {
+        /// ID of the consensus round
+        round_id: String,
+        /// BFT round the timeout is for
+        round: u32,
+        /// Step the timeout is for
+        step: Step,
+    },
 }
 
 /// The Proof of Cooperation consensus implementation
 pub struct ProofOfCooperation {
     /// Network connection
-    p2p: Arc<P2pNetwork>,
-    /// Reputation manager
-    reputation: Arc<ReputationManager>,
+    network: Arc<dyn NetworkService>,
     /// Configuration
     config: PocConfig,
+    /// Signer for the local validator's consensus messages
+    signer: Option<Arc<dyn EnvelopeSigner>>,
     /// Current committee of validators
     committee: RwLock<Vec<Validator>>,
+    /// Proof of the current committee's selection, if it was selected
+    committee_proof: RwLock<Option<CommitteeProof>>,
+    /// Certificate of the previous committee handing over to the current one
+    committee_certificate: RwLock<Option<CommitteeCertificate>>,
+    /// Committee of the previous epoch
+    previous_committee: RwLock<Vec<Validator>>,
+    /// Votes for the current and next committee
+    committee_votes: RwLock<Vec<CommitteeVote>>,
+    /// Candidates the next committee is selected from
+    candidates: RwLock<Vec<Validator>>,
+    /// Endorsements for the next committee
+    endorsements: RwLock<Vec<Endorsement>>,
+    /// Certificate of the last decided round, seeding the next selection
+    last_certificate: RwLock<Option<QuorumCertificate>>,
+    /// Log finalized batches are appended to
+    block_log: RwLock<Option<Arc<BlockLog>>>,
+    /// Ledger candidate reputations are read from at rotation
+    reputation_ledger: RwLock<Option<Arc<ReputationLedger>>>,
     /// Active consensus rounds
     active_rounds: RwLock<HashMap<String, ConsensusRound>>,
+    /// Final states of finished rounds and when they finished
+    finished_rounds: RwLock<HashMap<String, (ConsensusState, Instant)>>,
+    /// Requests from outside the committee, held until their round opens
+    pending_requests: RwLock<HashMap<String, PendingRequest>>,
     /// Transaction validator
-    transaction_validator: Option<Box<dyn TransactionValidator>>,
+    transaction_validator: RwLock<Option<Box<dyn TransactionValidator>>>,
     /// Proposal validator
-    proposal_validator: Option<Box<dyn ProposalValidator>>,
-    /// Message sender channel
-    message_sender: mpsc::Sender<PocMessage>,
-    /// Message receiver channel
-    message_receiver: mpsc::Receiver<PocMessage>,
+    proposal_validator: RwLock<Option<Box<dyn ProposalValidator>>>,
+    /// Event sender channel
+    event_sender: mpsc::Sender<PocEvent>,
+    /// Event receiver channel, taken when the consensus task starts
+    event_receiver: Mutex<Option<mpsc::Receiver<PocEvent>>>,
+    /// Decided values and their certificates
+    decisions: broadcast::Sender<(Vec<u8>, QuorumCertificate)>,
     /// Is the consensus mechanism running
     running: RwLock<bool>,
 }
 
 impl ProofOfCooperation {
     /// Create a new Proof of Cooperation consensus instance
+    ///
+    /// Without a signer the node follows consensus rounds but does not vote.
     pub async fn new(
-        p2p: Arc<P2pNetwork>,
-        reputation: Arc<ReputationManager>,
+        network: Arc<dyn NetworkService>,
+        signer: Option<Arc<dyn EnvelopeSigner>>,
         config: PocConfig,
-    ) -> Result<Arc<Self>, Error> {
-        let (tx, rx) = mpsc::channel(100);
-        
-        let poc = Arc::new(Self {
-            p2p,
-            reputation,
+    ) -> NetworkResult<Arc<Self>> {
+        let (tx, rx) = mpsc::channel(1000);
+        let (decisions, _) = broadcast::channel(100);
+
+        Ok(Arc::new(Self {
+            network,
             config,
+            signer,
             committee: RwLock::new(Vec::new()),
+            committee_proof: RwLock::new(None),
+            committee_certificate: RwLock::new(None),
+            previous_committee: RwLock::new(Vec::new()),
+            committee_votes: RwLock::new(Vec::new()),
+            candidates: RwLock::new(Vec::new()),
+            endorsements: RwLock::new(Vec::new()),
+            last_certificate: RwLock::new(None),
+            block_log: RwLock::new(None),
+            reputation_ledger: RwLock::new(None),
             active_rounds: RwLock::new(HashMap::new()),
-            transaction_validator: None,
-            proposal_validator: None,
-            message_sender: tx,
-            message_receiver: rx,
+            finished_rounds: RwLock::new(HashMap::new()),
+            pending_requests: RwLock::new(HashMap::new()),
+            transaction_validator: RwLock::new(None),
+            proposal_validator: RwLock::new(None),
+            event_sender: tx,
+            event_receiver: Mutex::new(Some(rx)),
+            decisions,
             running: RwLock::new(false),
-        });
-        
-        // Initialize the committee
-        poc.rotate_committee().await?;
-        
-        Ok(poc)
+        }))
     }
-    
+
     /// Start the consensus process
-    pub async fn start(&self) -> Result<(), Error> {
+    pub async fn start(self: &Arc<Self>) -> NetworkResult<()> {
         let mut running = self.running.write().await;
         if *running {
             return Ok(());
         }
-        
+
+        let mut receiver = self.event_receiver.lock().await.take()
+            .ok_or_else(|| NetworkError::ServiceError("Consensus task already started".to_string()))?;
         *running = true;
-        
-        // Start the committee rotation task
-        self.start_committee_rotation().await?;
-        
-        // Start the message processing task
-        self.start_message_processing().await?;
-        
+
+        let poc = Arc::clone(self);
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                if !*poc.running.read().await {
+                    break;
+                }
+
+                if let Err(e) = poc.process_event(event).await {
+                    debug!("Error processing consensus event: {}", e);
+                }
+            }
+        });
+
+        // Rotate the committee every rotation interval
+        let poc = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(poc.config.rotation_interval);
+            timer.tick().await;
+
+            loop {
+                timer.tick().await;
+                if !*poc.running.read().await {
+                    break;
+                }
+                if poc.candidates.read().await.is_empty() {
+                    continue;
+                }
+
+                if let Err(e) = poc.rotate_committee().await {
+                    warn!("Error rotating committee: {}", e);
+                }
+            }
+        });
+
         Ok(())
     }
-    
+
     /// Stop the consensus process
-    pub async fn stop(&self) -> Result<(), Error> {
+    pub async fn stop(&self) -> NetworkResult<()> {
         let mut running = self.running.write().await;
         *running = false;
         Ok(())
     }
-    
+
     /// Register a transaction validator
-    pub async fn register_transaction_validator(&self, validator: Box<dyn TransactionValidator>) -> Result<(), Error> {
-        let mut tx_validator = self.transaction_validator.as_mut().ok_or(Error::InvalidState("Transaction validator already registered"))?;
-        *tx_validator = validator;
+    pub async fn register_transaction_validator(&self, validator: Box<dyn TransactionValidator>) -> NetworkResult<()> {
+        *self.transaction_validator.write().await = Some(validator);
         Ok(())
     }
-    
+
     /// Register a proposal validator
-    pub async fn register_proposal_validator(&self, validator: Box<dyn ProposalValidator>) -> Result<(), Error> {
-        let mut prop_validator = self.proposal_validator.as_mut().ok_or(Error::InvalidState("Proposal validator already registered"))?;
-        *prop_validator = validator;
+    pub async fn register_proposal_validator(&self, validator: Box<dyn ProposalValidator>) -> NetworkResult<()> {
+        *self.proposal_validator.write().await = Some(validator);
         Ok(())
     }
-    
+
+    /// Set the committee used for new consensus rounds
+    ///
+    /// The committee is not backed by a selection proof or certificate; use
+    /// `rotate_committee` to select one from the candidates.
+    pub async fn set_committee(&self, committee: Vec<Validator>) {
+        *self.committee.write().await = committee;
+        *self.committee_proof.write().await = None;
+        *self.committee_certificate.write().await = None;
+    }
+
+    /// Get the current committee
+    pub async fn committee(&self) -> Vec<Validator> {
+        self.committee.read().await.clone()
+    }
+
+    /// Get the proof of the current committee's selection
+    pub async fn committee_proof(&self) -> Option<CommitteeProof> {
+        self.committee_proof.read().await.clone()
+    }
+
+    /// Get the epoch of the current committee
+    pub async fn epoch(&self) -> u64 {
+        self.committee_proof.read().await.as_ref().map_or(0, |p| p.epoch)
+    }
+
+    /// Set the candidates the next committee is selected from
+    pub async fn set_candidates(&self, candidates: Vec<Validator>) {
+        *self.candidates.write().await = candidates;
+    }
+
+    /// Set the ledger candidate reputations are read from
+    ///
+    /// At each rotation every candidate's reputation is set to its overall
+    /// ledger score, scaled to `0..=100`. Committees received from other
+    /// nodes are recomputed from the local candidates and ledger.
+    pub async fn set_reputation_ledger(&self, ledger: Arc<ReputationLedger>) {
+        *self.reputation_ledger.write().await = Some(ledger);
+    }
+
+    /// Endorse a candidate for the next committee
+    pub async fn endorse(&self, candidate: &str) -> NetworkResult<()> {
+        let signer = self.signer.as_ref()
+            .ok_or_else(|| NetworkError::ServiceError("Endorsing requires a signer".to_string()))?;
+        let endorsement = Endorsement::sign(self.epoch().await + 1, candidate, signer.as_ref())?;
+
+        self.endorsements.write().await.push(endorsement.clone());
+        self.network.broadcast(PocMessage::Endorsement(endorsement).to_network_message()?).await
+    }
+
+    /// Select the committee for the next epoch
+    ///
+    /// The selection is seeded by the certificate of the last decided round,
+    /// so nodes that saw the same decision select the same committee. If
+    /// this node is in the outgoing committee it votes for the selection.
+    pub async fn rotate_committee(&self) -> NetworkResult<Vec<Validator>> {
+        let epoch = self.epoch().await + 1;
+        let endorsements: Vec<Endorsement> = self.endorsements.read().await.iter()
+            .filter(|e| e.epoch == epoch)
+            .cloned()
+            .collect();
+
+        let proof = CommitteeProof::new(
+            &self.config,
+            epoch,
+            self.local_candidates().await,
+            endorsements,
+            self.last_certificate.read().await.clone(),
+        );
+        let committee = proof.select()?;
+
+        if let Some(signer) = &self.signer {
+            if self.committee.read().await.iter().any(|v| v.did == signer.sender()) {
+                let vote = CommitteeVote::sign(epoch, &committee, signer.as_ref())?;
+                self.committee_votes.write().await.push(vote.clone());
+                self.network.broadcast(PocMessage::CommitteeVote(vote).to_network_message()?).await?;
+            }
+        }
+
+        info!("Selected committee of {} validators for epoch {}", committee.len(), epoch);
+        self.adopt_committee(committee.clone(), proof, None).await;
+        Ok(committee)
+    }
+
+    /// Get the candidates with their reputations from the ledger, if set
+    async fn local_candidates(&self) -> Vec<Validator> {
+        let mut candidates = self.candidates.read().await.clone();
+        if let Some(ledger) = self.reputation_ledger.read().await.as_ref() {
+            for candidate in &mut candidates {
+                candidate.reputation = (ledger.overall(&candidate.did).await * 100.0).round() as i64;
+            }
+        }
+        candidates
+    }
+
+    /// Subscribe to decided values and their certificates
+    pub fn subscribe_decisions(&self) -> broadcast::Receiver<(Vec<u8>, QuorumCertificate)> {
+        self.decisions.subscribe()
+    }
+
+    /// Get the state of a consensus round
+    ///
+    /// Finished rounds are remembered for `max_round_age`.
+    pub async fn round_state(&self, round_id: &str) -> Option<ConsensusState> {
+        if let Some(round) = self.active_rounds.read().await.get(round_id) {
+            return Some(round.state.clone());
+        }
+        self.finished_rounds.read().await.get(round_id).map(|(state, _)| state.clone())
+    }
+
+    /// Set the log finalized batches are appended to
+    pub async fn set_block_log(&self, block_log: Arc<BlockLog>) {
+        *self.block_log.write().await = Some(block_log);
+    }
+
+    /// Request consensus on a value
+    ///
+    /// The request is gossiped to the committee and the round starts
+    /// locally; the decision is delivered through `subscribe_decisions`.
+    pub async fn propose(&self, value: Vec<u8>, metadata: HashMap<String, String>) -> NetworkResult<String> {
+        self.request(uuid::Uuid::new_v4().to_string(), value, metadata).await
+    }
+
+    /// Request consensus on a batch of transactions for the next block
+    ///
+    /// Every node proposing for the same height joins the same round, so at
+    /// most one batch is finalized per height. The finalized batch is
+    /// appended to the block log.
+    pub async fn propose_batch(&self, transactions: Vec<Vec<u8>>) -> NetworkResult<String> {
+        let block_log = self.block_log.read().await.clone()
+            .ok_or_else(|| NetworkError::ServiceError("No block log configured".to_string()))?;
+        let batch = block_log.next_batch(transactions).await?;
+        let metadata = HashMap::from([("type".to_string(), "batch".to_string())]);
+
+        self.request(block_round_id(batch.height), batch.encode()?, metadata).await
+    }
+
+    /// Gossip a consensus request and queue it locally
+    ///
+    /// Other nodes open the round for the request only if this node is in
+    /// the committee; otherwise they hold it until a committee member does.
+    async fn request(&self, round_id: String, value: Vec<u8>, metadata: HashMap<String, String>) -> NetworkResult<String> {
+        let (origin, signature) = match &self.signer {
+            Some(signer) => {
+                let origin = signer.sender().to_string();
+                let signature = signer.sign(&request_signing_bytes(&round_id, &value, &metadata, &origin)?)?;
+                (origin, signature)
+            }
+            None => ("system".to_string(), Vec::new()),
+        };
+
+        let request = PocMessage::Request { round_id: round_id.clone(), value, metadata, origin, signature };
+        self.network.broadcast(request.to_network_message()?).await?;
+        self.event_sender.send(PocEvent::Local(request)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to queue consensus request: {}", e)))?;
+
+        Ok(round_id)
+    }
+
+    /// Get a message handler to register with the network
+    pub fn network_handler(&self) -> Arc<dyn MessageHandler> {
+        Arc::new(PocMessageHandler { sender: self.event_sender.clone() })
+    }
+
     /// Get a message handler for the P2P network
-    pub fn message_handler(&self) -> impl Fn(&[u8]) -> Result<(), Error> + Send + Sync {
-        let sender = self.message_sender.clone();
-        
-        move |data: &[u8]| -> Result<(), Error> {
-            let message: PocMessage = serde_json::from_slice(data)?;
-            let _ = sender.try_send(message);
+    pub fn message_handler(&self) -> impl Fn(&[u8]) -> NetworkResult<()> + Send + Sync {
+        let sender = self.event_sender.clone();
+
+        move |data: &[u8]| -> NetworkResult<()> {
+            let message: PocMessage = serde_json::from_slice(data)
+                .map_err(|e| NetworkError::MessageError(format!("Failed to decode PoC message: {}", e)))?;
+            let _ = sender.try_send(PocEvent::Message(message));
             Ok(())
         }
     }
-    
+
     /// Get a handler for transaction processing
-    pub fn transaction_handler(&self) -> impl Fn(&[u8]) -> Result<bool, Error> + Send + Sync {
-        let sender = self.message_sender.clone();
-        
-        move |transaction: &[u8]| -> Result<bool, Error> {
+    pub fn transaction_handler(&self) -> impl Fn(&[u8]) -> NetworkResult<bool> + Send + Sync {
+        let sender = self.event_sender.clone();
+
+        move |transaction: &[u8]| -> NetworkResult<bool> {
             // Create a consensus round for the transaction
-            let round_id = uuid::Uuid::new_v4().to_string();
-            let proposal = PocMessage::Proposal {
-                round_id: round_id.clone(),
+            let request = PocMessage::Request {
+                round_id: uuid::Uuid::new_v4().to_string(),
                 value: transaction.to_vec(),
                 metadata: HashMap::from([("type".to_string(), "transaction".to_string())]),
                 origin: "system".to_string(),
+                signature: Vec::new(),
             };
-            
-            let _ = sender.try_send(proposal);
-            
-            // In a real implementation, we would wait for consensus
-            // For now, just return success
-            Ok(true)
+
+            // The decision is delivered through `subscribe_decisions`
+            Ok(sender.try_send(PocEvent::Local(request)).is_ok())
         }
     }
-    
+
     /// Get a handler for proposal processing
-    pub fn proposal_handler(&self) -> impl Fn(&[u8]) -> Result<bool, Error> + Send + Sync {
-        let sender = self.message_sender.clone();
-        
-        move |proposal: &[u8]| -> Result<bool, Error> {
+    pub fn proposal_handler(&self) -> impl Fn(&[u8]) -> NetworkResult<bool> + Send + Sync {
+        let sender = self.event_sender.clone();
+
+        move |proposal: &[u8]| -> NetworkResult<bool> {
             // Create a consensus round for the proposal
-            let round_id = uuid::Uuid::new_v4().to_string();
-            let proposal_msg = PocMessage::Proposal {
-                round_id: round_id.clone(),
+            let request = PocMessage::Request {
+                round_id: uuid::Uuid::new_v4().to_string(),
                 value: proposal.to_vec(),
                 metadata: HashMap::from([("type".to_string(), "governance".to_string())]),
                 origin: "system".to_string(),
+                signature: Vec::new(),
             };
-            
-            let _ = sender.try_send(proposal_msg);
-            
-            // In a real implementation, we would wait for consensus
-            // For now, just return success
-            Ok(true)
-        }
-    }
-    
-    /// Get a handler for execution events
-    pub fn execution_handler(&self) -> impl Fn(&[u8]) -> Result<(), Error> + Send + Sync {
-        move |_: &[u8]| -> Result<(), Error> {
-            // This would be implemented to handle execution of agreed-upon values
-            Ok(())
+
+            // The decision is delivered through `subscribe_decisions`
+            Ok(sender.try_send(PocEvent::Local(request)).is_ok())
         }
     }
-    
-    /// Provide events for reputation updates
-    pub fn reputation_event_emitter(&self) -> impl Fn() -> Result<HashMap<String, i64>, Error> + Send + Sync {
-        move || -> Result<HashMap<String, i64>, Error> {
-            // This would be implemented to emit reputation events based on consensus participation
-            Ok(HashMap::new())
+
+    // Private methods
+
+    async fn process_event(&self, event: PocEvent) -> NetworkResult<()> {
+        match event {
+            PocEvent::Message(message) => self.process_message(message, false).await,
+            PocEvent::Local(message) => self.process_message(message, true).await,
+            PocEvent::Timeout { round_id, round, step } => {
+                let outputs = match self.active_rounds.write().await.get_mut(&round_id) {
+                    Some(consensus_round) => consensus_round.engine.handle_timeout(round, step),
+                    None => return Ok(()),
+                };
+                self.apply_outputs(&round_id, outputs).await
+            }
         }
     }
-    
-    // Private methods
-    
-    async fn start_committee_rotation(&self) -> Result<(), Error> {
-        let poc = Arc::clone(&self);
-        
-        tokio::spawn(async move {
-            let interval = poc.config.rotation_interval;
-            let mut timer = time::interval(interval);
-            
-            loop {
-                timer.tick().await;
-                
-                if !*poc.running.read().await {
-                    break;
+
+    /// Process a consensus message
+    ///
+    /// Rounds are only opened for local requests and for messages signed by
+    /// a member of the current committee, up to `max_active_rounds` at once.
+    async fn process_message(&self, message: PocMessage, local: bool) -> NetworkResult<()> {
+        let Some(round_id) = message.round_id().map(str::to_string) else {
+            return match message {
+                PocMessage::CommitteeRequest { requester } => self.process_committee_request(requester).await,
+                PocMessage::CommitteeResponse { committee, proof, certificate, .. } => {
+                    self.process_committee_response(committee, proof, certificate).await
                 }
-                
-                if let Err(e) = poc.rotate_committee().await {
-                    eprintln!("Error rotating committee: {:?}", e);
+                PocMessage::Endorsement(endorsement) => self.process_endorsement(endorsement).await,
+                PocMessage::CommitteeVote(vote) => self.process_committee_vote(vote).await,
+                _ => Ok(()),
+            };
+        };
+
+        // Validate proposed values before the engine sees them
+        let valid = match &message {
+            PocMessage::Proposal(proposal) => {
+                self.validate_value(&round_id, &proposal.value, &proposal.metadata).await
+            }
+            _ => true,
+        };
+
+        if self.finished_rounds.read().await.contains_key(&round_id) {
+            return Ok(());
+        }
+
+        let mut rounds = self.active_rounds.write().await;
+        if !rounds.contains_key(&round_id) {
+            let committee = self.committee.read().await.clone();
+            self.prune_rounds(&mut rounds).await;
+
+            if !local {
+                if let Err(e) = verify_opening(&message, &committee, self.config.consensus_threshold) {
+                    if let PocMessage::Request { value, metadata, .. } = message {
+                        self.hold_request(round_id, value, metadata).await;
+                        return Ok(());
+                    }
+                    return Err(e);
                 }
             }
-        });
-        
-        Ok(())
+            if rounds.len() >= self.config.max_active_rounds {
+                return Err(NetworkError::ConsensusError(format!(
+                    "Not opening round {}: {} rounds already active", round_id, rounds.len()
+                )));
+            }
+
+            let mut engine = BftEngine::new(
+                round_id.clone(),
+                committee,
+                self.config.consensus_threshold,
+                self.signer.clone(),
+                self.config.consensus_timeout,
+            );
+            if let Some(pending) = self.pending_requests.write().await.remove(&round_id) {
+                engine.set_own_value(pending.value, pending.metadata);
+            }
+            rounds.insert(round_id.clone(), ConsensusRound {
+                id: round_id.clone(),
+                state: ConsensusState::Preparing,
+                engine,
+                start_time: Instant::now(),
+                end_time: None,
+            });
+        }
+        let consensus_round = rounds.get_mut(&round_id)
+            .ok_or_else(|| NetworkError::InternalError(format!("Missing consensus round {}", round_id)))?;
+
+        // The requested value is proposed if this node is the first proposer
+        if let PocMessage::Request { value, metadata, .. } = &message {
+            if local || verify_opening(&message, consensus_round.engine.committee(), self.config.consensus_threshold).is_ok() {
+                consensus_round.engine.set_own_value(value.clone(), metadata.clone());
+            }
+        }
+        let mut outputs = consensus_round.engine.start();
+        consensus_round.state = match consensus_round.state {
+            ConsensusState::Preparing => ConsensusState::Collecting,
+            ref state => state.clone(),
+        };
+
+        let result = match message {
+            PocMessage::Proposal(proposal) => consensus_round.engine.handle_proposal(proposal, valid),
+            PocMessage::Vote(vote) => consensus_round.engine.handle_vote(vote),
+            PocMessage::ConsensusReached { value, certificate, .. } => {
+                consensus_round.engine.handle_decision(value, certificate)
+            }
+            PocMessage::Request { .. }
+            | PocMessage::CommitteeRequest { .. }
+            | PocMessage::CommitteeResponse { .. }
+            | PocMessage::Endorsement(_)
+            | PocMessage::CommitteeVote(_) => Ok(Vec::new()),
+        };
+        drop(rounds);
+
+        match result {
+            Ok(more) => outputs.extend(more),
+            Err(e) => warn!("Rejected consensus message for {}: {}", round_id, e),
+        }
+        self.apply_outputs(&round_id, outputs).await
     }
-    
-    async fn start_message_processing(&self) -> Result<(), Error> {
-        let poc = Arc::clone(&self);
-        
-        tokio::spawn(async move {
-            let mut receiver = poc.message_receiver.clone();
-            
-            while let Some(message) = receiver.recv().await {
-                if !*poc.running.read().await {
-                    break;
-                }
-                
-                if let Err(e) = poc.process_message(message).await {
-                    eprintln!("Error processing message: {:?}", e);
-                }
+
+    /// Hold a request from outside the committee until its round opens
+    async fn hold_request(&self, round_id: String, value: Vec<u8>, metadata: HashMap<String, String>) {
+        let mut pending = self.pending_requests.write().await;
+        if pending.len() >= self.config.max_active_rounds && !pending.contains_key(&round_id) {
+            debug!("Dropping request for {}: too many pending requests", round_id);
+            return;
+        }
+        pending.entry(round_id).or_insert(PendingRequest { value, metadata, received: Instant::now() });
+    }
+
+    /// Drop rounds, requests and finished round states older than `max_round_age`
+    async fn prune_rounds(&self, rounds: &mut HashMap<String, ConsensusRound>) {
+        let max_age = self.config.max_round_age;
+        let mut finished = self.finished_rounds.write().await;
+
+        rounds.retain(|round_id, round| {
+            if round.start_time.elapsed() <= max_age {
+                return true;
             }
+            debug!("Dropping consensus round {} after {:?}", round_id, max_age);
+            finished.insert(round_id.clone(), (ConsensusState::TimedOut, Instant::now()));
+            false
         });
-        
-        Ok(())
+        finished.retain(|_, (_, at)| at.elapsed() <= max_age);
+        self.pending_requests.write().await.retain(|_, request| request.received.elapsed() <= max_age);
     }
-    
-    async fn rotate_committee(&self) -> Result<(), Error> {
-        let mut committee = self.committee.write().await;
-        
-        // In a real implementation, this would:
-        // 1. Select validators based on the configured strategy
-        // 2. Consider federation structure if federation_aware is true
-        // 3. Use reputation scores for selection if using ReputationBased strategy
-        // 4. Ensure proper distribution of validators across federations
-        
-        // For this skeleton, we'll just create a simple committee
-        *committee = vec![
-            Validator {
-                did: "did:icn:validator1".to_string(),
-                reputation: 100,
-                federation_id: Some("federation1".to_string()),
-                last_active: chrono::Utc::now(),
-            },
-            Validator {
-                did: "did:icn:validator2".to_string(),
-                reputation: 90,
-                federation_id: Some("federation1".to_string()),
-                last_active: chrono::Utc::now(),
-            },
-            Validator {
-                did: "did:icn:validator3".to_string(),
-                reputation: 80,
-                federation_id: Some("federation2".to_string()),
-                last_active: chrono::Utc::now(),
-            },
-            Validator {
-                did: "did:icn:validator4".to_string(),
-                reputation: 70,
-                federation_id: Some("federation2".to_string()),
-                last_active: chrono::Utc::now(),
+
+    async fn validate_value(&self, round_id: &str, value: &[u8], metadata: &HashMap<String, String>) -> bool {
+        if round_id.starts_with(BLOCK_ROUND_PREFIX) {
+            return self.validate_batch(round_id, value).await;
+        }
+
+        let result = match metadata.get("type").map(String::as_str) {
+            Some("transaction") => match &*self.transaction_validator.read().await {
+                Some(validator) => validator.validate_transaction(value).await,
+                None => Ok(true),
             },
-            Validator {
-                did: "did:icn:validator5".to_string(),
-                reputation: 60,
-                federation_id: Some("federation3".to_string()),
-                last_active: chrono::Utc::now(),
+            Some("governance") => match &*self.proposal_validator.read().await {
+                Some(validator) => validator.validate_proposal(value).await,
+                None => Ok(true),
             },
-        ];
-        
-        Ok(())
+            _ => Ok(true),
+        };
+
+        result.unwrap_or_else(|e| {
+            warn!("Failed to validate proposed value: {}", e);
+            false
+        })
     }
-    
-    async fn process_message(&self, message: PocMessage) -> Result<(), Error> {
-        match message {
-            PocMessage::Proposal { round_id, value, metadata, origin } => {
-                self.process_proposal(round_id, value, metadata, origin).await?;
-            }
-            PocMessage::Vote(vote) => {
-                self.process_vote(vote).await?;
-            }
-            PocMessage::ConsensusReached { round_id, value, approving_validators } => {
-                self.process_consensus_reached(round_id, value, approving_validators).await?;
-            }
-            PocMessage::CommitteeRequest { requester } => {
-                self.process_committee_request(requester).await?;
+
+    async fn validate_batch(&self, round_id: &str, value: &[u8]) -> bool {
+        let Ok(batch) = Batch::decode(value) else {
+            return false;
+        };
+        if round_id != block_round_id(batch.height) {
+            return false;
+        }
+
+        // A node that is behind can't check the parent and leaves it to the committee
+        if let Some(block_log) = &*self.block_log.read().await {
+            match block_log.next_batch(Vec::new()).await {
+                Ok(next) if next.height == batch.height && next.parent_hash != batch.parent_hash => return false,
+                Ok(next) if next.height > batch.height => return false,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Failed to read block log: {}", e);
+                    return false;
+                }
             }
-            PocMessage::CommitteeResponse { .. } => {
-                // Handle committee response if needed
+        }
+
+        if let Some(validator) = &*self.transaction_validator.read().await {
+            for transaction in &batch.transactions {
+                match validator.validate_transaction(transaction).await {
+                    Ok(true) => {}
+                    Ok(false) => return false,
+                    Err(e) => {
+                        warn!("Failed to validate transaction in batch: {}", e);
+                        return false;
+                    }
+                }
             }
         }
-        
-        Ok(())
+
+        true
     }
-    
-    async fn process_proposal(
-        &self,
-        round_id: String,
-        value: Vec<u8>,
-        metadata: HashMap<String, String>,
-        origin: String,
-    ) -> Result<(), Error> {
-        // Create a new consensus round
-        let round = ConsensusRound {
-            id: round_id.clone(),
-            proposed_value: value.clone(),
-            state: ConsensusState::Preparing,
-            validators: self.committee.read().await.clone(),
-            votes: HashMap::new(),
-            start_time: chrono::Utc::now(),
-            end_time: None,
-        };
-        
-        // Store the round
-        self.active_rounds.write().await.insert(round_id.clone(), round);
-        
-        // Validate the proposed value if applicable
-        let valid = match metadata.get("type") {
-            Some(typ) if typ == "transaction" => {
-                if let Some(validator) = &self.transaction_validator {
-                    validator.validate_transaction(&value).await?
-                } else {
-                    true
+
+    async fn apply_outputs(&self, round_id: &str, outputs: Vec<BftOutput>) -> NetworkResult<()> {
+        for output in outputs {
+            match output {
+                BftOutput::Broadcast(message) => {
+                    self.network.broadcast(message.to_network_message()?).await?;
                 }
-            }
-            Some(typ) if typ == "governance" => {
-                if let Some(validator) = &self.proposal_validator {
-                    validator.validate_proposal(&value).await?
-                } else {
-                    true
+                BftOutput::ScheduleTimeout { round, step, after } => {
+                    let sender = self.event_sender.clone();
+                    let round_id = round_id.to_string();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(after).await;
+                        let _ = sender.send(PocEvent::Timeout { round_id, round, step }).await;
+                    });
+                }
+                BftOutput::Decided { value, certificate } => {
+                    // Decided rounds are dropped; later messages for them are ignored
+                    self.active_rounds.write().await.remove(round_id);
+                    self.finished_rounds.write().await.insert(round_id.to_string(), (ConsensusState::Reached, Instant::now()));
+                    *self.last_certificate.write().await = Some(certificate.clone());
+                    if round_id.starts_with(BLOCK_ROUND_PREFIX) {
+                        self.append_block(&value, &certificate).await;
+                    }
+                    let _ = self.decisions.send((value, certificate));
                 }
             }
-            _ => true,
+        }
+
+        Ok(())
+    }
+
+    async fn append_block(&self, value: &[u8], certificate: &QuorumCertificate) {
+        let Some(block_log) = self.block_log.read().await.clone() else {
+            return;
+        };
+
+        let result = match Batch::decode(value) {
+            Ok(batch) => block_log.append(batch, certificate.clone()).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(block) => info!("Finalized block {} with {} transactions", block.header.height, block.header.transaction_count),
+            Err(e) => warn!("Failed to append finalized batch from {}: {}", certificate.round_id, e),
+        }
+    }
+
+    async fn process_committee_request(&self, requester: String) -> NetworkResult<()> {
+        debug!("Committee requested by {}", requester);
+        let committee = self.committee.read().await;
+        let response = PocMessage::CommitteeResponse {
+            committee: committee.iter().map(|v| v.did.clone()).collect(),
+            rotation_period: self.config.rotation_interval.as_secs(),
+            proof: self.committee_proof.read().await.clone(),
+            certificate: self.committee_certificate.read().await.clone(),
+        };
+
+        self.network.broadcast(response.to_network_message()?).await
+    }
+
+    /// Adopt the committee of the next epoch from another node
+    ///
+    /// The committee is recomputed from the local candidates and reputation
+    /// ledger, and must be certified by a quorum of the current committee.
+    async fn process_committee_response(
+        &self,
+        committee: Vec<String>,
+        proof: Option<CommitteeProof>,
+        certificate: Option<CommitteeCertificate>,
+    ) -> NetworkResult<()> {
+        let (Some(proof), Some(certificate)) = (proof, certificate) else {
+            debug!("Ignoring committee response without a selection proof and certificate");
+            return Ok(());
         };
-        
-        if !valid {
-            // If invalid, remove the round and reject the proposal
-            self.active_rounds.write().await.remove(&round_id);
+        let epoch = self.epoch().await + 1;
+        if proof.epoch != epoch || certificate.epoch != epoch {
             return Ok(());
         }
-        
-        // Start the consensus process
-        let mut round = self.active_rounds.write().await.get_mut(&round_id).ok_or(Error::NotFound)?;
-        round.state = ConsensusState::Collecting;
-        
-        // Cast own vote (if we're a validator)
-        // In a real implementation, we would check if we're in the committee
-        // and then cast a vote after validating the proposal
-        
-        // Distribute the proposal to other validators
-        // In a real implementation, this would send the proposal to the committee members
-        
+
+        let current = self.committee.read().await.clone();
+        if proof.previous_certificate.is_none() && !current.is_empty() {
+            return Err(NetworkError::ConsensusError(format!(
+                "committee for epoch {} is not seeded by a decided round", epoch
+            )));
+        }
+
+        // Only the seed and endorsements are taken from the sender; both are checked
+        let local = CommitteeProof::new(
+            &self.config,
+            epoch,
+            self.local_candidates().await,
+            proof.endorsements,
+            proof.previous_certificate,
+        );
+        let selected = local.verify(&self.config, &committee, &current)?;
+        certificate.verify(&selected, &current, self.config.consensus_threshold)?;
+
+        info!("Adopting committee of {} validators for epoch {}", selected.len(), epoch);
+        self.adopt_committee(selected, local, Some(certificate)).await;
         Ok(())
     }
-    
-    async fn process_vote(&self, vote: Vote) -> Result<(), Error> {
-        let mut rounds = self.active_rounds.write().await;
-        let round = rounds.get_mut(&vote.round_id).ok_or(Error::NotFound)?;
-        
-        // Verify the vote is from a committee member
-        if !round.validators.iter().any(|v| v.did == vote.validator_did) {
-            return Err(Error::Unauthorized("Validator not in committee".into()));
-        }
-        
-        // Verify the vote signature
-        // In a real implementation, this would verify the signature against the validator's DID
-        
-        // Record the vote
-        round.votes.insert(vote.validator_did.clone(), vote.approve);
-        
-        // Check if we've reached consensus
-        let required_votes = (round.validators.len() as f64 * self.config.consensus_threshold).ceil() as usize;
-        let approval_votes = round.votes.values().filter(|&&approve| approve).count();
-        
-        if approval_votes >= required_votes {
-            // We've reached consensus
-            round.state = ConsensusState::Reached;
-            round.end_time = Some(chrono::Utc::now());
-            
-            // Get the list of approving validators
-            let approving_validators: Vec<String> = round.votes.iter()
-                .filter(|(_, &approve)| approve)
-                .map(|(did, _)| did.clone())
-                .collect();
-            
-            // Notify all participants of the consensus
-            let consensus_message = PocMessage::ConsensusReached {
-                round_id: vote.round_id,
-                value: round.proposed_value.clone(),
-                approving_validators,
-            };
-            
-            // In a real implementation, this would broadcast the consensus result
-            
-            // Execute the agreed value
-            // In a real implementation, this would trigger execution of the transaction or proposal
+
+    /// Record a vote for the current or next committee
+    async fn process_committee_vote(&self, vote: CommitteeVote) -> NetworkResult<()> {
+        let epoch = self.epoch().await;
+        if vote.epoch == epoch + 1 {
+            vote.verify(&self.committee.read().await)?;
+        } else if vote.epoch == epoch && epoch > 0 {
+            vote.verify(&self.previous_committee.read().await)?;
+        } else {
+            return Ok(());
         }
-        
-        Ok(())
+
+        {
+            let mut votes = self.committee_votes.write().await;
+            if votes.iter().any(|v| v.epoch == vote.epoch && v.validator_did == vote.validator_did) {
+                return Ok(());
+            }
+            votes.push(vote);
+        }
+        self.certify_committee().await
     }
-    
-    async fn process_consensus_reached(
-        &self,
-        round_id: String,
-        value: Vec<u8>,
-        approving_validators: Vec<String>,
-    ) -> Result<(), Error> {
-        // If we're not the originator of the consensus, record it
-        if !self.active_rounds.read().await.contains_key(&round_id) {
-            // In a real implementation, this would verify the consensus and execute if valid
-        }
-        
-        // Acknowledge and update reputation for participating validators
-        for validator_did in approving_validators {
-            // In a real implementation, this would update reputation scores
-            self.reputation.record_context_success(&validator_did, ReputationContext::Consensus).await?;
-        }
-        
+
+    /// Assemble the certificate of the current committee once enough votes arrived
+    async fn certify_committee(&self) -> NetworkResult<()> {
+        if self.committee_certificate.read().await.is_some() {
+            return Ok(());
+        }
+
+        let certificate = CommitteeCertificate::from_votes(
+            self.epoch().await,
+            &self.committee.read().await,
+            &self.committee_votes.read().await,
+            &self.previous_committee.read().await,
+            self.config.consensus_threshold,
+        )?;
+        if certificate.is_some() {
+            *self.committee_certificate.write().await = certificate;
+        }
         Ok(())
     }
-    
-    async fn process_committee_request(&self, requester: String) -> Result<(), Error> {
-        let committee = self.committee.read().await;
-        let committee_dids: Vec<String> = committee.iter().map(|v| v.did.clone()).collect();
-        
-        // Send the committee information to the requester
-        let response = PocMessage::CommitteeResponse {
-            committee: committee_dids,
-            rotation_period: 0, // In a real implementation, this would be the current period
-        };
-        
-        // In a real implementation, this would send the response to the requester
-        
+
+    async fn process_endorsement(&self, endorsement: Endorsement) -> NetworkResult<()> {
+        if endorsement.epoch <= self.epoch().await {
+            return Ok(());
+        }
+        endorsement.verify(&self.candidates.read().await)?;
+
+        let mut endorsements = self.endorsements.write().await;
+        if !endorsements.contains(&endorsement) {
+            endorsements.push(endorsement);
+        }
         Ok(())
     }
+
+    async fn adopt_committee(&self, committee: Vec<Validator>, proof: CommitteeProof, certificate: Option<CommitteeCertificate>) {
+        let epoch = proof.epoch;
+        let previous = std::mem::replace(&mut *self.committee.write().await, committee);
+        *self.previous_committee.write().await = previous;
+        *self.committee_proof.write().await = Some(proof);
+        *self.committee_certificate.write().await = certificate;
+        self.endorsements.write().await.retain(|e| e.epoch > epoch);
+        self.committee_votes.write().await.retain(|v| v.epoch >= epoch);
+
+        if let Err(e) = self.certify_committee().await {
+            warn!("Failed to certify committee for epoch {}: {}", epoch, e);
+        }
+    }
+}
+
+/// Forwards gossiped PoC messages to the consensus task
+struct PocMessageHandler {
+    /// Event sender channel
+    sender: mpsc::Sender<PocEvent>,
+}
+
+#[async_trait]
+impl MessageHandler for PocMessageHandler {
+    fn id(&self) -> usize {
+        0
+    }
+
+    fn name(&self) -> &str {
+        "proof_of_cooperation"
+    }
+
+    async fn handle_message(&self, message: &NetworkMessage, _peer: &PeerInfo) -> NetworkResult<()> {
+        let message = PocMessage::from_network_message(message)?;
+        self.sender.send(PocEvent::Message(message)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Consensus task stopped: {}", e)))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
-    // Tests would be implemented here
-} 
\ No newline at end of file
+    use crate::consensus::bft::{QuorumCertificate, VotePhase};
+    use crate::envelope::IdentitySigner;
+    use crate::{Multiaddr, PeerId};
+    use icn_core::crypto::IdentityKeyPair;
+
+    /// Network dropping everything sent through it
+    struct NullNetwork;
+
+    #[async_trait]
+    impl NetworkService for NullNetwork {
+        async fn start(&self) -> NetworkResult<()> { Ok(()) }
+        async fn stop(&self) -> NetworkResult<()> { Ok(()) }
+        async fn broadcast(&self, _message: NetworkMessage) -> NetworkResult<()> { Ok(()) }
+        async fn send_to(&self, _peer_id: &str, _message: NetworkMessage) -> NetworkResult<()> { Ok(()) }
+        async fn connect(&self, _address: Multiaddr) -> NetworkResult<PeerId> {
+            Err(NetworkError::ServiceError("not connected".to_string()))
+        }
+        async fn disconnect(&self, _peer_id: &str) -> NetworkResult<()> { Ok(()) }
+        async fn get_peer_info(&self, peer_id: &str) -> NetworkResult<PeerInfo> {
+            Err(NetworkError::PeerNotFound(peer_id.to_string()))
+        }
+        async fn get_connected_peers(&self) -> NetworkResult<Vec<PeerInfo>> { Ok(Vec::new()) }
+        async fn register_message_handler(&self, _message_type: &str, _handler: Arc<dyn MessageHandler>) -> NetworkResult<()> {
+            Ok(())
+        }
+        async fn subscribe_messages(&self) -> NetworkResult<mpsc::Receiver<(String, NetworkMessage)>> {
+            Ok(mpsc::channel(1).1)
+        }
+    }
+
+    fn signer(did: &str) -> Arc<dyn EnvelopeSigner> {
+        Arc::new(IdentitySigner::new(did, IdentityKeyPair::generate().unwrap()))
+    }
+
+    fn validator(signer: &Arc<dyn EnvelopeSigner>) -> Validator {
+        Validator {
+            did: signer.sender().to_string(),
+            public_key: signer.public_key(),
+            reputation: 100,
+            federation_id: None,
+        }
+    }
+
+    fn prevote(round_id: &str, signer: &Arc<dyn EnvelopeSigner>) -> PocMessage {
+        PocMessage::Vote(Vote::sign(round_id, 0, VotePhase::Prevote, None, signer.as_ref()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_rounds_open_only_for_committee_messages() {
+        let members: Vec<_> = (0..4).map(|i| signer(&format!("did:icn:validator{}", i))).collect();
+        let outsider = signer("did:icn:outsider");
+        let config = PocConfig { max_active_rounds: 2, ..PocConfig::default() };
+        let poc = ProofOfCooperation::new(Arc::new(NullNetwork), None, config).await.unwrap();
+        poc.set_committee(members.iter().map(validator).collect()).await;
+
+        // Unsigned requests and votes from outside the committee don't open rounds
+        let request = PocMessage::Request {
+            round_id: "r1".to_string(),
+            value: b"value".to_vec(),
+            metadata: HashMap::new(),
+            origin: members[0].sender().to_string(),
+            signature: Vec::new(),
+        };
+        poc.process_message(request, false).await.unwrap();
+        assert!(poc.process_message(prevote("r1", &outsider), false).await.is_err());
+        assert_eq!(poc.round_state("r1").await, None);
+
+        // A committee member's vote does, picking up the held request
+        poc.process_message(prevote("r1", &members[1]), false).await.unwrap();
+        assert_eq!(poc.round_state("r1").await, Some(ConsensusState::Collecting));
+        assert!(poc.pending_requests.read().await.is_empty());
+
+        // Signed requests from committee members open rounds up to the limit
+        let value = b"value".to_vec();
+        let origin = members[0].sender().to_string();
+        let signature = members[0].sign(&request_signing_bytes("r2", &value, &HashMap::new(), &origin).unwrap()).unwrap();
+        let request = PocMessage::Request { round_id: "r2".to_string(), value, metadata: HashMap::new(), origin, signature };
+        poc.process_message(request, false).await.unwrap();
+        assert_eq!(poc.round_state("r2").await, Some(ConsensusState::Collecting));
+
+        assert!(poc.process_message(prevote("r3", &members[1]), false).await.is_err());
+        assert_eq!(poc.round_state("r3").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_committee_responses_need_local_selection_and_certificate() {
+        let members: Vec<_> = (0..4).map(|i| signer(&format!("did:icn:validator{}", i))).collect();
+        let config = PocConfig { committee_size: 3, min_reputation: 0, ..PocConfig::default() };
+        let poc = ProofOfCooperation::new(Arc::new(NullNetwork), None, config.clone()).await.unwrap();
+        let validators: Vec<Validator> = members.iter().map(validator).collect();
+        poc.set_committee(validators.clone()).await;
+        poc.set_candidates(validators.clone()).await;
+
+        // A round decided by the current committee seeds the selection
+        let hash = value_hash(b"value");
+        let precommits = members[..3].iter()
+            .map(|s| Vote::sign("r1", 0, VotePhase::Precommit, Some(hash.clone()), s.as_ref()).unwrap())
+            .collect();
+        let decided = QuorumCertificate { round_id: "r1".to_string(), round: 0, value_hash: hash, precommits };
+        let proof = CommitteeProof::new(&config, 1, validators.clone(), vec![], Some(decided));
+        let committee = proof.select().unwrap();
+        let dids: Vec<String> = committee.iter().map(|v| v.did.clone()).collect();
+        let response = |proof: CommitteeProof, certificate| PocMessage::CommitteeResponse {
+            committee: dids.clone(),
+            rotation_period: 0,
+            proof: Some(proof),
+            certificate,
+        };
+
+        // Uncertified committees are ignored
+        poc.process_message(response(proof.clone(), None), false).await.unwrap();
+        assert_eq!(poc.epoch().await, 0);
+
+        // Proofs claiming other reputations are recomputed from the local candidates
+        let votes: Vec<CommitteeVote> = members[..3].iter()
+            .map(|s| CommitteeVote::sign(1, &committee, s.as_ref()).unwrap())
+            .collect();
+        let certificate = CommitteeCertificate::from_votes(1, &committee, &votes, &validators, config.consensus_threshold)
+            .unwrap();
+        let mut inflated = proof.clone();
+        for candidate in &mut inflated.candidates {
+            candidate.reputation = if candidate.did == dids[0] { 1000 } else { 0 };
+        }
+        poc.process_message(response(inflated, certificate.clone()), false).await.unwrap();
+        assert_eq!(poc.committee().await, committee);
+        assert_eq!(poc.epoch().await, 1);
+
+        // Committees are only adopted for the next epoch
+        poc.process_message(response(proof, certificate), false).await.unwrap();
+        assert_eq!(poc.epoch().await, 1);
+    }
+}
@@ -0,0 +1,392 @@
+//! Deterministic simulation of BFT consensus rounds
+//!
+//! Runs a committee of `BftEngine`s over a simulated network with a virtual
+//! clock. Delivery latency is drawn from a seeded RNG so every run is
+//! reproducible, and a configurable number of validators can be silent or
+//! equivocate. Faulty validators are the first proposers of the round, so
+//! the honest validators always have to change rounds at least once.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use icn_core::crypto::IdentityKeyPair;
+
+use super::bft::{value_hash, BftEngine, BftOutput, Proposal, QuorumCertificate, Step, Vote};
+use super::proof_of_cooperation::{PocMessage, Validator};
+use crate::envelope::{EnvelopeSigner, IdentitySigner};
+
+/// ID of the simulated consensus round
+const ROUND_ID: &str = "simulated-round";
+/// Fraction of the committee required for a quorum
+const THRESHOLD: f64 = 0.67;
+
+/// How a simulated validator behaves
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Behaviour {
+    /// Follows the protocol
+    Honest,
+    /// Never sends anything
+    Silent,
+    /// Sends conflicting proposals and votes to different halves of the committee
+    Equivocating,
+}
+
+/// Simulation parameters
+#[derive(Clone, Debug)]
+struct SimConfig {
+    /// Committee size
+    validators: usize,
+    /// Number of silent validators
+    silent: usize,
+    /// Number of equivocating validators
+    equivocating: usize,
+    /// RNG seed
+    seed: u64,
+    /// Maximum message delivery latency
+    max_latency_ms: u64,
+    /// Step timeout of the first round
+    base_timeout_ms: u64,
+    /// Virtual time after which the simulation stops
+    time_limit_ms: u64,
+}
+
+impl SimConfig {
+    fn new(validators: usize, seed: u64) -> Self {
+        Self {
+            validators,
+            silent: 0,
+            equivocating: 0,
+            seed,
+            max_latency_ms: 50,
+            base_timeout_ms: 200,
+            time_limit_ms: 60_000,
+        }
+    }
+}
+
+/// A scheduled simulation event
+enum Event {
+    /// Deliver a message to a validator
+    Deliver { to: usize, message: Box<PocMessage> },
+    /// Fire a validator's step timeout
+    Timeout { node: usize, round: u32, step: Step },
+}
+
+/// A simulated validator
+struct Node {
+    /// Behaviour of the validator
+    behaviour: Behaviour,
+    /// Signer, used by equivocating validators to forge conflicting messages
+    signer: Arc<dyn EnvelopeSigner>,
+    /// Consensus state machine
+    engine: BftEngine,
+}
+
+/// Outcome of a simulation
+struct SimResult {
+    /// Committee the simulation ran with
+    committee: Vec<Validator>,
+    /// Behaviour of each validator
+    behaviours: Vec<Behaviour>,
+    /// Decision of each validator, if any
+    decisions: Vec<Option<(Vec<u8>, QuorumCertificate)>>,
+}
+
+impl SimResult {
+    /// Get the decisions of the honest validators
+    fn honest_decisions(&self) -> Vec<Option<&(Vec<u8>, QuorumCertificate)>> {
+        self.decisions.iter()
+            .zip(&self.behaviours)
+            .filter(|(_, b)| **b == Behaviour::Honest)
+            .map(|(d, _)| d.as_ref())
+            .collect()
+    }
+
+    /// Check that no two honest validators decided differently
+    fn assert_agreement(&self) {
+        let values: Vec<&Vec<u8>> = self.honest_decisions().into_iter().flatten().map(|(v, _)| v).collect();
+        assert!(values.windows(2).all(|w| w[0] == w[1]), "honest validators decided different values");
+    }
+
+    /// Check that every honest validator decided with a valid certificate
+    fn assert_all_decided(&self) {
+        for decision in self.honest_decisions() {
+            let (value, certificate) = decision.expect("honest validator did not decide");
+            assert_eq!(certificate.value_hash, value_hash(value));
+            certificate.verify(&self.committee, THRESHOLD).unwrap();
+        }
+    }
+}
+
+/// Simulated network of validators
+struct Simulation {
+    /// Validators
+    nodes: Vec<Node>,
+    /// Pending events by delivery time and insertion order
+    queue: BTreeMap<(u64, u64), Event>,
+    /// Current virtual time in milliseconds
+    now: u64,
+    /// Counter keeping events at the same time in insertion order
+    seq: u64,
+    /// RNG for delivery latency
+    rng: StdRng,
+    /// Maximum message delivery latency
+    max_latency_ms: u64,
+    /// Messages each validator has seen, for relaying like gossip
+    seen: HashSet<(usize, Vec<u8>)>,
+}
+
+impl Simulation {
+    fn run(config: SimConfig) -> SimResult {
+        let signers: Vec<Arc<dyn EnvelopeSigner>> = (0..config.validators)
+            .map(|i| {
+                let signer = IdentitySigner::new(format!("did:icn:validator{}", i), IdentityKeyPair::generate().unwrap());
+                Arc::new(signer) as Arc<dyn EnvelopeSigner>
+            })
+            .collect();
+        let committee: Vec<Validator> = signers.iter()
+            .map(|s| Validator {
+                did: s.sender().to_string(),
+                public_key: s.public_key(),
+                reputation: 100,
+                federation_id: None,
+            })
+            .collect();
+
+        // The first proposers are the faulty validators
+        let observer = BftEngine::new(ROUND_ID, committee.clone(), THRESHOLD, None, Duration::ZERO);
+        let mut behaviours = vec![Behaviour::Honest; config.validators];
+        for round in 0..(config.silent + config.equivocating) as u32 {
+            let proposer = &observer.proposer(round).unwrap().did;
+            let index = committee.iter().position(|v| &v.did == proposer).unwrap();
+            behaviours[index] = if (round as usize) < config.silent {
+                Behaviour::Silent
+            } else {
+                Behaviour::Equivocating
+            };
+        }
+
+        let nodes = signers.into_iter()
+            .zip(&behaviours)
+            .enumerate()
+            .map(|(i, (signer, behaviour))| {
+                let mut engine = BftEngine::new(
+                    ROUND_ID,
+                    committee.clone(),
+                    THRESHOLD,
+                    Some(signer.clone()),
+                    Duration::from_millis(config.base_timeout_ms),
+                );
+                engine.set_own_value(format!("value-{}", i).into_bytes(), HashMap::new());
+                Node { behaviour: *behaviour, signer, engine }
+            })
+            .collect();
+
+        let mut sim = Simulation {
+            nodes,
+            queue: BTreeMap::new(),
+            now: 0,
+            seq: 0,
+            rng: StdRng::seed_from_u64(config.seed),
+            max_latency_ms: config.max_latency_ms,
+            seen: HashSet::new(),
+        };
+
+        for node in 0..sim.nodes.len() {
+            if sim.nodes[node].behaviour != Behaviour::Silent {
+                let outputs = sim.nodes[node].engine.start();
+                sim.apply(node, outputs);
+            }
+        }
+
+        while let Some(((time, _), event)) = sim.queue.pop_first() {
+            if time > config.time_limit_ms || sim.all_honest_decided() {
+                break;
+            }
+            sim.now = time;
+            sim.handle(event);
+        }
+
+        SimResult {
+            committee,
+            behaviours,
+            decisions: sim.nodes.iter().map(|n| n.engine.decision().cloned()).collect(),
+        }
+    }
+
+    fn all_honest_decided(&self) -> bool {
+        self.nodes.iter()
+            .filter(|n| n.behaviour == Behaviour::Honest)
+            .all(|n| n.engine.decision().is_some())
+    }
+
+    fn schedule(&mut self, after: u64, event: Event) {
+        self.seq += 1;
+        self.queue.insert((self.now + after, self.seq), event);
+    }
+
+    fn handle(&mut self, event: Event) {
+        let (node, outputs) = match event {
+            Event::Deliver { to, message } => {
+                if self.nodes[to].behaviour == Behaviour::Silent {
+                    return;
+                }
+                // Honest validators relay new messages, as gossipsub does
+                let encoded = serde_json::to_vec(&message).unwrap();
+                if !self.seen.insert((to, encoded)) {
+                    return;
+                }
+                if self.nodes[to].behaviour == Behaviour::Honest {
+                    self.send_to_all(to, &message);
+                }
+                let engine = &mut self.nodes[to].engine;
+                let result = match *message {
+                    PocMessage::Proposal(proposal) => engine.handle_proposal(proposal, true),
+                    PocMessage::Vote(vote) => engine.handle_vote(vote),
+                    PocMessage::ConsensusReached { value, certificate, .. } => engine.handle_decision(value, certificate),
+                    _ => Ok(Vec::new()),
+                };
+                // Invalid messages from faulty validators are ignored
+                (to, result.unwrap_or_default())
+            }
+            Event::Timeout { node, round, step } => (node, self.nodes[node].engine.handle_timeout(round, step)),
+        };
+        self.apply(node, outputs);
+    }
+
+    fn apply(&mut self, from: usize, outputs: Vec<BftOutput>) {
+        for output in outputs {
+            match output {
+                BftOutput::Broadcast(message) => {
+                    self.seen.insert((from, serde_json::to_vec(&message).unwrap()));
+                    self.send_to_all(from, &message);
+                }
+                BftOutput::ScheduleTimeout { round, step, after } => {
+                    self.schedule(after.as_millis() as u64, Event::Timeout { node: from, round, step });
+                }
+                BftOutput::Decided { .. } => {}
+            }
+        }
+    }
+
+    /// Send a message to every other validator
+    fn send_to_all(&mut self, from: usize, message: &PocMessage) {
+        for to in (0..self.nodes.len()).filter(|to| *to != from) {
+            let message = match self.nodes[from].behaviour {
+                Behaviour::Equivocating if to % 2 == 1 => self.conflicting(from, message),
+                _ => message.clone(),
+            };
+            let latency = self.rng.gen_range(1..=self.max_latency_ms);
+            self.schedule(latency, Event::Deliver { to, message: Box::new(message) });
+        }
+    }
+
+    /// Forge a conflicting version of a message signed by the same validator
+    fn conflicting(&self, from: usize, message: &PocMessage) -> PocMessage {
+        let signer = self.nodes[from].signer.as_ref();
+        let forged_value = format!("forged-{}", from).into_bytes();
+        match message {
+            PocMessage::Proposal(p) => PocMessage::Proposal(
+                Proposal::sign(&p.round_id, p.round, forged_value, p.metadata.clone(), p.valid_round, signer).unwrap(),
+            ),
+            PocMessage::Vote(v) => PocMessage::Vote(
+                Vote::sign(&v.round_id, v.round, v.phase, Some(value_hash(&forged_value)), signer).unwrap(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+#[test]
+fn test_honest_committee_decides_in_first_round() {
+    for seed in 0..5 {
+        let result = Simulation::run(SimConfig::new(4, seed));
+        result.assert_all_decided();
+        result.assert_agreement();
+
+        for (_, certificate) in result.decisions.iter().flatten() {
+            assert_eq!(certificate.round, 0);
+        }
+    }
+}
+
+#[test]
+fn test_silent_proposer_triggers_round_change() {
+    for seed in 0..5 {
+        let mut config = SimConfig::new(4, seed);
+        config.silent = 1;
+        let result = Simulation::run(config);
+        result.assert_all_decided();
+        result.assert_agreement();
+
+        for (_, certificate) in result.decisions.iter().flatten() {
+            assert!(certificate.round >= 1);
+        }
+    }
+}
+
+#[test]
+fn test_equivocating_validators_cannot_break_agreement() {
+    for seed in 0..10 {
+        let mut config = SimConfig::new(7, seed);
+        config.equivocating = 2;
+        let result = Simulation::run(config);
+        result.assert_all_decided();
+        result.assert_agreement();
+    }
+
+    for seed in 0..10 {
+        let mut config = SimConfig::new(7, seed);
+        config.silent = 1;
+        config.equivocating = 1;
+        let result = Simulation::run(config);
+        result.assert_all_decided();
+        result.assert_agreement();
+    }
+}
+
+#[test]
+fn test_too_many_faulty_validators_stall_without_deciding() {
+    let mut config = SimConfig::new(4, 1);
+    config.silent = 2;
+    config.time_limit_ms = 10_000;
+    let result = Simulation::run(config);
+
+    assert!(result.honest_decisions().iter().all(|d| d.is_none()));
+}
+
+#[test]
+fn test_certificates_are_independently_verifiable() {
+    let result = Simulation::run(SimConfig::new(4, 7));
+    let (value, certificate) = result.decisions[0].clone().unwrap();
+
+    // A node that saw none of the round adopts the decision from the certificate
+    let mut observer = BftEngine::new(ROUND_ID, result.committee.clone(), THRESHOLD, None, Duration::from_secs(1));
+    observer.handle_decision(value.clone(), certificate.clone()).unwrap();
+    assert_eq!(observer.decision().map(|(v, _)| v), Some(&value));
+
+    // Dropping precommits below the quorum invalidates the certificate
+    let mut short = certificate.clone();
+    short.precommits.truncate(observer.quorum() - 1);
+    assert!(short.verify(&result.committee, THRESHOLD).is_err());
+
+    // A certificate can't be reused for another value
+    let mut other = BftEngine::new(ROUND_ID, result.committee.clone(), THRESHOLD, None, Duration::from_secs(1));
+    assert!(other.handle_decision(b"other".to_vec(), certificate.clone()).is_err());
+
+    // Precommits must come from the committee
+    let outsider = IdentitySigner::new("did:icn:outsider", IdentityKeyPair::generate().unwrap());
+    let mut forged = certificate;
+    forged.precommits[0] = Vote::sign(
+        ROUND_ID,
+        forged.round,
+        super::bft::VotePhase::Precommit,
+        Some(forged.value_hash.clone()),
+        &outsider,
+    ).unwrap();
+    assert!(forged.verify(&result.committee, THRESHOLD).is_err());
+}
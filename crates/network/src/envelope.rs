@@ -0,0 +1,620 @@
+//! Signed message envelopes for ICN
+//!
+//! Every `NetworkMessage` gossiped between nodes is wrapped in a
+//! `SignedEnvelope` carrying the sender's DID, a reference to the signing
+//! key and an Ed25519 signature over a canonical encoding of the envelope.
+//! Receivers verify the signature, check that the signing key is bound to
+//! the sender DID and that the sender is allowed to author the message
+//! (e.g. a vote can only be announced by its voter) before any handler runs.
+//!
+//! A DID is bound to a key only by the local identity system
+//! (`register_key`), by an identity announcement carrying a key attestation
+//! from a trusted issuer, or, for node DIDs, by the libp2p peer ID the DID
+//! is derived from. Envelopes signed outside a time window around the local
+//! clock are rejected, and each `(sender, nonce)` is only accepted once.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use libp2p::identity::PublicKey;
+use libp2p::PeerId;
+use serde::{Serialize, Deserialize};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+use icn_core::crypto::{verify_signature, IdentityKeyPair, Signature};
+
+use crate::{NetworkError, NetworkMessage, NetworkResult};
+
+/// Domain separation tag included in every signed payload
+const SIGNING_DOMAIN: &str = "icn-envelope-v2";
+
+/// Domain separation tag for key attestations
+const ATTESTATION_DOMAIN: &str = "icn-key-attestation-v1";
+
+/// Prefix of the DIDs nodes sign with, followed by their peer ID
+pub const NODE_DID_PREFIX: &str = "did:icn:node:";
+
+/// Identity announcement metadata naming the issuer of a key attestation
+pub const ATTESTATION_ISSUER: &str = "key_attestation_issuer";
+
+/// Identity announcement metadata holding a key attestation signature (hex)
+pub const ATTESTATION_SIGNATURE: &str = "key_attestation_signature";
+
+/// Default age after which envelopes are rejected
+pub const DEFAULT_MAX_ENVELOPE_AGE: Duration = Duration::from_secs(300);
+
+/// How far ahead of the local clock an envelope may be timestamped
+const MAX_CLOCK_SKEW: u64 = 60;
+
+/// Get the current time in seconds since the epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Get the DID a node signs with
+pub fn node_did(peer_id: &PeerId) -> String {
+    format!("{}{}", NODE_DID_PREFIX, peer_id)
+}
+
+/// Get the peer ID a node DID is derived from
+pub fn node_peer_id(did: &str) -> Option<PeerId> {
+    PeerId::from_str(did.strip_prefix(NODE_DID_PREFIX)?).ok()
+}
+
+/// Get the Ed25519 key of a node DID from the peer ID it is derived from
+///
+/// Peer IDs of Ed25519 keys embed the key itself, so no binding is needed.
+fn node_key(did: &str) -> Option<Vec<u8>> {
+    let bytes = node_peer_id(did)?.to_bytes();
+    // Identity multihashes (code 0) hold the encoded public key after its length
+    let (&0, rest) = bytes.split_first()? else {
+        return None;
+    };
+    let (&length, digest) = rest.split_first()?;
+    if usize::from(length) != digest.len() {
+        return None;
+    }
+    let key = PublicKey::try_decode_protobuf(digest).ok()?;
+    Some(key.try_into_ed25519().ok()?.to_bytes().to_vec())
+}
+
+/// Encode a key attestation's contents canonically for signing
+fn attestation_bytes(did: &str, public_key: &[u8]) -> Vec<u8> {
+    format!("{}:{}:{}", ATTESTATION_DOMAIN, did, hex::encode(public_key)).into_bytes()
+}
+
+/// Attest that a key belongs to a DID
+///
+/// Returns the identity announcement metadata carrying the attestation,
+/// which binds the key at verifiers trusting the issuer.
+pub fn attest_key(issuer: &dyn EnvelopeSigner, did: &str, public_key: &[u8]) -> NetworkResult<HashMap<String, String>> {
+    let signature = issuer.sign(&attestation_bytes(did, public_key))?;
+    Ok(HashMap::from([
+        (ATTESTATION_ISSUER.to_string(), issuer.sender().to_string()),
+        (ATTESTATION_SIGNATURE.to_string(), hex::encode(signature)),
+    ]))
+}
+
+/// A network message signed by its sender
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    /// The wrapped message
+    pub message: NetworkMessage,
+    /// DID of the sender
+    pub sender: String,
+    /// Reference to the key used for signing (e.g. `did:icn:alice#key-1`)
+    pub key_id: String,
+    /// Ed25519 public key of the signer
+    pub public_key: Vec<u8>,
+    /// Time the envelope was signed (seconds since the epoch)
+    pub timestamp: u64,
+    /// Random value making the envelope unique for its sender
+    pub nonce: u64,
+    /// Ed25519 signature over the canonical encoding
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by an envelope signature
+#[derive(Serialize)]
+struct SigningPayload<'a> {
+    domain: &'a str,
+    sender: &'a str,
+    key_id: &'a str,
+    public_key: String,
+    timestamp: u64,
+    nonce: u64,
+    message: serde_json::Value,
+}
+
+/// Encode an envelope's contents canonically for signing
+///
+/// The message is converted to a `serde_json::Value` first, whose maps are
+/// ordered by key, so the encoding does not depend on `HashMap` iteration
+/// order.
+fn signing_bytes(
+    message: &NetworkMessage,
+    sender: &str,
+    key_id: &str,
+    public_key: &[u8],
+    timestamp: u64,
+    nonce: u64,
+) -> NetworkResult<Vec<u8>> {
+    let payload = SigningPayload {
+        domain: SIGNING_DOMAIN,
+        sender,
+        key_id,
+        public_key: hex::encode(public_key),
+        timestamp,
+        nonce,
+        message: serde_json::to_value(message)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to encode message: {}", e)))?,
+    };
+
+    serde_json::to_vec(&payload)
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode envelope: {}", e)))
+}
+
+impl SignedEnvelope {
+    /// Sign a message
+    pub fn sign(message: NetworkMessage, signer: &dyn EnvelopeSigner) -> NetworkResult<Self> {
+        Self::sign_at(message, signer, unix_now())
+    }
+
+    /// Sign a message with a given timestamp
+    fn sign_at(message: NetworkMessage, signer: &dyn EnvelopeSigner, timestamp: u64) -> NetworkResult<Self> {
+        let public_key = signer.public_key();
+        let nonce = rand::random();
+
+        let bytes = signing_bytes(&message, signer.sender(), signer.key_id(), &public_key, timestamp, nonce)?;
+        let signature = signer.sign(&bytes)?;
+
+        Ok(Self {
+            message,
+            sender: signer.sender().to_string(),
+            key_id: signer.key_id().to_string(),
+            public_key,
+            timestamp,
+            nonce,
+            signature,
+        })
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> NetworkResult<Vec<u8>> {
+        signing_bytes(&self.message, &self.sender, &self.key_id, &self.public_key, self.timestamp, self.nonce)
+    }
+
+    /// Verify the signature against the envelope's public key
+    ///
+    /// This only proves the envelope was signed by the holder of
+    /// `public_key`; use `EnvelopeVerifier` to also check that the key
+    /// belongs to the sender.
+    pub fn verify_signature(&self) -> NetworkResult<()> {
+        let bytes = self.signing_bytes()?;
+        verify_signature(&self.public_key, &bytes, &Signature::new(self.signature.clone()))
+            .map_err(|e| NetworkError::InvalidSignature(format!("{} ({})", self.sender, e)))
+    }
+}
+
+/// Trait for keys able to sign envelopes
+pub trait EnvelopeSigner: Send + Sync {
+    /// DID of the signer
+    fn sender(&self) -> &str;
+
+    /// Reference to the signing key
+    fn key_id(&self) -> &str;
+
+    /// Ed25519 public key bytes
+    fn public_key(&self) -> Vec<u8>;
+
+    /// Sign data
+    fn sign(&self, data: &[u8]) -> NetworkResult<Vec<u8>>;
+}
+
+/// Envelope signer backed by an identity key pair
+pub struct IdentitySigner {
+    /// DID of the identity
+    sender: String,
+    /// Reference to the key
+    key_id: String,
+    /// The identity's key pair
+    key_pair: IdentityKeyPair,
+}
+
+impl IdentitySigner {
+    /// Create a signer for a DID using its first key
+    pub fn new(did: impl Into<String>, key_pair: IdentityKeyPair) -> Self {
+        let sender = did.into();
+        let key_id = format!("{}#key-1", sender);
+        Self::with_key_id(sender, key_id, key_pair)
+    }
+
+    /// Create a signer with an explicit key reference
+    pub fn with_key_id(did: impl Into<String>, key_id: impl Into<String>, key_pair: IdentityKeyPair) -> Self {
+        Self {
+            sender: did.into(),
+            key_id: key_id.into(),
+            key_pair,
+        }
+    }
+}
+
+impl EnvelopeSigner for IdentitySigner {
+    fn sender(&self) -> &str {
+        &self.sender
+    }
+
+    fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    fn public_key(&self) -> Vec<u8> {
+        self.key_pair.public_key_bytes().to_vec()
+    }
+
+    fn sign(&self, data: &[u8]) -> NetworkResult<Vec<u8>> {
+        Ok(self.key_pair.sign(data).as_bytes().to_vec())
+    }
+}
+
+/// Verifies envelopes against the keys bound to each DID
+///
+/// Keys are bound explicitly through `register_key`, by identity
+/// announcements attested by a trusted issuer, or derived from the peer ID
+/// of node DIDs. The first validly signed envelope is never trusted to bind
+/// its own key.
+#[derive(Clone)]
+pub struct EnvelopeVerifier {
+    /// Known public keys by DID
+    keys: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+    /// DIDs whose key attestations are trusted
+    trusted_issuers: Arc<RwLock<HashSet<String>>>,
+    /// Timestamps of the accepted envelopes, by sender and nonce
+    seen: Arc<RwLock<HashMap<(String, u64), u64>>>,
+    /// Age after which envelopes are rejected
+    max_age: Duration,
+}
+
+impl Default for EnvelopeVerifier {
+    fn default() -> Self {
+        Self::with_max_age(DEFAULT_MAX_ENVELOPE_AGE)
+    }
+}
+
+impl EnvelopeVerifier {
+    /// Create a new verifier
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a verifier rejecting envelopes older than `max_age`
+    pub fn with_max_age(max_age: Duration) -> Self {
+        Self {
+            keys: Arc::new(RwLock::new(HashMap::new())),
+            trusted_issuers: Arc::new(RwLock::new(HashSet::new())),
+            seen: Arc::new(RwLock::new(HashMap::new())),
+            max_age,
+        }
+    }
+
+    /// Bind a public key to a DID
+    ///
+    /// Only call this with keys verified through the identity system.
+    pub async fn register_key(&self, did: impl Into<String>, public_key: Vec<u8>) {
+        self.keys.write().await.insert(did.into(), public_key);
+    }
+
+    /// Trust the key attestations of an issuer whose key is known
+    pub async fn trust_issuer(&self, did: impl Into<String>) {
+        self.trusted_issuers.write().await.insert(did.into());
+    }
+
+    /// Get the public key bound to a DID
+    pub async fn key_for(&self, did: &str) -> Option<Vec<u8>> {
+        if let Some(key) = node_key(did) {
+            return Some(key);
+        }
+        self.keys.read().await.get(did).cloned()
+    }
+
+    /// Verify an envelope
+    pub async fn verify(&self, envelope: &SignedEnvelope) -> NetworkResult<()> {
+        self.verify_from(envelope, None).await
+    }
+
+    /// Verify an envelope received from a peer
+    ///
+    /// Envelopes from node DIDs must have been authored by the node's own
+    /// peer, so `origin` must be the peer the DID is derived from.
+    pub async fn verify_from(&self, envelope: &SignedEnvelope, origin: Option<&PeerId>) -> NetworkResult<()> {
+        envelope.verify_signature()?;
+        self.check_window(envelope)?;
+
+        if let Some(author) = envelope.message.claimed_author() {
+            if author != envelope.sender {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "{} cannot send {} on behalf of {}",
+                    envelope.sender, envelope.message.message_type(), author
+                )));
+            }
+        }
+
+        if let Some(peer_id) = node_peer_id(&envelope.sender) {
+            if origin.is_some_and(|origin| *origin != peer_id) {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "{} was not sent by peer {}", envelope.sender, peer_id
+                )));
+            }
+        }
+
+        match self.key_for(&envelope.sender).await {
+            Some(known_key) if known_key != envelope.public_key => {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "Key {} is not bound to {}", envelope.key_id, envelope.sender
+                )));
+            }
+            Some(_) => {}
+            None => self.bind_attested_key(envelope).await?,
+        }
+
+        self.record_nonce(envelope).await
+    }
+
+    /// Check an envelope was signed within the accepted time window
+    fn check_window(&self, envelope: &SignedEnvelope) -> NetworkResult<()> {
+        let now = unix_now();
+        if envelope.timestamp > now + MAX_CLOCK_SKEW
+            || envelope.timestamp + self.max_age.as_secs() < now {
+            return Err(NetworkError::ExpiredMessage(format!(
+                "{} signed at {}, now {}", envelope.sender, envelope.timestamp, now
+            )));
+        }
+        Ok(())
+    }
+
+    /// Bind the key of an identity announcement attested by a trusted issuer
+    async fn bind_attested_key(&self, envelope: &SignedEnvelope) -> NetworkResult<()> {
+        let unknown = || NetworkError::UnauthorizedSender(format!("No key known for {}", envelope.sender));
+        let NetworkMessage::IdentityAnnouncement(announcement) = &envelope.message else {
+            return Err(unknown());
+        };
+        if announcement.public_key != envelope.public_key {
+            return Err(unknown());
+        }
+
+        let issuer = announcement.metadata.get(ATTESTATION_ISSUER).ok_or_else(unknown)?;
+        if !self.trusted_issuers.read().await.contains(issuer) {
+            return Err(NetworkError::UnauthorizedSender(format!(
+                "{} is attested by untrusted issuer {}", envelope.sender, issuer
+            )));
+        }
+        let issuer_key = self.key_for(issuer).await.ok_or_else(|| NetworkError::UnauthorizedSender(format!(
+            "No key known for issuer {}", issuer
+        )))?;
+        let signature = announcement.metadata.get(ATTESTATION_SIGNATURE)
+            .and_then(|signature| hex::decode(signature).ok())
+            .ok_or_else(unknown)?;
+        verify_signature(
+            &issuer_key,
+            &attestation_bytes(&envelope.sender, &envelope.public_key),
+            &Signature::new(signature),
+        ).map_err(|e| NetworkError::InvalidSignature(format!("Key attestation for {} ({})", envelope.sender, e)))?;
+
+        debug!("Binding {} to key {} attested by {}", envelope.sender, envelope.key_id, issuer);
+        self.register_key(envelope.sender.clone(), envelope.public_key.clone()).await;
+        Ok(())
+    }
+
+    /// Accept each sender's nonce only once within the time window
+    async fn record_nonce(&self, envelope: &SignedEnvelope) -> NetworkResult<()> {
+        let mut seen = self.seen.write().await;
+        let oldest = unix_now().saturating_sub(self.max_age.as_secs());
+        seen.retain(|_, timestamp| *timestamp >= oldest);
+
+        if seen.insert((envelope.sender.clone(), envelope.nonce), envelope.timestamp).is_some() {
+            return Err(NetworkError::ReplayedMessage(format!(
+                "{} already sent nonce {}", envelope.sender, envelope.nonce
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+    use crate::{IdentityAnnouncement, VoteAnnouncement};
+
+    fn signer(did: &str) -> IdentitySigner {
+        IdentitySigner::new(did, IdentityKeyPair::generate().unwrap())
+    }
+
+    /// Signs as a node with its libp2p key
+    struct TestNodeSigner {
+        sender: String,
+        key_pair: Keypair,
+    }
+
+    impl TestNodeSigner {
+        fn new() -> Self {
+            let key_pair = Keypair::generate_ed25519();
+            Self { sender: node_did(&key_pair.public().to_peer_id()), key_pair }
+        }
+
+        fn peer_id(&self) -> PeerId {
+            self.key_pair.public().to_peer_id()
+        }
+    }
+
+    impl EnvelopeSigner for TestNodeSigner {
+        fn sender(&self) -> &str {
+            &self.sender
+        }
+
+        fn key_id(&self) -> &str {
+            "libp2p"
+        }
+
+        fn public_key(&self) -> Vec<u8> {
+            self.key_pair.public().try_into_ed25519().unwrap().to_bytes().to_vec()
+        }
+
+        fn sign(&self, data: &[u8]) -> NetworkResult<Vec<u8>> {
+            Ok(self.key_pair.sign(data).unwrap())
+        }
+    }
+
+    fn vote(voter_id: &str) -> NetworkMessage {
+        NetworkMessage::VoteAnnouncement(VoteAnnouncement {
+            proposal_id: "prop1".to_string(),
+            voter_id: voter_id.to_string(),
+            decision: "yes".to_string(),
+            timestamp: 1,
+            data_hash: "hash".to_string(),
+        })
+    }
+
+    fn identity(did: &str, public_key: Vec<u8>) -> NetworkMessage {
+        attested_identity(did, public_key, HashMap::new())
+    }
+
+    fn attested_identity(did: &str, public_key: Vec<u8>, mut metadata: HashMap<String, String>) -> NetworkMessage {
+        metadata.insert("name".to_string(), "Alice".to_string());
+        metadata.insert("org".to_string(), "Coop".to_string());
+
+        NetworkMessage::IdentityAnnouncement(IdentityAnnouncement {
+            identity_id: did.to_string(),
+            public_key,
+            metadata,
+            timestamp: 1,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_attested_identity_binds_key_and_authorizes_votes() {
+        let alice = signer("did:icn:alice");
+        let registrar = signer("did:icn:registrar");
+        let verifier = EnvelopeVerifier::new();
+        verifier.register_key("did:icn:registrar", registrar.public_key()).await;
+        verifier.trust_issuer("did:icn:registrar").await;
+
+        // Votes from an unknown DID are rejected
+        let envelope = SignedEnvelope::sign(vote("did:icn:alice"), &alice).unwrap();
+        assert!(matches!(verifier.verify(&envelope).await, Err(NetworkError::UnauthorizedSender(_))));
+
+        // Announcing an unattested identity doesn't bind the key
+        let announcement = SignedEnvelope::sign(identity("did:icn:alice", alice.public_key()), &alice).unwrap();
+        assert!(matches!(verifier.verify(&announcement).await, Err(NetworkError::UnauthorizedSender(_))));
+        assert_eq!(verifier.key_for("did:icn:alice").await, None);
+
+        // Nor does one attested by an untrusted issuer
+        let mallory = signer("did:icn:mallory");
+        let metadata = attest_key(&mallory, "did:icn:alice", &alice.public_key()).unwrap();
+        let announcement = SignedEnvelope::sign(attested_identity("did:icn:alice", alice.public_key(), metadata), &alice).unwrap();
+        assert!(matches!(verifier.verify(&announcement).await, Err(NetworkError::UnauthorizedSender(_))));
+
+        // An attestation for another key can't be reused
+        let squatter = signer("did:icn:alice");
+        let metadata = attest_key(&registrar, "did:icn:alice", &alice.public_key()).unwrap();
+        let announcement = SignedEnvelope::sign(attested_identity("did:icn:alice", squatter.public_key(), metadata.clone()), &squatter).unwrap();
+        assert!(verifier.verify(&announcement).await.is_err());
+
+        // Announcing the identity attested by a trusted issuer binds the key
+        let announcement = SignedEnvelope::sign(attested_identity("did:icn:alice", alice.public_key(), metadata), &alice).unwrap();
+        verifier.verify(&announcement).await.unwrap();
+        assert_eq!(verifier.key_for("did:icn:alice").await, Some(alice.public_key()));
+
+        let envelope = SignedEnvelope::sign(vote("did:icn:alice"), &alice).unwrap();
+        verifier.verify(&envelope).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_node_keys_are_derived_from_peer_ids() {
+        let node = TestNodeSigner::new();
+        let other = TestNodeSigner::new();
+        let verifier = EnvelopeVerifier::new();
+
+        assert_eq!(verifier.key_for(node.sender()).await, Some(node.public_key()));
+
+        // Node envelopes verify without any registration
+        let envelope = SignedEnvelope::sign(vote(node.sender()), &node).unwrap();
+        verifier.verify_from(&envelope, Some(&node.peer_id())).await.unwrap();
+
+        // But only when authored by the node's own peer
+        let envelope = SignedEnvelope::sign(vote(node.sender()), &node).unwrap();
+        assert!(matches!(
+            verifier.verify_from(&envelope, Some(&other.peer_id())).await,
+            Err(NetworkError::UnauthorizedSender(_))
+        ));
+
+        // Another key can't sign as the node
+        let impostor = IdentitySigner::new(node.sender(), IdentityKeyPair::generate().unwrap());
+        let envelope = SignedEnvelope::sign(vote(node.sender()), &impostor).unwrap();
+        assert!(matches!(verifier.verify(&envelope).await, Err(NetworkError::UnauthorizedSender(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_expired_and_replayed_envelopes() {
+        let alice = signer("did:icn:alice");
+        let verifier = EnvelopeVerifier::with_max_age(Duration::from_secs(60));
+        verifier.register_key("did:icn:alice", alice.public_key()).await;
+
+        let stale = SignedEnvelope::sign_at(vote("did:icn:alice"), &alice, unix_now() - 120).unwrap();
+        assert!(matches!(verifier.verify(&stale).await, Err(NetworkError::ExpiredMessage(_))));
+
+        let future = SignedEnvelope::sign_at(vote("did:icn:alice"), &alice, unix_now() + 3600).unwrap();
+        assert!(matches!(verifier.verify(&future).await, Err(NetworkError::ExpiredMessage(_))));
+
+        let envelope = SignedEnvelope::sign(vote("did:icn:alice"), &alice).unwrap();
+        verifier.verify(&envelope).await.unwrap();
+        assert!(matches!(verifier.verify(&envelope).await, Err(NetworkError::ReplayedMessage(_))));
+
+        // The same message with a fresh nonce is accepted
+        let envelope = SignedEnvelope::sign(vote("did:icn:alice"), &alice).unwrap();
+        verifier.verify(&envelope).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_forged_votes() {
+        let alice = signer("did:icn:alice");
+        let mallory = signer("did:icn:mallory");
+        let verifier = EnvelopeVerifier::new();
+        verifier.register_key("did:icn:alice", alice.public_key()).await;
+        verifier.register_key("did:icn:mallory", mallory.public_key()).await;
+
+        // Mallory signing a vote for Alice under her own DID
+        let envelope = SignedEnvelope::sign(vote("did:icn:alice"), &mallory).unwrap();
+        assert!(matches!(verifier.verify(&envelope).await, Err(NetworkError::UnauthorizedSender(_))));
+
+        // Mallory claiming to be Alice with her own key
+        let impostor = IdentitySigner::new("did:icn:alice", IdentityKeyPair::generate().unwrap());
+        let envelope = SignedEnvelope::sign(vote("did:icn:alice"), &impostor).unwrap();
+        assert!(matches!(verifier.verify(&envelope).await, Err(NetworkError::UnauthorizedSender(_))));
+
+        // Tampering with a signed vote
+        let mut envelope = SignedEnvelope::sign(vote("did:icn:alice"), &alice).unwrap();
+        if let NetworkMessage::VoteAnnouncement(v) = &mut envelope.message {
+            v.decision = "no".to_string();
+        }
+        assert!(matches!(verifier.verify(&envelope).await, Err(NetworkError::InvalidSignature(_))));
+    }
+
+    #[test]
+    fn test_signing_bytes_are_canonical() {
+        let alice = signer("did:icn:alice");
+        let envelope = SignedEnvelope::sign(identity("did:icn:alice", alice.public_key()), &alice).unwrap();
+
+        // A round trip through JSON may reorder the metadata map
+        let encoded = serde_json::to_vec(&envelope).unwrap();
+        let decoded: SignedEnvelope = serde_json::from_slice(&encoded).unwrap();
+        assert_eq!(decoded.signing_bytes().unwrap(), envelope.signing_bytes().unwrap());
+        decoded.verify_signature().unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+//! Full-object fetch protocol for ICN
+//!
+//! Announcements gossiped between nodes only carry identifiers and a hash of
+//! the announced data. This module defines the request/response messages used
+//! to pull the full object from the announcing peer, together with helpers to
+//! serve requests from local storage and to verify and persist fetched data.
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use tracing::debug;
+
+use icn_core::crypto::sha256;
+use icn_core::storage::Storage;
+
+use crate::{NetworkError, NetworkResult};
+
+/// Protocol name used for full-object fetches
+pub const FETCH_PROTOCOL: &str = "/icn/fetch/1.0.0";
+
+/// Kinds of objects that can be fetched from a peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ObjectKind {
+    /// Ledger transaction
+    Transaction,
+    /// Identity record
+    Identity,
+    /// Governance proposal
+    Proposal,
+    /// Governance vote
+    Vote,
+}
+
+impl ObjectKind {
+    /// Get the storage prefix under which objects of this kind are persisted
+    pub fn storage_prefix(&self) -> &'static str {
+        match self {
+            ObjectKind::Transaction => "sync/transactions",
+            ObjectKind::Identity => "sync/identities",
+            ObjectKind::Proposal => "sync/proposals",
+            ObjectKind::Vote => "sync/votes",
+        }
+    }
+
+    /// Get the storage key for an object of this kind
+    pub fn storage_key(&self, id: &str) -> String {
+        format!("{}/{}", self.storage_prefix(), id)
+    }
+
+    /// Get the storage key under which the originating node stores an object
+    ///
+    /// Governance stores the votes on a proposal together as one list, so for
+    /// votes this is the key of the proposal's votes.
+    pub fn origin_key(&self, id: &str) -> String {
+        match self {
+            ObjectKind::Transaction => format!("ledger/transactions/{}", id),
+            ObjectKind::Identity => format!("identity:{}", id),
+            ObjectKind::Proposal => format!("governance/proposals/{}", id),
+            ObjectKind::Vote => {
+                let proposal_id = id.split_once('/').map_or(id, |(proposal_id, _)| proposal_id);
+                format!("governance/votes/{}", proposal_id)
+            }
+        }
+    }
+}
+
+/// Request for the full data of an announced object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchRequest {
+    /// Kind of object being requested
+    pub kind: ObjectKind,
+    /// ID of the object being requested
+    pub id: String,
+}
+
+impl FetchRequest {
+    /// Create a new fetch request
+    pub fn new(kind: ObjectKind, id: impl Into<String>) -> Self {
+        Self {
+            kind,
+            id: id.into(),
+        }
+    }
+
+    /// Create a fetch request for a vote, identified by proposal and voter
+    pub fn vote(proposal_id: &str, voter_id: &str) -> Self {
+        Self::new(ObjectKind::Vote, format!("{}/{}", proposal_id, voter_id))
+    }
+
+    /// Get the storage key for the requested object
+    pub fn storage_key(&self) -> String {
+        self.kind.storage_key(&self.id)
+    }
+}
+
+/// Response to a fetch request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FetchResponse {
+    /// The object was found
+    Found {
+        /// The original request
+        request: FetchRequest,
+        /// Raw object data
+        data: Vec<u8>,
+    },
+    /// The peer does not have the object
+    NotFound(FetchRequest),
+}
+
+/// Trait for components able to fetch full objects from remote peers
+#[async_trait]
+pub trait ObjectFetcher: Send + Sync {
+    /// Fetch an object from the given peer
+    async fn fetch_object(&self, peer_id: &str, request: FetchRequest) -> NetworkResult<FetchResponse>;
+}
+
+/// Compute the data hash used in announcements for a piece of data
+pub fn compute_data_hash(data: &[u8]) -> String {
+    sha256(data).to_hex()
+}
+
+/// Verify that data matches the hash it was announced with
+pub fn verify_data_hash(data: &[u8], expected: &str) -> NetworkResult<()> {
+    let actual = compute_data_hash(data);
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(NetworkError::DataHashMismatch(expected.to_string(), actual))
+    }
+}
+
+/// Load the data of an object from local storage
+///
+/// Objects fetched from peers are read from their sync key, and objects this
+/// node originated from where the ledger, identity or governance system
+/// stored them. A vote is served as the JSON of the voter's entry in the
+/// proposal's votes. Announcements must carry the hash of this data.
+pub async fn load_object(storage: &dyn Storage, request: &FetchRequest) -> Option<Vec<u8>> {
+    if let Ok(data) = storage.get(&request.storage_key()).await {
+        return Some(data);
+    }
+
+    let data = storage.get(&request.kind.origin_key(&request.id)).await.ok()?;
+    if request.kind != ObjectKind::Vote {
+        return Some(data);
+    }
+
+    let (_, voter_id) = request.id.split_once('/')?;
+    let votes: Vec<serde_json::Value> = serde_json::from_slice(&data).ok()?;
+    let vote = votes.into_iter().find(|vote| vote["voter"].as_str() == Some(voter_id))?;
+    serde_json::to_vec(&vote).ok()
+}
+
+/// Answer a fetch request from local storage
+pub async fn serve_fetch_request(storage: &dyn Storage, request: FetchRequest) -> FetchResponse {
+    match load_object(storage, &request).await {
+        Some(data) => FetchResponse::Found { request, data },
+        None => FetchResponse::NotFound(request),
+    }
+}
+
+/// Fetch an object from a peer, verify it and persist it to storage
+///
+/// Returns `Ok(true)` if the object was fetched and stored, and `Ok(false)` if
+/// it was already present locally. When `expected_hash` is set, the fetched
+/// data must hash to it or the object is rejected.
+pub async fn fetch_and_store(
+    fetcher: &dyn ObjectFetcher,
+    storage: &dyn Storage,
+    peer_id: &str,
+    request: FetchRequest,
+    expected_hash: Option<&str>,
+) -> NetworkResult<bool> {
+    let key = request.storage_key();
+    if storage.exists(&key).await? {
+        debug!("Object {} already present, skipping fetch", key);
+        return Ok(false);
+    }
+
+    match fetcher.fetch_object(peer_id, request.clone()).await? {
+        FetchResponse::Found { request: answered, data } => {
+            if answered != request {
+                return Err(NetworkError::MessageError(format!(
+                    "Peer {} answered fetch for {} with {}",
+                    peer_id, key, answered.storage_key()
+                )));
+            }
+
+            if let Some(expected) = expected_hash {
+                verify_data_hash(&data, expected)?;
+            }
+
+            storage.put(&key, &data).await?;
+            Ok(true)
+        }
+        FetchResponse::NotFound(_) => Err(NetworkError::MessageError(format!(
+            "Peer {} does not have {}", peer_id, key
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::MockStorage;
+
+    /// Fetcher that answers every request from a fixed storage
+    struct StorageFetcher {
+        remote: MockStorage,
+    }
+
+    #[async_trait]
+    impl ObjectFetcher for StorageFetcher {
+        async fn fetch_object(&self, _peer_id: &str, request: FetchRequest) -> NetworkResult<FetchResponse> {
+            Ok(serve_fetch_request(&self.remote, request).await)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_store_verifies_hash() {
+        let remote = MockStorage::new();
+        let data = b"transaction body".to_vec();
+        let request = FetchRequest::new(ObjectKind::Transaction, "tx1");
+        remote.put(&request.storage_key(), &data).await.unwrap();
+
+        let fetcher = StorageFetcher { remote };
+        let local = MockStorage::new();
+        let hash = compute_data_hash(&data);
+
+        let stored = fetch_and_store(&fetcher, &local, "peer1", request.clone(), Some(&hash)).await.unwrap();
+        assert!(stored);
+        assert_eq!(local.get(&request.storage_key()).await.unwrap(), data);
+
+        // A second fetch is skipped because the object is already present
+        let stored = fetch_and_store(&fetcher, &local, "peer1", request, Some(&hash)).await.unwrap();
+        assert!(!stored);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_and_store_rejects_bad_hash() {
+        let remote = MockStorage::new();
+        let request = FetchRequest::new(ObjectKind::Proposal, "prop1");
+        remote.put(&request.storage_key(), b"tampered").await.unwrap();
+
+        let fetcher = StorageFetcher { remote };
+        let local = MockStorage::new();
+        let hash = compute_data_hash(b"original");
+
+        let result = fetch_and_store(&fetcher, &local, "peer1", request.clone(), Some(&hash)).await;
+        assert!(matches!(result, Err(NetworkError::DataHashMismatch(_, _))));
+        assert!(!local.exists(&request.storage_key()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_announced_objects_from_originator() {
+        // The originator stores its objects where the ledger and governance do
+        let remote = MockStorage::new();
+        remote.put("ledger/transactions/tx1", b"transaction body").await.unwrap();
+        let votes = serde_json::json!([
+            {"proposal_id": "prop1", "voter": "did:icn:alice", "approve": true},
+            {"proposal_id": "prop1", "voter": "did:icn:bob", "approve": false},
+        ]);
+        remote.put("governance/votes/prop1", &serde_json::to_vec(&votes).unwrap()).await.unwrap();
+
+        // It announces the hashes of the data it serves
+        let transaction = FetchRequest::new(ObjectKind::Transaction, "tx1");
+        let vote = FetchRequest::vote("prop1", "did:icn:bob");
+        let transaction_hash = compute_data_hash(&load_object(&remote, &transaction).await.unwrap());
+        let vote_hash = compute_data_hash(&load_object(&remote, &vote).await.unwrap());
+
+        let fetcher = StorageFetcher { remote };
+        let local = MockStorage::new();
+        assert!(fetch_and_store(&fetcher, &local, "peer1", transaction.clone(), Some(&transaction_hash)).await.unwrap());
+        assert!(fetch_and_store(&fetcher, &local, "peer1", vote.clone(), Some(&vote_hash)).await.unwrap());
+        assert_eq!(local.get(&transaction.storage_key()).await.unwrap(), b"transaction body");
+        let fetched: serde_json::Value = serde_json::from_slice(&local.get(&vote.storage_key()).await.unwrap()).unwrap();
+        assert_eq!(fetched, votes[1]);
+
+        // Votes the originator doesn't have aren't found
+        let missing = FetchRequest::vote("prop1", "did:icn:carol");
+        assert!(fetch_and_store(&fetcher, &local, "peer1", missing, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_missing_object() {
+        let fetcher = StorageFetcher { remote: MockStorage::new() };
+        let local = MockStorage::new();
+        let request = FetchRequest::vote("prop1", "did:icn:alice");
+
+        let result = fetch_and_store(&fetcher, &local, "peer1", request, None).await;
+        assert!(result.is_err());
+    }
+}
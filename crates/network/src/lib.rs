@@ -113,6 +113,10 @@ pub enum NetworkError {
     #[error("Queue is full")]
     QueueFull,
     
+    /// Traffic exceeds a configured rate limit
+    #[error("Rate limit exceeded: {0}")]
+    RateLimitExceeded(String),
+    
     /// Invalid priority
     #[error("Invalid message priority")]
     InvalidPriority,
@@ -132,6 +136,50 @@ pub enum NetworkError {
     /// Configuration error
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    
+    /// Fetched data does not match its announced hash
+    #[error("Data hash mismatch: expected {0}, got {1}")]
+    DataHashMismatch(String, String),
+    
+    /// Peer used a wire protocol version this node does not support
+    #[error("Unsupported protocol version: {0}")]
+    UnsupportedVersion(u16),
+    
+    /// Message signature is missing or invalid
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+    
+    /// Sender is not allowed to send a message
+    #[error("Unauthorized sender: {0}")]
+    UnauthorizedSender(String),
+    
+    /// Message was signed outside the accepted time window
+    #[error("Expired message: {0}")]
+    ExpiredMessage(String),
+    
+    /// Message was already received
+    #[error("Replayed message: {0}")]
+    ReplayedMessage(String),
+    
+    /// Request did not complete before its deadline
+    #[error("Request timed out: {0}")]
+    RequestTimeout(String),
+    
+    /// Request was cancelled by the caller
+    #[error("Request cancelled: {0}")]
+    RequestCancelled(u64),
+    
+    /// Remote peer failed to handle an RPC request
+    #[error("RPC error: {0}")]
+    RpcError(String),
+    
+    /// Invalid gossipsub topic
+    #[error("Invalid topic: {0}")]
+    InvalidTopic(String),
+    
+    /// Consensus protocol violation
+    #[error("Consensus error: {0}")]
+    ConsensusError(String),
 }
 
 /// Result type for network operations
@@ -163,6 +211,10 @@ pub enum NetworkMessage {
     /// Custom message type
     #[serde(rename = "custom")]
     Custom(CustomMessage),
+    
+    /// Signed reputation evidence
+    #[serde(rename = "reputation.evidence")]
+    ReputationEvidence(reputation::ReputationEvidence),
 }
 
 impl NetworkMessage {
@@ -175,6 +227,19 @@ impl NetworkMessage {
             Self::ProposalAnnouncement(_) => "governance.proposal".to_string(),
             Self::VoteAnnouncement(_) => "governance.vote".to_string(),
             Self::Custom(m) => m.message_type.clone(),
+            Self::ReputationEvidence(_) => reputation::EVIDENCE_MESSAGE_TYPE.to_string(),
+        }
+    }
+    
+    /// Get the DID the message claims to be authored by, if any
+    pub fn claimed_author(&self) -> Option<&str> {
+        match self {
+            Self::TransactionAnnouncement(m) => Some(&m.sender),
+            Self::IdentityAnnouncement(m) => Some(&m.identity_id),
+            Self::ProposalAnnouncement(m) => Some(&m.author),
+            Self::VoteAnnouncement(m) => Some(&m.voter_id),
+            Self::ReputationEvidence(m) => Some(&m.submitter),
+            Self::LedgerStateUpdate(_) | Self::Custom(_) => None,
         }
     }
 }
@@ -260,9 +325,44 @@ pub struct CustomMessage {
     /// Message type
     pub message_type: String,
     /// Data for the message as JSON value
+    #[serde(with = "json_map_serde")]
     pub data: serde_json::Map<String, serde_json::Value>,
 }
 
+/// Serialization helpers for JSON maps in binary formats
+///
+/// Binary formats such as bincode cannot represent arbitrary JSON values,
+/// so the map is carried as a JSON string there. Human-readable formats
+/// keep the map as is.
+mod json_map_serde {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::{Map, Value};
+
+    pub fn serialize<S>(map: &Map<String, Value>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            map.serialize(serializer)
+        } else {
+            let s = serde_json::to_string(map).map_err(serde::ser::Error::custom)?;
+            s.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Map<String, Value>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            Map::deserialize(deserializer)
+        } else {
+            let s = String::deserialize(deserializer)?;
+            serde_json::from_str(&s).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
 /// Information about a peer
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PeerInfo {
@@ -284,6 +384,9 @@ pub struct PeerInfo {
     pub last_seen: Option<u64>,
     /// Reputation score
     pub reputation: Option<i32>,
+    /// DID that signed the message being handled, if it was signed
+    #[serde(default)]
+    pub sender: Option<String>,
 }
 
 impl fmt::Display for PeerInfo {
@@ -348,9 +451,20 @@ pub mod p2p;
 pub mod discovery;
 pub mod messaging;
 pub mod sync;
+pub mod fetch;
+pub mod reconcile;
+pub mod checkpoint;
+pub mod envelope;
+pub mod codec;
+pub mod rpc;
+pub mod rate_limit;
+pub mod topics;
 pub mod metrics;
 pub mod reputation;
 pub mod circuit_relay;
+pub mod connection_manager;
+pub mod consensus;
+pub mod sharding;
 pub mod adapter;
 
 /// Private modules
@@ -366,6 +480,15 @@ pub use crate::discovery::DiscoveryConfig;
 pub use crate::messaging::{MessageProcessor, PriorityConfig};
 pub use crate::reputation::{ReputationConfig, ReputationManager, ReputationChange};
 pub use crate::circuit_relay::{CircuitRelayConfig, CircuitRelayManager};
+pub use crate::connection_manager::{ConnectionManager, ConnectionManagerConfig, ProtectionReason};
+pub use crate::fetch::{FetchRequest, FetchResponse, ObjectFetcher, ObjectKind};
+pub use crate::reconcile::{ReconcileClient, ReconcileRequest, ReconcileResponse, TimeRange};
+pub use crate::checkpoint::{CheckpointStore, PeerLag, SyncDomain, SyncProgress};
+pub use crate::envelope::{EnvelopeSigner, EnvelopeVerifier, IdentitySigner, SignedEnvelope};
+pub use crate::codec::{VersionRange, WireFormat};
+pub use crate::rate_limit::{Direction, PeerTraffic, RateLimit, RateLimitConfig, RateLimiter};
+pub use crate::topics::{TopicKind, TopicScope};
+pub use crate::rpc::{RpcClient, RpcHandler, RpcRegistry, RpcRequest, RpcResponse, RpcServerConfig, RpcService, RpcStatus};
 
 /// Re-export the messaging types for convenience
 pub mod messages {
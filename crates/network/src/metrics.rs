@@ -3,7 +3,8 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use prometheus::{
-    Counter, Gauge, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    Counter, Gauge, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry,
 };
 use tokio::sync::RwLock;
 use tracing::{debug, error, info};
@@ -69,6 +70,20 @@ pub struct NetworkMetrics {
     relay_connection_attempts: IntCounter,
     relay_connection_successes: IntCounter,
     relay_connection_failures: IntCounter,
+    relay_hole_punches: IntCounterVec,
+    
+    // Bandwidth metrics
+    peer_bytes: IntCounterVec,
+    rate_limited: IntCounterVec,
+    
+    // Connection manager metrics
+    connection_decisions: IntCounterVec,
+    
+    // RPC metrics
+    rpc_requests: IntCounterVec,
+    rpc_requests_served: IntCounterVec,
+    rpc_in_flight: IntGauge,
+    rpc_latency: HistogramVec,
 }
 
 impl NetworkMetrics {
@@ -175,6 +190,45 @@ impl NetworkMetrics {
         let relay_connection_attempts = IntCounter::new("network_relay_connection_attempts", "Number of relay connection attempts").unwrap();
         let relay_connection_successes = IntCounter::new("network_relay_connection_successes", "Number of successful relay connections").unwrap();
         let relay_connection_failures = IntCounter::new("network_relay_connection_failures", "Number of failed relay connections").unwrap();
+        let relay_hole_punches = IntCounterVec::new(
+            Opts::new("network_relay_hole_punches", "Number of DCUtR hole punch attempts by outcome"),
+            &["outcome"],
+        ).unwrap();
+        
+        // Bandwidth metrics
+        let peer_bytes = IntCounterVec::new(
+            Opts::new("network_peer_bytes", "Number of bytes exchanged by peer and direction"),
+            &["peer_id", "direction"],
+        ).unwrap();
+        
+        let rate_limited = IntCounterVec::new(
+            Opts::new("network_rate_limited", "Number of messages over a rate limit by scope and direction"),
+            &["scope", "direction"],
+        ).unwrap();
+        
+        // Connection manager metrics
+        let connection_decisions = IntCounterVec::new(
+            Opts::new("network_connection_manager_decisions", "Number of connection manager decisions by type"),
+            &["decision"],
+        ).unwrap();
+        
+        // RPC metrics
+        let rpc_requests = IntCounterVec::new(
+            Opts::new("network_rpc_requests", "Number of RPC requests sent by method and outcome"),
+            &["method", "outcome"],
+        ).unwrap();
+        
+        let rpc_requests_served = IntCounterVec::new(
+            Opts::new("network_rpc_requests_served", "Number of RPC requests served by method and outcome"),
+            &["method", "outcome"],
+        ).unwrap();
+        
+        let rpc_in_flight = IntGauge::new("network_rpc_in_flight", "Number of RPC requests awaiting a response").unwrap();
+        
+        let rpc_latency = HistogramVec::new(
+            HistogramOpts::new("network_rpc_latency", "Time until an RPC request completes"),
+            &["method"],
+        ).unwrap();
         
         // Register metrics
         registry.register(Box::new(peers_connected.clone())).unwrap();
@@ -210,6 +264,14 @@ impl NetworkMetrics {
         registry.register(Box::new(relay_connection_attempts.clone())).unwrap();
         registry.register(Box::new(relay_connection_successes.clone())).unwrap();
         registry.register(Box::new(relay_connection_failures.clone())).unwrap();
+        registry.register(Box::new(relay_hole_punches.clone())).unwrap();
+        registry.register(Box::new(peer_bytes.clone())).unwrap();
+        registry.register(Box::new(rate_limited.clone())).unwrap();
+        registry.register(Box::new(connection_decisions.clone())).unwrap();
+        registry.register(Box::new(rpc_requests.clone())).unwrap();
+        registry.register(Box::new(rpc_requests_served.clone())).unwrap();
+        registry.register(Box::new(rpc_in_flight.clone())).unwrap();
+        registry.register(Box::new(rpc_latency.clone())).unwrap();
         
         info!("Network metrics initialized");
         
@@ -249,6 +311,14 @@ impl NetworkMetrics {
             relay_connection_attempts,
             relay_connection_successes,
             relay_connection_failures,
+            relay_hole_punches,
+            peer_bytes,
+            rate_limited,
+            connection_decisions,
+            rpc_requests,
+            rpc_requests_served,
+            rpc_in_flight,
+            rpc_latency,
         }
     }
     
@@ -483,6 +553,38 @@ impl NetworkMetrics {
         self.queue_priorities.with_label_values(&[peer_id, &priority.to_string()]).dec();
         self.operation_durations.with_label_values(&["message_processing"]).set(duration.as_millis() as i64);
     }
+    
+    /// Record bytes exchanged with a peer
+    pub fn record_peer_bytes(&self, peer_id: &str, direction: &str, bytes: usize) {
+        self.peer_bytes.with_label_values(&[peer_id, direction]).inc_by(bytes as u64);
+    }
+    
+    /// Record a message over a rate limit
+    pub fn record_rate_limited(&self, scope: &str, direction: &str) {
+        self.rate_limited.with_label_values(&[scope, direction]).inc();
+    }
+    
+    /// Record a connection manager decision
+    pub fn record_connection_decision(&self, decision: &str) {
+        self.connection_decisions.with_label_values(&[decision]).inc();
+    }
+    
+    /// Record an RPC request being sent
+    pub fn record_rpc_started(&self) {
+        self.rpc_in_flight.inc();
+    }
+    
+    /// Record an RPC request completing
+    pub fn record_rpc_finished(&self, method: &str, outcome: &str, duration: Duration) {
+        self.rpc_in_flight.dec();
+        self.rpc_requests.with_label_values(&[method, outcome]).inc();
+        self.rpc_latency.with_label_values(&[method]).observe(duration.as_secs_f64());
+    }
+    
+    /// Record an RPC request served for a peer
+    pub fn record_rpc_served(&self, method: &str, outcome: &str) {
+        self.rpc_requests_served.with_label_values(&[method, outcome]).inc();
+    }
 }
 
 impl Default for NetworkMetrics {
@@ -682,6 +784,12 @@ impl crate::circuit_relay::CircuitRelayMetricsExt for NetworkMetrics {
     fn record_relay_connection_failure(&self) {
         self.relay_connection_failures.inc();
     }
+    
+    /// Record the outcome of a DCUtR hole punch
+    fn record_hole_punch(&self, success: bool) {
+        let outcome = if success { "success" } else { "failure" };
+        self.relay_hole_punches.with_label_values(&[outcome]).inc();
+    }
 }
 
 #[cfg(test)]
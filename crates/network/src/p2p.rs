@@ -9,7 +9,6 @@ use std::net::SocketAddr;
 use std::num::NonZeroU32;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::task::{Context, Poll};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::time::Instant;
 use std::str::FromStr;
@@ -19,10 +18,11 @@ use futures::prelude::*;
 use libp2p::{
     self,
     core::{muxing::StreamMuxerBox, upgrade},
-    gossipsub::{self, IdentTopic, MessageAuthenticity, MessageId, ValidationMode},
+    gossipsub::{self, IdentTopic, MessageAcceptance, MessageAuthenticity, MessageId, ValidationMode},
     identify, kad, mdns, noise, ping, relay,
-    swarm::{self, ConnectionError, NetworkBehaviour, SwarmEvent, ConnectionDenied, dial_opts::DialOpts},
-    tcp, yamux, Multiaddr, PeerId, Transport,
+    request_response::{self, OutboundRequestId, ProtocolSupport, ResponseChannel},
+    swarm::{self, ConnectionError, NetworkBehaviour, SwarmEvent, dial_opts::DialOpts},
+    tcp, yamux, Multiaddr, PeerId, StreamProtocol, Transport,
     identity::Keypair,
     SwarmBuilder,
 };
@@ -36,24 +36,23 @@ use icn_core::storage::Storage;
 
 use crate::{
     MessageHandler, NetworkError, NetworkMessage, NetworkResult, NetworkService,
-    PeerInfo, MessageProcessor,
+    PeerInfo,
 };
 use crate::metrics::{NetworkMetrics, self, start_metrics_server};
-use crate::reputation::{ReputationManager, ReputationConfig, ReputationChange};
+use crate::reputation::{PeerBindings, ReputationManager, ReputationConfig, ReputationChange, ReputationLedger};
 use crate::messaging;
-use crate::circuit_relay::{CircuitRelayConfig, CircuitRelayManager, create_relay_transport};
-use crate::tests::MockStorage;
-
-use libp2p::swarm::ConnectionId;
-use libp2p::core::Endpoint;
-use libp2p::core::transport::PortUse;
-use libp2p::swarm::dummy::ConnectionHandler;
+use crate::circuit_relay::{self, CircuitRelayBehaviour, CircuitRelayConfig, CircuitRelayManager, create_relay_transport};
+use crate::codec::{self, VersionRange, WireFormat};
+use crate::envelope::{node_did, EnvelopeSigner, EnvelopeVerifier, SignedEnvelope};
+use crate::fetch::{self, FetchRequest, FetchResponse, ObjectFetcher, FETCH_PROTOCOL};
+use crate::reconcile::{self, IndexEntry, ReconcileClient, ReconcileRequest, ReconcileResponse, RECONCILE_PROTOCOL};
+use crate::rate_limit::{Direction, PeerTraffic, RateLimitConfig, RateLimiter};
+use crate::connection_manager::{ConnectionManager, ConnectionManagerConfig, ProtectionReason};
+use crate::topics::{TopicKind, TopicScope};
+use crate::rpc::{RpcClient, RpcHandler, RpcRegistry, RpcRequest, RpcResponse, RpcServerConfig, RpcService, RpcTransport, RPC_PROTOCOL};
+use crate::sharding::ShardTransport;
 
 // Topic names for gossipsub
-const TOPIC_IDENTITY: &str = "icn/identity/v1";
-const TOPIC_TRANSACTIONS: &str = "icn/transactions/v1";
-const TOPIC_LEDGER: &str = "icn/ledger/v1";
-const TOPIC_GOVERNANCE: &str = "icn/governance/v1";
 
 /// Configuration for the P2P network
 #[derive(Debug, Clone)]
@@ -90,6 +89,22 @@ pub struct P2pConfig {
     pub enable_circuit_relay: bool,
     /// Circuit relay configuration
     pub circuit_relay_config: Option<CircuitRelayConfig>,
+    /// Reject gossiped messages that are not wrapped in a signed envelope
+    pub require_signed_messages: bool,
+    /// Payload format for framed messages (JSON is kept for debugging)
+    pub wire_format: WireFormat,
+    /// Enable per-peer and per-topic rate limiting
+    pub enable_rate_limiting: bool,
+    /// Rate limit configuration
+    pub rate_limit_config: Option<RateLimitConfig>,
+    /// Federations whose topics are joined at startup
+    pub federations: Vec<String>,
+    /// Enable the connection manager
+    pub enable_connection_manager: bool,
+    /// Connection manager configuration
+    pub connection_manager_config: Option<ConnectionManagerConfig>,
+    /// Limits applied when serving RPC requests
+    pub rpc_server_config: Option<RpcServerConfig>,
 }
 
 impl Default for P2pConfig {
@@ -111,11 +126,20 @@ impl Default for P2pConfig {
             priority_config: None,
             enable_circuit_relay: false,
             circuit_relay_config: None,
+            require_signed_messages: true,
+            wire_format: WireFormat::Bincode,
+            enable_rate_limiting: false,
+            rate_limit_config: None,
+            federations: Vec::new(),
+            enable_connection_manager: false,
+            connection_manager_config: None,
+            rpc_server_config: None,
         }
     }
 }
 
 /// P2P behavior combining multiple protocols
+#[derive(NetworkBehaviour)]
 pub struct P2pBehaviour {
     /// Gossipsub for message propagation
     gossipsub: gossipsub::Behaviour,
@@ -127,78 +151,38 @@ pub struct P2pBehaviour {
     kademlia: kad::Behaviour<kad::store::MemoryStore>,
     /// mDNS for local peer discovery
     mdns: mdns::Behaviour<mdns::tokio::Tokio>,
+    /// Request/response protocol for fetching full objects
+    fetch: request_response::cbor::Behaviour<FetchRequest, FetchResponse>,
+    /// Request/response protocol for Merkle-range reconciliation
+    reconcile: request_response::cbor::Behaviour<ReconcileRequest, ReconcileResponse>,
+    /// Request/response protocol for RPC calls
+    rpc: request_response::cbor::Behaviour<RpcRequest, RpcResponse>,
+    /// Circuit relay client, optional relay server and DCUtR
+    relay: CircuitRelayBehaviour,
 }
 
-/// Events emitted by the P2pBehaviour
-#[derive(Debug)]
-pub enum P2pBehaviourEvent {
-    /// Gossipsub events
-    Gossipsub(gossipsub::Event),
-    /// Identify events
-    Identify(identify::Event),
-    /// Ping events
-    Ping(ping::Event),
-    /// Kademlia events
-    Kad(kad::Event),
-    /// mDNS events
-    Mdns(mdns::Event),
-    /// Kademlia events (for backwards compatibility)
-    Kademlia(kad::Event),
-}
-
-/// Combined events from all protocols
-#[derive(Debug)]
-enum ComposedEvent {
-    /// Ping events
-    Ping(ping::Event),
-    /// Identify events
-    Identify(identify::Event),
-    /// Kademlia events
-    Kad(kad::Event),
-    /// mDNS events
-    Mdns(mdns::Event),
-    /// GossipSub events
-    Gossipsub(gossipsub::Event),
-}
-
-impl From<ping::Event> for ComposedEvent {
-    fn from(event: ping::Event) -> Self {
-        ComposedEvent::Ping(event)
-    }
-}
-
-impl From<identify::Event> for ComposedEvent {
-    fn from(event: identify::Event) -> Self {
-        ComposedEvent::Identify(event)
-    }
-}
+/// Pending outbound fetch requests awaiting a response
+type PendingFetches = HashMap<OutboundRequestId, oneshot::Sender<NetworkResult<FetchResponse>>>;
 
-impl From<kad::Event> for ComposedEvent {
-    fn from(event: kad::Event) -> Self {
-        ComposedEvent::Kad(event)
-    }
-}
+/// Pending outbound reconciliation requests awaiting a response
+type PendingReconciles = HashMap<OutboundRequestId, oneshot::Sender<NetworkResult<ReconcileResponse>>>;
 
-impl From<mdns::Event> for ComposedEvent {
-    fn from(event: mdns::Event) -> Self {
-        ComposedEvent::Mdns(event)
-    }
-}
+/// Pending outbound RPC requests awaiting a response
+type PendingRpcs = HashMap<OutboundRequestId, oneshot::Sender<NetworkResult<RpcResponse>>>;
 
-impl From<gossipsub::Event> for ComposedEvent {
-    fn from(event: gossipsub::Event) -> Self {
-        ComposedEvent::Gossipsub(event)
-    }
-}
+/// RPC responses produced by handler tasks, ready to be sent
+type ServedRpcs = mpsc::Sender<(ResponseChannel<RpcResponse>, RpcResponse)>;
 
 /// Command messages to control the network service
 enum Command {
-    /// Broadcast a message to all peers
-    Broadcast(NetworkMessage),
-    /// Send a message to a specific peer
-    SendTo(PeerId, NetworkMessage),
+    /// Broadcast a signed message to all peers subscribed to a scope
+    Broadcast(TopicScope, SignedEnvelope),
+    /// Send a signed message to a specific peer
+    SendTo(PeerId, SignedEnvelope),
     /// Connect to a peer
     Connect(Multiaddr, mpsc::Sender<NetworkResult<PeerId>>),
+    /// Reserve a slot on a relay server and listen for circuits through it
+    ReserveRelay(Multiaddr, mpsc::Sender<NetworkResult<()>>),
     /// Disconnect from a peer
     Disconnect(PeerId, mpsc::Sender<NetworkResult<()>>),
     /// Get information about a peer
@@ -209,10 +193,23 @@ enum Command {
     RegisterHandler(String, Arc<dyn MessageHandler>, mpsc::Sender<NetworkResult<()>>),
     /// Get listen addresses
     GetListenAddresses(mpsc::Sender<NetworkResult<Vec<Multiaddr>>>),
+    /// Fetch a full object from a peer
+    Fetch(PeerId, FetchRequest, oneshot::Sender<NetworkResult<FetchResponse>>),
+    /// Send a reconciliation request to a peer
+    Reconcile(PeerId, ReconcileRequest, oneshot::Sender<NetworkResult<ReconcileResponse>>),
+    /// Send an RPC request to a peer
+    Rpc(PeerId, RpcRequest, oneshot::Sender<NetworkResult<RpcResponse>>),
+    /// Subscribe to the topics of a scope
+    JoinScope(TopicScope, mpsc::Sender<NetworkResult<()>>),
+    /// Unsubscribe from the topics of a scope
+    LeaveScope(TopicScope, mpsc::Sender<NetworkResult<()>>),
     /// Stop the network service
     Stop(mpsc::Sender<NetworkResult<()>>),
 }
 
+/// Message handlers keyed by topic scope and message type
+type ScopedHandlers = Arc<RwLock<HashMap<(TopicScope, String), Vec<Arc<dyn MessageHandler>>>>>;
+
 /// P2P network implementation
 pub struct P2pNetwork {
     /// Storage for network data
@@ -235,6 +232,8 @@ pub struct P2pNetwork {
     metrics: Option<NetworkMetrics>,
     /// Reputation manager
     reputation: Option<Arc<ReputationManager>>,
+    /// Bindings between peers and the DIDs signing their gossip
+    peer_bindings: Arc<PeerBindings>,
     /// Message processor for prioritized handling
     message_processor: Option<Arc<messaging::MessageProcessor>>,
     /// Circuit relay manager
@@ -243,6 +242,545 @@ pub struct P2pNetwork {
     swarm: Arc<Mutex<Option<swarm::Swarm<P2pBehaviour>>>>,
     /// Running state
     running: Arc<watch::Sender<bool>>,
+    /// Signer for outgoing message envelopes
+    signer: Arc<RwLock<Arc<dyn EnvelopeSigner>>>,
+    /// Verifier for incoming message envelopes
+    verifier: EnvelopeVerifier,
+    /// Handlers for incoming RPC requests
+    rpc_registry: RpcRegistry,
+    /// Client for outgoing RPC requests
+    rpc_client: RpcClient,
+    /// Rate limiter for gossip and direct messages
+    rate_limiter: Option<RateLimiter>,
+    /// Topic scopes the node has joined
+    scopes: Arc<RwLock<HashSet<TopicScope>>>,
+    /// Message handlers restricted to a topic scope
+    scoped_handlers: ScopedHandlers,
+    /// Connection manager for trimming excess connections
+    connection_manager: Option<ConnectionManager>,
+}
+
+/// Envelope signer using the node's libp2p key
+///
+/// Used until an identity signer is set with `P2pNetwork::set_signer`. The
+/// sender DID is derived from the peer ID, so messages claiming another
+/// author will be rejected by receivers.
+struct NodeSigner {
+    /// DID derived from the peer ID
+    sender: String,
+    /// Key reference
+    key_id: String,
+    /// Node key pair
+    key_pair: Keypair,
+}
+
+impl NodeSigner {
+    /// Create a signer from the node key pair
+    fn new(key_pair: Keypair) -> Self {
+        let sender = node_did(&key_pair.public().to_peer_id());
+        let key_id = format!("{}#libp2p", sender);
+        Self { sender, key_id, key_pair }
+    }
+}
+
+impl EnvelopeSigner for NodeSigner {
+    fn sender(&self) -> &str {
+        &self.sender
+    }
+    
+    fn key_id(&self) -> &str {
+        &self.key_id
+    }
+    
+    fn public_key(&self) -> Vec<u8> {
+        self.key_pair.public()
+            .try_into_ed25519()
+            .map(|key| key.to_bytes().to_vec())
+            .unwrap_or_default()
+    }
+    
+    fn sign(&self, data: &[u8]) -> NetworkResult<Vec<u8>> {
+        self.key_pair.sign(data)
+            .map_err(|e| NetworkError::Libp2pError(format!("Failed to sign envelope: {}", e)))
+    }
+}
+
+/// Shared state the swarm event handlers work on
+///
+/// Holds the network's own handles rather than copies, so peers bound or
+/// scored while handling events are seen by the `P2pNetwork` and the
+/// reputation manager reading them.
+#[derive(Clone)]
+struct SwarmEventContext {
+    /// Network configuration
+    config: P2pConfig,
+    /// Message handlers
+    handlers: Arc<RwLock<HashMap<String, Vec<Arc<dyn MessageHandler>>>>>,
+    /// Known peers
+    peers: Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
+    /// Network metrics
+    metrics: Option<NetworkMetrics>,
+    /// Reputation manager
+    reputation: Option<Arc<ReputationManager>>,
+    /// Bindings between peers and the DIDs signing their gossip
+    peer_bindings: Arc<PeerBindings>,
+    /// Message processor for prioritized handling
+    message_processor: Option<Arc<messaging::MessageProcessor>>,
+    /// Circuit relay manager
+    circuit_relay: Option<Arc<CircuitRelayManager>>,
+    /// Verifier for incoming message envelopes
+    verifier: EnvelopeVerifier,
+    /// Rate limiter for gossip and direct messages
+    rate_limiter: Option<RateLimiter>,
+    /// Topic scopes the node has joined
+    scopes: Arc<RwLock<HashSet<TopicScope>>>,
+    /// Message handlers restricted to a topic scope
+    scoped_handlers: ScopedHandlers,
+    /// Connection manager for trimming excess connections
+    connection_manager: Option<ConnectionManager>,
+}
+
+impl SwarmEventContext {
+    /// Handle swarm events
+    async fn handle_swarm_event(
+        &self,
+        event: SwarmEvent<P2pBehaviourEvent>,
+        swarm: &mut swarm::Swarm<P2pBehaviour>,
+    ) {
+        let handlers = &self.handlers;
+        let peers = &self.peers;
+        let metrics = self.metrics.as_ref();
+        let reputation = self.reputation.as_deref();
+        let message_processor = self.message_processor.as_deref();
+        
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                info!("Listening on {}", address);
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, connection_id, endpoint, num_established, .. } => {
+                // Track circuits, closing those beyond the relay's limit
+                if let (Some(relay_manager), Some(relay_id)) = (&self.circuit_relay, circuit_relay::relayed_via(&endpoint)) {
+                    if let Err(e) = relay_manager.record_relayed_connection(peer_id, relay_id).await {
+                        warn!("Closing relayed connection to {} via {}: {}", peer_id, relay_id, e);
+                        swarm.close_connection(connection_id);
+                        return;
+                    }
+                }
+                
+                if num_established == NonZeroU32::new(1).unwrap() {
+                    // This is a new connection
+                    debug!("Connection established with peer: {}", peer_id);
+                    let addr = endpoint.get_remote_address().clone();
+                    P2pNetwork::update_peer_connection(peers, &peer_id, true, Some(addr)).await;
+                    
+                    // The transport handshake authenticated the peer ID, so
+                    // bind the peer to the node DID derived from it
+                    let did = node_did(&peer_id);
+                    if self.peer_bindings.bind(peer_id, &did).await {
+                        debug!("Bound peer {} to {}", peer_id, did);
+                    }
+                    
+                    // Record connection established
+                    if let Some(m) = metrics {
+                        m.record_peer_connected();
+                    }
+                    
+                    // Update reputation
+                    if let Some(rep) = reputation {
+                        let _ = rep.record_change(peer_id, ReputationChange::ConnectionEstablished).await;
+                    }
+                    
+                    // Trim connections once above the high watermark
+                    if let Some(manager) = &self.connection_manager {
+                        manager.peer_connected(peer_id).await;
+                        for candidate in manager.select_evictions().await {
+                            info!(
+                                "Disconnecting peer {} to stay under connection limit (score {:.2})",
+                                candidate.peer_id, candidate.score
+                            );
+                            let _ = swarm.disconnect_peer_id(candidate.peer_id);
+                        }
+                    }
+                }
+            }
+            SwarmEvent::ConnectionClosed { peer_id, endpoint, cause, num_established, .. } => {
+                if let (Some(relay_manager), Some(relay_id)) = (&self.circuit_relay, circuit_relay::relayed_via(&endpoint)) {
+                    relay_manager.record_relayed_connection_closed(peer_id, relay_id).await;
+                }
+                
+                if num_established == 0 {
+                    // All connections to this peer are closed
+                    debug!("Connection closed with peer: {}, cause: {:?}", peer_id, cause);
+                    P2pNetwork::update_peer_connection(peers, &peer_id, false, None).await;
+                    
+                    if let Some(limiter) = &self.rate_limiter {
+                        limiter.remove_peer(&peer_id.to_string()).await;
+                    }
+                    
+                    if let Some(manager) = &self.connection_manager {
+                        manager.peer_disconnected(&peer_id).await;
+                    }
+                    
+                    // Record connection closed
+                    if let Some(m) = metrics {
+                        m.record_peer_disconnected();
+                    }
+                    
+                    // Update reputation based on cause
+                    if let Some(rep) = reputation {
+                        match cause {
+                            Some(e) => {
+                                debug!("Connection closed due to error: {:?}", e);
+                                // Connection issues
+                                let _ = rep.record_change(peer_id, ReputationChange::ConnectionLost).await;
+                            }
+                            None => {
+                                debug!("Connection closed normally");
+                            }
+                        }
+                    }
+                }
+            }
+            SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                warn!("Outgoing connection error to {:?}: {}", peer_id, error);
+                
+                // Record connection failure in metrics
+                if let Some(m) = metrics {
+                    m.record_connection_failure();
+                    
+                    // Record specific error type
+                    let error_type = match &error {
+                        libp2p::swarm::DialError::Transport(_) => "transport",
+                        libp2p::swarm::DialError::LocalPeerId { endpoint: _ } => "local_peer_id",
+                        _ => "other",
+                    };
+                    
+                    m.record_error(error_type);
+                }
+                
+                // Update reputation if peer ID is available
+                if let Some(peer_id) = peer_id {
+                    if let Some(rep) = reputation {
+                        let _ = rep.record_change(peer_id, ReputationChange::ConnectionLost).await;
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(P2pBehaviourEvent::Gossipsub(gossipsub::Event::Message { 
+                propagation_source,
+                message_id,
+                message,
+            })) => {
+                // Record message received metrics
+                if let Some(m) = metrics {
+                    m.record_message_received("gossipsub", message.data.len());
+                }
+                
+                debug!("Received gossip message: {} from {}", message_id, propagation_source);
+                
+                // Drop traffic over the limits before it is forwarded
+                if let Some(limiter) = &self.rate_limiter {
+                    let sender = propagation_source.to_string();
+                    if let Err(e) = limiter.check(
+                        Some(&sender),
+                        Some(message.topic.as_str()),
+                        Direction::Inbound,
+                        message.data.len(),
+                    ).await {
+                        debug!("Dropping gossip message {}: {}", message_id, e);
+                        swarm.behaviour_mut().gossipsub.report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Ignore,
+                        );
+                        if let Some(rep) = reputation {
+                            let _ = rep.record_change(propagation_source, ReputationChange::RateLimitExceeded).await;
+                        }
+                        return;
+                    }
+                }
+                
+                // Ignore traffic for scopes that were left or never joined
+                let scope = match TopicScope::parse(message.topic.as_str()) {
+                    Some((scope, _)) if self.scopes.read().await.contains(&scope) => scope,
+                    _ => {
+                        debug!("Ignoring gossip message {} on topic {}", message_id, message.topic);
+                        swarm.behaviour_mut().gossipsub.report_message_validation_result(
+                            &message_id,
+                            &propagation_source,
+                            MessageAcceptance::Ignore,
+                        );
+                        return;
+                    }
+                };
+                
+                // Verify the envelope before any handler sees the message
+                let (network_message, sender) = match self.validate_gossip_message(
+                    swarm,
+                    &message_id,
+                    &propagation_source,
+                    message.source.as_ref(),
+                    &message.data,
+                ).await {
+                    Some(validated) => validated,
+                    None => return,
+                };
+                let message_type = network_message.message_type();
+                
+                // First check if using message processor
+                if let Some(processor) = message_processor {
+                    // Get peer info
+                    let mut peer_info = P2pNetwork::get_peer_info_from_id(peers, &propagation_source).await;
+                    peer_info.sender = sender;
+                    
+                    // Process with priority-based processor
+                    if let Err(e) = processor.process_message(network_message, peer_info).await {
+                        error!("Failed to process message: {}", e);
+                        
+                        // Record error and update reputation
+                        if let Some(m) = metrics {
+                            m.record_error("message_processing");
+                        }
+                        
+                        if let Some(rep) = reputation {
+                            let _ = rep.record_change(propagation_source, ReputationChange::MessageFailure).await;
+                        }
+                    }
+                } else {
+                    // Fall back to direct handler calling if no message processor
+                    let start_time = Instant::now();
+                    let mut handled_successfully = false;
+                    
+                    // Get peer info
+                    let mut peer_info = P2pNetwork::get_peer_info_from_id(peers, &propagation_source).await;
+                    peer_info.sender = sender;
+                    
+                    // Call all handlers for this message type, and those for this scope
+                    let mut type_handlers = handlers.read().await
+                        .get(&message_type)
+                        .cloned()
+                        .unwrap_or_default();
+                    if let Some(scope_handlers) = self.scoped_handlers.read().await.get(&(scope, message_type)) {
+                        type_handlers.extend(scope_handlers.iter().cloned());
+                    }
+                    
+                    if !type_handlers.is_empty() {
+                        let mut success = true;
+                        
+                        for handler in &type_handlers {
+                            if let Err(e) = handler.handle_message(&network_message, &peer_info).await {
+                                error!("Handler error: {}", e);
+                                success = false;
+                                
+                                // Update reputation for message failure
+                                if let Some(rep) = reputation {
+                                    let _ = rep.record_change(propagation_source, ReputationChange::MessageFailure).await;
+                                }
+                            }
+                        }
+                        
+                        // Update reputation for successful message
+                        if success {
+                            handled_successfully = true;
+                            if let Some(rep) = reputation {
+                                let _ = rep.record_change(propagation_source, ReputationChange::MessageSuccess).await;
+                            }
+                        }
+                    }
+                    
+                    // Record message processing time
+                    let elapsed = start_time.elapsed();
+                    if let Some(m) = metrics {
+                        m.record_message_processing_time(elapsed);
+                    }
+                    
+                    // Record response time
+                    if let Some(rep) = reputation {
+                        let _ = rep.record_response_time(&propagation_source, elapsed).await;
+                    }
+                    
+                    // Update reputation based on processing time
+                    if handled_successfully {
+                        if let Some(reputation) = &self.reputation {
+                            let _ = reputation.record_change(propagation_source, ReputationChange::VerifiedMessage).await;
+                        }
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(P2pBehaviourEvent::Ping(ping::Event {
+                peer,
+                result: Ok(rtt),
+                ..
+            })) => {
+                // Record ping/latency metrics
+                if let Some(m) = metrics {
+                    m.record_peer_latency(&peer.to_string(), rtt).await;
+                }
+                
+                // Update reputation based on ping time
+                if let Some(rep) = reputation {
+                    let _ = rep.record_response_time(&peer, rtt).await;
+                }
+                
+                debug!("Ping to {} took {:?}", peer, rtt);
+            }
+            SwarmEvent::Behaviour(P2pBehaviourEvent::Mdns(mdns::Event::Discovered(list))) => {
+                for (peer_id, addr) in list {
+                    debug!("mDNS discovered peer: {} at {}", peer_id, addr);
+                    
+                    // Record mDNS discovery
+                    if let Some(m) = metrics {
+                        m.record_mdns_discovery();
+                        m.record_peer_discovered();
+                    }
+                    
+                    // Update reputation for discovery help
+                    if let Some(rep) = reputation {
+                        let _ = rep.record_change(peer_id, ReputationChange::DiscoveryHelp).await;
+                    }
+                    
+                    // Update peer info
+                    P2pNetwork::update_peer_connection(peers, &peer_id, false, Some(addr.clone())).await;
+                    
+                    // Check if peer is banned
+                    let should_dial = if let Some(rep) = reputation {
+                        !rep.is_banned(&peer_id)
+                    } else {
+                        true
+                    };
+                    
+                    // Try to dial the peer if not banned and not connected
+                    if should_dial && !swarm.is_connected(&peer_id) {
+                        debug!("Dialing discovered peer {}", peer_id);
+                        match swarm.dial(addr) {
+                            Ok(_) => {}
+                            Err(e) => warn!("Failed to dial discovered peer {}: {}", peer_id, e),
+                        }
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(P2pBehaviourEvent::Kademlia(kad::Event::RoutingUpdated {
+                peer,
+                addresses,
+                ..
+            })) => {
+                debug!("Kademlia routing updated for peer: {}", peer);
+                
+                // Record Kademlia discovery
+                if let Some(m) = metrics {
+                    m.record_kad_discovery();
+                }
+                
+                // Convert addresses to Vec<Multiaddr>
+                let addrs: Vec<Multiaddr> = addresses.iter().cloned().collect();
+                
+                // Update peer info with the new addresses
+                P2pNetwork::update_peer_info(peers, &peer, &addrs, &[]).await;
+            }
+            SwarmEvent::Behaviour(P2pBehaviourEvent::Identify(identify::Event::Received {
+                peer_id,
+                info,
+                ..
+            })) => {
+                let protocols: Vec<String> = info.protocols.iter().map(|p| p.to_string()).collect();
+                P2pNetwork::update_peer_info(peers, &peer_id, &info.listen_addrs, &protocols).await;
+                
+                // Remember the peer's advertised wire versions for negotiation
+                match VersionRange::from_advertisement(&info.protocol_version) {
+                    Some(range) => debug!("Peer {} supports wire versions {}", peer_id, range),
+                    None => debug!("Peer {} does not advertise wire versions, leaving it out of negotiation", peer_id),
+                }
+                
+                let mut peers_guard = peers.write().await;
+                if let Some(entry) = peers_guard.get_mut(&peer_id) {
+                    entry.protocol_version = Some(info.protocol_version);
+                    entry.agent_version = Some(info.agent_version);
+                }
+            }
+            _ => {} // Ignore other events
+        }
+    }
+    
+    /// Validate a gossiped message and report the result to gossipsub
+    ///
+    /// Messages are only propagated further once accepted here. Envelopes
+    /// with a bad signature or an unauthorized sender are rejected and count
+    /// against the propagating peer's reputation. Unsigned messages are
+    /// accepted only when `require_signed_messages` is off. Envelopes framed
+    /// with an unsupported wire version, signed outside the accepted time
+    /// window or seen before are dropped without penalty. A verified
+    /// envelope's sender DID is returned with the message.
+    async fn validate_gossip_message(
+        &self,
+        swarm: &mut swarm::Swarm<P2pBehaviour>,
+        message_id: &MessageId,
+        source: &PeerId,
+        author: Option<&PeerId>,
+        data: &[u8],
+    ) -> Option<(NetworkMessage, Option<String>)> {
+        let metrics = self.metrics.as_ref();
+        let reputation = self.reputation.as_ref();
+        
+        let (result, acceptance) = match codec::decode::<SignedEnvelope>(data) {
+            Ok(envelope) => match self.verifier.verify_from(&envelope, author).await {
+                Ok(()) => {
+                    (Some((envelope.message, Some(envelope.sender))), MessageAcceptance::Accept)
+                }
+                Err(e @ (NetworkError::ExpiredMessage(_) | NetworkError::ReplayedMessage(_))) => {
+                    // Stale or duplicate copies may be relayed by honest peers
+                    debug!("Ignoring gossip message {} from {}: {}", message_id, source, e);
+                    if let Some(m) = metrics {
+                        m.record_error("message_replay");
+                    }
+                    (None, MessageAcceptance::Ignore)
+                }
+                Err(e) => {
+                    warn!("Rejecting gossip message {} from {}: {}", message_id, source, e);
+                    if let Some(m) = metrics {
+                        m.record_error("message_signature");
+                    }
+                    if let Some(rep) = reputation {
+                        let _ = rep.record_change(*source, ReputationChange::InvalidSignature).await;
+                    }
+                    (None, MessageAcceptance::Reject)
+                }
+            },
+            Err(NetworkError::UnsupportedVersion(version)) => {
+                // Not the sender's fault; drop the message without penalty
+                debug!("Ignoring gossip message {} with unsupported wire version {}", message_id, version);
+                if let Some(m) = metrics {
+                    m.record_error("unsupported_version");
+                }
+                (None, MessageAcceptance::Ignore)
+            }
+            Err(_) if !self.config.require_signed_messages => match codec::decode::<NetworkMessage>(data) {
+                Ok(network_message) => (Some((network_message, None)), MessageAcceptance::Accept),
+                Err(e) => {
+                    warn!("Failed to deserialize gossip message: {}", e);
+                    if let Some(m) = metrics {
+                        m.record_error("message_deserialization");
+                    }
+                    if let Some(rep) = reputation {
+                        let _ = rep.record_change(*source, ReputationChange::InvalidMessage).await;
+                    }
+                    (None, MessageAcceptance::Reject)
+                }
+            },
+            Err(e) => {
+                warn!("Rejecting unsigned gossip message {} from {}: {}", message_id, source, e);
+                if let Some(m) = metrics {
+                    m.record_error("message_signature");
+                }
+                if let Some(rep) = reputation {
+                    let _ = rep.record_change(*source, ReputationChange::InvalidSignature).await;
+                }
+                (None, MessageAcceptance::Reject)
+            }
+        };
+        
+        swarm.behaviour_mut().gossipsub.report_message_validation_result(message_id, source, acceptance);
+        
+        result
+    }
 }
 
 impl P2pNetwork {
@@ -250,6 +788,27 @@ impl P2pNetwork {
     pub async fn new(
         storage: Arc<dyn Storage>,
         config: P2pConfig,
+    ) -> NetworkResult<Self> {
+        Self::create(storage, config, None).await
+    }
+    
+    /// Create a P2P network whose peer scoring shares the DID reputation ledger
+    ///
+    /// Peers are bound to the DIDs that sign their gossip, and the scores of
+    /// bound peers are read from and recorded in the ledger.
+    pub async fn with_reputation_ledger(
+        storage: Arc<dyn Storage>,
+        config: P2pConfig,
+        ledger: Arc<ReputationLedger>,
+    ) -> NetworkResult<Self> {
+        Self::create(storage, config, Some(ledger)).await
+    }
+    
+    /// Create the network, sharing the reputation ledger if one is given
+    async fn create(
+        storage: Arc<dyn Storage>,
+        config: P2pConfig,
+        ledger: Option<Arc<ReputationLedger>>,
     ) -> NetworkResult<Self> {
         // Generate or load keypair
         let key_pair = Self::load_or_create_keypair(storage.clone()).await?;
@@ -259,6 +818,13 @@ impl P2pNetwork {
         
         // Create message handlers map
         let handlers = Arc::new(RwLock::new(HashMap::new()));
+        let scoped_handlers = Arc::new(RwLock::new(HashMap::new()));
+        
+        // Topics for these scopes are subscribed when the swarm is created
+        let mut scopes = HashSet::from([TopicScope::Global]);
+        for federation_id in &config.federations {
+            scopes.insert(TopicScope::federation(federation_id)?);
+        }
         
         // Create peer info map
         let peers = Arc::new(RwLock::new(HashMap::new()));
@@ -281,9 +847,13 @@ impl P2pNetwork {
         };
         
         // Create reputation manager if enabled
+        let peer_bindings = Arc::new(PeerBindings::new());
         let reputation = if config.enable_reputation {
             let rep_config = config.reputation_config.clone().unwrap_or_default();
-            let manager = ReputationManager::new(rep_config);
+            let manager = match ledger {
+                Some(ledger) => ReputationManager::with_ledger(rep_config, ledger, peer_bindings.clone()),
+                None => ReputationManager::new(rep_config),
+            };
             
             // Start decay task
             let _ = manager.start_decay_task().await;
@@ -300,7 +870,7 @@ impl P2pNetwork {
         let circuit_relay = if config.enable_circuit_relay {
             let relay_config = config.circuit_relay_config.clone().unwrap_or_default();
             let manager = CircuitRelayManager::new(
-                relay_config,
+                relay_config.clone(),
                 reputation.clone().expect("Reputation manager is required for circuit relay"),
                 metrics.clone().map(Arc::new)
             );
@@ -308,6 +878,13 @@ impl P2pNetwork {
             // Initialize relay manager
             manager.initialize().await?;
             
+            for addr in &relay_config.known_relay_servers {
+                match extract_peer_id(addr) {
+                    Some(relay_id) => manager.add_relay_server(relay_id, vec![addr.clone()]).await?,
+                    None => warn!("Ignoring relay server address without peer ID: {}", addr),
+                }
+            }
+            
             // Start cleanup task
             manager.start_cleanup_task();
             
@@ -316,6 +893,41 @@ impl P2pNetwork {
             None
         };
         
+        // Sign with the node key until an identity signer is set
+        let signer: Arc<dyn EnvelopeSigner> = Arc::new(NodeSigner::new(key_pair.clone()));
+        let verifier = EnvelopeVerifier::new();
+        
+        let rpc_registry = RpcRegistry::with_config(config.rpc_server_config.clone().unwrap_or_default(), metrics.clone());
+        let rpc_client = RpcClient::new(metrics.clone());
+        
+        let rate_limiter = config.enable_rate_limiting.then(|| {
+            RateLimiter::new(config.rate_limit_config.clone().unwrap_or_default(), metrics.clone())
+        });
+        
+        // Create connection manager if enabled, protecting bootstrap and relay peers
+        let connection_manager = if config.enable_connection_manager {
+            let manager = ConnectionManager::new(
+                config.connection_manager_config.clone().unwrap_or_default(),
+                reputation.clone(),
+                metrics.clone(),
+            );
+            
+            for addr in &config.bootstrap_peers {
+                if let Some(peer_id) = addr.parse::<Multiaddr>().ok().as_ref().and_then(extract_peer_id) {
+                    manager.protect(peer_id, ProtectionReason::Bootstrap).await;
+                }
+            }
+            if let Some(relay_config) = config.circuit_relay_config.as_ref().filter(|_| config.enable_circuit_relay) {
+                for peer_id in relay_config.known_relay_servers.iter().filter_map(extract_peer_id) {
+                    manager.protect(peer_id, ProtectionReason::Relay).await;
+                }
+            }
+            
+            Some(manager)
+        } else {
+            None
+        };
+        
         // Create network instance
         let network = Self {
             storage,
@@ -328,10 +940,19 @@ impl P2pNetwork {
             peers,
             metrics,
             reputation,
+            peer_bindings,
             message_processor,
             circuit_relay,
             swarm: Arc::new(Mutex::new(None)),
             running: watch::channel(true).0.into(),
+            signer: Arc::new(RwLock::new(signer)),
+            verifier,
+            rpc_registry,
+            rpc_client,
+            rate_limiter,
+            scopes: Arc::new(RwLock::new(scopes)),
+            scoped_handlers,
+            connection_manager,
         };
         
         // Start background task
@@ -345,6 +966,45 @@ impl P2pNetwork {
         self.local_peer_id
     }
     
+    /// Get the DID a peer is bound to
+    ///
+    /// Connected peers are bound to the node DID derived from their peer ID
+    /// once the connection handshake has authenticated it.
+    pub async fn peer_did(&self, peer_id: &PeerId) -> Option<String> {
+        self.peer_bindings.did_for(peer_id).await
+    }
+    
+    /// Set the signer used for outgoing message envelopes
+    ///
+    /// The signer's key is also registered with the local verifier.
+    pub async fn set_signer(&self, signer: Arc<dyn EnvelopeSigner>) {
+        self.verifier.register_key(signer.sender(), signer.public_key()).await;
+        *self.signer.write().await = signer;
+    }
+    
+    /// Get the bindings between peers and the DIDs signing their gossip
+    pub fn peer_bindings(&self) -> Arc<PeerBindings> {
+        self.peer_bindings.clone()
+    }
+    
+    /// Get the verifier used for incoming message envelopes
+    pub fn envelope_verifier(&self) -> EnvelopeVerifier {
+        self.verifier.clone()
+    }
+    
+    /// Get the client used for outgoing RPC requests
+    ///
+    /// Use it to allocate request IDs and cancel in-flight requests.
+    pub fn rpc_client(&self) -> RpcClient {
+        self.rpc_client.clone()
+    }
+    
+    /// Wrap a message in a signed envelope
+    async fn sign_message(&self, message: NetworkMessage) -> NetworkResult<SignedEnvelope> {
+        let signer = self.signer.read().await.clone();
+        SignedEnvelope::sign(message, signer.as_ref())
+    }
+    
     /// Get the listen addresses
     pub async fn listen_addresses(&self) -> NetworkResult<Vec<Multiaddr>> {
         let (tx, mut rx) = mpsc::channel(1);
@@ -392,30 +1052,28 @@ impl P2pNetwork {
     fn create_swarm(key_pair: &Keypair, config: &P2pConfig) -> NetworkResult<swarm::Swarm<P2pBehaviour>> {
         let local_peer_id = PeerId::from(key_pair.public());
         
-        // Create transport
-        let base_transport = {
-            let tcp = libp2p::tcp::tokio::Transport::default()
-                .upgrade(upgrade::Version::V1)
-                .authenticate(noise::Config::new(key_pair).map_err(|e| NetworkError::Libp2pError(e.to_string()))?)
-                .multiplex(yamux::Config::default())
-                .timeout(config.keep_alive);
-            
-            // Wrap in StreamMuxerBox to satisfy type requirements
-            let boxed = tcp.map(|(peer_id, muxer), _| {
-                (peer_id, libp2p::core::muxing::StreamMuxerBox::new(muxer))
-            });
-            
-            libp2p::Transport::boxed(boxed)
-        };
+        // Create transport, dialing relayed addresses through the relay client
+        let (relay_transport, relay_client) = relay::client::new(local_peer_id);
+        let transport = create_relay_transport(
+            libp2p::tcp::tokio::Transport::default(),
+            relay_transport,
+            key_pair,
+            config.keep_alive,
+        )?;
         
-        // We'll skip the relay transport for now since it's not fully implemented
-        // In a real implementation, we would create and use the relay transport here
-        let transport = base_transport;
+        // The relay server and DCUtR are only enabled with circuit relay
+        let mut relay_config = config.circuit_relay_config.clone().unwrap_or_default();
+        if !config.enable_circuit_relay {
+            relay_config.enable_relay_server = false;
+            relay_config.enable_dcutr = false;
+        }
         
         // Set up gossipsub
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .heartbeat_interval(Duration::from_secs(10))
             .validation_mode(config.gossipsub_validation.clone())
+            // Envelopes are verified before messages are forwarded
+            .validate_messages()
             .build()
             .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
         
@@ -423,20 +1081,11 @@ impl P2pNetwork {
         let mut gossipsub = gossipsub::Behaviour::new(message_authenticity, gossipsub_config)
             .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
         
-        // Subscribe to topics
-        let topic_identity = IdentTopic::new(TOPIC_IDENTITY);
-        let topic_transactions = IdentTopic::new(TOPIC_TRANSACTIONS);
-        let topic_ledger = IdentTopic::new(TOPIC_LEDGER);
-        let topic_governance = IdentTopic::new(TOPIC_GOVERNANCE);
-        
-        gossipsub.subscribe(&topic_identity)
-            .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
-        gossipsub.subscribe(&topic_transactions)
-            .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
-        gossipsub.subscribe(&topic_ledger)
-            .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
-        gossipsub.subscribe(&topic_governance)
-            .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
+        // Subscribe to the global topics and those of configured federations
+        Self::subscribe_scope(&mut gossipsub, &TopicScope::Global)?;
+        for federation_id in &config.federations {
+            Self::subscribe_scope(&mut gossipsub, &TopicScope::federation(federation_id)?)?;
+        }
         
         // Set up Kademlia
         let store = kad::store::MemoryStore::new(key_pair.public().to_peer_id());
@@ -451,16 +1100,40 @@ impl P2pNetwork {
         let mdns = mdns::Behaviour::<mdns::tokio::Tokio>::new(mdns::Config::default(), key_pair.public().to_peer_id())
             .map_err(|e| NetworkError::Libp2pError(format!("Failed to create mDNS: {}", e)))?;
         
+        // Set up the full-object fetch protocol
+        let fetch = request_response::cbor::Behaviour::new(
+            [(StreamProtocol::new(FETCH_PROTOCOL), ProtocolSupport::Full)],
+            request_response::Config::default().with_request_timeout(config.message_timeout),
+        );
+        
+        // Set up the Merkle-range reconciliation protocol
+        let reconcile = request_response::cbor::Behaviour::new(
+            [(StreamProtocol::new(RECONCILE_PROTOCOL), ProtocolSupport::Full)],
+            request_response::Config::default().with_request_timeout(config.message_timeout),
+        );
+        
+        // Set up the RPC protocol; callers enforce their own, shorter
+        // deadlines, so the transport only bounds the slowest QoS level
+        let rpc = request_response::cbor::Behaviour::new(
+            [(StreamProtocol::new(RPC_PROTOCOL), ProtocolSupport::Full)],
+            request_response::Config::default().with_request_timeout(messaging::QosLevel::Background.timeout()),
+        );
+        
         // Build the swarm
         let behaviour = P2pBehaviour {
             ping: ping::Behaviour::new(ping::Config::new()),
+            // Supported wire versions are advertised as the identify protocol version
             identify: identify::Behaviour::new(identify::Config::new(
-                "/ipfs/id/1.0.0".to_string(),
+                VersionRange::LOCAL.to_advertisement(),
                 key_pair.public(),
             )),
             kademlia: kad_behaviour,
             mdns,
             gossipsub,
+            fetch,
+            reconcile,
+            rpc,
+            relay: CircuitRelayBehaviour::new(local_peer_id, relay_client, &relay_config),
         };
         
         // Create the swarm directly without using the builder pattern
@@ -474,6 +1147,25 @@ impl P2pNetwork {
         Ok(swarm)
     }
     
+    /// Get the shared state for handling swarm events
+    fn swarm_event_context(&self) -> SwarmEventContext {
+        SwarmEventContext {
+            config: self.config.clone(),
+            handlers: self.handlers.clone(),
+            peers: self.peers.clone(),
+            metrics: self.metrics.clone(),
+            reputation: self.reputation.clone(),
+            peer_bindings: self.peer_bindings.clone(),
+            message_processor: self.message_processor.clone(),
+            circuit_relay: self.circuit_relay.clone(),
+            verifier: self.verifier.clone(),
+            rate_limiter: self.rate_limiter.clone(),
+            scopes: self.scopes.clone(),
+            scoped_handlers: self.scoped_handlers.clone(),
+            connection_manager: self.connection_manager.clone(),
+        }
+    }
+    
     /// Start the background network task
     async fn start_background_task(&self, command_rx: mpsc::Receiver<Command>) -> NetworkResult<()> {
         // Create a new swarm for the background task
@@ -485,6 +1177,18 @@ impl P2pNetwork {
                 .map_err(|e| NetworkError::Libp2pError(format!("Failed to listen on {}: {}", addr, e)))?;
         }
         
+        // Reserve slots on known relay servers
+        if let Some(relay_config) = self.config.circuit_relay_config.as_ref()
+            .filter(|c| self.config.enable_circuit_relay && c.enable_relay_client)
+        {
+            for addr in &relay_config.known_relay_servers {
+                match swarm.listen_on(circuit_relay::circuit_listen_address(addr)) {
+                    Ok(_) => info!("Reserving slot on relay {}", addr),
+                    Err(e) => warn!("Failed to listen through relay {}: {}", addr, e),
+                }
+            }
+        }
+        
         // Connect to bootstrap peers
         for addr in &self.config.bootstrap_peers {
             if let Ok(multiaddr) = addr.parse::<Multiaddr>() {
@@ -505,12 +1209,16 @@ impl P2pNetwork {
         // Release the lock
         drop(swarm_lock);
         
+        let events = self.swarm_event_context();
         let peers = self.peers.clone();
         let handlers = self.handlers.clone();
         let metrics = self.metrics.clone();
-        let reputation = self.reputation.clone();
-        let message_processor = self.message_processor.clone();
         let peer_id = self.local_peer_id;
+        let storage = self.storage.clone();
+        let config = self.config.clone();
+        let rpc_registry = self.rpc_registry.clone();
+        let circuit_relay = self.circuit_relay.clone();
+        let scopes = self.scopes.clone();
         let running = self.running.clone();
         let mut running_rx = running.subscribe();
         
@@ -519,6 +1227,10 @@ impl P2pNetwork {
             info!("P2P network task started, peer ID: {}", peer_id);
             
             let mut command_rx = command_rx;
+            let mut pending_fetches: PendingFetches = HashMap::new();
+            let mut pending_reconciles: PendingReconciles = HashMap::new();
+            let mut pending_rpcs: PendingRpcs = HashMap::new();
+            let (served_rpcs, mut served_rpc_rx) = mpsc::channel(100);
             
             // Main event loop
             loop {
@@ -527,31 +1239,45 @@ impl P2pNetwork {
                     event = swarm.select_next_some() => {
                         let start_time = Instant::now();
                         
-                        Self::handle_swarm_event(
-                            &P2pNetwork {
-                                storage: Arc::new(MockStorage::new()),
-                                key_pair: Keypair::generate_ed25519(),
-                                local_peer_id: peer_id,
-                                config: P2pConfig::default(),
-                                command_tx: Arc::new(Mutex::new(mpsc::channel(1).0)),
-                                task_handle: Arc::new(Mutex::new(None)),
-                                handlers: handlers.clone(),
-                                peers: peers.clone(),
-                                metrics: metrics.clone(),
-                                reputation: reputation.clone(),
-                                message_processor: message_processor.clone(),
-                                circuit_relay: None,
-                                swarm: Arc::new(Mutex::new(None)),
-                                running: watch::channel(true).0.into(),
-                            },
-                            event, 
-                            &mut swarm,
-                            &handlers,
-                            &peers,
-                            metrics.as_ref(),
-                            reputation.as_ref().map(|r| r.as_ref()),
-                            message_processor.as_ref().map(|m| m.as_ref()),
-                        ).await;
+                        // Fetch protocol events need the task-local request state
+                        let event = match event {
+                            SwarmEvent::Behaviour(P2pBehaviourEvent::Fetch(fetch_event)) => {
+                                Self::handle_fetch_event(
+                                    fetch_event,
+                                    &mut swarm,
+                                    storage.as_ref(),
+                                    &mut pending_fetches,
+                                ).await;
+                                continue;
+                            }
+                            SwarmEvent::Behaviour(P2pBehaviourEvent::Reconcile(reconcile_event)) => {
+                                Self::handle_reconcile_event(
+                                    reconcile_event,
+                                    &mut swarm,
+                                    storage.as_ref(),
+                                    &mut pending_reconciles,
+                                ).await;
+                                continue;
+                            }
+                            SwarmEvent::Behaviour(P2pBehaviourEvent::Relay(relay_event)) => {
+                                if let Some(relay_manager) = &circuit_relay {
+                                    relay_manager.handle_event(relay_event).await;
+                                }
+                                continue;
+                            }
+                            SwarmEvent::Behaviour(P2pBehaviourEvent::Rpc(rpc_event)) => {
+                                Self::handle_rpc_event(
+                                    rpc_event,
+                                    &rpc_registry,
+                                    &served_rpcs,
+                                    &mut pending_rpcs,
+                                );
+                                continue;
+                            }
+                            event => event,
+                        };
+                        
+                        events.handle_swarm_event(event, &mut swarm).await;
                         
                         // Record event processing time
                         if let Some(metrics) = &metrics {
@@ -560,6 +1286,13 @@ impl P2pNetwork {
                         }
                     }
                     
+                    // Send responses from RPC handler tasks
+                    Some((channel, response)) = served_rpc_rx.recv() => {
+                        if swarm.behaviour_mut().rpc.send_response(channel, response).is_err() {
+                            warn!("Failed to send RPC response: connection closed");
+                        }
+                    }
+                    
                     // Handle commands
                     Some(cmd) = command_rx.recv() => {
                         match cmd {
@@ -568,328 +1301,206 @@ impl P2pNetwork {
                                 let _ = response_tx.send(Ok(())).await;
                                 break;
                             }
-                            // Handle other commands...
-                            _ => { /* Handle other commands */ }
-                        }
-                    }
-                    
-                    // Exit if running_rx is closed or changed to false
-                    Ok(running) = running_rx.changed() => {
-                        if !*running_rx.borrow() {
-                            info!("P2P service is shutting down");
-                            break;
-                        }
-                    }
-                    
-                    else => {
-                        // Channel closed, exit loop
-                        error!("All channels closed unexpectedly");
-                        break;
-                    }
-                }
-            }
-            
-            info!("P2P network task stopped");
-        });
-        
-        // Store the task handle
-        let mut handle = self.task_handle.lock().await;
-        *handle = Some(task);
-        
-        Ok(())
-    }
-    
-    /// Handle swarm events
-    async fn handle_swarm_event(
-        &self,
-        event: SwarmEvent<P2pBehaviourEvent>,
-        swarm: &mut swarm::Swarm<P2pBehaviour>,
-        handlers: &Arc<RwLock<HashMap<String, Vec<Arc<dyn MessageHandler>>>>>,
-        peers: &Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
-        metrics: Option<&NetworkMetrics>,
-        reputation: Option<&ReputationManager>,
-        message_processor: Option<&MessageProcessor>,
-    ) {
-        match event {
-            SwarmEvent::NewListenAddr { address, .. } => {
-                info!("Listening on {}", address);
-            }
-            SwarmEvent::ConnectionEstablished { peer_id, endpoint, num_established, .. } => {
-                if num_established == NonZeroU32::new(1).unwrap() {
-                    // This is a new connection
-                    debug!("Connection established with peer: {}", peer_id);
-                    let addr = endpoint.get_remote_address().clone();
-                    Self::update_peer_connection(peers, &peer_id, true, Some(addr)).await;
-                    
-                    // Record connection established
-                    if let Some(m) = metrics {
-                        m.record_peer_connected();
-                    }
-                    
-                    // Update reputation
-                    if let Some(rep) = reputation {
-                        let _ = rep.record_change(peer_id, ReputationChange::ConnectionEstablished).await;
-                    }
-                }
-            }
-            SwarmEvent::ConnectionClosed { peer_id, cause, num_established, .. } => {
-                if num_established == 0 {
-                    // All connections to this peer are closed
-                    debug!("Connection closed with peer: {}, cause: {:?}", peer_id, cause);
-                    Self::update_peer_connection(peers, &peer_id, false, None).await;
-                    
-                    // Record connection closed
-                    if let Some(m) = metrics {
-                        m.record_peer_disconnected();
-                    }
-                    
-                    // Update reputation based on cause
-                    if let Some(rep) = reputation {
-                        match cause {
-                            Some(e) => {
-                                debug!("Connection closed due to error: {:?}", e);
-                                // Connection issues
-                                let _ = rep.record_change(peer_id, ReputationChange::ConnectionLost).await;
+                            Command::Broadcast(scope, envelope) => {
+                                Self::handle_broadcast(&mut swarm, &scope, envelope, &peers, config.wire_format).await;
                             }
-                            None => {
-                                debug!("Connection closed normally");
+                            Command::SendTo(target, envelope) => {
+                                Self::handle_send_to(&mut swarm, &target, envelope, &peers, config.wire_format).await;
                             }
-                        }
-                    }
-                }
-            }
-            SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
-                warn!("Outgoing connection error to {:?}: {}", peer_id, error);
-                
-                // Record connection failure in metrics
-                if let Some(m) = metrics {
-                    m.record_connection_failure();
-                    
-                    // Record specific error type
-                    let error_type = match &error {
-                        libp2p::swarm::DialError::Transport(_) => "transport",
-                        libp2p::swarm::DialError::LocalPeerId { endpoint: _ } => "local_peer_id",
-                        _ => "other",
-                    };
-                    
-                    m.record_error(error_type);
-                }
-                
-                // Update reputation if peer ID is available
-                if let Some(peer_id) = peer_id {
-                    if let Some(rep) = reputation {
-                        let _ = rep.record_change(peer_id, ReputationChange::ConnectionLost).await;
-                    }
-                }
-            }
-            SwarmEvent::Behaviour(P2pBehaviourEvent::Gossipsub(gossipsub::Event::Message { 
-                propagation_source,
-                message_id,
-                message,
-            })) => {
-                // Record message received metrics
-                if let Some(m) = metrics {
-                    m.record_message_received("gossipsub", message.data.len());
-                }
-                
-                debug!("Received gossip message: {} from {}", message_id, propagation_source);
-                
-                // Extract message type from the topic
-                let topic = &message.topic;
-                let message_type = match topic.as_str() {
-                    TOPIC_IDENTITY => "identity",
-                    TOPIC_TRANSACTIONS => "transaction",
-                    TOPIC_LEDGER => "ledger",
-                    TOPIC_GOVERNANCE => {
-                        // For governance, we need to look at the message content to determine if it's a proposal or vote
-                        // This is a simplification; in a real system we would have a more robust mechanism
-                        if message.data.starts_with(b"proposal") {
-                            "proposal"
-                        } else {
-                            "vote"
-                        }
-                    },
-                    _ => {
-                        // For unknown topics, we'll just use the topic name
-                        topic.as_str()
-                    }
-                };
-                
-                // First check if using message processor
-                if let Some(processor) = message_processor {
-                    // Get peer info
-                    let peer_info = Self::get_peer_info_from_id(peers, &propagation_source).await;
-                    
-                    // Deserialize the message
-                    match serde_json::from_slice::<NetworkMessage>(&message.data) {
-                        Ok(network_message) => {
-                            // Process with priority-based processor
-                            let net_message = network_message;
-                            
-                            if let Err(e) = processor.process_message(net_message, peer_info).await {
-                                error!("Failed to process message: {}", e);
-                                
-                                // Record error and update reputation
-                                if let Some(m) = metrics {
-                                    m.record_error("message_processing");
-                                }
-                                
-                                if let Some(rep) = reputation {
-                                    let _ = rep.record_change(propagation_source, ReputationChange::MessageFailure).await;
-                                }
+                            Command::Fetch(target, request, response_tx) => {
+                                debug!("Requesting {} from {}", request.storage_key(), target);
+                                let request_id = swarm.behaviour_mut().fetch.send_request(&target, request);
+                                pending_fetches.insert(request_id, response_tx);
                             }
-                        }
-                        Err(e) => {
-                            warn!("Failed to deserialize gossip message: {}", e);
-                            
-                            // Record error
-                            if let Some(m) = metrics {
-                                m.record_error("message_deserialization");
+                            Command::Reconcile(target, request, response_tx) => {
+                                let request_id = swarm.behaviour_mut().reconcile.send_request(&target, request);
+                                pending_reconciles.insert(request_id, response_tx);
                             }
-                            
-                            // Update reputation for invalid message
-                            if let Some(rep) = reputation {
-                                let _ = rep.record_change(propagation_source, ReputationChange::InvalidMessage).await;
+                            Command::Connect(address, response_tx) => {
+                                let _ = Self::handle_connect(&mut swarm, address, response_tx).await;
                             }
-                        }
-                    }
-                } else {
-                    // Fall back to direct handler calling if no message processor
-                    let start_time = Instant::now();
-                    let mut handled_successfully = false;
-                    
-                    match serde_json::from_slice::<NetworkMessage>(&message.data) {
-                        Ok(network_message) => {
-                            // Get peer info
-                            let peer_info = Self::get_peer_info_from_id(peers, &propagation_source).await;
-                            
-                            // Call all handlers for this message type
-                            let handlers_guard = handlers.read().await;
-                            if let Some(type_handlers) = handlers_guard.get(message_type) {
-                                let mut success = true;
-                                
-                                for handler in type_handlers {
-                                    if let Err(e) = handler.handle_message(&network_message, &peer_info).await {
-                                        error!("Handler error: {}", e);
-                                        success = false;
-                                        
-                                        // Update reputation for message failure
-                                        if let Some(rep) = reputation {
-                                            let _ = rep.record_change(propagation_source, ReputationChange::MessageFailure).await;
-                                        }
-                                    }
-                                }
-                                
-                                // Update reputation for successful message
-                                if success {
-                                    handled_successfully = true;
-                                    if let Some(rep) = reputation {
-                                        let _ = rep.record_change(propagation_source, ReputationChange::MessageSuccess).await;
-                                    }
+                            Command::ReserveRelay(relay_addr, response_tx) => {
+                                let result = swarm.listen_on(circuit_relay::circuit_listen_address(&relay_addr))
+                                    .map(|_| ())
+                                    .map_err(|e| NetworkError::RelayConnectionError(format!("{}: {}", relay_addr, e)));
+                                let _ = response_tx.send(result).await;
+                            }
+                            Command::Rpc(target, request, response_tx) => {
+                                let request_id = swarm.behaviour_mut().rpc.send_request(&target, request);
+                                pending_rpcs.insert(request_id, response_tx);
+                            }
+                            Command::RegisterHandler(message_type, handler, response_tx) => {
+                                Self::handle_register_handler(&handlers, message_type, handler, response_tx).await;
+                            }
+                            Command::JoinScope(scope, response_tx) => {
+                                let result = Self::subscribe_scope(&mut swarm.behaviour_mut().gossipsub, &scope);
+                                if result.is_ok() {
+                                    info!("Joined topics for {}", scope);
+                                    scopes.write().await.insert(scope);
                                 }
+                                let _ = response_tx.send(result).await;
                             }
-                        }
-                        Err(e) => {
-                            warn!("Failed to deserialize gossip message: {}", e);
-                            
-                            // Update reputation for invalid message
-                            if let Some(rep) = reputation {
-                                let _ = rep.record_change(propagation_source, ReputationChange::InvalidMessage).await;
+                            Command::LeaveScope(scope, response_tx) => {
+                                // Stop dispatching before unsubscribing so in-flight messages are dropped
+                                scopes.write().await.remove(&scope);
+                                for topic in scope.topic_names() {
+                                    swarm.behaviour_mut().gossipsub.unsubscribe(&IdentTopic::new(topic));
+                                }
+                                info!("Left topics for {}", scope);
+                                let _ = response_tx.send(Ok(())).await;
                             }
+                            // Handle other commands...
+                            _ => { /* Handle other commands */ }
                         }
                     }
                     
-                    // Record message processing time
-                    let elapsed = start_time.elapsed();
-                    if let Some(m) = metrics {
-                        m.record_message_processing_time(elapsed);
-                    }
-                    
-                    // Record response time
-                    if let Some(rep) = reputation {
-                        let _ = rep.record_response_time(&propagation_source, elapsed).await;
+                    // Exit if running_rx is closed or changed to false
+                    Ok(running) = running_rx.changed() => {
+                        if !*running_rx.borrow() {
+                            info!("P2P service is shutting down");
+                            break;
+                        }
                     }
                     
-                    // Update reputation based on processing time
-                    if handled_successfully {
-                        if let Some(reputation) = &self.reputation {
-                            let _ = reputation.record_change(propagation_source, ReputationChange::VerifiedMessage).await;
-                        }
+                    else => {
+                        // Channel closed, exit loop
+                        error!("All channels closed unexpectedly");
+                        break;
                     }
                 }
             }
-            SwarmEvent::Behaviour(P2pBehaviourEvent::Ping(ping::Event {
-                peer,
-                result: Ok(rtt),
-                ..
-            })) => {
-                // Record ping/latency metrics
-                if let Some(m) = metrics {
-                    m.record_peer_latency(&peer.to_string(), rtt).await;
+            
+            info!("P2P network task stopped");
+        });
+        
+        // Store the task handle
+        let mut handle = self.task_handle.lock().await;
+        *handle = Some(task);
+        
+        Ok(())
+    }
+    
+    /// Handle events from the full-object fetch protocol
+    async fn handle_fetch_event(
+        event: request_response::Event<FetchRequest, FetchResponse>,
+        swarm: &mut swarm::Swarm<P2pBehaviour>,
+        storage: &dyn Storage,
+        pending_fetches: &mut PendingFetches,
+    ) {
+        match event {
+            request_response::Event::Message { peer, message, .. } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    debug!("Serving fetch of {} for {}", request.storage_key(), peer);
+                    let response = fetch::serve_fetch_request(storage, request).await;
+                    if swarm.behaviour_mut().fetch.send_response(channel, response).is_err() {
+                        warn!("Failed to send fetch response to {}", peer);
+                    }
                 }
-                
-                // Update reputation based on ping time
-                if let Some(rep) = reputation {
-                    let _ = rep.record_response_time(&peer, rtt).await;
+                request_response::Message::Response { request_id, response } => {
+                    if let Some(response_tx) = pending_fetches.remove(&request_id) {
+                        let _ = response_tx.send(Ok(response));
+                    }
+                }
+            },
+            request_response::Event::OutboundFailure { peer, request_id, error, .. } => {
+                warn!("Fetch request to {} failed: {}", peer, error);
+                if let Some(response_tx) = pending_fetches.remove(&request_id) {
+                    let _ = response_tx.send(Err(NetworkError::ConnectionError(
+                        format!("Fetch request to {} failed: {}", peer, error)
+                    )));
                 }
-                
-                debug!("Ping to {} took {:?}", peer, rtt);
             }
-            SwarmEvent::Behaviour(P2pBehaviourEvent::Mdns(mdns::Event::Discovered(list))) => {
-                for (peer_id, addr) in list {
-                    debug!("mDNS discovered peer: {} at {}", peer_id, addr);
-                    
-                    // Record mDNS discovery
-                    if let Some(m) = metrics {
-                        m.record_mdns_discovery();
-                        m.record_peer_discovered();
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                warn!("Failed to answer fetch request from {}: {}", peer, error);
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+    
+    /// Handle events from the Merkle-range reconciliation protocol
+    async fn handle_reconcile_event(
+        event: request_response::Event<ReconcileRequest, ReconcileResponse>,
+        swarm: &mut swarm::Swarm<P2pBehaviour>,
+        storage: &dyn Storage,
+        pending_reconciles: &mut PendingReconciles,
+    ) {
+        match event {
+            request_response::Event::Message { peer, message, .. } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let response = reconcile::serve_reconcile_request(storage, request).await;
+                    if swarm.behaviour_mut().reconcile.send_response(channel, response).is_err() {
+                        warn!("Failed to send reconciliation response to {}", peer);
                     }
-                    
-                    // Update reputation for discovery help
-                    if let Some(rep) = reputation {
-                        let _ = rep.record_change(peer_id, ReputationChange::DiscoveryHelp).await;
+                }
+                request_response::Message::Response { request_id, response } => {
+                    if let Some(response_tx) = pending_reconciles.remove(&request_id) {
+                        let _ = response_tx.send(Ok(response));
                     }
-                    
-                    // Update peer info
-                    Self::update_peer_connection(peers, &peer_id, false, Some(addr.clone())).await;
-                    
-                    // Check if peer is banned
-                    let should_dial = if let Some(rep) = reputation {
-                        !rep.is_banned(&peer_id)
-                    } else {
-                        true
+                }
+            },
+            request_response::Event::OutboundFailure { peer, request_id, error, .. } => {
+                warn!("Reconciliation request to {} failed: {}", peer, error);
+                if let Some(response_tx) = pending_reconciles.remove(&request_id) {
+                    let _ = response_tx.send(Err(NetworkError::ConnectionError(
+                        format!("Reconciliation request to {} failed: {}", peer, error)
+                    )));
+                }
+            }
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                warn!("Failed to answer reconciliation request from {}: {}", peer, error);
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+    
+    /// Handle events from the RPC protocol
+    ///
+    /// Incoming requests are served on their own task so slow handlers do
+    /// not stall the event loop; responses come back through `served_rpcs`.
+    /// Requests beyond the registry's in-flight limits are refused as busy
+    /// without spawning a task.
+    fn handle_rpc_event(
+        event: request_response::Event<RpcRequest, RpcResponse>,
+        rpc_registry: &RpcRegistry,
+        served_rpcs: &ServedRpcs,
+        pending_rpcs: &mut PendingRpcs,
+    ) {
+        match event {
+            request_response::Event::Message { peer, message, .. } => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let Some(permit) = rpc_registry.try_admit(&peer.to_string()) else {
+                        warn!("Refusing RPC {} #{} from {}: too many requests in flight", request.method, request.id, peer);
+                        // If the queue is full too the request is dropped and fails on the caller's side
+                        let _ = served_rpcs.try_send((channel, rpc_registry.busy(&request)));
+                        return;
                     };
-                    
-                    // Try to dial the peer if not banned and not connected
-                    if should_dial && !swarm.is_connected(&peer_id) {
-                        debug!("Dialing discovered peer {}", peer_id);
-                        match swarm.dial(addr) {
-                            Ok(_) => {}
-                            Err(e) => warn!("Failed to dial discovered peer {}: {}", peer_id, e),
-                        }
+                    debug!("Serving RPC {} #{} for {}", request.method, request.id, peer);
+                    let rpc_registry = rpc_registry.clone();
+                    let served_rpcs = served_rpcs.clone();
+                    tokio::spawn(async move {
+                        let response = rpc_registry.serve_admitted(permit, request).await;
+                        let _ = served_rpcs.send((channel, response)).await;
+                    });
+                }
+                request_response::Message::Response { request_id, response } => {
+                    // The caller may have given up already
+                    if let Some(response_tx) = pending_rpcs.remove(&request_id) {
+                        let _ = response_tx.send(Ok(response));
                     }
                 }
-            }
-            SwarmEvent::Behaviour(P2pBehaviourEvent::Kademlia(kad::Event::RoutingUpdated {
-                peer,
-                addresses,
-                ..
-            })) => {
-                debug!("Kademlia routing updated for peer: {}", peer);
-                
-                // Record Kademlia discovery
-                if let Some(m) = metrics {
-                    m.record_kad_discovery();
+            },
+            request_response::Event::OutboundFailure { peer, request_id, error, .. } => {
+                warn!("RPC request to {} failed: {}", peer, error);
+                if let Some(response_tx) = pending_rpcs.remove(&request_id) {
+                    let _ = response_tx.send(Err(NetworkError::ConnectionError(
+                        format!("RPC request to {} failed: {}", peer, error)
+                    )));
                 }
-                
-                // Convert addresses to Vec<Multiaddr>
-                let addrs: Vec<Multiaddr> = addresses.iter().cloned().collect();
-                
-                // Update peer info with the new addresses
-                let peers = Arc::clone(&self.peers);
-                Self::update_peer_info(&peers, &peer, &addrs, &[]).await;
             }
-            _ => {} // Ignore other events
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                warn!("Failed to answer RPC request from {}: {}", peer, error);
+            }
+            request_response::Event::ResponseSent { .. } => {}
         }
     }
     
@@ -917,6 +1528,7 @@ impl P2pNetwork {
                 connected: true,
                 last_seen: Some(now),
                 reputation: None,
+                sender: None,
             }
         });
         
@@ -956,6 +1568,7 @@ impl P2pNetwork {
                 connected: false,
                 last_seen: Some(now),
                 reputation: None,
+                sender: None,
             };
             
             peers_lock.insert(*peer_id, peer_info);
@@ -1004,17 +1617,162 @@ impl P2pNetwork {
                 connected: true,
                 last_seen: Some(now),
                 reputation: None,
+                sender: None,
             }
         }
     }
     
+    /// Subscribe to all topics of a scope
+    fn subscribe_scope(gossipsub: &mut gossipsub::Behaviour, scope: &TopicScope) -> NetworkResult<()> {
+        for topic in scope.topic_names() {
+            gossipsub.subscribe(&IdentTopic::new(topic))
+                .map_err(|e| NetworkError::Libp2pError(e.to_string()))?;
+        }
+        Ok(())
+    }
+    
+    /// Charge an outgoing envelope against the rate limits
+    ///
+    /// Direct messages are charged to the recipient and the topic,
+    /// broadcasts to the topic only.
+    async fn check_outbound_rate(
+        &self,
+        peer_id: Option<&PeerId>,
+        scope: &TopicScope,
+        envelope: &SignedEnvelope,
+    ) -> NetworkResult<()> {
+        let Some(limiter) = &self.rate_limiter else {
+            return Ok(());
+        };
+        
+        let size = codec::encode(envelope, codec::CURRENT_WIRE_VERSION, self.config.wire_format)?.len();
+        let peer_id = peer_id.map(|p| p.to_string());
+        limiter.check(
+            peer_id.as_deref(),
+            Some(&scope.topic_name(TopicKind::for_message(&envelope.message))),
+            Direction::Outbound,
+            size,
+        ).await
+    }
+    
+    /// Get the traffic exchanged with a peer
+    ///
+    /// Returns `None` if rate limiting is disabled or nothing was exchanged.
+    pub async fn peer_traffic(&self, peer_id: &PeerId) -> Option<PeerTraffic> {
+        self.rate_limiter.as_ref()?.peer_traffic(&peer_id.to_string()).await
+    }
+    
+    /// Join a federation, subscribing to its topics
+    pub async fn join_federation(&self, federation_id: &str) -> NetworkResult<()> {
+        self.join_scope(TopicScope::federation(federation_id)?).await
+    }
+    
+    /// Leave a federation, unsubscribing from its topics and those of its shards
+    pub async fn leave_federation(&self, federation_id: &str) -> NetworkResult<()> {
+        let scope = TopicScope::federation(federation_id)?;
+        let shards: Vec<TopicScope> = self.scopes.read().await.iter()
+            .filter(|s| matches!(s, TopicScope::Shard { .. }) && s.federation_id() == Some(federation_id))
+            .cloned()
+            .collect();
+        
+        for shard in shards {
+            self.leave_scope(shard).await?;
+        }
+        self.leave_scope(scope).await
+    }
+    
+    /// Join a shard of a federation, subscribing to its topics
+    pub async fn join_shard(&self, federation_id: &str, shard_id: u32) -> NetworkResult<()> {
+        self.join_scope(TopicScope::shard(federation_id, shard_id)?).await
+    }
+    
+    /// Leave a shard of a federation, unsubscribing from its topics
+    pub async fn leave_shard(&self, federation_id: &str, shard_id: u32) -> NetworkResult<()> {
+        self.leave_scope(TopicScope::shard(federation_id, shard_id)?).await
+    }
+    
+    /// Get the topic scopes the node has joined
+    pub async fn joined_scopes(&self) -> Vec<TopicScope> {
+        self.scopes.read().await.iter().cloned().collect()
+    }
+    
+    /// Broadcast a message to the peers subscribed to a scope
+    ///
+    /// The node does not need to have joined the scope to publish to it.
+    /// Transactions the node announces were committed locally, so they are
+    /// added to the reconciliation index for peers to sync.
+    pub async fn broadcast_to(&self, scope: TopicScope, message: NetworkMessage) -> NetworkResult<()> {
+        if let NetworkMessage::TransactionAnnouncement(announcement) = &message {
+            reconcile::record_transaction(self.storage.as_ref(), IndexEntry::from(announcement)).await?;
+        }
+        
+        let envelope = self.sign_message(message).await?;
+        self.check_outbound_rate(None, &scope, &envelope).await?;
+        self.command_tx.lock().await.send(Command::Broadcast(scope, envelope)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send broadcast command: {}", e)))?;
+        Ok(())
+    }
+    
+    /// Register a handler for a message type received on a scope's topics
+    ///
+    /// Handlers registered with `register_message_handler` receive messages
+    /// from every joined scope; these only receive messages from `scope`.
+    pub async fn register_topic_handler(
+        &self,
+        scope: TopicScope,
+        message_type: &str,
+        handler: Arc<dyn MessageHandler>,
+    ) -> NetworkResult<()> {
+        debug!("Registered handler for message type {} on {}", message_type, scope);
+        self.scoped_handlers.write().await
+            .entry((scope, message_type.to_string()))
+            .or_default()
+            .push(handler);
+        Ok(())
+    }
+    
+    /// Subscribe to the topics of a scope
+    async fn join_scope(&self, scope: TopicScope) -> NetworkResult<()> {
+        let (tx, mut rx) = mpsc::channel(1);
+        self.command_tx.lock().await.send(Command::JoinScope(scope, tx)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send join command: {}", e)))?;
+        
+        rx.recv().await
+            .unwrap_or_else(|| Err(NetworkError::ServiceError("Failed to receive join response".to_string())))
+    }
+    
+    /// Unsubscribe from the topics of a scope
+    async fn leave_scope(&self, scope: TopicScope) -> NetworkResult<()> {
+        let (tx, mut rx) = mpsc::channel(1);
+        self.command_tx.lock().await.send(Command::LeaveScope(scope, tx)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send leave command: {}", e)))?;
+        
+        rx.recv().await
+            .unwrap_or_else(|| Err(NetworkError::ServiceError("Failed to receive leave response".to_string())))
+    }
+    
     /// Handle broadcast command
+    ///
+    /// The envelope is encoded with the highest wire version supported by
+    /// all connected peers.
     async fn handle_broadcast(
         swarm: &mut swarm::Swarm<P2pBehaviour>,
-        message: NetworkMessage,
+        scope: &TopicScope,
+        envelope: SignedEnvelope,
+        peers: &Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
+        wire_format: WireFormat,
     ) {
-        // Serialize the message
-        let data = match serde_json::to_vec(&message) {
+        let version = {
+            let peers_guard = peers.read().await;
+            codec::negotiate_version(
+                peers_guard.values()
+                    .filter(|info| info.connected)
+                    .map(|info| info.protocol_version.as_deref())
+            )
+        };
+        
+        // Serialize the envelope
+        let data = match codec::encode(&envelope, version, wire_format) {
             Ok(data) => data,
             Err(e) => {
                 error!("Failed to serialize broadcast message: {}", e);
@@ -1022,17 +1780,7 @@ impl P2pNetwork {
             }
         };
         
-        // Choose the appropriate topic based on message type
-        let message_type = match &message {
-            NetworkMessage::IdentityAnnouncement(_) => "identity",
-            NetworkMessage::TransactionAnnouncement(_) => "transaction",
-            NetworkMessage::LedgerStateUpdate(_) => "ledger",
-            NetworkMessage::ProposalAnnouncement(_) => "proposal",
-            NetworkMessage::VoteAnnouncement(_) => "vote",
-            NetworkMessage::Custom(m) => &m.message_type,
-        };
-        
-        let topic = IdentTopic::new(message_type);
+        let topic = IdentTopic::new(scope.topic_name(TopicKind::for_message(&envelope.message)));
         
         // Publish the message
         if let Err(e) = swarm.behaviour_mut().gossipsub.publish(topic, data) {
@@ -1044,11 +1792,13 @@ impl P2pNetwork {
     async fn handle_send_to(
         swarm: &mut swarm::Swarm<P2pBehaviour>,
         peer_id: &PeerId,
-        message: NetworkMessage,
+        envelope: SignedEnvelope,
+        peers: &Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
+        wire_format: WireFormat,
     ) {
         // For direct messages, we use gossipsub for now
         // In a more complete implementation, we would use request-response or a direct messaging protocol
-        Self::handle_broadcast(swarm, message).await;
+        Self::handle_broadcast(swarm, &TopicScope::Global, envelope, peers, wire_format).await;
     }
     
     /// Handle connect command
@@ -1064,14 +1814,15 @@ impl P2pNetwork {
                 
                 // In a real implementation, we would wait for the connection to be established
                 // and then return the peer ID. For now, we'll just return success with a placeholder.
-                // We'll assume this is using multiaddr with a peer ID component for now
-                if let Some(peer_id) = addr.iter().find_map(|p| {
+                // We'll assume this is using multiaddr with a peer ID component for now;
+                // for relayed addresses the target is the peer ID after the relay's
+                if let Some(peer_id) = addr.iter().filter_map(|p| {
                     if let libp2p::multiaddr::Protocol::P2p(hash) = p {
                         Some(PeerId::from_multihash(hash.into()).ok()?)
                     } else {
                         None
                     }
-                }) {
+                }).last() {
                     let result = Ok(peer_id.clone());
                     let _ = response_tx.send(result.clone()).await;
                     result
@@ -1197,6 +1948,27 @@ impl P2pNetwork {
         Ok(())
     }
     
+    /// Get the connection manager
+    pub fn connection_manager(&self) -> Option<&ConnectionManager> {
+        self.connection_manager.as_ref()
+    }
+    
+    /// Protect a peer from being disconnected by the connection manager
+    ///
+    /// Used for federation members, which only higher layers know about.
+    pub async fn protect_peer(&self, peer_id: PeerId, reason: ProtectionReason) {
+        if let Some(manager) = &self.connection_manager {
+            manager.protect(peer_id, reason).await;
+        }
+    }
+    
+    /// Remove one reason a peer is protected from the connection manager
+    pub async fn unprotect_peer(&self, peer_id: &PeerId, reason: ProtectionReason) {
+        if let Some(manager) = &self.connection_manager {
+            manager.unprotect(peer_id, reason).await;
+        }
+    }
+    
     /// Get message queue statistics
     pub async fn get_message_queue_stats(&self) -> NetworkResult<(usize, Option<i32>, Option<i32>)> {
         if let Some(processor) = &self.message_processor {
@@ -1280,10 +2052,24 @@ impl P2pNetwork {
     }
     
     /// Add a relay server
+    ///
+    /// With the relay client enabled, a slot is reserved on the relay so
+    /// peers can reach this node through it.
     pub async fn add_relay_server(&self, addr: &Multiaddr) -> NetworkResult<()> {
         if let Some(relay_manager) = &self.circuit_relay {
             if let Some(peer_id) = extract_peer_id(addr) {
                 relay_manager.add_relay_server(peer_id, vec![addr.clone()]).await?;
+                self.protect_peer(peer_id, ProtectionReason::Relay).await;
+                
+                let client_enabled = self.config.circuit_relay_config.as_ref()
+                    .is_none_or(|c| c.enable_relay_client);
+                if client_enabled {
+                    let (tx, mut rx) = mpsc::channel(1);
+                    self.command_tx.lock().await.send(Command::ReserveRelay(addr.clone(), tx)).await
+                        .map_err(|_| NetworkError::ChannelClosed("Failed to send reserve command".into()))?;
+                    rx.recv().await
+                        .unwrap_or_else(|| Err(NetworkError::ChannelClosed("Reserve response channel closed".into())))?;
+                }
                 Ok(())
             } else {
                 Err(NetworkError::InvalidRelayAddress)
@@ -1312,151 +2098,6 @@ fn extract_peer_id(addr: &Multiaddr) -> Option<PeerId> {
     })
 }
 
-// Implement NetworkBehaviour for P2pBehaviour
-impl NetworkBehaviour for P2pBehaviour {
-    type ConnectionHandler = libp2p::swarm::dummy::ConnectionHandler;
-    type ToSwarm = P2pBehaviourEvent;
-
-    fn handle_established_inbound_connection(
-        &mut self,
-        connection_id: libp2p::swarm::ConnectionId,
-        peer: PeerId,
-        local_addr: &Multiaddr,
-        remote_addr: &Multiaddr,
-    ) -> Result<Self::ConnectionHandler, libp2p::swarm::ConnectionDenied> {
-        self.identify.handle_established_inbound_connection(
-            connection_id, peer, local_addr, remote_addr,
-        )?;
-        self.ping.handle_established_inbound_connection(
-            connection_id, peer, local_addr, remote_addr,
-        )?;
-        self.gossipsub.handle_established_inbound_connection(
-            connection_id, peer, local_addr, remote_addr,
-        )?;
-        self.kademlia.handle_established_inbound_connection(
-            connection_id, peer, local_addr, remote_addr,
-        )?;
-        self.mdns.handle_established_inbound_connection(
-            connection_id, peer, local_addr, remote_addr,
-        )?;
-
-        Ok(libp2p::swarm::dummy::ConnectionHandler)
-    }
-
-    fn handle_established_outbound_connection(
-        &mut self,
-        connection_id: ConnectionId,
-        peer: PeerId,
-        addr: &Multiaddr,
-        role_override: Endpoint,
-        port_use: PortUse,
-    ) -> Result<ConnectionHandler, ConnectionDenied> {
-        // Forward to all sub-components
-        self.identify.handle_established_outbound_connection(
-            connection_id,
-            peer,
-            addr,
-            role_override,
-            port_use,
-        )?;
-        self.ping.handle_established_outbound_connection(
-            connection_id,
-            peer,
-            addr,
-            role_override,
-            port_use,
-        )?;
-        self.gossipsub.handle_established_outbound_connection(
-            connection_id,
-            peer,
-            addr,
-            role_override,
-            port_use,
-        )?;
-        self.kademlia.handle_established_outbound_connection(
-            connection_id,
-            peer,
-            addr,
-            role_override,
-            port_use,
-        )?;
-        
-        // Get the handler from the last component's implementation and return it
-        self.mdns.handle_established_outbound_connection(
-            connection_id,
-            peer,
-            addr,
-            role_override,
-            port_use,
-        )
-    }
-
-    fn on_swarm_event(&mut self, event: libp2p::swarm::FromSwarm) {
-        self.identify.on_swarm_event(event.clone());
-        self.ping.on_swarm_event(event.clone());
-        self.gossipsub.on_swarm_event(event.clone());
-        self.kademlia.on_swarm_event(event.clone());
-        self.mdns.on_swarm_event(event);
-    }
-
-    fn on_connection_handler_event(
-        &mut self,
-        peer_id: PeerId,
-        connection_id: libp2p::swarm::ConnectionId,
-        event: std::convert::Infallible,
-    ) {
-        // This method should never be called since we're using Infallible as event type
-        // Infallible cannot be constructed, so this is unreachable
-        let _: std::convert::Infallible = event; // Using the event to silence unused variable warning
-        
-        // Type annotation to help compiler with type inference
-        let _: &mut P2pBehaviour = self;
-    }
-
-    fn poll(
-        &mut self,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<libp2p::swarm::ToSwarm<Self::ToSwarm, libp2p::swarm::THandlerInEvent<Self>>> {
-        // Poll each protocol and convert the events to our custom event type
-        if let Poll::Ready(event) = self.gossipsub.poll(cx) {
-            if let libp2p::swarm::ToSwarm::GenerateEvent(ev) = event {
-                return Poll::Ready(libp2p::swarm::ToSwarm::GenerateEvent(P2pBehaviourEvent::Gossipsub(ev)));
-            }
-            return Poll::Pending;
-        }
-        
-        if let Poll::Ready(event) = self.identify.poll(cx) {
-            if let libp2p::swarm::ToSwarm::GenerateEvent(ev) = event {
-                return Poll::Ready(libp2p::swarm::ToSwarm::GenerateEvent(P2pBehaviourEvent::Identify(ev)));
-            }
-            return Poll::Pending;
-        }
-        
-        if let Poll::Ready(event) = self.ping.poll(cx) {
-            if let libp2p::swarm::ToSwarm::GenerateEvent(ev) = event {
-                return Poll::Ready(libp2p::swarm::ToSwarm::GenerateEvent(P2pBehaviourEvent::Ping(ev)));
-            }
-            return Poll::Pending;
-        }
-        
-        if let Poll::Ready(event) = self.kademlia.poll(cx) {
-            if let libp2p::swarm::ToSwarm::GenerateEvent(ev) = event {
-                return Poll::Ready(libp2p::swarm::ToSwarm::GenerateEvent(P2pBehaviourEvent::Kad(ev)));
-            }
-            return Poll::Pending;
-        }
-        
-        if let Poll::Ready(event) = self.mdns.poll(cx) {
-            if let libp2p::swarm::ToSwarm::GenerateEvent(ev) = event {
-                return Poll::Ready(libp2p::swarm::ToSwarm::GenerateEvent(P2pBehaviourEvent::Mdns(ev)));
-            }
-            return Poll::Pending;
-        }
-
-        Poll::Pending
-    }
-}
-
 #[async_trait]
 impl NetworkService for P2pNetwork {
     async fn start(&self) -> NetworkResult<()> {
@@ -1480,9 +2121,7 @@ impl NetworkService for P2pNetwork {
     }
     
     async fn broadcast(&self, message: NetworkMessage) -> NetworkResult<()> {
-        self.command_tx.lock().await.send(Command::Broadcast(message)).await
-            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send broadcast command: {}", e)))?;
-        Ok(())
+        self.broadcast_to(TopicScope::Global, message).await
     }
     
     async fn send_to(&self, peer_id: &str, message: NetworkMessage) -> NetworkResult<()> {
@@ -1490,8 +2129,11 @@ impl NetworkService for P2pNetwork {
         let peer_id = PeerId::from_str(peer_id)
             .map_err(|_| NetworkError::InvalidPeerId(peer_id.to_string()))?;
             
+        let envelope = self.sign_message(message).await?;
+        self.check_outbound_rate(Some(&peer_id), &TopicScope::Global, &envelope).await?;
+        
         let (tx, mut rx) = mpsc::channel::<NetworkResult<()>>(1);
-        self.command_tx.lock().await.send(Command::SendTo(peer_id, message)).await
+        self.command_tx.lock().await.send(Command::SendTo(peer_id, envelope)).await
             .map_err(|_| NetworkError::ChannelClosed("Failed to send message command".into()))?;
         
         Ok(())
@@ -1575,4 +2217,79 @@ impl NetworkService for P2pNetwork {
         
         Ok(rx)
     }
-} 
\ No newline at end of file
+}
+
+#[async_trait]
+impl ObjectFetcher for P2pNetwork {
+    async fn fetch_object(&self, peer_id: &str, request: FetchRequest) -> NetworkResult<FetchResponse> {
+        // Convert string to PeerId
+        let peer_id = PeerId::from_str(peer_id)
+            .map_err(|_| NetworkError::InvalidPeerId(peer_id.to_string()))?;
+        
+        let (tx, rx) = oneshot::channel();
+        self.command_tx.lock().await.send(Command::Fetch(peer_id, request, tx)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send fetch command: {}", e)))?;
+        
+        rx.await
+            .unwrap_or_else(|_| Err(NetworkError::ChannelClosed("Fetch response channel closed".to_string())))
+    }
+}
+
+#[async_trait]
+impl ReconcileClient for P2pNetwork {
+    async fn reconcile_request(&self, peer_id: &str, request: ReconcileRequest) -> NetworkResult<ReconcileResponse> {
+        // Convert string to PeerId
+        let peer_id = PeerId::from_str(peer_id)
+            .map_err(|_| NetworkError::InvalidPeerId(peer_id.to_string()))?;
+        
+        let (tx, rx) = oneshot::channel();
+        self.command_tx.lock().await.send(Command::Reconcile(peer_id, request, tx)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send reconcile command: {}", e)))?;
+        
+        rx.await
+            .unwrap_or_else(|_| Err(NetworkError::ChannelClosed("Reconcile response channel closed".to_string())))
+    }
+}
+
+#[async_trait]
+impl RpcTransport for P2pNetwork {
+    async fn send_rpc(&self, peer_id: &str, request: RpcRequest) -> NetworkResult<RpcResponse> {
+        // Convert string to PeerId
+        let peer_id = PeerId::from_str(peer_id)
+            .map_err(|_| NetworkError::InvalidPeerId(peer_id.to_string()))?;
+        
+        let (tx, rx) = oneshot::channel();
+        self.command_tx.lock().await.send(Command::Rpc(peer_id, request, tx)).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Failed to send RPC command: {}", e)))?;
+        
+        rx.await
+            .unwrap_or_else(|_| Err(NetworkError::ChannelClosed("RPC response channel closed".to_string())))
+    }
+}
+
+#[async_trait]
+impl RpcService for P2pNetwork {
+    async fn request(&self, peer_id: &str, method: &str, payload: Vec<u8>, qos: messaging::QosLevel) -> NetworkResult<Vec<u8>> {
+        self.rpc_client.request(self, peer_id, method, payload, qos).await
+    }
+    
+    async fn register_rpc_handler(&self, method: &str, handler: Arc<dyn RpcHandler>) -> NetworkResult<()> {
+        self.rpc_registry.register(method, handler).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ShardTransport for P2pNetwork {
+    async fn join_shard(&self, federation_id: &str, shard_id: u32) -> NetworkResult<()> {
+        P2pNetwork::join_shard(self, federation_id, shard_id).await
+    }
+
+    async fn leave_shard(&self, federation_id: &str, shard_id: u32) -> NetworkResult<()> {
+        P2pNetwork::leave_shard(self, federation_id, shard_id).await
+    }
+
+    async fn broadcast_to(&self, scope: TopicScope, message: NetworkMessage) -> NetworkResult<()> {
+        P2pNetwork::broadcast_to(self, scope, message).await
+    }
+}
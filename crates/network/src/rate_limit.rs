@@ -0,0 +1,387 @@
+//! Bandwidth accounting and rate limiting for ICN
+//!
+//! Cooperative nodes often run on metered links. Traffic is accounted per
+//! peer, and token buckets limit the number of messages and bytes per second
+//! exchanged with each peer and on each gossipsub topic, separately for each
+//! direction. Inbound gossip over a limit is dropped before it is forwarded;
+//! outbound messages over a limit fail with `NetworkError::RateLimitExceeded`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::RwLock;
+use tracing::debug;
+
+use crate::metrics::NetworkMetrics;
+use crate::{NetworkError, NetworkResult};
+
+/// Direction of traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Traffic received from a peer
+    Inbound,
+    /// Traffic sent to a peer
+    Outbound,
+}
+
+impl Direction {
+    /// Get the name used in logs and metrics
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Inbound => "inbound",
+            Direction::Outbound => "outbound",
+        }
+    }
+}
+
+/// A message and bandwidth limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Sustained messages per second
+    pub messages_per_second: f64,
+    /// Sustained bytes per second
+    pub bytes_per_second: f64,
+    /// How much unused allowance can accumulate for bursts
+    pub burst: Duration,
+}
+
+impl RateLimit {
+    /// Create a limit allowing bursts of one second of traffic
+    pub fn new(messages_per_second: f64, bytes_per_second: f64) -> Self {
+        Self {
+            messages_per_second,
+            bytes_per_second,
+            burst: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the burst allowance
+    pub fn with_burst(mut self, burst: Duration) -> Self {
+        self.burst = burst;
+        self
+    }
+}
+
+/// Configuration for rate limiting
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Limit applied to each peer, per direction
+    pub per_peer: Option<RateLimit>,
+    /// Limits for specific topics, per direction
+    pub per_topic: HashMap<String, RateLimit>,
+    /// Limit for topics without a specific limit
+    pub default_topic: Option<RateLimit>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_peer: Some(RateLimit::new(100.0, 1024.0 * 1024.0)),
+            per_topic: HashMap::new(),
+            default_topic: None,
+        }
+    }
+}
+
+/// Token bucket refilled continuously at a fixed rate
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    /// Maximum number of tokens
+    capacity: f64,
+    /// Available tokens
+    tokens: f64,
+    /// Tokens added per second
+    rate: f64,
+    /// Last time tokens were added
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    fn new(rate: f64, burst: Duration, now: Instant) -> Self {
+        let capacity = (rate * burst.as_secs_f64()).max(1.0);
+        Self {
+            capacity,
+            tokens: capacity,
+            rate,
+            last_refill: now,
+        }
+    }
+
+    /// Add the tokens accumulated since the last refill
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Check whether an amount can be taken
+    ///
+    /// Amounts larger than the capacity pass when the bucket is full, so
+    /// oversized messages are slowed down rather than blocked forever.
+    fn can_take(&self, amount: f64) -> bool {
+        self.tokens >= amount.min(self.capacity)
+    }
+
+    /// Take an amount, possibly leaving the bucket in debt
+    fn take(&mut self, amount: f64) {
+        self.tokens -= amount;
+    }
+}
+
+/// Message and byte buckets enforcing one `RateLimit`
+#[derive(Debug, Clone)]
+struct LimitBuckets {
+    messages: TokenBucket,
+    bytes: TokenBucket,
+}
+
+impl LimitBuckets {
+    /// Create full buckets for a limit
+    fn new(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            messages: TokenBucket::new(limit.messages_per_second, limit.burst, now),
+            bytes: TokenBucket::new(limit.bytes_per_second, limit.burst, now),
+        }
+    }
+
+    /// Refill and check whether a message of the given size fits
+    fn allows(&mut self, bytes: usize, now: Instant) -> bool {
+        self.messages.refill(now);
+        self.bytes.refill(now);
+        self.messages.can_take(1.0) && self.bytes.can_take(bytes as f64)
+    }
+
+    /// Charge a message of the given size
+    fn charge(&mut self, bytes: usize) {
+        self.messages.take(1.0);
+        self.bytes.take(bytes as f64);
+    }
+}
+
+/// Traffic exchanged with a peer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerTraffic {
+    /// Bytes received
+    pub bytes_in: u64,
+    /// Bytes sent
+    pub bytes_out: u64,
+    /// Messages received
+    pub messages_in: u64,
+    /// Messages sent
+    pub messages_out: u64,
+    /// Messages dropped or refused for exceeding a limit
+    pub rate_limited: u64,
+}
+
+/// Per-peer and per-topic rate limiter
+#[derive(Clone)]
+pub struct RateLimiter {
+    /// Configuration
+    config: RateLimitConfig,
+    /// Buckets by peer and direction
+    peer_buckets: Arc<RwLock<HashMap<(String, Direction), LimitBuckets>>>,
+    /// Buckets by topic and direction
+    topic_buckets: Arc<RwLock<HashMap<(String, Direction), LimitBuckets>>>,
+    /// Traffic by peer
+    traffic: Arc<RwLock<HashMap<String, PeerTraffic>>>,
+    /// Network metrics
+    metrics: Option<NetworkMetrics>,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter
+    pub fn new(config: RateLimitConfig, metrics: Option<NetworkMetrics>) -> Self {
+        Self {
+            config,
+            peer_buckets: Arc::new(RwLock::new(HashMap::new())),
+            topic_buckets: Arc::new(RwLock::new(HashMap::new())),
+            traffic: Arc::new(RwLock::new(HashMap::new())),
+            metrics,
+        }
+    }
+
+    /// Get the limit applying to a topic
+    fn topic_limit(&self, topic: &str) -> Option<&RateLimit> {
+        self.config.per_topic.get(topic).or(self.config.default_topic.as_ref())
+    }
+
+    /// Check a message against the limits and charge it if allowed
+    ///
+    /// `peer_id` is the sender of inbound messages or the recipient of
+    /// outbound ones; broadcasts have no single peer and are only charged to
+    /// their topic.
+    pub async fn check(
+        &self,
+        peer_id: Option<&str>,
+        topic: Option<&str>,
+        direction: Direction,
+        bytes: usize,
+    ) -> NetworkResult<()> {
+        self.check_at(peer_id, topic, direction, bytes, Instant::now()).await
+    }
+
+    /// Check a message at a given time
+    async fn check_at(
+        &self,
+        peer_id: Option<&str>,
+        topic: Option<&str>,
+        direction: Direction,
+        bytes: usize,
+        now: Instant,
+    ) -> NetworkResult<()> {
+        let mut peer_buckets = self.peer_buckets.write().await;
+        let mut topic_buckets = self.topic_buckets.write().await;
+
+        let mut peer_entry = match (peer_id, &self.config.per_peer) {
+            (Some(peer_id), Some(limit)) => Some(
+                peer_buckets.entry((peer_id.to_string(), direction))
+                    .or_insert_with(|| LimitBuckets::new(limit, now))
+            ),
+            _ => None,
+        };
+        let mut topic_entry = topic
+            .and_then(|topic| self.topic_limit(topic).map(|limit| (topic, limit)))
+            .map(|(topic, limit)| {
+                topic_buckets.entry((topic.to_string(), direction))
+                    .or_insert_with(|| LimitBuckets::new(limit, now))
+            });
+
+        // Both limits must allow the message before either is charged
+        let exceeded = if peer_entry.as_mut().is_some_and(|b| !b.allows(bytes, now)) {
+            Some(("peer", peer_id.unwrap_or_default()))
+        } else if topic_entry.as_mut().is_some_and(|b| !b.allows(bytes, now)) {
+            Some(("topic", topic.unwrap_or_default()))
+        } else {
+            None
+        };
+
+        if exceeded.is_none() {
+            if let Some(buckets) = peer_entry {
+                buckets.charge(bytes);
+            }
+            if let Some(buckets) = topic_entry {
+                buckets.charge(bytes);
+            }
+        }
+
+        drop(topic_buckets);
+        drop(peer_buckets);
+
+        // Received traffic used the link even if it is dropped
+        let counted = exceeded.is_none() || direction == Direction::Inbound;
+        if let Some(peer_id) = peer_id {
+            let mut traffic = self.traffic.write().await;
+            let entry = traffic.entry(peer_id.to_string()).or_default();
+            if counted {
+                match direction {
+                    Direction::Inbound => {
+                        entry.bytes_in += bytes as u64;
+                        entry.messages_in += 1;
+                    }
+                    Direction::Outbound => {
+                        entry.bytes_out += bytes as u64;
+                        entry.messages_out += 1;
+                    }
+                }
+            }
+            if exceeded.is_some() {
+                entry.rate_limited += 1;
+            }
+
+            if let Some(metrics) = &self.metrics {
+                if counted {
+                    metrics.record_peer_bytes(peer_id, direction.as_str(), bytes);
+                }
+            }
+        }
+
+        match exceeded {
+            None => Ok(()),
+            Some((scope, name)) => {
+                debug!("{} {} rate limit exceeded for {}", direction.as_str(), scope, name);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_rate_limited(scope, direction.as_str());
+                }
+                Err(NetworkError::RateLimitExceeded(format!(
+                    "{} {} limit for {}", direction.as_str(), scope, name
+                )))
+            }
+        }
+    }
+
+    /// Get the traffic exchanged with a peer
+    pub async fn peer_traffic(&self, peer_id: &str) -> Option<PeerTraffic> {
+        self.traffic.read().await.get(peer_id).cloned()
+    }
+
+    /// Forget the buckets of a disconnected peer
+    ///
+    /// Traffic counters are kept.
+    pub async fn remove_peer(&self, peer_id: &str) {
+        self.peer_buckets.write().await.retain(|(peer, _), _| peer != peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_peer: Option<RateLimit>, per_topic: &[(&str, RateLimit)]) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            per_peer,
+            per_topic: per_topic.iter().map(|(t, l)| (t.to_string(), *l)).collect(),
+            default_topic: None,
+        }, None)
+    }
+
+    #[tokio::test]
+    async fn test_peer_limits_refill_over_time() {
+        let limiter = limiter(Some(RateLimit::new(2.0, 1000.0)), &[]);
+        let start = Instant::now();
+
+        // Two messages per second
+        limiter.check_at(Some("peer1"), None, Direction::Inbound, 100, start).await.unwrap();
+        limiter.check_at(Some("peer1"), None, Direction::Inbound, 100, start).await.unwrap();
+        assert!(matches!(
+            limiter.check_at(Some("peer1"), None, Direction::Inbound, 100, start).await,
+            Err(NetworkError::RateLimitExceeded(_))
+        ));
+
+        // Other peers and directions have their own buckets
+        limiter.check_at(Some("peer2"), None, Direction::Inbound, 100, start).await.unwrap();
+        limiter.check_at(Some("peer1"), None, Direction::Outbound, 100, start).await.unwrap();
+
+        // Half a second later one more message is allowed
+        let later = start + Duration::from_millis(500);
+        limiter.check_at(Some("peer1"), None, Direction::Inbound, 100, later).await.unwrap();
+
+        // Bytes are limited too
+        let much_later = start + Duration::from_secs(10);
+        limiter.check_at(Some("peer1"), None, Direction::Inbound, 900, much_later).await.unwrap();
+        assert!(limiter.check_at(Some("peer1"), None, Direction::Inbound, 200, much_later).await.is_err());
+
+        let traffic = limiter.peer_traffic("peer1").await.unwrap();
+        // Dropped inbound messages still used the link
+        assert_eq!(traffic.messages_in, 6);
+        assert_eq!(traffic.bytes_in, 1500);
+        assert_eq!(traffic.rate_limited, 2);
+        assert_eq!(traffic.messages_out, 1);
+    }
+
+    #[tokio::test]
+    async fn test_topic_limits_and_oversized_messages() {
+        let limiter = limiter(None, &[("icn/ledger/v1", RateLimit::new(10.0, 1000.0))]);
+        let now = Instant::now();
+
+        // A message larger than the burst passes on a full bucket only
+        limiter.check_at(None, Some("icn/ledger/v1"), Direction::Outbound, 5000, now).await.unwrap();
+        assert!(limiter.check_at(None, Some("icn/ledger/v1"), Direction::Outbound, 10, now).await.is_err());
+
+        // Topics without a limit are not restricted
+        for _ in 0..100 {
+            limiter.check_at(Some("peer1"), Some("icn/identity/v1"), Direction::Outbound, 5000, now).await.unwrap();
+        }
+    }
+}
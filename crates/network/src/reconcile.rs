@@ -0,0 +1,511 @@
+//! Merkle-range reconciliation for ICN ledger sync
+//!
+//! Instead of exchanging complete lists of transaction IDs, peers compare
+//! Merkle roots computed over time ranges of their transaction history and
+//! only descend into ranges whose roots differ. Once a differing range is
+//! small enough, its entries are transferred and diffed directly, so the
+//! cost of a resync is proportional to the difference rather than the
+//! history.
+
+use std::collections::{HashSet, VecDeque};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use icn_core::crypto::merkle::MerkleTree;
+use icn_core::storage::{Storage, StorageError};
+
+use crate::{NetworkError, NetworkResult, TransactionAnnouncement};
+
+/// Protocol name used for range reconciliation
+pub const RECONCILE_PROTOCOL: &str = "/icn/reconcile/1.0.0";
+
+/// Width of the smallest indexed time bucket (in seconds)
+///
+/// Ranges are never split below this width; both peers must agree on it.
+pub const INDEX_BUCKET_SECS: u64 = 60;
+
+/// Maximum number of entries returned in a single items response
+pub const MAX_ITEMS_PER_RESPONSE: usize = 1000;
+
+/// Storage prefix for the transaction index buckets
+const INDEX_PREFIX: &str = "sync/index/transactions/";
+
+/// Serializes updates to the index, which read, modify and write a bucket
+static INDEX_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// A half-open time range `[start, end)` in seconds since the epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TimeRange {
+    /// Start of the range (inclusive)
+    pub start: u64,
+    /// End of the range (exclusive)
+    pub end: u64,
+}
+
+impl TimeRange {
+    /// Create a new time range
+    pub fn new(start: u64, end: u64) -> Self {
+        Self { start, end }
+    }
+
+    /// Create a range covering all history up to the given time
+    pub fn up_to(now: u64) -> Self {
+        let end = (now / INDEX_BUCKET_SECS + 1) * INDEX_BUCKET_SECS;
+        Self { start: 0, end }
+    }
+
+    /// Create a bucket-aligned range covering history from `start` up to the given time
+    pub fn since(start: u64, now: u64) -> Self {
+        let start = start / INDEX_BUCKET_SECS * INDEX_BUCKET_SECS;
+        Self { start, end: Self::up_to(now).end }
+    }
+
+    /// Get the width of the range in seconds
+    pub fn width(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Check whether a timestamp falls in this range
+    pub fn contains(&self, timestamp: u64) -> bool {
+        timestamp >= self.start && timestamp < self.end
+    }
+
+    /// Split the range into at most `fanout` bucket-aligned sub-ranges
+    pub fn split(&self, fanout: usize) -> Vec<TimeRange> {
+        let fanout = fanout.max(2) as u64;
+        let buckets = self.width().div_ceil(INDEX_BUCKET_SECS);
+        let child_width = buckets.div_ceil(fanout).max(1) * INDEX_BUCKET_SECS;
+
+        let mut children = Vec::new();
+        let mut start = self.start;
+        while start < self.end {
+            let end = (start + child_width).min(self.end);
+            children.push(TimeRange::new(start, end));
+            start = end;
+        }
+
+        children
+    }
+}
+
+/// An entry in the transaction index
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// ID of the transaction
+    pub transaction_id: String,
+    /// Timestamp of the transaction
+    pub timestamp: u64,
+    /// Hash of the transaction data
+    pub data_hash: String,
+}
+
+impl From<&TransactionAnnouncement> for IndexEntry {
+    fn from(announcement: &TransactionAnnouncement) -> Self {
+        Self {
+            transaction_id: announcement.transaction_id.clone(),
+            timestamp: announcement.timestamp,
+            data_hash: announcement.data_hash.clone(),
+        }
+    }
+}
+
+impl IndexEntry {
+    /// Encode the entry as a Merkle leaf
+    fn leaf(&self) -> Vec<u8> {
+        format!("{}:{}:{}", self.timestamp, self.transaction_id, self.data_hash).into_bytes()
+    }
+}
+
+/// Merkle digest of the entries in a time range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeDigest {
+    /// The range the digest covers
+    pub range: TimeRange,
+    /// Hex-encoded Merkle root of the entries in the range
+    pub root: String,
+    /// Number of entries in the range
+    pub count: u64,
+}
+
+/// Reconciliation request sent to a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReconcileRequest {
+    /// Request digests for a set of ranges
+    Digests(Vec<TimeRange>),
+    /// Request the entries of a range
+    Items {
+        /// The range to list
+        range: TimeRange,
+        /// Number of entries to skip
+        offset: usize,
+        /// Maximum number of entries to return
+        limit: usize,
+    },
+}
+
+/// Reconciliation response from a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReconcileResponse {
+    /// Digests for the requested ranges, in request order
+    Digests(Vec<RangeDigest>),
+    /// Entries of the requested range
+    Items {
+        /// Entries in `(timestamp, transaction_id)` order
+        entries: Vec<IndexEntry>,
+        /// Whether more entries remain after this page
+        has_more: bool,
+    },
+    /// The request could not be served
+    Error(String),
+}
+
+/// Trait for components able to send reconciliation requests to peers
+#[async_trait]
+pub trait ReconcileClient: Send + Sync {
+    /// Send a reconciliation request to the given peer
+    async fn reconcile_request(&self, peer_id: &str, request: ReconcileRequest) -> NetworkResult<ReconcileResponse>;
+}
+
+/// Get the storage key of the index bucket containing a timestamp
+fn bucket_key(timestamp: u64) -> String {
+    let bucket_start = timestamp / INDEX_BUCKET_SECS * INDEX_BUCKET_SECS;
+    format!("{}{:020}", INDEX_PREFIX, bucket_start)
+}
+
+/// Parse the bucket start time from an index key
+fn bucket_start(key: &str) -> Option<u64> {
+    key.strip_prefix(INDEX_PREFIX)?.parse().ok()
+}
+
+/// Load the entries of a single index bucket
+async fn load_bucket(storage: &dyn Storage, key: &str) -> NetworkResult<Vec<IndexEntry>> {
+    match storage.get(key).await {
+        Ok(data) => serde_json::from_slice(&data)
+            .map_err(|e| NetworkError::StorageError(StorageError::DeserializationError(e.to_string()))),
+        Err(StorageError::KeyNotFound(_)) => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Record a transaction in the local index
+///
+/// Recording the same entry twice has no effect. Concurrent recordings are
+/// serialized so none of them are lost.
+pub async fn record_transaction(storage: &dyn Storage, entry: IndexEntry) -> NetworkResult<()> {
+    let _guard = INDEX_LOCK.lock().await;
+    let key = bucket_key(entry.timestamp);
+    let mut entries = load_bucket(storage, &key).await?;
+
+    if entries.iter().any(|e| e.transaction_id == entry.transaction_id) {
+        return Ok(());
+    }
+
+    entries.push(entry);
+    entries.sort_by(|a, b| (a.timestamp, &a.transaction_id).cmp(&(b.timestamp, &b.transaction_id)));
+
+    let data = serde_json::to_vec(&entries)
+        .map_err(|e| NetworkError::StorageError(StorageError::SerializationError(e.to_string())))?;
+    storage.put(&key, &data).await?;
+
+    Ok(())
+}
+
+/// List all index entries within a range, ordered by timestamp and ID
+pub async fn range_entries(storage: &dyn Storage, range: TimeRange) -> NetworkResult<Vec<IndexEntry>> {
+    let first_bucket = range.start / INDEX_BUCKET_SECS * INDEX_BUCKET_SECS;
+
+    let mut keys: Vec<String> = storage.list(INDEX_PREFIX).await?
+        .into_iter()
+        .filter(|key| matches!(bucket_start(key), Some(start) if start >= first_bucket && start < range.end))
+        .collect();
+    keys.sort();
+
+    let mut entries = Vec::new();
+    for key in keys {
+        entries.extend(
+            load_bucket(storage, &key).await?
+                .into_iter()
+                .filter(|e| range.contains(e.timestamp))
+        );
+    }
+
+    Ok(entries)
+}
+
+/// Compute the Merkle digest of the local entries in a range
+pub async fn range_digest(storage: &dyn Storage, range: TimeRange) -> NetworkResult<RangeDigest> {
+    let entries = range_entries(storage, range).await?;
+    Ok(digest_entries(range, &entries))
+}
+
+/// Compute the digest of an ordered set of entries
+fn digest_entries(range: TimeRange, entries: &[IndexEntry]) -> RangeDigest {
+    let leaves: Vec<Vec<u8>> = entries.iter().map(IndexEntry::leaf).collect();
+    let tree = MerkleTree::new(&leaves);
+
+    RangeDigest {
+        range,
+        root: tree.root().to_hex(),
+        count: entries.len() as u64,
+    }
+}
+
+/// Answer a reconciliation request from local storage
+pub async fn serve_reconcile_request(storage: &dyn Storage, request: ReconcileRequest) -> ReconcileResponse {
+    match request {
+        ReconcileRequest::Digests(ranges) => {
+            let mut digests = Vec::with_capacity(ranges.len());
+            for range in ranges {
+                match range_digest(storage, range).await {
+                    Ok(digest) => digests.push(digest),
+                    Err(e) => return ReconcileResponse::Error(e.to_string()),
+                }
+            }
+            ReconcileResponse::Digests(digests)
+        }
+        ReconcileRequest::Items { range, offset, limit } => {
+            match range_entries(storage, range).await {
+                Ok(entries) => {
+                    let limit = limit.min(MAX_ITEMS_PER_RESPONSE);
+                    let has_more = entries.len() > offset.saturating_add(limit);
+                    let entries = entries.into_iter().skip(offset).take(limit).collect();
+                    ReconcileResponse::Items { entries, has_more }
+                }
+                Err(e) => ReconcileResponse::Error(e.to_string()),
+            }
+        }
+    }
+}
+
+/// Find the entries a peer has in `range` that are missing locally
+///
+/// Digests are requested for at most `max_batch_size` ranges at a time.
+/// Differing ranges holding more than `max_batch_size` remote entries are
+/// split into `fanout` sub-ranges; smaller ones are listed and diffed.
+pub async fn find_missing(
+    client: &dyn ReconcileClient,
+    storage: &dyn Storage,
+    peer_id: &str,
+    range: TimeRange,
+    fanout: usize,
+    max_batch_size: usize,
+) -> NetworkResult<Vec<IndexEntry>> {
+    let max_batch_size = max_batch_size.max(1);
+    let mut pending = VecDeque::from(vec![range]);
+    let mut missing = Vec::new();
+    let mut digest_requests = 0usize;
+
+    while !pending.is_empty() {
+        let batch: Vec<TimeRange> = pending.drain(..pending.len().min(max_batch_size)).collect();
+
+        let remote = match client.reconcile_request(peer_id, ReconcileRequest::Digests(batch.clone())).await? {
+            ReconcileResponse::Digests(digests) => digests,
+            ReconcileResponse::Error(e) => return Err(NetworkError::MessageError(e)),
+            _ => return Err(NetworkError::MessageError("Unexpected reconciliation response".to_string())),
+        };
+        digest_requests += 1;
+
+        if remote.len() != batch.len() {
+            return Err(NetworkError::MessageError(format!(
+                "Peer {} returned {} digests for {} ranges", peer_id, remote.len(), batch.len()
+            )));
+        }
+
+        for (range, remote_digest) in batch.into_iter().zip(remote) {
+            if remote_digest.range != range {
+                return Err(NetworkError::MessageError(format!(
+                    "Peer {} returned digest for the wrong range", peer_id
+                )));
+            }
+
+            // Nothing on the remote side that we could be missing
+            if remote_digest.count == 0 {
+                continue;
+            }
+
+            let local_entries = range_entries(storage, range).await?;
+            let local_digest = digest_entries(range, &local_entries);
+            if local_digest.root == remote_digest.root && local_digest.count == remote_digest.count {
+                continue;
+            }
+
+            if remote_digest.count as usize <= max_batch_size || range.width() <= INDEX_BUCKET_SECS {
+                let local_ids: HashSet<&str> = local_entries.iter()
+                    .map(|e| e.transaction_id.as_str())
+                    .collect();
+
+                let remote_entries = list_remote_entries(client, peer_id, range, max_batch_size).await?;
+                missing.extend(
+                    remote_entries.into_iter()
+                        .filter(|e| !local_ids.contains(e.transaction_id.as_str()))
+                );
+            } else {
+                pending.extend(range.split(fanout));
+            }
+        }
+    }
+
+    debug!("Reconciled with {} using {} digest requests, {} entries missing",
+           peer_id, digest_requests, missing.len());
+
+    Ok(missing)
+}
+
+/// List all entries a peer holds in a range, page by page
+async fn list_remote_entries(
+    client: &dyn ReconcileClient,
+    peer_id: &str,
+    range: TimeRange,
+    page_size: usize,
+) -> NetworkResult<Vec<IndexEntry>> {
+    let mut entries = Vec::new();
+
+    loop {
+        let request = ReconcileRequest::Items {
+            range,
+            offset: entries.len(),
+            limit: page_size,
+        };
+
+        match client.reconcile_request(peer_id, request).await? {
+            ReconcileResponse::Items { entries: page, has_more } => {
+                let received = page.len();
+                entries.extend(page.into_iter().filter(|e| range.contains(e.timestamp)));
+                if !has_more || received == 0 {
+                    break;
+                }
+            }
+            ReconcileResponse::Error(e) => return Err(NetworkError::MessageError(e)),
+            _ => return Err(NetworkError::MessageError("Unexpected reconciliation response".to_string())),
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::MockStorage;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Client that answers requests from a fixed remote storage
+    struct StorageClient {
+        remote: MockStorage,
+        requests: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ReconcileClient for StorageClient {
+        async fn reconcile_request(&self, _peer_id: &str, request: ReconcileRequest) -> NetworkResult<ReconcileResponse> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(serve_reconcile_request(&self.remote, request).await)
+        }
+    }
+
+    fn entry(i: u64) -> IndexEntry {
+        IndexEntry {
+            transaction_id: format!("tx-{}", i),
+            timestamp: 1_000_000 + i * 7,
+            data_hash: format!("hash-{}", i),
+        }
+    }
+
+    #[test]
+    fn test_range_split() {
+        let range = TimeRange::new(0, 16 * INDEX_BUCKET_SECS);
+        let children = range.split(4);
+        assert_eq!(children.len(), 4);
+        assert_eq!(children[0], TimeRange::new(0, 4 * INDEX_BUCKET_SECS));
+        assert_eq!(children[3].end, range.end);
+
+        // Ranges no wider than a bucket cannot be split further
+        let bucket = TimeRange::new(0, INDEX_BUCKET_SECS);
+        assert_eq!(bucket.split(16), vec![bucket]);
+    }
+
+    #[tokio::test]
+    async fn test_identical_sets_have_equal_digests() {
+        let a = MockStorage::new();
+        let b = MockStorage::new();
+        for i in 0..50 {
+            record_transaction(&a, entry(i)).await.unwrap();
+            // Insert in reverse order on the other side
+            record_transaction(&b, entry(49 - i)).await.unwrap();
+        }
+        // Duplicates are ignored
+        record_transaction(&a, entry(3)).await.unwrap();
+
+        let range = TimeRange::up_to(2_000_000);
+        let digest_a = range_digest(&a, range).await.unwrap();
+        let digest_b = range_digest(&b, range).await.unwrap();
+        assert_eq!(digest_a, digest_b);
+        assert_eq!(digest_a.count, 50);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_records_are_not_lost() {
+        let storage = std::sync::Arc::new(MockStorage::new());
+        let records: Vec<_> = (0..40)
+            .map(|i| {
+                let storage = storage.clone();
+                // All entries fall into the same bucket
+                tokio::spawn(async move {
+                    record_transaction(storage.as_ref(), IndexEntry { timestamp: 1_000_000, ..entry(i) }).await
+                })
+            })
+            .collect();
+        for record in records {
+            record.await.unwrap().unwrap();
+        }
+
+        let digest = range_digest(storage.as_ref(), TimeRange::up_to(2_000_000)).await.unwrap();
+        assert_eq!(digest.count, 40);
+    }
+
+    #[tokio::test]
+    async fn test_find_missing_descends_into_differences() {
+        let local = MockStorage::new();
+        let remote = MockStorage::new();
+
+        for i in 0..500 {
+            record_transaction(&remote, entry(i)).await.unwrap();
+            if i % 100 != 42 {
+                record_transaction(&local, entry(i)).await.unwrap();
+            }
+        }
+
+        let client = StorageClient { remote, requests: AtomicUsize::new(0) };
+        let missing = find_missing(&client, &local, "peer1", TimeRange::up_to(2_000_000), 16, 10)
+            .await
+            .unwrap();
+
+        let mut ids: Vec<String> = missing.into_iter().map(|e| e.transaction_id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["tx-142", "tx-242", "tx-342", "tx-42", "tx-442"]);
+
+        // Far fewer round trips than listing the whole history in batches
+        assert!(client.requests.load(Ordering::SeqCst) < 50);
+    }
+
+    #[tokio::test]
+    async fn test_find_missing_in_sync() {
+        let local = MockStorage::new();
+        let remote = MockStorage::new();
+        for i in 0..20 {
+            record_transaction(&local, entry(i)).await.unwrap();
+            record_transaction(&remote, entry(i)).await.unwrap();
+        }
+
+        let client = StorageClient { remote, requests: AtomicUsize::new(0) };
+        let missing = find_missing(&client, &local, "peer1", TimeRange::up_to(2_000_000), 16, 10)
+            .await
+            .unwrap();
+
+        assert!(missing.is_empty());
+        assert_eq!(client.requests.load(Ordering::SeqCst), 1);
+    }
+}
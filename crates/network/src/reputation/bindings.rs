@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use libp2p::PeerId;
+use tokio::sync::RwLock;
+
+/// Bindings between peers and the DIDs operating them
+///
+/// The network binds a peer to the node DID derived from its peer ID once
+/// the transport handshake has authenticated it, so a peer can't claim a DID
+/// without its key. A later binding replaces an earlier one for the same
+/// peer or DID.
+#[derive(Debug, Default)]
+pub struct PeerBindings {
+    /// DIDs by peer
+    dids: RwLock<HashMap<PeerId, String>>,
+    /// Peers by DID
+    peers: RwLock<HashMap<String, PeerId>>,
+}
+
+impl PeerBindings {
+    /// Create an empty set of bindings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bind a peer to a DID, returning `false` if it was already bound to it
+    pub async fn bind(&self, peer_id: PeerId, did: &str) -> bool {
+        let mut dids = self.dids.write().await;
+        let mut peers = self.peers.write().await;
+        if dids.get(&peer_id).map(String::as_str) == Some(did) {
+            return false;
+        }
+
+        if let Some(old_did) = dids.insert(peer_id, did.to_string()) {
+            peers.remove(&old_did);
+        }
+        if let Some(old_peer) = peers.insert(did.to_string(), peer_id) {
+            if old_peer != peer_id {
+                dids.remove(&old_peer);
+            }
+        }
+        true
+    }
+
+    /// Remove a peer's binding
+    pub async fn unbind(&self, peer_id: &PeerId) {
+        let mut dids = self.dids.write().await;
+        if let Some(did) = dids.remove(peer_id) {
+            self.peers.write().await.remove(&did);
+        }
+    }
+
+    /// Get the DID a peer is bound to
+    pub async fn did_for(&self, peer_id: &PeerId) -> Option<String> {
+        self.dids.read().await.get(peer_id).cloned()
+    }
+
+    /// Get the DID a peer is bound to without waiting, for synchronous code paths
+    pub fn try_did_for(&self, peer_id: &PeerId) -> Option<String> {
+        self.dids.try_read().ok()?.get(peer_id).cloned()
+    }
+
+    /// Get the peer a DID is bound to
+    pub async fn peer_for(&self, did: &str) -> Option<PeerId> {
+        self.peers.read().await.get(did).copied()
+    }
+}
@@ -0,0 +1,172 @@
+//! EigenTrust aggregation of local trust into global trust
+//!
+//! Each DID's local trust in the DIDs it has evidence about is normalized so
+//! its outgoing trust sums to one, and global trust is the stationary
+//! distribution of a walk along those edges that returns to the pre-trusted
+//! anchors with probability `pre_trust_weight` at every step. A group of
+//! DIDs vouching for each other only gains the trust that flows into it from
+//! outside, so colluding groups can't inflate their own scores, and every
+//! node computing from the same evidence gets the same result.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Local trust by truster and trusted DID
+pub type LocalTrust = BTreeMap<String, BTreeMap<String, f64>>;
+
+/// Pre-trusted anchor DIDs by federation
+pub type TrustAnchors = BTreeMap<String, BTreeSet<String>>;
+
+/// Configuration for EigenTrust
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EigenTrustConfig {
+    /// Probability of returning to the anchors at each step
+    pub pre_trust_weight: f64,
+    /// Change in trust (L1 distance) below which the iteration stops
+    pub tolerance: f64,
+    /// Maximum number of iterations
+    pub max_iterations: usize,
+}
+
+impl Default for EigenTrustConfig {
+    fn default() -> Self {
+        Self {
+            pre_trust_weight: 0.15,
+            tolerance: 1e-9,
+            max_iterations: 100,
+        }
+    }
+}
+
+/// Get the pre-trust distribution over DIDs
+///
+/// Each federation with anchors gets an equal share, split evenly between
+/// its anchors, so a federation can't gain weight by naming more anchors.
+/// Without anchors every DID is pre-trusted equally.
+pub fn pre_trust(anchors: &TrustAnchors, dids: &BTreeSet<String>) -> BTreeMap<String, f64> {
+    let federations: Vec<&BTreeSet<String>> = anchors.values().filter(|a| !a.is_empty()).collect();
+    let mut distribution = BTreeMap::new();
+    if federations.is_empty() {
+        for did in dids {
+            distribution.insert(did.clone(), 1.0 / dids.len() as f64);
+        }
+        return distribution;
+    }
+
+    for federation in &federations {
+        let share = 1.0 / (federations.len() * federation.len()) as f64;
+        for did in *federation {
+            *distribution.entry(did.clone()).or_insert(0.0) += share;
+        }
+    }
+    distribution
+}
+
+/// Compute global trust, summing to one over all DIDs
+pub fn global_trust(local: &LocalTrust, anchors: &TrustAnchors, config: &EigenTrustConfig) -> BTreeMap<String, f64> {
+    let mut dids: BTreeSet<String> = anchors.values().flatten().cloned().collect();
+    for (truster, trusted) in local {
+        dids.insert(truster.clone());
+        dids.extend(trusted.keys().cloned());
+    }
+    if dids.is_empty() {
+        return BTreeMap::new();
+    }
+
+    let pre_trust = pre_trust(anchors, &dids);
+    let alpha = config.pre_trust_weight.clamp(0.0, 1.0);
+
+    // Only positive trust in others counts; DIDs trusting no one defer to the anchors
+    let normalized: BTreeMap<&str, Vec<(&str, f64)>> = local.iter()
+        .filter_map(|(truster, trusted)| {
+            let edges: Vec<(&str, f64)> = trusted.iter()
+                .filter(|(did, trust)| *did != truster && **trust > 0.0)
+                .map(|(did, trust)| (did.as_str(), *trust))
+                .collect();
+            let total: f64 = edges.iter().map(|(_, trust)| trust).sum();
+            (total > 0.0).then(|| (truster.as_str(), edges.into_iter().map(|(did, t)| (did, t / total)).collect()))
+        })
+        .collect();
+
+    let mut trust = pre_trust.clone();
+    for _ in 0..config.max_iterations {
+        let mut next: BTreeMap<String, f64> = pre_trust.iter().map(|(did, p)| (did.clone(), alpha * p)).collect();
+        for (did, current) in &trust {
+            let flow = (1.0 - alpha) * current;
+            match normalized.get(did.as_str()) {
+                Some(edges) => {
+                    for (trusted, share) in edges {
+                        *next.entry(trusted.to_string()).or_insert(0.0) += flow * share;
+                    }
+                }
+                None => {
+                    for (anchor, p) in &pre_trust {
+                        *next.entry(anchor.clone()).or_insert(0.0) += flow * p;
+                    }
+                }
+            }
+        }
+
+        let change: f64 = dids.iter()
+            .map(|did| (next.get(did).unwrap_or(&0.0) - trust.get(did).unwrap_or(&0.0)).abs())
+            .sum();
+        trust = next;
+        if change < config.tolerance {
+            break;
+        }
+    }
+
+    dids.into_iter().map(|did| {
+        let score = trust.get(&did).copied().unwrap_or(0.0);
+        (did, score)
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(local: &mut LocalTrust, from: &str, to: &str, trust: f64) {
+        local.entry(from.to_string()).or_default().insert(to.to_string(), trust);
+    }
+
+    #[test]
+    fn test_colluding_group_gains_only_trust_flowing_into_it() {
+        let mut local = LocalTrust::new();
+        for (from, to) in [("anchor", "alice"), ("anchor", "bob"), ("alice", "bob"), ("bob", "alice")] {
+            edge(&mut local, from, to, 1.0);
+        }
+        // Sybils vouch for each other as strongly as they like, and one honest
+        // DID was fooled into a small amount of trust in them
+        for from in ["sybil-1", "sybil-2", "sybil-3"] {
+            for to in ["sybil-1", "sybil-2", "sybil-3"] {
+                edge(&mut local, from, to, 100.0);
+            }
+        }
+        edge(&mut local, "alice", "sybil-1", 0.1);
+        edge(&mut local, "bob", "mallory", -5.0);
+
+        let anchors = TrustAnchors::from([("coop".to_string(), BTreeSet::from(["anchor".to_string()]))]);
+        let trust = global_trust(&local, &anchors, &EigenTrustConfig::default());
+
+        assert!((trust.values().sum::<f64>() - 1.0).abs() < 1e-6);
+        let sybils: f64 = ["sybil-1", "sybil-2", "sybil-3"].iter().map(|d| trust[*d]).sum();
+        assert!(sybils < trust["alice"]);
+        assert!(trust["alice"] > trust["sybil-1"] && trust["bob"] > trust["sybil-1"]);
+        assert_eq!(trust["mallory"], 0.0);
+
+        // Without the honest link the sybils get nothing
+        local.get_mut("alice").unwrap().remove("sybil-1");
+        let trust = global_trust(&local, &anchors, &EigenTrustConfig::default());
+        assert!(trust["sybil-1"] < 1e-9);
+
+        // Each federation gets the same pre-trust however many anchors it names
+        let anchors = TrustAnchors::from([
+            ("a".to_string(), BTreeSet::from(["a1".to_string()])),
+            ("b".to_string(), BTreeSet::from(["b1".to_string(), "b2".to_string()])),
+        ]);
+        let pre = pre_trust(&anchors, &BTreeSet::new());
+        assert_eq!((pre["a1"], pre["b1"], pre["b2"]), (0.5, 0.25, 0.25));
+    }
+}
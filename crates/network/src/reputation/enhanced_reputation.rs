@@ -4,7 +4,7 @@ use std::sync::Arc;
 use libp2p::PeerId;
 use async_trait::async_trait;
 use serde::{Serialize, Deserialize};
-use crate::reputation::ReputationContext;
+use crate::reputation::{PeerBindings, ReputationContext, ReputationLedger};
 use tokio::sync::RwLock;
 
 /// Configuration for the enhanced reputation system
@@ -56,6 +56,10 @@ pub enum InteractionValue {
     MessageFailure,
     /// Invalid message
     InvalidMessage,
+    /// Message with a missing or invalid signature
+    InvalidSignature,
+    /// Traffic over a configured rate limit
+    RateLimitExceeded,
     /// Verified message
     VerifiedMessage,
     /// Discovery help
@@ -94,6 +98,10 @@ pub trait ContributionMetricsProvider: Send + Sync {
 }
 
 /// Enhanced reputation manager
+///
+/// Peers bound to a DID share that DID's score in the reputation ledger, if
+/// one is attached: their changes are recorded in the ledger and their
+/// scores read from it. Other peers are scored locally.
 #[derive(Debug)]
 pub struct EnhancedReputationManager {
     /// Reputation scores by peer ID and context
@@ -104,6 +112,10 @@ pub struct EnhancedReputationManager {
     config: EnhancedReputationConfig,
     /// Banned peers
     banned_peers: Arc<RwLock<HashMap<PeerId, Instant>>>,
+    /// Shared DID reputation ledger
+    ledger: Option<Arc<ReputationLedger>>,
+    /// Bindings between peers and DIDs
+    bindings: Arc<PeerBindings>,
 }
 
 impl EnhancedReputationManager {
@@ -114,98 +126,98 @@ impl EnhancedReputationManager {
             metrics: Arc::new(RwLock::new(HashMap::new())),
             config,
             banned_peers: Arc::new(RwLock::new(HashMap::new())),
+            ledger: None,
+            bindings: Arc::new(PeerBindings::new()),
         }
     }
-    
+
+    /// Create a reputation manager backed by the shared DID reputation ledger
+    pub fn with_ledger(
+        config: EnhancedReputationConfig,
+        ledger: Arc<ReputationLedger>,
+        bindings: Arc<PeerBindings>,
+    ) -> Self {
+        Self {
+            ledger: Some(ledger),
+            bindings,
+            ..Self::new(config)
+        }
+    }
+
+    /// Get the bindings between peers and DIDs
+    pub fn bindings(&self) -> Arc<PeerBindings> {
+        self.bindings.clone()
+    }
+
+    /// Get the score change for an interaction
+    fn interaction_points(value: InteractionValue) -> i32 {
+        match value {
+            InteractionValue::Positive(val) => val,
+            InteractionValue::Negative(val) => -val,
+            InteractionValue::Neutral => 0,
+            InteractionValue::ConnectionEstablished => 1,
+            // No penalty for normal connection loss
+            InteractionValue::ConnectionLost => 0,
+            InteractionValue::MessageSuccess => 1,
+            InteractionValue::MessageFailure => -1,
+            InteractionValue::InvalidMessage => -2,
+            InteractionValue::InvalidSignature => -5,
+            InteractionValue::RateLimitExceeded => -2,
+            InteractionValue::VerifiedMessage => 1,
+            InteractionValue::DiscoveryHelp => 1,
+            InteractionValue::RelaySuccess => 2,
+            InteractionValue::RelayFailure => -2,
+        }
+    }
+
+    /// Convert a change in points on this manager's scale to a ledger score change
+    fn ledger_delta(&self, points: i32) -> f64 {
+        let range = (self.config.max_score - self.config.min_score).max(1) as f64;
+        points as f64 / range
+    }
+
+    /// Convert a ledger score in `[0, 1]` to this manager's scale
+    fn scale_ledger_score(&self, score: f64) -> i32 {
+        let range = (self.config.max_score - self.config.min_score) as f64;
+        (self.config.min_score as f64 + score * range).round() as i32
+    }
+
     /// Update a peer's reputation
     async fn update_reputation(&self, peer_id: &PeerId, context: ReputationContext, value: InteractionValue) -> crate::NetworkResult<()> {
-        let mut scores = self.scores.write().await;
-        let context_scores = scores.entry(*peer_id).or_insert_with(HashMap::new);
-        let score = context_scores.entry(context).or_insert(self.config.default_score);
-        
-        match value {
-            InteractionValue::Positive(val) => {
-                *score = (*score + val).min(self.config.max_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.positive_interactions += 1;
-            },
-            InteractionValue::Negative(val) => {
-                *score = (*score - val).max(self.config.min_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.negative_interactions += 1;
-            },
-            InteractionValue::Neutral => {},
-            InteractionValue::ConnectionEstablished => {
-                *score = (*score + 1).min(self.config.max_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.positive_interactions += 1;
-            },
-            InteractionValue::ConnectionLost => {
-                // No penalty for normal connection loss
-            },
-            InteractionValue::MessageSuccess => {
-                *score = (*score + 1).min(self.config.max_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.positive_interactions += 1;
-            },
-            InteractionValue::MessageFailure => {
-                *score = (*score - 1).max(self.config.min_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.negative_interactions += 1;
-            },
-            InteractionValue::InvalidMessage => {
-                *score = (*score - 2).max(self.config.min_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.negative_interactions += 1;
-            },
-            InteractionValue::VerifiedMessage => {
-                *score = (*score + 1).min(self.config.max_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.positive_interactions += 1;
-            },
-            InteractionValue::DiscoveryHelp => {
-                *score = (*score + 1).min(self.config.max_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.positive_interactions += 1;
-            },
-            InteractionValue::RelaySuccess => {
-                *score = (*score + 2).min(self.config.max_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.positive_interactions += 1;
-            },
-            InteractionValue::RelayFailure => {
-                *score = (*score - 2).max(self.config.min_score);
-                let mut metrics = self.metrics.write().await;
-                let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
-                metrics_entry.negative_interactions += 1;
-            },
+        let points = Self::interaction_points(value);
+        {
+            let mut scores = self.scores.write().await;
+            let context_scores = scores.entry(*peer_id).or_insert_with(HashMap::new);
+            let score = context_scores.entry(context.clone()).or_insert(self.config.default_score);
+            *score = (*score + points).clamp(self.config.min_score, self.config.max_score);
         }
-        
-        // Update last interaction time
+
         {
             let mut metrics = self.metrics.write().await;
-            if let Some(metrics_entry) = metrics.get_mut(peer_id) {
-                metrics_entry.last_interaction = Some(Instant::now());
+            let metrics_entry = metrics.entry(*peer_id).or_insert_with(ReputationMetrics::default);
+            match value {
+                InteractionValue::Neutral | InteractionValue::ConnectionLost => {}
+                InteractionValue::Positive(_) => metrics_entry.positive_interactions += 1,
+                InteractionValue::Negative(_) => metrics_entry.negative_interactions += 1,
+                _ if points > 0 => metrics_entry.positive_interactions += 1,
+                _ => metrics_entry.negative_interactions += 1,
             }
+            metrics_entry.last_interaction = Some(Instant::now());
         }
-        
-        // Check if the peer should be banned
-        if let Some(score) = scores.get(peer_id).and_then(|ctx_scores| ctx_scores.get(&ReputationContext::Networking)) {
-            if *score <= self.config.ban_threshold {
-                let mut banned = self.banned_peers.write().await;
-                banned.insert(*peer_id, Instant::now());
+
+        // Peers operated by a DID share its score in the ledger
+        if let (Some(ledger), Some(did)) = (&self.ledger, self.bindings.did_for(peer_id).await) {
+            if points != 0 {
+                ledger.record(&did, context, self.ledger_delta(points), "network", &format!("{:?}", value)).await?;
             }
         }
-        
+
+        // Check if the peer should be banned
+        if self.get_reputation_async(peer_id, &ReputationContext::Networking).await <= self.config.ban_threshold {
+            let mut banned = self.banned_peers.write().await;
+            banned.insert(*peer_id, Instant::now());
+        }
+
         Ok(())
     }
     
@@ -248,6 +260,10 @@ impl EnhancedReputationManager {
     
     /// Get a peer's reputation score for a specific context
     pub async fn get_reputation_async(&self, peer_id: &PeerId, context: &ReputationContext) -> i32 {
+        if let (Some(ledger), Some(did)) = (&self.ledger, self.bindings.did_for(peer_id).await) {
+            return self.scale_ledger_score(ledger.score(&did, context).await);
+        }
+
         let scores = self.scores.read().await;
         scores
             .get(peer_id)
@@ -260,6 +276,11 @@ impl EnhancedReputationManager {
     pub fn get_reputation(&self, peer_id: &PeerId, context: &ReputationContext) -> i32 {
         // This is a fallback for synchronous code paths that can't use the async version
         // In production, you should prefer the async version above
+        if let (Some(ledger), Some(did)) = (&self.ledger, self.bindings.try_did_for(peer_id)) {
+            return ledger.try_score(&did, context)
+                .map_or(self.config.default_score, |score| self.scale_ledger_score(score));
+        }
+
         if let Ok(scores) = self.scores.try_read() {
             scores
                 .get(peer_id)
@@ -352,4 +373,35 @@ impl EnhancedReputationManager {
 }
 
 /// Alias for ReputationChange for backward compatibility
-pub type ReputationChange = InteractionValue; 
\ No newline at end of file
+pub type ReputationChange = InteractionValue; 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icn_core::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_bound_peers_share_the_ledger_score() {
+        let ledger = Arc::new(ReputationLedger::open(Arc::new(MemoryStorage::new())).await.unwrap());
+        let bindings = Arc::new(PeerBindings::new());
+        let manager = EnhancedReputationManager::with_ledger(Default::default(), ledger.clone(), bindings.clone());
+        let bound = PeerId::random();
+        let unbound = PeerId::random();
+        assert!(bindings.bind(bound, "did:icn:alice").await);
+
+        // Governance evidence about the DID moves the peer's score
+        ledger.record("did:icn:alice", ReputationContext::Networking, 0.25, "governance", "reviewed").await.unwrap();
+        assert_eq!(manager.get_reputation_async(&bound, &ReputationContext::Networking).await, 50);
+        assert_eq!(manager.get_reputation(&bound, &ReputationContext::Networking), 50);
+
+        // The peer's interactions are recorded against the DID, unbound peers stay local
+        manager.record_change(bound, InteractionValue::InvalidSignature).await.unwrap();
+        manager.record_change(unbound, InteractionValue::InvalidSignature).await.unwrap();
+        assert_eq!(manager.get_reputation_async(&bound, &ReputationContext::Networking).await, 45);
+        assert_eq!(manager.get_reputation_async(&unbound, &ReputationContext::Networking).await, -5);
+
+        let trail = ledger.audit_trail("did:icn:alice").await;
+        assert_eq!(trail.len(), 2);
+        assert_eq!(trail[1].source, "network");
+        assert_eq!(trail[1].reason, "InvalidSignature");
+    }
+}
@@ -0,0 +1,287 @@
+//! Signed reputation evidence gossiped between nodes
+//!
+//! Evidence is a statement signed by one DID about another DID's behaviour
+//! in a reputation context. It is published as
+//! `NetworkMessage::ReputationEvidence`, and each node stores the evidence
+//! it receives keyed by its content hash, so duplicates are dropped and
+//! nodes that have seen the same evidence compute the same global trust.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+use icn_core::crypto::{sha256, verify_signature, Signature};
+use icn_core::storage::{Storage, StorageError};
+use icn_core::utils::timestamp_secs;
+
+use super::eigentrust::{global_trust, EigenTrustConfig, LocalTrust, TrustAnchors};
+use super::ReputationContext;
+use crate::envelope::{EnvelopeSigner, EnvelopeVerifier};
+use crate::{MessageHandler, NetworkError, NetworkMessage, NetworkResult, NetworkService, PeerInfo};
+
+/// Message type of gossiped evidence
+pub const EVIDENCE_MESSAGE_TYPE: &str = "reputation.evidence";
+
+/// Storage prefix for evidence, keyed by ID
+const EVIDENCE_PREFIX: &str = "reputation/evidence/";
+
+/// Domain separator for evidence signatures
+const SIGNING_DOMAIN: &str = "icn-reputation-evidence-v1";
+
+/// Evidence about a DID, signed by the submitting DID
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReputationEvidence {
+    /// Hash of the signed content
+    pub id: String,
+    /// DID that submitted the evidence
+    pub submitter: String,
+    /// DID the evidence is about
+    pub subject: String,
+    /// Context the evidence applies to
+    pub context: ReputationContext,
+    /// How good (positive) or bad (negative) the behaviour was, in `[-1, 1]`
+    pub weight: f64,
+    /// What happened
+    pub description: String,
+    /// When the evidence was created (seconds since the epoch)
+    pub timestamp: u64,
+    /// Signature of the content by the submitter's key
+    pub signature: Vec<u8>,
+}
+
+/// Payload covered by an evidence signature
+#[derive(Serialize)]
+struct EvidencePayload<'a> {
+    domain: &'a str,
+    submitter: &'a str,
+    subject: &'a str,
+    context: &'a ReputationContext,
+    weight: f64,
+    description: &'a str,
+    timestamp: u64,
+}
+
+impl ReputationEvidence {
+    /// Create evidence signed by the signer's DID
+    pub fn sign(
+        subject: &str,
+        context: ReputationContext,
+        weight: f64,
+        description: &str,
+        signer: &dyn EnvelopeSigner,
+    ) -> NetworkResult<Self> {
+        let mut evidence = Self {
+            id: String::new(),
+            submitter: signer.sender().to_string(),
+            subject: subject.to_string(),
+            context,
+            weight: weight.clamp(-1.0, 1.0),
+            description: description.to_string(),
+            timestamp: timestamp_secs(),
+            signature: Vec::new(),
+        };
+        let bytes = evidence.signing_bytes()?;
+        evidence.id = sha256(&bytes).to_hex();
+        evidence.signature = signer.sign(&bytes)?;
+        Ok(evidence)
+    }
+
+    /// Get the canonical encoding covered by the signature
+    pub fn signing_bytes(&self) -> NetworkResult<Vec<u8>> {
+        serde_json::to_vec(&EvidencePayload {
+            domain: SIGNING_DOMAIN,
+            submitter: &self.submitter,
+            subject: &self.subject,
+            context: &self.context,
+            weight: self.weight,
+            description: &self.description,
+            timestamp: self.timestamp,
+        })
+        .map_err(|e| NetworkError::MessageError(format!("Failed to encode evidence: {}", e)))
+    }
+
+    /// Verify the evidence was signed by the submitter's key and its ID matches its content
+    pub fn verify(&self, public_key: &[u8]) -> NetworkResult<()> {
+        if !(-1.0..=1.0).contains(&self.weight) {
+            return Err(NetworkError::MessageError(format!("Evidence {} has weight {} outside [-1, 1]", self.id, self.weight)));
+        }
+        if self.submitter == self.subject {
+            return Err(NetworkError::MessageError(format!("Evidence {} is about its own submitter", self.id)));
+        }
+
+        let bytes = self.signing_bytes()?;
+        if sha256(&bytes).to_hex() != self.id {
+            return Err(NetworkError::MessageError(format!("Evidence {} does not match its ID", self.id)));
+        }
+        verify_signature(public_key, &bytes, &Signature::new(self.signature.clone()))
+            .map_err(|e| NetworkError::InvalidSignature(format!("evidence from {} ({})", self.submitter, e)))
+    }
+
+    /// Wrap the evidence in a network message
+    pub fn to_network_message(&self) -> NetworkMessage {
+        NetworkMessage::ReputationEvidence(self.clone())
+    }
+}
+
+/// Store of verified evidence, deduplicated by ID
+pub struct EvidenceStore {
+    /// Storage for evidence
+    storage: Arc<dyn Storage>,
+    /// Keys submitters' signatures are checked against
+    verifier: EnvelopeVerifier,
+    /// Evidence by ID
+    evidence: RwLock<BTreeMap<String, ReputationEvidence>>,
+}
+
+impl EvidenceStore {
+    /// Open the store, loading stored evidence
+    pub async fn open(storage: Arc<dyn Storage>, verifier: EnvelopeVerifier) -> NetworkResult<Arc<Self>> {
+        let mut evidence = BTreeMap::new();
+        for key in storage.list(EVIDENCE_PREFIX).await? {
+            let data = storage.get(&key).await?;
+            let item: ReputationEvidence = serde_json::from_slice(&data)
+                .map_err(|e| StorageError::DeserializationError(e.to_string()))?;
+            evidence.insert(item.id.clone(), item);
+        }
+
+        Ok(Arc::new(Self {
+            storage,
+            verifier,
+            evidence: RwLock::new(evidence),
+        }))
+    }
+
+    /// Verify and store evidence, returning `false` if it was already stored
+    pub async fn insert(&self, evidence: ReputationEvidence) -> NetworkResult<bool> {
+        if self.evidence.read().await.contains_key(&evidence.id) {
+            return Ok(false);
+        }
+
+        let public_key = self.verifier.key_for(&evidence.submitter).await
+            .ok_or_else(|| NetworkError::UnauthorizedSender(format!("No key known for {}", evidence.submitter)))?;
+        evidence.verify(&public_key)?;
+
+        let mut stored = self.evidence.write().await;
+        if stored.contains_key(&evidence.id) {
+            return Ok(false);
+        }
+        let data = serde_json::to_vec(&evidence)
+            .map_err(|e| StorageError::SerializationError(e.to_string()))?;
+        self.storage.put(&format!("{}{}", EVIDENCE_PREFIX, evidence.id), &data).await?;
+        debug!("Stored evidence {} from {} about {}", evidence.id, evidence.submitter, evidence.subject);
+        stored.insert(evidence.id.clone(), evidence);
+        Ok(true)
+    }
+
+    /// Store evidence and gossip it to other nodes
+    pub async fn publish(&self, network: &dyn NetworkService, evidence: ReputationEvidence) -> NetworkResult<()> {
+        if self.insert(evidence.clone()).await? {
+            network.broadcast(evidence.to_network_message()).await?;
+        }
+        Ok(())
+    }
+
+    /// Get the stored evidence about a DID
+    pub async fn evidence_about(&self, did: &str) -> Vec<ReputationEvidence> {
+        self.evidence.read().await.values()
+            .filter(|e| e.subject == did)
+            .cloned()
+            .collect()
+    }
+
+    /// Get the local trust expressed by the stored evidence
+    ///
+    /// A submitter's trust in a subject is the sum of the weights of its
+    /// evidence about the subject, optionally in one context only.
+    pub async fn local_trust(&self, context: Option<&ReputationContext>) -> LocalTrust {
+        let mut local = LocalTrust::new();
+        for evidence in self.evidence.read().await.values() {
+            if context.is_none_or(|c| *c == evidence.context) {
+                *local.entry(evidence.submitter.clone())
+                    .or_default()
+                    .entry(evidence.subject.clone())
+                    .or_insert(0.0) += evidence.weight;
+            }
+        }
+        local
+    }
+
+    /// Compute global trust from the stored evidence
+    pub async fn global_trust(
+        &self,
+        context: Option<&ReputationContext>,
+        anchors: &TrustAnchors,
+        config: &EigenTrustConfig,
+    ) -> BTreeMap<String, f64> {
+        global_trust(&self.local_trust(context).await, anchors, config)
+    }
+
+    /// Get a message handler to register with the network
+    pub fn network_handler(self: &Arc<Self>) -> Arc<dyn MessageHandler> {
+        Arc::new(EvidenceMessageHandler { store: Arc::clone(self) })
+    }
+}
+
+/// Stores gossiped evidence
+struct EvidenceMessageHandler {
+    /// Store received evidence is added to
+    store: Arc<EvidenceStore>,
+}
+
+#[async_trait]
+impl MessageHandler for EvidenceMessageHandler {
+    fn id(&self) -> usize {
+        0
+    }
+
+    fn name(&self) -> &str {
+        "reputation_evidence"
+    }
+
+    async fn handle_message(&self, message: &NetworkMessage, _peer: &PeerInfo) -> NetworkResult<()> {
+        if let NetworkMessage::ReputationEvidence(evidence) = message {
+            self.store.insert(evidence.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envelope::IdentitySigner;
+    use icn_core::crypto::IdentityKeyPair;
+    use icn_core::storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn test_evidence_is_verified_and_deduplicated() {
+        let alice = IdentitySigner::new("did:icn:alice", IdentityKeyPair::generate().unwrap());
+        let verifier = EnvelopeVerifier::new();
+        verifier.register_key(alice.sender(), alice.public_key()).await;
+        let storage = Arc::new(MemoryStorage::new());
+        let store = EvidenceStore::open(storage.clone(), verifier.clone()).await.unwrap();
+
+        let evidence = ReputationEvidence::sign("did:icn:bob", ReputationContext::Economic, 0.8, "repaid", &alice).unwrap();
+        assert!(store.insert(evidence.clone()).await.unwrap());
+        assert!(!store.insert(evidence.clone()).await.unwrap());
+
+        // Tampered evidence and evidence from unknown DIDs are rejected
+        let mut tampered = ReputationEvidence::sign("did:icn:bob", ReputationContext::Economic, -0.5, "late", &alice).unwrap();
+        tampered.weight = 1.0;
+        assert!(store.insert(tampered).await.is_err());
+        let mallory = IdentitySigner::new("did:icn:mallory", IdentityKeyPair::generate().unwrap());
+        let forged = ReputationEvidence::sign("did:icn:bob", ReputationContext::Economic, 1.0, "forged", &mallory).unwrap();
+        assert!(store.insert(forged).await.is_err());
+
+        // Reopening loads the stored evidence
+        let reopened = EvidenceStore::open(storage, verifier).await.unwrap();
+        assert_eq!(reopened.evidence_about("did:icn:bob").await, vec![evidence]);
+        let local = reopened.local_trust(Some(&ReputationContext::Economic)).await;
+        assert_eq!(local["did:icn:alice"]["did:icn:bob"], 0.8);
+        assert!(reopened.local_trust(Some(&ReputationContext::Governance)).await.is_empty());
+    }
+}
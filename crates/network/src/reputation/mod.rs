@@ -1,23 +1,12 @@
+mod bindings;
+pub mod eigentrust;
 mod enhanced_reputation;
+mod evidence;
 
-/// Different contexts for reputation tracking
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
-pub enum ReputationContext {
-    /// General networking (connections, message processing)
-    Networking,
-    /// Consensus participation
-    Consensus,
-    /// Data validation and verification
-    DataValidation,
-    /// Resource sharing and provisioning
-    ResourceSharing,
-    /// Economic transactions
-    Economic,
-    /// Governance participation
-    Governance,
-    /// Custom context
-    Custom(String),
-}
+pub use icn_core::reputation::{ReputationContext, ReputationEvent, ReputationLedger};
+pub use bindings::PeerBindings;
+pub use eigentrust::{EigenTrustConfig, LocalTrust, TrustAnchors};
+pub use evidence::{EvidenceStore, ReputationEvidence, EVIDENCE_MESSAGE_TYPE};
 
 pub use enhanced_reputation::{
     EnhancedReputationManager,
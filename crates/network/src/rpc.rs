@@ -0,0 +1,577 @@
+//! Request/response RPC for ICN
+//!
+//! Higher layers (DID resolution, storage retrieval, sync) need to ask a
+//! specific peer for something and wait for the answer. This module provides
+//! named RPC methods on top of a libp2p request-response protocol: requests
+//! carry a correlation ID and a deadline derived from their `QosLevel`,
+//! in-flight requests can be cancelled, and each call is recorded in
+//! `NetworkMetrics`. Servers cap the deadline a caller may ask for and the
+//! number of requests they serve at once, per peer and in total.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use tokio::sync::{oneshot, RwLock};
+use tracing::{debug, warn};
+
+use crate::messaging::QosLevel;
+use crate::metrics::NetworkMetrics;
+use crate::{NetworkError, NetworkResult};
+
+/// Protocol name used for RPC requests
+pub const RPC_PROTOCOL: &str = "/icn/rpc/1.0.0";
+
+/// An RPC request sent to a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcRequest {
+    /// Correlation ID, echoed in the response
+    pub id: u64,
+    /// Name of the method to call
+    pub method: String,
+    /// Encoded request payload
+    pub payload: Vec<u8>,
+    /// Time the caller is willing to wait (in milliseconds)
+    pub timeout_ms: u64,
+}
+
+/// Outcome of an RPC request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RpcStatus {
+    /// The request was handled successfully
+    Ok,
+    /// No handler is registered for the method
+    MethodNotFound,
+    /// The handler did not finish before the deadline
+    DeadlineExceeded,
+    /// The handler failed
+    Error(String),
+    /// The server is serving too many requests
+    Busy,
+}
+
+/// An RPC response from a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcResponse {
+    /// Correlation ID of the request
+    pub id: u64,
+    /// Outcome of the request
+    pub status: RpcStatus,
+    /// Encoded response payload
+    pub payload: Vec<u8>,
+}
+
+impl RpcStatus {
+    /// Get a short label for metrics
+    fn label(&self) -> &'static str {
+        match self {
+            RpcStatus::Ok => "ok",
+            RpcStatus::MethodNotFound => "method_not_found",
+            RpcStatus::DeadlineExceeded => "deadline_exceeded",
+            RpcStatus::Error(_) => "error",
+            RpcStatus::Busy => "busy",
+        }
+    }
+}
+
+/// Trait for handlers serving an RPC method
+#[async_trait]
+pub trait RpcHandler: Send + Sync {
+    /// Handle a request from a peer and return the encoded response
+    async fn handle(&self, peer_id: &str, payload: Vec<u8>) -> NetworkResult<Vec<u8>>;
+}
+
+/// RPC handler decoding requests and encoding responses with bincode
+struct TypedRpcHandler<Req, Resp, F> {
+    handler: F,
+    _types: PhantomData<fn(Req) -> Resp>,
+}
+
+#[async_trait]
+impl<Req, Resp, F, Fut> RpcHandler for TypedRpcHandler<Req, Resp, F>
+where
+    Req: DeserializeOwned + Send + 'static,
+    Resp: Serialize + Send + 'static,
+    F: Fn(String, Req) -> Fut + Send + Sync,
+    Fut: Future<Output = NetworkResult<Resp>> + Send,
+{
+    async fn handle(&self, peer_id: &str, payload: Vec<u8>) -> NetworkResult<Vec<u8>> {
+        let request: Req = bincode::deserialize(&payload).map_err(|_| NetworkError::DecodingError)?;
+        let response = (self.handler)(peer_id.to_string(), request).await?;
+        bincode::serialize(&response).map_err(|_| NetworkError::EncodingError)
+    }
+}
+
+/// Create a handler from an async function over typed requests and responses
+pub fn typed_handler<Req, Resp, F, Fut>(handler: F) -> Arc<dyn RpcHandler>
+where
+    Req: DeserializeOwned + Send + 'static,
+    Resp: Serialize + Send + 'static,
+    F: Fn(String, Req) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = NetworkResult<Resp>> + Send + 'static,
+{
+    Arc::new(TypedRpcHandler {
+        handler,
+        _types: PhantomData,
+    })
+}
+
+/// Limits applied when serving requests from peers
+#[derive(Debug, Clone)]
+pub struct RpcServerConfig {
+    /// Longest deadline granted to a request, whatever the caller asks for
+    pub max_timeout: Duration,
+    /// Maximum number of requests served at once for a single peer
+    pub max_in_flight_per_peer: usize,
+    /// Maximum number of requests served at once
+    pub max_in_flight: usize,
+}
+
+impl Default for RpcServerConfig {
+    fn default() -> Self {
+        Self {
+            max_timeout: QosLevel::Background.timeout(),
+            max_in_flight_per_peer: 16,
+            max_in_flight: 256,
+        }
+    }
+}
+
+/// Requests being served, in total and by peer
+#[derive(Debug, Default)]
+struct ServedRequests {
+    /// Number of requests being served
+    total: usize,
+    /// Number of requests being served by peer
+    per_peer: HashMap<String, usize>,
+}
+
+/// Slot for serving a request, released when dropped
+pub struct RpcPermit {
+    /// Peer the request came from
+    peer_id: String,
+    /// Requests being served
+    served: Arc<Mutex<ServedRequests>>,
+}
+
+impl Drop for RpcPermit {
+    fn drop(&mut self) {
+        let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+        served.total = served.total.saturating_sub(1);
+        if let Some(count) = served.per_peer.get_mut(&self.peer_id) {
+            *count -= 1;
+            if *count == 0 {
+                served.per_peer.remove(&self.peer_id);
+            }
+        }
+    }
+}
+
+/// Registry of RPC handlers by method name
+#[derive(Clone, Default)]
+pub struct RpcRegistry {
+    /// Handlers by method name
+    handlers: Arc<RwLock<HashMap<String, Arc<dyn RpcHandler>>>>,
+    /// Limits applied when serving requests
+    config: RpcServerConfig,
+    /// Requests being served
+    served: Arc<Mutex<ServedRequests>>,
+    /// Network metrics
+    metrics: Option<NetworkMetrics>,
+}
+
+impl RpcRegistry {
+    /// Create a new registry with the default limits
+    pub fn new(metrics: Option<NetworkMetrics>) -> Self {
+        Self::with_config(RpcServerConfig::default(), metrics)
+    }
+
+    /// Create a new registry with the given limits
+    pub fn with_config(config: RpcServerConfig, metrics: Option<NetworkMetrics>) -> Self {
+        Self {
+            handlers: Arc::new(RwLock::new(HashMap::new())),
+            config,
+            served: Arc::new(Mutex::new(ServedRequests::default())),
+            metrics,
+        }
+    }
+
+    /// Register the handler for a method, replacing any existing one
+    pub async fn register(&self, method: &str, handler: Arc<dyn RpcHandler>) {
+        self.handlers.write().await.insert(method.to_string(), handler);
+    }
+
+    /// Remove the handler for a method
+    pub async fn unregister(&self, method: &str) -> bool {
+        self.handlers.write().await.remove(method).is_some()
+    }
+
+    /// Reserve a slot for serving a request from a peer
+    ///
+    /// Returns `None` if the peer or the server as a whole already has the
+    /// maximum number of requests in flight.
+    pub fn try_admit(&self, peer_id: &str) -> Option<RpcPermit> {
+        let mut served = self.served.lock().unwrap_or_else(|e| e.into_inner());
+        let peer_count = served.per_peer.get(peer_id).copied().unwrap_or(0);
+        if served.total >= self.config.max_in_flight || peer_count >= self.config.max_in_flight_per_peer {
+            return None;
+        }
+
+        served.total += 1;
+        served.per_peer.insert(peer_id.to_string(), peer_count + 1);
+        Some(RpcPermit {
+            peer_id: peer_id.to_string(),
+            served: self.served.clone(),
+        })
+    }
+
+    /// Get the response refusing a request while the server is busy
+    pub fn busy(&self, request: &RpcRequest) -> RpcResponse {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_rpc_served(&request.method, RpcStatus::Busy.label());
+        }
+        RpcResponse {
+            id: request.id,
+            status: RpcStatus::Busy,
+            payload: Vec::new(),
+        }
+    }
+
+    /// Serve a request from a peer
+    ///
+    /// Requests beyond the in-flight limits are refused as busy.
+    pub async fn serve(&self, peer_id: &str, request: RpcRequest) -> RpcResponse {
+        match self.try_admit(peer_id) {
+            Some(permit) => self.serve_admitted(permit, request).await,
+            None => self.busy(&request),
+        }
+    }
+
+    /// Serve a request admitted with `try_admit`
+    ///
+    /// The handler is abandoned once the caller's deadline, capped at the
+    /// configured maximum, has passed.
+    pub async fn serve_admitted(&self, permit: RpcPermit, request: RpcRequest) -> RpcResponse {
+        let handler = self.handlers.read().await.get(&request.method).cloned();
+
+        let (status, payload) = match handler {
+            None => (RpcStatus::MethodNotFound, Vec::new()),
+            Some(handler) => {
+                let deadline = Duration::from_millis(request.timeout_ms).min(self.config.max_timeout);
+                match tokio::time::timeout(deadline, handler.handle(&permit.peer_id, request.payload)).await {
+                    Ok(Ok(payload)) => (RpcStatus::Ok, payload),
+                    Ok(Err(e)) => (RpcStatus::Error(e.to_string()), Vec::new()),
+                    Err(_) => (RpcStatus::DeadlineExceeded, Vec::new()),
+                }
+            }
+        };
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_rpc_served(&request.method, status.label());
+        }
+
+        RpcResponse {
+            id: request.id,
+            status,
+            payload,
+        }
+    }
+}
+
+/// Trait for transports able to deliver RPC requests to peers
+#[async_trait]
+pub trait RpcTransport: Send + Sync {
+    /// Send a request to a peer and wait for its response
+    async fn send_rpc(&self, peer_id: &str, request: RpcRequest) -> NetworkResult<RpcResponse>;
+}
+
+/// Client side of the RPC layer
+///
+/// Assigns correlation IDs, enforces deadlines and tracks in-flight
+/// requests so they can be cancelled.
+#[derive(Clone)]
+pub struct RpcClient {
+    /// Next correlation ID
+    next_id: Arc<AtomicU64>,
+    /// Cancellation senders for in-flight requests
+    in_flight: Arc<RwLock<HashMap<u64, oneshot::Sender<()>>>>,
+    /// Network metrics
+    metrics: Option<NetworkMetrics>,
+}
+
+impl RpcClient {
+    /// Create a new client
+    pub fn new(metrics: Option<NetworkMetrics>) -> Self {
+        Self {
+            next_id: Arc::new(AtomicU64::new(1)),
+            in_flight: Arc::new(RwLock::new(HashMap::new())),
+            metrics,
+        }
+    }
+
+    /// Allocate a correlation ID for a request
+    pub fn next_request_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Send a request with the deadline of a QoS level
+    pub async fn request(
+        &self,
+        transport: &dyn RpcTransport,
+        peer_id: &str,
+        method: &str,
+        payload: Vec<u8>,
+        qos: QosLevel,
+    ) -> NetworkResult<Vec<u8>> {
+        let id = self.next_request_id();
+        self.request_with_id(transport, id, peer_id, method, payload, qos.timeout()).await
+    }
+
+    /// Send a request with a pre-allocated correlation ID
+    ///
+    /// The ID can be passed to `cancel` from another task while the request
+    /// is in flight.
+    pub async fn request_with_id(
+        &self,
+        transport: &dyn RpcTransport,
+        id: u64,
+        peer_id: &str,
+        method: &str,
+        payload: Vec<u8>,
+        timeout: Duration,
+    ) -> NetworkResult<Vec<u8>> {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        self.in_flight.write().await.insert(id, cancel_tx);
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_rpc_started();
+        }
+        let start = Instant::now();
+
+        let request = RpcRequest {
+            id,
+            method: method.to_string(),
+            payload,
+            timeout_ms: timeout.as_millis() as u64,
+        };
+
+        let result = tokio::select! {
+            response = transport.send_rpc(peer_id, request) => response,
+            _ = tokio::time::sleep(timeout) => Err(NetworkError::RequestTimeout(format!(
+                "{} to {} timed out after {:?}", method, peer_id, timeout
+            ))),
+            _ = cancel_rx => Err(NetworkError::RequestCancelled(id)),
+        };
+
+        self.in_flight.write().await.remove(&id);
+
+        let result = result.and_then(|response| {
+            if response.id != id {
+                return Err(NetworkError::RpcError(format!(
+                    "Response ID {} does not match request {}", response.id, id
+                )));
+            }
+
+            match response.status {
+                RpcStatus::Ok => Ok(response.payload),
+                RpcStatus::MethodNotFound => Err(NetworkError::RpcError(format!(
+                    "Peer {} has no handler for {}", peer_id, method
+                ))),
+                RpcStatus::DeadlineExceeded => Err(NetworkError::RequestTimeout(format!(
+                    "{} on {} exceeded its deadline", method, peer_id
+                ))),
+                RpcStatus::Error(e) => Err(NetworkError::RpcError(e)),
+                RpcStatus::Busy => Err(NetworkError::RpcError(format!(
+                    "Peer {} is too busy to serve {}", peer_id, method
+                ))),
+            }
+        });
+
+        let outcome = match &result {
+            Ok(_) => "ok",
+            Err(NetworkError::RequestTimeout(_)) => "timeout",
+            Err(NetworkError::RequestCancelled(_)) => "cancelled",
+            Err(_) => "error",
+        };
+        debug!("RPC {} #{} to {} finished: {}", method, id, peer_id, outcome);
+
+        if let Some(metrics) = &self.metrics {
+            metrics.record_rpc_finished(method, outcome, start.elapsed());
+        }
+
+        result
+    }
+
+    /// Cancel an in-flight request
+    ///
+    /// Returns `false` if no request with this ID is in flight.
+    pub async fn cancel(&self, id: u64) -> bool {
+        match self.in_flight.write().await.remove(&id) {
+            Some(cancel_tx) => cancel_tx.send(()).is_ok(),
+            None => {
+                warn!("Cannot cancel RPC #{}: not in flight", id);
+                false
+            }
+        }
+    }
+
+    /// Get the number of in-flight requests
+    pub async fn in_flight(&self) -> usize {
+        self.in_flight.read().await.len()
+    }
+}
+
+/// Trait for network services offering RPC
+#[async_trait]
+pub trait RpcService: Send + Sync {
+    /// Call a method on a peer with the deadline of the given QoS level
+    async fn request(&self, peer_id: &str, method: &str, payload: Vec<u8>, qos: QosLevel) -> NetworkResult<Vec<u8>>;
+
+    /// Register the handler serving a method
+    async fn register_rpc_handler(&self, method: &str, handler: Arc<dyn RpcHandler>) -> NetworkResult<()>;
+}
+
+/// Call a method with typed, bincode-encoded request and response
+pub async fn call<Req, Resp>(
+    service: &dyn RpcService,
+    peer_id: &str,
+    method: &str,
+    request: &Req,
+    qos: QosLevel,
+) -> NetworkResult<Resp>
+where
+    Req: Serialize + Sync,
+    Resp: DeserializeOwned,
+{
+    let payload = bincode::serialize(request).map_err(|_| NetworkError::EncodingError)?;
+    let response = service.request(peer_id, method, payload, qos).await?;
+    bincode::deserialize(&response).map_err(|_| NetworkError::DecodingError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Transport delivering requests straight to a local registry
+    struct LocalTransport {
+        registry: RpcRegistry,
+    }
+
+    #[async_trait]
+    impl RpcTransport for LocalTransport {
+        async fn send_rpc(&self, _peer_id: &str, request: RpcRequest) -> NetworkResult<RpcResponse> {
+            Ok(self.registry.serve("caller", request).await)
+        }
+    }
+
+    /// Service combining a client with a local transport
+    struct LocalService {
+        client: RpcClient,
+        transport: LocalTransport,
+    }
+
+    #[async_trait]
+    impl RpcService for LocalService {
+        async fn request(&self, peer_id: &str, method: &str, payload: Vec<u8>, qos: QosLevel) -> NetworkResult<Vec<u8>> {
+            self.client.request(&self.transport, peer_id, method, payload, qos).await
+        }
+
+        async fn register_rpc_handler(&self, method: &str, handler: Arc<dyn RpcHandler>) -> NetworkResult<()> {
+            self.transport.registry.register(method, handler).await;
+            Ok(())
+        }
+    }
+
+    fn service() -> LocalService {
+        LocalService {
+            client: RpcClient::new(None),
+            transport: LocalTransport { registry: RpcRegistry::new(None) },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_call() {
+        let service = service();
+        service.register_rpc_handler("math.double", typed_handler(|_peer: String, n: u64| async move {
+            Ok(n * 2)
+        })).await.unwrap();
+
+        let doubled: u64 = call(&service, "peer1", "math.double", &21u64, QosLevel::Normal).await.unwrap();
+        assert_eq!(doubled, 42);
+
+        let result: NetworkResult<u64> = call(&service, "peer1", "math.missing", &1u64, QosLevel::Normal).await;
+        assert!(matches!(result, Err(NetworkError::RpcError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_and_cancellation() {
+        let service = Arc::new(service());
+        service.register_rpc_handler("slow", typed_handler(|_peer: String, _: ()| async move {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            Ok(())
+        })).await.unwrap();
+
+        // Requests give up once their deadline has passed
+        let id = service.client.next_request_id();
+        let result = service.client.request_with_id(
+            &service.transport, id, "peer1", "slow", Vec::new(), Duration::from_millis(50)
+        ).await;
+        assert!(matches!(result, Err(NetworkError::RequestTimeout(_))));
+
+        // In-flight requests can be cancelled by ID
+        let id = service.client.next_request_id();
+        let caller = service.clone();
+        let call = tokio::spawn(async move {
+            caller.client.request_with_id(
+                &caller.transport, id, "peer1", "slow", Vec::new(), QosLevel::Background.timeout()
+            ).await
+        });
+
+        while service.client.in_flight().await == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(service.client.cancel(id).await);
+        assert!(matches!(call.await.unwrap(), Err(NetworkError::RequestCancelled(i)) if i == id));
+        assert_eq!(service.client.in_flight().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_server_limits() {
+        let registry = RpcRegistry::with_config(RpcServerConfig {
+            max_timeout: Duration::from_millis(50),
+            max_in_flight_per_peer: 1,
+            max_in_flight: 2,
+        }, None);
+        registry.register("slow", typed_handler(|_peer: String, _: ()| async move {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+            Ok(())
+        })).await;
+
+        let request = RpcRequest {
+            id: 1,
+            method: "slow".to_string(),
+            payload: bincode::serialize(&()).unwrap(),
+            timeout_ms: u64::MAX,
+        };
+
+        // The caller's deadline is capped
+        let response = registry.serve("peer1", request.clone()).await;
+        assert_eq!(response.status, RpcStatus::DeadlineExceeded);
+
+        // In-flight requests are limited per peer and in total
+        let first = registry.try_admit("peer1").unwrap();
+        assert_eq!(registry.serve("peer1", request.clone()).await.status, RpcStatus::Busy);
+        let _second = registry.try_admit("peer2").unwrap();
+        assert!(registry.try_admit("peer3").is_none());
+
+        // Finished requests free their slot
+        drop(first);
+        assert!(registry.try_admit("peer1").is_some());
+    }
+}
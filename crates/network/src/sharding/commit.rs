@@ -0,0 +1,664 @@
+//! Two-phase commit of cross-shard transactions
+//!
+//! The node initiating a cross-shard transaction coordinates it: it asks the
+//! coordinator of every shard holding one of its accounts to prepare, and
+//! commits only if all of them vote to. A shard prepares by computing the
+//! new state of its accounts with the `ShardStateMachine`, persisting it and
+//! locking the accounts until the decision arrives. The coordinator persists
+//! its decision before announcing it and resends it until every shard has
+//! acknowledged it, so a decision survives crashes on either side. Shards
+//! that prepared but never heard the decision ask the coordinator again;
+//! a coordinator without a record of the transaction answers that it
+//! aborted, as it never decides to commit without keeping a record.
+//! Requests to prepare and decisions are only accepted from the transaction's
+//! coordinator, and votes and acknowledgements from the coordinator of the
+//! shard they are for.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{oneshot, RwLock};
+use tracing::{debug, info, warn};
+
+use icn_core::storage::StorageError;
+
+use super::{ShardId, ShardManager, ShardMessage, ShardTransaction};
+use crate::{NetworkError, NetworkResult};
+
+/// Storage prefix for transactions coordinated by this node
+const COORDINATOR_PREFIX: &str = "sharding/2pc/coordinator/";
+
+/// Storage prefix for transactions this node's shard prepared
+const PREPARED_PREFIX: &str = "sharding/2pc/prepared/";
+
+/// Computes account state changes for cross-shard transactions
+pub trait ShardStateMachine: Send + Sync {
+    /// Get an account's state after an operation, or an error if the operation can't be applied
+    fn apply(&self, account: &str, state: Option<&[u8]>, operation: &[u8]) -> NetworkResult<Vec<u8>>;
+}
+
+/// An operation on one account of a cross-shard transaction
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardOperation {
+    /// Account the operation applies to
+    pub account: String,
+    /// Operation, interpreted by the `ShardStateMachine`
+    pub operation: Vec<u8>,
+}
+
+/// A transaction that spans multiple shards
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrossShardTransaction {
+    /// ID of the transaction
+    pub id: String,
+    /// Node coordinating the transaction
+    pub coordinator: String,
+    /// Shard of the coordinating node
+    pub origin_shard: ShardId,
+    /// Shards holding the transaction's accounts
+    pub target_shards: Vec<ShardId>,
+    /// Operations, applied in order
+    pub operations: Vec<ShardOperation>,
+}
+
+impl CrossShardTransaction {
+    /// Create a transaction from its operations
+    ///
+    /// The coordinator and shards are filled in when it is initiated.
+    pub fn new(operations: Vec<ShardOperation>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            coordinator: String::new(),
+            origin_shard: 0,
+            target_shards: Vec::new(),
+            operations,
+        }
+    }
+}
+
+/// Outcome of a cross-shard transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionOutcome {
+    /// Applied on every shard
+    Committed,
+    /// Applied on no shard
+    Aborted,
+}
+
+/// Coordinator's durable record of a transaction
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CoordinatorRecord {
+    /// The transaction
+    transaction: CrossShardTransaction,
+    /// Shards that voted to commit
+    votes: BTreeSet<ShardId>,
+    /// Decision, once made
+    decision: Option<TransactionOutcome>,
+    /// Shards that acknowledged the decision
+    acks: BTreeSet<ShardId>,
+}
+
+/// A shard's durable record of a transaction it prepared
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct PreparedRecord {
+    /// ID of the transaction
+    transaction_id: String,
+    /// Node coordinating the transaction
+    coordinator: String,
+    /// Shard of the coordinating node
+    origin_shard: ShardId,
+    /// Shard that prepared
+    shard: ShardId,
+    /// New state of the shard's accounts, written on commit
+    updates: BTreeMap<String, Vec<u8>>,
+}
+
+/// A transaction coordinated by this node
+struct Coordinated {
+    /// Durable record
+    record: CoordinatorRecord,
+    /// When the transaction was started or its decision last sent
+    since: Instant,
+}
+
+/// A transaction prepared by this node's shard
+struct Prepared {
+    /// Durable record
+    record: PreparedRecord,
+    /// When the transaction was prepared or the decision last requested
+    since: Instant,
+    /// Transactions on the locked accounts, held until the decision
+    held: Vec<ShardTransaction>,
+}
+
+/// State of the two-phase commits the node takes part in
+#[derive(Default)]
+pub(super) struct CommitState {
+    /// Applies operations to account state
+    state_machine: RwLock<Option<Arc<dyn ShardStateMachine>>>,
+    /// Transactions coordinated by this node by ID
+    coordinated: RwLock<HashMap<String, Coordinated>>,
+    /// Transactions prepared by this node's shard by ID
+    prepared: RwLock<HashMap<String, Prepared>>,
+    /// Listeners for transaction outcomes by transaction ID
+    listeners: RwLock<HashMap<String, Vec<oneshot::Sender<TransactionOutcome>>>>,
+}
+
+impl ShardManager {
+    /// Set the state machine applying cross-shard operations
+    ///
+    /// Without one, the node's shard votes to abort every transaction.
+    pub async fn set_state_machine(&self, state_machine: Arc<dyn ShardStateMachine>) {
+        *self.commit.state_machine.write().await = Some(state_machine);
+    }
+
+    /// Check if a transaction spans multiple shards
+    pub async fn is_cross_shard_transaction(&self, transaction: &CrossShardTransaction) -> NetworkResult<bool> {
+        Ok(self.operation_shards(transaction).await?.len() > 1)
+    }
+
+    /// Initiate a cross-shard transaction, coordinated by this node
+    ///
+    /// Register a listener first to learn the outcome.
+    pub async fn initiate_cross_shard_transaction(
+        &self,
+        mut transaction: CrossShardTransaction,
+    ) -> NetworkResult<String> {
+        if transaction.operations.is_empty() {
+            return Err(NetworkError::MessageError("Cross-shard transaction without operations".to_string()));
+        }
+        transaction.coordinator = self.local.node_id.clone();
+        transaction.origin_shard = self.local_shard_id().await
+            .ok_or_else(|| NetworkError::InternalError("Local node has no shard".to_string()))?;
+        transaction.target_shards = self.operation_shards(&transaction).await?.into_iter().collect();
+
+        let id = transaction.id.clone();
+        if self.commit.coordinated.read().await.contains_key(&id) {
+            return Err(NetworkError::MessageError(format!("Transaction {} already initiated", id)));
+        }
+
+        let record = CoordinatorRecord {
+            transaction: transaction.clone(),
+            votes: BTreeSet::new(),
+            decision: None,
+            acks: BTreeSet::new(),
+        };
+        self.save_coordinator_record(&record).await?;
+        self.commit.coordinated.write().await.insert(id.clone(), Coordinated { record, since: Instant::now() });
+        info!("Preparing transaction {} on shards {:?}", id, transaction.target_shards);
+
+        let outbound = transaction.target_shards.iter()
+            .map(|shard| (*shard, ShardMessage::Prepare(transaction.clone())))
+            .collect();
+        self.dispatch(outbound).await?;
+        Ok(id)
+    }
+
+    /// Register a listener for the outcome of a transaction coordinated by this node
+    pub async fn register_transaction_listener(&self, transaction_id: &str) -> oneshot::Receiver<TransactionOutcome> {
+        let (tx, rx) = oneshot::channel();
+        let decided = self.commit.coordinated.read().await.get(transaction_id).and_then(|c| c.record.decision);
+        match decided {
+            Some(outcome) => {
+                let _ = tx.send(outcome);
+            }
+            None => {
+                self.commit.listeners.write().await.entry(transaction_id.to_string()).or_default().push(tx);
+            }
+        }
+        rx
+    }
+
+    /// Get the IDs of transactions this node's shard prepared but hasn't seen decided
+    pub async fn in_doubt_transactions(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.commit.prepared.read().await.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Reload the transactions in progress after a restart
+    ///
+    /// Undecided transactions coordinated by this node are aborted, decided
+    /// ones are announced again, and the coordinators of transactions the
+    /// node's shard prepared are asked for their decision.
+    pub async fn recover_transactions(&self) -> NetworkResult<()> {
+        let now = Instant::now();
+        let mut outbound = Vec::new();
+
+        for key in self.storage.list(PREPARED_PREFIX).await? {
+            let record: PreparedRecord = self.load_record(&key).await?;
+            debug!("Recovered prepared transaction {}", record.transaction_id);
+            outbound.push(decision_query(&record));
+            self.commit.prepared.write().await.insert(record.transaction_id.clone(), Prepared {
+                record,
+                since: now,
+                held: Vec::new(),
+            });
+        }
+
+        for key in self.storage.list(COORDINATOR_PREFIX).await? {
+            let mut record: CoordinatorRecord = self.load_record(&key).await?;
+            debug!("Recovered coordinated transaction {}", record.transaction.id);
+            if record.decision.is_none() {
+                record.decision = Some(TransactionOutcome::Aborted);
+                self.save_coordinator_record(&record).await?;
+            }
+            outbound.extend(decision_messages(&record));
+            self.commit.coordinated.write().await.insert(record.transaction.id.clone(), Coordinated {
+                record,
+                since: now,
+            });
+        }
+
+        self.dispatch(outbound).await
+    }
+
+    /// Act on transactions that timed out
+    ///
+    /// Transactions still collecting votes are aborted, decisions not yet
+    /// acknowledged by every shard are resent, and coordinators of in-doubt
+    /// transactions are asked for their decision again.
+    pub async fn check_transactions(&self) -> NetworkResult<()> {
+        let timeout = self.config.transaction_timeout;
+        let now = Instant::now();
+        let mut outbound = Vec::new();
+
+        let mut expired = Vec::new();
+        for (id, coordinated) in self.commit.coordinated.write().await.iter_mut() {
+            if now.duration_since(coordinated.since) < timeout {
+                continue;
+            }
+            coordinated.since = now;
+            match coordinated.record.decision {
+                None => expired.push(id.clone()),
+                Some(_) => outbound.extend(decision_messages(&coordinated.record)),
+            }
+        }
+        for id in expired {
+            warn!("Aborting transaction {} after timeout", id);
+            outbound.extend(self.decide(&id, TransactionOutcome::Aborted).await?);
+        }
+
+        for prepared in self.commit.prepared.write().await.values_mut() {
+            if now.duration_since(prepared.since) >= timeout {
+                prepared.since = now;
+                outbound.push(decision_query(&prepared.record));
+            }
+        }
+
+        self.dispatch(outbound).await
+    }
+
+    /// Hold a transaction if its account is locked by a prepared transaction
+    ///
+    /// Returns the transaction back if the account isn't locked.
+    pub(super) async fn hold_if_locked(&self, transaction: ShardTransaction) -> Option<ShardTransaction> {
+        let mut prepared = self.commit.prepared.write().await;
+        match prepared.values_mut().find(|p| p.record.updates.contains_key(&transaction.account)) {
+            Some(lock) => {
+                debug!("Holding transaction {} until {} is decided", transaction.id, lock.record.transaction_id);
+                lock.held.push(transaction);
+                None
+            }
+            None => Some(transaction),
+        }
+    }
+
+    /// Handle a two-phase commit message from a node and send the replies it requires
+    pub(super) async fn handle_commit_message(&self, sender: &str, message: ShardMessage) -> NetworkResult<()> {
+        let outbound = self.process_commit_message(sender, message).await?;
+        self.dispatch(outbound).await
+    }
+
+    // Private methods
+
+    /// Send messages to shards, handling those for the local node's shards directly
+    ///
+    /// Gossip isn't delivered back to the sender, so replies to messages the
+    /// node handles itself are queued here.
+    async fn dispatch(&self, outbound: Vec<(ShardId, ShardMessage)>) -> NetworkResult<()> {
+        let mut queue = VecDeque::from(outbound);
+        while let Some((shard, message)) = queue.pop_front() {
+            self.send_to_shard(shard, message.clone()).await?;
+            if self.joined.read().await.contains(&shard) {
+                queue.extend(self.process_commit_message(&self.local.node_id, message).await?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Process a two-phase commit message from a node, returning the replies to send
+    async fn process_commit_message(
+        &self,
+        sender: &str,
+        message: ShardMessage,
+    ) -> NetworkResult<Vec<(ShardId, ShardMessage)>> {
+        match message {
+            ShardMessage::Prepare(transaction) => {
+                if transaction.coordinator != sender {
+                    return Err(NetworkError::UnauthorizedSender(format!(
+                        "{} does not coordinate transaction {}", sender, transaction.id
+                    )));
+                }
+                self.prepare(transaction).await
+            }
+            ShardMessage::PrepareVote { transaction_id, shard, commit } => {
+                self.check_shard_coordinator(sender, shard).await?;
+                let outcome = {
+                    let mut coordinated = self.commit.coordinated.write().await;
+                    let Some(entry) = coordinated.get_mut(&transaction_id) else {
+                        return Ok(Vec::new());
+                    };
+                    let record = &mut entry.record;
+                    if record.decision.is_some() || !record.transaction.target_shards.contains(&shard) {
+                        return Ok(Vec::new());
+                    }
+
+                    if !commit {
+                        Some(TransactionOutcome::Aborted)
+                    } else {
+                        record.votes.insert(shard);
+                        let all = record.transaction.target_shards.iter().all(|s| record.votes.contains(s));
+                        all.then_some(TransactionOutcome::Committed)
+                    }
+                };
+
+                match outcome {
+                    Some(outcome) => self.decide(&transaction_id, outcome).await,
+                    None => Ok(Vec::new()),
+                }
+            }
+            ShardMessage::Decision { transaction_id, shard, origin_shard, outcome } => {
+                self.finish(sender, &transaction_id, shard, origin_shard, outcome).await
+            }
+            ShardMessage::DecisionAck { transaction_id, shard } => {
+                self.check_shard_coordinator(sender, shard).await?;
+                let done = {
+                    let mut coordinated = self.commit.coordinated.write().await;
+                    let Some(entry) = coordinated.get_mut(&transaction_id) else {
+                        return Ok(Vec::new());
+                    };
+                    let record = &mut entry.record;
+                    if record.decision.is_none() {
+                        return Ok(Vec::new());
+                    }
+                    record.acks.insert(shard);
+                    let done = record.transaction.target_shards.iter().all(|s| record.acks.contains(s));
+                    if done {
+                        coordinated.remove(&transaction_id);
+                    }
+                    done
+                };
+
+                if done {
+                    debug!("Transaction {} acknowledged by every shard", transaction_id);
+                    self.storage.delete(&coordinator_key(&transaction_id)).await?;
+                }
+                Ok(Vec::new())
+            }
+            ShardMessage::DecisionQuery { transaction_id, coordinator, shard } => {
+                if coordinator != self.local.node_id {
+                    return Ok(Vec::new());
+                }
+
+                let decision = self.commit.coordinated.read().await.get(&transaction_id)
+                    .map(|entry| (entry.record.decision, entry.record.transaction.origin_shard));
+                let (outcome, origin_shard) = match decision {
+                    Some((Some(outcome), origin_shard)) => (outcome, origin_shard),
+                    // Still collecting votes
+                    Some((None, _)) => return Ok(Vec::new()),
+                    // Never committed without a record
+                    None => match self.local_shard_id().await {
+                        Some(origin_shard) => (TransactionOutcome::Aborted, origin_shard),
+                        None => return Ok(Vec::new()),
+                    },
+                };
+                Ok(vec![(shard, ShardMessage::Decision { transaction_id, shard, origin_shard, outcome })])
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Prepare the local shard's part of a transaction and vote on it
+    async fn prepare(&self, transaction: CrossShardTransaction) -> NetworkResult<Vec<(ShardId, ShardMessage)>> {
+        let Some(shard) = self.local_shard_id().await else {
+            return Ok(Vec::new());
+        };
+        if !transaction.target_shards.contains(&shard) || !self.coordinates(shard).await {
+            return Ok(Vec::new());
+        }
+
+        let vote = |commit| vec![(transaction.origin_shard, ShardMessage::PrepareVote {
+            transaction_id: transaction.id.clone(),
+            shard,
+            commit,
+        })];
+        // Checking the locks and taking them happen under one write lock
+        let mut prepared = self.commit.prepared.write().await;
+        if prepared.contains_key(&transaction.id) {
+            return Ok(vote(true));
+        }
+
+        match self.stage(&transaction, shard, &prepared).await {
+            Ok(updates) => {
+                let record = PreparedRecord {
+                    transaction_id: transaction.id.clone(),
+                    coordinator: transaction.coordinator.clone(),
+                    origin_shard: transaction.origin_shard,
+                    shard,
+                    updates,
+                };
+                let data = serde_json::to_vec(&record)
+                    .map_err(|e| NetworkError::MessageError(format!("Failed to encode prepared transaction: {}", e)))?;
+                self.storage.put(&prepared_key(&transaction.id), &data).await?;
+
+                debug!("Prepared transaction {} on shard {}", transaction.id, shard);
+                prepared.insert(transaction.id.clone(), Prepared {
+                    record,
+                    since: Instant::now(),
+                    held: Vec::new(),
+                });
+                Ok(vote(true))
+            }
+            Err(e) => {
+                debug!("Shard {} votes to abort transaction {}: {}", shard, transaction.id, e);
+                Ok(vote(false))
+            }
+        }
+    }
+
+    /// Compute the new state of the local shard's accounts in a transaction
+    ///
+    /// Fails if an account is locked by one of the `prepared` transactions.
+    async fn stage(
+        &self,
+        transaction: &CrossShardTransaction,
+        shard: ShardId,
+        prepared: &HashMap<String, Prepared>,
+    ) -> NetworkResult<BTreeMap<String, Vec<u8>>> {
+        let state_machine = self.commit.state_machine.read().await.clone()
+            .ok_or_else(|| NetworkError::ServiceError("No shard state machine set".to_string()))?;
+
+        let mut updates: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for op in &transaction.operations {
+            if self.account_shard(&op.account).await? != shard {
+                continue;
+            }
+            if !updates.contains_key(&op.account) {
+                let handoffs = self.handoffs.read().await;
+                if handoffs.values().any(|h| h.handoff.covers(&op.account)) {
+                    return Err(NetworkError::ServiceError(format!("Account {} is being handed off", op.account)));
+                }
+                if prepared.values().any(|p| p.record.updates.contains_key(&op.account)) {
+                    return Err(NetworkError::ServiceError(format!("Account {} is locked", op.account)));
+                }
+            }
+
+            let current = match updates.get(&op.account) {
+                Some(state) => Some(state.clone()),
+                None => self.get_state(&op.account).await?,
+            };
+            let next = state_machine.apply(&op.account, current.as_deref(), &op.operation)?;
+            updates.insert(op.account.clone(), next);
+        }
+
+        Ok(updates)
+    }
+
+    /// Record the coordinator's decision and announce it to every shard
+    async fn decide(&self, transaction_id: &str, outcome: TransactionOutcome) -> NetworkResult<Vec<(ShardId, ShardMessage)>> {
+        let record = {
+            let mut coordinated = self.commit.coordinated.write().await;
+            let Some(entry) = coordinated.get_mut(transaction_id) else {
+                return Ok(Vec::new());
+            };
+            entry.record.decision = Some(outcome);
+            entry.since = Instant::now();
+            entry.record.clone()
+        };
+
+        // The decision must be durable before any shard acts on it
+        self.save_coordinator_record(&record).await?;
+        info!("Transaction {} {:?}", transaction_id, outcome);
+
+        if let Some(listeners) = self.commit.listeners.write().await.remove(transaction_id) {
+            for listener in listeners {
+                let _ = listener.send(outcome);
+            }
+        }
+        Ok(decision_messages(&record))
+    }
+
+    /// Apply a decision to the local shard's prepared state and acknowledge it
+    ///
+    /// Decisions on prepared transactions are only taken from their coordinator.
+    async fn finish(
+        &self,
+        sender: &str,
+        transaction_id: &str,
+        shard: ShardId,
+        origin_shard: ShardId,
+        outcome: TransactionOutcome,
+    ) -> NetworkResult<Vec<(ShardId, ShardMessage)>> {
+        let prepared = self.commit.prepared.read().await.get(transaction_id)
+            .map(|p| p.record.clone())
+            .filter(|r| r.shard == shard);
+
+        let Some(record) = prepared else {
+            // The shard voted to abort or already applied the decision, but the coordinator
+            // waits for an acknowledgement
+            if !self.coordinates(shard).await {
+                return Ok(Vec::new());
+            }
+            return Ok(vec![(origin_shard, ShardMessage::DecisionAck {
+                transaction_id: transaction_id.to_string(),
+                shard,
+            })]);
+        };
+        if record.coordinator != sender {
+            return Err(NetworkError::UnauthorizedSender(format!(
+                "{} does not coordinate transaction {}", sender, transaction_id
+            )));
+        }
+
+        if outcome == TransactionOutcome::Committed {
+            for (account, state) in &record.updates {
+                self.put_state(account, state).await?;
+            }
+        }
+        self.storage.delete(&prepared_key(transaction_id)).await?;
+        let held = self.commit.prepared.write().await.remove(transaction_id)
+            .map(|p| p.held)
+            .unwrap_or_default();
+        debug!("Applied {:?} decision for transaction {} on shard {}", outcome, transaction_id, shard);
+
+        for transaction in held {
+            self.submit_transaction(transaction).await?;
+        }
+        Ok(vec![(origin_shard, ShardMessage::DecisionAck {
+            transaction_id: transaction_id.to_string(),
+            shard,
+        })])
+    }
+
+    /// Check if the local node coordinates a shard
+    async fn coordinates(&self, shard: ShardId) -> bool {
+        self.topology.read().await.shard(shard)
+            .is_some_and(|s| s.coordinator.as_deref() == Some(self.local.node_id.as_str()))
+    }
+
+    /// Check that a message speaking for a shard was sent by the shard's coordinator
+    async fn check_shard_coordinator(&self, sender: &str, shard: ShardId) -> NetworkResult<()> {
+        let coordinator = self.topology.read().await.shard(shard).and_then(|s| s.coordinator);
+        if coordinator.as_deref() != Some(sender) {
+            return Err(NetworkError::UnauthorizedSender(format!(
+                "{} does not coordinate shard {}", sender, shard
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get the shards holding the accounts of a transaction
+    async fn operation_shards(&self, transaction: &CrossShardTransaction) -> NetworkResult<BTreeSet<ShardId>> {
+        let mut shards = BTreeSet::new();
+        for op in &transaction.operations {
+            shards.insert(self.account_shard(&op.account).await?);
+        }
+        Ok(shards)
+    }
+
+    /// Persist a coordinator record
+    async fn save_coordinator_record(&self, record: &CoordinatorRecord) -> NetworkResult<()> {
+        let data = serde_json::to_vec(record)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to encode coordinated transaction: {}", e)))?;
+        self.storage.put(&coordinator_key(&record.transaction.id), &data).await?;
+        Ok(())
+    }
+
+    /// Load a persisted record
+    async fn load_record<T: serde::de::DeserializeOwned>(&self, key: &str) -> NetworkResult<T> {
+        let data = self.storage.get(key).await.map_err(|e| match e {
+            StorageError::KeyNotFound(_) => NetworkError::InternalError(format!("Transaction record {} vanished", key)),
+            e => e.into(),
+        })?;
+        serde_json::from_slice(&data)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to decode transaction record {}: {}", key, e)))
+    }
+}
+
+/// Get the messages announcing a decision to shards that haven't acknowledged it
+fn decision_messages(record: &CoordinatorRecord) -> Vec<(ShardId, ShardMessage)> {
+    let Some(outcome) = record.decision else {
+        return Vec::new();
+    };
+
+    record.transaction.target_shards.iter()
+        .filter(|shard| !record.acks.contains(shard))
+        .map(|shard| (*shard, ShardMessage::Decision {
+            transaction_id: record.transaction.id.clone(),
+            shard: *shard,
+            origin_shard: record.transaction.origin_shard,
+            outcome,
+        }))
+        .collect()
+}
+
+/// Get the message asking a transaction's coordinator for its decision
+fn decision_query(record: &PreparedRecord) -> (ShardId, ShardMessage) {
+    (record.origin_shard, ShardMessage::DecisionQuery {
+        transaction_id: record.transaction_id.clone(),
+        coordinator: record.coordinator.clone(),
+        shard: record.shard,
+    })
+}
+
+/// Get the storage key of a coordinated transaction
+fn coordinator_key(transaction_id: &str) -> String {
+    format!("{}{}", COORDINATOR_PREFIX, transaction_id)
+}
+
+/// Get the storage key of a prepared transaction
+fn prepared_key(transaction_id: &str) -> String {
+    format!("{}{}", PREPARED_PREFIX, transaction_id)
+}
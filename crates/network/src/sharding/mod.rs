@@ -1,10 +1,55 @@
-use crate::error::Error;
-use crate::p2p::P2pNetwork;
+//! Sharding of nodes and accounts
+//!
+//! Accounts are assigned to shards by consistent hashing and nodes by
+//! consistent hashing or federation membership (see `ShardTopology`). Each
+//! shard uses the shard-scoped gossip topics of the configured federation.
+//! When dynamic resizing splits or merges shards, the state of the accounts
+//! that move is handed off from the old shard to the new one; transactions
+//! for those accounts are held back until the handoff completes and then
+//! delivered or forwarded, so none are lost. Only the layout coordinator
+//! plans resizes, and only nodes of the old shard may hand off state; nodes
+//! that missed resizes catch up from the coordinator's layout. Transactions
+//! touching accounts on several shards are committed atomically with
+//! two-phase commit (see `commit`).
+
+pub mod commit;
+pub mod ring;
+pub mod topology;
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, RwLock};
+use tracing::{debug, info, warn};
+
+use icn_core::storage::{Storage, StorageError};
+
+use crate::topics::TopicScope;
+use crate::{CustomMessage, MessageHandler, NetworkError, NetworkMessage, NetworkResult, PeerInfo};
+
+pub use commit::{CrossShardTransaction, ShardOperation, ShardStateMachine, TransactionOutcome};
+pub use ring::{HashRing, MovedRange, RingRange};
+pub use topology::{Handoff, NodeInfo, RebalancePlan, Resize, ShardTopology, TopologySnapshot};
+
+/// Shard identifier, as used in shard topic names
+pub type ShardId = u32;
+
+/// Message type used to gossip sharding messages as custom network messages
+pub const SHARD_MESSAGE_TYPE: &str = "sharding.message";
+
+/// Storage prefix for account state held by the local shard
+const STATE_PREFIX: &str = "sharding/state/";
+
+/// Maximum number of times a transaction is forwarded between shards
+const MAX_FORWARD_HOPS: u8 = 3;
+
+/// Maximum number of gossiped sharding messages waiting to be handled
+const MESSAGE_QUEUE_SIZE: usize = 1000;
+
+/// Number of times a new shard asks for handed off state before giving up
+const MAX_HANDOFF_ATTEMPTS: u32 = 3;
 
 /// Configuration for the sharding system
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -21,23 +66,32 @@ pub struct ShardConfig {
     pub min_nodes_per_shard: usize,
     /// Maximum number of nodes per shard
     pub max_nodes_per_shard: usize,
+    /// Federation whose shard topics are used
+    pub federation_id: String,
+    /// Time after which undecided cross-shard transactions are aborted
+    pub transaction_timeout: Duration,
+    /// Time after which handed off state is requested again
+    pub handoff_timeout: Duration,
 }
 
 impl Default for ShardConfig {
     fn default() -> Self {
         Self {
             shard_count: 4,
-            assignment_strategy: ShardAssignmentStrategy::Geographic,
+            assignment_strategy: ShardAssignmentStrategy::ConsistentHashing,
             dynamic_resizing: true,
             federation_based: true,
             min_nodes_per_shard: 3,
             max_nodes_per_shard: 100,
+            federation_id: "default".to_string(),
+            transaction_timeout: Duration::from_secs(30),
+            handoff_timeout: Duration::from_secs(60),
         }
     }
 }
 
 /// Strategies for assigning nodes to shards
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ShardAssignmentStrategy {
     /// Assign by geographic proximity
     Geographic,
@@ -53,7 +107,7 @@ pub enum ShardAssignmentStrategy {
 #[derive(Clone, Debug)]
 pub struct Shard {
     /// ID of the shard
-    pub id: String,
+    pub id: ShardId,
     /// Nodes in this shard
     pub nodes: HashSet<String>,
     /// Federation IDs associated with this shard
@@ -68,46 +122,64 @@ pub struct CrossShardMessage {
     /// ID of the message
     pub id: String,
     /// Origin shard ID
-    pub origin_shard: String,
+    pub origin_shard: ShardId,
     /// Target shard IDs
-    pub target_shards: Vec<String>,
+    pub target_shards: Vec<ShardId>,
     /// Message type
     pub message_type: String,
     /// Message payload
     pub payload: Vec<u8>,
 }
 
-/// A transaction that spans multiple shards
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct CrossShardTransaction {
+/// A transaction on a single account, routed to the account's shard
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardTransaction {
     /// ID of the transaction
     pub id: String,
-    /// Origin shard ID
-    pub origin_shard: String,
-    /// Target shard IDs
-    pub target_shards: Vec<String>,
+    /// Account the transaction applies to
+    pub account: String,
     /// Transaction data
     pub data: Vec<u8>,
-    /// Dependencies on other shards
-    pub dependencies: HashMap<String, Vec<String>>,
+    /// Number of times the transaction was forwarded
+    pub hops: u8,
 }
 
-impl CrossShardTransaction {
-    /// Create a cross-shard transaction from a regular transaction
-    pub fn from_transaction(transaction: Vec<u8>, origin_shard: String) -> Result<Self, Error> {
-        // In a real implementation, this would analyze the transaction to determine
-        // which shards are involved and create the cross-shard transaction accordingly
-        
-        Ok(Self {
+impl ShardTransaction {
+    /// Create a transaction on an account
+    pub fn new(account: &str, data: Vec<u8>) -> Self {
+        Self {
             id: uuid::Uuid::new_v4().to_string(),
-            origin_shard,
-            target_shards: vec![], // This would be determined based on transaction analysis
-            data: transaction,
-            dependencies: HashMap::new(),
-        })
+            account: account.to_string(),
+            data,
+            hops: 0,
+        }
     }
 }
 
+/// Where a submitted transaction went
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionRoute {
+    /// Delivered to the local transaction receiver
+    Local,
+    /// Held until a handoff of the account completes
+    Buffered,
+    /// Forwarded to the nodes of another shard
+    Forwarded(ShardId),
+}
+
+/// Gossip transport used by the shard manager
+#[async_trait]
+pub trait ShardTransport: Send + Sync {
+    /// Subscribe to a shard's topics
+    async fn join_shard(&self, federation_id: &str, shard_id: ShardId) -> NetworkResult<()>;
+
+    /// Unsubscribe from a shard's topics
+    async fn leave_shard(&self, federation_id: &str, shard_id: ShardId) -> NetworkResult<()>;
+
+    /// Broadcast a message on a scope's topics
+    async fn broadcast_to(&self, scope: TopicScope, message: NetworkMessage) -> NetworkResult<()>;
+}
+
 /// Handler for cross-shard message routing
 pub struct ShardRouter {
     /// The shard manager
@@ -119,109 +191,722 @@ impl ShardRouter {
     pub fn new(shard_manager: Arc<ShardManager>) -> Self {
         Self { shard_manager }
     }
-    
-    /// Handle a message
-    pub async fn handle_message(&self, data: &[u8]) -> Result<(), Error> {
-        // In a real implementation, this would determine if the message
-        // needs to be routed to another shard and handle it accordingly
-        Ok(())
+
+    /// Handle an encoded sharding message signed by a node
+    pub async fn handle_message(&self, sender: &str, data: &[u8]) -> NetworkResult<()> {
+        let message: ShardMessage = serde_json::from_slice(data)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to decode sharding message: {}", e)))?;
+        self.shard_manager.handle_message(sender, message).await
     }
 }
 
+/// A handoff the local node takes part in
+struct ActiveHandoff {
+    /// The handoff
+    handoff: Handoff,
+    /// Nodes of the shard handing off state, which may send it
+    senders: BTreeSet<String>,
+    /// Nodes of the shard taking over the state, which may acknowledge it
+    receivers: BTreeSet<String>,
+    /// The local node was in the shard handing off state
+    source: bool,
+    /// The local node is in the shard taking over the state
+    target: bool,
+    /// Transactions held until the handoff completes
+    buffered: Vec<ShardTransaction>,
+    /// Number of times the state was requested
+    attempts: u32,
+    /// When the state is requested again or the handoff aborted
+    deadline: Instant,
+}
+
 /// Manager for the sharding system
 pub struct ShardManager {
-    /// Network connection
-    p2p: Arc<P2pNetwork>,
+    /// Gossip transport
+    transport: Arc<dyn ShardTransport>,
+    /// Storage for the state of the local shard's accounts
+    storage: Arc<dyn Storage>,
     /// Configuration
     config: ShardConfig,
-    /// Local shard ID
-    local_shard_id: String,
-    /// All shards in the network
-    shards: RwLock<HashMap<String, Shard>>,
-    /// Active cross-shard transactions
-    cross_shard_transactions: RwLock<HashMap<String, CrossShardTransaction>>,
-    /// Message sender channel
-    message_sender: mpsc::Sender<CrossShardMessage>,
-    /// Message receiver channel
-    message_receiver: mpsc::Receiver<CrossShardMessage>,
+    /// The local node
+    local: NodeInfo,
+    /// Current shard layout
+    topology: RwLock<ShardTopology>,
+    /// Shards whose topics the node has joined
+    joined: RwLock<BTreeSet<ShardId>>,
+    /// Handoffs in progress by ID
+    handoffs: RwLock<HashMap<String, ActiveHandoff>>,
+    /// Cross-shard transactions in progress
+    commit: commit::CommitState,
+    /// Sender for transactions the local shard should apply
+    transaction_sender: mpsc::Sender<ShardTransaction>,
+    /// Receiver for transactions the local shard should apply, until taken
+    transaction_receiver: Mutex<Option<mpsc::Receiver<ShardTransaction>>>,
 }
 
 impl ShardManager {
     /// Create a new shard manager
-    pub async fn new(config: ShardConfig, p2p: Arc<P2pNetwork>) -> Result<Arc<Self>, Error> {
-        let (tx, rx) = mpsc::channel(100);
-        
-        // In a real implementation, this would determine the local shard ID
-        // based on the node's characteristics and the assignment strategy
-        let local_shard_id = "shard-0".to_string();
-        
-        let manager = Arc::new(Self {
-            p2p,
+    pub async fn new(
+        config: ShardConfig,
+        local: NodeInfo,
+        transport: Arc<dyn ShardTransport>,
+        storage: Arc<dyn Storage>,
+    ) -> NetworkResult<Arc<Self>> {
+        TopicScope::federation(&config.federation_id)?;
+        let (tx, rx) = mpsc::channel(1000);
+
+        let mut topology = ShardTopology::new(&config);
+        topology.add_node(local.clone());
+
+        Ok(Arc::new(Self {
+            transport,
+            storage,
             config,
-            local_shard_id,
-            shards: RwLock::new(HashMap::new()),
-            cross_shard_transactions: RwLock::new(HashMap::new()),
-            message_sender: tx,
-            message_receiver: rx,
-        });
-        
-        Ok(manager)
+            local,
+            topology: RwLock::new(topology),
+            joined: RwLock::new(BTreeSet::new()),
+            handoffs: RwLock::new(HashMap::new()),
+            commit: commit::CommitState::default(),
+            transaction_sender: tx,
+            transaction_receiver: Mutex::new(Some(rx)),
+        }))
     }
-    
+
+    /// Get the ID of the local node
+    pub fn local_node_id(&self) -> &str {
+        &self.local.node_id
+    }
+
     /// Get the local shard ID
-    pub fn local_shard_id(&self) -> String {
-        self.local_shard_id.clone()
+    pub async fn local_shard_id(&self) -> Option<ShardId> {
+        self.topology.read().await.node_shard(&self.local.node_id)
     }
-    
+
+    /// Get a snapshot of the shard layout
+    pub async fn topology(&self) -> ShardTopology {
+        self.topology.read().await.clone()
+    }
+
+    /// Take the receiver for transactions the local shard should apply
+    pub async fn take_transaction_receiver(&self) -> Option<mpsc::Receiver<ShardTransaction>> {
+        self.transaction_receiver.lock().await.take()
+    }
+
+    /// Get the shard an account is assigned to
+    pub async fn account_shard(&self, account: &str) -> NetworkResult<ShardId> {
+        self.topology.read().await.account_shard(account)
+            .ok_or_else(|| NetworkError::InternalError("No shards configured".to_string()))
+    }
+
     /// Discover peers in the same shard
-    pub async fn discover_shard_peers(&self) -> Result<(), Error> {
-        // In a real implementation, this would discover and connect to peers in the same shard
-        Ok(())
+    pub async fn discover_shard_peers(&self) -> NetworkResult<Vec<String>> {
+        let topology = self.topology.read().await;
+        let Some(shard) = topology.node_shard(&self.local.node_id) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(topology.shard_nodes(shard)
+            .into_iter()
+            .filter(|n| *n != self.local.node_id)
+            .collect())
     }
-    
+
     /// Start shard synchronization
-    pub async fn start_synchronization(&self) -> Result<(), Error> {
-        // In a real implementation, this would start synchronizing state with other nodes in the shard
+    ///
+    /// Joins the local shard's topics, announces the node to every shard, asks
+    /// the layout coordinator for resizes the node missed and recovers
+    /// cross-shard transactions left in progress.
+    pub async fn start_synchronization(&self) -> NetworkResult<()> {
+        self.sync_subscriptions().await?;
+        self.distribute_to_all_shards(ShardMessage::NodeJoined(self.local.clone())).await?;
+        self.request_topology().await?;
+        self.recover_transactions().await
+    }
+
+    /// Check cross-shard transactions and handoffs for timeouts until the manager is dropped
+    pub fn start_transaction_timer(self: &Arc<Self>) {
+        let timeout = self.config.transaction_timeout.min(self.config.handoff_timeout);
+        let period = (timeout / 2).max(Duration::from_millis(100));
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut timer = tokio::time::interval(period);
+            timer.tick().await;
+
+            loop {
+                timer.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = manager.check_transactions().await {
+                    warn!("Error checking cross-shard transactions: {}", e);
+                }
+                if let Err(e) = manager.check_handoffs().await {
+                    warn!("Error checking shard handoffs: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Add a node to the layout, rebalancing if it pushes a shard past its limits
+    ///
+    /// Only the layout coordinator rebalances; other nodes wait for its plan.
+    pub async fn add_node(&self, node: NodeInfo) -> NetworkResult<()> {
+        if self.topology.write().await.add_node(node) {
+            self.rebalance().await?;
+        }
+        Ok(())
+    }
+
+    /// Remove a node from the layout, rebalancing if it leaves a shard too small
+    ///
+    /// Only the layout coordinator rebalances; other nodes wait for its plan.
+    pub async fn remove_node(&self, node_id: &str) -> NetworkResult<()> {
+        if self.topology.write().await.remove_node(node_id) {
+            self.rebalance().await?;
+        }
         Ok(())
     }
-    
-    /// Check if a transaction spans multiple shards
-    pub async fn is_cross_shard_transaction(&self, transaction: &[u8]) -> Result<bool, Error> {
-        // In a real implementation, this would analyze the transaction to determine
-        // if it spans multiple shards
-        
-        // For this skeleton, randomly return true 10% of the time
-        Ok(rand::random::<f64>() < 0.1)
-    }
-    
-    /// Initiate a cross-shard transaction
-    pub async fn initiate_cross_shard_transaction(
-        &self,
-        transaction: CrossShardTransaction,
-    ) -> Result<String, Error> {
-        // Store the transaction
-        let tx_id = transaction.id.clone();
-        self.cross_shard_transactions.write().await.insert(tx_id.clone(), transaction.clone());
-        
-        // In a real implementation, this would coordinate with other shards
-        // to execute the transaction across all involved shards
-        
-        Ok(tx_id)
-    }
-    
-    /// Register a listener for transaction completion
-    pub async fn register_transaction_listener(&self, transaction_id: String) -> Result<(), Error> {
-        // In a real implementation, this would register a callback for when the transaction completes
+
+    /// Split or merge shards until they are within the node limits
+    ///
+    /// Each resize is announced to every shard so other nodes apply it too.
+    /// Only the layout coordinator plans resizes; on other nodes this returns
+    /// no plans.
+    pub async fn rebalance(&self) -> NetworkResult<Vec<RebalancePlan>> {
+        let mut plans = Vec::new();
+        loop {
+            let resize = {
+                let topology = self.topology.read().await;
+                if topology.coordinator().as_deref() != Some(self.local.node_id.as_str()) {
+                    break;
+                }
+                topology.plan_resize()
+            };
+            let Some(resize) = resize else {
+                break;
+            };
+
+            let plan = self.apply_resize(resize).await?;
+            info!("Rebalanced shards to epoch {} ({:?})", plan.epoch, plan.resize);
+            self.distribute_to_all_shards(ShardMessage::Rebalance(plan.clone())).await?;
+            plans.push(plan);
+        }
+        Ok(plans)
+    }
+
+    /// Act on handoffs that timed out
+    ///
+    /// A new shard still waiting for handed off state asks the old shard's
+    /// nodes for it again, up to `MAX_HANDOFF_ATTEMPTS` times, and then gives
+    /// up and applies the transactions it held without it. An old shard that
+    /// gets no acknowledgement for as long keeps the state and forwards the
+    /// transactions it held.
+    pub async fn check_handoffs(&self) -> NetworkResult<()> {
+        let now = Instant::now();
+        let (requests, expired) = {
+            let mut handoffs = self.handoffs.write().await;
+            let mut requests = Vec::new();
+            let mut expired = Vec::new();
+            for (id, active) in handoffs.iter_mut() {
+                if now < active.deadline {
+                    continue;
+                }
+                if active.target && active.attempts < MAX_HANDOFF_ATTEMPTS {
+                    active.attempts += 1;
+                    active.deadline = now + self.config.handoff_timeout;
+                    requests.push((active.handoff.from, id.clone()));
+                } else {
+                    expired.push(id.clone());
+                }
+            }
+            let expired: Vec<ActiveHandoff> = expired.iter().filter_map(|id| handoffs.remove(id)).collect();
+            (requests, expired)
+        };
+
+        for (from, handoff_id) in requests {
+            debug!("Requesting state of handoff {} again", handoff_id);
+            self.send_to_shard(from, ShardMessage::HandoffRequest { handoff_id }).await?;
+        }
+        if expired.is_empty() {
+            return Ok(());
+        }
+        for active in expired {
+            self.abort_handoff(active).await?;
+        }
+        self.sync_subscriptions().await
+    }
+
+    /// Store the state of an account owned by the local shard
+    pub async fn put_state(&self, account: &str, data: &[u8]) -> NetworkResult<()> {
+        self.storage.put(&state_key(account), data).await?;
         Ok(())
     }
-    
+
+    /// Get the state of an account held by the local shard
+    pub async fn get_state(&self, account: &str) -> NetworkResult<Option<Vec<u8>>> {
+        match self.storage.get(&state_key(account)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(StorageError::KeyNotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Route a transaction to the shard owning its account
+    ///
+    /// Transactions for accounts being handed off are held until the handoff
+    /// completes, and those for accounts locked by a prepared cross-shard
+    /// transaction until it is decided. Forwarded transactions are delivered
+    /// to every node of the owning shard.
+    pub async fn submit_transaction(&self, transaction: ShardTransaction) -> NetworkResult<TransactionRoute> {
+        {
+            let mut handoffs = self.handoffs.write().await;
+            if let Some(active) = handoffs.values_mut().find(|h| h.handoff.covers(&transaction.account)) {
+                debug!("Holding transaction {} until handoff {} completes", transaction.id, active.handoff.id);
+                active.buffered.push(transaction);
+                return Ok(TransactionRoute::Buffered);
+            }
+        }
+
+        let owner = self.account_shard(&transaction.account).await?;
+        if Some(owner) == self.local_shard_id().await {
+            let Some(transaction) = self.hold_if_locked(transaction).await else {
+                return Ok(TransactionRoute::Buffered);
+            };
+            self.deliver(transaction).await?;
+            return Ok(TransactionRoute::Local);
+        }
+
+        self.send_to_shard(owner, ShardMessage::Forward(transaction)).await?;
+        Ok(TransactionRoute::Forwarded(owner))
+    }
+
     /// Distribute a message to all shards
-    pub async fn distribute_to_all_shards(&self, message: ShardMessage) -> Result<(), Error> {
-        // In a real implementation, this would send the message to coordinators of all shards
+    pub async fn distribute_to_all_shards(&self, message: ShardMessage) -> NetworkResult<()> {
+        let shards = self.topology.read().await.shard_ids();
+        for shard in shards {
+            self.send_to_shard(shard, message.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Send a message to the nodes of a shard
+    pub async fn send_to_shard(&self, shard: ShardId, message: ShardMessage) -> NetworkResult<()> {
+        let scope = TopicScope::shard(&self.config.federation_id, shard)?;
+        self.transport.broadcast_to(scope, message.to_network_message()?).await
+    }
+
+    /// Get a message handler to register with the network
+    ///
+    /// Gossiped messages are queued and handled in order on a separate task,
+    /// since handling them can change the node's shard subscriptions, which
+    /// waits on the network loop the handler is called from. Messages are
+    /// attributed to the DID that signed them, so node IDs must be the DIDs
+    /// nodes sign with; unsigned messages have an empty sender.
+    pub fn network_handler(self: &Arc<Self>) -> Arc<dyn MessageHandler> {
+        let (sender, mut receiver) = mpsc::channel::<(String, ShardMessage)>(MESSAGE_QUEUE_SIZE);
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while let Some((sender, message)) = receiver.recv().await {
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                if let Err(e) = manager.handle_message(&sender, message).await {
+                    warn!("Error handling sharding message: {}", e);
+                }
+            }
+        });
+
+        Arc::new(ShardMessageHandler { queue: sender })
+    }
+
+    /// Handle a sharding message from another node
+    ///
+    /// `sender` is the ID of the node that signed the message. Nodes only
+    /// announce themselves, resizes are only taken from the layout
+    /// coordinator, handed off state only from nodes of the old shard, and
+    /// two-phase commit messages only from the coordinators they speak for.
+    pub async fn handle_message(&self, sender: &str, message: ShardMessage) -> NetworkResult<()> {
+        match message {
+            ShardMessage::NodeJoined(node) => {
+                check_sender(sender, &node.node_id)?;
+                self.add_node(node).await
+            }
+            ShardMessage::NodeLeft { node_id } => {
+                check_sender(sender, &node_id)?;
+                self.remove_node(&node_id).await
+            }
+            ShardMessage::Rebalance(plan) => self.apply_plan(sender, plan).await,
+            ShardMessage::TopologyRequest { epoch, shard } => self.send_topology(epoch, shard).await,
+            ShardMessage::TopologySnapshot(snapshot) => self.restore_topology(sender, snapshot).await,
+            ShardMessage::HandoffRequest { handoff_id } => self.resend_handoff(sender, &handoff_id).await,
+            ShardMessage::HandoffData { handoff_id, entries } => self.complete_target(sender, &handoff_id, entries).await,
+            ShardMessage::HandoffAck { handoff_id } => self.complete_source(sender, &handoff_id).await,
+            ShardMessage::Forward(mut transaction) => {
+                let owner = self.account_shard(&transaction.account).await?;
+                let covered = self.handoffs.read().await.values().any(|h| h.handoff.covers(&transaction.account));
+                if covered || Some(owner) == self.local_shard_id().await {
+                    return self.submit_transaction(transaction).await.map(|_| ());
+                }
+
+                // Our layout disagrees with the sender's; pass it on to the owner we know of
+                if transaction.hops >= MAX_FORWARD_HOPS {
+                    warn!("Dropping transaction {} after {} forwards", transaction.id, transaction.hops);
+                    return Ok(());
+                }
+                transaction.hops += 1;
+                self.send_to_shard(owner, ShardMessage::Forward(transaction)).await
+            }
+            message @ (ShardMessage::Prepare(_)
+            | ShardMessage::PrepareVote { .. }
+            | ShardMessage::Decision { .. }
+            | ShardMessage::DecisionAck { .. }
+            | ShardMessage::DecisionQuery { .. }) => self.handle_commit_message(sender, message).await,
+            ShardMessage::Proposal(_)
+            | ShardMessage::Transaction(_)
+            | ShardMessage::Vote { .. }
+            | ShardMessage::DataRequest { .. }
+            | ShardMessage::DataResponse { .. } => Ok(()),
+        }
+    }
+
+    // Private methods
+
+    /// Apply a resize planned by the layout coordinator
+    ///
+    /// A plan skipping epochs means the node missed resizes, so it asks for
+    /// the current layout instead.
+    async fn apply_plan(&self, sender: &str, plan: RebalancePlan) -> NetworkResult<()> {
+        let (epoch, coordinator) = {
+            let topology = self.topology.read().await;
+            (topology.epoch(), topology.coordinator())
+        };
+        if plan.epoch <= epoch {
+            debug!("Ignoring rebalance to epoch {} at epoch {}", plan.epoch, epoch);
+            return Ok(());
+        }
+        if plan.epoch > epoch + 1 {
+            debug!("Missed resizes before epoch {} at epoch {}", plan.epoch, epoch);
+            return self.request_topology().await;
+        }
+        if coordinator.as_deref() != Some(sender) {
+            return Err(NetworkError::UnauthorizedSender(format!(
+                "{} does not coordinate the shard layout", sender
+            )));
+        }
+
+        self.apply_resize(plan.resize).await.map(|_| ())
+    }
+
+    /// Ask the layout coordinator for the current layout
+    ///
+    /// The request goes to every shard the node knows of. Shards created by
+    /// splits the node missed are not among them, but the coordinator's shard
+    /// normally is (see `ShardTopology::coordinator`).
+    async fn request_topology(&self) -> NetworkResult<()> {
+        let epoch = self.topology.read().await.epoch();
+        let Some(shard) = self.local_shard_id().await else {
+            return Ok(());
+        };
+        self.distribute_to_all_shards(ShardMessage::TopologyRequest { epoch, shard }).await
+    }
+
+    /// Send the layout to a shard of nodes that are behind, if the local node coordinates it
+    async fn send_topology(&self, epoch: u64, shard: ShardId) -> NetworkResult<()> {
+        let snapshot = {
+            let topology = self.topology.read().await;
+            if topology.epoch() <= epoch || topology.coordinator().as_deref() != Some(self.local.node_id.as_str()) {
+                return Ok(());
+            }
+            topology.snapshot()
+        };
+        debug!("Sending shard layout at epoch {} to shard {}", snapshot.epoch, shard);
+        self.send_to_shard(shard, ShardMessage::TopologySnapshot(snapshot)).await
+    }
+
+    /// Adopt a newer layout from the node coordinating it
+    ///
+    /// No handoffs are started for the resizes the node missed, so it doesn't
+    /// hold the state of accounts its shard took over in them.
+    async fn restore_topology(&self, sender: &str, snapshot: TopologySnapshot) -> NetworkResult<()> {
+        {
+            let mut topology = self.topology.write().await;
+            if snapshot.epoch <= topology.epoch() {
+                return Ok(());
+            }
+
+            let mut restored = topology.clone();
+            restored.restore(snapshot);
+            if restored.coordinator().as_deref() != Some(sender) {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "{} does not coordinate the shard layout it sent", sender
+                )));
+            }
+            info!("Caught up with the shard layout at epoch {}", restored.epoch());
+            *topology = restored;
+        }
+        self.sync_subscriptions().await
+    }
+
+    /// Apply a resize and start the handoffs the local node takes part in
+    async fn apply_resize(&self, resize: Resize) -> NetworkResult<RebalancePlan> {
+        let (before, after, plan) = {
+            let mut topology = self.topology.write().await;
+            let before = topology.clone();
+            let plan = topology.apply(resize);
+            (before, topology.clone(), plan)
+        };
+        let old_shard = before.node_shard(&self.local.node_id);
+        let new_shard = after.node_shard(&self.local.node_id);
+
+        let now = Instant::now();
+        for handoff in &plan.handoffs {
+            let source = old_shard == Some(handoff.from);
+            let target = new_shard == Some(handoff.to);
+            if !source && !target {
+                continue;
+            }
+
+            // The old shard waits for as long as the new one keeps asking for the state
+            let deadline = match target {
+                true => now + self.config.handoff_timeout,
+                false => now + self.config.handoff_timeout * (MAX_HANDOFF_ATTEMPTS + 1),
+            };
+            debug!("Starting handoff {} from shard {} to shard {}", handoff.id, handoff.from, handoff.to);
+            self.handoffs.write().await.insert(handoff.id.clone(), ActiveHandoff {
+                handoff: handoff.clone(),
+                senders: before.shard_nodes(handoff.from),
+                receivers: after.shard_nodes(handoff.to),
+                source,
+                target,
+                buffered: Vec::new(),
+                attempts: 0,
+                deadline,
+            });
+        }
+        self.sync_subscriptions().await?;
+
+        // The old shard's coordinator sends the moving state to the new shard
+        for handoff in &plan.handoffs {
+            let coordinator = before.shard(handoff.from).and_then(|s| s.coordinator);
+            if coordinator.as_deref() == Some(self.local.node_id.as_str()) {
+                self.send_handoff(handoff, new_shard == Some(handoff.to)).await?;
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Send the state a handoff moves to the new shard
+    async fn send_handoff(&self, handoff: &Handoff, local_target: bool) -> NetworkResult<()> {
+        let entries = self.export_state(handoff).await?;
+        info!("Handing off {} accounts from shard {} to shard {}", entries.len(), handoff.from, handoff.to);
+        if local_target {
+            // Gossip isn't delivered back to the sender
+            self.complete_target(&self.local.node_id, &handoff.id, entries.clone()).await?;
+        }
+        self.send_to_shard(handoff.to, ShardMessage::HandoffData { handoff_id: handoff.id.clone(), entries }).await
+    }
+
+    /// Send the state of a handoff again for a node of the new shard that didn't get it
+    async fn resend_handoff(&self, sender: &str, handoff_id: &str) -> NetworkResult<()> {
+        let (handoff, local_target) = {
+            let handoffs = self.handoffs.read().await;
+            let Some(active) = handoffs.get(handoff_id).filter(|h| h.source) else {
+                return Ok(());
+            };
+            if !active.receivers.contains(sender) {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "{} is not in shard {} of handoff {}", sender, active.handoff.to, handoff_id
+                )));
+            }
+            (active.handoff.clone(), active.target)
+        };
+        self.send_handoff(&handoff, local_target).await
+    }
+
+    /// Import handed off state and release transactions held for it
+    ///
+    /// The state is only accepted from nodes of the old shard, and only for
+    /// accounts the handoff moves.
+    async fn complete_target(&self, sender: &str, handoff_id: &str, entries: Vec<(String, Vec<u8>)>) -> NetworkResult<()> {
+        let (from, source, buffered) = {
+            let mut handoffs = self.handoffs.write().await;
+            let Some(active) = handoffs.get_mut(handoff_id).filter(|h| h.target) else {
+                return Ok(());
+            };
+            if !active.senders.contains(sender) {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "{} is not in shard {} of handoff {}", sender, active.handoff.from, handoff_id
+                )));
+            }
+            if let Some((account, _)) = entries.iter().find(|(account, _)| !active.handoff.covers(account)) {
+                return Err(NetworkError::MessageError(format!(
+                    "Handoff {} doesn't move account {}", handoff_id, account
+                )));
+            }
+            active.target = false;
+            let result = (active.handoff.from, active.source, std::mem::take(&mut active.buffered));
+            if !active.source {
+                handoffs.remove(handoff_id);
+            }
+            result
+        };
+
+        for (account, data) in &entries {
+            self.put_state(account, data).await?;
+        }
+        for transaction in buffered {
+            self.deliver(transaction).await?;
+        }
+        debug!("Imported {} accounts from handoff {}", entries.len(), handoff_id);
+
+        self.send_to_shard(from, ShardMessage::HandoffAck { handoff_id: handoff_id.to_string() }).await?;
+        if source {
+            // Our own acknowledgement isn't delivered back to us
+            return self.complete_source(&self.local.node_id, handoff_id).await;
+        }
+        self.sync_subscriptions().await
+    }
+
+    /// Drop handed off state and forward transactions held for it
+    async fn complete_source(&self, sender: &str, handoff_id: &str) -> NetworkResult<()> {
+        let (handoff, still_target, buffered) = {
+            let mut handoffs = self.handoffs.write().await;
+            let Some(active) = handoffs.get_mut(handoff_id).filter(|h| h.source) else {
+                return Ok(());
+            };
+            if !active.receivers.contains(sender) {
+                return Err(NetworkError::UnauthorizedSender(format!(
+                    "{} is not in shard {} of handoff {}", sender, active.handoff.to, handoff_id
+                )));
+            }
+            active.source = false;
+            let result = (active.handoff.clone(), active.target, std::mem::take(&mut active.buffered));
+            if !active.target {
+                handoffs.remove(handoff_id);
+            }
+            result
+        };
+
+        // Nodes that moved to the new shard keep the state
+        let now_target = self.local_shard_id().await == Some(handoff.to);
+        if !still_target && !now_target {
+            for (account, _) in self.export_state(&handoff).await? {
+                self.storage.delete(&state_key(&account)).await?;
+            }
+        }
+        for transaction in buffered {
+            self.send_to_shard(handoff.to, ShardMessage::Forward(transaction)).await?;
+        }
+        debug!("Completed handoff {} to shard {}", handoff_id, handoff.to);
+
+        self.sync_subscriptions().await
+    }
+
+    /// Release the transactions held for a handoff that timed out
+    ///
+    /// A new shard that never got the state applies them without it; an old
+    /// shard that never got an acknowledgement keeps the state and forwards them.
+    async fn abort_handoff(&self, active: ActiveHandoff) -> NetworkResult<()> {
+        let handoff = active.handoff;
+        if active.target {
+            warn!("Aborting handoff {}: no state received from shard {}", handoff.id, handoff.from);
+            for transaction in active.buffered {
+                self.deliver(transaction).await?;
+            }
+        } else {
+            warn!("Aborting handoff {}: shard {} never acknowledged the state", handoff.id, handoff.to);
+            for transaction in active.buffered {
+                self.send_to_shard(handoff.to, ShardMessage::Forward(transaction)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the stored state of the accounts a handoff moves
+    async fn export_state(&self, handoff: &Handoff) -> NetworkResult<Vec<(String, Vec<u8>)>> {
+        let mut keys = self.storage.list(STATE_PREFIX).await?;
+        keys.sort();
+
+        let mut entries = Vec::new();
+        for key in keys {
+            let Some(account) = key.strip_prefix(STATE_PREFIX) else {
+                continue;
+            };
+            if handoff.covers(account) {
+                entries.push((account.to_string(), self.storage.get(&key).await?));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Deliver a transaction to the local transaction receiver
+    async fn deliver(&self, transaction: ShardTransaction) -> NetworkResult<()> {
+        self.transaction_sender.send(transaction).await
+            .map_err(|e| NetworkError::ChannelClosed(format!("Shard transaction receiver dropped: {}", e)))
+    }
+
+    /// Join the topics of the local shard and of shards still handing off state
+    async fn sync_subscriptions(&self) -> NetworkResult<()> {
+        let mut wanted: BTreeSet<ShardId> = self.handoffs.read().await.values()
+            .filter(|h| h.source)
+            .map(|h| h.handoff.from)
+            .collect();
+        wanted.extend(self.local_shard_id().await);
+
+        let mut joined = self.joined.write().await;
+        for shard in wanted.difference(&joined) {
+            self.transport.join_shard(&self.config.federation_id, *shard).await?;
+        }
+        for shard in joined.difference(&wanted) {
+            self.transport.leave_shard(&self.config.federation_id, *shard).await?;
+        }
+        *joined = wanted;
         Ok(())
     }
 }
 
+/// Get the storage key of an account's state
+fn state_key(account: &str) -> String {
+    format!("{}{}", STATE_PREFIX, account)
+}
+
+/// Check that a message about a node was signed by the node itself
+fn check_sender(sender: &str, node_id: &str) -> NetworkResult<()> {
+    if sender != node_id {
+        return Err(NetworkError::UnauthorizedSender(format!("{} can't announce {}", sender, node_id)));
+    }
+    Ok(())
+}
+
+/// Queues gossiped sharding messages for the shard manager
+struct ShardMessageHandler {
+    /// Queue of messages to handle with their sender
+    queue: mpsc::Sender<(String, ShardMessage)>,
+}
+
+#[async_trait]
+impl MessageHandler for ShardMessageHandler {
+    fn id(&self) -> usize {
+        0
+    }
+
+    fn name(&self) -> &str {
+        "shard_manager"
+    }
+
+    async fn handle_message(&self, message: &NetworkMessage, peer: &PeerInfo) -> NetworkResult<()> {
+        let sender = peer.sender.clone().unwrap_or_default();
+        self.queue.try_send((sender, ShardMessage::from_network_message(message)?))
+            .map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => NetworkError::QueueFull,
+                mpsc::error::TrySendError::Closed(_) => {
+                    NetworkError::ChannelClosed("Sharding message queue closed".to_string())
+                }
+            })
+    }
+}
+
 /// Message types for sharding
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ShardMessage {
@@ -258,11 +943,306 @@ pub enum ShardMessage {
         /// Data value
         value: Option<Vec<u8>>,
     },
+    /// A node joined the sharded network
+    NodeJoined(NodeInfo),
+    /// A node left the sharded network
+    NodeLeft {
+        /// ID of the node
+        node_id: String,
+    },
+    /// Shards were split or merged
+    Rebalance(RebalancePlan),
+    /// State handed off to a new shard
+    HandoffData {
+        /// ID of the handoff
+        handoff_id: String,
+        /// Accounts and their state
+        entries: Vec<(String, Vec<u8>)>,
+    },
+    /// The new shard imported handed off state
+    HandoffAck {
+        /// ID of the handoff
+        handoff_id: String,
+    },
+    /// A transaction forwarded to the shard owning its account
+    Forward(ShardTransaction),
+    /// Request to prepare a cross-shard transaction
+    Prepare(CrossShardTransaction),
+    /// A shard's vote on a prepared transaction
+    PrepareVote {
+        /// ID of the transaction
+        transaction_id: String,
+        /// Voting shard
+        shard: ShardId,
+        /// Whether the shard prepared and can commit
+        commit: bool,
+    },
+    /// The coordinator's decision on a transaction
+    Decision {
+        /// ID of the transaction
+        transaction_id: String,
+        /// Shard the decision is for
+        shard: ShardId,
+        /// Shard of the coordinator
+        origin_shard: ShardId,
+        /// The decision
+        outcome: TransactionOutcome,
+    },
+    /// A shard applied a decision
+    DecisionAck {
+        /// ID of the transaction
+        transaction_id: String,
+        /// Acknowledging shard
+        shard: ShardId,
+    },
+    /// A shard asks for the decision on a transaction it prepared
+    DecisionQuery {
+        /// ID of the transaction
+        transaction_id: String,
+        /// Node coordinating the transaction
+        coordinator: String,
+        /// Asking shard
+        shard: ShardId,
+    },
+    /// A node asks the layout coordinator for resizes it missed
+    TopologyRequest {
+        /// Epoch of the asking node's layout
+        epoch: u64,
+        /// Shard the asking node listens on
+        shard: ShardId,
+    },
+    /// The layout coordinator's current layout
+    TopologySnapshot(TopologySnapshot),
+    /// The new shard asks for handed off state again
+    HandoffRequest {
+        /// ID of the handoff
+        handoff_id: String,
+    },
+}
+
+impl ShardMessage {
+    /// Wrap the message for gossip
+    pub fn to_network_message(&self) -> NetworkResult<NetworkMessage> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| NetworkError::MessageError(format!("Failed to encode sharding message: {}", e)))?;
+
+        let mut data = serde_json::Map::new();
+        data.insert("message".to_string(), value);
+        Ok(NetworkMessage::Custom(CustomMessage {
+            message_type: SHARD_MESSAGE_TYPE.to_string(),
+            data,
+        }))
+    }
+
+    /// Unwrap a gossiped message
+    pub fn from_network_message(message: &NetworkMessage) -> NetworkResult<Self> {
+        match message {
+            NetworkMessage::Custom(custom) if custom.message_type == SHARD_MESSAGE_TYPE => {
+                let value = custom.data.get("message")
+                    .ok_or_else(|| NetworkError::MessageError("Sharding message without payload".to_string()))?;
+                serde_json::from_value(value.clone())
+                    .map_err(|e| NetworkError::MessageError(format!("Failed to decode sharding message: {}", e)))
+            }
+            other => Err(NetworkError::MessageError(format!("Not a sharding message: {}", other.message_type()))),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
-    // Tests would be implemented here
-} 
\ No newline at end of file
+    use crate::tests::MockStorage;
+
+    /// Transport recording what was sent
+    #[derive(Default)]
+    struct RecordingTransport {
+        joined: Mutex<BTreeSet<ShardId>>,
+        sent: Mutex<Vec<(TopicScope, ShardMessage)>>,
+    }
+
+    impl RecordingTransport {
+        async fn take_sent(&self) -> Vec<(TopicScope, ShardMessage)> {
+            std::mem::take(&mut *self.sent.lock().await)
+        }
+    }
+
+    #[async_trait]
+    impl ShardTransport for RecordingTransport {
+        async fn join_shard(&self, _federation_id: &str, shard_id: ShardId) -> NetworkResult<()> {
+            self.joined.lock().await.insert(shard_id);
+            Ok(())
+        }
+
+        async fn leave_shard(&self, _federation_id: &str, shard_id: ShardId) -> NetworkResult<()> {
+            self.joined.lock().await.remove(&shard_id);
+            Ok(())
+        }
+
+        async fn broadcast_to(&self, scope: TopicScope, message: NetworkMessage) -> NetworkResult<()> {
+            self.sent.lock().await.push((scope, ShardMessage::from_network_message(&message)?));
+            Ok(())
+        }
+    }
+
+    fn config() -> ShardConfig {
+        ShardConfig {
+            shard_count: 1,
+            dynamic_resizing: false,
+            min_nodes_per_shard: 1,
+            ..ShardConfig::default()
+        }
+    }
+
+    fn node(id: &str) -> NodeInfo {
+        NodeInfo { node_id: id.to_string(), federation_id: None }
+    }
+
+    const RESIZE: Resize = Resize::Split { shard: 0, new_shard: 1 };
+
+    /// Pick two nodes that end up in different shards when shard 0 splits
+    fn split_nodes() -> (String, String, ShardTopology) {
+        let mut split = ShardTopology::new(&config());
+        let names: Vec<String> = (0..100).map(|i| format!("node-{:03}", i)).collect();
+        for name in &names {
+            split.add_node(node(name));
+        }
+        split.apply(RESIZE);
+        let a = names.iter().find(|n| split.node_shard(n) == Some(0)).unwrap().clone();
+        let b = names.iter().find(|n| split.node_shard(n) == Some(1) && **n > a).unwrap().clone();
+        (a, b, split)
+    }
+
+    #[tokio::test]
+    async fn test_split_hands_off_state_without_losing_transactions() {
+        let (a, b, split) = split_nodes();
+        let resize = RESIZE;
+
+        let transport_a = Arc::new(RecordingTransport::default());
+        let transport_b = Arc::new(RecordingTransport::default());
+        let manager_a = ShardManager::new(config(), node(&a), transport_a.clone(), Arc::new(MockStorage::new())).await.unwrap();
+        let manager_b = ShardManager::new(config(), node(&b), transport_b.clone(), Arc::new(MockStorage::new())).await.unwrap();
+        manager_a.add_node(node(&b)).await.unwrap();
+        manager_b.add_node(node(&a)).await.unwrap();
+        manager_a.start_synchronization().await.unwrap();
+        let mut received_b = manager_b.take_transaction_receiver().await.unwrap();
+
+        let accounts: Vec<String> = (0..40).map(|i| format!("account-{}", i)).collect();
+        for account in &accounts {
+            manager_a.put_state(account, account.as_bytes()).await.unwrap();
+        }
+        transport_a.take_sent().await;
+
+        // Both nodes apply the split; the coordinator of shard 0 sends the moving state
+        let plan = ShardTopology::new(&config()).apply(resize);
+        manager_a.handle_message(&a, ShardMessage::Rebalance(plan.clone())).await.unwrap();
+        manager_b.handle_message(&a, ShardMessage::Rebalance(plan)).await.unwrap();
+        assert_eq!(manager_b.local_shard_id().await, Some(1));
+
+        let moved: Vec<&String> = accounts.iter().filter(|a| split.account_shard(a) == Some(1)).collect();
+        assert!(!moved.is_empty());
+
+        // Transactions for moving accounts are held on both sides
+        let early = ShardTransaction::new(moved[0], b"early".to_vec());
+        assert_eq!(manager_a.submit_transaction(early.clone()).await.unwrap(), TransactionRoute::Buffered);
+        let late = ShardTransaction::new(moved[0], b"late".to_vec());
+        assert_eq!(manager_b.submit_transaction(late.clone()).await.unwrap(), TransactionRoute::Buffered);
+
+        let sent = transport_a.take_sent().await;
+        let (scope, data) = sent.into_iter()
+            .find(|(_, m)| matches!(m, ShardMessage::HandoffData { .. }))
+            .unwrap();
+        assert_eq!(scope, TopicScope::shard("default", 1).unwrap());
+        let ShardMessage::HandoffData { entries, .. } = &data else { unreachable!() };
+        assert_eq!(entries.len(), moved.len());
+
+        // The new shard imports the state, releases held transactions and acknowledges
+        manager_b.handle_message(&a, data).await.unwrap();
+        assert_eq!(manager_b.get_state(moved[0]).await.unwrap(), Some(moved[0].as_bytes().to_vec()));
+        assert_eq!(received_b.recv().await.unwrap(), late);
+        let ack = transport_b.take_sent().await.into_iter()
+            .find(|(_, m)| matches!(m, ShardMessage::HandoffAck { .. }))
+            .unwrap();
+        assert_eq!(ack.0, TopicScope::shard("default", 0).unwrap());
+
+        // The old shard drops the moved state and forwards what it held
+        manager_a.handle_message(&b, ack.1).await.unwrap();
+        assert_eq!(manager_a.get_state(moved[0]).await.unwrap(), None);
+        let kept = accounts.iter().find(|a| split.account_shard(a) == Some(0)).unwrap();
+        assert!(manager_a.get_state(kept).await.unwrap().is_some());
+        let forwarded: Vec<ShardTransaction> = transport_a.take_sent().await.into_iter()
+            .filter_map(|(scope, m)| match m {
+                ShardMessage::Forward(tx) if scope == TopicScope::shard("default", 1).unwrap() => Some(tx),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(forwarded, vec![early.clone()]);
+
+        // Later transactions are routed by the new layout
+        assert_eq!(manager_a.submit_transaction(early).await.unwrap(), TransactionRoute::Forwarded(1));
+        assert_eq!(*transport_a.joined.lock().await, BTreeSet::from([0]));
+    }
+
+    #[tokio::test]
+    async fn test_handoffs_and_layouts_only_come_from_authorized_nodes() {
+        let (a, b, split) = split_nodes();
+        let config = ShardConfig { handoff_timeout: Duration::ZERO, ..config() };
+        let transport_a = Arc::new(RecordingTransport::default());
+        let transport_b = Arc::new(RecordingTransport::default());
+        let manager_a = ShardManager::new(config.clone(), node(&a), transport_a.clone(), Arc::new(MockStorage::new())).await.unwrap();
+        let manager_b = ShardManager::new(config.clone(), node(&b), transport_b.clone(), Arc::new(MockStorage::new())).await.unwrap();
+        manager_a.add_node(node(&b)).await.unwrap();
+        manager_b.add_node(node(&a)).await.unwrap();
+        let mut received_b = manager_b.take_transaction_receiver().await.unwrap();
+
+        // Only the layout coordinator's resizes are applied
+        let plan = ShardTopology::new(&config).apply(RESIZE);
+        assert!(manager_b.handle_message(&b, ShardMessage::Rebalance(plan.clone())).await.is_err());
+        assert_eq!(manager_b.topology().await.epoch(), 0);
+        manager_a.handle_message(&a, ShardMessage::Rebalance(plan.clone())).await.unwrap();
+        manager_b.handle_message(&a, ShardMessage::Rebalance(plan.clone())).await.unwrap();
+
+        let moved = (0..).map(|i| format!("account-{}", i)).find(|a| split.account_shard(a) == Some(1)).unwrap();
+        let kept = (0..).map(|i| format!("account-{}", i)).find(|a| split.account_shard(a) == Some(0)).unwrap();
+        let held = ShardTransaction::new(&moved, b"held".to_vec());
+        assert_eq!(manager_b.submit_transaction(held.clone()).await.unwrap(), TransactionRoute::Buffered);
+
+        // Handed off state must come from the old shard and only cover moving accounts
+        let handoff_id = plan.handoffs[0].id.clone();
+        let forged = ShardMessage::HandoffData { handoff_id: handoff_id.clone(), entries: vec![(moved.clone(), b"forged".to_vec())] };
+        assert!(manager_b.handle_message("node-mallory", forged).await.is_err());
+        let stray = ShardMessage::HandoffData { handoff_id: handoff_id.clone(), entries: vec![(kept, b"stray".to_vec())] };
+        assert!(manager_b.handle_message(&a, stray).await.is_err());
+        assert_eq!(manager_b.get_state(&moved).await.unwrap(), None);
+
+        // Without the state, the new shard asks for it again and finally gives up
+        transport_b.take_sent().await;
+        for _ in 0..MAX_HANDOFF_ATTEMPTS {
+            manager_b.check_handoffs().await.unwrap();
+        }
+        let requests = transport_b.take_sent().await.into_iter()
+            .filter(|(scope, m)| {
+                *scope == TopicScope::shard("default", 0).unwrap() && matches!(m, ShardMessage::HandoffRequest { .. })
+            })
+            .count();
+        assert_eq!(requests as u32, MAX_HANDOFF_ATTEMPTS);
+        manager_b.check_handoffs().await.unwrap();
+        assert_eq!(received_b.recv().await.unwrap(), held);
+
+        // A late joiner catches up with the coordinator's layout
+        let transport_c = Arc::new(RecordingTransport::default());
+        let manager_c = ShardManager::new(config, node("node-999"), transport_c.clone(), Arc::new(MockStorage::new())).await.unwrap();
+        manager_c.start_synchronization().await.unwrap();
+        let request = transport_c.take_sent().await.into_iter()
+            .find(|(_, m)| matches!(m, ShardMessage::TopologyRequest { .. }))
+            .unwrap();
+        transport_a.take_sent().await;
+        manager_a.handle_message("node-999", request.1).await.unwrap();
+        let snapshot = transport_a.take_sent().await.into_iter()
+            .find(|(_, m)| matches!(m, ShardMessage::TopologySnapshot(_)))
+            .unwrap();
+        assert!(manager_c.handle_message(&b, snapshot.1.clone()).await.is_err());
+        manager_c.handle_message(&a, snapshot.1).await.unwrap();
+        assert_eq!(manager_c.topology().await.epoch(), 1);
+        assert_eq!(manager_c.topology().await.shard_ids(), BTreeSet::from([0, 1]));
+    }
+}
@@ -0,0 +1,201 @@
+//! Consistent hash ring mapping keys to shards
+//!
+//! Each shard owns a number of points on a 64-bit ring, and a key belongs to
+//! the shard owning the first point at or after the key's hash. Adding,
+//! splitting or removing a shard only moves the keys in the arcs whose
+//! owner changed, which `HashRing::moved_ranges` reports so their state can
+//! be handed off.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use icn_core::crypto::sha256;
+
+use super::ShardId;
+
+/// Get the position of a key on the ring
+pub fn ring_point(key: &str) -> u64 {
+    let hash = sha256(key.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// An arc of the ring from `start` (exclusive) to `end` (inclusive)
+///
+/// Arcs with `start >= end` wrap around zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RingRange {
+    /// Point before the arc
+    pub start: u64,
+    /// Last point of the arc
+    pub end: u64,
+}
+
+impl RingRange {
+    /// Check if a point lies on the arc
+    pub fn contains(&self, point: u64) -> bool {
+        if self.start < self.end {
+            point > self.start && point <= self.end
+        } else {
+            point > self.start || point <= self.end
+        }
+    }
+}
+
+/// An arc whose owner changed between two rings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovedRange {
+    /// The arc
+    pub range: RingRange,
+    /// Shard that owned the arc
+    pub from: ShardId,
+    /// Shard that owns the arc now
+    pub to: ShardId,
+}
+
+/// Consistent hash ring
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashRing {
+    /// Points on the ring and the shards owning them
+    points: BTreeMap<u64, ShardId>,
+}
+
+impl HashRing {
+    /// Create a ring with `virtual_nodes` points per shard
+    pub fn new(shards: impl IntoIterator<Item = ShardId>, virtual_nodes: usize) -> Self {
+        let mut ring = Self::default();
+        for shard in shards {
+            ring.add_shard(shard, virtual_nodes);
+        }
+        ring
+    }
+
+    /// Add a shard with `virtual_nodes` points
+    pub fn add_shard(&mut self, shard: ShardId, virtual_nodes: usize) {
+        for vnode in 0..virtual_nodes {
+            self.points.insert(ring_point(&format!("shard-{}-{}", shard, vnode)), shard);
+        }
+    }
+
+    /// Split a shard, giving `new_shard` the first half of each of its arcs
+    pub fn split_shard(&mut self, shard: ShardId, new_shard: ShardId) {
+        let midpoints: Vec<u64> = self.ranges()
+            .into_iter()
+            .filter(|(_, owner)| *owner == shard)
+            .filter_map(|(range, _)| {
+                let length = range.end.wrapping_sub(range.start);
+                (length >= 2).then(|| range.start.wrapping_add(length / 2))
+            })
+            .collect();
+
+        for point in midpoints {
+            self.points.insert(point, new_shard);
+        }
+    }
+
+    /// Remove a shard, handing its arcs to the shards that follow them
+    pub fn remove_shard(&mut self, shard: ShardId) {
+        self.points.retain(|_, owner| *owner != shard);
+    }
+
+    /// Get the shards on the ring
+    pub fn shards(&self) -> BTreeSet<ShardId> {
+        self.points.values().copied().collect()
+    }
+
+    /// Get the shard a key belongs to
+    pub fn shard_for(&self, key: &str) -> Option<ShardId> {
+        self.owner(ring_point(key))
+    }
+
+    /// Get the shard owning a point
+    pub fn owner(&self, point: u64) -> Option<ShardId> {
+        self.points.range(point..)
+            .next()
+            .or_else(|| self.points.iter().next())
+            .map(|(_, shard)| *shard)
+    }
+
+    /// Get every arc of the ring and its owner
+    pub fn ranges(&self) -> Vec<(RingRange, ShardId)> {
+        let Some((&last, _)) = self.points.iter().next_back() else {
+            return Vec::new();
+        };
+
+        let mut start = last;
+        self.points.iter()
+            .map(|(&end, &shard)| {
+                let range = RingRange { start, end };
+                start = end;
+                (range, shard)
+            })
+            .collect()
+    }
+
+    /// Get the arcs whose owner differs between two rings
+    pub fn moved_ranges(before: &HashRing, after: &HashRing) -> Vec<MovedRange> {
+        let boundaries: BTreeSet<u64> = before.points.keys().chain(after.points.keys()).copied().collect();
+        let Some(&last) = boundaries.iter().next_back() else {
+            return Vec::new();
+        };
+
+        let mut moved = Vec::new();
+        let mut start = last;
+        for &end in &boundaries {
+            // No boundary lies inside the arc, so its end point's owner owns all of it
+            if let (Some(from), Some(to)) = (before.owner(end), after.owner(end)) {
+                if from != to {
+                    moved.push(MovedRange { range: RingRange { start, end }, from, to });
+                }
+            }
+            start = end;
+        }
+
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_remove_only_move_affected_keys() {
+        let ring = HashRing::new(0..4, 16);
+        let keys: Vec<String> = (0..2000).map(|i| format!("account-{}", i)).collect();
+
+        // Keys spread over every shard
+        let owners: Vec<ShardId> = keys.iter().map(|k| ring.shard_for(k).unwrap()).collect();
+        for shard in 0..4 {
+            assert!(owners.iter().filter(|o| **o == shard).count() > 200);
+        }
+
+        // Splitting shard 1 only moves keys from shard 1 to shard 4
+        let mut split = ring.clone();
+        split.split_shard(1, 4);
+        let moved = HashRing::moved_ranges(&ring, &split);
+        assert!(moved.iter().all(|m| m.from == 1 && m.to == 4));
+        for (key, before) in keys.iter().zip(&owners) {
+            let after = split.shard_for(key).unwrap();
+            if after != *before {
+                assert_eq!((*before, after), (1, 4));
+                assert!(moved.iter().any(|m| m.range.contains(ring_point(key))));
+            }
+        }
+        let taken = keys.iter().filter(|k| split.shard_for(k) == Some(4)).count();
+        let kept = keys.iter().filter(|k| split.shard_for(k) == Some(1)).count();
+        assert!(taken > 0 && kept > 0);
+
+        // Removing shard 2 only moves its own keys
+        let mut merged = ring.clone();
+        merged.remove_shard(2);
+        assert!(HashRing::moved_ranges(&ring, &merged).iter().all(|m| m.from == 2));
+        for (key, before) in keys.iter().zip(&owners) {
+            if *before != 2 {
+                assert_eq!(merged.shard_for(key), Some(*before));
+            }
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Assignment of nodes and accounts to shards
+//!
+//! `ShardTopology` is a deterministic view of the shard layout: accounts are
+//! placed on a consistent hash ring, and nodes are placed on the same ring by
+//! their own ID or, with the federation strategy, by their federation's ID so
+//! that federation members share a shard. With dynamic resizing, shards with
+//! too many nodes are split and shards with too few are merged into their
+//! neighbours, and each resize yields the handoffs that move state between
+//! shards.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::ring::{ring_point, HashRing, RingRange};
+use super::{Shard, ShardAssignmentStrategy, ShardConfig, ShardId};
+
+/// Number of ring points per shard
+const VIRTUAL_NODES: usize = 16;
+
+/// A node taking part in sharding
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeInfo {
+    /// ID of the node
+    pub node_id: String,
+    /// Federation the node belongs to, if any
+    pub federation_id: Option<String>,
+}
+
+/// A change to the set of shards
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resize {
+    /// Split a shard, moving half of its keys to a new shard
+    Split {
+        /// Shard to split
+        shard: ShardId,
+        /// ID of the new shard
+        new_shard: ShardId,
+    },
+    /// Merge a shard into the shards that follow it on the ring
+    Merge {
+        /// Shard to remove
+        shard: ShardId,
+    },
+}
+
+/// State that moves from one shard to another after a resize
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// ID of the handoff
+    pub id: String,
+    /// Topology epoch the handoff leads to
+    pub epoch: u64,
+    /// Shard handing off state
+    pub from: ShardId,
+    /// Shard taking over the state
+    pub to: ShardId,
+    /// Arcs of the ring that move
+    pub ranges: Vec<RingRange>,
+}
+
+impl Handoff {
+    /// Check if the handoff moves a key
+    pub fn covers(&self, key: &str) -> bool {
+        let point = ring_point(key);
+        self.ranges.iter().any(|r| r.contains(point))
+    }
+}
+
+/// The handoffs resulting from a resize
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebalancePlan {
+    /// Topology epoch after the resize
+    pub epoch: u64,
+    /// The resize
+    pub resize: Resize,
+    /// State to hand off, one entry per pair of shards
+    pub handoffs: Vec<Handoff>,
+}
+
+/// A node's view of the shard layout, sent to nodes catching up with it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopologySnapshot {
+    /// Number of resizes applied
+    pub epoch: u64,
+    /// Ring of shards
+    pub ring: HashRing,
+    /// Known nodes
+    pub nodes: Vec<NodeInfo>,
+}
+
+/// Deterministic view of the shard layout
+#[derive(Clone, Debug)]
+pub struct ShardTopology {
+    /// Node assignment strategy
+    strategy: ShardAssignmentStrategy,
+    /// Whether shards are split and merged
+    dynamic_resizing: bool,
+    /// Minimum number of nodes per shard
+    min_nodes_per_shard: usize,
+    /// Maximum number of nodes per shard
+    max_nodes_per_shard: usize,
+    /// Number of resizes applied
+    epoch: u64,
+    /// Ring of shards
+    ring: HashRing,
+    /// Known nodes by ID
+    nodes: BTreeMap<String, NodeInfo>,
+}
+
+impl ShardTopology {
+    /// Create a topology with the configured number of shards
+    pub fn new(config: &ShardConfig) -> Self {
+        Self {
+            strategy: config.assignment_strategy.clone(),
+            dynamic_resizing: config.dynamic_resizing,
+            min_nodes_per_shard: config.min_nodes_per_shard,
+            max_nodes_per_shard: config.max_nodes_per_shard,
+            epoch: 0,
+            ring: HashRing::new(0..config.shard_count.max(1) as ShardId, VIRTUAL_NODES),
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Get the number of resizes applied
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Get the IDs of all shards
+    pub fn shard_ids(&self) -> BTreeSet<ShardId> {
+        self.ring.shards()
+    }
+
+    /// Add a node, returning `false` if it was already known
+    pub fn add_node(&mut self, node: NodeInfo) -> bool {
+        self.nodes.insert(node.node_id.clone(), node.clone()) != Some(node)
+    }
+
+    /// Remove a node, returning `false` if it was unknown
+    pub fn remove_node(&mut self, node_id: &str) -> bool {
+        self.nodes.remove(node_id).is_some()
+    }
+
+    /// Get the known nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &NodeInfo> {
+        self.nodes.values()
+    }
+
+    /// Get the shard a node is assigned to
+    pub fn node_shard(&self, node_id: &str) -> Option<ShardId> {
+        self.assign(&self.ring, self.nodes.get(node_id)?)
+    }
+
+    /// Get the shard an account is assigned to
+    pub fn account_shard(&self, account: &str) -> Option<ShardId> {
+        self.ring.shard_for(account)
+    }
+
+    /// Get the nodes assigned to a shard
+    pub fn shard_nodes(&self, shard: ShardId) -> BTreeSet<String> {
+        self.nodes_by_shard(&self.ring).remove(&shard).unwrap_or_default()
+    }
+
+    /// Get a shard with its nodes and federations
+    ///
+    /// The coordinator is the node with the lowest ID.
+    pub fn shard(&self, shard: ShardId) -> Option<Shard> {
+        if !self.ring.shards().contains(&shard) {
+            return None;
+        }
+
+        let nodes = self.shard_nodes(shard);
+        Some(Shard {
+            id: shard,
+            coordinator: nodes.iter().next().cloned(),
+            federations: nodes.iter()
+                .filter_map(|n| self.nodes[n].federation_id.clone())
+                .collect(),
+            nodes: nodes.into_iter().collect(),
+        })
+    }
+
+    /// Get all shards
+    pub fn shards(&self) -> Vec<Shard> {
+        self.ring.shards().into_iter().filter_map(|s| self.shard(s)).collect()
+    }
+
+    /// Get the node that plans resizes
+    ///
+    /// This is the coordinator of the lowest shard with nodes. Splits give new
+    /// shards higher IDs than existing ones, so it is normally on one of the
+    /// initial shards, which nodes that missed resizes still reach.
+    pub fn coordinator(&self) -> Option<String> {
+        self.shards().into_iter().find_map(|s| s.coordinator)
+    }
+
+    /// Get a snapshot of the layout
+    pub fn snapshot(&self) -> TopologySnapshot {
+        TopologySnapshot {
+            epoch: self.epoch,
+            ring: self.ring.clone(),
+            nodes: self.nodes.values().cloned().collect(),
+        }
+    }
+
+    /// Adopt the ring and epoch of a snapshot, adding its nodes to the known ones
+    pub fn restore(&mut self, snapshot: TopologySnapshot) {
+        self.epoch = snapshot.epoch;
+        self.ring = snapshot.ring;
+        for node in snapshot.nodes {
+            self.nodes.insert(node.node_id.clone(), node);
+        }
+    }
+
+    /// Pick the next resize needed to keep shards within the node limits
+    ///
+    /// Oversized shards are split when both halves would keep the minimum
+    /// number of nodes; undersized shards are merged while more than one
+    /// shard remains. Returns `None` without dynamic resizing.
+    pub fn plan_resize(&self) -> Option<Resize> {
+        if !self.dynamic_resizing {
+            return None;
+        }
+
+        let shards = self.ring.shards();
+        let by_shard = self.nodes_by_shard(&self.ring);
+        let count = |shard: &ShardId| by_shard.get(shard).map_or(0, BTreeSet::len);
+
+        let new_shard = shards.iter().next_back().map_or(0, |s| s + 1);
+        for shard in &shards {
+            if count(shard) <= self.max_nodes_per_shard {
+                continue;
+            }
+
+            let resize = Resize::Split { shard: *shard, new_shard };
+            let split = self.resized_ring(resize);
+            let after = self.nodes_by_shard(&split);
+            let halves = [*shard, new_shard].map(|s| after.get(&s).map_or(0, BTreeSet::len));
+            if halves.iter().all(|n| *n >= self.min_nodes_per_shard) {
+                return Some(resize);
+            }
+        }
+
+        if shards.len() > 1 {
+            // Smallest shard first, lowest ID breaking ties
+            if let Some(shard) = shards.iter()
+                .filter(|s| count(s) < self.min_nodes_per_shard)
+                .min_by_key(|s| (count(s), **s))
+            {
+                return Some(Resize::Merge { shard: *shard });
+            }
+        }
+
+        None
+    }
+
+    /// Apply a resize, returning the handoffs it requires
+    pub fn apply(&mut self, resize: Resize) -> RebalancePlan {
+        let ring = self.resized_ring(resize);
+        self.epoch += 1;
+
+        let mut ranges: BTreeMap<(ShardId, ShardId), Vec<RingRange>> = BTreeMap::new();
+        for moved in HashRing::moved_ranges(&self.ring, &ring) {
+            ranges.entry((moved.from, moved.to)).or_default().push(moved.range);
+        }
+        self.ring = ring;
+
+        RebalancePlan {
+            epoch: self.epoch,
+            resize,
+            handoffs: ranges.into_iter()
+                .map(|((from, to), ranges)| Handoff {
+                    id: format!("{}-{}-{}", self.epoch, from, to),
+                    epoch: self.epoch,
+                    from,
+                    to,
+                    ranges,
+                })
+                .collect(),
+        }
+    }
+
+    /// Get the ring after a resize
+    fn resized_ring(&self, resize: Resize) -> HashRing {
+        let mut ring = self.ring.clone();
+        match resize {
+            Resize::Split { shard, new_shard } => ring.split_shard(shard, new_shard),
+            Resize::Merge { shard } => ring.remove_shard(shard),
+        }
+        ring
+    }
+
+    /// Assign a node to a shard of a ring
+    ///
+    /// Geographic and capacity-based assignment need information nodes don't
+    /// announce yet, so they fall back to consistent hashing.
+    fn assign(&self, ring: &HashRing, node: &NodeInfo) -> Option<ShardId> {
+        match (&self.strategy, &node.federation_id) {
+            (ShardAssignmentStrategy::Federation, Some(federation_id)) => ring.shard_for(federation_id),
+            _ => ring.shard_for(&node.node_id),
+        }
+    }
+
+    /// Group the nodes by the shard of a ring they are assigned to
+    fn nodes_by_shard(&self, ring: &HashRing) -> BTreeMap<ShardId, BTreeSet<String>> {
+        let mut by_shard: BTreeMap<ShardId, BTreeSet<String>> = BTreeMap::new();
+        for node in self.nodes.values() {
+            if let Some(shard) = self.assign(ring, node) {
+                by_shard.entry(shard).or_default().insert(node.node_id.clone());
+            }
+        }
+        by_shard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: usize, federation: Option<&str>) -> NodeInfo {
+        NodeInfo {
+            node_id: format!("node-{}", id),
+            federation_id: federation.map(str::to_string),
+        }
+    }
+
+    fn config(strategy: ShardAssignmentStrategy, min: usize, max: usize) -> ShardConfig {
+        ShardConfig {
+            shard_count: 2,
+            assignment_strategy: strategy,
+            dynamic_resizing: true,
+            min_nodes_per_shard: min,
+            max_nodes_per_shard: max,
+            ..ShardConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_federation_members_share_a_shard() {
+        let mut topology = ShardTopology::new(&config(ShardAssignmentStrategy::Federation, 1, 100));
+        for i in 0..10 {
+            topology.add_node(node(i, Some(if i % 2 == 0 { "coop-east" } else { "coop-west" })));
+        }
+        topology.add_node(node(10, None));
+
+        let east = topology.node_shard("node-0").unwrap();
+        assert!((0..10).step_by(2).all(|i| topology.node_shard(&format!("node-{}", i)) == Some(east)));
+        let shard = topology.shard(east).unwrap();
+        assert!(shard.federations.contains("coop-east"));
+        assert_eq!(shard.coordinator.as_deref(), Some("node-0"));
+
+        // Accounts are assigned by consistent hashing regardless of the strategy
+        assert_eq!(topology.account_shard("alice"), topology.account_shard("alice"));
+    }
+
+    #[test]
+    fn test_resizing_splits_and_merges_to_node_limits() {
+        let mut topology = ShardTopology::new(&config(ShardAssignmentStrategy::ConsistentHashing, 3, 12));
+        for i in 0..40 {
+            topology.add_node(node(i, None));
+        }
+
+        // Split until no shard is over the limit
+        while let Some(resize) = topology.plan_resize() {
+            assert!(matches!(resize, Resize::Split { .. }));
+            let plan = topology.apply(resize);
+            assert_eq!(plan.epoch, topology.epoch());
+            assert!(!plan.handoffs.is_empty());
+        }
+        assert!(topology.shard_ids().len() > 2);
+        assert!(topology.shards().iter().all(|s| s.nodes.len() <= 12));
+
+        // Losing most nodes merges shards back together
+        for i in 6..40 {
+            topology.remove_node(&format!("node-{}", i));
+        }
+        while let Some(resize) = topology.plan_resize() {
+            assert!(matches!(resize, Resize::Merge { .. }));
+            let plan = topology.apply(resize);
+
+            // Every moved account is covered by exactly one handoff from the merged shard
+            let Resize::Merge { shard } = plan.resize else { unreachable!() };
+            for handoff in &plan.handoffs {
+                assert_eq!(handoff.from, shard);
+            }
+        }
+        assert!(topology.shards().iter().all(|s| s.nodes.len() >= 3) || topology.shard_ids().len() == 1);
+    }
+}
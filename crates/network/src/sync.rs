@@ -5,9 +5,10 @@
 
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
+use futures::future::join_all;
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
 
@@ -21,6 +22,9 @@ use crate::{
     LedgerStateUpdate, IdentityAnnouncement, TransactionAnnouncement,
     ProposalAnnouncement, VoteAnnouncement, MessageHandler, PeerInfo
 };
+use crate::checkpoint::{CheckpointStore, PeerLag, SyncDomain, SyncProgress};
+use crate::fetch::{self, FetchRequest, ObjectFetcher, ObjectKind};
+use crate::reconcile::{self, IndexEntry, ReconcileClient, TimeRange};
 
 /// Sync configuration
 #[derive(Clone, Debug)]
@@ -37,6 +41,12 @@ pub struct SyncConfig {
     pub sync_timeout: u64,
     /// Maximum number of items to sync in one batch
     pub max_batch_size: usize,
+    /// How ledger transactions are synchronized during periodic syncs
+    pub ledger_sync_mode: LedgerSyncMode,
+    /// Number of sub-ranges a differing range is split into during reconciliation
+    pub reconciliation_fanout: usize,
+    /// How far before a peer's high-water mark reconciliation restarts (in seconds)
+    pub checkpoint_overlap: u64,
 }
 
 impl Default for SyncConfig {
@@ -48,10 +58,23 @@ impl Default for SyncConfig {
             sync_interval: 300, // 5 minutes
             sync_timeout: 30,   // 30 seconds
             max_batch_size: 100,
+            ledger_sync_mode: LedgerSyncMode::MerkleRange,
+            reconciliation_fanout: 16,
+            checkpoint_overlap: 3600, // 1 hour
         }
     }
 }
 
+/// Ledger synchronization mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerSyncMode {
+    /// Only fetch transactions as they are announced
+    Announcements,
+    /// Additionally reconcile transaction history with peers by comparing
+    /// Merkle roots over time ranges
+    MerkleRange,
+}
+
 /// Synchronization state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SyncState {
@@ -77,6 +100,10 @@ pub struct Synchronizer {
     network: Arc<dyn NetworkService>,
     /// Ledger
     ledger: Option<Arc<dyn Storage>>,
+    /// Fetcher used to pull full objects from announcing peers
+    fetcher: Option<Arc<dyn ObjectFetcher>>,
+    /// Client used for Merkle-range reconciliation with peers
+    reconciler: Option<Arc<dyn ReconcileClient>>,
     /// Configuration
     config: SyncConfig,
     /// Current sync state
@@ -85,6 +112,8 @@ pub struct Synchronizer {
     running: Arc<RwLock<bool>>,
     /// Latest known state hashes by peer
     peer_states: Arc<RwLock<HashMap<String, PeerState>>>,
+    /// Persistent checkpoints and progress counters
+    tracker: ProgressTracker,
     /// Command channel
     command_tx: mpsc::Sender<SyncCommand>,
     /// Command receiver
@@ -108,6 +137,48 @@ struct PeerState {
     known_proposals: HashSet<String>,
 }
 
+/// Counters reported through `SyncProgress`
+#[derive(Debug, Default)]
+struct SyncCounters {
+    /// Items fetched since the synchronizer started
+    items_fetched: u64,
+    /// Items known to be missing and not yet fetched
+    items_remaining: u64,
+}
+
+/// Persistent checkpoints and in-memory progress counters
+#[derive(Clone)]
+struct ProgressTracker {
+    /// Checkpoint store
+    checkpoints: CheckpointStore,
+    /// Progress counters
+    counters: Arc<RwLock<SyncCounters>>,
+}
+
+impl ProgressTracker {
+    /// Record items found missing
+    async fn record_missing(&self, count: usize) {
+        self.counters.write().await.items_remaining += count as u64;
+    }
+    
+    /// Record items fetched, optionally clearing them from the remaining count
+    async fn record_fetched(&self, count: usize, were_missing: bool) {
+        let mut counters = self.counters.write().await;
+        counters.items_fetched += count as u64;
+        if were_missing {
+            counters.items_remaining = counters.items_remaining.saturating_sub(count as u64);
+        }
+    }
+}
+
+/// Get the current time in seconds since the epoch
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Synchronization commands
 enum SyncCommand {
     /// Start synchronization
@@ -136,15 +207,22 @@ impl Synchronizer {
         config: SyncConfig,
     ) -> Self {
         let (command_tx, command_rx) = mpsc::channel(100);
+        let tracker = ProgressTracker {
+            checkpoints: CheckpointStore::new(storage.clone()),
+            counters: Arc::new(RwLock::new(SyncCounters::default())),
+        };
         
         Self {
             storage,
             network,
             ledger: None,
+            fetcher: None,
+            reconciler: None,
             config,
             state: Arc::new(RwLock::new(SyncState::Idle)),
             running: Arc::new(RwLock::new(false)),
             peer_states: Arc::new(RwLock::new(HashMap::new())),
+            tracker,
             command_tx,
             command_rx: Arc::new(RwLock::new(Some(command_rx))),
         }
@@ -155,6 +233,16 @@ impl Synchronizer {
         self.ledger = Some(ledger);
     }
     
+    /// Set the fetcher used to request full objects from announcing peers
+    pub fn set_fetcher(&mut self, fetcher: Arc<dyn ObjectFetcher>) {
+        self.fetcher = Some(fetcher);
+    }
+    
+    /// Set the client used for Merkle-range reconciliation with peers
+    pub fn set_reconciler(&mut self, reconciler: Arc<dyn ReconcileClient>) {
+        self.reconciler = Some(reconciler);
+    }
+    
     /// Start the synchronizer
     pub async fn start(&self) -> NetworkResult<()> {
         // Check if already running
@@ -177,6 +265,18 @@ impl Synchronizer {
             .await
             .map_err(|e| NetworkError::InternalError(format!("Failed to send start command: {}", e)))?;
         
+        // Resume any sync that was interrupted before the last shutdown
+        let pending: usize = self.tracker.checkpoints.load_peers().await?
+            .iter()
+            .map(|checkpoint| checkpoint.pending.len())
+            .sum();
+        self.tracker.counters.write().await.items_remaining = pending as u64;
+        
+        if self.tracker.checkpoints.has_interrupted_sync().await? {
+            info!("Resuming interrupted sync from checkpoint ({} items pending)", pending);
+            self.sync_now().await?;
+        }
+        
         info!("Synchronizer started");
         
         Ok(())
@@ -217,6 +317,27 @@ impl Synchronizer {
         *self.state.read().await
     }
     
+    /// Get a report of the sync progress
+    pub async fn get_progress(&self) -> NetworkResult<SyncProgress> {
+        let (items_fetched, items_remaining) = {
+            let counters = self.tracker.counters.read().await;
+            (counters.items_fetched, counters.items_remaining)
+        };
+        
+        let peers = self.tracker.checkpoints.load_peers().await?
+            .iter()
+            .map(PeerLag::from)
+            .collect();
+        
+        Ok(SyncProgress {
+            state: self.get_state().await,
+            items_fetched,
+            items_remaining,
+            peers,
+            domains: self.tracker.checkpoints.load_domains().await?,
+        })
+    }
+    
     /// Register message handlers with the network service
     async fn register_message_handlers(&self) -> NetworkResult<()> {
         let command_tx = self.command_tx.clone();
@@ -270,6 +391,9 @@ impl Synchronizer {
         let command_rx_lock = self.command_rx.clone();
         let storage = self.storage.clone();
         let network = self.network.clone();
+        let fetcher = self.fetcher.clone();
+        let reconciler = self.reconciler.clone();
+        let tracker = self.tracker.clone();
         
         let mut command_rx = {
             let mut rx_guard = command_rx_lock.write().await;
@@ -291,7 +415,9 @@ impl Synchronizer {
                         // It's time for a periodic sync
                         if config.sync_ledger || config.sync_governance || config.sync_identities {
                             debug!("Running periodic synchronization");
-                            Self::perform_sync(&network, &storage, &state, &peer_states, &config).await;
+                            Self::perform_sync(
+                                &network, &storage, &state, &tracker, &config, &fetcher, &reconciler
+                            ).await;
                         }
                     }
                     
@@ -307,7 +433,9 @@ impl Synchronizer {
                             }
                             SyncCommand::SyncNow => {
                                 debug!("Received sync now command");
-                                Self::perform_sync(&network, &storage, &state, &peer_states, &config).await;
+                                Self::perform_sync(
+                                &network, &storage, &state, &tracker, &config, &fetcher, &reconciler
+                            ).await;
                             }
                             SyncCommand::LedgerStateUpdate(peer_id, update) => {
                                 debug!("Received ledger state update from {}", peer_id);
@@ -318,25 +446,25 @@ impl Synchronizer {
                             SyncCommand::TransactionUpdate(peer_id, announcement) => {
                                 debug!("Received transaction announcement from {}", peer_id);
                                 Self::process_transaction_announcement(
-                                    &peer_id, announcement, &peer_states, &storage
+                                    &peer_id, announcement, &peer_states, &storage, &fetcher, &tracker
                                 ).await;
                             }
                             SyncCommand::IdentityUpdate(peer_id, announcement) => {
                                 debug!("Received identity announcement from {}", peer_id);
                                 Self::process_identity_announcement(
-                                    &peer_id, announcement, &storage
+                                    &peer_id, announcement, &storage, &fetcher, &tracker
                                 ).await;
                             }
                             SyncCommand::ProposalUpdate(peer_id, announcement) => {
                                 debug!("Received proposal announcement from {}", peer_id);
                                 Self::process_proposal_announcement(
-                                    &peer_id, announcement, &peer_states, &storage
+                                    &peer_id, announcement, &peer_states, &storage, &fetcher, &tracker
                                 ).await;
                             }
                             SyncCommand::VoteUpdate(peer_id, announcement) => {
                                 debug!("Received vote announcement from {}", peer_id);
                                 Self::process_vote_announcement(
-                                    &peer_id, announcement, &storage
+                                    &peer_id, announcement, &storage, &fetcher, &tracker
                                 ).await;
                             }
                         }
@@ -349,12 +477,17 @@ impl Synchronizer {
     }
     
     /// Perform synchronization
+    ///
+    /// Each domain is marked in progress in its checkpoint while it syncs and
+    /// its state hash is recorded once it completes.
     async fn perform_sync(
         network: &Arc<dyn NetworkService>,
         storage: &Arc<dyn Storage>,
         state: &Arc<RwLock<SyncState>>,
-        peer_states: &Arc<RwLock<HashMap<String, PeerState>>>,
+        tracker: &ProgressTracker,
         config: &SyncConfig,
+        fetcher: &Option<Arc<dyn ObjectFetcher>>,
+        reconciler: &Option<Arc<dyn ReconcileClient>>,
     ) {
         // Update sync state
         *state.write().await = SyncState::SyncingIdentities;
@@ -382,30 +515,64 @@ impl Synchronizer {
         
         // For now, just update the sync state as a placeholder
         if config.sync_identities {
+            Self::begin_domain(tracker, SyncDomain::Identity).await;
             *state.write().await = SyncState::SyncingIdentities;
             tokio::time::sleep(Duration::from_millis(100)).await;
+            Self::complete_domain(tracker, SyncDomain::Identity).await;
         }
         
         if config.sync_ledger {
+            Self::begin_domain(tracker, SyncDomain::Ledger).await;
             *state.write().await = SyncState::SyncingTransactions;
-            tokio::time::sleep(Duration::from_millis(100)).await;
+            
+            if config.ledger_sync_mode == LedgerSyncMode::MerkleRange {
+                if let (Some(reconciler), Some(fetcher)) = (reconciler, fetcher) {
+                    for peer in &peers {
+                        Self::reconcile_transactions(
+                            &peer.peer_id, reconciler, fetcher, storage, tracker, config
+                        ).await;
+                    }
+                } else {
+                    debug!("Merkle-range sync needs a reconciler and a fetcher, skipping");
+                }
+            } else {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
             
             *state.write().await = SyncState::SyncingAccounts;
             tokio::time::sleep(Duration::from_millis(100)).await;
+            Self::complete_domain(tracker, SyncDomain::Ledger).await;
         }
         
         if config.sync_governance {
+            Self::begin_domain(tracker, SyncDomain::Governance).await;
             *state.write().await = SyncState::SyncingProposals;
             tokio::time::sleep(Duration::from_millis(100)).await;
             
             *state.write().await = SyncState::SyncingVotes;
             tokio::time::sleep(Duration::from_millis(100)).await;
+            Self::complete_domain(tracker, SyncDomain::Governance).await;
         }
         
         // Return to idle state
         *state.write().await = SyncState::Idle;
     }
     
+    /// Mark a domain sync as started in its checkpoint
+    async fn begin_domain(tracker: &ProgressTracker, domain: SyncDomain) {
+        if let Err(e) = tracker.checkpoints.begin_domain(domain, unix_now()).await {
+            warn!("Failed to checkpoint start of {} sync: {}", domain.name(), e);
+        }
+    }
+    
+    /// Record the completion of a domain sync in its checkpoint
+    async fn complete_domain(tracker: &ProgressTracker, domain: SyncDomain) {
+        match tracker.checkpoints.complete_domain(domain, unix_now()).await {
+            Ok(checkpoint) => debug!("Checkpointed {} sync at {:?}", domain.name(), checkpoint.state_hash),
+            Err(e) => warn!("Failed to checkpoint {} sync: {}", domain.name(), e),
+        }
+    }
+    
     /// Process a ledger state update from a peer
     async fn process_ledger_state_update(
         peer_id: &str,
@@ -444,6 +611,8 @@ impl Synchronizer {
         announcement: TransactionAnnouncement,
         peer_states: &Arc<RwLock<HashMap<String, PeerState>>>,
         storage: &Arc<dyn Storage>,
+        fetcher: &Option<Arc<dyn ObjectFetcher>>,
+        tracker: &ProgressTracker,
     ) {
         // Track the transaction
         {
@@ -466,7 +635,15 @@ impl Synchronizer {
             state.known_transactions.insert(announcement.transaction_id.clone());
         }
         
-        // TODO: Request the full transaction and validate it
+        if let Err(e) = tracker.checkpoints.observe_peer(peer_id, announcement.timestamp).await {
+            warn!("Failed to update checkpoint for {}: {}", peer_id, e);
+        }
+        
+        let request = FetchRequest::new(ObjectKind::Transaction, announcement.transaction_id.clone());
+        let index_entry = IndexEntry::from(&announcement);
+        Self::fetch_announced_object(
+            peer_id, request, Some(announcement.data_hash.clone()), Some(index_entry), storage, fetcher, tracker
+        );
         
         debug!("Received transaction announcement from {}: tx_id={}", 
                peer_id, announcement.transaction_id);
@@ -477,8 +654,13 @@ impl Synchronizer {
         peer_id: &str,
         announcement: IdentityAnnouncement,
         storage: &Arc<dyn Storage>,
+        fetcher: &Option<Arc<dyn ObjectFetcher>>,
+        tracker: &ProgressTracker,
     ) {
-        // TODO: Request the full identity and validate it
+        // Identity announcements carry no data hash, so the fetched record
+        // is only bound to the announcing peer
+        let request = FetchRequest::new(ObjectKind::Identity, announcement.identity_id.clone());
+        Self::fetch_announced_object(peer_id, request, None, None, storage, fetcher, tracker);
         
         debug!("Received identity announcement from {}: identity_id={}", 
                peer_id, announcement.identity_id);
@@ -490,6 +672,8 @@ impl Synchronizer {
         announcement: ProposalAnnouncement,
         peer_states: &Arc<RwLock<HashMap<String, PeerState>>>,
         storage: &Arc<dyn Storage>,
+        fetcher: &Option<Arc<dyn ObjectFetcher>>,
+        tracker: &ProgressTracker,
     ) {
         // Track the proposal
         {
@@ -512,7 +696,10 @@ impl Synchronizer {
             state.known_proposals.insert(announcement.proposal_id.clone());
         }
         
-        // TODO: Request the full proposal and validate it
+        let request = FetchRequest::new(ObjectKind::Proposal, announcement.proposal_id.clone());
+        Self::fetch_announced_object(
+            peer_id, request, Some(announcement.data_hash.clone()), None, storage, fetcher, tracker
+        );
         
         debug!("Received proposal announcement from {}: proposal_id={}", 
                peer_id, announcement.proposal_id);
@@ -523,12 +710,186 @@ impl Synchronizer {
         peer_id: &str,
         announcement: VoteAnnouncement,
         storage: &Arc<dyn Storage>,
+        fetcher: &Option<Arc<dyn ObjectFetcher>>,
+        tracker: &ProgressTracker,
     ) {
-        // TODO: Request the full vote and validate it
+        let request = FetchRequest::vote(&announcement.proposal_id, &announcement.voter_id);
+        Self::fetch_announced_object(
+            peer_id, request, Some(announcement.data_hash.clone()), None, storage, fetcher, tracker
+        );
         
         debug!("Received vote announcement from {}: proposal_id={}, voter={}", 
                peer_id, announcement.proposal_id, announcement.voter_id);
     }
+    
+    /// Fetch an announced object from the announcing peer in the background
+    ///
+    /// The fetched data is verified against `expected_hash` (when present)
+    /// before being persisted, so a node that missed the original data can
+    /// catch up from any peer that announces it. Transactions are also added
+    /// to the reconciliation index via `index_entry`.
+    fn fetch_announced_object(
+        peer_id: &str,
+        request: FetchRequest,
+        expected_hash: Option<String>,
+        index_entry: Option<IndexEntry>,
+        storage: &Arc<dyn Storage>,
+        fetcher: &Option<Arc<dyn ObjectFetcher>>,
+        tracker: &ProgressTracker,
+    ) {
+        let fetcher = match fetcher {
+            Some(fetcher) => fetcher.clone(),
+            None => {
+                debug!("No object fetcher configured, not fetching {}", request.storage_key());
+                return;
+            }
+        };
+        
+        let storage = storage.clone();
+        let tracker = tracker.clone();
+        let peer_id = peer_id.to_string();
+        
+        tokio::spawn(async move {
+            let key = request.storage_key();
+            match fetch::fetch_and_store(
+                fetcher.as_ref(),
+                storage.as_ref(),
+                &peer_id,
+                request,
+                expected_hash.as_deref(),
+            ).await {
+                Ok(fetched) => {
+                    if fetched {
+                        info!("Fetched {} from {}", key, peer_id);
+                        tracker.record_fetched(1, false).await;
+                    } else {
+                        debug!("Already have {}", key);
+                    }
+                    
+                    if let Some(entry) = index_entry {
+                        if let Err(e) = reconcile::record_transaction(storage.as_ref(), entry).await {
+                            warn!("Failed to index {}: {}", key, e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to fetch {} from {}: {}", key, peer_id, e),
+            }
+        });
+    }
+    
+    /// Reconcile transaction history with a peer and fetch what is missing
+    ///
+    /// Reconciliation resumes from the peer's checkpoint: entries left pending
+    /// by an interrupted sync are fetched first, and only history after the
+    /// high-water mark (minus `checkpoint_overlap`) is compared. Missing
+    /// transactions are fetched in batches of `max_batch_size`, each verified
+    /// against the data hash from the peer's index, and the checkpoint is
+    /// persisted after every batch.
+    async fn reconcile_transactions(
+        peer_id: &str,
+        reconciler: &Arc<dyn ReconcileClient>,
+        fetcher: &Arc<dyn ObjectFetcher>,
+        storage: &Arc<dyn Storage>,
+        tracker: &ProgressTracker,
+        config: &SyncConfig,
+    ) {
+        let now = unix_now();
+        
+        let mut checkpoint = match tracker.checkpoints.load_peer(peer_id).await {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                warn!("Failed to load checkpoint for {}: {}", peer_id, e);
+                return;
+            }
+        };
+        
+        let range = if checkpoint.last_synced.is_some() {
+            TimeRange::since(checkpoint.high_water_mark.saturating_sub(config.checkpoint_overlap), now)
+        } else {
+            TimeRange::up_to(now)
+        };
+        
+        let reconciled = match reconcile::find_missing(
+            reconciler.as_ref(),
+            storage.as_ref(),
+            peer_id,
+            range,
+            config.reconciliation_fanout,
+            config.max_batch_size,
+        ).await {
+            Ok(missing) => {
+                let before = checkpoint.pending.len();
+                checkpoint.add_pending(missing);
+                tracker.record_missing(checkpoint.pending.len() - before).await;
+                true
+            }
+            Err(e) => {
+                warn!("Failed to reconcile transactions with {}: {}", peer_id, e);
+                if checkpoint.pending.is_empty() {
+                    return;
+                }
+                false
+            }
+        };
+        
+        if checkpoint.pending.is_empty() {
+            debug!("Transactions in sync with {}", peer_id);
+        } else {
+            info!("Fetching {} missing transactions from {}", checkpoint.pending.len(), peer_id);
+            
+            if let Err(e) = tracker.checkpoints.save_peer(&checkpoint).await {
+                warn!("Failed to save checkpoint for {}: {}", peer_id, e);
+            }
+        }
+        
+        let pending = checkpoint.pending.clone();
+        for batch in pending.chunks(config.max_batch_size.max(1)) {
+            let fetches = batch.iter().map(|entry| async move {
+                let request = FetchRequest::new(ObjectKind::Transaction, entry.transaction_id.clone());
+                let result = fetch::fetch_and_store(
+                    fetcher.as_ref(),
+                    storage.as_ref(),
+                    peer_id,
+                    request,
+                    Some(&entry.data_hash),
+                ).await;
+                (entry, result)
+            });
+            
+            let mut fetched = 0;
+            for (entry, result) in join_all(fetches).await {
+                match result {
+                    Ok(_) => {
+                        if let Err(e) = reconcile::record_transaction(storage.as_ref(), entry.clone()).await {
+                            warn!("Failed to index transaction {}: {}", entry.transaction_id, e);
+                            continue;
+                        }
+                        checkpoint.remove_pending(&entry.transaction_id);
+                        fetched += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to fetch transaction {} from {}: {}", entry.transaction_id, peer_id, e);
+                    }
+                }
+            }
+            
+            tracker.record_fetched(fetched, true).await;
+            if let Err(e) = tracker.checkpoints.save_peer(&checkpoint).await {
+                warn!("Failed to save checkpoint for {}: {}", peer_id, e);
+            }
+        }
+        
+        // Only advance the high-water mark once the range was compared and
+        // nothing is left to fetch, so failed ranges and entries are retried
+        // from the same checkpoint
+        if reconciled && checkpoint.pending.is_empty() {
+            checkpoint.high_water_mark = now;
+            checkpoint.last_synced = Some(now);
+            if let Err(e) = tracker.checkpoints.save_peer(&checkpoint).await {
+                warn!("Failed to save checkpoint for {}: {}", peer_id, e);
+            }
+        }
+    }
 }
 
 /// Handler for ledger state updates
@@ -679,4 +1040,66 @@ impl MessageHandler for VoteHandler {
         
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::FetchResponse;
+    use crate::reconcile::{ReconcileRequest, ReconcileResponse};
+    use crate::tests::MockStorage;
+
+    /// Peer whose reconciliation fails but who serves fetches
+    struct FailingPeer {
+        remote: MockStorage,
+    }
+
+    #[async_trait]
+    impl ReconcileClient for FailingPeer {
+        async fn reconcile_request(&self, _peer_id: &str, _request: ReconcileRequest) -> NetworkResult<ReconcileResponse> {
+            Err(NetworkError::ConnectionError("timed out".to_string()))
+        }
+    }
+
+    #[async_trait]
+    impl ObjectFetcher for FailingPeer {
+        async fn fetch_object(&self, _peer_id: &str, request: FetchRequest) -> NetworkResult<FetchResponse> {
+            Ok(fetch::serve_fetch_request(&self.remote, request).await)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_reconciliation_does_not_advance_checkpoint() {
+        let remote = MockStorage::new();
+        remote.put("ledger/transactions/tx1", b"transaction body").await.unwrap();
+        let peer = Arc::new(FailingPeer { remote });
+        let storage: Arc<dyn Storage> = Arc::new(MockStorage::new());
+        let tracker = ProgressTracker {
+            checkpoints: CheckpointStore::new(storage.clone()),
+            counters: Arc::new(RwLock::new(SyncCounters::default())),
+        };
+
+        // An entry left pending by an interrupted sync
+        let mut checkpoint = tracker.checkpoints.load_peer("peer1").await.unwrap();
+        checkpoint.high_water_mark = 1_000;
+        checkpoint.last_synced = Some(1_000);
+        checkpoint.add_pending(vec![IndexEntry {
+            transaction_id: "tx1".to_string(),
+            timestamp: 1_500,
+            data_hash: fetch::compute_data_hash(b"transaction body"),
+        }]);
+        tracker.checkpoints.save_peer(&checkpoint).await.unwrap();
+
+        let reconciler: Arc<dyn ReconcileClient> = peer.clone();
+        let fetcher: Arc<dyn ObjectFetcher> = peer;
+        Synchronizer::reconcile_transactions(
+            "peer1", &reconciler, &fetcher, &storage, &tracker, &SyncConfig::default()
+        ).await;
+
+        // The pending entry was fetched, but the range after the checkpoint was never compared
+        let checkpoint = tracker.checkpoints.load_peer("peer1").await.unwrap();
+        assert!(checkpoint.pending.is_empty());
+        assert!(storage.exists(&FetchRequest::new(ObjectKind::Transaction, "tx1").storage_key()).await.unwrap());
+        assert_eq!((checkpoint.high_water_mark, checkpoint.last_synced), (1_000, Some(1_000)));
+    }
+}
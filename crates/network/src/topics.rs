@@ -0,0 +1,212 @@
+//! Gossipsub topic naming for global, federation and shard scoped traffic
+//!
+//! Topics are named `icn/<kind>/v1` for network-wide traffic,
+//! `icn/<federation_id>/<kind>/v1` for a federation and
+//! `icn/<federation_id>/shard/<shard_id>/<kind>/v1` for a shard of a
+//! federation, so nodes only receive traffic for the scopes they join.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{NetworkError, NetworkMessage, NetworkResult};
+
+/// Prefix shared by all ICN topics
+const TOPIC_PREFIX: &str = "icn";
+/// Topic schema version
+const TOPIC_VERSION: &str = "v1";
+/// Path segment separating a federation from its shard
+const SHARD_SEGMENT: &str = "shard";
+
+/// Kind of traffic carried on a topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TopicKind {
+    /// Identity announcements
+    Identity,
+    /// Transaction announcements
+    Transactions,
+    /// Ledger state updates
+    Ledger,
+    /// Governance proposals and votes
+    Governance,
+    /// Reputation evidence
+    Reputation,
+    /// Custom messages
+    Custom,
+}
+
+impl TopicKind {
+    /// All topic kinds, subscribed together when joining a scope
+    pub const ALL: [TopicKind; 6] = [
+        TopicKind::Identity,
+        TopicKind::Transactions,
+        TopicKind::Ledger,
+        TopicKind::Governance,
+        TopicKind::Reputation,
+        TopicKind::Custom,
+    ];
+
+    /// Get the topic kind a message is published on
+    pub fn for_message(message: &NetworkMessage) -> Self {
+        match message {
+            NetworkMessage::IdentityAnnouncement(_) => TopicKind::Identity,
+            NetworkMessage::TransactionAnnouncement(_) => TopicKind::Transactions,
+            NetworkMessage::LedgerStateUpdate(_) => TopicKind::Ledger,
+            NetworkMessage::ProposalAnnouncement(_) | NetworkMessage::VoteAnnouncement(_) => TopicKind::Governance,
+            NetworkMessage::ReputationEvidence(_) => TopicKind::Reputation,
+            NetworkMessage::Custom(_) => TopicKind::Custom,
+        }
+    }
+
+    /// Get the topic path segment for this kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TopicKind::Identity => "identity",
+            TopicKind::Transactions => "transactions",
+            TopicKind::Ledger => "ledger",
+            TopicKind::Governance => "governance",
+            TopicKind::Reputation => "reputation",
+            TopicKind::Custom => "custom",
+        }
+    }
+
+    /// Parse a topic path segment
+    fn parse(segment: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == segment)
+    }
+}
+
+/// Scope a topic is restricted to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TopicScope {
+    /// Network-wide traffic
+    Global,
+    /// Traffic for a single federation
+    Federation(String),
+    /// Traffic for a shard of a federation
+    Shard {
+        /// Federation the shard belongs to
+        federation_id: String,
+        /// Shard identifier
+        shard_id: u32,
+    },
+}
+
+impl TopicScope {
+    /// Create a federation scope, rejecting IDs that can't be used in a topic name
+    pub fn federation(federation_id: &str) -> NetworkResult<Self> {
+        validate_federation_id(federation_id)?;
+        Ok(TopicScope::Federation(federation_id.to_string()))
+    }
+
+    /// Create a shard scope, rejecting IDs that can't be used in a topic name
+    pub fn shard(federation_id: &str, shard_id: u32) -> NetworkResult<Self> {
+        validate_federation_id(federation_id)?;
+        Ok(TopicScope::Shard {
+            federation_id: federation_id.to_string(),
+            shard_id,
+        })
+    }
+
+    /// Get the federation this scope belongs to, if any
+    pub fn federation_id(&self) -> Option<&str> {
+        match self {
+            TopicScope::Global => None,
+            TopicScope::Federation(federation_id) => Some(federation_id),
+            TopicScope::Shard { federation_id, .. } => Some(federation_id),
+        }
+    }
+
+    /// Get the topic name for a kind of traffic in this scope
+    pub fn topic_name(&self, kind: TopicKind) -> String {
+        match self {
+            TopicScope::Global => format!("{}/{}/{}", TOPIC_PREFIX, kind.as_str(), TOPIC_VERSION),
+            TopicScope::Federation(federation_id) => {
+                format!("{}/{}/{}/{}", TOPIC_PREFIX, federation_id, kind.as_str(), TOPIC_VERSION)
+            }
+            TopicScope::Shard { federation_id, shard_id } => format!(
+                "{}/{}/{}/{}/{}/{}",
+                TOPIC_PREFIX, federation_id, SHARD_SEGMENT, shard_id, kind.as_str(), TOPIC_VERSION
+            ),
+        }
+    }
+
+    /// Get the names of all topics in this scope
+    pub fn topic_names(&self) -> Vec<String> {
+        TopicKind::ALL.iter().map(|kind| self.topic_name(*kind)).collect()
+    }
+
+    /// Parse a topic name into its scope and kind
+    pub fn parse(topic: &str) -> Option<(TopicScope, TopicKind)> {
+        let segments: Vec<&str> = topic.split('/').collect();
+        if segments.first() != Some(&TOPIC_PREFIX) || segments.last() != Some(&TOPIC_VERSION) {
+            return None;
+        }
+
+        match segments[1..segments.len() - 1] {
+            [kind] => Some((TopicScope::Global, TopicKind::parse(kind)?)),
+            [federation_id, kind] => {
+                Some((TopicScope::federation(federation_id).ok()?, TopicKind::parse(kind)?))
+            }
+            [federation_id, SHARD_SEGMENT, shard_id, kind] => Some((
+                TopicScope::shard(federation_id, shard_id.parse().ok()?).ok()?,
+                TopicKind::parse(kind)?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TopicScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopicScope::Global => write!(f, "global"),
+            TopicScope::Federation(federation_id) => write!(f, "{}", federation_id),
+            TopicScope::Shard { federation_id, shard_id } => write!(f, "{}/shard/{}", federation_id, shard_id),
+        }
+    }
+}
+
+/// Check that a federation ID forms a single topic path segment
+fn validate_federation_id(federation_id: &str) -> NetworkResult<()> {
+    if federation_id.is_empty() || federation_id.contains('/') || federation_id.chars().any(char::is_whitespace) {
+        return Err(NetworkError::InvalidTopic(format!("invalid federation ID '{}'", federation_id)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_names_round_trip() {
+        let scopes = [
+            TopicScope::Global,
+            TopicScope::federation("coop-east").unwrap(),
+            TopicScope::shard("coop-east", 3).unwrap(),
+        ];
+
+        assert_eq!(TopicScope::Global.topic_name(TopicKind::Ledger), "icn/ledger/v1");
+        assert_eq!(scopes[1].topic_name(TopicKind::Ledger), "icn/coop-east/ledger/v1");
+        assert_eq!(scopes[2].topic_name(TopicKind::Governance), "icn/coop-east/shard/3/governance/v1");
+
+        for scope in &scopes {
+            for kind in TopicKind::ALL {
+                assert_eq!(TopicScope::parse(&scope.topic_name(kind)), Some((scope.clone(), kind)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_topics_and_federation_ids() {
+        assert!(TopicScope::federation("").is_err());
+        assert!(TopicScope::federation("a/b").is_err());
+        assert!(TopicScope::shard("coop east", 1).is_err());
+
+        assert_eq!(TopicScope::parse("ledger"), None);
+        assert_eq!(TopicScope::parse("icn/ledger/v2"), None);
+        assert_eq!(TopicScope::parse("icn/coop/unknown/v1"), None);
+        assert_eq!(TopicScope::parse("icn/coop/shard/x/ledger/v1"), None);
+    }
+}
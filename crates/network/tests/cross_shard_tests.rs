@@ -0,0 +1,316 @@
+//! Cross-shard transactions between two shards running in-process
+//!
+//! Every test checks that no credit is created or destroyed while messages
+//! are lost and nodes crash in the middle of the two-phase commit.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use icn_core::storage::MemoryStorage;
+use icn_network::sharding::{
+    CrossShardTransaction, NodeInfo, ShardConfig, ShardId, ShardManager, ShardMessage, ShardOperation,
+    ShardStateMachine, ShardTopology, ShardTransaction, ShardTransport, TransactionOutcome, TransactionRoute,
+};
+use icn_network::topics::TopicScope;
+use icn_network::{NetworkError, NetworkMessage, NetworkResult};
+
+/// Credit each test starts with on each shard
+const INITIAL_BALANCE: i64 = 100;
+
+/// Balances stored as little-endian integers, changed by signed deltas
+struct Balances;
+
+impl ShardStateMachine for Balances {
+    fn apply(&self, _account: &str, state: Option<&[u8]>, operation: &[u8]) -> NetworkResult<Vec<u8>> {
+        let balance = state.map_or(Ok(0), decode)? + decode(operation)?;
+        if balance < 0 {
+            return Err(NetworkError::ServiceError("Insufficient balance".to_string()));
+        }
+        Ok(balance.to_le_bytes().to_vec())
+    }
+}
+
+fn decode(bytes: &[u8]) -> NetworkResult<i64> {
+    bytes.try_into()
+        .map(i64::from_le_bytes)
+        .map_err(|_| NetworkError::MessageError("Invalid balance".to_string()))
+}
+
+/// Gossip between in-process nodes, delivered one message at a time
+#[derive(Default)]
+struct Hub {
+    /// Messages in flight with their sender and shard
+    queue: Mutex<VecDeque<(String, ShardId, NetworkMessage)>>,
+}
+
+struct HubTransport {
+    node_id: String,
+    hub: Arc<Hub>,
+    joined: Mutex<BTreeSet<ShardId>>,
+}
+
+#[async_trait]
+impl ShardTransport for HubTransport {
+    async fn join_shard(&self, _federation_id: &str, shard_id: ShardId) -> NetworkResult<()> {
+        self.joined.lock().await.insert(shard_id);
+        Ok(())
+    }
+
+    async fn leave_shard(&self, _federation_id: &str, shard_id: ShardId) -> NetworkResult<()> {
+        self.joined.lock().await.remove(&shard_id);
+        Ok(())
+    }
+
+    async fn broadcast_to(&self, scope: TopicScope, message: NetworkMessage) -> NetworkResult<()> {
+        let TopicScope::Shard { shard_id, .. } = scope else {
+            return Err(NetworkError::InvalidTopic("Expected a shard scope".to_string()));
+        };
+        self.hub.queue.lock().await.push_back((self.node_id.clone(), shard_id, message));
+        Ok(())
+    }
+}
+
+struct Node {
+    manager: Arc<ShardManager>,
+    transport: Arc<HubTransport>,
+    storage: Arc<MemoryStorage>,
+}
+
+/// Two nodes, one per shard, each holding one account
+struct Cluster {
+    hub: Arc<Hub>,
+    nodes: Vec<Node>,
+    node_ids: Vec<String>,
+    accounts: Vec<String>,
+}
+
+impl Cluster {
+    async fn new() -> Self {
+        // Pick a node and an account for each shard
+        let mut topology = ShardTopology::new(&config());
+        let names: Vec<String> = (0..100).map(|i| format!("node-{}", i)).collect();
+        for name in &names {
+            topology.add_node(NodeInfo { node_id: name.clone(), federation_id: None });
+        }
+        let node_ids: Vec<String> = (0..2)
+            .map(|shard| names.iter().find(|n| topology.node_shard(n) == Some(shard)).unwrap().clone())
+            .collect();
+        let accounts: Vec<String> = (0..2)
+            .map(|shard| (0..).map(|i| format!("account-{}", i)).find(|a| topology.account_shard(a) == Some(shard)).unwrap())
+            .collect();
+
+        let hub = Arc::new(Hub::default());
+        let mut cluster = Self { hub, nodes: Vec::new(), node_ids, accounts };
+        for index in 0..2 {
+            let node = cluster.start_node(index, Arc::new(MemoryStorage::new())).await;
+            node.manager.put_state(&cluster.accounts[index], &INITIAL_BALANCE.to_le_bytes()).await.unwrap();
+            cluster.nodes.push(node);
+        }
+        cluster.deliver().await;
+        cluster
+    }
+
+    async fn start_node(&self, index: usize, storage: Arc<MemoryStorage>) -> Node {
+        let node_id = self.node_ids[index].clone();
+        let transport = Arc::new(HubTransport {
+            node_id: node_id.clone(),
+            hub: self.hub.clone(),
+            joined: Mutex::new(BTreeSet::new()),
+        });
+        let local = NodeInfo { node_id, federation_id: None };
+        let manager = ShardManager::new(config(), local, transport.clone(), storage.clone()).await.unwrap();
+
+        let peer = NodeInfo { node_id: self.node_ids[1 - index].clone(), federation_id: None };
+        manager.add_node(peer).await.unwrap();
+        manager.set_state_machine(Arc::new(Balances)).await;
+        manager.start_synchronization().await.unwrap();
+        Node { manager, transport, storage }
+    }
+
+    /// Crash a node, losing everything it held in memory, and start it again
+    async fn restart(&mut self, index: usize) {
+        let storage = self.nodes[index].storage.clone();
+        let node = self.start_node(index, storage).await;
+        self.nodes[index] = node;
+    }
+
+    /// Deliver the next message in flight that has a recipient, returning `false` if there was none
+    async fn deliver_one(&self) -> bool {
+        loop {
+            let Some((sender, shard, message)) = self.hub.queue.lock().await.pop_front() else {
+                return false;
+            };
+
+            let mut delivered = false;
+            for (node_id, node) in self.node_ids.iter().zip(&self.nodes) {
+                if *node_id != sender && node.transport.joined.lock().await.contains(&shard) {
+                    let message = ShardMessage::from_network_message(&message).unwrap();
+                    node.manager.handle_message(&sender, message).await.unwrap();
+                    delivered = true;
+                }
+            }
+            if delivered {
+                return true;
+            }
+        }
+    }
+
+    async fn deliver(&self) {
+        while self.deliver_one().await {}
+    }
+
+    /// Lose every message in flight
+    async fn lose_messages(&self) {
+        self.hub.queue.lock().await.clear();
+    }
+
+    fn transfer(&self, from: usize, to: usize, amount: i64) -> CrossShardTransaction {
+        CrossShardTransaction::new(vec![
+            ShardOperation { account: self.accounts[from].clone(), operation: (-amount).to_le_bytes().to_vec() },
+            ShardOperation { account: self.accounts[to].clone(), operation: amount.to_le_bytes().to_vec() },
+        ])
+    }
+
+    async fn balance(&self, index: usize) -> i64 {
+        let state = self.nodes[index].manager.get_state(&self.accounts[index]).await.unwrap();
+        decode(&state.unwrap()).unwrap()
+    }
+
+    async fn balances(&self) -> [i64; 2] {
+        [self.balance(0).await, self.balance(1).await]
+    }
+
+    /// Check that every transaction is resolved and no credit appeared or vanished
+    async fn assert_settled(&self) {
+        for node in &self.nodes {
+            assert!(node.manager.in_doubt_transactions().await.is_empty());
+        }
+        let [a, b] = self.balances().await;
+        assert_eq!(a + b, 2 * INITIAL_BALANCE);
+    }
+}
+
+fn config() -> ShardConfig {
+    ShardConfig {
+        shard_count: 2,
+        dynamic_resizing: false,
+        min_nodes_per_shard: 1,
+        // Timeouts are only checked when a test asks for it
+        transaction_timeout: Duration::ZERO,
+        ..ShardConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn test_transfers_commit_or_abort_atomically() {
+    let cluster = Cluster::new().await;
+    let coordinator = &cluster.nodes[0].manager;
+
+    // Both shards prepare, so the transfer commits
+    let transaction = cluster.transfer(0, 1, 30);
+    assert!(coordinator.is_cross_shard_transaction(&transaction).await.unwrap());
+    let outcome = coordinator.register_transaction_listener(&transaction.id).await;
+    coordinator.initiate_cross_shard_transaction(transaction).await.unwrap();
+    cluster.deliver().await;
+    assert_eq!(outcome.await.unwrap(), TransactionOutcome::Committed);
+    assert_eq!(cluster.balances().await, [70, 130]);
+    cluster.assert_settled().await;
+
+    // The paying shard can't cover the transfer and votes to abort
+    let transaction = cluster.transfer(1, 0, 500);
+    let outcome = coordinator.register_transaction_listener(&transaction.id).await;
+    coordinator.initiate_cross_shard_transaction(transaction).await.unwrap();
+    cluster.deliver().await;
+    assert_eq!(outcome.await.unwrap(), TransactionOutcome::Aborted);
+    assert_eq!(cluster.balances().await, [70, 130]);
+    cluster.assert_settled().await;
+
+    // The request to prepare is lost, so the coordinator times out and aborts
+    let transaction = cluster.transfer(0, 1, 20);
+    let outcome = coordinator.register_transaction_listener(&transaction.id).await;
+    coordinator.initiate_cross_shard_transaction(transaction).await.unwrap();
+    cluster.lose_messages().await;
+    coordinator.check_transactions().await.unwrap();
+    cluster.deliver().await;
+    assert_eq!(outcome.await.unwrap(), TransactionOutcome::Aborted);
+    assert_eq!(cluster.balances().await, [70, 130]);
+    cluster.assert_settled().await;
+}
+
+#[tokio::test]
+async fn test_in_doubt_transactions_recover_after_crashes() {
+    let mut cluster = Cluster::new().await;
+
+    // The receiving shard crashes after the commit decision but before hearing it
+    let transaction = cluster.transfer(0, 1, 30);
+    cluster.nodes[0].manager.initiate_cross_shard_transaction(transaction).await.unwrap();
+    assert!(cluster.deliver_one().await); // prepare
+    assert!(cluster.deliver_one().await); // vote
+    cluster.lose_messages().await;
+    cluster.restart(1).await;
+
+    // Its account stays locked until it learns the decision from the coordinator
+    assert_eq!(cluster.nodes[1].manager.in_doubt_transactions().await.len(), 1);
+    let mut received = cluster.nodes[1].manager.take_transaction_receiver().await.unwrap();
+    let held = ShardTransaction::new(&cluster.accounts[1], b"held".to_vec());
+    assert_eq!(cluster.nodes[1].manager.submit_transaction(held.clone()).await.unwrap(), TransactionRoute::Buffered);
+    cluster.deliver().await;
+    assert_eq!(cluster.balances().await, [70, 130]);
+    assert_eq!(received.recv().await.unwrap(), held);
+    cluster.assert_settled().await;
+
+    // The coordinator crashes before deciding, so it aborts on recovery
+    let transaction = cluster.transfer(1, 0, 50);
+    cluster.nodes[0].manager.initiate_cross_shard_transaction(transaction).await.unwrap();
+    assert!(cluster.deliver_one().await); // prepare
+    cluster.lose_messages().await;
+    cluster.restart(0).await;
+    cluster.deliver().await;
+    assert_eq!(cluster.balances().await, [70, 130]);
+    cluster.assert_settled().await;
+
+    // The coordinator crashes after deciding to commit, so it announces the decision again
+    let transaction = cluster.transfer(1, 0, 50);
+    cluster.nodes[0].manager.initiate_cross_shard_transaction(transaction).await.unwrap();
+    assert!(cluster.deliver_one().await); // prepare
+    assert!(cluster.deliver_one().await); // vote
+    cluster.lose_messages().await;
+    assert_eq!(cluster.balances().await, [120, 130]);
+    cluster.restart(0).await;
+    cluster.deliver().await;
+    assert_eq!(cluster.balances().await, [120, 80]);
+    cluster.assert_settled().await;
+}
+
+#[tokio::test]
+async fn test_only_coordinators_prepare_vote_and_decide() {
+    let cluster = Cluster::new().await;
+    let transaction = cluster.transfer(0, 1, 30);
+    let id = transaction.id.clone();
+    cluster.nodes[0].manager.initiate_cross_shard_transaction(transaction.clone()).await.unwrap();
+    assert!(cluster.deliver_one().await); // prepare
+    assert_eq!(cluster.nodes[1].manager.in_doubt_transactions().await, vec![id.clone()]);
+
+    // Messages claiming to come from a coordinator are rejected from anyone else
+    let mallory = "node-mallory";
+    let prepare = ShardMessage::Prepare(CrossShardTransaction { id: "forged".to_string(), ..transaction });
+    assert!(cluster.nodes[1].manager.handle_message(mallory, prepare).await.is_err());
+    let decision = ShardMessage::Decision {
+        transaction_id: id.clone(),
+        shard: 1,
+        origin_shard: 0,
+        outcome: TransactionOutcome::Aborted,
+    };
+    assert!(cluster.nodes[1].manager.handle_message(mallory, decision).await.is_err());
+    let vote = ShardMessage::PrepareVote { transaction_id: id.clone(), shard: 1, commit: false };
+    assert!(cluster.nodes[0].manager.handle_message(mallory, vote).await.is_err());
+    assert_eq!(cluster.nodes[1].manager.in_doubt_transactions().await, vec![id]);
+
+    cluster.deliver().await;
+    assert_eq!(cluster.balances().await, [70, 130]);
+    cluster.assert_settled().await;
+}
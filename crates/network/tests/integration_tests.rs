@@ -8,6 +8,7 @@ use tokio::time::sleep;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use icn_core::storage::mock_storage::MockStorage;
+use icn_network::envelope::node_did;
 use icn_network::{
     DefaultMessageHandler, DiscoveryConfig, DiscoveryManager, MessageProcessor, NetworkMessage, 
     NetworkService, P2pConfig, P2pNetwork, PeerInfo, Synchronizer, SyncConfig, TransactionAnnouncement,
@@ -348,4 +349,32 @@ async fn test_network_reconnection() {
     // Stop networks
     network1.stop().await.unwrap();
     network2.stop().await.unwrap();
-} 
\ No newline at end of file
+} 
+
+#[tokio::test]
+async fn test_connected_peer_is_bound_to_its_did() {
+    init_logging();
+    
+    let network1 = create_test_network(9301, "binding1", false, vec![]).await;
+    let network2 = create_test_network(9302, "binding2", false, vec![]).await;
+    
+    network1.start().await.unwrap();
+    network2.start().await.unwrap();
+    sleep(Duration::from_millis(100)).await;
+    
+    let network1_peer_id = network1.local_peer_id();
+    let network2_peer_id = network2.local_peer_id();
+    assert_eq!(network1.peer_did(&network2_peer_id).await, None);
+    
+    let network1_addr = network1.listen_addresses().await.unwrap()[0].clone();
+    let network1_full_addr = format!("{}/p2p/{}", network1_addr, network1_peer_id);
+    network2.connect(&network1_full_addr.parse().unwrap()).await.unwrap();
+    sleep(Duration::from_millis(200)).await;
+    
+    // Both sides bind the other to the DID derived from its authenticated peer ID
+    assert_eq!(network1.peer_did(&network2_peer_id).await, Some(node_did(&network2_peer_id)));
+    assert_eq!(network2.peer_did(&network1_peer_id).await, Some(node_did(&network1_peer_id)));
+    
+    network1.stop().await.unwrap();
+    network2.stop().await.unwrap();
+}
@@ -1,12 +1,13 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH, Duration};
 use serde::{Deserialize, Serialize};
 use crate::identity::{Identity, DidDocument};
 use crate::crypto::CryptoUtils;
 use icn_core::storage::Storage;
+use icn_network::reputation::eigentrust::{self, EigenTrustConfig, LocalTrust, TrustAnchors};
 // Import directly from the crate
 use ed25519_dalek::{Keypair, PublicKey, Signature};
 
@@ -318,53 +319,82 @@ impl AttestationManager {
     }
 }
 
-// Trust graph for indirect trust calculation
+// Trust graph for indirect and global trust calculation
 pub struct TrustGraph {
     storage: Arc<dyn Storage>,
+    // Pre-trusted anchor DIDs by federation
+    anchors: RwLock<TrustAnchors>,
+    config: EigenTrustConfig,
 }
 
 impl TrustGraph {
     pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self::with_config(storage, EigenTrustConfig::default())
+    }
+    
+    pub fn with_config(storage: Arc<dyn Storage>, config: EigenTrustConfig) -> Self {
         TrustGraph {
             storage,
+            anchors: RwLock::new(TrustAnchors::new()),
+            config,
         }
     }
     
-    // Calculate trust between parties that don't have direct attestations
-    pub fn calculate_indirect_trust(
-        &self,
-        source_did: &str,
-        target_did: &str,
-        max_depth: usize,
-        min_trust_threshold: f64,
-    ) -> Result<Option<f64>, Box<dyn Error>> {
-        // This would be a more sophisticated algorithm in a real implementation
-        // For example, using a variation of PageRank or path finding algorithm
-        
-        // For demonstration, we implement a simplified approach:
-        
-        // Get all attestations
+    // Pre-trust a DID on behalf of a federation
+    pub fn add_anchor(&self, federation_id: &str, did: &str) {
+        self.anchors.write().unwrap()
+            .entry(federation_id.to_string())
+            .or_default()
+            .insert(did.to_string());
+    }
+    
+    // Get the pre-trusted anchors
+    pub fn anchors(&self) -> TrustAnchors {
+        self.anchors.read().unwrap().clone()
+    }
+    
+    // Build the trust graph from unrevoked attestations at or above the threshold
+    fn load_graph(&self, min_trust_threshold: f64) -> Result<LocalTrust, Box<dyn Error>> {
         let attestation_ids = self.storage.list("attestations/")?;
         
-        // Build an adjacency list for the trust graph
-        let mut graph: HashMap<String, HashMap<String, f64>> = HashMap::new();
-        
+        let mut graph = LocalTrust::new();
         for id in attestation_ids {
             let attestation: Attestation = self.storage.get_json(&format!("attestations/{}", id))?;
-            if attestation.is_revoked {
+            if attestation.is_revoked || attestation.score < min_trust_threshold {
                 continue;
             }
             
-            if attestation.score < min_trust_threshold {
-                continue;
-            }
-            
-            // Add to graph
             graph.entry(attestation.issuer_did.clone())
-                .or_insert_with(HashMap::new)
+                .or_default()
                 .insert(attestation.subject_did.clone(), attestation.score);
         }
         
+        Ok(graph)
+    }
+    
+    // Calculate the global trust of every DID with EigenTrust
+    //
+    // Trust flows from the federation anchors along attestations, so every
+    // node with the same attestations gets the same scores, and DIDs only
+    // attested by each other get none. Scores sum to one over all DIDs.
+    pub fn calculate_global_trust(&self) -> Result<BTreeMap<String, f64>, Box<dyn Error>> {
+        let graph = self.load_graph(0.0)?;
+        Ok(eigentrust::global_trust(&graph, &self.anchors(), &self.config))
+    }
+    
+    // Calculate the trust one party places in another through chains of attestations
+    //
+    // This is the best path from the source's point of view, bounded by
+    // `max_depth`; use `calculate_global_trust` for scores all nodes agree on.
+    pub fn calculate_indirect_trust(
+        &self,
+        source_did: &str,
+        target_did: &str,
+        max_depth: usize,
+        min_trust_threshold: f64,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let graph = self.load_graph(min_trust_threshold)?;
+        
         // Simple path finding with depth limit
         self.find_trust_path(source_did, target_did, &graph, max_depth, min_trust_threshold)
     }
@@ -374,7 +404,7 @@ impl TrustGraph {
         &self,
         source: &str,
         target: &str,
-        graph: &HashMap<String, HashMap<String, f64>>,
+        graph: &LocalTrust,
         max_depth: usize,
         min_threshold: f64,
     ) -> Result<Option<f64>, Box<dyn Error>> {
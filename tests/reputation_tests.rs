@@ -341,4 +341,61 @@ async fn test_indirect_trust() -> Result<(), Box<dyn Error>> {
     assert!(insufficient_depth.is_none());
     
     Ok(())
-} 
\ No newline at end of file
+} 
+#[tokio::test]
+async fn test_global_trust_flows_from_anchors() -> Result<(), Box<dyn Error>> {
+    // Set up test environment
+    let (identity, storage, crypto) = setup_test_environment()?;
+    
+    // Create reputation system
+    let reputation_system = ReputationSystem::new(
+        identity.clone(),
+        storage.clone(),
+        crypto.clone(),
+    );
+    
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)?
+        .as_secs();
+    
+    let attest = |issuer: &str, subject: &str, score: f64| Attestation {
+        id: format!("att:{}:{}:{}", issuer, subject, now),
+        issuer_did: issuer.to_string(),
+        subject_did: subject.to_string(),
+        attestation_type: AttestationType::GeneralTrust,
+        score,
+        context: vec!["https://schema.icn.coop/attestation/v1".to_string()],
+        claims: serde_json::json!({"verified": true}),
+        evidence: vec![],
+        signatures: vec![],
+        quorum_threshold: 1,
+        created_at: now,
+        expires_at: Some(now + 31536000),
+        is_revoked: false,
+    };
+    
+    // The anchor vouches for a member; two sybils only vouch for each other
+    let anchor = "did:icn:coop-1:anchor";
+    let member = "did:icn:coop-1:member";
+    let sybils = ["did:icn:sybil:1", "did:icn:sybil:2"];
+    let attestations = [
+        attest(anchor, member, 0.9),
+        attest(member, anchor, 0.8),
+        attest(sybils[0], sybils[1], 1.0),
+        attest(sybils[1], sybils[0], 1.0),
+    ];
+    for attestation in &attestations {
+        storage.store_json(&format!("attestations/{}", attestation.id), attestation)?;
+    }
+    
+    let trust_graph = reputation_system.trust_graph();
+    trust_graph.add_anchor("coop-1", anchor);
+    let trust = trust_graph.calculate_global_trust()?;
+    
+    // All trust stays with the anchor's side of the graph
+    assert!(trust[member] > 0.4);
+    assert!(trust[sybils[0]] < 1e-9 && trust[sybils[1]] < 1e-9);
+    assert!((trust.values().sum::<f64>() - 1.0).abs() < 1e-6);
+    
+    Ok(())
+}